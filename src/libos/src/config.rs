@@ -103,12 +103,26 @@ pub struct Config {
     pub resource_limits: ConfigResourceLimits,
     pub process: ConfigProcess,
     pub env: ConfigEnv,
+    pub net: ConfigNet,
     pub app: Vec<ConfigApp>,
 }
 
 #[derive(Debug)]
 pub struct ConfigResourceLimits {
     pub user_space_size: usize,
+    pub oom_policy: ConfigOomPolicy,
+    /// The number of the host threads that execute the AIO requests, for which
+    /// the TCSs are reserved
+    pub num_of_aio_workers: u32,
+}
+
+/// What to do when the user space is exhausted by an allocation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigOomPolicy {
+    /// Fail the allocation with ENOMEM
+    Fail,
+    /// Kill the process using the most memory with SIGKILL and retry the allocation
+    KillLargest,
 }
 
 #[derive(Debug)]
@@ -116,6 +130,21 @@ pub struct ConfigProcess {
     pub default_stack_size: usize,
     pub default_heap_size: usize,
     pub default_mmap_size: usize,
+    /// The max size of memory mapped by all the processes of an app, whose quota is not
+    /// given in `mem_quotas`. There is no quota if it is `None`.
+    pub default_mem_quota: Option<usize>,
+    /// The memory quotas of the apps, indexed by the paths of the programs of the apps
+    pub mem_quotas: HashMap<String, usize>,
+    /// Where and when to generate core dumps. No core dump is generated if it is `None`.
+    pub core_dump: Option<ConfigCoreDump>,
+}
+
+#[derive(Debug)]
+pub struct ConfigCoreDump {
+    /// The directory in the LibOS file system where the core files are written
+    pub dir: String,
+    /// Whether to generate core dumps in release enclaves, which may leak secrets
+    pub enable_in_release: bool,
 }
 
 #[derive(Debug)]
@@ -124,6 +153,23 @@ pub struct ConfigEnv {
     pub untrusted: HashSet<String>,
 }
 
+#[derive(Debug)]
+pub struct ConfigNet {
+    pub loopback: ConfigLoopback,
+    /// The names of the network interfaces visible to the LibOS. All the interfaces are visible
+    /// if it is `None`.
+    pub interfaces: Option<HashSet<String>>,
+}
+
+/// Where the traffic of the sockets bound or connected to the loopback addresses goes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigLoopback {
+    /// Through the sockets of the host OS
+    Host,
+    /// Through the in-enclave transport, so the traffic never leaves the enclave
+    Enclave,
+}
+
 #[derive(Clone, Debug)]
 pub struct ConfigMount {
     pub type_: ConfigMountFsType,
@@ -176,6 +222,7 @@ pub struct ConfigMountOptions {
     pub temporary: bool,
     pub cache_size: Option<u64>,
     pub index: u32,
+    pub xattr_namespace: Option<String>,
 }
 
 impl Config {
@@ -183,6 +230,7 @@ impl Config {
         let resource_limits = ConfigResourceLimits::from_input(&input.resource_limits)?;
         let process = ConfigProcess::from_input(&input.process)?;
         let env = ConfigEnv::from_input(&input.env)?;
+        let net = ConfigNet::from_input(&input.net)?;
 
         let app = {
             let mut app = Vec::new();
@@ -196,6 +244,7 @@ impl Config {
             resource_limits,
             process,
             env,
+            net,
             app,
         })
     }
@@ -214,7 +263,16 @@ impl Config {
 impl ConfigResourceLimits {
     fn from_input(input: &InputConfigResourceLimits) -> Result<ConfigResourceLimits> {
         let user_space_size = parse_memory_size(&input.user_space_size)?;
-        Ok(ConfigResourceLimits { user_space_size })
+        let oom_policy = match input.oom_policy.as_str() {
+            "fail" => ConfigOomPolicy::Fail,
+            "kill_largest" => ConfigOomPolicy::KillLargest,
+            _ => return_errno!(EINVAL, "Unsupported OOM policy"),
+        };
+        Ok(ConfigResourceLimits {
+            user_space_size,
+            oom_policy,
+            num_of_aio_workers: input.num_of_aio_workers,
+        })
     }
 }
 
@@ -223,10 +281,45 @@ impl ConfigProcess {
         let default_stack_size = parse_memory_size(&input.default_stack_size)?;
         let default_heap_size = parse_memory_size(&input.default_heap_size)?;
         let default_mmap_size = parse_memory_size(&input.default_mmap_size)?;
+        let default_mem_quota = input
+            .default_mem_quota
+            .as_ref()
+            .map(|quota| parse_memory_size(quota))
+            .transpose()?;
+        let mem_quotas = input
+            .mem_quotas
+            .iter()
+            .map(|(path, quota)| {
+                if !path.starts_with('/') {
+                    return_errno!(EINVAL, "the path of an app must be absolute");
+                }
+                Ok((path.clone(), parse_memory_size(quota)?))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        let core_dump = input
+            .core_dump
+            .as_ref()
+            .map(|core_dump| ConfigCoreDump::from_input(core_dump))
+            .transpose()?;
         Ok(ConfigProcess {
             default_stack_size,
             default_heap_size,
             default_mmap_size,
+            default_mem_quota,
+            mem_quotas,
+            core_dump,
+        })
+    }
+}
+
+impl ConfigCoreDump {
+    fn from_input(input: &InputConfigCoreDump) -> Result<ConfigCoreDump> {
+        if !input.dir.starts_with('/') {
+            return_errno!(EINVAL, "The core dump directory must be an absolute path");
+        }
+        Ok(ConfigCoreDump {
+            dir: input.dir.clone(),
+            enable_in_release: input.enable_in_release,
         })
     }
 }
@@ -240,6 +333,24 @@ impl ConfigEnv {
     }
 }
 
+impl ConfigNet {
+    fn from_input(input: &InputConfigNet) -> Result<ConfigNet> {
+        let loopback = match input.loopback.as_str() {
+            "host" => ConfigLoopback::Host,
+            "enclave" => ConfigLoopback::Enclave,
+            _ => return_errno!(EINVAL, "Unsupported loopback transport"),
+        };
+        let interfaces = input
+            .interfaces
+            .as_ref()
+            .map(|names| names.iter().cloned().collect());
+        Ok(ConfigNet {
+            loopback,
+            interfaces,
+        })
+    }
+}
+
 impl ConfigApp {
     fn from_input(input: &InputConfigApp) -> Result<ConfigApp> {
         let stage = input.stage.clone();
@@ -324,6 +435,7 @@ impl ConfigMountOptions {
             temporary: input.temporary,
             cache_size,
             index: input.index,
+            xattr_namespace: input.xattr_namespace.clone(),
         })
     }
 }
@@ -363,6 +475,8 @@ struct InputConfig {
     #[serde(default)]
     pub env: InputConfigEnv,
     #[serde(default)]
+    pub net: InputConfigNet,
+    #[serde(default)]
     pub app: Vec<InputConfigApp>,
 }
 
@@ -371,18 +485,32 @@ struct InputConfig {
 struct InputConfigResourceLimits {
     #[serde(default = "InputConfigResourceLimits::get_user_space_size")]
     pub user_space_size: String,
+    #[serde(default = "InputConfigResourceLimits::get_oom_policy")]
+    pub oom_policy: String,
+    #[serde(default = "InputConfigResourceLimits::get_num_of_aio_workers")]
+    pub num_of_aio_workers: u32,
 }
 
 impl InputConfigResourceLimits {
     fn get_user_space_size() -> String {
         "128MB".to_string()
     }
+
+    fn get_oom_policy() -> String {
+        "fail".to_string()
+    }
+
+    fn get_num_of_aio_workers() -> u32 {
+        2
+    }
 }
 
 impl Default for InputConfigResourceLimits {
     fn default() -> InputConfigResourceLimits {
         InputConfigResourceLimits {
             user_space_size: InputConfigResourceLimits::get_user_space_size(),
+            oom_policy: InputConfigResourceLimits::get_oom_policy(),
+            num_of_aio_workers: InputConfigResourceLimits::get_num_of_aio_workers(),
         }
     }
 }
@@ -395,6 +523,12 @@ struct InputConfigProcess {
     pub default_heap_size: String,
     #[serde(default = "InputConfigProcess::get_default_mmap_size")]
     pub default_mmap_size: String,
+    #[serde(default)]
+    pub default_mem_quota: Option<String>,
+    #[serde(default)]
+    pub mem_quotas: HashMap<String, String>,
+    #[serde(default)]
+    pub core_dump: Option<InputConfigCoreDump>,
 }
 
 impl InputConfigProcess {
@@ -417,10 +551,21 @@ impl Default for InputConfigProcess {
             default_stack_size: InputConfigProcess::get_default_stack_size(),
             default_heap_size: InputConfigProcess::get_default_heap_size(),
             default_mmap_size: InputConfigProcess::get_default_mmap_size(),
+            default_mem_quota: None,
+            mem_quotas: HashMap::new(),
+            core_dump: None,
         }
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct InputConfigCoreDump {
+    pub dir: String,
+    #[serde(default)]
+    pub enable_in_release: bool,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct InputConfigEnv {
@@ -437,6 +582,30 @@ impl Default for InputConfigEnv {
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct InputConfigNet {
+    #[serde(default = "InputConfigNet::get_loopback")]
+    pub loopback: String,
+    #[serde(default)]
+    pub interfaces: Option<Vec<String>>,
+}
+
+impl InputConfigNet {
+    fn get_loopback() -> String {
+        "host".to_string()
+    }
+}
+
+impl Default for InputConfigNet {
+    fn default() -> InputConfigNet {
+        InputConfigNet {
+            loopback: InputConfigNet::get_loopback(),
+            interfaces: None,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct InputConfigMount {
@@ -462,6 +631,8 @@ struct InputConfigMountOptions {
     pub cache_size: Option<String>,
     #[serde(default)]
     pub index: u32,
+    #[serde(default)]
+    pub xattr_namespace: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -122,6 +122,13 @@ pub extern "C" fn occlum_ecall_init(
         backtrace::__rust_begin_short_backtrace(|| {
             // Ignore the error when parsing host files
             let _ = parse_host_files(file_buffer);
+            // No network interface is visible if they cannot be captured from the host
+            if let Err(e) = net::init_net_ifaces() {
+                error!(
+                    "failed to capture the network interfaces: {}",
+                    e.backtrace()
+                );
+            }
             0 as i32
         })
     })
@@ -212,17 +219,39 @@ pub extern "C" fn occlum_ecall_broadcast_interrupts() -> i32 {
     }
 
     panic::catch_unwind(|| {
-        backtrace::__rust_begin_short_backtrace(|| match interrupt::broadcast_interrupts() {
-            Ok(count) => count as i32,
-            Err(e) => {
-                eprintln!("failed to broadcast interrupts: {}", e.backtrace());
-                ecall_errno!(e.errno())
+        backtrace::__rust_begin_short_backtrace(|| {
+            // The periodic ECall also serves as the tick of POSIX timers, whose
+            // signals can thus be broadcast right away, and the tick of the
+            // resource usage accounting.
+            time::posix_timer::handle_expired_timers();
+            process::rusage::tick_all_threads();
+            match interrupt::broadcast_interrupts() {
+                Ok(count) => count as i32,
+                Err(e) => {
+                    eprintln!("failed to broadcast interrupts: {}", e.backtrace());
+                    ecall_errno!(e.errno())
+                }
             }
         })
     })
     .unwrap_or(ecall_errno!(EFAULT))
 }
 
+#[no_mangle]
+pub extern "C" fn occlum_ecall_run_aio_worker() -> i32 {
+    if HAS_INIT.load(Ordering::Acquire) == false {
+        return ecall_errno!(EAGAIN);
+    }
+
+    panic::catch_unwind(|| {
+        backtrace::__rust_begin_short_backtrace(|| {
+            fs::aio::run_worker();
+            0
+        })
+    })
+    .unwrap_or(ecall_errno!(EFAULT))
+}
+
 fn parse_log_level(level_chars: *const c_char) -> Result<LevelFilter> {
     const DEFAULT_LEVEL: LevelFilter = LevelFilter::Off;
 
@@ -15,6 +15,7 @@ struct Subscriber<E: Event, F: EventFilter<E>> {
     observer: Weak<dyn Observer<E>>,
     filter: Option<F>,
     metadata: Option<Weak<dyn Any + Send + Sync>>,
+    exclusive: bool,
 }
 
 impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
@@ -36,6 +37,27 @@ impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
             observer,
             filter,
             metadata,
+            exclusive: false,
+        });
+    }
+
+    /// Register an exclusive observer with its interesting events and metadata.
+    ///
+    /// An event is delivered to all the non-exclusive observers, but only to the
+    /// exclusive observers until one of them takes it. This avoids waking up
+    /// many waiters for an event that only one of them can handle.
+    pub fn register_exclusive(
+        &self,
+        observer: Weak<dyn Observer<E>>,
+        filter: Option<F>,
+        metadata: Option<Weak<dyn Any + Send + Sync>>,
+    ) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.push_back(Subscriber {
+            observer,
+            filter,
+            metadata,
+            exclusive: true,
         });
     }
 
@@ -48,7 +70,11 @@ impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
     /// Broadcast an event to all registered observers.
     pub fn broadcast(&self, event: &E) {
         let subscribers = self.subscribers.lock().unwrap();
+        let mut is_taken = false;
         for subscriber in subscribers.iter() {
+            if subscriber.exclusive && is_taken {
+                continue;
+            }
             if let Some(filter) = subscriber.filter.as_ref() {
                 if !filter.filter(event) {
                     continue;
@@ -60,7 +86,11 @@ impl<E: Event, F: EventFilter<E>> Notifier<E, F> {
                 Some(observer) => observer,
             };
 
-            observer.on_event(event, &subscriber.metadata);
+            if subscriber.exclusive {
+                is_taken = observer.on_exclusive_event(event, &subscriber.metadata);
+            } else {
+                observer.on_event(event, &subscriber.metadata);
+            }
         }
     }
 }
@@ -17,4 +17,19 @@ pub trait Observer<E: Event>: Send + Sync {
     /// the odds of deadlocks, the `on_event` method should be written short
     /// and sweet.
     fn on_event(&self, event: &E, metadata: &Option<Weak<dyn Any + Send + Sync>>) -> ();
+
+    /// The callback that will be executed when some interesting events are
+    /// delivered to this observer, which is registered to the notifier as an
+    /// exclusive observer.
+    ///
+    /// Returns whether the events are taken by this observer. If so, the events
+    /// are not delivered to the remaining exclusive observers.
+    fn on_exclusive_event(
+        &self,
+        event: &E,
+        metadata: &Option<Weak<dyn Any + Send + Sync>>,
+    ) -> bool {
+        self.on_event(event, metadata);
+        true
+    }
 }
@@ -4,6 +4,7 @@ use std::time::Duration;
 
 use super::host_event_fd::HostEventFd;
 use crate::prelude::*;
+use crate::process::rusage::SleepGuard;
 
 /// A waiter enables a thread to sleep.
 pub struct Waiter {
@@ -154,6 +155,10 @@ impl Inner {
     }
 
     pub fn wait(&self, timeout: Option<&Duration>) -> Result<()> {
+        if self.is_woken() {
+            return Ok(());
+        }
+        let _sleep_guard = SleepGuard::new();
         while !self.is_woken() {
             self.host_eventfd.poll(timeout)?;
         }
@@ -174,6 +179,10 @@ impl Inner {
     }
 
     fn do_wait_mut(&self, remain: &mut Option<Duration>) -> Result<()> {
+        if self.is_woken() {
+            return Ok(());
+        }
+        let _sleep_guard = SleepGuard::new();
         while !self.is_woken() {
             self.host_eventfd.poll_mut(remain.as_mut())?;
         }
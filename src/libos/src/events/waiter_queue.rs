@@ -91,3 +91,11 @@ impl WaiterQueue {
         to_wake.len()
     }
 }
+
+impl std::fmt::Debug for WaiterQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WaiterQueue")
+            .field("count", &self.count.load(Ordering::Relaxed))
+            .finish()
+    }
+}
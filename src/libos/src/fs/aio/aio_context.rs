@@ -0,0 +1,262 @@
+use super::*;
+use crate::events::{Waiter, WaiterQueue};
+
+/// The AIO contexts of a process
+#[derive(Debug)]
+pub struct AioContexts {
+    contexts: SgxMutex<HashMap<aio_context_t, Arc<AioContext>>>,
+}
+
+impl AioContexts {
+    pub fn new() -> Self {
+        Self {
+            contexts: SgxMutex::new(HashMap::new()),
+        }
+    }
+
+    /// Create a context, unless the events of all the contexts would exceed
+    /// `max_total_events`.
+    pub fn create(
+        &self,
+        id: aio_context_t,
+        max_events: usize,
+        max_total_events: usize,
+    ) -> Result<Arc<AioContext>> {
+        let mut contexts = self.contexts.lock().unwrap();
+        let total_events: usize = contexts.values().map(|context| context.max_events).sum();
+        if total_events + max_events > max_total_events {
+            return_errno!(EAGAIN, "too many events");
+        }
+        let context = Arc::new(AioContext::new(id, max_events));
+        contexts.insert(id, context.clone());
+        Ok(context)
+    }
+
+    pub fn get(&self, id: aio_context_t) -> Result<Arc<AioContext>> {
+        self.contexts
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| errno!(EINVAL, "invalid AIO context"))
+    }
+
+    pub fn remove(&self, id: aio_context_t) -> Result<Arc<AioContext>> {
+        self.contexts
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| errno!(EINVAL, "invalid AIO context"))
+    }
+
+    /// Shut down all the contexts, which is done when the process exits or
+    /// executes a new program.
+    ///
+    /// The running requests are not waited for since they never access the
+    /// memory of the process.
+    pub fn clear(&self) {
+        let contexts: Vec<_> = self.contexts.lock().unwrap().drain().collect();
+        for (_, context) in contexts {
+            context.shut_down();
+        }
+    }
+}
+
+/// An AIO context, which owns the completion ring of its requests.
+pub struct AioContext {
+    id: aio_context_t,
+    max_events: usize,
+    inner: SgxMutex<Inner>,
+    /// The threads waiting for completions in io_getevents or io_destroy
+    waiters: WaiterQueue,
+}
+
+struct Inner {
+    /// The completion ring
+    completions: VecDeque<Completion>,
+    /// The requests that have been submitted but not completed
+    inflight: Vec<Arc<AioRequest>>,
+    is_destroyed: bool,
+}
+
+struct Completion {
+    event: io_event_t,
+    /// The data read by the request, which is copied to the user buffers when
+    /// the completion is reaped
+    read_back: Option<ReadBack>,
+}
+
+impl AioContext {
+    fn new(id: aio_context_t, max_events: usize) -> Self {
+        let inner = Inner {
+            completions: VecDeque::with_capacity(max_events),
+            inflight: Vec::new(),
+            is_destroyed: false,
+        };
+        Self {
+            id,
+            max_events,
+            inner: SgxMutex::new(inner),
+            waiters: WaiterQueue::new(),
+        }
+    }
+
+    /// Queue a request to the worker threads.
+    ///
+    /// Like Linux, a slot in the completion ring is reserved for each request,
+    /// so the completions are never dropped.
+    pub fn submit(&self, request: Arc<AioRequest>) -> Result<()> {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_destroyed {
+                return_errno!(EINVAL, "the AIO context is destroyed");
+            }
+            if inner.inflight.len() + inner.completions.len() >= self.max_events {
+                return_errno!(EAGAIN, "the completion ring is full");
+            }
+            inner.inflight.push(request.clone());
+        }
+        worker::enqueue(request);
+        Ok(())
+    }
+
+    /// Cancel a request that has not been started by a worker thread.
+    ///
+    /// Like Linux, the completion event of the canceled request is delivered
+    /// to the completion ring and EINPROGRESS is returned on success.
+    pub fn cancel(&self, obj: u64) -> Result<()> {
+        let request = self
+            .inner
+            .lock()
+            .unwrap()
+            .inflight
+            .iter()
+            .find(|request| request.obj() == obj)
+            .cloned()
+            .ok_or_else(|| errno!(EINVAL, "the request is not found"))?;
+        if !request.cancel() {
+            return_errno!(EAGAIN, "the request is being executed");
+        }
+        self.complete(&request, -(Errno::ECANCELED as i64));
+        return_errno!(EINPROGRESS, "the request is canceled");
+    }
+
+    /// Deliver the result of a request to the completion ring.
+    pub fn complete(&self, request: &AioRequest, res: i64) {
+        let event = io_event_t {
+            data: request.data(),
+            obj: request.obj(),
+            res,
+            res2: 0,
+        };
+        let read_back = request.take_read_back();
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner
+                .inflight
+                .retain(|inflight| !std::ptr::eq(Arc::as_ptr(inflight), request));
+            inner.completions.push_back(Completion { event, read_back });
+        }
+        self.waiters.dequeue_and_wake_all();
+
+        if let Some(resfd) = request.resfd() {
+            // The eventfd counter is increased by one, as in Linux. It is fine
+            // to fail when the counter would overflow.
+            let _ = resfd.write(&1u64.to_ne_bytes());
+        }
+    }
+
+    /// Wait until at least `min_nr` events are completed, or the timeout
+    /// expires, and then pop as many events as possible.
+    ///
+    /// The data read by the requests is copied to the user buffers here, which
+    /// is done by a thread of the process.
+    pub fn get_events(
+        &self,
+        min_nr: usize,
+        events: &mut [io_event_t],
+        mut timeout: Option<&mut Duration>,
+    ) -> Result<usize> {
+        let mut waiter = None;
+        let completions = loop {
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if inner.completions.len() >= min_nr || inner.is_destroyed {
+                    break inner.pop_completions(events.len());
+                }
+                self.waiters
+                    .reset_and_enqueue(waiter.get_or_insert_with(Waiter::new));
+            }
+            match waiter.as_ref().unwrap().wait_mut(timeout.as_deref_mut()) {
+                Ok(()) => {}
+                Err(e) if e.errno() == ETIMEDOUT => {
+                    break self.inner.lock().unwrap().pop_completions(events.len());
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let count = completions.len();
+        for (event, completion) in events.iter_mut().zip(completions) {
+            *event = completion.event;
+            if let Some(read_back) = completion.read_back {
+                event.res = read_back.copy_to_user(event.res);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Cancel the requests that have not been started and wait for the others
+    /// to complete.
+    pub fn destroy(&self) -> Result<()> {
+        self.shut_down();
+
+        let waiter = Waiter::new();
+        loop {
+            {
+                let inner = self.inner.lock().unwrap();
+                if inner.inflight.is_empty() {
+                    return Ok(());
+                }
+                self.waiters.reset_and_enqueue(&waiter);
+            }
+            waiter.wait(None)?;
+        }
+    }
+
+    /// Cancel the requests that have not been started and refuse new requests.
+    fn shut_down(&self) {
+        let inflight = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.is_destroyed = true;
+            inner.inflight.clone()
+        };
+        for request in inflight {
+            if request.cancel() {
+                self.complete(&request, -(Errno::ECANCELED as i64));
+            }
+        }
+        // Wake up the threads in io_getevents
+        self.waiters.dequeue_and_wake_all();
+    }
+}
+
+impl Inner {
+    fn pop_completions(&mut self, max_count: usize) -> Vec<Completion> {
+        let count = max_count.min(self.completions.len());
+        self.completions.drain(..count).collect()
+    }
+}
+
+impl Debug for AioContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("AioContext")
+            .field("id", &self.id)
+            .field("max_events", &self.max_events)
+            .field("num_completions", &inner.completions.len())
+            .field("num_inflight", &inner.inflight.len())
+            .field("is_destroyed", &inner.is_destroyed)
+            .finish()
+    }
+}
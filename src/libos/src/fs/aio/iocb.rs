@@ -0,0 +1,82 @@
+use super::*;
+
+#[allow(non_camel_case_types)]
+pub type aio_context_t = u64;
+
+/// The AIO control block that describes a request, i.e., struct iocb.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct iocb_t {
+    pub aio_data: u64,
+    pub aio_key: u32,
+    pub aio_rw_flags: i32,
+    pub aio_lio_opcode: u16,
+    pub aio_reqprio: i16,
+    pub aio_fildes: u32,
+    pub aio_buf: u64,
+    pub aio_nbytes: u64,
+    pub aio_offset: i64,
+    pub aio_reserved2: u64,
+    pub aio_flags: u32,
+    pub aio_resfd: u32,
+}
+
+/// The completion event of a request, i.e., struct io_event.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_event_t {
+    /// The aio_data of the control block
+    pub data: u64,
+    /// The address of the control block
+    pub obj: u64,
+    /// The result of the request
+    pub res: i64,
+    pub res2: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IocbCmd {
+    PRead,
+    PWrite,
+    FSync,
+    FDSync,
+    PReadV,
+    PWriteV,
+}
+
+impl IocbCmd {
+    pub fn from_raw(raw: u16) -> Result<Self> {
+        Ok(match raw {
+            0 => IocbCmd::PRead,
+            1 => IocbCmd::PWrite,
+            2 => IocbCmd::FSync,
+            3 => IocbCmd::FDSync,
+            7 => IocbCmd::PReadV,
+            8 => IocbCmd::PWriteV,
+            // IOCB_CMD_POLL and IOCB_CMD_NOOP
+            5 | 6 => return_errno!(EINVAL, "unsupported AIO command"),
+            _ => return_errno!(EINVAL, "invalid AIO command"),
+        })
+    }
+}
+
+bitflags! {
+    pub struct IocbFlags: u32 {
+        /// Notify the eventfd given by aio_resfd on completion
+        const IOCB_FLAG_RESFD   = 1 << 0;
+        /// The aio_reqprio field is an I/O priority, which is ignored
+        const IOCB_FLAG_IOPRIO  = 1 << 1;
+    }
+}
+
+bitflags! {
+    pub struct RwFlags: i32 {
+        const RWF_HIPRI     = 0x01;
+        const RWF_DSYNC     = 0x02;
+        const RWF_SYNC      = 0x04;
+        const RWF_NOWAIT    = 0x08;
+        const RWF_APPEND    = 0x10;
+    }
+}
@@ -0,0 +1,112 @@
+//! Linux native asynchronous I/O, i.e., io_setup(2) and its friends.
+//!
+//! The requests submitted to an AIO context are executed by a pool of worker
+//! threads, which are host threads that enter the enclave through a dedicated
+//! ECall and wait there for new requests. They are started on the first
+//! io_setup. The results are delivered to the
+//! completion ring of the context, which lives inside the LibOS, and
+//! optionally signaled to an eventfd (IOCB_FLAG_RESFD).
+//!
+//! Since all the processes share one address space, the worker threads never
+//! touch the user buffers. The data to write is copied to the LibOS at
+//! submission time, and the data read is copied to the user buffers by
+//! io_getevents. So, unlike Linux, the data read is not visible in the user
+//! buffers until the completion is reaped.
+//!
+//! Like Linux, the context ID returned to the user is the address of a memory
+//! page mapped for the context. The page is left zeroed, which tells user
+//! libraries (e.g., libaio) that the completion ring cannot be read in the user
+//! space and io_getevents must be used instead.
+
+use super::*;
+
+pub use self::aio_context::{AioContext, AioContexts};
+pub use self::iocb::{aio_context_t, io_event_t, iocb_t};
+pub use self::worker::run_worker;
+
+mod aio_context;
+mod iocb;
+mod request;
+mod worker;
+
+use self::request::{AioRequest, ReadBack};
+use crate::vm::{MMapFlags, VMPerms, PAGE_SIZE};
+use std::time::Duration;
+
+/// The maximum number of events of all the AIO contexts of a process
+const AIO_MAX_NR: usize = 65536;
+
+pub fn do_io_setup(max_events: usize) -> Result<aio_context_t> {
+    if max_events == 0 {
+        return_errno!(EINVAL, "the number of events must be positive");
+    }
+    if max_events > AIO_MAX_NR {
+        return_errno!(EAGAIN, "too many events");
+    }
+
+    worker::start_workers()?;
+
+    let current = current!();
+    let contexts = current.process().aio_contexts();
+    // The page that serves as the context ID
+    let id = crate::vm::do_mmap(
+        0,
+        PAGE_SIZE,
+        VMPerms::READ,
+        MMapFlags::MAP_PRIVATE | MMapFlags::MAP_ANONYMOUS,
+        0,
+        0,
+    )? as aio_context_t;
+    if let Err(e) = contexts.create(id, max_events, AIO_MAX_NR) {
+        crate::vm::do_munmap(id as usize, PAGE_SIZE)?;
+        return Err(e);
+    }
+    Ok(id)
+}
+
+pub fn do_io_destroy(id: aio_context_t) -> Result<()> {
+    let current = current!();
+    let contexts = current.process().aio_contexts();
+    // The context is kept until its requests complete, so io_destroy can be
+    // retried if it is interrupted
+    contexts.get(id)?.destroy()?;
+    contexts.remove(id)?;
+    crate::vm::do_munmap(id as usize, PAGE_SIZE)
+}
+
+/// Submit the requests. Return the number of requests submitted, which is less
+/// than the number of the control blocks if an error occurs halfway.
+pub fn do_io_submit(id: aio_context_t, iocbs: &[(u64, iocb_t)]) -> Result<usize> {
+    let context = current!().process().aio_contexts().get(id)?;
+    let mut count = 0;
+    for (obj, iocb) in iocbs {
+        let res = AioRequest::new(&context, *obj, iocb).and_then(|request| context.submit(request));
+        match res {
+            Ok(()) => count += 1,
+            Err(e) if count == 0 => return Err(e),
+            Err(_) => break,
+        }
+    }
+    if count > 0 {
+        worker::notify();
+    }
+    Ok(count)
+}
+
+pub fn do_io_cancel(id: aio_context_t, obj: u64) -> Result<()> {
+    let context = current!().process().aio_contexts().get(id)?;
+    context.cancel(obj)
+}
+
+pub fn do_io_getevents(
+    id: aio_context_t,
+    min_nr: usize,
+    events: &mut [io_event_t],
+    timeout: Option<&mut Duration>,
+) -> Result<usize> {
+    if min_nr > events.len() {
+        return_errno!(EINVAL, "min_nr is greater than nr");
+    }
+    let context = current!().process().aio_contexts().get(id)?;
+    context.get_events(min_nr, events, timeout)
+}
@@ -0,0 +1,280 @@
+use super::iocb::{IocbCmd, IocbFlags, RwFlags};
+use super::*;
+use atomic::{Atomic, Ordering};
+
+/// A request submitted by io_submit.
+///
+/// The control block is validated at submission time, so a request can be
+/// executed by a worker thread that knows nothing about the submitter. The
+/// worker threads never access the user buffers, which may be unmapped by then.
+/// Instead, the data to write is copied at submission time, and the data read
+/// is copied to the user buffers when the completion is reaped.
+pub struct AioRequest {
+    context: Arc<AioContext>,
+    obj: u64,
+    data: u64,
+    file: FileRef,
+    op: AioOp,
+    resfd: Option<FileRef>,
+    state: Atomic<RequestState>,
+}
+
+enum AioOp {
+    Read {
+        offset: usize,
+        /// Taken by the completion when the request completes
+        read_back: SgxMutex<Option<ReadBack>>,
+    },
+    Write {
+        /// The data copied from the user buffers
+        data: Vec<u8>,
+        offset: usize,
+        rw_flags: RwFlags,
+    },
+    Sync {
+        data_only: bool,
+    },
+}
+
+/// The data read by a request and the user buffers that it goes to.
+pub struct ReadBack {
+    bufs: Vec<UserBuf>,
+    data: Vec<u8>,
+}
+
+impl ReadBack {
+    /// Copy the data read to the user buffers and return the result of the
+    /// request, which becomes EFAULT if the buffers are no longer mapped.
+    ///
+    /// This must be called by a thread of the process that submits the request.
+    pub fn copy_to_user(&self, res: i64) -> i64 {
+        if res <= 0 {
+            return res;
+        }
+        match UserBuf::scatter(&self.bufs, &self.data[..res as usize]) {
+            Ok(_) => res,
+            Err(e) => -(e.errno() as i64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RequestState {
+    /// Waiting for a worker thread
+    Queued,
+    /// Being executed by a worker thread
+    Running,
+    /// Canceled before being executed
+    Canceled,
+}
+
+impl AioRequest {
+    pub fn new(context: &Arc<AioContext>, obj: u64, iocb: &iocb_t) -> Result<Arc<Self>> {
+        if iocb.aio_reserved2 != 0 {
+            return_errno!(EINVAL, "the reserved field must be zero");
+        }
+        let flags = IocbFlags::from_bits(iocb.aio_flags)
+            .ok_or_else(|| errno!(EINVAL, "invalid control block flags"))?;
+        let rw_flags = RwFlags::from_bits(iocb.aio_rw_flags)
+            .ok_or_else(|| errno!(EOPNOTSUPP, "invalid read/write flags"))?;
+        if rw_flags.intersects(RwFlags::RWF_NOWAIT | RwFlags::RWF_APPEND) {
+            return_errno!(EOPNOTSUPP, "RWF_NOWAIT and RWF_APPEND are not supported");
+        }
+        let cmd = IocbCmd::from_raw(iocb.aio_lio_opcode)?;
+
+        let current = current!();
+        let file = current.file(iocb.aio_fildes as FileDesc)?;
+        let resfd = if flags.contains(IocbFlags::IOCB_FLAG_RESFD) {
+            let resfd = current.file(iocb.aio_resfd as FileDesc)?;
+            resfd
+                .as_event()
+                .map_err(|_| errno!(EINVAL, "aio_resfd is not an eventfd"))?;
+            Some(resfd)
+        } else {
+            None
+        };
+
+        let op = match cmd {
+            IocbCmd::PRead | IocbCmd::PReadV | IocbCmd::PWrite | IocbCmd::PWriteV => {
+                if iocb.aio_offset < 0 {
+                    return_errno!(EINVAL, "the offset is negative");
+                }
+                let offset = iocb.aio_offset as usize;
+                let is_read = cmd == IocbCmd::PRead || cmd == IocbCmd::PReadV;
+                let access_mode = file.access_mode()?;
+                if is_read && !access_mode.readable() {
+                    return_errno!(EBADF, "the file is not readable");
+                }
+                if !is_read && !access_mode.writable() {
+                    return_errno!(EBADF, "the file is not writable");
+                }
+
+                let bufs = match cmd {
+                    IocbCmd::PRead | IocbCmd::PWrite => {
+                        vec![UserBuf::new(iocb.aio_buf, iocb.aio_nbytes, is_read)?]
+                    }
+                    _ => UserBuf::from_iovecs(iocb.aio_buf, iocb.aio_nbytes, is_read)?,
+                };
+                let mut data = UserBuf::alloc_bounce_buf(&bufs)?;
+                if is_read {
+                    AioOp::Read {
+                        offset,
+                        read_back: SgxMutex::new(Some(ReadBack { bufs, data })),
+                    }
+                } else {
+                    UserBuf::gather(&bufs, &mut data)?;
+                    AioOp::Write {
+                        data,
+                        offset,
+                        rw_flags,
+                    }
+                }
+            }
+            IocbCmd::FSync | IocbCmd::FDSync => {
+                if iocb.aio_buf != 0
+                    || iocb.aio_nbytes != 0
+                    || iocb.aio_offset != 0
+                    || iocb.aio_rw_flags != 0
+                {
+                    return_errno!(EINVAL, "the buffer fields of a sync request must be zero");
+                }
+                AioOp::Sync {
+                    data_only: cmd == IocbCmd::FDSync,
+                }
+            }
+        };
+
+        Ok(Arc::new(Self {
+            context: context.clone(),
+            obj,
+            data: iocb.aio_data,
+            file,
+            op,
+            resfd,
+            state: Atomic::new(RequestState::Queued),
+        }))
+    }
+
+    /// The address of the control block, which identifies the request.
+    pub fn obj(&self) -> u64 {
+        self.obj
+    }
+
+    pub fn data(&self) -> u64 {
+        self.data
+    }
+
+    pub fn resfd(&self) -> Option<&FileRef> {
+        self.resfd.as_ref()
+    }
+
+    /// Take the data read by the request, which is done when it completes.
+    pub fn take_read_back(&self) -> Option<ReadBack> {
+        match &self.op {
+            AioOp::Read { read_back, .. } => read_back.lock().unwrap().take(),
+            _ => None,
+        }
+    }
+
+    /// Cancel the request if it has not been started by a worker thread.
+    pub fn cancel(&self) -> bool {
+        self.state
+            .compare_exchange(
+                RequestState::Queued,
+                RequestState::Canceled,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+
+    /// Execute the request and complete it to the context, unless the request
+    /// has been canceled.
+    ///
+    /// This is called by the worker threads.
+    pub fn run(&self) {
+        if self
+            .state
+            .compare_exchange(
+                RequestState::Queued,
+                RequestState::Running,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            return;
+        }
+
+        let res = match self.do_io() {
+            Ok(len) => len as i64,
+            Err(e) => -(e.errno() as i64),
+        };
+        self.context.complete(self, res);
+    }
+
+    fn do_io(&self) -> Result<usize> {
+        match &self.op {
+            AioOp::Read { offset, read_back } => {
+                let mut read_back = read_back.lock().unwrap();
+                let data = &mut read_back.as_mut().unwrap().data;
+                self.file.read_at(*offset, data)
+            }
+            AioOp::Write {
+                data,
+                offset,
+                rw_flags,
+            } => {
+                let len = self.file.write_at(*offset, data)?;
+                if rw_flags.contains(RwFlags::RWF_SYNC) {
+                    self.file.sync_all()?;
+                } else if rw_flags.contains(RwFlags::RWF_DSYNC) {
+                    self.file.sync_data()?;
+                }
+                Ok(len)
+            }
+            AioOp::Sync { data_only } => {
+                if *data_only {
+                    self.file.sync_data()?;
+                } else {
+                    self.file.sync_all()?;
+                }
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl Debug for AioRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AioRequest")
+            .field("obj", &self.obj)
+            .field("data", &self.data)
+            .field("op", &self.op)
+            .field("state", &self.state.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl Debug for AioOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            // The buffer is locked by the worker during the read, so its length is not shown
+            AioOp::Read { offset, .. } => f.debug_struct("Read").field("offset", offset).finish(),
+            AioOp::Write {
+                data,
+                offset,
+                rw_flags,
+            } => f
+                .debug_struct("Write")
+                .field("offset", offset)
+                .field("len", &data.len())
+                .field("rw_flags", rw_flags)
+                .finish(),
+            AioOp::Sync { data_only } => f
+                .debug_struct("Sync")
+                .field("data_only", data_only)
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! The worker threads that execute AIO requests.
+//!
+//! A worker thread is a host thread that calls the ECall of AIO workers in a
+//! loop. It executes the queued requests and then sleeps on a host eventfd,
+//! which is written when new requests are queued. If no requests arrive for a
+//! while, the ECall returns so that the host thread can be stopped.
+//!
+//! The worker threads are started on the first io_setup, so that they take no
+//! TCS unless AIO is used.
+
+use super::*;
+use crate::config::LIBOS_CONFIG;
+use crate::events::HostEventFd;
+
+/// How long an idle worker thread stays in the enclave
+const IDLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    static ref QUEUE: WorkQueue = WorkQueue::new();
+    static ref IS_STARTED: SgxMutex<bool> = SgxMutex::new(false);
+}
+
+/// Start the worker threads unless they have been started.
+pub fn start_workers() -> Result<()> {
+    let mut is_started = IS_STARTED.lock().unwrap();
+    if *is_started {
+        return Ok(());
+    }
+
+    // The TCSs of the worker threads are reserved by the number in the config
+    let num_workers = LIBOS_CONFIG.resource_limits.num_of_aio_workers;
+    if num_workers == 0 {
+        return_errno!(ENOSYS, "AIO is disabled by the config");
+    }
+    let mut ret = 0;
+    let status = unsafe { occlum_ocall_start_aio_threads(&mut ret, num_workers as i32) };
+    assert!(status == sgx_status_t::SGX_SUCCESS);
+    if ret < 0 {
+        return_errno!(EAGAIN, "failed to start the AIO worker threads");
+    }
+    *is_started = true;
+    Ok(())
+}
+
+struct WorkQueue {
+    requests: SgxMutex<VecDeque<Arc<AioRequest>>>,
+    doorbell: HostEventFd,
+}
+
+impl WorkQueue {
+    fn new() -> Self {
+        Self {
+            requests: SgxMutex::new(VecDeque::new()),
+            doorbell: HostEventFd::new().expect("failed to create the host eventfd of AIO workers"),
+        }
+    }
+
+    fn pop(&self) -> Option<Arc<AioRequest>> {
+        self.requests.lock().unwrap().pop_front()
+    }
+}
+
+/// Queue a request. The worker threads are notified by `notify`, which can be
+/// called once for a batch of requests.
+pub fn enqueue(request: Arc<AioRequest>) {
+    QUEUE.requests.lock().unwrap().push_back(request);
+}
+
+/// Wake up the worker threads to execute the queued requests.
+pub fn notify() {
+    QUEUE.doorbell.write_u64(1);
+}
+
+/// Execute the queued requests until the worker thread is idle for a while.
+///
+/// This is called by the worker threads outside the LibOS threads, so the
+/// requests must not depend on the current thread.
+pub fn run_worker() {
+    loop {
+        while let Some(request) = QUEUE.pop() {
+            request.run();
+        }
+        // The doorbell is written after requests are queued, so a request
+        // queued after the above check is not missed.
+        if let Err(e) = QUEUE.doorbell.poll(Some(&IDLE_TIMEOUT)) {
+            if e.errno() == ETIMEDOUT {
+                return;
+            }
+        }
+    }
+}
+
+extern "C" {
+    fn occlum_ocall_start_aio_threads(ret: *mut i32, max_num_threads: i32) -> sgx_status_t;
+}
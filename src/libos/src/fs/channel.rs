@@ -4,7 +4,9 @@ use std::sync::Weak;
 use ringbuf::{Consumer as RbConsumer, Producer as RbProducer, RingBuffer};
 
 use super::{IoEvents, IoNotifier};
-use crate::events::{Event, EventFilter, Notifier, Observer, Waiter, WaiterQueueObserver};
+use crate::events::{
+    Event, EventFilter, Notifier, Observer, Waiter, WaiterQueue, WaiterQueueObserver,
+};
 use crate::prelude::*;
 
 /// A unidirectional communication channel, intended to implement IPC, e.g., pipe,
@@ -125,6 +127,9 @@ macro_rules! impl_end_point_type {
         /// An endpoint is either the producer or consumer of a channel.
         $(#[$attr])* $vis struct $end_point<$i> {
             inner: SgxMutex<$inner<$i>>,
+            // Serialize the operations on the endpoint, so that the items stay
+            // unchanged while they are accessed without locking the ring buffer
+            io_lock: SgxMutex<()>,
             state: Arc<State>,
             observer: Arc<WaiterQueueObserver<IoEvents>>,
             notifier: Arc<IoNotifier>,
@@ -135,12 +140,14 @@ macro_rules! impl_end_point_type {
         impl<$i> $end_point<$i> {
             fn new(inner: $inner<$i>, state: Arc<State>) -> Self {
                 let inner = SgxMutex::new(inner);
+                let io_lock = SgxMutex::new(());
                 let observer = WaiterQueueObserver::new();
                 let notifier = Arc::new(IoNotifier::new());
                 let peer_notifier = Default::default();
                 let is_nonblocking = AtomicBool::new(false);
                 Self {
                     inner,
+                    io_lock,
                     state,
                     observer,
                     notifier,
@@ -220,6 +227,7 @@ impl<I> Producer<I> {
     pub fn push(&self, mut item: I) -> Result<()> {
         waiter_loop!(
             {
+                let _io_lock = self.io_lock.lock().unwrap();
                 let mut rb_producer = self.inner.lock().unwrap();
                 if self.is_self_shutdown() || self.is_peer_shutdown() {
                     return_errno!(EPIPE, "one or both endpoints have been shutdown");
@@ -298,6 +306,7 @@ impl<I: Copy> Producer<I> {
 
         waiter_loop!(
             {
+                let _io_lock = self.io_lock.lock().unwrap();
                 let mut rb_producer = self.inner.lock().unwrap();
                 if self.is_self_shutdown() || self.is_peer_shutdown() {
                     return_errno!(EPIPE, "one or both endpoints have been shutdown");
@@ -329,6 +338,54 @@ impl<I: Copy> Producer<I> {
     }
 }
 
+impl<I: Copy> Producer<I> {
+    /// Let `f` fill the bounce buffer `buf` and push the items filled, whose
+    /// number is returned by `f`. At most the free space of the channel is
+    /// passed to `f`, so all the filled items are pushed.
+    ///
+    /// Unlike `push_slice`, the ring buffer is not locked while `f` is called,
+    /// thus `f` may do I/O, e.g., reading a file. Yet the other operations on
+    /// the producer wait until `f` returns.
+    pub fn push_with<F>(&self, buf: &mut [I], nonblocking: bool, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&mut [I]) -> Result<usize>,
+    {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        waiter_loop!(
+            {
+                let _io_lock = self.io_lock.lock().unwrap();
+                let free_count = {
+                    let rb_producer = self.inner.lock().unwrap();
+                    if self.is_self_shutdown() || self.is_peer_shutdown() {
+                        return_errno!(EPIPE, "one or both endpoints have been shutdown");
+                    }
+                    rb_producer.capacity() - rb_producer.len()
+                };
+
+                if free_count > 0 {
+                    let items = &mut buf[..free_count.min(buf.len())];
+                    let count = f(items)?;
+                    debug_assert!(count <= items.len());
+                    // The free space never shrinks while the I/O lock is held
+                    let count = self.inner.lock().unwrap().push_slice(&items[..count]);
+                    if count > 0 {
+                        self.trigger_peer_events(&IoEvents::IN);
+                    }
+                    return Ok(count);
+                }
+
+                if nonblocking {
+                    return_errno!(EAGAIN, "try again later");
+                }
+            },
+            self.observer.waiter_queue()
+        );
+    }
+}
+
 impl<I> Drop for Producer<I> {
     fn drop(&mut self) {
         self.shutdown();
@@ -346,6 +403,7 @@ impl<I> Consumer<I> {
     pub fn pop(&self) -> Result<Option<I>> {
         waiter_loop!(
             {
+                let _io_lock = self.io_lock.lock().unwrap();
                 let mut rb_consumer = self.inner.lock().unwrap();
                 if self.is_self_shutdown() {
                     return_errno!(EPIPE, "this endpoint has been shutdown");
@@ -443,6 +501,7 @@ impl<I: Copy> Consumer<I> {
 
         waiter_loop!(
             {
+                let _io_lock = self.io_lock.lock().unwrap();
                 let mut rb_consumer = self.inner.lock().unwrap();
                 if self.is_self_shutdown() {
                     return_errno!(EPIPE, "this endpoint has been shutdown");
@@ -477,6 +536,176 @@ impl<I: Copy> Consumer<I> {
     }
 }
 
+impl<I: Copy> Consumer<I> {
+    /// Move at most `count` items from this channel to another one, without
+    /// copying them to an intermediate buffer.
+    ///
+    /// Returns 0 if the channel is empty and its producer has been shutdown.
+    pub fn move_to(
+        &self,
+        producer: &Producer<I>,
+        count: usize,
+        nonblocking: bool,
+    ) -> Result<usize> {
+        self.transfer_to(producer, count, nonblocking, true)
+    }
+
+    /// Copy at most `count` items from this channel to another one, without
+    /// consuming them.
+    ///
+    /// Returns 0 if the channel is empty and its producer has been shutdown.
+    pub fn copy_to(
+        &self,
+        producer: &Producer<I>,
+        count: usize,
+        nonblocking: bool,
+    ) -> Result<usize> {
+        self.transfer_to(producer, count, nonblocking, false)
+    }
+
+    /// Returns whether the producer is the other endpoint of this channel.
+    pub fn is_peer_of(&self, producer: &Producer<I>) -> bool {
+        Arc::ptr_eq(&self.state, &producer.state)
+    }
+
+    fn transfer_to(
+        &self,
+        producer: &Producer<I>,
+        count: usize,
+        nonblocking: bool,
+        consume: bool,
+    ) -> Result<usize> {
+        debug_assert!(!self.is_peer_of(producer));
+        if count == 0 {
+            return Ok(0);
+        }
+
+        // Unlike waiter_loop, the waiter queue to wait on depends on which of
+        // the two channels is not ready
+        let waiter = Waiter::new();
+        let mut enqueued: Option<&WaiterQueue> = None;
+        loop {
+            let waiter_queue = {
+                // The consumer is always locked before the producer of the other
+                // channel, so two transfers in opposite directions do not deadlock
+                let _consumer_io_lock = self.io_lock.lock().unwrap();
+                let _producer_io_lock = producer.io_lock.lock().unwrap();
+                let mut rb_consumer = self.inner.lock().unwrap();
+                let mut rb_producer = producer.inner.lock().unwrap();
+                if self.is_self_shutdown() {
+                    return_errno!(EPIPE, "this endpoint has been shutdown");
+                }
+                if producer.is_self_shutdown() || producer.is_peer_shutdown() {
+                    return_errno!(EPIPE, "one or both endpoints have been shutdown");
+                }
+
+                if rb_consumer.is_empty() {
+                    if self.is_peer_shutdown() {
+                        return Ok(0);
+                    }
+                    self.observer.waiter_queue()
+                } else if rb_producer.is_full() {
+                    producer.observer.waiter_queue()
+                } else {
+                    let total_count = if consume {
+                        rb_consumer.move_to(&mut rb_producer, Some(count))
+                    } else {
+                        let mut total_count = 0;
+                        rb_consumer.for_each(|item| {
+                            if total_count < count && rb_producer.push(*item).is_ok() {
+                                total_count += 1;
+                            }
+                        });
+                        total_count
+                    };
+                    drop(rb_producer);
+                    drop(rb_consumer);
+                    if consume {
+                        self.trigger_peer_events(&IoEvents::OUT);
+                    }
+                    producer.trigger_peer_events(&IoEvents::IN);
+                    return Ok(total_count);
+                }
+            };
+
+            if nonblocking {
+                return_errno!(EAGAIN, "try again later");
+            }
+            match enqueued {
+                Some(enqueued_queue) if std::ptr::eq(enqueued_queue, waiter_queue) => {
+                    waiter.wait(None)?;
+                    enqueued = None;
+                }
+                _ => {
+                    waiter_queue.reset_and_enqueue(&waiter);
+                    enqueued = Some(waiter_queue);
+                }
+            }
+        }
+    }
+}
+
+impl<I: Copy> Consumer<I> {
+    /// Copy the items at the head of the channel to the bounce buffer `buf`
+    /// and pass them to `f` without removing them. Only the number of items
+    /// returned by `f` are consumed, so no items are lost if `f` fails.
+    ///
+    /// Unlike `pop_slice`, the ring buffer is not locked while `f` is called,
+    /// thus `f` may do I/O, e.g., writing a file. Yet the other operations on
+    /// the consumer wait until `f` returns.
+    ///
+    /// Returns 0 if the channel is empty and its producer has been shutdown.
+    pub fn pop_with<F>(&self, buf: &mut [I], nonblocking: bool, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&[I]) -> Result<usize>,
+    {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        waiter_loop!(
+            {
+                let _io_lock = self.io_lock.lock().unwrap();
+                let peek_count = {
+                    let rb_consumer = self.inner.lock().unwrap();
+                    if self.is_self_shutdown() {
+                        return_errno!(EPIPE, "this endpoint has been shutdown");
+                    }
+                    let mut peek_count = 0;
+                    rb_consumer.for_each(|item| {
+                        if peek_count < buf.len() {
+                            buf[peek_count] = *item;
+                            peek_count += 1;
+                        }
+                    });
+                    peek_count
+                };
+
+                if peek_count > 0 {
+                    let items = &mut buf[..peek_count];
+                    let count = f(items)?;
+                    debug_assert!(count <= items.len());
+                    // The items at the head never change while the I/O lock is
+                    // held, so the ones passed to `f` are consumed
+                    let count = self.inner.lock().unwrap().pop_slice(&mut items[..count]);
+                    if count > 0 {
+                        self.trigger_peer_events(&IoEvents::OUT);
+                    }
+                    return Ok(count);
+                }
+
+                if self.is_peer_shutdown() {
+                    return Ok(0);
+                }
+                if nonblocking {
+                    return_errno!(EAGAIN, "try again later");
+                }
+            },
+            self.observer.waiter_queue()
+        );
+    }
+}
+
 impl<I> Drop for Consumer<I> {
     fn drop(&mut self) {
         self.shutdown();
@@ -0,0 +1,121 @@
+use super::*;
+
+use crate::fs::mq_file::{mq_lookup, mq_names, mq_unlink, MsgQueue};
+
+/// The directory that lists all POSIX message queues, like the mqueue
+/// filesystem mounted at /dev/mqueue on Linux.
+#[derive(Debug)]
+pub struct DevMqueue;
+
+// The inode number of the entries in the directory
+const MQUEUE_INO: usize = 0x6d71_7565;
+
+impl INode for DevMqueue {
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> vfs::Result<usize> {
+        Err(vfs::FsError::NotFile)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> vfs::Result<usize> {
+        Err(vfs::FsError::NotFile)
+    }
+
+    fn metadata(&self) -> vfs::Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 3,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: vfs::FileType::Dir,
+            mode: 0o1777,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+
+    fn find(&self, name: &str) -> vfs::Result<Arc<dyn INode>> {
+        let queue = mq_lookup(name).ok_or(vfs::FsError::EntryNotFound)?;
+        Ok(Arc::new(MqueueINode(queue)))
+    }
+
+    fn get_entry(&self, id: usize) -> vfs::Result<String> {
+        match id {
+            0 => Ok(String::from(".")),
+            1 => Ok(String::from("..")),
+            i => mq_names()
+                .into_iter()
+                .nth(i - 2)
+                .ok_or(vfs::FsError::EntryNotFound),
+        }
+    }
+
+    fn iterate_entries(&self, mut ctx: &mut DirentWriterContext) -> vfs::Result<usize> {
+        let idx = ctx.pos();
+        if idx == 0 {
+            write_entry!(&mut ctx, ".", 3, vfs::FileType::Dir);
+        }
+        if idx <= 1 {
+            write_entry!(&mut ctx, "..", 1, vfs::FileType::Dir);
+        }
+        let skipped = if idx < 2 { 0 } else { idx - 2 };
+        for name in mq_names().iter().skip(skipped) {
+            write_entry!(&mut ctx, name, MQUEUE_INO, vfs::FileType::File);
+        }
+        Ok(ctx.written_len())
+    }
+
+    fn unlink(&self, name: &str) -> vfs::Result<()> {
+        mq_unlink(name).map_err(|_| vfs::FsError::EntryNotFound)
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A message queue shown in the directory. Reading it gives the status of the queue.
+struct MqueueINode(Arc<MsgQueue>);
+
+impl INode for MqueueINode {
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> vfs::Result<usize> {
+        let data = self.0.status().into_bytes();
+        let start = data.len().min(offset);
+        let end = data.len().min(offset + buf.len());
+        let len = end - start;
+        buf[0..len].copy_from_slice(&data[start..end]);
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> vfs::Result<usize> {
+        Err(vfs::FsError::PermError)
+    }
+
+    fn metadata(&self) -> vfs::Result<Metadata> {
+        let (uid, gid) = self.0.owner();
+        Ok(Metadata {
+            dev: 0,
+            inode: MQUEUE_INO,
+            size: self.0.status().len(),
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: vfs::FileType::File,
+            mode: self.0.mode().bits(),
+            nlinks: 1,
+            uid: uid as usize,
+            gid: gid as usize,
+            rdev: 0,
+        })
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
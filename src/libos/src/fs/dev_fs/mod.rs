@@ -7,6 +7,7 @@ use rcore_fs_mountfs::MountFS;
 use rcore_fs_ramfs::RamFS;
 
 use self::dev_fd::DevFd;
+use self::dev_mqueue::DevMqueue;
 use self::dev_null::DevNull;
 use self::dev_random::DevRandom;
 use self::dev_sgx::DevSgx;
@@ -14,6 +15,7 @@ use self::dev_shm::DevShm;
 use self::dev_zero::DevZero;
 
 mod dev_fd;
+mod dev_mqueue;
 mod dev_null;
 mod dev_random;
 mod dev_sgx;
@@ -37,6 +39,8 @@ pub fn init_devfs() -> Result<Arc<MountFS>> {
     devfs.add("shm", dev_shm)?;
     let dev_fd = Arc::new(DevFd) as _;
     devfs.add("fd", dev_fd);
+    let dev_mqueue = Arc::new(DevMqueue) as _;
+    devfs.add("mqueue", dev_mqueue)?;
     let mountable_devfs = MountFS::new(devfs);
     // Mount the ramfs at '/shm'
     let ramfs = RamFS::new();
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 bitflags! {
     pub struct FileMode: u16 {
@@ -69,8 +70,8 @@ impl FileMode {
 pub fn do_fchmodat(fs_path: &FsPath, mode: FileMode) -> Result<()> {
     debug!("fchmodat: fs_path: {:?}, mode: {:#o}", fs_path, mode);
 
+    let path = fs_path.to_abs_path()?;
     let inode = {
-        let path = fs_path.to_abs_path()?;
         let current = current!();
         let fs = current.fs().read().unwrap();
         fs.lookup_inode(&path)?
@@ -78,6 +79,7 @@ pub fn do_fchmodat(fs_path: &FsPath, mode: FileMode) -> Result<()> {
     let mut info = inode.metadata()?;
     info.mode = mode.bits();
     inode.set_metadata(&info)?;
+    inotify_file::notify_path(&path, &inode, InotifyMask::IN_ATTRIB);
     Ok(())
 }
 
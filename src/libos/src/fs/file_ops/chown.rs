@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 bitflags! {
     pub struct ChownFlags: i32 {
@@ -13,8 +14,8 @@ pub fn do_fchownat(fs_path: &FsPath, uid: u32, gid: u32, flags: ChownFlags) -> R
         fs_path, uid, gid, flags
     );
 
+    let path = fs_path.to_abs_path()?;
     let inode = {
-        let path = fs_path.to_abs_path()?;
         let current = current!();
         let fs = current.fs().read().unwrap();
         if flags.contains(ChownFlags::AT_SYMLINK_NOFOLLOW) {
@@ -27,6 +28,7 @@ pub fn do_fchownat(fs_path: &FsPath, uid: u32, gid: u32, flags: ChownFlags) -> R
     info.uid = uid as usize;
     info.gid = gid as usize;
     inode.set_metadata(&info)?;
+    inotify_file::notify_path(&path, &inode, InotifyMask::IN_ATTRIB);
     Ok(())
 }
 
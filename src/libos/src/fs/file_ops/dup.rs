@@ -25,7 +25,10 @@ pub fn do_dup2(old_fd: FileDesc, new_fd: FileDesc) -> Result<FileDesc> {
     if old_fd != new_fd {
         if let Some(old_file) = files.put_at(new_fd, file, false) {
             // If the file descriptor `new_fd` was previously open, close it silently.
-            old_file.release_advisory_locks()
+            old_file.release_advisory_locks();
+            if let Ok(mq_file) = old_file.as_mq() {
+                mq_file.on_close(&files);
+            }
         }
     }
     Ok(new_fd)
@@ -51,7 +54,10 @@ pub fn do_dup3(old_fd: FileDesc, new_fd: FileDesc, flags: u32) -> Result<FileDes
     }
     if let Some(old_file) = files.put_at(new_fd, file, creation_flags.must_close_on_spawn()) {
         // If the file descriptor `new_fd` was previously open, close it silently.
-        old_file.release_advisory_locks()
+        old_file.release_advisory_locks();
+        if let Ok(mq_file) = old_file.as_mq() {
+            mq_file.on_close(&files);
+        }
     }
     Ok(new_fd)
 }
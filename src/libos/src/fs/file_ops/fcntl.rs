@@ -1,7 +1,11 @@
 use super::locks::range_lock::c_flock;
 use super::*;
+use crate::vm::USER_SPACE_VM_MANAGER;
 use util::mem_util::from_user;
 
+const F_ADD_SEALS: c_int = 1033;
+const F_GET_SEALS: c_int = 1034;
+
 #[derive(Debug)]
 pub enum FcntlCmd<'a> {
     /// Duplicate the file descriptor fd using the lowest-numbered available
@@ -24,6 +28,10 @@ pub enum FcntlCmd<'a> {
     SetLk(&'a c_flock),
     /// The blocking version of SetLK
     SetLkWait(&'a c_flock),
+    /// Add seals to the file
+    AddSeals(FileSeals),
+    /// Get the seals of the file
+    GetSeals(),
 }
 
 impl<'a> FcntlCmd<'a> {
@@ -54,6 +62,8 @@ impl<'a> FcntlCmd<'a> {
                 let lock_c = unsafe { &*lock_ptr };
                 FcntlCmd::SetLkWait(lock_c)
             }
+            F_ADD_SEALS => FcntlCmd::AddSeals(FileSeals::from_u32(arg as u32)?),
+            F_GET_SEALS => FcntlCmd::GetSeals(),
             _ => return_errno!(EINVAL, "unsupported command"),
         })
     }
@@ -135,6 +145,26 @@ pub fn do_fcntl(fd: FileDesc, cmd: &mut FcntlCmd) -> Result<isize> {
             file.set_advisory_lock(&lock, is_nonblocking)?;
             0
         }
+        FcntlCmd::AddSeals(seals) => {
+            let file = file_table.get(fd)?;
+            let inode_file = file
+                .as_inode_file()
+                .map_err(|_| errno!(EINVAL, "the file does not support sealing"))?;
+            if seals.contains(FileSeals::F_SEAL_WRITE)
+                && USER_SPACE_VM_MANAGER.has_writable_shared_mapping(&file)
+            {
+                return_errno!(EBUSY, "the file has writable shared mappings");
+            }
+            inode_file.add_seals(*seals)?;
+            0
+        }
+        FcntlCmd::GetSeals() => {
+            let file = file_table.get(fd)?;
+            let inode_file = file
+                .as_inode_file()
+                .map_err(|_| errno!(EINVAL, "the file does not support sealing"))?;
+            inode_file.seals()?.bits() as isize
+        }
     };
     Ok(ret)
 }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 bitflags! {
     pub struct LinkFlags: i32 {
@@ -31,5 +32,12 @@ pub fn do_linkat(old_fs_path: &FsPath, new_fs_path: &FsPath, flags: LinkFlags) -
         (inode, new_dir_inode)
     };
     new_dir_inode.link(new_file_name, &inode)?;
+    inotify_file::notify_inode(&inode, InotifyMask::IN_ATTRIB);
+    inotify_file::notify_dir_entry(
+        &new_dir_inode,
+        new_file_name,
+        &inode,
+        InotifyMask::IN_CREATE,
+    );
     Ok(())
 }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 pub fn do_mkdirat(fs_path: &FsPath, mode: FileMode) -> Result<()> {
     debug!("mkdirat: fs_path: {:?}, mode: {:#o}", fs_path, mode.bits());
@@ -17,6 +18,7 @@ pub fn do_mkdirat(fs_path: &FsPath, mode: FileMode) -> Result<()> {
         return_errno!(EPERM, "dir cannot be written");
     }
     let masked_mode = mode & !current.process().umask();
-    inode.create(file_name, FileType::Dir, masked_mode.bits())?;
+    let new_inode = inode.create(file_name, FileType::Dir, masked_mode.bits())?;
+    inotify_file::notify_dir_entry(&inode, file_name, &new_inode, InotifyMask::IN_CREATE);
     Ok(())
 }
@@ -25,6 +25,7 @@ pub use self::read::{do_pread, do_preadv, do_read, do_readv};
 pub use self::rename::do_renameat;
 pub use self::rmdir::do_rmdir;
 pub use self::sendfile::do_sendfile;
+pub use self::splice::{do_splice, do_tee, do_vmsplice, SpliceFlags};
 pub use self::stat::{do_fstat, do_fstatat, Stat, StatFlags};
 pub use self::symlink::{do_readlinkat, do_symlinkat};
 pub use self::truncate::{do_ftruncate, do_truncate};
@@ -33,6 +34,10 @@ pub use self::utimes::{
     do_utimes_fd, do_utimes_path, get_utimes, utimbuf_t, Utime, UtimeFlags, UTIME_OMIT,
 };
 pub use self::write::{do_pwrite, do_pwritev, do_write, do_writev};
+pub use self::xattr::{
+    do_getxattr, do_listxattr, do_removexattr, do_setxattr, register_sefs_xattr_storage,
+    XattrFlags, XattrNamespace, XattrTarget,
+};
 
 mod access;
 mod chmod;
@@ -55,9 +60,11 @@ mod read;
 mod rename;
 mod rmdir;
 mod sendfile;
+mod splice;
 mod stat;
 mod symlink;
 mod truncate;
 mod unlink;
 mod utimes;
 mod write;
+mod xattr;
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file;
 
 pub fn do_renameat(old_fs_path: &FsPath, new_fs_path: &FsPath) -> Result<()> {
     debug!(
@@ -24,8 +25,8 @@ pub fn do_renameat(old_fs_path: &FsPath, new_fs_path: &FsPath) -> Result<()> {
     let (new_dir_path, new_file_name) = split_path(&newpath.trim_end_matches('/'));
     let old_dir_inode = fs.lookup_inode(old_dir_path)?;
     let new_dir_inode = fs.lookup_inode(new_dir_path)?;
+    let old_file_inode = old_dir_inode.find(old_file_name)?;
     let old_file_mode = {
-        let old_file_inode = old_dir_inode.find(old_file_name)?;
         let metadata = old_file_inode.metadata()?;
         // oldpath is directory, the old_file_inode should be directory
         if oldpath.ends_with("/") && metadata.type_ != FileType::Dir {
@@ -36,6 +37,29 @@ pub fn do_renameat(old_fs_path: &FsPath, new_fs_path: &FsPath) -> Result<()> {
     if old_file_mode.has_sticky_bit() {
         warn!("ignoring the sticky bit");
     }
+    // The target to be overwritten, unless it is the same file as the source
+    let replaced_inode = new_dir_inode
+        .find(new_file_name)
+        .ok()
+        .filter(|inode| !is_same_inode(inode, &old_file_inode));
     old_dir_inode.move_(old_file_name, &new_dir_inode, new_file_name)?;
+    if let Some(replaced_inode) = replaced_inode {
+        inotify_file::notify_link_removed(&replaced_inode);
+        super::xattr::remove_xattrs_of_unlinked(&replaced_inode);
+    }
+    inotify_file::notify_move(
+        &old_dir_inode,
+        old_file_name,
+        &new_dir_inode,
+        new_file_name,
+        &old_file_inode,
+    );
     Ok(())
 }
+
+fn is_same_inode(lhs: &Arc<dyn INode>, rhs: &Arc<dyn INode>) -> bool {
+    match (lhs.metadata(), rhs.metadata()) {
+        (Ok(lhs), Ok(rhs)) => lhs.dev == rhs.dev && lhs.inode == rhs.inode,
+        _ => false,
+    }
+}
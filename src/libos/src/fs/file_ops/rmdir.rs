@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 pub fn do_rmdir(path: &str) -> Result<()> {
     debug!("rmdir: path: {:?}", path);
@@ -14,5 +15,8 @@ pub fn do_rmdir(path: &str) -> Result<()> {
         return_errno!(ENOTDIR, "rmdir on not directory");
     }
     dir_inode.unlink(file_name)?;
+    inotify_file::notify_dir_entry(&dir_inode, file_name, &file_inode, InotifyMask::IN_DELETE);
+    inotify_file::notify_inode(&file_inode, InotifyMask::IN_DELETE_SELF);
+    super::xattr::remove_xattrs_of_unlinked(&file_inode);
     Ok(())
 }
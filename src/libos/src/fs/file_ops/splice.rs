@@ -0,0 +1,341 @@
+use super::super::channel::{Consumer, Producer};
+use super::*;
+use crate::net::{
+    AsLoopbackSocket, AsUnixSocket, EventMonitorBuilder, HostSocketType, RecvFlags, SendFlags,
+};
+
+/// The max size of the bounce buffer to copy between a pipe and a file that is
+/// not a pipe.
+///
+/// Only the data moved between two pipes avoids the copy, which is done by the
+/// ring buffers of the pipes directly. A large buffer makes the copy with the
+/// other files, e.g., sockets, take fewer calls.
+const SPLICE_BUF_SIZE: usize = 128 * 1024;
+
+bitflags! {
+    /// The flags of splice, tee and vmsplice
+    pub struct SpliceFlags: u32 {
+        /// Move pages instead of copying, which is only a hint
+        const SPLICE_F_MOVE = 0x1;
+        /// Do not block on the pipe operations
+        const SPLICE_F_NONBLOCK = 0x2;
+        /// More data will be coming in a subsequent splice
+        const SPLICE_F_MORE = 0x4;
+        /// Gift the user pages to the kernel, which is only for vmsplice
+        const SPLICE_F_GIFT = 0x8;
+    }
+}
+
+impl SpliceFlags {
+    pub fn from_u32(raw_flags: u32) -> Result<Self> {
+        Self::from_bits(raw_flags).ok_or_else(|| errno!(EINVAL, "invalid splice flags"))
+    }
+}
+
+pub fn do_splice(
+    fd_in: FileDesc,
+    off_in: Option<&mut off_t>,
+    fd_out: FileDesc,
+    off_out: Option<&mut off_t>,
+    len: usize,
+    flags: SpliceFlags,
+) -> Result<usize> {
+    debug!(
+        "splice: fd_in: {}, off_in: {:?}, fd_out: {}, off_out: {:?}, len: {}, flags: {:?}",
+        fd_in, off_in, fd_out, off_out, len, flags
+    );
+
+    let current = current!();
+    let in_file = current.file(fd_in)?;
+    let out_file = current.file(fd_out)?;
+    if in_file.access_mode().map_or(false, |mode| !mode.readable()) {
+        return_errno!(EBADF, "the input file is not readable");
+    }
+    if out_file
+        .access_mode()
+        .map_or(false, |mode| !mode.writable())
+    {
+        return_errno!(EBADF, "the output file is not writable");
+    }
+    if out_file
+        .status_flags()
+        .map_or(false, |flags| flags.always_append())
+    {
+        return_errno!(EINVAL, "the output file is opened in append mode");
+    }
+    for offset in off_in.iter().chain(off_out.iter()) {
+        if **offset < 0 {
+            return_errno!(EINVAL, "the offset is negative");
+        }
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+
+    match (in_file.as_pipe_reader(), out_file.as_pipe_writer()) {
+        (Ok(reader), Ok(writer)) => {
+            if off_in.is_some() || off_out.is_some() {
+                return_errno!(ESPIPE, "pipes have no offsets");
+            }
+            let (consumer, producer) = (reader.consumer(), writer.producer());
+            if consumer.is_peer_of(producer) {
+                return_errno!(EINVAL, "cannot splice a pipe to itself");
+            }
+            // Like Linux, a non-blocking pipe makes the splice non-blocking
+            let nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)
+                || consumer.is_nonblocking()
+                || producer.is_nonblocking();
+            consumer.move_to(producer, len, nonblocking)
+        }
+        (Ok(reader), Err(_)) => {
+            if off_in.is_some() {
+                return_errno!(ESPIPE, "pipes have no offsets");
+            }
+            splice_from_pipe(reader.consumer(), &out_file, off_out, len, flags)
+        }
+        (Err(_), Ok(writer)) => {
+            if off_out.is_some() {
+                return_errno!(ESPIPE, "pipes have no offsets");
+            }
+            splice_to_pipe(&in_file, off_in, writer.producer(), len, flags)
+        }
+        (Err(_), Err(_)) => return_errno!(EINVAL, "neither of the files is a pipe"),
+    }
+}
+
+pub fn do_tee(fd_in: FileDesc, fd_out: FileDesc, len: usize, flags: SpliceFlags) -> Result<usize> {
+    debug!(
+        "tee: fd_in: {}, fd_out: {}, len: {}, flags: {:?}",
+        fd_in, fd_out, len, flags
+    );
+
+    let current = current!();
+    let in_file = current.file(fd_in)?;
+    let out_file = current.file(fd_out)?;
+    let (consumer, producer) = match (in_file.as_pipe_reader(), out_file.as_pipe_writer()) {
+        (Ok(reader), Ok(writer)) => (reader.consumer(), writer.producer()),
+        _ => return_errno!(EINVAL, "both files must be pipes"),
+    };
+    if consumer.is_peer_of(producer) {
+        return_errno!(EINVAL, "cannot tee a pipe to itself");
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+
+    let nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)
+        || consumer.is_nonblocking()
+        || producer.is_nonblocking();
+    consumer.copy_to(producer, len, nonblocking)
+}
+
+/// Copy between the user buffers and a pipe.
+///
+/// Since the buffers are not shared with the pipe like Linux does, the data is
+/// always copied, and SPLICE_F_GIFT is ignored.
+pub fn do_vmsplice(fd: FileDesc, bufs: &[UserBuf], flags: SpliceFlags) -> Result<usize> {
+    debug!("vmsplice: fd: {}, bufs: {:?}, flags: {:?}", fd, bufs, flags);
+
+    let file = current!().file(fd)?;
+    let len: usize = bufs.iter().map(|buf| buf.len()).sum();
+    let nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK);
+    let mut bounce_buf = vec![0; len.min(SPLICE_BUF_SIZE)];
+    if let Ok(writer) = file.as_pipe_writer() {
+        let producer = writer.producer();
+        let nonblocking = nonblocking || producer.is_nonblocking();
+        producer.push_with(&mut bounce_buf, nonblocking, |items| {
+            UserBuf::gather(bufs, items)
+        })
+    } else if let Ok(reader) = file.as_pipe_reader() {
+        let consumer = reader.consumer();
+        let nonblocking = nonblocking || consumer.is_nonblocking();
+        consumer.pop_with(&mut bounce_buf, nonblocking, |items| {
+            UserBuf::scatter(bufs, items)
+        })
+    } else {
+        return_errno!(EBADF, "not a pipe");
+    }
+}
+
+/// Move the data of a pipe to a file, which is done by chunks through a bounce
+/// buffer. Only the data written to the file is consumed, so no data is lost if
+/// the write fails.
+///
+/// A socket is written without blocking, and waited for outside the pipe
+/// operations if it is not ready, so that the pipe is never held by a blocked
+/// write.
+fn splice_from_pipe(
+    consumer: &Consumer<u8>,
+    out_file: &FileRef,
+    offset: Option<&mut off_t>,
+    len: usize,
+    flags: SpliceFlags,
+) -> Result<usize> {
+    let mut pos = offset.as_ref().map(|offset| **offset as usize);
+    // A file that may block, e.g., a socket, is written only once
+    let write_once = !is_regular_file(out_file);
+
+    let mut bounce_buf = vec![0; len.min(SPLICE_BUF_SIZE)];
+    let mut total_len = 0;
+    while total_len < len {
+        let chunk_len = (len - total_len).min(bounce_buf.len());
+        // Never block for the pipe once some data has been moved
+        let nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)
+            || consumer.is_nonblocking()
+            || total_len > 0;
+        let mut is_file_ready = true;
+        let res = consumer.pop_with(&mut bounce_buf[..chunk_len], nonblocking, |buf| {
+            let res = match pos.as_mut() {
+                Some(pos) => out_file.write_at(*pos, buf).map(|write_len| {
+                    *pos += write_len;
+                    write_len
+                }),
+                None => {
+                    // Tell the socket that more data is coming, as Linux does
+                    let more =
+                        flags.contains(SpliceFlags::SPLICE_F_MORE) || total_len + buf.len() < len;
+                    let send_flags = if more {
+                        SendFlags::MSG_MORE
+                    } else {
+                        SendFlags::empty()
+                    };
+                    write_to_file(out_file, buf, send_flags)
+                }
+            };
+            is_file_ready = !matches!(&res, Err(e) if e.errno() == EAGAIN);
+            res
+        });
+        match res {
+            Ok(0) => break,
+            Ok(write_len) => total_len += write_len,
+            Err(_) if !is_file_ready && total_len == 0 && !is_nonblocking_file(out_file) => {
+                wait_for_file(out_file, IoEvents::OUT)?;
+                continue;
+            }
+            Err(_) if total_len > 0 => break,
+            Err(e) => return Err(e),
+        }
+        if write_once {
+            break;
+        }
+    }
+
+    if let (Some(offset), Some(pos)) = (offset, pos) {
+        *offset = pos as off_t;
+    }
+    Ok(total_len)
+}
+
+/// Move the data of a file to a pipe, which is done by chunks through a bounce
+/// buffer. The data read from the file always fits into the pipe.
+///
+/// A socket is read without blocking, and waited for outside the pipe
+/// operations if it is not ready, so that the pipe is never held by a blocked
+/// read.
+fn splice_to_pipe(
+    in_file: &FileRef,
+    offset: Option<&mut off_t>,
+    producer: &Producer<u8>,
+    len: usize,
+    flags: SpliceFlags,
+) -> Result<usize> {
+    let mut pos = offset.as_ref().map(|offset| **offset as usize);
+    // A file that may block, e.g., a socket, is read only once
+    let read_once = !is_regular_file(in_file);
+
+    let mut bounce_buf = vec![0; len.min(SPLICE_BUF_SIZE)];
+    let mut total_len = 0;
+    while total_len < len {
+        let chunk_len = (len - total_len).min(bounce_buf.len());
+        // Never block for the pipe once some data has been moved
+        let nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)
+            || producer.is_nonblocking()
+            || total_len > 0;
+        let mut is_file_ready = true;
+        let res = producer.push_with(&mut bounce_buf[..chunk_len], nonblocking, |buf| {
+            let res = match pos.as_mut() {
+                Some(pos) => in_file.read_at(*pos, buf).map(|read_len| {
+                    *pos += read_len;
+                    read_len
+                }),
+                None => read_from_file(in_file, buf),
+            };
+            is_file_ready = !matches!(&res, Err(e) if e.errno() == EAGAIN);
+            res
+        });
+        match res {
+            Ok(0) => break,
+            Ok(read_len) => total_len += read_len,
+            Err(_) if !is_file_ready && total_len == 0 && !is_nonblocking_file(in_file) => {
+                wait_for_file(in_file, IoEvents::IN)?;
+                continue;
+            }
+            Err(_) if total_len > 0 => break,
+            Err(e) => return Err(e),
+        }
+        if read_once {
+            break;
+        }
+    }
+
+    if let (Some(offset), Some(pos)) = (offset, pos) {
+        *offset = pos as off_t;
+    }
+    Ok(total_len)
+}
+
+/// Write to a file, with the send flags if it is a socket. A socket is never
+/// blocked, but returns EAGAIN if it is not ready.
+fn write_to_file(file: &FileRef, buf: &[u8], send_flags: SendFlags) -> Result<usize> {
+    let send_flags = send_flags | SendFlags::MSG_DONTWAIT;
+    if let Ok(socket) = file.as_host_socket() {
+        socket.sendto(buf, send_flags, &None)
+    } else if let Ok(socket) = file.as_loopback_socket() {
+        socket.sendto(buf, send_flags, &None)
+    } else if let Ok(socket) = file.as_unix_socket() {
+        socket.sendto(buf, send_flags, &None)
+    } else {
+        file.write(buf)
+    }
+}
+
+/// Read from a file. A socket is never blocked, but returns EAGAIN if it is
+/// not ready.
+fn read_from_file(file: &FileRef, buf: &mut [u8]) -> Result<usize> {
+    let recv_flags = RecvFlags::MSG_DONTWAIT;
+    if let Ok(socket) = file.as_host_socket() {
+        socket.recvfrom(buf, recv_flags).map(|(len, _)| len)
+    } else if let Ok(socket) = file.as_loopback_socket() {
+        socket.recvfrom(buf, recv_flags).map(|(len, _)| len)
+    } else if let Ok(socket) = file.as_unix_socket() {
+        socket.recvfrom(buf, recv_flags).map(|(len, _)| len)
+    } else {
+        file.read(buf)
+    }
+}
+
+/// Wait until the file is ready for the events, or has an error to report.
+fn wait_for_file(file: &FileRef, events: IoEvents) -> Result<()> {
+    let events = events | IoEvents::ERR | IoEvents::HUP;
+    let mut builder = EventMonitorBuilder::new(1);
+    builder.add_file(file.clone(), events);
+    // Building the monitor polls the host files
+    let mut monitor = builder.build();
+    loop {
+        monitor.reset_events();
+        if file.poll_new().intersects(events) {
+            return Ok(());
+        }
+        monitor.wait_events(None)?;
+    }
+}
+
+fn is_nonblocking_file(file: &FileRef) -> bool {
+    file.status_flags()
+        .map_or(false, |flags| flags.contains(StatusFlags::O_NONBLOCK))
+}
+
+fn is_regular_file(file: &FileRef) -> bool {
+    file.metadata()
+        .map_or(false, |metadata| metadata.type_ == FileType::File)
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 pub fn do_readlinkat(fs_path: &FsPath, buf: &mut [u8]) -> Result<usize> {
     debug!("readlinkat: fs_path: {:?}", fs_path);
@@ -51,5 +52,6 @@ pub fn do_symlinkat(target: &str, link_path: &FsPath) -> Result<usize> {
     let data = target.as_bytes();
     link_inode.resize(data.len())?;
     link_inode.write_at(0, data)?;
+    inotify_file::notify_dir_entry(&dir_inode, link_name, &link_inode, InotifyMask::IN_CREATE);
     Ok(0)
 }
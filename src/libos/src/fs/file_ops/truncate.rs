@@ -1,13 +1,15 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 pub fn do_truncate(path: &str, len: usize) -> Result<()> {
     debug!("truncate: path: {:?}, len: {}", path, len);
-    let inode = {
+    let (inode, abs_path) = {
         let current = current!();
         let fs = current.fs().read().unwrap();
-        fs.lookup_inode(&path)?
+        (fs.lookup_inode(&path)?, fs.convert_to_abs_path(&path))
     };
     inode.resize(len)?;
+    inotify_file::notify_path(&abs_path, &inode, InotifyMask::IN_MODIFY);
     Ok(())
 }
 
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 bitflags! {
     pub struct UnlinkFlags: i32 {
@@ -26,6 +27,10 @@ fn do_unlink(path: &str) -> Result<()> {
         warn!("ignoring the sticky bit");
     }
     dir_inode.unlink(file_name)?;
+    inotify_file::notify_dir_entry(&dir_inode, file_name, &file_inode, InotifyMask::IN_DELETE);
+    // The link count of the file is decreased
+    inotify_file::notify_link_removed(&file_inode);
+    super::xattr::remove_xattrs_of_unlinked(&file_inode);
     Ok(())
 }
 
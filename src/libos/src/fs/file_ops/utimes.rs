@@ -2,6 +2,7 @@ use crate::util::mem_util::from_user;
 
 use super::time::{timespec_t, OcclumTimeProvider};
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 use rcore_fs::dev::TimeProvider;
 
@@ -116,8 +117,8 @@ pub fn do_utimes_path(
         fs_path, atime, mtime, flags
     );
 
+    let path = fs_path.to_abs_path()?;
     let inode = {
-        let path = fs_path.to_abs_path()?;
         let current = current!();
         let fs = current.fs().read().unwrap();
         if flags.contains(UtimeFlags::AT_SYMLINK_NOFOLLOW) {
@@ -134,5 +135,6 @@ pub fn do_utimes_path(
         info.mtime = mtime;
     }
     inode.set_metadata(&info)?;
+    inotify_file::notify_path(&path, &inode, InotifyMask::IN_ATTRIB);
     Ok(0)
 }
@@ -0,0 +1,684 @@
+//! Extended attributes of files. See xattr(7) for the details.
+//!
+//! For the HostFS, the extended attributes are passed through to the host
+//! file, but only those in the namespace configured for the mount. For other
+//! file systems, they are held by the extension of the inode. The xattrs of
+//! SEFS inodes are also saved in a record file beside the files of the SEFS on
+//! the host, so that they survive the eviction of the inode and remounting.
+//! The xattrs are not supported by the UnionFS, whose inodes are not
+//! persistent.
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::sync::Weak;
+use std::untrusted::path::PathEx;
+
+use super::*;
+use crate::fs::hostfs::HNode;
+use crate::fs::inotify_file::{self, InotifyMask};
+use crate::fs::sefs::SgxStorage;
+use rcore_fs::vfs::AnyExt;
+use rcore_fs_mountfs::MNode;
+use rcore_fs_sefs::dev::{File as _, Storage};
+use rcore_fs_sefs::SEFS;
+
+/// The maximum length of the name of an extended attribute
+const XATTR_NAME_MAX: usize = 255;
+/// The maximum size of the value of an extended attribute
+const XATTR_SIZE_MAX: usize = 65536;
+/// The maximum size of the list of the names of extended attributes
+const XATTR_LIST_MAX: usize = 65536;
+/// The prefix of the names of the xattr record files of a SEFS, which are
+/// followed by the inode numbers
+const XATTR_RECORD_PREFIX: &str = "xattrs.";
+
+lazy_static! {
+    static ref SEFS_XATTR_STORAGES: SgxMutex<Vec<Arc<SefsXattrStorage>>> =
+        SgxMutex::new(Vec::new());
+}
+
+bitflags! {
+    pub struct XattrFlags: i32 {
+        /// Fail if the attribute already exists
+        const XATTR_CREATE = 0x1;
+        /// Fail if the attribute does not exist
+        const XATTR_REPLACE = 0x2;
+    }
+}
+
+impl XattrFlags {
+    pub fn from_i32(raw_flags: i32) -> Result<Self> {
+        let flags =
+            Self::from_bits(raw_flags).ok_or_else(|| errno!(EINVAL, "invalid xattr flags"))?;
+        if flags.contains(Self::XATTR_CREATE | Self::XATTR_REPLACE) {
+            return_errno!(EINVAL, "XATTR_CREATE and XATTR_REPLACE are exclusive");
+        }
+        Ok(flags)
+    }
+}
+
+/// The namespaces of extended attributes that are supported.
+///
+/// The "system" namespace is not supported as it is used by the kernel
+/// features, e.g., POSIX ACLs, which the LibOS does not have.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum XattrNamespace {
+    Security,
+    Trusted,
+    User,
+}
+
+impl XattrNamespace {
+    pub fn from_str(namespace: &str) -> Result<Self> {
+        Ok(match namespace {
+            "security" => Self::Security,
+            "trusted" => Self::Trusted,
+            "user" => Self::User,
+            _ => return_errno!(EOPNOTSUPP, "unsupported xattr namespace"),
+        })
+    }
+
+    /// Get the namespace of the attribute name, e.g., "user.mime_type".
+    fn of_name(name: &str) -> Result<Self> {
+        let (namespace, suffix) = name
+            .split_once('.')
+            .ok_or_else(|| errno!(EOPNOTSUPP, "no xattr namespace"))?;
+        if suffix.is_empty() {
+            return_errno!(EINVAL, "empty xattr name in the namespace");
+        }
+        Self::from_str(namespace)
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::Security => "security.",
+            Self::Trusted => "trusted.",
+            Self::User => "user.",
+        }
+    }
+}
+
+/// The target file of an xattr syscall.
+#[derive(Debug)]
+pub enum XattrTarget<'a> {
+    /// A path whose final symlink is followed
+    Path(&'a str),
+    /// A path whose final symlink is not followed
+    LPath(&'a str),
+    Fd(FileDesc),
+}
+
+impl<'a> XattrTarget<'a> {
+    fn inode(&self) -> Result<Arc<dyn INode>> {
+        let inode = match self {
+            Self::Path(path) => {
+                let current = current!();
+                let fs = current.fs().read().unwrap();
+                fs.lookup_inode(path)?
+            }
+            Self::LPath(path) => {
+                let current = current!();
+                let fs = current.fs().read().unwrap();
+                fs.lookup_inode_no_follow(path)?
+            }
+            Self::Fd(fd) => {
+                let file_ref = current!().file(*fd)?;
+                let inode_file = file_ref
+                    .as_inode_file()
+                    .map_err(|_| errno!(EOPNOTSUPP, "xattrs are not supported by the file"))?;
+                inode_file.inode().clone()
+            }
+        };
+        Ok(inode)
+    }
+
+    fn notify_attrib(&self, inode: &Arc<dyn INode>) {
+        if let Self::Path(path) | Self::LPath(path) = self {
+            let abs_path = current!().fs().read().unwrap().convert_to_abs_path(path);
+            inotify_file::notify_path(&abs_path, inode, InotifyMask::IN_ATTRIB);
+        }
+    }
+}
+
+pub fn do_setxattr(
+    target: &XattrTarget,
+    name: &str,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<()> {
+    debug!(
+        "setxattr: target: {:?}, name: {:?}, size: {}, flags: {:?}",
+        target,
+        name,
+        value.len(),
+        flags
+    );
+    let namespace = check_name(name)?;
+    if value.len() > XATTR_SIZE_MAX {
+        return_errno!(E2BIG, "the xattr value is too large");
+    }
+    let inode = target.inode()?;
+    if namespace == XattrNamespace::User {
+        // The same as Linux, the user xattrs are restricted to regular files
+        // and directories as the permission bits of others have different
+        // meanings
+        let type_ = inode.metadata()?.type_;
+        if type_ != FileType::File && type_ != FileType::Dir {
+            return_errno!(EPERM, "user xattrs are not allowed on the file type");
+        }
+    }
+    match XattrStore::of_inode(&inode)? {
+        XattrStore::Host(hnode) => host_setxattr(hnode, namespace, name, value, flags)?,
+        XattrStore::Memory(xattrs) => xattrs.set(name, value, flags)?,
+    }
+    target.notify_attrib(&inode);
+    Ok(())
+}
+
+/// Get the value of the attribute. If the buffer is empty, return the size
+/// of the value only.
+pub fn do_getxattr(target: &XattrTarget, name: &str, value: &mut [u8]) -> Result<usize> {
+    debug!(
+        "getxattr: target: {:?}, name: {:?}, size: {}",
+        target,
+        name,
+        value.len()
+    );
+    let namespace = check_name(name)?;
+    let inode = target.inode()?;
+    match XattrStore::of_inode(&inode)? {
+        XattrStore::Host(hnode) => host_getxattr(hnode, namespace, name, value),
+        XattrStore::Memory(xattrs) => xattrs.get(name, value),
+    }
+}
+
+/// List the null-terminated names of the attributes. If the buffer is empty,
+/// return the size of the list only.
+pub fn do_listxattr(target: &XattrTarget, list: &mut [u8]) -> Result<usize> {
+    debug!("listxattr: target: {:?}, size: {}", target, list.len());
+    let inode = target.inode()?;
+    let names = match XattrStore::of_inode(&inode)? {
+        XattrStore::Host(hnode) => host_listxattr(hnode)?,
+        XattrStore::Memory(xattrs) => xattrs.list(),
+    };
+    copy_to_buf(&names, list)
+}
+
+pub fn do_removexattr(target: &XattrTarget, name: &str) -> Result<()> {
+    debug!("removexattr: target: {:?}, name: {:?}", target, name);
+    let namespace = check_name(name)?;
+    let inode = target.inode()?;
+    match XattrStore::of_inode(&inode)? {
+        XattrStore::Host(hnode) => host_removexattr(hnode, namespace, name)?,
+        XattrStore::Memory(xattrs) => xattrs.remove(name)?,
+    }
+    target.notify_attrib(&inode);
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<XattrNamespace> {
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return_errno!(ERANGE, "invalid length of the xattr name");
+    }
+    XattrNamespace::of_name(name)
+}
+
+/// Copy the data to the buffer, or return the size of the data if the buffer
+/// is empty.
+fn copy_to_buf(data: &[u8], buf: &mut [u8]) -> Result<usize> {
+    if buf.is_empty() {
+        return Ok(data.len());
+    }
+    if buf.len() < data.len() {
+        return_errno!(ERANGE, "the buffer is too small");
+    }
+    buf[..data.len()].copy_from_slice(data);
+    Ok(data.len())
+}
+
+/// Where the extended attributes of an inode are stored.
+enum XattrStore<'a> {
+    Host(&'a HNode),
+    Memory(Arc<XattrMap>),
+}
+
+impl<'a> XattrStore<'a> {
+    fn of_inode(inode: &'a Arc<dyn INode>) -> Result<Self> {
+        let inode = match inode.downcast_ref::<MNode>() {
+            Some(mnode) => &mnode.inode,
+            None => inode,
+        };
+        if let Some(hnode) = inode.downcast_ref::<HNode>() {
+            return Ok(Self::Host(hnode));
+        }
+        // The xattrs would be lost when the inode is evicted
+        if inode.fs().info().magic == rcore_fs_unionfs::UNIONFS_MAGIC {
+            return_errno!(EOPNOTSUPP, "xattrs are not supported by the UnionFS");
+        }
+        let ext = inode
+            .ext()
+            .ok_or_else(|| errno!(EOPNOTSUPP, "xattrs are not supported by the file system"))?;
+        let xattrs = match ext.get::<XattrMap>() {
+            Some(xattrs) => xattrs,
+            None => ext.get_or_put_default::<XattrMap>(),
+        };
+        xattrs.load_once(inode)?;
+        Ok(Self::Memory(xattrs))
+    }
+}
+
+/// Remove the xattr record of an inode whose last link has been removed, so
+/// that it is not inherited by a new inode reusing the inode number.
+pub(super) fn remove_xattrs_of_unlinked(inode: &Arc<dyn INode>) {
+    let inode = match inode.downcast_ref::<MNode>() {
+        Some(mnode) => &mnode.inode,
+        None => inode,
+    };
+    let result = XattrRecord::of_inode(inode).and_then(|record| match record {
+        Some(record) => {
+            let metadata = inode.metadata()?;
+            if metadata.nlinks == 0 || metadata.type_ == FileType::Dir {
+                record.save(&BTreeMap::new())?;
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    });
+    if let Err(e) = result {
+        warn!("failed to remove the xattr record: {:?}", e);
+    }
+}
+
+/// The extended attributes of an inode held in memory.
+#[derive(Default)]
+struct XattrMap {
+    inner: RwLock<XattrMapInner>,
+}
+
+#[derive(Default)]
+struct XattrMapInner {
+    xattrs: BTreeMap<String, Vec<u8>>,
+    /// The record where the xattrs are saved if the file system is persistent
+    record: Option<XattrRecord>,
+    is_loaded: bool,
+}
+
+impl AnyExt for XattrMap {}
+
+impl XattrMap {
+    /// Load the xattrs from the record when the map is put in the extension of
+    /// an inode, i.e., the first time after the inode is opened.
+    fn load_once(&self, inode: &Arc<dyn INode>) -> Result<()> {
+        if self.inner.read().unwrap().is_loaded {
+            return Ok(());
+        }
+        let mut inner = self.inner.write().unwrap();
+        if inner.is_loaded {
+            return Ok(());
+        }
+        if let Some(record) = XattrRecord::of_inode(inode)? {
+            inner.xattrs = record.load()?;
+            inner.record = Some(record);
+        }
+        inner.is_loaded = true;
+        Ok(())
+    }
+
+    fn get(&self, name: &str, value: &mut [u8]) -> Result<usize> {
+        let inner = self.inner.read().unwrap();
+        let xattr_value = inner
+            .xattrs
+            .get(name)
+            .ok_or_else(|| errno!(ENODATA, "the xattr does not exist"))?;
+        copy_to_buf(xattr_value, value)
+    }
+
+    fn set(&self, name: &str, value: &[u8], flags: XattrFlags) -> Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        let exists = inner.xattrs.contains_key(name);
+        if exists && flags.contains(XattrFlags::XATTR_CREATE) {
+            return_errno!(EEXIST, "the xattr already exists");
+        }
+        if !exists {
+            if flags.contains(XattrFlags::XATTR_REPLACE) {
+                return_errno!(ENODATA, "the xattr does not exist");
+            }
+            let list_len: usize = inner.xattrs.keys().map(|name| name.len() + 1).sum();
+            if list_len + name.len() + 1 > XATTR_LIST_MAX {
+                return_errno!(ENOSPC, "too many xattrs");
+            }
+        }
+        let mut xattrs = inner.xattrs.clone();
+        xattrs.insert(name.to_owned(), value.to_vec());
+        inner.update(xattrs)
+    }
+
+    fn list(&self) -> Vec<u8> {
+        let inner = self.inner.read().unwrap();
+        let mut names = Vec::new();
+        for name in inner.xattrs.keys() {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        names
+    }
+
+    fn remove(&self, name: &str) -> Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        if !inner.xattrs.contains_key(name) {
+            return_errno!(ENODATA, "the xattr does not exist");
+        }
+        let mut xattrs = inner.xattrs.clone();
+        xattrs.remove(name);
+        inner.update(xattrs)
+    }
+}
+
+impl XattrMapInner {
+    /// Save the new xattrs to the record before taking them, so that the
+    /// xattrs in memory are unchanged if the saving fails.
+    fn update(&mut self, xattrs: BTreeMap<String, Vec<u8>>) -> Result<()> {
+        if let Some(record) = self.record.as_ref() {
+            record.save(&xattrs)?;
+        }
+        self.xattrs = xattrs;
+        Ok(())
+    }
+}
+
+/// Keep the xattr records of a writable SEFS in the storage, which is in the
+/// same host directory as the files of the SEFS and has the same protection.
+/// The records are not in the namespace of the SEFS, so they can be neither
+/// seen nor modified by the user.
+pub fn register_sefs_xattr_storage(sefs: &Arc<SEFS>, dir: &Path, storage: SgxStorage) {
+    let mut storages = SEFS_XATTR_STORAGES.lock().unwrap();
+    storages.retain(|storage| storage.sefs.strong_count() > 0);
+    storages.push(Arc::new(SefsXattrStorage {
+        sefs: Arc::downgrade(sefs),
+        dir: dir.to_path_buf(),
+        storage,
+    }));
+}
+
+struct SefsXattrStorage {
+    sefs: Weak<SEFS>,
+    dir: PathBuf,
+    storage: SgxStorage,
+}
+
+impl SefsXattrStorage {
+    fn of_fs(fs: &Arc<dyn FileSystem>) -> Option<Arc<Self>> {
+        let storages = SEFS_XATTR_STORAGES.lock().unwrap();
+        storages
+            .iter()
+            .find(|storage| {
+                Weak::as_ptr(&storage.sefs) as *const u8 == Arc::as_ptr(fs) as *const u8
+            })
+            .cloned()
+    }
+}
+
+/// The file that saves the xattrs of a SEFS inode. The xattrs are encoded as
+/// a sequence of the length of the name (u32), the name, the length of the
+/// value (u32) and the value.
+///
+/// Only the SEFS inodes have records as their inode numbers are persistent.
+struct XattrRecord {
+    storage: Arc<SefsXattrStorage>,
+    file_id: String,
+}
+
+impl XattrRecord {
+    fn of_inode(inode: &Arc<dyn INode>) -> Result<Option<Self>> {
+        let fs = inode.fs();
+        if fs.info().magic != rcore_fs_sefs::SEFS_MAGIC as usize {
+            return Ok(None);
+        }
+        // The integrity-only SEFS is read-only, which has no records
+        let storage = match SefsXattrStorage::of_fs(&fs) {
+            Some(storage) => storage,
+            None => return Ok(None),
+        };
+        Ok(Some(Self {
+            storage,
+            file_id: format!("{}{}", XATTR_RECORD_PREFIX, inode.metadata()?.inode),
+        }))
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, Vec<u8>>> {
+        if !self.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let file = self
+            .storage
+            .storage
+            .open(&self.file_id)
+            .map_err(|_| errno!(EIO, "failed to open the xattr record"))?;
+        let mut data = Vec::new();
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let len = file
+                .read_at(&mut buf, data.len())
+                .map_err(|_| errno!(EIO, "failed to read the xattr record"))?;
+            if len == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..len]);
+        }
+
+        let mut xattrs = BTreeMap::new();
+        let mut rest = data.as_slice();
+        while !rest.is_empty() {
+            let name = Self::decode_field(&mut rest)?;
+            let name = String::from_utf8(name.to_vec())
+                .map_err(|_| errno!(EINVAL, "invalid xattr name in the record"))?;
+            let value = Self::decode_field(&mut rest)?;
+            xattrs.insert(name, value.to_vec());
+        }
+        Ok(xattrs)
+    }
+
+    /// Save the xattrs to the file, or remove the file if there is no xattr.
+    fn save(&self, xattrs: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+        // A protected file cannot be truncated, so it is written from scratch
+        if self.exists() {
+            self.storage
+                .storage
+                .remove(&self.file_id)
+                .map_err(|_| errno!(EIO, "failed to remove the xattr record"))?;
+        }
+        if xattrs.is_empty() {
+            return Ok(());
+        }
+
+        let mut data = Vec::new();
+        for (name, value) in xattrs {
+            Self::encode_field(&mut data, name.as_bytes());
+            Self::encode_field(&mut data, value);
+        }
+        let file = self
+            .storage
+            .storage
+            .create(&self.file_id)
+            .map_err(|_| errno!(EIO, "failed to create the xattr record"))?;
+        let mut offset = 0;
+        while offset < data.len() {
+            offset += file
+                .write_at(&data[offset..], offset)
+                .map_err(|_| errno!(EIO, "failed to write the xattr record"))?;
+        }
+        file.flush()
+            .map_err(|_| errno!(EIO, "failed to write the xattr record"))?;
+        Ok(())
+    }
+
+    fn exists(&self) -> bool {
+        self.storage.dir.join(&self.file_id).exists()
+    }
+
+    fn encode_field(data: &mut Vec<u8>, field: &[u8]) {
+        data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        data.extend_from_slice(field);
+    }
+
+    fn decode_field<'a>(rest: &mut &'a [u8]) -> Result<&'a [u8]> {
+        let data: &'a [u8] = rest;
+        if data.len() < 4 {
+            return_errno!(EINVAL, "truncated xattr record");
+        }
+        let (len, data) = data.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if data.len() < len {
+            return_errno!(EINVAL, "truncated xattr record");
+        }
+        let (field, data) = data.split_at(len);
+        *rest = data;
+        Ok(field)
+    }
+}
+
+extern "C" {
+    fn occlum_ocall_lgetxattr(
+        ret: *mut isize,
+        path: *const i8,
+        name: *const i8,
+        value: *mut u8,
+        size: usize,
+    ) -> sgx_status_t;
+    fn occlum_ocall_lsetxattr(
+        ret: *mut i32,
+        path: *const i8,
+        name: *const i8,
+        value: *const u8,
+        size: usize,
+        flags: i32,
+    ) -> sgx_status_t;
+    fn occlum_ocall_llistxattr(
+        ret: *mut isize,
+        path: *const i8,
+        list: *mut u8,
+        size: usize,
+    ) -> sgx_status_t;
+    fn occlum_ocall_lremovexattr(ret: *mut i32, path: *const i8, name: *const i8) -> sgx_status_t;
+}
+
+/// Only the xattrs in the namespace of the HostFS are visible to the LibOS.
+fn check_host_namespace(hnode: &HNode, namespace: XattrNamespace) -> Result<()> {
+    if namespace != hnode.xattr_namespace() {
+        return_errno!(
+            EOPNOTSUPP,
+            "the xattr namespace is not allowed by the HostFS"
+        );
+    }
+    Ok(())
+}
+
+/// An empty buffer is passed to the host as a null pointer
+fn buf_ptr_or_null(buf: &[u8]) -> *mut u8 {
+    if buf.is_empty() {
+        std::ptr::null_mut()
+    } else {
+        buf.as_ptr() as *mut u8
+    }
+}
+
+fn host_path_cstring(hnode: &HNode) -> Result<CString> {
+    CString::new(hnode.host_path().to_string_lossy().as_bytes())
+        .map_err(|_| errno!(EINVAL, "invalid host path"))
+}
+
+fn host_getxattr(
+    hnode: &HNode,
+    namespace: XattrNamespace,
+    name: &str,
+    value: &mut [u8],
+) -> Result<usize> {
+    check_host_namespace(hnode, namespace)?;
+    let path = host_path_cstring(hnode)?;
+    let name = CString::new(name).unwrap();
+    let len = try_libc!({
+        let mut retval: isize = 0;
+        let status = occlum_ocall_lgetxattr(
+            &mut retval,
+            path.as_ptr(),
+            name.as_ptr(),
+            buf_ptr_or_null(value),
+            value.len(),
+        );
+        assert!(status == sgx_status_t::SGX_SUCCESS);
+        retval
+    }) as usize;
+    // Do not trust the length returned by the host
+    if len > XATTR_SIZE_MAX || (!value.is_empty() && len > value.len()) {
+        return_errno!(EINVAL, "invalid xattr size from the host");
+    }
+    Ok(len)
+}
+
+fn host_setxattr(
+    hnode: &HNode,
+    namespace: XattrNamespace,
+    name: &str,
+    value: &[u8],
+    flags: XattrFlags,
+) -> Result<()> {
+    check_host_namespace(hnode, namespace)?;
+    let path = host_path_cstring(hnode)?;
+    let name = CString::new(name).unwrap();
+    try_libc!({
+        let mut retval: i32 = 0;
+        let status = occlum_ocall_lsetxattr(
+            &mut retval,
+            path.as_ptr(),
+            name.as_ptr(),
+            buf_ptr_or_null(value) as *const u8,
+            value.len(),
+            flags.bits(),
+        );
+        assert!(status == sgx_status_t::SGX_SUCCESS);
+        retval
+    });
+    Ok(())
+}
+
+/// Get the null-terminated names of the host xattrs in the namespace of the
+/// HostFS.
+fn host_listxattr(hnode: &HNode) -> Result<Vec<u8>> {
+    let path = host_path_cstring(hnode)?;
+    let mut list = vec![0u8; XATTR_LIST_MAX];
+    let len = try_libc!({
+        let mut retval: isize = 0;
+        let status =
+            occlum_ocall_llistxattr(&mut retval, path.as_ptr(), list.as_mut_ptr(), list.len());
+        assert!(status == sgx_status_t::SGX_SUCCESS);
+        retval
+    }) as usize;
+    if len > list.len() {
+        return_errno!(EINVAL, "invalid xattr list size from the host");
+    }
+
+    let prefix = hnode.xattr_namespace().prefix().as_bytes();
+    let mut names = Vec::new();
+    for name in list[..len]
+        .split(|&c| c == 0)
+        .filter(|name| name.starts_with(prefix))
+    {
+        names.extend_from_slice(name);
+        names.push(0);
+    }
+    Ok(names)
+}
+
+fn host_removexattr(hnode: &HNode, namespace: XattrNamespace, name: &str) -> Result<()> {
+    check_host_namespace(hnode, namespace)?;
+    let path = host_path_cstring(hnode)?;
+    let name = CString::new(name).unwrap();
+    try_libc!({
+        let mut retval: i32 = 0;
+        let status = occlum_ocall_lremovexattr(&mut retval, path.as_ptr(), name.as_ptr());
+        assert!(status == sgx_status_t::SGX_SUCCESS);
+        retval
+    });
+    Ok(())
+}
@@ -1,5 +1,6 @@
 /// Present a per-process view of FS.
 use super::*;
+use crate::fs::inotify_file::{self, InotifyMask};
 
 #[derive(Debug, Clone)]
 pub struct FsView {
@@ -79,7 +80,14 @@ impl FsView {
                     if !dir_inode.allow_write()? {
                         return_errno!(EPERM, "file cannot be created");
                     }
-                    dir_inode.create(file_name, FileType::File, mode.bits())?
+                    let inode = dir_inode.create(file_name, FileType::File, mode.bits())?;
+                    inotify_file::notify_dir_entry(
+                        &dir_inode,
+                        file_name,
+                        &inode,
+                        InotifyMask::IN_CREATE,
+                    );
+                    inode
                 }
                 Err(e) => return Err(e),
             }
@@ -115,7 +123,14 @@ impl FsView {
                     if !dir_inode.allow_write()? {
                         return_errno!(EPERM, "file cannot be created");
                     }
-                    dir_inode.create(file_name, FileType::File, mode.bits())?
+                    let inode = dir_inode.create(file_name, FileType::File, mode.bits())?;
+                    inotify_file::notify_dir_entry(
+                        &dir_inode,
+                        file_name,
+                        &inode,
+                        InotifyMask::IN_CREATE,
+                    );
+                    inode
                 }
                 Err(e) => return Err(e),
             }
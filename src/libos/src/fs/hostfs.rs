@@ -1,4 +1,5 @@
 use crate::fs::fs_ops::fetch_host_statfs;
+use crate::fs::XattrNamespace;
 use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use core::any::Any;
@@ -13,6 +14,7 @@ use std::untrusted::path::PathEx;
 /// Untrusted file system at host
 pub struct HostFS {
     path: PathBuf,
+    xattr_namespace: XattrNamespace,
     self_ref: Weak<HostFS>,
 }
 
@@ -47,9 +49,13 @@ impl FileSystem for HostFS {
 
 impl HostFS {
     /// Create a new `HostFS` from host `path`
-    pub fn new(path: impl AsRef<Path>) -> Arc<HostFS> {
+    ///
+    /// Only the extended attributes in `xattr_namespace` of the host files are
+    /// accessible.
+    pub fn new(path: impl AsRef<Path>, xattr_namespace: XattrNamespace) -> Arc<HostFS> {
         HostFS {
             path: path.as_ref().to_path_buf(),
+            xattr_namespace,
             self_ref: Weak::default(),
         }
         .wrap()
@@ -307,6 +313,16 @@ impl HNode {
         Ok(maybe_file)
     }
 
+    /// Returns the path of the file at host.
+    pub fn host_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the namespace of the extended attributes that are accessible.
+    pub fn xattr_namespace(&self) -> XattrNamespace {
+        self.fs.xattr_namespace
+    }
+
     /// Returns `true` if this HNode is for a regular file.
     fn is_file(&self) -> bool {
         self.type_ == FileType::File
@@ -1,5 +1,6 @@
+use super::inotify_file::{self, InotifyMask};
+use super::memfd::{self, FileSeals, InodeSeals};
 use super::*;
-use crate::net::PollEventFlags;
 use crate::process::do_getuid;
 use rcore_fs::vfs::FallocateMode;
 use rcore_fs_sefs::dev::SefsMac;
@@ -32,8 +33,12 @@ impl File for INodeFile {
             let info = self.inode.metadata()?;
             *offset = info.size;
         }
-        let len = self.inode.write_at(*offset, buf)?;
+        let len = self.with_seals(
+            |seals| self.check_seals_for_write(seals, *offset, buf.len()),
+            || Ok(self.inode.write_at(*offset, buf)?),
+        )?;
         *offset += len;
+        self.notify_modified();
         Ok(len)
     }
 
@@ -49,7 +54,11 @@ impl File for INodeFile {
         if !self.access_mode.writable() {
             return_errno!(EBADF, "File not writable");
         }
-        let len = self.inode.write_at(offset, buf)?;
+        let len = self.with_seals(
+            |seals| self.check_seals_for_write(seals, offset, buf.len()),
+            || Ok(self.inode.write_at(offset, buf)?),
+        )?;
+        self.notify_modified();
         Ok(len)
     }
 
@@ -81,17 +90,15 @@ impl File for INodeFile {
             let info = self.inode.metadata()?;
             *offset = info.size;
         }
-        let mut total_len = 0;
-        for buf in bufs {
-            match self.inode.write_at(*offset, buf) {
-                Ok(len) => {
-                    total_len += len;
-                    *offset += len;
-                }
-                Err(_) if total_len != 0 => break,
-                Err(e) => return Err(e.into()),
-            }
-        }
+        let start = *offset;
+        let total_len = self.with_seals(
+            |seals| {
+                self.check_seals_for_write(seals, start, bufs.iter().map(|buf| buf.len()).sum())
+            },
+            || self.write_bufs_at(bufs, start),
+        )?;
+        *offset += total_len;
+        self.notify_modified();
         Ok(total_len)
     }
 
@@ -136,18 +143,13 @@ impl File for INodeFile {
         if !self.access_mode.writable() {
             return_errno!(EBADF, "File not writable");
         }
-        let mut offset = offset;
-        let mut total_len = 0;
-        for buf in bufs {
-            match self.inode.write_at(offset, buf) {
-                Ok(len) => {
-                    total_len += len;
-                    offset += len;
-                }
-                Err(_) if total_len != 0 => break,
-                Err(e) => return Err(e.into()),
-            }
-        }
+        let total_len = self.with_seals(
+            |seals| {
+                self.check_seals_for_write(seals, offset, bufs.iter().map(|buf| buf.len()).sum())
+            },
+            || self.write_bufs_at(bufs, offset),
+        )?;
+        self.notify_modified();
         Ok(total_len)
     }
 
@@ -163,6 +165,7 @@ impl File for INodeFile {
 
     fn set_metadata(&self, metadata: &Metadata) -> Result<()> {
         self.inode.set_metadata(metadata)?;
+        self.notify(InotifyMask::IN_ATTRIB);
         Ok(())
     }
 
@@ -171,7 +174,11 @@ impl File for INodeFile {
             return_errno!(EBADF, "File is not opened for writing");
         }
         let mode = FallocateMode::from(flags);
-        self.inode.fallocate(&mode, offset, len)?;
+        self.with_seals(
+            |seals| self.check_seals_for_fallocate(seals, flags, offset, len),
+            || Ok(self.inode.fallocate(&mode, offset, len)?),
+        )?;
+        self.notify_modified();
         Ok(())
     }
 
@@ -179,7 +186,11 @@ impl File for INodeFile {
         if !self.access_mode.writable() {
             return_errno!(EBADF, "File not writable. Can't set len.");
         }
-        self.inode.resize(len as usize)?;
+        self.with_seals(
+            |seals| self.check_seals_for_resize(seals, len as usize),
+            || Ok(self.inode.resize(len as usize)?),
+        )?;
+        self.notify_modified();
         Ok(())
     }
 
@@ -338,10 +349,158 @@ impl INodeFile {
         })
     }
 
+    /// Get the seals of the file, which are shared by all the files of the inode.
+    pub fn seals(&self) -> Result<FileSeals> {
+        match InodeSeals::of_inode(&self.inode) {
+            Some(seals) => Ok(seals.get()),
+            None => return_errno!(EINVAL, "the file does not support sealing"),
+        }
+    }
+
+    // Only the files created by memfd_create support sealing
+    fn current_seals(&self) -> Option<FileSeals> {
+        InodeSeals::of_inode(&self.inode).map(|seals| seals.get())
+    }
+
+    // Do the operation if the seals allow it. The seals are locked during the
+    // operation, so no seals can be added between the check and the operation.
+    fn with_seals<R>(
+        &self,
+        check_fn: impl FnOnce(FileSeals) -> Result<()>,
+        op_fn: impl FnOnce() -> Result<R>,
+    ) -> Result<R> {
+        match InodeSeals::of_inode(&self.inode) {
+            Some(inode_seals) => {
+                let seals = inode_seals.lock();
+                check_fn(*seals)?;
+                op_fn()
+            }
+            None => op_fn(),
+        }
+    }
+
+    fn write_bufs_at(&self, bufs: &[&[u8]], offset: usize) -> Result<usize> {
+        let mut offset = offset;
+        let mut total_len = 0;
+        for buf in bufs {
+            match self.inode.write_at(offset, buf) {
+                Ok(len) => {
+                    total_len += len;
+                    offset += len;
+                }
+                Err(_) if total_len != 0 => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(total_len)
+    }
+
+    /// Write the memory of a shared mapping back to the file.
+    ///
+    /// The seals are not checked, since the mapping can only be created before
+    /// the file is sealed against writing, and the existing writable mappings
+    /// keep writing to the file on Linux.
+    pub fn write_back_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if !self.access_mode.writable() {
+            return_errno!(EBADF, "File not writable");
+        }
+        let len = self.inode.write_at(offset, buf)?;
+        self.notify_modified();
+        Ok(len)
+    }
+
+    /// Add new seals to the file.
+    ///
+    /// The caller is responsible for checking that there is no writable shared
+    /// mapping of the file when adding `F_SEAL_WRITE`.
+    pub fn add_seals(&self, new_seals: FileSeals) -> Result<()> {
+        let seals = InodeSeals::of_inode(&self.inode)
+            .ok_or_else(|| errno!(EINVAL, "the file does not support sealing"))?;
+        if !self.access_mode.writable() {
+            return_errno!(EPERM, "the file is not writable");
+        }
+        seals.add(new_seals)
+    }
+
+    /// Check whether the file can be mapped as shared and writable.
+    pub fn check_seals_for_shared_mmap(&self) -> Result<()> {
+        if let Some(seals) = self.current_seals() {
+            if seals.intersects(FileSeals::F_SEAL_WRITE | FileSeals::F_SEAL_FUTURE_WRITE) {
+                return_errno!(EPERM, "the file is sealed against writing");
+            }
+        }
+        Ok(())
+    }
+
+    fn check_seals_for_write(&self, seals: FileSeals, offset: usize, len: usize) -> Result<()> {
+        if seals.intersects(FileSeals::F_SEAL_WRITE | FileSeals::F_SEAL_FUTURE_WRITE) {
+            return_errno!(EPERM, "the file is sealed against writing");
+        }
+        if seals.contains(FileSeals::F_SEAL_GROW) && offset + len > self.inode.metadata()?.size {
+            return_errno!(EPERM, "the file is sealed against growing");
+        }
+        Ok(())
+    }
+
+    fn check_seals_for_resize(&self, seals: FileSeals, new_len: usize) -> Result<()> {
+        let len = self.inode.metadata()?.size;
+        if new_len < len && seals.contains(FileSeals::F_SEAL_SHRINK) {
+            return_errno!(EPERM, "the file is sealed against shrinking");
+        }
+        if new_len > len && seals.contains(FileSeals::F_SEAL_GROW) {
+            return_errno!(EPERM, "the file is sealed against growing");
+        }
+        Ok(())
+    }
+
+    fn check_seals_for_fallocate(
+        &self,
+        seals: FileSeals,
+        flags: FallocateFlags,
+        offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        let modifies_data = flags.intersects(
+            FallocateFlags::FALLOC_FL_PUNCH_HOLE
+                | FallocateFlags::FALLOC_FL_COLLAPSE_RANGE
+                | FallocateFlags::FALLOC_FL_ZERO_RANGE
+                | FallocateFlags::FALLOC_FL_INSERT_RANGE,
+        );
+        if modifies_data
+            && seals.intersects(FileSeals::F_SEAL_WRITE | FileSeals::F_SEAL_FUTURE_WRITE)
+        {
+            return_errno!(EPERM, "the file is sealed against writing");
+        }
+        let grows = flags.contains(FallocateFlags::FALLOC_FL_INSERT_RANGE)
+            || (!flags.contains(FallocateFlags::FALLOC_FL_KEEP_SIZE)
+                && offset + len > self.inode.metadata()?.size);
+        if grows && seals.contains(FileSeals::F_SEAL_GROW) {
+            return_errno!(EPERM, "the file is sealed against growing");
+        }
+        let shrinks = flags.contains(FallocateFlags::FALLOC_FL_COLLAPSE_RANGE);
+        if shrinks && seals.contains(FileSeals::F_SEAL_SHRINK) {
+            return_errno!(EPERM, "the file is sealed against shrinking");
+        }
+        Ok(())
+    }
+
     pub fn abs_path(&self) -> &str {
         &self.abs_path
     }
 
+    fn notify_modified(&self) {
+        self.notify(InotifyMask::IN_MODIFY);
+    }
+
+    fn notify(&self, mask: InotifyMask) {
+        // The path of an anonymous memfd is made up, which must not be taken
+        // as a path in the filesystem
+        if memfd::is_memfd_inode(&self.inode) {
+            return;
+        }
+        inotify_file::notify_path(&self.abs_path, &self.inode, mask);
+    }
+
     fn check_advisory_lock_with_access_mode(&self, lock: &RangeLock) -> Result<()> {
         match lock.type_() {
             RangeLockType::F_RDLCK => {
@@ -413,7 +572,14 @@ impl INodeFile {
 
 impl Drop for INodeFile {
     fn drop(&mut self) {
-        self.unlock_flock()
+        self.unlock_flock();
+
+        let mask = if self.access_mode.writable() {
+            InotifyMask::IN_CLOSE_WRITE
+        } else {
+            InotifyMask::IN_CLOSE_NOWRITE
+        };
+        self.notify(mask);
     }
 }
 
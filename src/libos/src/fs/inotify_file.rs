@@ -0,0 +1,586 @@
+//! inotify: monitoring filesystem events.
+//!
+//! Watches are keyed by the identity of the inodes being watched. The VFS
+//! operations in `file_ops` (and `INodeFile`) report events on inodes through
+//! the `notify_*` functions, which queue the events to the inotify instances
+//! whose watches are interested in them.
+use super::*;
+
+use crate::events::{Waiter, WaiterQueue};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Weak;
+
+// The max number of events queued on an inotify instance
+const MAX_QUEUED_EVENTS: usize = 16384;
+// The max number of watches of an inotify instance
+const MAX_USER_WATCHES: usize = 8192;
+
+lazy_static! {
+    /// All the watches in the system, indexed by the inodes being watched
+    static ref WATCHES: RwLock<HashMap<InodeId, Vec<Watch>>> = RwLock::new(HashMap::new());
+}
+
+// The number of watched directories. The parent of an inode needs to be
+// resolved to report an event on it only if some directory is being watched.
+static NUM_WATCHED_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+// The cookie that associates the IN_MOVED_FROM and IN_MOVED_TO events of a rename
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+bitflags! {
+    pub struct InotifyMask: u32 {
+        /// File was accessed
+        const IN_ACCESS = 0x0000_0001;
+        /// File was modified
+        const IN_MODIFY = 0x0000_0002;
+        /// Metadata changed
+        const IN_ATTRIB = 0x0000_0004;
+        /// Writtable file was closed
+        const IN_CLOSE_WRITE = 0x0000_0008;
+        /// Unwrittable file was closed
+        const IN_CLOSE_NOWRITE = 0x0000_0010;
+        /// File was opened
+        const IN_OPEN = 0x0000_0020;
+        /// File was moved from X
+        const IN_MOVED_FROM = 0x0000_0040;
+        /// File was moved to Y
+        const IN_MOVED_TO = 0x0000_0080;
+        /// Subfile was created
+        const IN_CREATE = 0x0000_0100;
+        /// Subfile was deleted
+        const IN_DELETE = 0x0000_0200;
+        /// Self was deleted
+        const IN_DELETE_SELF = 0x0000_0400;
+        /// Self was moved
+        const IN_MOVE_SELF = 0x0000_0800;
+        /// Backing fs was unmounted
+        const IN_UNMOUNT = 0x0000_2000;
+        /// Event queued overflowed
+        const IN_Q_OVERFLOW = 0x0000_4000;
+        /// File was ignored
+        const IN_IGNORED = 0x0000_8000;
+        /// Only watch the path if it is a directory
+        const IN_ONLYDIR = 0x0100_0000;
+        /// Don't follow a symlink
+        const IN_DONT_FOLLOW = 0x0200_0000;
+        /// Exclude events on unlinked objects
+        const IN_EXCL_UNLINK = 0x0400_0000;
+        /// Only create watches
+        const IN_MASK_CREATE = 0x1000_0000;
+        /// Add to the mask of an already existing watch
+        const IN_MASK_ADD = 0x2000_0000;
+        /// Event occurred against dir
+        const IN_ISDIR = 0x4000_0000;
+        /// Only send event once
+        const IN_ONESHOT = 0x8000_0000;
+
+        const IN_ALL_EVENTS = 0x0000_0fff;
+    }
+}
+
+bitflags! {
+    pub struct InotifyFlags: i32 {
+        /// Non-blocking
+        const IN_NONBLOCK = 1 << 11;
+        /// Close on exec
+        const IN_CLOEXEC = 1 << 19;
+    }
+}
+
+/// The identity of an inode: the address of its filesystem and its inode number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct InodeId(usize, usize);
+
+impl InodeId {
+    fn of(inode: &Arc<dyn INode>) -> Result<Self> {
+        let fs_addr = Arc::as_ptr(&inode.fs()) as *const u8 as usize;
+        let ino = inode.metadata()?.inode;
+        Ok(Self(fs_addr, ino))
+    }
+}
+
+#[derive(Debug)]
+struct Watch {
+    wd: i32,
+    mask: InotifyMask,
+    is_dir: bool,
+    instance: Weak<Inotify>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: InotifyMask,
+    cookie: u32,
+    name: Option<String>,
+}
+
+impl InotifyEvent {
+    // The size of the fixed part of `struct inotify_event`
+    const HEADER_SIZE: usize = 16;
+
+    // The name is null-terminated and padded to align the next event
+    fn name_len(&self) -> usize {
+        match self.name.as_ref() {
+            Some(name) => align_up(name.len() + 1, Self::HEADER_SIZE),
+            None => 0,
+        }
+    }
+
+    fn size(&self) -> usize {
+        Self::HEADER_SIZE + self.name_len()
+    }
+
+    fn write_to(&self, buf: &mut [u8]) {
+        let name_len = self.name_len();
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.bits().to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+        let name_buf = &mut buf[Self::HEADER_SIZE..Self::HEADER_SIZE + name_len];
+        for byte in name_buf.iter_mut() {
+            *byte = 0;
+        }
+        if let Some(name) = self.name.as_ref() {
+            name_buf[..name.len()].copy_from_slice(name.as_bytes());
+        }
+    }
+}
+
+/// An inotify instance, which is shared by the file and its watches.
+#[derive(Debug)]
+struct Inotify {
+    inner: SgxMutex<InotifyInner>,
+    readers: WaiterQueue,
+    notifier: IoNotifier,
+}
+
+#[derive(Debug)]
+struct InotifyInner {
+    events: VecDeque<InotifyEvent>,
+    watches: HashMap<i32, InodeId>,
+    next_wd: i32,
+}
+
+impl Inotify {
+    fn new() -> Self {
+        let inner = InotifyInner {
+            events: VecDeque::new(),
+            watches: HashMap::new(),
+            next_wd: 1,
+        };
+        Self {
+            inner: SgxMutex::new(inner),
+            readers: WaiterQueue::new(),
+            notifier: IoNotifier::new(),
+        }
+    }
+
+    fn enqueue_event(&self, event: InotifyEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        // Merge the event with the last one if they are identical
+        if inner.events.back() == Some(&event) {
+            return;
+        }
+        if inner.events.len() >= MAX_QUEUED_EVENTS {
+            let overflow = InotifyEvent {
+                wd: -1,
+                mask: InotifyMask::IN_Q_OVERFLOW,
+                cookie: 0,
+                name: None,
+            };
+            if inner.events.back() != Some(&overflow) {
+                inner.events.push_back(overflow);
+            }
+        } else {
+            inner.events.push_back(event);
+        }
+        drop(inner);
+
+        self.readers.dequeue_and_wake_all();
+        self.notifier.broadcast(&IoEvents::IN);
+    }
+}
+
+/// The file of an inotify instance
+#[derive(Debug)]
+pub struct InotifyFile {
+    inotify: Arc<Inotify>,
+    status_flags: RwLock<StatusFlags>,
+}
+
+impl InotifyFile {
+    pub fn new(flags: InotifyFlags) -> Self {
+        let status_flags = if flags.contains(InotifyFlags::IN_NONBLOCK) {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        };
+        Self {
+            inotify: Arc::new(Inotify::new()),
+            status_flags: RwLock::new(status_flags),
+        }
+    }
+
+    /// Add a watch on the inode, or modify the watch if it already exists.
+    /// Return the watch descriptor.
+    pub fn add_watch(&self, inode: &Arc<dyn INode>, mask: InotifyMask) -> Result<i32> {
+        if mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE) {
+            return_errno!(EINVAL, "IN_MASK_ADD and IN_MASK_CREATE cannot be both set");
+        }
+        if (mask & InotifyMask::IN_ALL_EVENTS).is_empty() {
+            return_errno!(EINVAL, "no valid events in the mask");
+        }
+        let metadata = inode.metadata()?;
+        if mask.contains(InotifyMask::IN_ONLYDIR) && metadata.type_ != FileType::Dir {
+            return_errno!(ENOTDIR, "the path is not a directory");
+        }
+        let inode_id = InodeId::of(inode)?;
+
+        let mut watches = WATCHES.write().unwrap();
+        let mut inner = self.inotify.inner.lock().unwrap();
+        if let Some(inode_watches) = watches.get_mut(&inode_id) {
+            let existing = inode_watches.iter_mut().find(|watch| {
+                watch
+                    .instance
+                    .upgrade()
+                    .map_or(false, |instance| Arc::ptr_eq(&instance, &self.inotify))
+            });
+            if let Some(watch) = existing {
+                if mask.contains(InotifyMask::IN_MASK_CREATE) {
+                    return_errno!(EEXIST, "the inode is already being watched");
+                }
+                if mask.contains(InotifyMask::IN_MASK_ADD) {
+                    watch.mask |= mask;
+                } else {
+                    watch.mask = mask;
+                }
+                return Ok(watch.wd);
+            }
+        }
+
+        if inner.watches.len() >= MAX_USER_WATCHES {
+            return_errno!(ENOSPC, "too many watches");
+        }
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.insert(wd, inode_id);
+        let is_dir = metadata.type_ == FileType::Dir;
+        let inode_watches = watches.entry(inode_id).or_insert_with(|| {
+            if is_dir {
+                NUM_WATCHED_DIRS.fetch_add(1, Ordering::Relaxed);
+            }
+            Vec::new()
+        });
+        inode_watches.push(Watch {
+            wd,
+            mask,
+            is_dir,
+            instance: Arc::downgrade(&self.inotify),
+        });
+        Ok(wd)
+    }
+
+    /// Remove a watch, which generates an IN_IGNORED event.
+    pub fn rm_watch(&self, wd: i32) -> Result<()> {
+        let inode_id = self
+            .inotify
+            .inner
+            .lock()
+            .unwrap()
+            .watches
+            .remove(&wd)
+            .ok_or_else(|| errno!(EINVAL, "invalid watch descriptor"))?;
+        remove_watch_from_inode(&mut WATCHES.write().unwrap(), inode_id, wd, &self.inotify);
+        self.inotify.enqueue_event(InotifyEvent {
+            wd,
+            mask: InotifyMask::IN_IGNORED,
+            cookie: 0,
+            name: None,
+        });
+        Ok(())
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status_flags
+            .read()
+            .unwrap()
+            .contains(StatusFlags::O_NONBLOCK)
+    }
+}
+
+impl File for InotifyFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inotify.inner.lock().unwrap();
+            if let Some(first) = inner.events.front() {
+                if first.size() > buf.len() {
+                    return_errno!(EINVAL, "the buffer is too small for the next event");
+                }
+                let mut len = 0;
+                while let Some(event) = inner.events.front() {
+                    let size = event.size();
+                    if len + size > buf.len() {
+                        break;
+                    }
+                    event.write_to(&mut buf[len..len + size]);
+                    len += size;
+                    inner.events.pop_front();
+                }
+                return Ok(len);
+            }
+            if self.is_nonblocking() {
+                return_errno!(EAGAIN, "no inotify events");
+            }
+
+            self.inotify.readers.reset_and_enqueue(&waiter);
+            // Ensure that we drop any locks before wait
+            drop(inner);
+            waiter.wait(None)?;
+        }
+    }
+
+    fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        match cmd {
+            IoctlCmd::FIONREAD(arg) => {
+                let inner = self.inotify.inner.lock().unwrap();
+                let nbytes: usize = inner.events.iter().map(|event| event.size()).sum();
+                **arg = nbytes as i32;
+                Ok(0)
+            }
+            _ => return_errno!(EINVAL, "unsupported ioctl for inotify"),
+        }
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDONLY)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(self.status_flags.read().unwrap().clone())
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        let mut status_flags = self.status_flags.write().unwrap();
+        status_flags.remove(STATUS_FLAGS_MASK);
+        status_flags.insert(new_status_flags & STATUS_FLAGS_MASK);
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        if self.inotify.inner.lock().unwrap().events.is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        }
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(&self.inotify.notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Drop for InotifyFile {
+    fn drop(&mut self) {
+        let mut watches = WATCHES.write().unwrap();
+        let inner = self.inotify.inner.lock().unwrap();
+        for (wd, inode_id) in inner.watches.iter() {
+            remove_watch_from_inode(&mut watches, *inode_id, *wd, &self.inotify);
+        }
+    }
+}
+
+pub trait AsInotify {
+    fn as_inotify(&self) -> Result<&InotifyFile>;
+}
+
+impl AsInotify for FileRef {
+    fn as_inotify(&self) -> Result<&InotifyFile> {
+        self.as_any()
+            .downcast_ref::<InotifyFile>()
+            .ok_or_else(|| errno!(EINVAL, "not an inotify file"))
+    }
+}
+
+fn remove_watch_from_inode(
+    watches: &mut HashMap<InodeId, Vec<Watch>>,
+    inode_id: InodeId,
+    wd: i32,
+    instance: &Arc<Inotify>,
+) {
+    if let Some(inode_watches) = watches.get_mut(&inode_id) {
+        let is_dir = inode_watches.first().map_or(false, |watch| watch.is_dir);
+        inode_watches.retain(|watch| {
+            watch.wd != wd || !Weak::ptr_eq(&watch.instance, &Arc::downgrade(instance))
+        });
+        if inode_watches.is_empty() {
+            remove_inode_watches(watches, inode_id, is_dir);
+        }
+    }
+}
+
+fn remove_inode_watches(
+    watches: &mut HashMap<InodeId, Vec<Watch>>,
+    inode_id: InodeId,
+    is_dir: bool,
+) {
+    watches.remove(&inode_id);
+    if is_dir {
+        NUM_WATCHED_DIRS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Queue an event to all the watches on the inode that are interested in it.
+fn notify(inode: &Arc<dyn INode>, mask: InotifyMask, cookie: u32, name: Option<&str>) {
+    // Fast path: nobody is watching
+    if WATCHES.read().unwrap().is_empty() {
+        return;
+    }
+    let inode_id = match InodeId::of(inode) {
+        Ok(inode_id) => inode_id,
+        Err(_) => return,
+    };
+    let is_self_deleted = mask.contains(InotifyMask::IN_DELETE_SELF);
+
+    // The instances to receive the event: (instance, wd, is_interested, is_removed)
+    let mut receivers = Vec::new();
+    {
+        let mut watches = WATCHES.write().unwrap();
+        let inode_watches = match watches.get_mut(&inode_id) {
+            Some(inode_watches) => inode_watches,
+            None => return,
+        };
+        let is_dir = inode_watches.first().map_or(false, |watch| watch.is_dir);
+        inode_watches.retain(|watch| {
+            let instance = match watch.instance.upgrade() {
+                Some(instance) => instance,
+                None => return false,
+            };
+            let is_interested = watch.mask.intersects(mask & InotifyMask::IN_ALL_EVENTS);
+            // A watch is removed once its inode is deleted or its one-shot event is sent
+            let is_removed =
+                is_self_deleted || (is_interested && watch.mask.contains(InotifyMask::IN_ONESHOT));
+            if is_removed {
+                instance.inner.lock().unwrap().watches.remove(&watch.wd);
+            }
+            if is_interested || is_removed {
+                receivers.push((instance, watch.wd, is_interested, is_removed));
+            }
+            !is_removed
+        });
+        if inode_watches.is_empty() {
+            remove_inode_watches(&mut watches, inode_id, is_dir);
+        }
+    }
+
+    for (instance, wd, is_interested, is_removed) in receivers {
+        if is_interested {
+            instance.enqueue_event(InotifyEvent {
+                wd,
+                mask: mask & (InotifyMask::IN_ALL_EVENTS | InotifyMask::IN_ISDIR),
+                cookie,
+                name: name.map(|name| name.to_owned()),
+            });
+        }
+        if is_removed {
+            instance.enqueue_event(InotifyEvent {
+                wd,
+                mask: InotifyMask::IN_IGNORED,
+                cookie: 0,
+                name: None,
+            });
+        }
+    }
+}
+
+fn isdir_mask(inode: &Arc<dyn INode>) -> InotifyMask {
+    match inode.metadata() {
+        Ok(metadata) if metadata.type_ == FileType::Dir => InotifyMask::IN_ISDIR,
+        _ => InotifyMask::empty(),
+    }
+}
+
+/// Report an event on the inode itself, e.g., IN_DELETE_SELF.
+pub fn notify_inode(inode: &Arc<dyn INode>, mask: InotifyMask) {
+    notify(inode, mask | isdir_mask(inode), 0, None);
+}
+
+/// Report an event on an entry of the directory, e.g., IN_CREATE.
+pub fn notify_dir_entry(
+    dir_inode: &Arc<dyn INode>,
+    name: &str,
+    inode: &Arc<dyn INode>,
+    mask: InotifyMask,
+) {
+    notify(dir_inode, mask | isdir_mask(inode), 0, Some(name));
+}
+
+/// Report an event on the inode at the absolute path, which is reported on
+/// both the inode and its parent directory, e.g., IN_MODIFY.
+pub fn notify_path(abs_path: &str, inode: &Arc<dyn INode>, mask: InotifyMask) {
+    if WATCHES.read().unwrap().is_empty() {
+        return;
+    }
+    notify_inode(inode, mask);
+
+    // Looking up the parent directory by the path is expensive, which is
+    // only worthwhile when there is a directory being watched.
+    if NUM_WATCHED_DIRS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    let (dir_path, file_name) = split_path(abs_path.trim_end_matches('/'));
+    let dir_inode = ROOT_FS
+        .read()
+        .unwrap()
+        .root_inode()
+        .lookup_follow(dir_path.trim_start_matches('/'), MAX_SYMLINKS);
+    if let Ok(dir_inode) = dir_inode {
+        notify_dir_entry(&dir_inode, file_name, inode, mask);
+    }
+}
+
+/// Report that a link to the inode was removed, which deletes the inode
+/// if it was the last link.
+pub fn notify_link_removed(inode: &Arc<dyn INode>) {
+    match inode.metadata() {
+        Ok(metadata) if metadata.type_ != FileType::Dir && metadata.nlinks > 0 => {
+            notify_inode(inode, InotifyMask::IN_ATTRIB)
+        }
+        _ => notify_inode(inode, InotifyMask::IN_DELETE_SELF),
+    }
+}
+
+/// Report the rename of an inode from one directory to another.
+pub fn notify_move(
+    old_dir_inode: &Arc<dyn INode>,
+    old_name: &str,
+    new_dir_inode: &Arc<dyn INode>,
+    new_name: &str,
+    inode: &Arc<dyn INode>,
+) {
+    if WATCHES.read().unwrap().is_empty() {
+        return;
+    }
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    let isdir = isdir_mask(inode);
+    notify(
+        old_dir_inode,
+        InotifyMask::IN_MOVED_FROM | isdir,
+        cookie,
+        Some(old_name),
+    );
+    notify(
+        new_dir_inode,
+        InotifyMask::IN_MOVED_TO | isdir,
+        cookie,
+        Some(new_name),
+    );
+    notify_inode(inode, InotifyMask::IN_MOVE_SELF);
+}
@@ -0,0 +1,203 @@
+//! The data structures and constants shared with the user space, which are
+//! defined in <linux/io_uring.h>.
+
+use super::*;
+
+/// The parameters of io_uring_setup, i.e., struct io_uring_params.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_uring_params {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: io_sqring_offsets,
+    pub cq_off: io_cqring_offsets,
+}
+
+/// The offsets of the fields in the mapped submission queue ring.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_sqring_offsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub resv2: u64,
+}
+
+/// The offsets of the fields in the mapped completion queue ring.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_cqring_offsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub resv2: u64,
+}
+
+/// A submission queue entry, i.e., struct io_uring_sqe.
+///
+/// The unions of the C struct are flattened into the fields of their most
+/// common names.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct io_uring_sqe {
+    pub opcode: u8,
+    pub flags: u8,
+    pub ioprio: u16,
+    pub fd: i32,
+    /// The file offset, or addr2 of accept
+    pub off: u64,
+    pub addr: u64,
+    pub len: u32,
+    /// The flags of the operation, e.g., rw_flags, poll_events or msg_flags
+    pub op_flags: u32,
+    pub user_data: u64,
+    pub buf_index: u16,
+    pub personality: u16,
+    pub splice_fd_in: i32,
+    pub pad: [u64; 2],
+}
+
+/// A completion queue entry, i.e., struct io_uring_cqe.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_uring_cqe {
+    pub user_data: u64,
+    pub res: i32,
+    pub flags: u32,
+}
+
+/// The header of the result of IORING_REGISTER_PROBE, i.e., struct
+/// io_uring_probe, which is followed by an array of io_uring_probe_op.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_uring_probe {
+    pub last_op: u8,
+    pub ops_len: u8,
+    pub resv: u16,
+    pub resv2: [u32; 3],
+}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct io_uring_probe_op {
+    pub op: u8,
+    pub resv: u8,
+    pub flags: u16,
+    pub resv2: u32,
+}
+
+/// The operation is supported, which is set in io_uring_probe_op.flags
+pub const IO_URING_OP_SUPPORTED: u16 = 1 << 0;
+
+/// The offsets given to mmap to map the rings
+pub const IORING_OFF_SQ_RING: usize = 0;
+pub const IORING_OFF_CQ_RING: usize = 0x8000000;
+pub const IORING_OFF_SQES: usize = 0x10000000;
+
+bitflags! {
+    pub struct SetupFlags: u32 {
+        const IORING_SETUP_IOPOLL       = 1 << 0;
+        const IORING_SETUP_SQPOLL       = 1 << 1;
+        const IORING_SETUP_SQ_AFF       = 1 << 2;
+        const IORING_SETUP_CQSIZE       = 1 << 3;
+        const IORING_SETUP_CLAMP        = 1 << 4;
+        const IORING_SETUP_ATTACH_WQ    = 1 << 5;
+        const IORING_SETUP_R_DISABLED   = 1 << 6;
+    }
+}
+
+bitflags! {
+    pub struct Features: u32 {
+        const IORING_FEAT_SINGLE_MMAP       = 1 << 0;
+        const IORING_FEAT_NODROP            = 1 << 1;
+        const IORING_FEAT_SUBMIT_STABLE     = 1 << 2;
+        const IORING_FEAT_RW_CUR_POS        = 1 << 3;
+        const IORING_FEAT_CUR_PERSONALITY   = 1 << 4;
+        const IORING_FEAT_FAST_POLL         = 1 << 5;
+        const IORING_FEAT_POLL_32BITS       = 1 << 6;
+    }
+}
+
+bitflags! {
+    pub struct EnterFlags: u32 {
+        const IORING_ENTER_GETEVENTS    = 1 << 0;
+        const IORING_ENTER_SQ_WAKEUP    = 1 << 1;
+        const IORING_ENTER_SQ_WAIT      = 1 << 2;
+        const IORING_ENTER_EXT_ARG      = 1 << 3;
+    }
+}
+
+bitflags! {
+    /// The flags of the submission queue ring
+    pub struct SqRingFlags: u32 {
+        const IORING_SQ_NEED_WAKEUP = 1 << 0;
+        /// The completion queue ring has overflowed
+        const IORING_SQ_CQ_OVERFLOW = 1 << 1;
+    }
+}
+
+bitflags! {
+    /// The flags of a submission queue entry
+    pub struct SqeFlags: u8 {
+        const IOSQE_FIXED_FILE      = 1 << 0;
+        const IOSQE_IO_DRAIN        = 1 << 1;
+        const IOSQE_IO_LINK         = 1 << 2;
+        const IOSQE_IO_HARDLINK     = 1 << 3;
+        const IOSQE_ASYNC           = 1 << 4;
+        const IOSQE_BUFFER_SELECT   = 1 << 5;
+    }
+}
+
+/// The fsync_flags of IORING_OP_FSYNC
+pub const IORING_FSYNC_DATASYNC: u32 = 1 << 0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterOp {
+    RegisterBuffers,
+    UnregisterBuffers,
+    RegisterFiles,
+    UnregisterFiles,
+    RegisterEventfd,
+    UnregisterEventfd,
+    RegisterEventfdAsync,
+    RegisterProbe,
+}
+
+impl RegisterOp {
+    pub fn from_raw(raw: u32) -> Result<Self> {
+        Ok(match raw {
+            0 => RegisterOp::RegisterBuffers,
+            1 => RegisterOp::UnregisterBuffers,
+            2 => RegisterOp::RegisterFiles,
+            3 => RegisterOp::UnregisterFiles,
+            4 => RegisterOp::RegisterEventfd,
+            5 => RegisterOp::UnregisterEventfd,
+            7 => RegisterOp::RegisterEventfdAsync,
+            8 => RegisterOp::RegisterProbe,
+            _ => return_errno!(EINVAL, "unsupported io_uring register opcode"),
+        })
+    }
+}
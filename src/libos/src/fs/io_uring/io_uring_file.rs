@@ -0,0 +1,444 @@
+use super::abi::*;
+use super::op::{is_pollable, Request};
+use super::ring::Rings;
+use super::*;
+use crate::net::EventMonitorBuilder;
+use crate::process::Process;
+use crate::vm::MMapFlags;
+use std::sync::Weak;
+
+/// The maximum number of registered files
+const IORING_MAX_FIXED_FILES: usize = 1 << 15;
+/// The maximum number and size of registered buffers
+const IORING_MAX_REG_BUFFERS: usize = 1 << 14;
+const IORING_MAX_BUF_SIZE: u64 = 1 << 30;
+
+/// An io_uring instance.
+///
+/// The rings are mapped into the process that creates the instance, so the
+/// instance can only be used by that process.
+pub struct IoUringFile {
+    rings: Rings,
+    owner: Weak<Process>,
+    /// Serialize the submissions
+    sq_lock: SgxMutex<()>,
+    /// The completions that do not fit into the completion queue ring
+    /// (IORING_FEAT_NODROP). The lock also serializes the completions.
+    cq_overflow: SgxMutex<VecDeque<io_uring_cqe>>,
+    /// The requests waiting for their files to be ready
+    pending: SgxMutex<Vec<Request>>,
+    registered: RwLock<Registered>,
+    notifier: IoNotifier,
+}
+
+#[derive(Debug, Default)]
+struct Registered {
+    files: Option<Vec<Option<FileRef>>>,
+    bufs: Option<Vec<UserBuf>>,
+    /// The eventfd and whether it is only notified of asynchronous completions
+    eventfd: Option<(FileRef, bool)>,
+}
+
+impl IoUringFile {
+    pub fn new(sq_entries: u32, cq_entries: u32) -> Result<Self> {
+        let rings = Rings::new(sq_entries, cq_entries)?;
+        Ok(Self {
+            rings,
+            owner: Arc::downgrade(current!().process()),
+            sq_lock: SgxMutex::new(()),
+            cq_overflow: SgxMutex::new(VecDeque::new()),
+            pending: SgxMutex::new(Vec::new()),
+            registered: RwLock::new(Registered::default()),
+            notifier: IoNotifier::new(),
+        })
+    }
+
+    /// Check that the current process is the one that the rings are mapped
+    /// into.
+    pub fn check_owner(&self) -> Result<()> {
+        let is_owner = self
+            .owner
+            .upgrade()
+            .map_or(false, |owner| Arc::ptr_eq(&owner, current!().process()));
+        if !is_owner {
+            return_errno!(EOPNOTSUPP, "io_uring cannot be shared between processes");
+        }
+        Ok(())
+    }
+
+    /// Map the rings given by the offset.
+    ///
+    /// The rings have been mapped by io_uring_setup, so their addresses are
+    /// returned and the mappings are shared by all the mmap calls.
+    pub fn mmap(&self, addr: usize, size: usize, flags: MMapFlags, offset: usize) -> Result<usize> {
+        self.check_owner()?;
+        let (region_addr, region_size) = self
+            .rings
+            .region(offset)
+            .ok_or_else(|| errno!(EINVAL, "invalid io_uring mmap offset"))?;
+        if size > region_size {
+            return_errno!(EINVAL, "the size is larger than the ring");
+        }
+        if flags.contains(MMapFlags::MAP_FIXED) && addr != region_addr {
+            return_errno!(EINVAL, "the ring cannot be mapped at a fixed address");
+        }
+        Ok(region_addr)
+    }
+
+    /// Consume at most `to_submit` entries of the submission queue ring.
+    /// Return the number of entries consumed.
+    pub fn submit(&self, to_submit: u32) -> usize {
+        let count = {
+            let _sq_guard = self.sq_lock.lock().unwrap();
+            let mut head = self.rings.sq_head();
+            let tail = self.rings.sq_tail();
+            let available = tail.wrapping_sub(head).min(self.rings.sq_entries());
+            let mut count = 0;
+            for _ in 0..available.min(to_submit) {
+                let sqe = self.rings.sqe(head);
+                head = head.wrapping_add(1);
+                match sqe {
+                    Some(sqe) => {
+                        self.submit_sqe(&sqe);
+                        count += 1;
+                    }
+                    None => self.rings.inc_sq_dropped(),
+                }
+            }
+            self.rings.set_sq_head(head);
+            count
+        };
+        if count > 0 {
+            self.refresh_host_files();
+            self.run_pending(false);
+            self.notifier.broadcast(&IoEvents::OUT);
+        }
+        count
+    }
+
+    fn submit_sqe(&self, sqe: &io_uring_sqe) {
+        let request = match Request::new(sqe, self) {
+            Ok(request) => request,
+            Err(e) => return self.post_cqe(sqe.user_data, -(e.errno() as i32), false),
+        };
+        if let Some((target, poll_only)) = request.cancel_target() {
+            let res = match self.cancel(target, poll_only) {
+                Ok(()) => 0,
+                Err(e) => -(e.errno() as i32),
+            };
+            return self.post_cqe(request.user_data(), res, false);
+        }
+
+        // The requests on pollable files are run after the events of the host
+        // files are refreshed
+        let is_deferred = match request.awaited_events() {
+            Some((file, _)) => is_pollable(file) || request.is_poll(),
+            None => false,
+        };
+        if !is_deferred {
+            if let Some(res) = request.try_run() {
+                return self.post_cqe(request.user_data(), res, false);
+            }
+        }
+        self.pending.lock().unwrap().push(request);
+    }
+
+    fn cancel(&self, target: u64, poll_only: bool) -> Result<()> {
+        let request = {
+            let mut pending = self.pending.lock().unwrap();
+            let idx = pending
+                .iter()
+                .position(|request| {
+                    request.user_data() == target && (!poll_only || request.is_poll())
+                })
+                .ok_or_else(|| errno!(ENOENT, "the request is not found"))?;
+            pending.remove(idx)
+        };
+        self.post_cqe(request.user_data(), -(Errno::ECANCELED as i32), false);
+        Ok(())
+    }
+
+    /// Update the events of the host files of the pending requests, which are
+    /// not notified by the host.
+    fn refresh_host_files(&self) {
+        let pending = self.pending.lock().unwrap();
+        let mut builder = EventMonitorBuilder::new(pending.len());
+        let mut has_host_files = false;
+        for (file, events) in pending
+            .iter()
+            .filter_map(|request| request.awaited_events())
+        {
+            if file.host_fd().is_some() {
+                builder.add_file(file.clone(), events);
+                has_host_files = true;
+            }
+        }
+        if has_host_files {
+            // Building the monitor polls the host files
+            builder.build();
+        }
+    }
+
+    /// Run the pending requests whose files are ready.
+    fn run_pending(&self, is_async: bool) {
+        let mut completed = Vec::new();
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|request| match request.try_run() {
+                Some(res) => {
+                    completed.push((request.user_data(), res));
+                    false
+                }
+                None => true,
+            });
+        for (user_data, res) in completed {
+            self.post_cqe(user_data, res, is_async);
+        }
+    }
+
+    /// Wait until there are at least `min_complete` completions.
+    ///
+    /// The pending requests are run by the waiting thread, so the files of the
+    /// requests are monitored as well as the io_uring file itself, which is
+    /// notified of the completions by other threads.
+    pub fn wait_cqes(&self, min_complete: usize, uring_file: &FileRef) -> Result<()> {
+        loop {
+            let mut monitor = {
+                let pending = self.pending.lock().unwrap();
+                let mut builder = EventMonitorBuilder::new(pending.len() + 1);
+                builder.add_file(uring_file.clone(), IoEvents::IN);
+                for (file, events) in pending
+                    .iter()
+                    .filter_map(|request| request.awaited_events())
+                {
+                    builder.add_file(file.clone(), events);
+                }
+                builder.build()
+            };
+
+            monitor.reset_events();
+            self.run_pending(true);
+            if self.num_cqes() >= min_complete {
+                return Ok(());
+            }
+            monitor.wait_events(None)?;
+        }
+    }
+
+    /// Deliver a completion to the completion queue ring, or to the overflow
+    /// list if the ring is full.
+    fn post_cqe(&self, user_data: u64, res: i32, is_async: bool) {
+        let cqe = io_uring_cqe {
+            user_data,
+            res,
+            flags: 0,
+        };
+        {
+            let mut overflow = self.cq_overflow.lock().unwrap();
+            self.flush_overflow(&mut overflow);
+            if overflow.is_empty() && self.cq_has_space() {
+                self.rings.push_cqe(&cqe);
+            } else {
+                overflow.push_back(cqe);
+                self.rings.set_sq_flags(SqRingFlags::IORING_SQ_CQ_OVERFLOW);
+            }
+        }
+        self.notifier.broadcast(&IoEvents::IN);
+
+        if let Some((eventfd, async_only)) = &self.registered.read().unwrap().eventfd {
+            if is_async || !async_only {
+                // It is fine to fail when the counter would overflow
+                let _ = eventfd.write(&1u64.to_ne_bytes());
+            }
+        }
+    }
+
+    fn flush_overflow(&self, overflow: &mut VecDeque<io_uring_cqe>) {
+        while !overflow.is_empty() && self.cq_has_space() {
+            self.rings.push_cqe(&overflow.pop_front().unwrap());
+        }
+        if overflow.is_empty() {
+            self.rings
+                .clear_sq_flags(SqRingFlags::IORING_SQ_CQ_OVERFLOW);
+        }
+    }
+
+    fn cq_has_space(&self) -> bool {
+        let len = self.rings.cq_tail().wrapping_sub(self.rings.cq_head());
+        len < self.rings.cq_entries()
+    }
+
+    /// The number of completions, including those in the overflow list.
+    fn num_cqes(&self) -> usize {
+        let mut overflow = self.cq_overflow.lock().unwrap();
+        self.flush_overflow(&mut overflow);
+        let len = self.rings.cq_tail().wrapping_sub(self.rings.cq_head());
+        len as usize + overflow.len()
+    }
+
+    pub fn fixed_file(&self, idx: i32) -> Result<FileRef> {
+        self.registered
+            .read()
+            .unwrap()
+            .files
+            .as_ref()
+            .and_then(|files| files.get(idx as usize))
+            .cloned()
+            .flatten()
+            .ok_or_else(|| errno!(EBADF, "invalid fixed file"))
+    }
+
+    /// Check that a buffer is inside the registered buffer given by the index.
+    pub fn check_fixed_buf(&self, idx: u16, addr: u64, len: u32) -> Result<()> {
+        let registered = self.registered.read().unwrap();
+        let buf = registered
+            .bufs
+            .as_ref()
+            .and_then(|bufs| bufs.get(idx as usize))
+            .ok_or_else(|| errno!(EFAULT, "invalid fixed buffer"))?;
+        let (start, end) = (addr as usize, addr as usize + len as usize);
+        if start < buf.addr() || end > buf.addr() + buf.len() {
+            return_errno!(EFAULT, "the buffer is outside the fixed buffer");
+        }
+        Ok(())
+    }
+
+    pub fn register_files(&self, fds: &[i32]) -> Result<()> {
+        if fds.len() > IORING_MAX_FIXED_FILES {
+            return_errno!(EINVAL, "too many files");
+        }
+        let current = current!();
+        let mut files = Vec::with_capacity(fds.len());
+        for &fd in fds {
+            // A negative fd leaves the slot empty
+            if fd < 0 {
+                files.push(None);
+                continue;
+            }
+            let file = current.file(fd as FileDesc)?;
+            // Registering an io_uring file would create a reference cycle
+            if file.as_io_uring().is_ok() {
+                return_errno!(EBADF, "an io_uring file cannot be registered");
+            }
+            files.push(Some(file));
+        }
+
+        let mut registered = self.registered.write().unwrap();
+        if registered.files.is_some() {
+            return_errno!(EBUSY, "the files have been registered");
+        }
+        registered.files = Some(files);
+        Ok(())
+    }
+
+    pub fn unregister_files(&self) -> Result<()> {
+        self.registered
+            .write()
+            .unwrap()
+            .files
+            .take()
+            .map(|_| ())
+            .ok_or_else(|| errno!(ENXIO, "no files are registered"))
+    }
+
+    pub fn register_bufs(&self, iovecs: &[libc::iovec]) -> Result<()> {
+        if iovecs.len() > IORING_MAX_REG_BUFFERS {
+            return_errno!(EINVAL, "too many buffers");
+        }
+        let mut bufs = Vec::with_capacity(iovecs.len());
+        for iovec in iovecs {
+            let len = iovec.iov_len as u64;
+            if iovec.iov_base.is_null() || len == 0 || len > IORING_MAX_BUF_SIZE {
+                return_errno!(EFAULT, "invalid buffer");
+            }
+            bufs.push(UserBuf::new(iovec.iov_base as u64, len, true)?);
+        }
+
+        let mut registered = self.registered.write().unwrap();
+        if registered.bufs.is_some() {
+            return_errno!(EBUSY, "the buffers have been registered");
+        }
+        registered.bufs = Some(bufs);
+        Ok(())
+    }
+
+    pub fn unregister_bufs(&self) -> Result<()> {
+        self.registered
+            .write()
+            .unwrap()
+            .bufs
+            .take()
+            .map(|_| ())
+            .ok_or_else(|| errno!(ENXIO, "no buffers are registered"))
+    }
+
+    pub fn register_eventfd(&self, eventfd: FileRef, async_only: bool) -> Result<()> {
+        eventfd
+            .as_event()
+            .map_err(|_| errno!(EINVAL, "not an eventfd"))?;
+        let mut registered = self.registered.write().unwrap();
+        if registered.eventfd.is_some() {
+            return_errno!(EBUSY, "an eventfd has been registered");
+        }
+        registered.eventfd = Some((eventfd, async_only));
+        Ok(())
+    }
+
+    pub fn unregister_eventfd(&self) -> Result<()> {
+        self.registered
+            .write()
+            .unwrap()
+            .eventfd
+            .take()
+            .map(|_| ())
+            .ok_or_else(|| errno!(ENXIO, "no eventfd is registered"))
+    }
+}
+
+impl File for IoUringFile {
+    fn poll_new(&self) -> IoEvents {
+        let mut events = IoEvents::empty();
+        // The rings can only be read by the owner process
+        if self.check_owner().is_err() {
+            return events;
+        }
+        if self.num_cqes() > 0 {
+            events |= IoEvents::IN;
+        }
+        let sq_len = self.rings.sq_tail().wrapping_sub(self.rings.sq_head());
+        if sq_len < self.rings.sq_entries() {
+            events |= IoEvents::OUT;
+        }
+        events
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(&self.notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Debug for IoUringFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("IoUringFile")
+            .field("rings", &self.rings)
+            .field("num_pending", &self.pending.lock().unwrap().len())
+            .finish()
+    }
+}
+
+pub trait AsIoUring {
+    fn as_io_uring(&self) -> Result<&IoUringFile>;
+}
+
+impl AsIoUring for FileRef {
+    fn as_io_uring(&self) -> Result<&IoUringFile> {
+        self.as_any()
+            .downcast_ref::<IoUringFile>()
+            .ok_or_else(|| errno!(EBADF, "not an io_uring file"))
+    }
+}
@@ -0,0 +1,197 @@
+//! An emulation of io_uring, i.e., io_uring_setup(2) and its friends.
+//!
+//! The submission and completion queue rings are anonymous mappings of the
+//! process created by io_uring_setup. Mapping the io_uring file with the
+//! offsets of the rings returns the existing mappings, so both the user and the
+//! LibOS access the rings directly, without exiting the enclave.
+//!
+//! The submitted requests are executed by io_uring_enter through the `File`
+//! trait. A request that would block, e.g., reading an empty pipe, is kept
+//! pending until its file is ready. Unlike the AIO requests, which are executed
+//! by the AIO worker threads, the pending requests are not handed over to any
+//! other thread, so they make progress only in io_uring_enter, typically while
+//! waiting for completions with IORING_ENTER_GETEVENTS.
+
+use super::*;
+
+pub use self::abi::io_uring_params;
+pub use self::io_uring_file::{AsIoUring, IoUringFile};
+
+mod abi;
+mod io_uring_file;
+mod op;
+mod ring;
+
+use self::abi::*;
+use self::op::{Opcode, IORING_OP_LAST};
+use self::ring::Rings;
+use crate::signal::{do_with_sig_mask, SigSet};
+use std::mem::size_of;
+use util::mem_util::from_user;
+
+/// The maximum number of submission queue entries
+const IORING_MAX_ENTRIES: u32 = 32768;
+/// The maximum number of completion queue entries
+const IORING_MAX_CQ_ENTRIES: u32 = 2 * IORING_MAX_ENTRIES;
+
+pub fn do_io_uring_setup(entries: u32, params: &mut io_uring_params) -> Result<IoUringFile> {
+    let flags = SetupFlags::from_bits(params.flags)
+        .ok_or_else(|| errno!(EINVAL, "invalid io_uring setup flags"))?;
+    if !(flags - SetupFlags::IORING_SETUP_CQSIZE - SetupFlags::IORING_SETUP_CLAMP).is_empty() {
+        return_errno!(EINVAL, "unsupported io_uring setup flags");
+    }
+    if params.resv.iter().any(|&resv| resv != 0) {
+        return_errno!(EINVAL, "the reserved fields must be zero");
+    }
+
+    let clamp = flags.contains(SetupFlags::IORING_SETUP_CLAMP);
+    if entries == 0 {
+        return_errno!(EINVAL, "the number of entries must be positive");
+    }
+    if entries > IORING_MAX_ENTRIES && !clamp {
+        return_errno!(EINVAL, "too many entries");
+    }
+    let sq_entries = entries.min(IORING_MAX_ENTRIES).next_power_of_two();
+    let cq_entries = if flags.contains(SetupFlags::IORING_SETUP_CQSIZE) {
+        if params.cq_entries == 0 {
+            return_errno!(EINVAL, "the number of completion entries must be positive");
+        }
+        if params.cq_entries > IORING_MAX_CQ_ENTRIES && !clamp {
+            return_errno!(EINVAL, "too many completion entries");
+        }
+        let cq_entries = params
+            .cq_entries
+            .min(IORING_MAX_CQ_ENTRIES)
+            .next_power_of_two();
+        if cq_entries < sq_entries {
+            return_errno!(EINVAL, "fewer completion entries than submission entries");
+        }
+        cq_entries
+    } else {
+        2 * sq_entries
+    };
+
+    let file = IoUringFile::new(sq_entries, cq_entries)?;
+    params.sq_entries = sq_entries;
+    params.cq_entries = cq_entries;
+    params.features = (Features::IORING_FEAT_NODROP
+        | Features::IORING_FEAT_SUBMIT_STABLE
+        | Features::IORING_FEAT_RW_CUR_POS)
+        .bits();
+    params.sq_off = Rings::sq_offsets();
+    params.cq_off = Rings::cq_offsets();
+    Ok(file)
+}
+
+/// Submit the requests and wait for the completions. Return the number of
+/// requests submitted.
+pub fn do_io_uring_enter(
+    fd: FileDesc,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sig_mask: Option<SigSet>,
+) -> Result<usize> {
+    let flags = EnterFlags::from_bits(flags)
+        .ok_or_else(|| errno!(EINVAL, "invalid io_uring enter flags"))?;
+    if flags.contains(EnterFlags::IORING_ENTER_EXT_ARG) {
+        return_errno!(EINVAL, "IORING_ENTER_EXT_ARG is not supported");
+    }
+    let file = current!().file(fd)?;
+    let uring = file.as_io_uring()?;
+    uring.check_owner()?;
+
+    let submitted = uring.submit(to_submit);
+    if flags.contains(EnterFlags::IORING_ENTER_GETEVENTS) {
+        let wait = || uring.wait_cqes(min_complete as usize, &file);
+        let res = match sig_mask {
+            Some(sig_mask) => do_with_sig_mask(sig_mask, wait),
+            None => wait(),
+        };
+        // Like Linux, the error of waiting is returned only if no requests
+        // are submitted
+        if let Err(e) = res {
+            if submitted == 0 {
+                return Err(e);
+            }
+        }
+    }
+    Ok(submitted)
+}
+
+pub fn do_io_uring_register(fd: FileDesc, opcode: u32, arg: usize, nr_args: u32) -> Result<usize> {
+    let op = RegisterOp::from_raw(opcode)?;
+    let file = current!().file(fd)?;
+    let uring = file.as_io_uring()?;
+    uring.check_owner()?;
+
+    let nr_args = nr_args as usize;
+    if nr_args == 0 && (op == RegisterOp::RegisterBuffers || op == RegisterOp::RegisterFiles) {
+        return_errno!(EINVAL, "nothing to register");
+    }
+    match op {
+        RegisterOp::RegisterBuffers => {
+            let iovecs = {
+                let iovecs = arg as *const libc::iovec;
+                from_user::check_array(iovecs, nr_args)?;
+                unsafe { std::slice::from_raw_parts(iovecs, nr_args) }
+            };
+            uring.register_bufs(iovecs)?;
+        }
+        RegisterOp::RegisterFiles => {
+            let fds = {
+                let fds = arg as *const i32;
+                from_user::check_array(fds, nr_args)?;
+                unsafe { std::slice::from_raw_parts(fds, nr_args) }
+            };
+            uring.register_files(fds)?;
+        }
+        RegisterOp::RegisterEventfd | RegisterOp::RegisterEventfdAsync => {
+            if nr_args != 1 {
+                return_errno!(EINVAL, "only one eventfd can be registered");
+            }
+            let eventfd_ptr = arg as *const i32;
+            from_user::check_ptr(eventfd_ptr)?;
+            let eventfd = current!().file(unsafe { eventfd_ptr.read() } as FileDesc)?;
+            uring.register_eventfd(eventfd, op == RegisterOp::RegisterEventfdAsync)?;
+        }
+        RegisterOp::UnregisterBuffers
+        | RegisterOp::UnregisterFiles
+        | RegisterOp::UnregisterEventfd => {
+            if arg != 0 || nr_args != 0 {
+                return_errno!(EINVAL, "the arguments must be zero");
+            }
+            match op {
+                RegisterOp::UnregisterBuffers => uring.unregister_bufs()?,
+                RegisterOp::UnregisterFiles => uring.unregister_files()?,
+                _ => uring.unregister_eventfd()?,
+            }
+        }
+        RegisterOp::RegisterProbe => {
+            let nr_ops = nr_args.min(IORING_OP_LAST as usize);
+            let probe_ptr = arg as *mut io_uring_probe;
+            let probe_bytes = {
+                let len = size_of::<io_uring_probe>() + nr_ops * size_of::<io_uring_probe_op>();
+                from_user::check_mut_array(probe_ptr as *mut u8, len)?;
+                unsafe { std::slice::from_raw_parts(probe_ptr as *const u8, len) }
+            };
+            if probe_bytes.iter().any(|&byte| byte != 0) {
+                return_errno!(EINVAL, "the probe must be zeroed");
+            }
+
+            let probe = unsafe { &mut *probe_ptr };
+            probe.last_op = IORING_OP_LAST - 1;
+            probe.ops_len = nr_ops as u8;
+            let ops = unsafe {
+                std::slice::from_raw_parts_mut(probe_ptr.add(1) as *mut io_uring_probe_op, nr_ops)
+            };
+            for (raw_op, op) in ops.iter_mut().enumerate() {
+                op.op = raw_op as u8;
+                if Opcode::from_raw(raw_op as u8).is_some() {
+                    op.flags = IO_URING_OP_SUPPORTED;
+                }
+            }
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,428 @@
+use super::abi::*;
+use super::*;
+use crate::net::{do_accept4, do_recvfrom, do_sendto, RecvFlags, SendFlags};
+
+/// The opcodes of the supported operations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opcode {
+    Nop,
+    Readv,
+    Writev,
+    Fsync,
+    ReadFixed,
+    WriteFixed,
+    PollAdd,
+    PollRemove,
+    Accept,
+    AsyncCancel,
+    Read,
+    Write,
+    Send,
+    Recv,
+}
+
+/// One past the largest opcode known, which is reported by IORING_REGISTER_PROBE
+pub const IORING_OP_LAST: u8 = 28;
+
+impl Opcode {
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        Some(match raw {
+            0 => Opcode::Nop,
+            1 => Opcode::Readv,
+            2 => Opcode::Writev,
+            3 => Opcode::Fsync,
+            4 => Opcode::ReadFixed,
+            5 => Opcode::WriteFixed,
+            6 => Opcode::PollAdd,
+            7 => Opcode::PollRemove,
+            13 => Opcode::Accept,
+            14 => Opcode::AsyncCancel,
+            22 => Opcode::Read,
+            23 => Opcode::Write,
+            26 => Opcode::Send,
+            27 => Opcode::Recv,
+            _ => return None,
+        })
+    }
+}
+
+/// A request given by a submission queue entry.
+///
+/// Like io_submit, the entry is validated and its buffers are checked at
+/// submission time. A request that would block is kept pending and retried
+/// when its file becomes ready. Since the buffers may be unmapped by then, they
+/// are accessed through a kernel buffer after being checked to be mapped by
+/// the process again.
+#[derive(Debug)]
+pub struct Request {
+    user_data: u64,
+    op: Op,
+}
+
+#[derive(Debug)]
+enum Op {
+    Nop,
+    Read {
+        file: FileRef,
+        bufs: Vec<UserBuf>,
+        offset: Option<usize>,
+    },
+    Write {
+        file: FileRef,
+        bufs: Vec<UserBuf>,
+        offset: Option<usize>,
+    },
+    Fsync {
+        file: FileRef,
+        data_only: bool,
+    },
+    PollAdd {
+        file: FileRef,
+        events: IoEvents,
+    },
+    PollRemove {
+        target: u64,
+    },
+    AsyncCancel {
+        target: u64,
+    },
+    // The socket operations are done by the fd, which is checked to still
+    // refer to the file when the operations are executed
+    Accept {
+        file: FileRef,
+        fd: FileDesc,
+        addr: usize,
+        addr_len: usize,
+        flags: i32,
+    },
+    Send {
+        file: FileRef,
+        fd: FileDesc,
+        buf: UserBuf,
+        flags: SendFlags,
+    },
+    Recv {
+        file: FileRef,
+        fd: FileDesc,
+        buf: UserBuf,
+        flags: RecvFlags,
+    },
+}
+
+impl Request {
+    pub fn new(sqe: &io_uring_sqe, uring: &IoUringFile) -> Result<Self> {
+        let opcode = Opcode::from_raw(sqe.opcode)
+            .ok_or_else(|| errno!(EINVAL, "invalid io_uring opcode"))?;
+        let flags =
+            SqeFlags::from_bits(sqe.flags).ok_or_else(|| errno!(EINVAL, "invalid sqe flags"))?;
+        // IOSQE_ASYNC is only a hint, while the others change the order or the
+        // buffers of requests, which are not supported
+        if flags.intersects(
+            SqeFlags::IOSQE_IO_DRAIN
+                | SqeFlags::IOSQE_IO_LINK
+                | SqeFlags::IOSQE_IO_HARDLINK
+                | SqeFlags::IOSQE_BUFFER_SELECT,
+        ) {
+            return_errno!(EINVAL, "unsupported sqe flags");
+        }
+        if sqe.personality != 0 {
+            return_errno!(EINVAL, "personalities are not supported");
+        }
+        let get_file = || -> Result<FileRef> {
+            if flags.contains(SqeFlags::IOSQE_FIXED_FILE) {
+                uring.fixed_file(sqe.fd)
+            } else {
+                current!().file(sqe.fd as FileDesc)
+            }
+        };
+        let get_socket = || -> Result<(FileRef, FileDesc)> {
+            if flags.contains(SqeFlags::IOSQE_FIXED_FILE) {
+                return_errno!(EINVAL, "fixed files are not supported by socket operations");
+            }
+            let fd = sqe.fd as FileDesc;
+            Ok((current!().file(fd)?, fd))
+        };
+
+        let op = match opcode {
+            Opcode::Nop => Op::Nop,
+            Opcode::Read | Opcode::Readv | Opcode::ReadFixed => {
+                let offset = Self::offset(sqe)?;
+                let bufs = Self::bufs(sqe, opcode, uring, true)?;
+                Op::Read {
+                    file: get_file()?,
+                    bufs,
+                    offset,
+                }
+            }
+            Opcode::Write | Opcode::Writev | Opcode::WriteFixed => {
+                let offset = Self::offset(sqe)?;
+                let bufs = Self::bufs(sqe, opcode, uring, false)?;
+                Op::Write {
+                    file: get_file()?,
+                    bufs,
+                    offset,
+                }
+            }
+            Opcode::Fsync => {
+                if sqe.addr != 0 || sqe.buf_index != 0 {
+                    return_errno!(EINVAL, "invalid fsync request");
+                }
+                if sqe.op_flags & !IORING_FSYNC_DATASYNC != 0 {
+                    return_errno!(EINVAL, "invalid fsync flags");
+                }
+                Op::Fsync {
+                    file: get_file()?,
+                    data_only: sqe.op_flags & IORING_FSYNC_DATASYNC != 0,
+                }
+            }
+            Opcode::PollAdd => {
+                if sqe.addr != 0 || sqe.off != 0 || sqe.buf_index != 0 {
+                    return_errno!(EINVAL, "invalid poll request");
+                }
+                // Only the lower 16 bits are used since IORING_FEAT_POLL_32BITS is
+                // not supported
+                let events = IoEvents::from_raw(sqe.op_flags & 0xffff);
+                Op::PollAdd {
+                    file: get_file()?,
+                    events: events | IoEvents::ERR | IoEvents::HUP,
+                }
+            }
+            Opcode::PollRemove => Op::PollRemove { target: sqe.addr },
+            Opcode::AsyncCancel => {
+                if sqe.op_flags != 0 {
+                    return_errno!(EINVAL, "invalid cancel flags");
+                }
+                Op::AsyncCancel { target: sqe.addr }
+            }
+            Opcode::Accept => {
+                let (file, fd) = get_socket()?;
+                Op::Accept {
+                    file,
+                    fd,
+                    addr: sqe.addr as usize,
+                    // The pointer to the address length is given by addr2
+                    addr_len: sqe.off as usize,
+                    flags: sqe.op_flags as i32,
+                }
+            }
+            Opcode::Send => {
+                let flags = SendFlags::from_bits(sqe.op_flags as i32)
+                    .ok_or_else(|| errno!(EINVAL, "invalid send flags"))?;
+                let buf = UserBuf::new(sqe.addr, sqe.len as u64, false)?;
+                let (file, fd) = get_socket()?;
+                Op::Send {
+                    file,
+                    fd,
+                    buf,
+                    flags,
+                }
+            }
+            Opcode::Recv => {
+                let flags = RecvFlags::from_bits(sqe.op_flags as i32)
+                    .ok_or_else(|| errno!(EINVAL, "invalid recv flags"))?;
+                let buf = UserBuf::new(sqe.addr, sqe.len as u64, true)?;
+                let (file, fd) = get_socket()?;
+                Op::Recv {
+                    file,
+                    fd,
+                    buf,
+                    flags,
+                }
+            }
+        };
+        Ok(Self {
+            user_data: sqe.user_data,
+            op,
+        })
+    }
+
+    /// The offset of a read or write request. An offset of -1 means the
+    /// current file position (IORING_FEAT_RW_CUR_POS).
+    fn offset(sqe: &io_uring_sqe) -> Result<Option<usize>> {
+        if sqe.op_flags != 0 {
+            return_errno!(EOPNOTSUPP, "read/write flags are not supported");
+        }
+        match sqe.off as i64 {
+            -1 => Ok(None),
+            offset if offset < 0 => return_errno!(EINVAL, "the offset is negative"),
+            offset => Ok(Some(offset as usize)),
+        }
+    }
+
+    fn bufs(
+        sqe: &io_uring_sqe,
+        opcode: Opcode,
+        uring: &IoUringFile,
+        is_mut: bool,
+    ) -> Result<Vec<UserBuf>> {
+        match opcode {
+            Opcode::Readv | Opcode::Writev => {
+                UserBuf::from_iovecs(sqe.addr, sqe.len as u64, is_mut)
+            }
+            Opcode::ReadFixed | Opcode::WriteFixed => {
+                uring.check_fixed_buf(sqe.buf_index, sqe.addr, sqe.len)?;
+                Ok(vec![UserBuf::new(sqe.addr, sqe.len as u64, is_mut)?])
+            }
+            _ => Ok(vec![UserBuf::new(sqe.addr, sqe.len as u64, is_mut)?]),
+        }
+    }
+
+    pub fn user_data(&self) -> u64 {
+        self.user_data
+    }
+
+    /// The user data of the request to be canceled, and whether only poll
+    /// requests can be canceled.
+    pub fn cancel_target(&self) -> Option<(u64, bool)> {
+        match self.op {
+            Op::PollRemove { target } => Some((target, true)),
+            Op::AsyncCancel { target } => Some((target, false)),
+            _ => None,
+        }
+    }
+
+    pub fn is_poll(&self) -> bool {
+        match self.op {
+            Op::PollAdd { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// The file and the events that the request waits for.
+    pub fn awaited_events(&self) -> Option<(&FileRef, IoEvents)> {
+        let (file, events) = match &self.op {
+            Op::Read { file, .. } | Op::Accept { file, .. } | Op::Recv { file, .. } => {
+                (file, IoEvents::IN)
+            }
+            Op::Write { file, .. } | Op::Send { file, .. } => (file, IoEvents::OUT),
+            Op::PollAdd { file, events } => return Some((file, *events)),
+            _ => return None,
+        };
+        Some((file, events | IoEvents::ERR | IoEvents::HUP))
+    }
+
+    /// Execute the request if it would not block. Return the result of the
+    /// request, or None if the request is still pending.
+    pub fn try_run(&self) -> Option<i32> {
+        // A request on a pollable file is retried later if it would block
+        let can_retry = match self.awaited_events() {
+            Some((file, events)) if is_pollable(file) || self.is_poll() => {
+                if (file.poll_new() & events).is_empty() {
+                    return None;
+                }
+                true
+            }
+            _ => false,
+        };
+        match self.do_op() {
+            Ok(res) => Some(res.min(i32::MAX as usize) as i32),
+            Err(e) if e.errno() == EAGAIN && can_retry => None,
+            Err(e) => Some(-(e.errno() as i32)),
+        }
+    }
+
+    fn do_op(&self) -> Result<usize> {
+        match &self.op {
+            Op::Nop => Ok(0),
+            Op::Read { file, bufs, offset } => {
+                // Fail before the data is consumed if the buffers are unmapped
+                for buf in bufs {
+                    buf.check_mapped()?;
+                }
+                let mut data = UserBuf::alloc_bounce_buf(bufs)?;
+                let len = match offset {
+                    Some(offset) => file.read_at(*offset, &mut data)?,
+                    None => file.read(&mut data)?,
+                };
+                UserBuf::scatter(bufs, &data[..len])
+            }
+            Op::Write { file, bufs, offset } => {
+                let mut data = UserBuf::alloc_bounce_buf(bufs)?;
+                UserBuf::gather(bufs, &mut data)?;
+                match offset {
+                    Some(offset) => file.write_at(*offset, &data),
+                    None => file.write(&data),
+                }
+            }
+            Op::Fsync { file, data_only } => {
+                if *data_only {
+                    file.sync_data()?;
+                } else {
+                    file.sync_all()?;
+                }
+                Ok(0)
+            }
+            Op::PollAdd { file, events } => Ok((file.poll_new() & *events).to_raw() as usize),
+            Op::PollRemove { .. } | Op::AsyncCancel { .. } => {
+                unreachable!("cancel requests are done by the io_uring file")
+            }
+            Op::Accept {
+                file,
+                fd,
+                addr,
+                addr_len,
+                flags,
+            } => {
+                check_fd(*fd, file)?;
+                let new_fd = do_accept4(
+                    *fd as c_int,
+                    *addr as *mut libc::sockaddr,
+                    *addr_len as *mut libc::socklen_t,
+                    *flags,
+                )?;
+                Ok(new_fd as usize)
+            }
+            Op::Send {
+                file,
+                fd,
+                buf,
+                flags,
+            } => {
+                check_fd(*fd, file)?;
+                buf.check_mapped()?;
+                let len = do_sendto(
+                    *fd as c_int,
+                    buf.addr() as *const c_void,
+                    buf.len(),
+                    (*flags | SendFlags::MSG_DONTWAIT).bits(),
+                    std::ptr::null(),
+                    0,
+                )?;
+                Ok(len as usize)
+            }
+            Op::Recv {
+                file,
+                fd,
+                buf,
+                flags,
+            } => {
+                check_fd(*fd, file)?;
+                buf.check_mapped()?;
+                let len = do_recvfrom(
+                    *fd as c_int,
+                    buf.addr() as *mut c_void,
+                    buf.len(),
+                    (*flags | RecvFlags::MSG_DONTWAIT).bits(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )?;
+                Ok(len as usize)
+            }
+        }
+    }
+}
+
+/// Whether the readiness of a file can be polled. Other files are assumed to
+/// be always ready.
+pub fn is_pollable(file: &FileRef) -> bool {
+    file.notifier().is_some() || file.host_fd().is_some()
+}
+
+/// Check that the fd of a pending socket request still refers to its file.
+fn check_fd(fd: FileDesc, file: &FileRef) -> Result<()> {
+    let current_file = current!().file(fd)?;
+    if !Arc::ptr_eq(&current_file, file) {
+        return_errno!(EBADF, "the fd has been closed");
+    }
+    Ok(())
+}
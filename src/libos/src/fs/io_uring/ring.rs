@@ -0,0 +1,253 @@
+//! The rings shared with the user space.
+//!
+//! The rings are mapped into the process by the LibOS, so both the LibOS and
+//! the user access them directly. Since all the processes share one address
+//! space, the user is not allowed to unmap, remap or protect the rings, which
+//! would let the LibOS access the memory of others. The rings are unmapped when
+//! the io_uring file is closed, or freed with the memory of the process.
+//! The indexes written by the other side are read
+//! with the acquire ordering and those written by the LibOS are stored with
+//! the release ordering, as is done by Linux.
+
+use super::abi::*;
+use super::*;
+use crate::vm::{ProcessVM, VMRange};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Weak;
+
+// The layout of the submission queue ring
+const SQ_HEAD: usize = 0;
+const SQ_TAIL: usize = 4;
+const SQ_RING_MASK: usize = 8;
+const SQ_RING_ENTRIES: usize = 12;
+const SQ_FLAGS: usize = 16;
+const SQ_DROPPED: usize = 20;
+const SQ_ARRAY: usize = 64;
+
+// The layout of the completion queue ring
+const CQ_HEAD: usize = 0;
+const CQ_TAIL: usize = 4;
+const CQ_RING_MASK: usize = 8;
+const CQ_RING_ENTRIES: usize = 12;
+const CQ_OVERFLOW: usize = 16;
+const CQ_FLAGS: usize = 20;
+const CQ_CQES: usize = 64;
+
+#[derive(Debug)]
+pub struct Rings {
+    sq_ring: Region,
+    cq_ring: Region,
+    sqes: Region,
+    sq_entries: u32,
+    cq_entries: u32,
+    /// The VM of the process that the rings are mapped into
+    vm: Weak<ProcessVM>,
+}
+
+/// A memory region mapped for the rings
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    range: VMRange,
+}
+
+impl Rings {
+    /// Map the rings into the current process.
+    pub fn new(sq_entries: u32, cq_entries: u32) -> Result<Self> {
+        debug_assert!(sq_entries.is_power_of_two() && cq_entries.is_power_of_two());
+        let sizes = [
+            SQ_ARRAY + sq_entries as usize * size_of::<u32>(),
+            CQ_CQES + cq_entries as usize * size_of::<io_uring_cqe>(),
+            sq_entries as usize * size_of::<io_uring_sqe>(),
+        ];
+        let vm = current!().vm().clone();
+        let mut regions = Vec::with_capacity(sizes.len());
+        for size in sizes.iter() {
+            match Region::map(&vm, *size) {
+                Ok(region) => regions.push(region),
+                Err(e) => {
+                    for region in regions {
+                        region.unmap(&vm);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let rings = Self {
+            sq_ring: regions[0],
+            cq_ring: regions[1],
+            sqes: regions[2],
+            sq_entries,
+            cq_entries,
+            vm: Arc::downgrade(&vm),
+        };
+        // The mappings are zeroed, so only the constant fields are initialized
+        rings
+            .sq_u32(SQ_RING_MASK)
+            .store(sq_entries - 1, Ordering::Relaxed);
+        rings
+            .sq_u32(SQ_RING_ENTRIES)
+            .store(sq_entries, Ordering::Relaxed);
+        rings
+            .cq_u32(CQ_RING_MASK)
+            .store(cq_entries - 1, Ordering::Relaxed);
+        rings
+            .cq_u32(CQ_RING_ENTRIES)
+            .store(cq_entries, Ordering::Relaxed);
+        Ok(rings)
+    }
+
+    pub fn sq_offsets() -> io_sqring_offsets {
+        io_sqring_offsets {
+            head: SQ_HEAD as u32,
+            tail: SQ_TAIL as u32,
+            ring_mask: SQ_RING_MASK as u32,
+            ring_entries: SQ_RING_ENTRIES as u32,
+            flags: SQ_FLAGS as u32,
+            dropped: SQ_DROPPED as u32,
+            array: SQ_ARRAY as u32,
+            ..Default::default()
+        }
+    }
+
+    pub fn cq_offsets() -> io_cqring_offsets {
+        io_cqring_offsets {
+            head: CQ_HEAD as u32,
+            tail: CQ_TAIL as u32,
+            ring_mask: CQ_RING_MASK as u32,
+            ring_entries: CQ_RING_ENTRIES as u32,
+            overflow: CQ_OVERFLOW as u32,
+            cqes: CQ_CQES as u32,
+            flags: CQ_FLAGS as u32,
+            ..Default::default()
+        }
+    }
+
+    pub fn sq_entries(&self) -> u32 {
+        self.sq_entries
+    }
+
+    pub fn cq_entries(&self) -> u32 {
+        self.cq_entries
+    }
+
+    /// Get the address and the size of the region given by the mmap offset.
+    pub fn region(&self, offset: usize) -> Option<(usize, usize)> {
+        let region = match offset {
+            IORING_OFF_SQ_RING => self.sq_ring,
+            IORING_OFF_CQ_RING => self.cq_ring,
+            IORING_OFF_SQES => self.sqes,
+            _ => return None,
+        };
+        Some((region.range.start(), region.range.size()))
+    }
+
+    pub fn sq_head(&self) -> u32 {
+        self.sq_u32(SQ_HEAD).load(Ordering::Relaxed)
+    }
+
+    pub fn set_sq_head(&self, head: u32) {
+        self.sq_u32(SQ_HEAD).store(head, Ordering::Release);
+    }
+
+    pub fn sq_tail(&self) -> u32 {
+        self.sq_u32(SQ_TAIL).load(Ordering::Acquire)
+    }
+
+    /// Get the submission queue entry at the head of the ring, or None if the
+    /// index given by the user is out of bound.
+    pub fn sqe(&self, head: u32) -> Option<io_uring_sqe> {
+        let array_idx = (head & (self.sq_entries - 1)) as usize;
+        let idx = self
+            .sq_u32(SQ_ARRAY + array_idx * size_of::<u32>())
+            .load(Ordering::Relaxed);
+        if idx >= self.sq_entries {
+            return None;
+        }
+        let sqe_addr = self.sqes.range.start() + idx as usize * size_of::<io_uring_sqe>();
+        // The entry is copied, so later changes by the user do not matter
+        Some(unsafe { std::ptr::read_volatile(sqe_addr as *const io_uring_sqe) })
+    }
+
+    /// Count a submission queue entry with an invalid index.
+    pub fn inc_sq_dropped(&self) {
+        self.sq_u32(SQ_DROPPED).fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_sq_flags(&self, flags: SqRingFlags) {
+        self.sq_u32(SQ_FLAGS)
+            .fetch_or(flags.bits(), Ordering::Relaxed);
+    }
+
+    pub fn clear_sq_flags(&self, flags: SqRingFlags) {
+        self.sq_u32(SQ_FLAGS)
+            .fetch_and(!flags.bits(), Ordering::Relaxed);
+    }
+
+    pub fn cq_head(&self) -> u32 {
+        self.cq_u32(CQ_HEAD).load(Ordering::Acquire)
+    }
+
+    pub fn cq_tail(&self) -> u32 {
+        self.cq_u32(CQ_TAIL).load(Ordering::Relaxed)
+    }
+
+    /// Write a completion queue entry at the tail of the ring, which must not
+    /// be full, and advance the tail.
+    pub fn push_cqe(&self, cqe: &io_uring_cqe) {
+        let tail = self.cq_tail();
+        let idx = (tail & (self.cq_entries - 1)) as usize;
+        let cqe_addr = self.cq_ring.range.start() + CQ_CQES + idx * size_of::<io_uring_cqe>();
+        unsafe {
+            std::ptr::write_volatile(cqe_addr as *mut io_uring_cqe, *cqe);
+        }
+        self.cq_u32(CQ_TAIL)
+            .store(tail.wrapping_add(1), Ordering::Release);
+    }
+
+    fn sq_u32(&self, offset: usize) -> &AtomicU32 {
+        self.sq_ring.u32_at(offset)
+    }
+
+    fn cq_u32(&self, offset: usize) -> &AtomicU32 {
+        self.cq_ring.u32_at(offset)
+    }
+}
+
+impl Drop for Rings {
+    fn drop(&mut self) {
+        // The rings may be dropped by another process that inherits the file,
+        // or by an AIO worker thread. The memory of another process cannot be
+        // unmapped, so the rings are left to be freed when the owner exits.
+        let vm = match self.vm.upgrade() {
+            Some(vm) if Arc::ptr_eq(&vm, current!().vm()) => vm,
+            _ => return,
+        };
+        for region in [self.sq_ring, self.cq_ring, self.sqes].iter() {
+            region.unmap(&vm);
+        }
+    }
+}
+
+impl Region {
+    fn map(vm: &ProcessVM, size: usize) -> Result<Self> {
+        let range = vm.mmap_libos(size)?;
+        Ok(Self { range })
+    }
+
+    fn unmap(&self, vm: &ProcessVM) {
+        if let Err(e) = vm.munmap_libos(&self.range) {
+            warn!("failed to unmap the io_uring region {:?}: {:?}", self, e);
+        }
+    }
+
+    fn u32_at(&self, offset: usize) -> &AtomicU32 {
+        debug_assert!(offset % size_of::<u32>() == 0 && offset < self.range.size());
+        // Safety. The region cannot be unmapped by the user, and the rings are
+        // only accessed by the threads of the owner process (see
+        // `IoUringFile::check_owner`), so the region is mapped until the rings
+        // are dropped or the threads exit.
+        unsafe { &*((self.range.start() + offset) as *const AtomicU32) }
+    }
+}
@@ -0,0 +1,120 @@
+//! Anonymous files created by memfd_create(2).
+//!
+//! A memfd is an `INodeFile` whose inode lives in a private RamFS that is not
+//! mounted anywhere. The inode is unlinked right after it is created, so it is
+//! only reachable through the file descriptors and the memory mappings.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::*;
+use rcore_fs::vfs::AnyExt;
+use rcore_fs_ramfs::RamFS;
+
+/// The maximum length of the name of a memfd, excluding the terminating null byte
+const MEMFD_NAME_MAX: usize = 249;
+
+lazy_static! {
+    static ref MEMFD_FS: Arc<RamFS> = RamFS::new();
+}
+
+static NEXT_MEMFD_ID: AtomicUsize = AtomicUsize::new(0);
+
+bitflags! {
+    pub struct MemfdFlags: u32 {
+        const MFD_CLOEXEC = 0x1;
+        const MFD_ALLOW_SEALING = 0x2;
+        const MFD_HUGETLB = 0x4;
+    }
+}
+
+bitflags! {
+    /// The seals of a file. See fcntl(2) for the details.
+    pub struct FileSeals: u32 {
+        /// Prevent further seals from being set
+        const F_SEAL_SEAL = 0x1;
+        /// Prevent the file from shrinking
+        const F_SEAL_SHRINK = 0x2;
+        /// Prevent the file from growing
+        const F_SEAL_GROW = 0x4;
+        /// Prevent writes to the file
+        const F_SEAL_WRITE = 0x8;
+        /// Like `F_SEAL_WRITE`, but the existing shared writable mappings are not affected
+        const F_SEAL_FUTURE_WRITE = 0x10;
+    }
+}
+
+impl FileSeals {
+    pub fn from_u32(raw_seals: u32) -> Result<Self> {
+        Self::from_bits(raw_seals).ok_or_else(|| errno!(EINVAL, "invalid seals"))
+    }
+}
+
+/// The seals of an inode, which are kept in the inode extension so that all
+/// the open files of the inode share them. Only the inodes created by
+/// memfd_create have the seals.
+#[derive(Debug, Default)]
+pub struct InodeSeals {
+    inner: SgxMutex<FileSeals>,
+}
+
+impl AnyExt for InodeSeals {}
+
+impl InodeSeals {
+    pub fn get(&self) -> FileSeals {
+        *self.inner.lock().unwrap()
+    }
+
+    /// Lock the seals, so that no seals can be added until the guard is dropped,
+    /// e.g., while the file is being written after the seals are checked.
+    pub fn lock(&self) -> SgxMutexGuard<FileSeals> {
+        self.inner.lock().unwrap()
+    }
+
+    /// Add new seals, unless the seals are sealed.
+    pub fn add(&self, new_seals: FileSeals) -> Result<()> {
+        let mut seals = self.inner.lock().unwrap();
+        if seals.contains(FileSeals::F_SEAL_SEAL) {
+            return_errno!(EPERM, "the seals of the file cannot be changed");
+        }
+        seals.insert(new_seals);
+        Ok(())
+    }
+
+    /// Get the seals of the inode, or None if the inode does not support sealing.
+    pub fn of_inode(inode: &Arc<dyn INode>) -> Option<Arc<Self>> {
+        inode.ext()?.get::<Self>()
+    }
+}
+
+/// Whether the inode is created by memfd_create, which is anonymous, i.e., not
+/// reachable by any path.
+pub fn is_memfd_inode(inode: &Arc<dyn INode>) -> bool {
+    // The seals are put into every memfd inode when it is created
+    InodeSeals::of_inode(inode).is_some()
+}
+
+pub fn create_memfd(name: &str, flags: MemfdFlags) -> Result<INodeFile> {
+    if name.len() > MEMFD_NAME_MAX {
+        return_errno!(EINVAL, "the name is too long");
+    }
+    if flags.contains(MemfdFlags::MFD_HUGETLB) {
+        return_errno!(EINVAL, "huge pages are not supported");
+    }
+
+    let root_inode = MEMFD_FS.root_inode();
+    let inode_name = NEXT_MEMFD_ID.fetch_add(1, Ordering::Relaxed).to_string();
+    let inode = root_inode.create(&inode_name, FileType::File, 0o777)?;
+    root_inode.unlink(&inode_name)?;
+
+    let seals = inode
+        .ext()
+        .ok_or_else(|| errno!(EOPNOTSUPP, "the inode extension is not supported"))?
+        .get_or_put_default::<InodeSeals>();
+    if !flags.contains(MemfdFlags::MFD_ALLOW_SEALING) {
+        seals.add(FileSeals::F_SEAL_SEAL)?;
+    }
+
+    // The same as the link target of /proc/[pid]/fd/[fd] on Linux
+    let abs_path = format!("/memfd:{} (deleted)", name);
+    INodeFile::open(inode, &abs_path, AccessMode::O_RDWR as u32)
+}
@@ -21,23 +21,29 @@ pub use self::file::{File, FileRef};
 pub use self::file_ops::{
     occlum_ocall_ioctl, utimbuf_t, AccessMode, BuiltinIoctlNum, CreationFlags, FallocateFlags,
     FileMode, IfConf, IoctlCmd, Stat, StatusFlags, StructuredIoctlArgType, StructuredIoctlNum,
-    STATUS_FLAGS_MASK,
+    XattrNamespace, STATUS_FLAGS_MASK,
 };
 pub use self::file_table::{FileDesc, FileTable, FileTableEvent, FileTableNotifier};
 pub use self::fs_ops::Statfs;
 pub use self::fs_view::FsView;
 pub use self::host_fd::HostFd;
 pub use self::inode_file::{AsINodeFile, INodeExt, INodeFile};
+pub use self::inotify_file::{AsInotify, InotifyFile, InotifyFlags, InotifyMask};
 pub use self::locks::flock::{Flock, FlockList, FlockOps, FlockType};
 pub use self::locks::range_lock::{
     FileRange, RangeLock, RangeLockBuilder, RangeLockList, RangeLockType, OFFSET_MAX,
 };
+pub use self::memfd::{FileSeals, MemfdFlags};
+pub use self::mq_file::{mq_attr_t, AsMq, MqFile};
 pub use self::pipe::PipeType;
 pub use self::rootfs::ROOT_FS;
+pub use self::signal_file::{AsSignal, SignalFile, SignalFileFlags};
 pub use self::stdio::{HostStdioFds, StdinFile, StdoutFile};
 pub use self::syscalls::*;
 pub use self::timer_file::{AsTimer, TimerCreationFlags, TimerFile};
+pub use self::user_buf::UserBuf;
 
+pub mod aio;
 pub mod channel;
 mod dev_fs;
 mod event_file;
@@ -50,14 +56,20 @@ mod fs_view;
 mod host_fd;
 mod hostfs;
 mod inode_file;
+mod inotify_file;
+pub mod io_uring;
 mod locks;
+mod memfd;
+mod mq_file;
 mod pipe;
 mod procfs;
 mod rootfs;
 mod sefs;
+mod signal_file;
 mod stdio;
 mod syscalls;
 mod timer_file;
+mod user_buf;
 
 /// Split a `path` to (`dir_path`, `file_name`).
 ///
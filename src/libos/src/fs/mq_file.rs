@@ -0,0 +1,543 @@
+//! POSIX message queues.
+//!
+//! A message queue is identified by a name in a system-wide namespace. Opening
+//! a queue returns a file descriptor, so it can be polled or added to epoll
+//! just like other files. Messages are delivered in the order of their
+//! priorities, and in FIFO order for those of the same priority.
+use super::*;
+
+use crate::events::{Waiter, WaiterQueue};
+use crate::process::{do_getegid, do_geteuid, gid_t, table, uid_t, ProcessStatus};
+use crate::signal::{
+    sigevent_t, SigNum, UserSignal, UserSignalKind, SIGEV_NONE, SIGEV_SIGNAL, SIGEV_THREAD,
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+/// The priority of a message must be less than MQ_PRIO_MAX
+pub const MQ_PRIO_MAX: u32 = 32768;
+// The default attributes of a new queue if not specified by the user
+const DFLT_MAXMSG: usize = 10;
+const DFLT_MSGSIZE: usize = 8192;
+// The upper limits of the attributes of a queue
+const HARD_MAXMSG: usize = 65536;
+const HARD_MSGSIZE: usize = 16 * 1024 * 1024;
+// The max length of the name of a queue
+const MQ_NAME_MAX: usize = 255;
+
+lazy_static! {
+    static ref MQ_NAMESPACE: RwLock<HashMap<String, Arc<MsgQueue>>> = RwLock::new(HashMap::new());
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct mq_attr_t {
+    pub mq_flags: i64,
+    pub mq_maxmsg: i64,
+    pub mq_msgsize: i64,
+    pub mq_curmsgs: i64,
+    __reserved: [i64; 4],
+}
+
+/// Open a message queue with the name, creating it if O_CREAT is given.
+pub fn mq_open(name: &str, flags: u32, mode: FileMode, attr: Option<&mq_attr_t>) -> Result<MqFile> {
+    check_name(name)?;
+    let access_mode = AccessMode::from_u32(flags)?;
+    let creation_flags = CreationFlags::from_bits_truncate(flags);
+    let status_flags = StatusFlags::from_bits_truncate(flags) & StatusFlags::O_NONBLOCK;
+
+    let mut namespace = MQ_NAMESPACE.write().unwrap();
+    let queue = match namespace.get(name) {
+        Some(queue) => {
+            if creation_flags.can_create() && creation_flags.is_exclusive() {
+                return_errno!(EEXIST, "the message queue already exists");
+            }
+            queue.clone()
+        }
+        None => {
+            if !creation_flags.can_create() {
+                return_errno!(ENOENT, "the message queue does not exist");
+            }
+            let (maxmsg, msgsize) = match attr {
+                Some(attr) => {
+                    if attr.mq_maxmsg <= 0 || attr.mq_maxmsg as usize > HARD_MAXMSG {
+                        return_errno!(EINVAL, "invalid mq_maxmsg");
+                    }
+                    if attr.mq_msgsize <= 0 || attr.mq_msgsize as usize > HARD_MSGSIZE {
+                        return_errno!(EINVAL, "invalid mq_msgsize");
+                    }
+                    (attr.mq_maxmsg as usize, attr.mq_msgsize as usize)
+                }
+                None => (DFLT_MAXMSG, DFLT_MSGSIZE),
+            };
+            let queue = Arc::new(MsgQueue::new(name, maxmsg, msgsize, mode));
+            namespace.insert(name.to_string(), queue.clone());
+            queue
+        }
+    };
+    Ok(MqFile::new(queue, access_mode, status_flags))
+}
+
+/// Remove the name of a message queue. The queue is destroyed once all
+/// the descriptors referring to it are closed.
+pub fn mq_unlink(name: &str) -> Result<()> {
+    check_name(name)?;
+    MQ_NAMESPACE
+        .write()
+        .unwrap()
+        .remove(name)
+        .ok_or_else(|| errno!(ENOENT, "the message queue does not exist"))?;
+    Ok(())
+}
+
+/// Look up a message queue by its name.
+pub fn mq_lookup(name: &str) -> Option<Arc<MsgQueue>> {
+    MQ_NAMESPACE.read().unwrap().get(name).cloned()
+}
+
+/// Return the names of all message queues.
+pub fn mq_names() -> Vec<String> {
+    let mut names: Vec<String> = MQ_NAMESPACE.read().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return_errno!(ENOENT, "the name is empty");
+    }
+    if name.len() > MQ_NAME_MAX {
+        return_errno!(ENAMETOOLONG, "the name is too long");
+    }
+    if name.contains('/') {
+        return_errno!(EACCES, "the name must not contain slashes");
+    }
+    Ok(())
+}
+
+pub struct MsgQueue {
+    name: String,
+    maxmsg: usize,
+    msgsize: usize,
+    inner: SgxMutex<MsgQueueInner>,
+    senders: WaiterQueue,
+    receivers: WaiterQueue,
+    notifier: IoNotifier,
+}
+
+struct MsgQueueInner {
+    // Messages of the same priority are kept in FIFO order
+    msgs: BTreeMap<u32, VecDeque<Vec<u8>>>,
+    num_msgs: usize,
+    num_bytes: usize,
+    num_blocked_receivers: usize,
+    mode: FileMode,
+    uid: uid_t,
+    gid: gid_t,
+    notification: Option<MqNotification>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MqNotification {
+    pid: pid_t,
+    sigevent: sigevent_t,
+}
+
+impl MsgQueue {
+    fn new(name: &str, maxmsg: usize, msgsize: usize, mode: FileMode) -> Self {
+        let inner = MsgQueueInner {
+            msgs: BTreeMap::new(),
+            num_msgs: 0,
+            num_bytes: 0,
+            num_blocked_receivers: 0,
+            mode,
+            uid: do_geteuid().unwrap() as uid_t,
+            gid: do_getegid().unwrap() as gid_t,
+            notification: None,
+        };
+        Self {
+            name: name.to_string(),
+            maxmsg,
+            msgsize,
+            inner: SgxMutex::new(inner),
+            senders: WaiterQueue::new(),
+            receivers: WaiterQueue::new(),
+            notifier: IoNotifier::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mode(&self) -> FileMode {
+        self.inner.lock().unwrap().mode
+    }
+
+    pub fn owner(&self) -> (uid_t, gid_t) {
+        let inner = self.inner.lock().unwrap();
+        (inner.uid, inner.gid)
+    }
+
+    /// Send a message, waiting for free space if the queue is full.
+    fn send(
+        &self,
+        msg: &[u8],
+        prio: u32,
+        nonblocking: bool,
+        mut timeout: Option<Duration>,
+    ) -> Result<()> {
+        if msg.len() > self.msgsize {
+            return_errno!(EMSGSIZE, "the message is too long");
+        }
+        if prio >= MQ_PRIO_MAX {
+            return_errno!(EINVAL, "invalid priority");
+        }
+
+        let waiter = Waiter::new();
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.num_msgs < self.maxmsg {
+                let was_empty = inner.num_msgs == 0;
+                inner
+                    .msgs
+                    .entry(prio)
+                    .or_insert_with(VecDeque::new)
+                    .push_back(msg.to_vec());
+                inner.num_msgs += 1;
+                inner.num_bytes += msg.len();
+                // The notification is only sent when a message arrives on an
+                // empty queue that no one is waiting to receive from
+                let notification = if was_empty && inner.num_blocked_receivers == 0 {
+                    inner.notification.take()
+                } else {
+                    None
+                };
+                drop(inner);
+
+                if let Some(notification) = notification {
+                    notification.deliver();
+                }
+                self.receivers.dequeue_and_wake_all();
+                self.notifier.broadcast(&IoEvents::IN);
+                return Ok(());
+            }
+            if nonblocking {
+                return_errno!(EAGAIN, "the message queue is full");
+            }
+
+            self.senders.reset_and_enqueue(&waiter);
+            // Ensure that we drop any locks before wait
+            drop(inner);
+            wait_until_timeout(&waiter, &mut timeout)?;
+        }
+    }
+
+    /// Receive the oldest message of the highest priority, waiting for one
+    /// if the queue is empty. Return the length and the priority of the message.
+    fn receive(
+        &self,
+        buf: &mut [u8],
+        nonblocking: bool,
+        mut timeout: Option<Duration>,
+    ) -> Result<(usize, u32)> {
+        if buf.len() < self.msgsize {
+            return_errno!(EMSGSIZE, "the buffer is smaller than mq_msgsize");
+        }
+
+        let waiter = Waiter::new();
+        let mut is_blocked = false;
+        loop {
+            let mut inner = self.inner.lock().unwrap();
+            if is_blocked {
+                inner.num_blocked_receivers -= 1;
+                is_blocked = false;
+            }
+            if let Some((prio, msg)) = inner.pop() {
+                drop(inner);
+
+                let len = msg.len();
+                buf[..len].copy_from_slice(&msg);
+                self.senders.dequeue_and_wake_all();
+                self.notifier.broadcast(&IoEvents::OUT);
+                return Ok((len, prio));
+            }
+            if nonblocking {
+                return_errno!(EAGAIN, "the message queue is empty");
+            }
+
+            self.receivers.reset_and_enqueue(&waiter);
+            inner.num_blocked_receivers += 1;
+            is_blocked = true;
+            // Ensure that we drop any locks before wait
+            drop(inner);
+            if let Err(e) = wait_until_timeout(&waiter, &mut timeout) {
+                self.inner.lock().unwrap().num_blocked_receivers -= 1;
+                return Err(e);
+            }
+        }
+    }
+
+    /// Register or unregister (if `sigevent` is None) the current process
+    /// for the notification of message arrival.
+    fn set_notification(&self, sigevent: Option<&sigevent_t>) -> Result<()> {
+        let pid = current!().process().pid();
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(notification) = inner.notification.as_ref() {
+            // The registration of a process which has exited is stale
+            let is_alive = table::get_process(notification.pid)
+                .map(|process| process.status() != ProcessStatus::Zombie)
+                .unwrap_or(false);
+            if notification.pid != pid && is_alive {
+                return_errno!(EBUSY, "another process has registered for notification");
+            }
+        }
+
+        inner.notification = match sigevent {
+            None => None,
+            Some(sigevent) => {
+                match sigevent.sigev_notify {
+                    SIGEV_NONE => {}
+                    SIGEV_SIGNAL => {
+                        signo_of(sigevent)?;
+                    }
+                    SIGEV_THREAD => {
+                        return_errno!(EINVAL, "SIGEV_THREAD is not supported");
+                    }
+                    _ => {
+                        return_errno!(EINVAL, "invalid sigev_notify");
+                    }
+                }
+                Some(MqNotification {
+                    pid,
+                    sigevent: *sigevent,
+                })
+            }
+        };
+        Ok(())
+    }
+
+    /// Remove the registration of the process with `pid`, if any.
+    fn remove_notification(&self, pid: pid_t) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.notification.map(|n| n.pid) == Some(pid) {
+            inner.notification = None;
+        }
+    }
+
+    /// The status line as shown by reading the queue under /dev/mqueue.
+    pub fn status(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let (notify, signo, notify_pid) = match inner.notification.as_ref() {
+            Some(notification) => (
+                notification.sigevent.sigev_notify,
+                notification.sigevent.sigev_signo,
+                notification.pid,
+            ),
+            None => (0, 0, 0),
+        };
+        format!(
+            "QSIZE:{:<10} NOTIFY:{:<5} SIGNO:{:<5} NOTIFY_PID:{:<6}\n",
+            inner.num_bytes, notify, signo, notify_pid
+        )
+    }
+
+    fn num_msgs(&self) -> usize {
+        self.inner.lock().unwrap().num_msgs
+    }
+
+    fn poll(&self) -> IoEvents {
+        let num_msgs = self.num_msgs();
+        let mut events = IoEvents::empty();
+        if num_msgs > 0 {
+            events |= IoEvents::IN;
+        }
+        if num_msgs < self.maxmsg {
+            events |= IoEvents::OUT;
+        }
+        events
+    }
+}
+
+impl MsgQueueInner {
+    fn pop(&mut self) -> Option<(u32, Vec<u8>)> {
+        let (&prio, msgs) = self.msgs.iter_mut().next_back()?;
+        let msg = msgs.pop_front().unwrap();
+        if msgs.is_empty() {
+            self.msgs.remove(&prio);
+        }
+        self.num_msgs -= 1;
+        self.num_bytes -= msg.len();
+        Some((prio, msg))
+    }
+}
+
+impl MqNotification {
+    fn deliver(&self) {
+        if self.sigevent.sigev_notify != SIGEV_SIGNAL {
+            return;
+        }
+        let process = match table::get_process(self.pid) {
+            Ok(process) if process.status() != ProcessStatus::Zombie => process,
+            _ => return,
+        };
+        let signum = signo_of(&self.sigevent).unwrap();
+        let signal = {
+            let src_pid = current!().process().pid();
+            let src_uid = do_geteuid().unwrap() as uid_t;
+            Box::new(UserSignal::new(
+                signum,
+                UserSignalKind::Mesgq(self.sigevent.sigev_value),
+                src_pid,
+                src_uid,
+            ))
+        };
+        let mut sig_queues = process.sig_queues().write().unwrap();
+        sig_queues.enqueue(signal);
+    }
+}
+
+fn signo_of(sigevent: &sigevent_t) -> Result<SigNum> {
+    let signo =
+        u8::try_from(sigevent.sigev_signo).map_err(|_| errno!(EINVAL, "invalid signal number"))?;
+    SigNum::from_u8(signo)
+}
+
+fn wait_until_timeout(waiter: &Waiter, timeout: &mut Option<Duration>) -> Result<()> {
+    if *timeout == Some(Duration::from_secs(0)) {
+        return_errno!(ETIMEDOUT, "the timeout expired");
+    }
+    waiter.wait_mut(timeout.as_mut())
+}
+
+impl std::fmt::Debug for MsgQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MsgQueue")
+            .field("name", &self.name)
+            .field("maxmsg", &self.maxmsg)
+            .field("msgsize", &self.msgsize)
+            .field("num_msgs", &self.num_msgs())
+            .finish()
+    }
+}
+
+/// The file of an opened POSIX message queue
+#[derive(Debug)]
+pub struct MqFile {
+    queue: Arc<MsgQueue>,
+    access_mode: AccessMode,
+    status_flags: RwLock<StatusFlags>,
+}
+
+impl MqFile {
+    fn new(queue: Arc<MsgQueue>, access_mode: AccessMode, status_flags: StatusFlags) -> Self {
+        Self {
+            queue,
+            access_mode,
+            status_flags: RwLock::new(status_flags),
+        }
+    }
+
+    pub fn queue(&self) -> &Arc<MsgQueue> {
+        &self.queue
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status_flags
+            .read()
+            .unwrap()
+            .contains(StatusFlags::O_NONBLOCK)
+    }
+
+    pub fn send(&self, msg: &[u8], prio: u32, timeout: Option<Duration>) -> Result<()> {
+        if !self.access_mode.writable() {
+            return_errno!(EBADF, "the message queue is not opened for writing");
+        }
+        self.queue.send(msg, prio, self.is_nonblocking(), timeout)
+    }
+
+    pub fn receive(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<(usize, u32)> {
+        if !self.access_mode.readable() {
+            return_errno!(EBADF, "the message queue is not opened for reading");
+        }
+        self.queue.receive(buf, self.is_nonblocking(), timeout)
+    }
+
+    pub fn set_notification(&self, sigevent: Option<&sigevent_t>) -> Result<()> {
+        self.queue.set_notification(sigevent)
+    }
+
+    /// Called after the current process closed a descriptor of this file.
+    /// Once the process has no descriptor referring to the queue any more,
+    /// its notification registration is removed.
+    pub fn on_close(&self, files: &FileTable) {
+        let still_open = files.table().iter().flatten().any(|entry| {
+            entry
+                .get_file()
+                .as_mq()
+                .map_or(false, |mq| Arc::ptr_eq(mq.queue(), &self.queue))
+        });
+        if !still_open {
+            let pid = current!().process().pid();
+            self.queue.remove_notification(pid);
+        }
+    }
+
+    pub fn attr(&self) -> mq_attr_t {
+        mq_attr_t {
+            mq_flags: self.status_flags.read().unwrap().bits() as i64,
+            mq_maxmsg: self.queue.maxmsg as i64,
+            mq_msgsize: self.queue.msgsize as i64,
+            mq_curmsgs: self.queue.num_msgs() as i64,
+            ..Default::default()
+        }
+    }
+
+    /// Only O_NONBLOCK in `mq_flags` can be changed.
+    pub fn set_attr(&self, new_attr: &mq_attr_t) -> Result<()> {
+        let new_flags = StatusFlags::from_bits_truncate(new_attr.mq_flags as u32);
+        *self.status_flags.write().unwrap() = new_flags & StatusFlags::O_NONBLOCK;
+        Ok(())
+    }
+}
+
+impl File for MqFile {
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(self.access_mode.clone())
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(self.status_flags.read().unwrap().clone())
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        let mut status_flags = self.status_flags.write().unwrap();
+        *status_flags = new_status_flags & StatusFlags::O_NONBLOCK;
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.queue.poll()
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(&self.queue.notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub trait AsMq {
+    fn as_mq(&self) -> Result<&MqFile>;
+}
+
+impl AsMq for FileRef {
+    fn as_mq(&self) -> Result<&MqFile> {
+        self.as_any()
+            .downcast_ref::<MqFile>()
+            .ok_or_else(|| errno!(EBADF, "not a message queue descriptor"))
+    }
+}
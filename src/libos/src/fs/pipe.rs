@@ -2,7 +2,6 @@ use atomic::{Atomic, Ordering};
 
 use super::channel::{Channel, Consumer, Producer};
 use super::*;
-use net::PollEventFlags;
 
 // TODO: Add F_SETPIPE_SZ in fcntl to dynamically change the size of pipe
 // to improve memory efficiency. This value is got from /proc/sys/fs/pipe-max-size on linux.
@@ -89,12 +88,6 @@ impl File for PipeReader {
         Ok(())
     }
 
-    fn poll(&self) -> Result<PollEventFlags> {
-        warn!("poll is not supported for pipe");
-        let events = PollEventFlags::empty();
-        Ok(events)
-    }
-
     fn poll_new(&self) -> IoEvents {
         self.consumer.poll()
     }
@@ -126,6 +119,10 @@ impl PipeReader {
     fn get_ready_len(&self) -> usize {
         self.consumer.ready_len()
     }
+
+    pub(super) fn consumer(&self) -> &Consumer<u8> {
+        &self.consumer
+    }
 }
 
 pub struct PipeWriter {
@@ -191,12 +188,6 @@ impl File for PipeWriter {
         Ok(())
     }
 
-    fn poll(&self) -> Result<PollEventFlags> {
-        warn!("poll is not supported for pipe");
-        let events = PollEventFlags::empty();
-        Ok(events)
-    }
-
     fn poll_new(&self) -> IoEvents {
         self.producer.poll()
     }
@@ -219,6 +210,12 @@ impl File for PipeWriter {
     }
 }
 
+impl PipeWriter {
+    pub(super) fn producer(&self) -> &Producer<u8> {
+        &self.producer
+    }
+}
+
 impl fmt::Debug for PipeReader {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PipeReader")
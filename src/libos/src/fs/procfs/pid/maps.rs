@@ -7,6 +7,9 @@ use crate::vm::{ChunkType, VMArea, VMPerms, VMRange};
 // Print format:
 // vmrange_start-vmrange_end, permission, shared/private, offset, device ID, inode, pathname
 //
+// The pathname of a mapping locked by mlock and its friends is followed by " (locked)", like
+// " (deleted)" following the pathname of a deleted file on Linux.
+//
 // Example:
 // - cat /proc/self/maps
 // 555555554000-555555556000 r--p 00000000 08:12 39321752                   /usr/bin/cat
@@ -86,7 +89,7 @@ impl ProcINode for ProcMapsINode {
     }
 }
 
-fn get_output_for_vma(vma: &VMArea, heap_or_stack: Option<&str>) -> String {
+pub(super) fn get_output_for_vma(vma: &VMArea, heap_or_stack: Option<&str>) -> String {
     let range = vma.range();
     let perms = vma.perms();
 
@@ -105,8 +108,9 @@ fn get_output_for_vma(vma: &VMArea, heap_or_stack: Option<&str>) -> String {
     };
 
     let shared = vma.writeback_file().is_some();
+    let locked = vma.is_locked();
     print_each_map(
-        range, perms, shared, offset, device_id, inode_num, file_path,
+        range, perms, shared, offset, device_id, inode_num, file_path, locked,
     )
 }
 
@@ -118,9 +122,10 @@ fn print_each_map(
     device_id: usize,
     inode_num: usize,
     file_path: &str,
+    locked: bool,
 ) -> String {
     let result_str = format!(
-        "{:x}-{:x} {}{} {:08x} {} {}      {}\n",
+        "{:x}-{:x} {}{} {:08x} {} {}      {}{}\n",
         range.start(),
         range.end(),
         perms.display(),
@@ -128,7 +133,8 @@ fn print_each_map(
         offset,
         device_id,
         inode_num,
-        file_path
+        file_path,
+        if locked { " (locked)" } else { "" }
     );
     result_str
 }
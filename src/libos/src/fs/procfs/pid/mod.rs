@@ -9,7 +9,11 @@ use self::exe::ProcExeSymINode;
 use self::fd::LockedProcFdDirINode;
 use self::maps::ProcMapsINode;
 use self::root::ProcRootSymINode;
+use self::smaps::ProcSmapsINode;
+use self::smaps_rollup::ProcSmapsRollupINode;
 use self::stat::ProcStatINode;
+use self::statm::ProcStatmINode;
+use self::status::ProcStatusINode;
 
 mod cmdline;
 mod comm;
@@ -18,7 +22,11 @@ mod exe;
 mod fd;
 mod maps;
 mod root;
+mod smaps;
+mod smaps_rollup;
 mod stat;
+mod statm;
+mod status;
 
 pub struct LockedPidDirINode(RwLock<PidDirINode>);
 
@@ -65,6 +73,19 @@ impl LockedPidDirINode {
         // maps
         let maps_inode = ProcMapsINode::new(&file.process_ref);
         file.entries.insert(String::from("maps"), maps_inode);
+        // status
+        let status_inode = ProcStatusINode::new(&file.process_ref);
+        file.entries.insert(String::from("status"), status_inode);
+        // statm
+        let statm_inode = ProcStatmINode::new(&file.process_ref);
+        file.entries.insert(String::from("statm"), statm_inode);
+        // smaps
+        let smaps_inode = ProcSmapsINode::new(&file.process_ref);
+        file.entries.insert(String::from("smaps"), smaps_inode);
+        // smaps_rollup
+        let smaps_rollup_inode = ProcSmapsRollupINode::new(&file.process_ref);
+        file.entries
+            .insert(String::from("smaps_rollup"), smaps_rollup_inode);
 
         Ok(())
     }
@@ -0,0 +1,110 @@
+use super::*;
+
+use super::maps::get_output_for_vma;
+use crate::vm::{MemStat, VMAdvice, VMArea, PAGE_SIZE};
+
+// This file is to implement /proc/self(pid)/smaps file system.
+//
+// Print format:
+// The line of the VMA in /proc/self(pid)/maps, followed by the memory usage of the VMA.
+//
+// Example:
+// 7ffff7536000-7ffff7558000 rw-p 00000000 0 0
+// Size:                136 kB
+// KernelPageSize:        4 kB
+// MMUPageSize:           4 kB
+// Rss:                 136 kB
+// Pss:                 136 kB
+// Shared_Clean:          0 kB
+// Shared_Dirty:          0 kB
+// Private_Clean:         0 kB
+// Private_Dirty:       136 kB
+// Referenced:          136 kB
+// Anonymous:           136 kB
+// Swap:                  0 kB
+// Locked:                0 kB
+// VmFlags: rd wr
+//
+// Known limitation:
+// - The memory is committed when it is mapped, so Rss is always the same as Size
+// - Whether the memory is clean is unknown, so all the memory is taken as dirty
+
+pub struct ProcSmapsINode(ProcessRef);
+
+const KB: usize = 1024;
+
+impl ProcSmapsINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcSmapsINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let process_vm = main_thread.vm();
+        let heap_range = process_vm.heap_range();
+        let stack_range = process_vm.stack_range();
+
+        let mut result_string = String::new();
+        process_vm.for_each_vma(self.0.pid(), |vma| {
+            let heap_or_stack = if vma.range() == heap_range {
+                Some(" [heap]")
+            } else if vma.range() == stack_range {
+                Some(" [stack]")
+            } else {
+                None
+            };
+            result_string += &get_output_for_vma(vma, heap_or_stack);
+            result_string += &get_output_for_mem_stat(&process_vm.vma_mem_stat(vma));
+            result_string += &get_output_for_vm_flags(vma);
+        });
+        Ok(result_string.into_bytes())
+    }
+}
+
+pub(super) fn get_output_for_mem_stat(stat: &MemStat) -> String {
+    let private = stat.size - stat.shared_with_others;
+    let fields = [
+        ("Size:", stat.size),
+        ("KernelPageSize:", PAGE_SIZE),
+        ("MMUPageSize:", PAGE_SIZE),
+        ("Rss:", stat.size),
+        ("Pss:", stat.proportional_size),
+        ("Shared_Clean:", 0),
+        ("Shared_Dirty:", stat.shared_with_others),
+        ("Private_Clean:", 0),
+        ("Private_Dirty:", private),
+        ("Referenced:", stat.size),
+        ("Anonymous:", stat.anonymous),
+        ("Swap:", 0),
+        ("Locked:", stat.locked),
+    ];
+    fields
+        .iter()
+        .map(|(name, size)| format!("{:<16}{:>8} kB\n", name, size / KB))
+        .collect()
+}
+
+// Only the flags that make sense for the VMAs of the LibOS are shown, in the same
+// order as Linux.
+fn get_output_for_vm_flags(vma: &VMArea) -> String {
+    let perms = vma.perms();
+    let advice = vma.advice();
+    let flags = [
+        ("rd", perms.can_read()),
+        ("wr", perms.can_write()),
+        ("ex", perms.can_execute()),
+        ("sh", vma.writeback_file().is_some()),
+        ("lo", vma.is_locked()),
+        ("sr", advice.contains(VMAdvice::SEQ_READ)),
+        ("rr", advice.contains(VMAdvice::RAND_READ)),
+        ("dc", advice.contains(VMAdvice::DONT_COPY)),
+        ("wf", advice.contains(VMAdvice::WIPE_ON_FORK)),
+    ];
+    flags
+        .iter()
+        .filter(|(_, is_set)| *is_set)
+        .fold(String::from("VmFlags:"), |acc, (name, _)| acc + " " + name)
+        + "\n"
+}
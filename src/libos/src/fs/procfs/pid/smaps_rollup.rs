@@ -0,0 +1,49 @@
+use super::*;
+
+use super::smaps::get_output_for_mem_stat;
+use crate::vm::MemStat;
+
+// This file is to implement /proc/self(pid)/smaps_rollup file system.
+//
+// Print format:
+// The fields in /proc/self(pid)/smaps summed up for all the VMAs, following a
+// header line with the lowest start and the highest end of the VMAs.
+//
+// Example:
+// 555555554000-7ffffffff000 ---p 00000000 0 0      [rollup]
+// Size:              38208 kB
+// ...
+
+pub struct ProcSmapsRollupINode(ProcessRef);
+
+impl ProcSmapsRollupINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcSmapsRollupINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let process_vm = main_thread.vm();
+
+        let mut stat = MemStat::default();
+        let (mut start, mut end) = (usize::MAX, 0);
+        process_vm.for_each_vma(self.0.pid(), |vma| {
+            start = start.min(vma.start());
+            end = end.max(vma.end());
+            stat += process_vm.vma_mem_stat(vma);
+        });
+        if start > end {
+            start = 0;
+        }
+
+        let result_string = format!(
+            "{:x}-{:x} ---p 00000000 0 0      [rollup]\n{}",
+            start,
+            end,
+            get_output_for_mem_stat(&stat)
+        );
+        Ok(result_string.into_bytes())
+    }
+}
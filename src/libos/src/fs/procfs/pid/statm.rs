@@ -0,0 +1,34 @@
+use super::*;
+
+use crate::vm::PAGE_SIZE;
+
+// This file is to implement /proc/self(pid)/statm file system.
+//
+// Print format (in pages):
+// size resident shared text lib data dt
+//
+// The lib and dt fields are always 0 as Linux 2.6 and later.
+
+pub struct ProcStatmINode(ProcessRef);
+
+impl ProcStatmINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcStatmINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let stat = main_thread.vm().mem_stat(self.0.pid());
+        let result = format!(
+            "{} {} {} {} 0 {} 0\n",
+            stat.size / PAGE_SIZE,
+            stat.size / PAGE_SIZE,
+            (stat.file + stat.shared) / PAGE_SIZE,
+            stat.elf / PAGE_SIZE,
+            (stat.data + stat.stack) / PAGE_SIZE,
+        );
+        Ok(result.into_bytes())
+    }
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+// This file is to implement /proc/self(pid)/status file system.
+//
+// Besides the fields of Linux, the last four fields show the chunks used by
+// the process and the free space in the multi-VMA chunks, which tells how
+// fragmented the chunks are.
+//
+// All the memory of a mapping is committed when it is mapped, so VmRSS is the
+// same as VmSize.
+
+pub struct ProcStatusINode(ProcessRef);
+
+const KB: usize = 1024;
+
+impl ProcStatusINode {
+    pub fn new(process_ref: &ProcessRef) -> Arc<dyn INode> {
+        Arc::new(File::new(Self(Arc::clone(process_ref))))
+    }
+}
+
+impl ProcINode for ProcStatusINode {
+    fn generate_data_in_bytes(&self) -> vfs::Result<Vec<u8>> {
+        let main_thread = self.0.main_thread().ok_or(FsError::EntryNotFound)?;
+        let pid = self.0.pid();
+        let name = String::from_utf8_lossy(main_thread.name().as_c_str().to_bytes()).into_owned();
+        let state = match self.0.status() {
+            ProcessStatus::Running => "R (running)",
+            ProcessStatus::Stopped => "T (stopped)",
+            ProcessStatus::Zombie => "Z (zombie)",
+        };
+        let ppid = self.0.parent().pid();
+        let num_threads = self.0.threads().len();
+        let stat = main_thread.vm().mem_stat(pid);
+
+        let result = format!(
+            "Name:\t{}\n\
+             State:\t{}\n\
+             Tgid:\t{}\n\
+             Pid:\t{}\n\
+             PPid:\t{}\n\
+             VmSize:\t{:>8} kB\n\
+             VmLck:\t{:>8} kB\n\
+             VmRSS:\t{:>8} kB\n\
+             RssAnon:\t{:>8} kB\n\
+             RssFile:\t{:>8} kB\n\
+             RssShmem:\t{:>8} kB\n\
+             VmData:\t{:>8} kB\n\
+             VmStk:\t{:>8} kB\n\
+             VmExe:\t{:>8} kB\n\
+             Threads:\t{}\n\
+             SingleVMAChunks:\t{}\n\
+             MultiVMAChunks:\t{}\n\
+             MultiVMAChunksSize:\t{:>8} kB\n\
+             MultiVMAChunksFree:\t{:>8} kB\n",
+            name,
+            state,
+            pid,
+            pid,
+            ppid,
+            stat.size / KB,
+            stat.locked / KB,
+            stat.size / KB,
+            stat.anonymous / KB,
+            stat.file / KB,
+            stat.shared / KB,
+            stat.data / KB,
+            stat.stack / KB,
+            stat.elf / KB,
+            num_threads,
+            stat.single_vma_chunks,
+            stat.multi_vma_chunks,
+            stat.multi_vma_chunks_size / KB,
+            stat.multi_vma_chunks_free / KB,
+        );
+        Ok(result.into_bytes())
+    }
+}
@@ -1,4 +1,5 @@
 use super::dev_fs;
+use super::file_ops::register_sefs_xattr_storage;
 use super::hostfs::HostFS;
 use super::procfs::ProcFS;
 use super::sefs::{SgxStorage, SgxUuidProvider};
@@ -132,7 +133,13 @@ pub fn mount_nonroot_fs_according_to(
                     return_errno!(EINVAL, "Source is expected for HostFS");
                 }
 
-                let hostfs = HostFS::new(source_path.unwrap());
+                // Only the user xattrs of the host files are accessible by default
+                let xattr_namespace = match &mc.options.xattr_namespace {
+                    Some(namespace) => XattrNamespace::from_str(namespace)
+                        .map_err(|_| errno!(EINVAL, "invalid xattr namespace for HostFS"))?,
+                    None => XattrNamespace::User,
+                };
+                let hostfs = HostFS::new(source_path.unwrap(), xattr_namespace);
                 mount_fs_at(hostfs, root, &mc.target, follow_symlink)?;
             }
             TYPE_RAMFS => {
@@ -264,5 +271,10 @@ fn open_or_create_sefs_according_to(
             &SgxUuidProvider,
         )?
     };
+    // The xattr records are saved beside the files of a writable SEFS
+    if root_mac.is_none() {
+        let xattr_storage = SgxStorage::new(source_path, user_key, &None, None)?;
+        register_sefs_xattr_storage(&sefs, source_path, xattr_storage);
+    }
     Ok(sefs)
 }
@@ -0,0 +1,366 @@
+use super::*;
+
+use crate::events::{Observer, Waiter, WaiterQueue};
+use crate::process::{Process, Thread, ThreadRef};
+use crate::signal::{
+    dequeue_pending_signal, siginfo_t, SigNum, SigSet, Signal, SIGBUS, SIGCHLD, SIGFPE, SIGILL,
+    SIGKILL, SIGSEGV, SIGSTOP, SIGTRAP, SI_TIMER,
+};
+use std::sync::Weak;
+
+/// The record read from a signalfd, which is `struct signalfd_siginfo` in C
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct signalfd_siginfo_t {
+    ssi_signo: u32,
+    ssi_errno: i32,
+    ssi_code: i32,
+    ssi_pid: u32,
+    ssi_uid: u32,
+    ssi_fd: i32,
+    ssi_tid: u32,
+    ssi_band: u32,
+    ssi_overrun: u32,
+    ssi_trapno: u32,
+    ssi_status: i32,
+    ssi_int: i32,
+    ssi_ptr: u64,
+    ssi_utime: u64,
+    ssi_stime: u64,
+    ssi_addr: u64,
+    ssi_addr_lsb: u16,
+    __pad2: u16,
+    ssi_syscall: i32,
+    ssi_call_addr: u64,
+    ssi_arch: u32,
+    __pad: [u8; 28],
+}
+
+impl signalfd_siginfo_t {
+    fn from_siginfo(info: &siginfo_t) -> Self {
+        let mut ssi: Self = unsafe { std::mem::zeroed() };
+        ssi.ssi_signo = info.si_signo as u32;
+        ssi.ssi_errno = info.si_errno;
+        ssi.ssi_code = info.si_code;
+
+        let signum = SigNum::from_u8(info.si_signo as u8).unwrap();
+        let is_fault = info.si_code > 0
+            && (signum == SIGSEGV
+                || signum == SIGBUS
+                || signum == SIGILL
+                || signum == SIGFPE
+                || signum == SIGTRAP);
+        if is_fault {
+            ssi.ssi_addr = info.si_addr() as u64;
+            ssi.ssi_addr_lsb = info.si_addr_lsb() as u16;
+        } else if info.si_code == SI_TIMER {
+            ssi.ssi_tid = info.si_timerid() as u32;
+            ssi.ssi_overrun = info.si_overrune() as u32;
+            ssi.set_value(info);
+        } else if signum == SIGCHLD && info.si_code > 0 {
+            ssi.ssi_pid = info.si_pid() as u32;
+            ssi.ssi_uid = info.si_uid() as u32;
+            ssi.ssi_status = info.si_status();
+            ssi.ssi_utime = info.si_utime() as u64;
+            ssi.ssi_stime = info.si_stime() as u64;
+        } else {
+            ssi.ssi_pid = info.si_pid() as u32;
+            ssi.ssi_uid = info.si_uid() as u32;
+            ssi.set_value(info);
+        }
+        ssi
+    }
+
+    fn set_value(&mut self, info: &siginfo_t) {
+        // The value is a union of an int and a pointer
+        let value: u64 = unsafe { std::mem::transmute(info.si_value()) };
+        self.ssi_int = value as i32;
+        self.ssi_ptr = value;
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const Self as *const u8,
+                std::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
+
+bitflags! {
+    pub struct SignalFileFlags: i32 {
+        /// Non-blocking
+        const SFD_NONBLOCK = 1 << 11;
+        /// Close on exec
+        const SFD_CLOEXEC = 1 << 19;
+    }
+}
+
+/// File for accepting the signals targeted at the caller
+#[derive(Debug)]
+pub struct SignalFile {
+    observer: Arc<SigQueuesObserver>,
+    // The thread that creates the file, whose signals are polled
+    owner: Weak<Thread>,
+    owner_process: Weak<Process>,
+    // The signals are those of the task that reads the file, so the signal
+    // queues of a task are observed since its first read of the file.
+    observed: SgxMutex<ObservedTasks>,
+    status_flags: RwLock<StatusFlags>,
+}
+
+#[derive(Debug, Default)]
+struct ObservedTasks {
+    threads: Vec<Weak<Thread>>,
+    processes: Vec<Weak<Process>>,
+}
+
+/// The observer of the signal queues, which turns the arrival of the signals
+/// of interest into the I/O events of the file.
+struct SigQueuesObserver {
+    mask: RwLock<SigSet>,
+    waiter_queue: WaiterQueue,
+    notifier: IoNotifier,
+}
+
+impl Observer<SigNum> for SigQueuesObserver {
+    fn on_event(&self, signum: &SigNum, _metadata: &Option<Weak<dyn Any + Send + Sync>>) {
+        if !self.mask.read().unwrap().contains(*signum) {
+            return;
+        }
+        self.waiter_queue.dequeue_and_wake_all();
+        self.notifier.broadcast(&IoEvents::IN);
+    }
+}
+
+impl std::fmt::Debug for SigQueuesObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigQueuesObserver")
+            .field("mask", &*self.mask.read().unwrap())
+            .finish()
+    }
+}
+
+impl SignalFile {
+    pub fn new(mask: SigSet, flags: SignalFileFlags) -> Self {
+        let observer = Arc::new(SigQueuesObserver {
+            mask: RwLock::new(Self::valid_mask(mask)),
+            waiter_queue: WaiterQueue::new(),
+            notifier: IoNotifier::new(),
+        });
+        let status_flags = if flags.contains(SignalFileFlags::SFD_NONBLOCK) {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        };
+        let thread = current!();
+        let file = Self {
+            observer,
+            owner: Arc::downgrade(&thread),
+            owner_process: Arc::downgrade(thread.process()),
+            observed: SgxMutex::new(ObservedTasks::default()),
+            status_flags: RwLock::new(status_flags),
+        };
+        file.observe(&thread);
+        file
+    }
+
+    /// Observe the signal queues of the thread and its process if they are
+    /// not observed yet.
+    fn observe(&self, thread: &ThreadRef) {
+        let weak_observer = Arc::downgrade(&self.observer) as Weak<dyn Observer<_>>;
+        let mut observed = self.observed.lock().unwrap();
+
+        let weak_thread = Arc::downgrade(thread);
+        observed.threads.retain(|thread| thread.strong_count() > 0);
+        if !observed
+            .threads
+            .iter()
+            .any(|observed_thread| Weak::ptr_eq(observed_thread, &weak_thread))
+        {
+            thread.sig_queues().read().unwrap().notifier().register(
+                weak_observer.clone(),
+                None,
+                None,
+            );
+            observed.threads.push(weak_thread);
+        }
+
+        let process = thread.process();
+        let weak_process = Arc::downgrade(process);
+        observed
+            .processes
+            .retain(|process| process.strong_count() > 0);
+        if !observed
+            .processes
+            .iter()
+            .any(|observed_process| Weak::ptr_eq(observed_process, &weak_process))
+        {
+            process
+                .sig_queues()
+                .read()
+                .unwrap()
+                .notifier()
+                .register(weak_observer, None, None);
+            observed.processes.push(weak_process);
+        }
+    }
+
+    pub fn mask(&self) -> SigSet {
+        *self.observer.mask.read().unwrap()
+    }
+
+    pub fn set_mask(&self, mask: SigSet) {
+        *self.observer.mask.write().unwrap() = Self::valid_mask(mask);
+        // Signals of the new mask may be pending already
+        if !self.poll_new().is_empty() {
+            self.observer.notifier.broadcast(&IoEvents::IN);
+        }
+    }
+
+    // SIGKILL and SIGSTOP cannot be received via a signalfd
+    fn valid_mask(mut mask: SigSet) -> SigSet {
+        mask -= SIGKILL;
+        mask -= SIGSTOP;
+        mask
+    }
+
+    // Whether any signal of interest is pending for the thread or the process
+    fn has_pending(&self, thread: Option<&ThreadRef>, process: Option<&Arc<Process>>) -> bool {
+        let mut pending = SigSet::new_empty();
+        if let Some(thread) = thread {
+            pending |= thread.sig_queues().read().unwrap().pending();
+        }
+        if let Some(process) = process {
+            pending |= process.sig_queues().read().unwrap().pending();
+        }
+        !(pending & self.mask()).empty()
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.status_flags
+            .read()
+            .unwrap()
+            .contains(StatusFlags::O_NONBLOCK)
+    }
+}
+
+impl File for SignalFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        const SSI_SIZE: usize = std::mem::size_of::<signalfd_siginfo_t>();
+        if buf.len() < SSI_SIZE {
+            return_errno!(EINVAL, "the buffer is too small for a signalfd_siginfo");
+        }
+
+        let thread = current!();
+        let process = thread.process().clone();
+        self.observe(&thread);
+        let waiter = Waiter::new();
+        let mut len = 0;
+        loop {
+            let mask = self.mask();
+            while len + SSI_SIZE <= buf.len() {
+                let signal = match dequeue_pending_signal(&mask, &thread, &process) {
+                    Some(signal) => signal,
+                    None => break,
+                };
+                let ssi = signalfd_siginfo_t::from_siginfo(&signal.to_info());
+                buf[len..len + SSI_SIZE].copy_from_slice(ssi.as_bytes());
+                len += SSI_SIZE;
+            }
+            if len > 0 {
+                return Ok(len);
+            }
+            if self.is_nonblocking() {
+                return_errno!(EAGAIN, "no signals of interest are pending");
+            }
+
+            self.observer.waiter_queue.reset_and_enqueue(&waiter);
+            // Check again in case that a signal arrived before the waiter is enqueued
+            if self.has_pending(Some(&thread), Some(&process)) {
+                continue;
+            }
+            waiter.wait(None)?;
+        }
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDONLY)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(self.status_flags.read().unwrap().clone())
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        let mut status_flags = self.status_flags.write().unwrap();
+        status_flags.remove(STATUS_FLAGS_MASK);
+        status_flags.insert(new_status_flags & STATUS_FLAGS_MASK);
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        // The readiness is that of the owner, no matter which thread polls the
+        // file, e.g., a thread waiting on an epoll file.
+        let owner = self.owner.upgrade();
+        let owner_process = self.owner_process.upgrade();
+        if self.has_pending(owner.as_ref(), owner_process.as_ref()) {
+            IoEvents::IN
+        } else {
+            IoEvents::empty()
+        }
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(&self.observer.notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Drop for SignalFile {
+    fn drop(&mut self) {
+        let weak_observer = Arc::downgrade(&self.observer) as Weak<dyn Observer<_>>;
+        let observed = self.observed.lock().unwrap();
+        for thread in observed
+            .threads
+            .iter()
+            .filter_map(|thread| thread.upgrade())
+        {
+            thread
+                .sig_queues()
+                .read()
+                .unwrap()
+                .notifier()
+                .unregister(&weak_observer);
+        }
+        for process in observed
+            .processes
+            .iter()
+            .filter_map(|process| process.upgrade())
+        {
+            process
+                .sig_queues()
+                .read()
+                .unwrap()
+                .notifier()
+                .unregister(&weak_observer);
+        }
+    }
+}
+
+pub trait AsSignal {
+    fn as_signal(&self) -> Result<&SignalFile>;
+}
+
+impl AsSignal for FileRef {
+    fn as_signal(&self) -> Result<&SignalFile> {
+        self.as_any()
+            .downcast_ref::<SignalFile>()
+            .ok_or_else(|| errno!(EINVAL, "not a signal file"))
+    }
+}
@@ -1,15 +1,22 @@
+use super::aio::{self, aio_context_t, io_event_t, iocb_t};
 use super::event_file::EventCreationFlags;
 use super::file_ops;
 use super::file_ops::{
     get_abs_path_by_fd, get_utimes, AccessibilityCheckFlags, AccessibilityCheckMode, ChownFlags,
-    FcntlCmd, FsPath, LinkFlags, StatFlags, UnlinkFlags, Utime, UtimeFlags, AT_FDCWD, UTIME_OMIT,
+    FcntlCmd, FsPath, LinkFlags, SpliceFlags, StatFlags, UnlinkFlags, Utime, UtimeFlags,
+    XattrFlags, XattrTarget, AT_FDCWD, UTIME_OMIT,
 };
 use super::fs_ops;
 use super::fs_ops::{MountFlags, MountOptions, UmountFlags};
-use super::time::{clockid_t, itimerspec_t, timespec_t, timeval_t, ClockID};
+use super::io_uring::{self, io_uring_params};
+use super::memfd;
+use super::mq_file;
+use super::time::{clockid_t, do_clock_gettime, itimerspec_t, timespec_t, timeval_t, ClockID};
 use super::timer_file::{TimerCreationFlags, TimerSetFlags};
 use super::*;
 use crate::config::{user_rootfs_config, ConfigApp, ConfigMountFsType};
+use crate::signal::{sigevent_t, sigset_t, SigSet};
+use std::time::Duration;
 use util::mem_util::from_user;
 
 #[allow(non_camel_case_types)]
@@ -99,6 +106,219 @@ pub fn do_timerfd_gettime(fd: FileDesc, curr_value_ptr: *mut itimerspec_t) -> Re
     Ok(0)
 }
 
+pub fn do_mq_open(
+    name: *const i8,
+    oflag: u32,
+    mode: u16,
+    attr_ptr: *const mq_attr_t,
+) -> Result<isize> {
+    let name = clone_mq_name_safely(name)?;
+    let attr = if !attr_ptr.is_null() && (oflag & CreationFlags::O_CREAT.bits()) != 0 {
+        from_user::check_ptr(attr_ptr)?;
+        Some(unsafe { &*attr_ptr })
+    } else {
+        None
+    };
+    debug!(
+        "mq_open: name: {:?}, oflag: {:#o}, mode: {:#o}, attr: {:?}",
+        name, oflag, mode, attr
+    );
+
+    let current = current!();
+    let mode = FileMode::from_bits_truncate(mode) & !current.process().umask();
+    let file_ref: Arc<dyn File> = Arc::new(mq_file::mq_open(&name, oflag, mode, attr)?);
+    let fd = current.add_file(
+        file_ref,
+        CreationFlags::from_bits_truncate(oflag).must_close_on_spawn(),
+    );
+    Ok(fd as isize)
+}
+
+pub fn do_mq_unlink(name: *const i8) -> Result<isize> {
+    let name = clone_mq_name_safely(name)?;
+    debug!("mq_unlink: name: {:?}", name);
+    mq_file::mq_unlink(&name)?;
+    Ok(0)
+}
+
+pub fn do_mq_timedsend(
+    mqdes: FileDesc,
+    msg_ptr: *const u8,
+    msg_len: usize,
+    msg_prio: u32,
+    abs_timeout: *const timespec_t,
+) -> Result<isize> {
+    from_user::check_array(msg_ptr, msg_len)?;
+    let msg = unsafe { std::slice::from_raw_parts(msg_ptr, msg_len) };
+    let timeout = mq_timeout_from_user(abs_timeout)?;
+
+    let file_ref = current!().file(mqdes)?;
+    file_ref.as_mq()?.send(msg, msg_prio, timeout)?;
+    Ok(0)
+}
+
+pub fn do_mq_timedreceive(
+    mqdes: FileDesc,
+    msg_ptr: *mut u8,
+    msg_len: usize,
+    msg_prio: *mut u32,
+    abs_timeout: *const timespec_t,
+) -> Result<isize> {
+    from_user::check_mut_array(msg_ptr, msg_len)?;
+    let buf = unsafe { std::slice::from_raw_parts_mut(msg_ptr, msg_len) };
+    if !msg_prio.is_null() {
+        from_user::check_mut_ptr(msg_prio)?;
+    }
+    let timeout = mq_timeout_from_user(abs_timeout)?;
+
+    let file_ref = current!().file(mqdes)?;
+    let (len, prio) = file_ref.as_mq()?.receive(buf, timeout)?;
+    if !msg_prio.is_null() {
+        unsafe {
+            msg_prio.write(prio);
+        }
+    }
+    Ok(len as isize)
+}
+
+pub fn do_mq_notify(mqdes: FileDesc, sevp: *const sigevent_t) -> Result<isize> {
+    let sigevent = if !sevp.is_null() {
+        from_user::check_ptr(sevp)?;
+        Some(unsafe { &*sevp })
+    } else {
+        None
+    };
+    debug!("mq_notify: mqdes: {:?}, sigevent: {:?}", mqdes, sigevent);
+
+    let file_ref = current!().file(mqdes)?;
+    file_ref.as_mq()?.set_notification(sigevent)?;
+    Ok(0)
+}
+
+pub fn do_mq_getsetattr(
+    mqdes: FileDesc,
+    new_attr: *const mq_attr_t,
+    old_attr: *mut mq_attr_t,
+) -> Result<isize> {
+    let file_ref = current!().file(mqdes)?;
+    let mq_file = file_ref.as_mq()?;
+    if !old_attr.is_null() {
+        from_user::check_mut_ptr(old_attr)?;
+        unsafe {
+            old_attr.write(mq_file.attr());
+        }
+    }
+    if !new_attr.is_null() {
+        from_user::check_ptr(new_attr)?;
+        mq_file.set_attr(unsafe { &*new_attr })?;
+    }
+    Ok(0)
+}
+
+fn clone_mq_name_safely(name: *const i8) -> Result<String> {
+    let name = from_user::clone_cstring_safely(name)?
+        .to_string_lossy()
+        .into_owned();
+    // The C library strips the leading slash, but tolerate it if it is kept
+    Ok(name.strip_prefix('/').unwrap_or(&name).to_string())
+}
+
+/// Convert the absolute timeout (against CLOCK_REALTIME) to a relative one.
+fn mq_timeout_from_user(abs_timeout: *const timespec_t) -> Result<Option<Duration>> {
+    if abs_timeout.is_null() {
+        return Ok(None);
+    }
+    from_user::check_ptr(abs_timeout)?;
+    let abs_timeout = timespec_t::from_raw_ptr(abs_timeout)?.as_duration();
+    let now = do_clock_gettime(ClockID::CLOCK_REALTIME)?.as_duration();
+    Ok(Some(
+        abs_timeout
+            .checked_sub(now)
+            .unwrap_or_else(|| Duration::from_secs(0)),
+    ))
+}
+
+pub fn do_inotify_init() -> Result<isize> {
+    do_inotify_init1(0)
+}
+
+pub fn do_inotify_init1(flags: i32) -> Result<isize> {
+    debug!("inotify_init1: flags: {:#x}", flags);
+
+    let flags = InotifyFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    let file_ref: Arc<dyn File> = Arc::new(InotifyFile::new(flags));
+    let fd = current!().add_file(file_ref, flags.contains(InotifyFlags::IN_CLOEXEC));
+    Ok(fd as isize)
+}
+
+pub fn do_inotify_add_watch(fd: FileDesc, path: *const i8, mask: u32) -> Result<isize> {
+    let path = from_user::clone_cstring_safely(path)?
+        .to_string_lossy()
+        .into_owned();
+    let mask = InotifyMask::from_bits_truncate(mask);
+    debug!(
+        "inotify_add_watch: fd: {}, path: {:?}, mask: {:?}",
+        fd, path, mask
+    );
+
+    let current = current!();
+    let file_ref = current.file(fd)?;
+    let inotify_file = file_ref.as_inotify()?;
+    let inode = {
+        let abs_path = FsPath::new(&path, AT_FDCWD, false)?.to_abs_path()?;
+        let fs = current.fs().read().unwrap();
+        if mask.contains(InotifyMask::IN_DONT_FOLLOW) {
+            fs.lookup_inode_no_follow(&abs_path)?
+        } else {
+            fs.lookup_inode(&abs_path)?
+        }
+    };
+    let wd = inotify_file.add_watch(&inode, mask)?;
+    Ok(wd as isize)
+}
+
+pub fn do_inotify_rm_watch(fd: FileDesc, wd: i32) -> Result<isize> {
+    debug!("inotify_rm_watch: fd: {}, wd: {}", fd, wd);
+
+    let file_ref = current!().file(fd)?;
+    file_ref.as_inotify()?.rm_watch(wd)?;
+    Ok(0)
+}
+
+pub fn do_signalfd(fd: c_int, mask_ptr: *const sigset_t, sizemask: usize) -> Result<isize> {
+    do_signalfd4(fd, mask_ptr, sizemask, 0)
+}
+
+pub fn do_signalfd4(
+    fd: c_int,
+    mask_ptr: *const sigset_t,
+    sizemask: usize,
+    flags: i32,
+) -> Result<isize> {
+    if sizemask != std::mem::size_of::<sigset_t>() {
+        return_errno!(EINVAL, "invalid size of mask");
+    }
+    from_user::check_ptr(mask_ptr)?;
+    let mask = SigSet::from_c(unsafe { *mask_ptr });
+    let flags = SignalFileFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    debug!(
+        "signalfd4: fd: {}, mask: {:?}, flags: {:?}",
+        fd, mask, flags
+    );
+
+    let current = current!();
+    if fd == -1 {
+        let file_ref: Arc<dyn File> = Arc::new(SignalFile::new(mask, flags));
+        let fd = current.add_file(file_ref, flags.contains(SignalFileFlags::SFD_CLOEXEC));
+        Ok(fd as isize)
+    } else {
+        // Replace the mask of the existing signalfd
+        let file_ref = current.file(fd as FileDesc)?;
+        file_ref.as_signal()?.set_mask(mask);
+        Ok(fd as isize)
+    }
+}
+
 pub fn do_creat(path: *const i8, mode: u16) -> Result<isize> {
     let flags =
         AccessMode::O_WRONLY as u32 | (CreationFlags::O_CREAT | CreationFlags::O_TRUNC).bits();
@@ -119,6 +339,18 @@ pub fn do_openat(dirfd: i32, path: *const i8, flags: u32, mode: u16) -> Result<i
     Ok(fd as isize)
 }
 
+pub fn do_memfd_create(name: *const i8, flags: u32) -> Result<isize> {
+    let name = from_user::clone_cstring_safely(name)?
+        .to_string_lossy()
+        .into_owned();
+    let flags = MemfdFlags::from_bits(flags).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    debug!("memfd_create: name: {:?}, flags: {:?}", name, flags);
+
+    let file_ref: Arc<dyn File> = Arc::new(memfd::create_memfd(&name, flags)?);
+    let fd = current!().add_file(file_ref, flags.contains(MemfdFlags::MFD_CLOEXEC));
+    Ok(fd as isize)
+}
+
 pub fn do_umask(mask: u16) -> Result<isize> {
     let new_mask = FileMode::from_bits_truncate(mask).to_umask();
     let old_mask = current!().process().set_umask(new_mask);
@@ -620,6 +852,154 @@ pub fn do_lchown(path: *const i8, uid: u32, gid: u32) -> Result<isize> {
     )
 }
 
+pub fn do_setxattr(
+    path: *const i8,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    setxattr_common(&XattrTarget::Path(&path), name, value, size, flags)
+}
+
+pub fn do_lsetxattr(
+    path: *const i8,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    setxattr_common(&XattrTarget::LPath(&path), name, value, size, flags)
+}
+
+pub fn do_fsetxattr(
+    fd: FileDesc,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    setxattr_common(&XattrTarget::Fd(fd), name, value, size, flags)
+}
+
+fn setxattr_common(
+    target: &XattrTarget,
+    name: *const i8,
+    value: *const u8,
+    size: usize,
+    flags: i32,
+) -> Result<isize> {
+    let name = clone_xattr_name_from_user(name)?;
+    let value: &[u8] = if size == 0 {
+        &[]
+    } else {
+        from_user::check_array(value, size)?;
+        unsafe { std::slice::from_raw_parts(value, size) }
+    };
+    let flags = XattrFlags::from_i32(flags)?;
+    file_ops::do_setxattr(target, &name, value, flags)?;
+    Ok(0)
+}
+
+pub fn do_getxattr(path: *const i8, name: *const i8, value: *mut u8, size: usize) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    getxattr_common(&XattrTarget::Path(&path), name, value, size)
+}
+
+pub fn do_lgetxattr(
+    path: *const i8,
+    name: *const i8,
+    value: *mut u8,
+    size: usize,
+) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    getxattr_common(&XattrTarget::LPath(&path), name, value, size)
+}
+
+pub fn do_fgetxattr(fd: FileDesc, name: *const i8, value: *mut u8, size: usize) -> Result<isize> {
+    getxattr_common(&XattrTarget::Fd(fd), name, value, size)
+}
+
+fn getxattr_common(
+    target: &XattrTarget,
+    name: *const i8,
+    value: *mut u8,
+    size: usize,
+) -> Result<isize> {
+    let name = clone_xattr_name_from_user(name)?;
+    let value: &mut [u8] = if size == 0 {
+        &mut []
+    } else {
+        from_user::check_mut_array(value, size)?;
+        unsafe { std::slice::from_raw_parts_mut(value, size) }
+    };
+    let len = file_ops::do_getxattr(target, &name, value)?;
+    Ok(len as isize)
+}
+
+pub fn do_listxattr(path: *const i8, list: *mut u8, size: usize) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    listxattr_common(&XattrTarget::Path(&path), list, size)
+}
+
+pub fn do_llistxattr(path: *const i8, list: *mut u8, size: usize) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    listxattr_common(&XattrTarget::LPath(&path), list, size)
+}
+
+pub fn do_flistxattr(fd: FileDesc, list: *mut u8, size: usize) -> Result<isize> {
+    listxattr_common(&XattrTarget::Fd(fd), list, size)
+}
+
+fn listxattr_common(target: &XattrTarget, list: *mut u8, size: usize) -> Result<isize> {
+    let list: &mut [u8] = if size == 0 {
+        &mut []
+    } else {
+        from_user::check_mut_array(list, size)?;
+        unsafe { std::slice::from_raw_parts_mut(list, size) }
+    };
+    let len = file_ops::do_listxattr(target, list)?;
+    Ok(len as isize)
+}
+
+pub fn do_removexattr(path: *const i8, name: *const i8) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    let name = clone_xattr_name_from_user(name)?;
+    file_ops::do_removexattr(&XattrTarget::Path(&path), &name)?;
+    Ok(0)
+}
+
+pub fn do_lremovexattr(path: *const i8, name: *const i8) -> Result<isize> {
+    let path = clone_path_from_user(path)?;
+    let name = clone_xattr_name_from_user(name)?;
+    file_ops::do_removexattr(&XattrTarget::LPath(&path), &name)?;
+    Ok(0)
+}
+
+pub fn do_fremovexattr(fd: FileDesc, name: *const i8) -> Result<isize> {
+    let name = clone_xattr_name_from_user(name)?;
+    file_ops::do_removexattr(&XattrTarget::Fd(fd), &name)?;
+    Ok(0)
+}
+
+fn clone_path_from_user(path: *const i8) -> Result<String> {
+    let path = from_user::clone_cstring_safely(path)?
+        .to_string_lossy()
+        .into_owned();
+    if path.is_empty() {
+        return_errno!(ENOENT, "path is an empty string");
+    }
+    Ok(path)
+}
+
+fn clone_xattr_name_from_user(name: *const i8) -> Result<String> {
+    let name = from_user::clone_cstring_safely(name)?;
+    name.into_string()
+        .map_err(|_| errno!(EOPNOTSUPP, "the xattr name is not valid UTF-8"))
+}
+
 pub fn do_sendfile(
     out_fd: FileDesc,
     in_fd: FileDesc,
@@ -642,6 +1022,179 @@ pub fn do_sendfile(
     Ok(len as isize)
 }
 
+pub fn do_splice(
+    fd_in: FileDesc,
+    off_in_ptr: *mut off_t,
+    fd_out: FileDesc,
+    off_out_ptr: *mut off_t,
+    len: usize,
+    flags: u32,
+) -> Result<isize> {
+    let flags = SpliceFlags::from_u32(flags)?;
+    let mut off_in = if off_in_ptr.is_null() {
+        None
+    } else {
+        from_user::check_mut_ptr(off_in_ptr)?;
+        Some(unsafe { off_in_ptr.read() })
+    };
+    let mut off_out = if off_out_ptr.is_null() {
+        None
+    } else {
+        from_user::check_mut_ptr(off_out_ptr)?;
+        Some(unsafe { off_out_ptr.read() })
+    };
+
+    let len = file_ops::do_splice(fd_in, off_in.as_mut(), fd_out, off_out.as_mut(), len, flags)?;
+    if let Some(off_in) = off_in {
+        unsafe {
+            off_in_ptr.write(off_in);
+        }
+    }
+    if let Some(off_out) = off_out {
+        unsafe {
+            off_out_ptr.write(off_out);
+        }
+    }
+    Ok(len as isize)
+}
+
+pub fn do_tee(fd_in: FileDesc, fd_out: FileDesc, len: usize, flags: u32) -> Result<isize> {
+    let flags = SpliceFlags::from_u32(flags)?;
+    let len = file_ops::do_tee(fd_in, fd_out, len, flags)?;
+    Ok(len as isize)
+}
+
+pub fn do_vmsplice(
+    fd: FileDesc,
+    iov_ptr: *const iovec_t,
+    nr_segs: usize,
+    flags: u32,
+) -> Result<isize> {
+    let flags = SpliceFlags::from_u32(flags)?;
+    let file = current!().file(fd)?;
+    // The buffers are read from for a pipe writer and written to for a pipe reader
+    let is_mut = file.as_pipe_reader().is_ok();
+    let bufs = UserBuf::from_iovecs(iov_ptr as u64, nr_segs as u64, is_mut)?;
+    let len = file_ops::do_vmsplice(fd, &bufs, flags)?;
+    Ok(len as isize)
+}
+
+pub fn do_io_setup(nr_events: u32, ctx_ptr: *mut aio_context_t) -> Result<isize> {
+    from_user::check_mut_ptr(ctx_ptr)?;
+    if unsafe { ctx_ptr.read() } != 0 {
+        return_errno!(EINVAL, "the context must be initialized to zero");
+    }
+    let id = aio::do_io_setup(nr_events as usize)?;
+    unsafe {
+        ctx_ptr.write(id);
+    }
+    Ok(0)
+}
+
+pub fn do_io_destroy(ctx_id: aio_context_t) -> Result<isize> {
+    aio::do_io_destroy(ctx_id)?;
+    Ok(0)
+}
+
+pub fn do_io_submit(ctx_id: aio_context_t, nr: i64, iocbpp: *const *const iocb_t) -> Result<isize> {
+    if nr < 0 {
+        return_errno!(EINVAL, "the number of requests is negative");
+    }
+    if nr == 0 {
+        return Ok(0);
+    }
+    let iocb_ptrs = {
+        from_user::check_array(iocbpp, nr as usize)?;
+        unsafe { std::slice::from_raw_parts(iocbpp, nr as usize) }
+    };
+    let mut iocbs = Vec::with_capacity(iocb_ptrs.len());
+    for &iocb_ptr in iocb_ptrs {
+        // Like Linux, an invalid control block fails io_submit only if it is
+        // the first one
+        if let Err(e) = from_user::check_ptr(iocb_ptr) {
+            if iocbs.is_empty() {
+                return Err(e);
+            }
+            break;
+        }
+        iocbs.push((iocb_ptr as u64, unsafe { iocb_ptr.read() }));
+    }
+    let count = aio::do_io_submit(ctx_id, &iocbs)?;
+    Ok(count as isize)
+}
+
+pub fn do_io_cancel(
+    ctx_id: aio_context_t,
+    iocb_ptr: *const iocb_t,
+    _result: *mut io_event_t,
+) -> Result<isize> {
+    // The result argument is not used since the completion event of a canceled
+    // request is delivered to the completion ring
+    from_user::check_ptr(iocb_ptr)?;
+    aio::do_io_cancel(ctx_id, iocb_ptr as u64)?;
+    Ok(0)
+}
+
+pub fn do_io_getevents(
+    ctx_id: aio_context_t,
+    min_nr: i64,
+    nr: i64,
+    events_ptr: *mut io_event_t,
+    timeout_ptr: *const timespec_t,
+) -> Result<isize> {
+    if min_nr < 0 || nr < 0 {
+        return_errno!(EINVAL, "the number of events is negative");
+    }
+    let events = {
+        from_user::check_mut_array(events_ptr, nr as usize)?;
+        unsafe { std::slice::from_raw_parts_mut(events_ptr, nr as usize) }
+    };
+    let mut timeout = if timeout_ptr.is_null() {
+        None
+    } else {
+        from_user::check_ptr(timeout_ptr)?;
+        Some(timespec_t::from_raw_ptr(timeout_ptr)?.as_duration())
+    };
+    let count = aio::do_io_getevents(ctx_id, min_nr as usize, events, timeout.as_mut())?;
+    Ok(count as isize)
+}
+
+pub fn do_io_uring_setup(entries: u32, params_ptr: *mut io_uring_params) -> Result<isize> {
+    from_user::check_mut_ptr(params_ptr)?;
+    let mut params = unsafe { params_ptr.read() };
+    let file_ref: Arc<dyn File> = Arc::new(io_uring::do_io_uring_setup(entries, &mut params)?);
+    unsafe {
+        params_ptr.write(params);
+    }
+    // Like Linux, an io_uring fd is close-on-exec
+    let fd = current!().add_file(file_ref, true);
+    Ok(fd as isize)
+}
+
+pub fn do_io_uring_enter(
+    fd: u32,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sig_mask: *const sigset_t,
+    sigset_size: size_t,
+) -> Result<isize> {
+    let sig_mask = crate::net::copy_sigmask_from_user(sig_mask, sigset_size)?;
+    let count =
+        io_uring::do_io_uring_enter(fd as FileDesc, to_submit, min_complete, flags, sig_mask)?;
+    Ok(count as isize)
+}
+
+pub fn do_io_uring_register(
+    fd: u32,
+    opcode: u32,
+    arg: *const c_void,
+    nr_args: u32,
+) -> Result<isize> {
+    let ret = io_uring::do_io_uring_register(fd as FileDesc, opcode, arg as usize, nr_args)?;
+    Ok(ret as isize)
+}
+
 pub fn do_fcntl(fd: FileDesc, cmd: u32, arg: u64) -> Result<isize> {
     let mut cmd = FcntlCmd::from_raw(cmd, arg)?;
     file_ops::do_fcntl(fd, &mut cmd)
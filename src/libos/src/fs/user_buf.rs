@@ -0,0 +1,140 @@
+use super::*;
+use crate::vm::{VMRange, PAGE_SIZE, USER_SPACE_VM_MANAGER};
+use util::mem_util::from_user;
+
+/// The maximum number of buffers of a vectored request
+const IOV_MAX: usize = 1024;
+
+/// A user buffer that has been checked to be inside the user space.
+///
+/// Unlike a slice, the buffer can be kept by a request that is executed later,
+/// as is done by AIO and io_uring. Since all the processes share one address
+/// space, the memory may have been unmapped, or even mapped by another process,
+/// by then. So the buffer is never accessed in place. Instead, the data is
+/// copied between the buffer and a kernel buffer by a thread of the process
+/// that submits the buffer, which checks that the buffer is still mapped by the
+/// process before the copy.
+#[derive(Debug)]
+pub struct UserBuf {
+    addr: usize,
+    len: usize,
+}
+
+impl UserBuf {
+    pub fn new(addr: u64, len: u64, is_mut: bool) -> Result<Self> {
+        if len > isize::MAX as u64 {
+            return_errno!(EINVAL, "the buffer is too large");
+        }
+        let (addr, len) = (addr as usize, len as usize);
+        if len > 0 {
+            if is_mut {
+                from_user::check_mut_array(addr as *mut u8, len)?;
+            } else {
+                from_user::check_array(addr as *const u8, len)?;
+            }
+        }
+        Ok(Self { addr, len })
+    }
+
+    /// Get the buffers from an array of struct iovec, which is copied at
+    /// submission time like Linux.
+    pub fn from_iovecs(addr: u64, count: u64, is_mut: bool) -> Result<Vec<Self>> {
+        if count > IOV_MAX as u64 {
+            return_errno!(EINVAL, "too many buffers");
+        }
+        let iovecs = {
+            let iovecs = addr as *const libc::iovec;
+            from_user::check_array(iovecs, count as usize)?;
+            unsafe { std::slice::from_raw_parts(iovecs, count as usize) }
+        };
+
+        let mut total_len: u64 = 0;
+        let mut bufs = Vec::with_capacity(iovecs.len());
+        for iovec in iovecs {
+            let len = iovec.iov_len as u64;
+            total_len = total_len
+                .checked_add(len)
+                .ok_or_else(|| errno!(EINVAL, "the buffers are too large"))?;
+            bufs.push(Self::new(iovec.iov_base as u64, len, is_mut)?);
+        }
+        if total_len > isize::MAX as u64 {
+            return_errno!(EINVAL, "the buffers are too large");
+        }
+        Ok(bufs)
+    }
+
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check that the buffer is mapped by the current process.
+    pub fn check_mapped(&self) -> Result<()> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let range = VMRange::new(
+            align_down(self.addr, PAGE_SIZE),
+            align_up(self.addr + self.len, PAGE_SIZE),
+        )?;
+        if USER_SPACE_VM_MANAGER.mapped_size(&range) != range.size() {
+            return_errno!(EFAULT, "the buffer is not mapped by the process");
+        }
+        Ok(())
+    }
+
+    /// Copy the data of the buffers to `dst` in order, until `dst` is full or
+    /// all the buffers are copied. Return the number of bytes copied.
+    ///
+    /// This must be called by a thread of the process that submits the buffers.
+    pub fn gather(bufs: &[UserBuf], dst: &mut [u8]) -> Result<usize> {
+        let mut count = 0;
+        for buf in bufs {
+            if count == dst.len() {
+                break;
+            }
+            buf.check_mapped()?;
+            let copy_len = buf.len.min(dst.len() - count);
+            // Safety. The buffer is mapped by the current process.
+            let src = unsafe { std::slice::from_raw_parts(buf.addr as *const u8, copy_len) };
+            dst[count..count + copy_len].copy_from_slice(src);
+            count += copy_len;
+        }
+        Ok(count)
+    }
+
+    /// Copy `src` to the buffers in order, until `src` is used up or all the
+    /// buffers are filled. Return the number of bytes copied.
+    ///
+    /// This must be called by a thread of the process that submits the buffers.
+    pub fn scatter(bufs: &[UserBuf], src: &[u8]) -> Result<usize> {
+        let mut count = 0;
+        for buf in bufs {
+            if count == src.len() {
+                break;
+            }
+            buf.check_mapped()?;
+            let copy_len = buf.len.min(src.len() - count);
+            // Safety. The buffer is mapped by the current process.
+            let dst = unsafe { std::slice::from_raw_parts_mut(buf.addr as *mut u8, copy_len) };
+            dst.copy_from_slice(&src[count..count + copy_len]);
+            count += copy_len;
+        }
+        Ok(count)
+    }
+
+    /// Allocate a kernel buffer for the buffers, which is not aborted but fails
+    /// with ENOMEM if the memory is not enough.
+    pub fn alloc_bounce_buf(bufs: &[UserBuf]) -> Result<Vec<u8>> {
+        let len: usize = bufs.iter().map(|buf| buf.len).sum();
+        let mut bounce_buf = Vec::new();
+        bounce_buf
+            .try_reserve_exact(len)
+            .map_err(|_| errno!(ENOMEM, "no memory for the bounce buffer"))?;
+        bounce_buf.resize(len, 0);
+        Ok(bounce_buf)
+    }
+}
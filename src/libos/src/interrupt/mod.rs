@@ -38,6 +38,7 @@ pub fn do_handle_interrupt(
     // interrupt happened
     *context = CpuContext::from_sgx(&info.cpu_context);
     context.fpregs = fpregs;
+    current!().usage().inc_nivcsw();
     Ok(0)
 }
 
@@ -0,0 +1,174 @@
+//! Definitions shared by System V IPC mechanisms: keys, permissions, flags and IDs.
+
+use super::*;
+
+use crate::fs::FileMode;
+use crate::process::{do_getegid, do_geteuid, gid_t, uid_t};
+use crate::time::{do_gettimeofday, time_t};
+use std::collections::HashSet;
+
+#[allow(non_camel_case_types)]
+pub type key_t = u32;
+pub type CmdId = u32;
+
+pub const IPC_PRIVATE: key_t = 0;
+
+// Common cmds for shmctl(), semctl() and msgctl()
+pub const IPC_RMID: CmdId = 0;
+pub const IPC_SET: CmdId = 1;
+pub const IPC_STAT: CmdId = 2;
+pub const IPC_INFO: CmdId = 3;
+// Some libc versions pass this flag along with the cmd to request the new
+// version of the IPC data structures, which are the only versions supported.
+pub const IPC_64: CmdId = 0x100;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ipc_perm_t {
+    pub key: key_t,
+    pub uid: uid_t,
+    pub gid: gid_t,
+    pub cuid: uid_t,
+    pub cgid: gid_t,
+    pub mode: u16,
+    pub pad1: u16,
+    pub seq: u16,
+    pub pad2: u16,
+    pub unused1: u64,
+    pub unused2: u64,
+}
+
+bitflags! {
+    pub struct IpcFlags: u32 {
+        const IPC_CREAT = 0o1000;
+        const IPC_EXCL = 0o2000;
+        const IPC_NOWAIT = 0o4000;
+
+        /// read by owner
+        const S_IRUSR = FileMode::S_IRUSR.bits() as u32;
+        /// write by owner
+        const S_IWUSR = FileMode::S_IWUSR.bits() as u32;
+        /// execute/search by owner
+        const S_IXUSR = FileMode::S_IXUSR.bits() as u32;
+        /// read by group
+        const S_IRGRP = FileMode::S_IRGRP.bits() as u32;
+        /// write by group
+        const S_IWGRP = FileMode::S_IWGRP.bits() as u32;
+        /// execute/search by group
+        const S_IXGRP = FileMode::S_IXGRP.bits() as u32;
+        /// read by others
+        const S_IROTH = FileMode::S_IROTH.bits() as u32;
+        /// write by others
+        const S_IWOTH = FileMode::S_IWOTH.bits() as u32;
+        /// execute/search by others
+        const S_IXOTH = FileMode::S_IXOTH.bits() as u32;
+    }
+}
+
+impl IpcFlags {
+    pub fn to_file_mode(&self) -> FileMode {
+        FileMode::from_bits_truncate(self.bits as u16 & FileMode::all().bits())
+    }
+}
+
+/// The ownership and permission of an IPC object.
+#[derive(Debug, Clone, Copy)]
+pub struct IpcPerm {
+    pub key: key_t,
+    pub uid: uid_t,
+    pub gid: gid_t,
+    pub cuid: uid_t,
+    pub cgid: gid_t,
+    pub mode: FileMode,
+}
+
+impl IpcPerm {
+    pub fn new(key: key_t, mode: FileMode) -> Self {
+        let uid = do_geteuid().unwrap() as uid_t;
+        let gid = do_getegid().unwrap() as gid_t;
+        Self {
+            key,
+            uid,
+            gid,
+            cuid: uid,
+            cgid: gid,
+            mode,
+        }
+    }
+
+    pub fn check_perm(&self) -> Result<()> {
+        // TODO: Add permission control
+        Ok(())
+    }
+
+    /// Update the owner and the mode from the IPC_SET command.
+    pub fn set(&mut self, new_perm: &ipc_perm_t) {
+        self.uid = new_perm.uid;
+        self.gid = new_perm.gid;
+        self.mode = FileMode::from_bits_truncate(new_perm.mode & FileMode::all().bits());
+    }
+
+    pub fn to_c(&self, extra_mode_bits: u16) -> ipc_perm_t {
+        ipc_perm_t {
+            key: self.key,
+            uid: self.uid,
+            gid: self.gid,
+            cuid: self.cuid,
+            cgid: self.cgid,
+            mode: self.mode.bits() | extra_mode_bits,
+            pad1: 0,
+            seq: 0,
+            pad2: 0,
+            unused1: 0,
+            unused2: 0,
+        }
+    }
+}
+
+/// Allocate IDs for IPC objects of the same kind in a round-robin manner.
+#[derive(Debug)]
+pub struct IpcIdManager {
+    used_id: HashSet<u32>,
+    max_id: u32,
+    last_alloc_id: u32,
+}
+
+impl IpcIdManager {
+    /// Create an ID manager that allocates IDs in `[0, max_id)`.
+    pub fn new(max_id: u32) -> Self {
+        Self {
+            used_id: HashSet::new(),
+            max_id,
+            last_alloc_id: max_id - 1,
+        }
+    }
+
+    // Always return next free id
+    pub fn alloc_id(&mut self) -> Result<u32> {
+        if self.used_id.len() as u32 >= self.max_id {
+            return_errno!(ENOSPC, "all possible IPC IDs have been taken");
+        }
+        let mut id = self.last_alloc_id + 1;
+        loop {
+            if id == self.max_id {
+                id = 0;
+            }
+            if !self.used_id.contains(&id) {
+                break;
+            }
+            id += 1;
+        }
+        self.used_id.insert(id);
+        self.last_alloc_id = id;
+        Ok(id)
+    }
+
+    pub fn free_id(&mut self, id: &u32) {
+        self.used_id.remove(id);
+    }
+}
+
+pub fn current_time() -> time_t {
+    do_gettimeofday().sec()
+}
@@ -1,7 +1,16 @@
 use super::*;
 
+mod common;
+mod msg;
+mod sem;
 mod shm;
 mod syscalls;
 
-pub use self::shm::{key_t, shmids_t, SHM_MANAGER};
-pub use self::syscalls::{do_shmat, do_shmctl, do_shmdt, do_shmget};
+pub use self::common::key_t;
+pub use self::msg::msqid_ds_t;
+pub use self::sem::{sembuf_t, SEM_MANAGER};
+pub use self::shm::{shmids_t, SHM_MANAGER};
+pub use self::syscalls::{
+    do_msgctl, do_msgget, do_msgrcv, do_msgsnd, do_semctl, do_semget, do_semop, do_semtimedop,
+    do_shmat, do_shmctl, do_shmdt, do_shmget,
+};
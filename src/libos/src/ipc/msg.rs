@@ -0,0 +1,386 @@
+use super::*;
+
+use super::common::{
+    current_time, ipc_perm_t, key_t, CmdId, IpcFlags, IpcIdManager, IpcPerm, IPC_PRIVATE, IPC_RMID,
+    IPC_SET, IPC_STAT,
+};
+use crate::events::{Waiter, WaiterQueue};
+use crate::fs::FileMode;
+use crate::time::time_t;
+use std::collections::HashMap;
+
+pub type MsgId = u32;
+
+// max size of a message (bytes)
+const MSGMAX: usize = 8192;
+// default max size of a message queue (bytes)
+const MSGMNB: usize = 16384;
+// max num of message queues system wide,
+// also indicates the max msqid - 1 in Occlum
+const MSGMNI: MsgId = 32000;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+#[repr(C)]
+pub struct msqid_ds_t {
+    msg_perm: ipc_perm_t,
+    msg_stime: time_t,
+    msg_rtime: time_t,
+    msg_ctime: time_t,
+    msg_cbytes: u64,
+    msg_qnum: u64,
+    msg_qbytes: u64,
+    msg_lspid: pid_t,
+    msg_lrpid: pid_t,
+    unused1: u64,
+    unused2: u64,
+}
+
+bitflags! {
+    pub struct MsgFlags: u32 {
+        const IPC_NOWAIT = 0o4000;
+        /// Truncate the message text if longer than the buffer
+        const MSG_NOERROR = 0o10000;
+        /// Receive the first message whose type is not the given type
+        const MSG_EXCEPT = 0o20000;
+        /// Copy the message without removing it, unsupported now
+        const MSG_COPY = 0o40000;
+    }
+}
+
+#[derive(Debug)]
+struct Message {
+    mtype: i64,
+    mtext: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct MsgQueue {
+    msqid: MsgId,
+    perm: IpcPerm,
+    messages: VecDeque<Message>,
+    // The total size of message texts in the queue
+    cbytes: usize,
+    // The max size of message texts in the queue
+    qbytes: usize,
+
+    msg_stime: time_t,
+    msg_rtime: time_t,
+    msg_ctime: time_t,
+    msg_lspid: pid_t,
+    msg_lrpid: pid_t,
+
+    // Threads blocked in msgsnd wait here for free space
+    senders: Arc<WaiterQueue>,
+    // Threads blocked in msgrcv wait here for new messages
+    receivers: Arc<WaiterQueue>,
+}
+
+impl MsgQueue {
+    fn new(msqid: MsgId, key: key_t, mode: FileMode) -> Self {
+        MsgQueue {
+            msqid,
+            perm: IpcPerm::new(key, mode),
+            messages: VecDeque::new(),
+            cbytes: 0,
+            qbytes: MSGMNB,
+            msg_stime: 0,
+            msg_rtime: 0,
+            msg_ctime: current_time(),
+            msg_lspid: 0,
+            msg_lrpid: 0,
+            senders: Arc::new(WaiterQueue::new()),
+            receivers: Arc::new(WaiterQueue::new()),
+        }
+    }
+
+    fn has_space_for(&self, size: usize) -> bool {
+        self.cbytes + size <= self.qbytes
+    }
+
+    fn push(&mut self, msg: Message, pid: pid_t) {
+        self.cbytes += msg.mtext.len();
+        self.messages.push_back(msg);
+        self.msg_stime = current_time();
+        self.msg_lspid = pid;
+        self.receivers.dequeue_and_wake_all();
+    }
+
+    /// Find the index of the first message that matches the type.
+    fn find(&self, msgtyp: i64, flags: MsgFlags) -> Option<usize> {
+        if msgtyp == 0 {
+            return if self.messages.is_empty() {
+                None
+            } else {
+                Some(0)
+            };
+        }
+        if msgtyp > 0 {
+            let is_except = flags.contains(MsgFlags::MSG_EXCEPT);
+            return self
+                .messages
+                .iter()
+                .position(|msg| (msg.mtype == msgtyp) != is_except);
+        }
+        // Get the first message with the lowest type less than or equal to |msgtyp|
+        let max_type = msgtyp.checked_neg().unwrap_or(i64::MAX);
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| msg.mtype <= max_type)
+            .min_by_key(|(idx, msg)| (msg.mtype, *idx))
+            .map(|(idx, _)| idx)
+    }
+
+    fn remove(&mut self, idx: usize, pid: pid_t) -> Message {
+        let msg = self.messages.remove(idx).unwrap();
+        self.cbytes -= msg.mtext.len();
+        self.msg_rtime = current_time();
+        self.msg_lrpid = pid;
+        self.senders.dequeue_and_wake_all();
+        msg
+    }
+
+    fn to_c(&self) -> msqid_ds_t {
+        msqid_ds_t {
+            msg_perm: self.perm.to_c(0),
+            msg_stime: self.msg_stime,
+            msg_rtime: self.msg_rtime,
+            msg_ctime: self.msg_ctime,
+            msg_cbytes: self.cbytes as u64,
+            msg_qnum: self.messages.len() as u64,
+            msg_qbytes: self.qbytes as u64,
+            msg_lspid: self.msg_lspid,
+            msg_lrpid: self.msg_lrpid,
+            unused1: 0,
+            unused2: 0,
+        }
+    }
+}
+
+impl Drop for MsgQueue {
+    fn drop(&mut self) {
+        debug!("drop msg queue: {:?}", self.msqid);
+        // Waiters will find the queue removed and return EIDRM
+        self.senders.dequeue_and_wake_all();
+        self.receivers.dequeue_and_wake_all();
+    }
+}
+
+lazy_static! {
+    pub static ref MSG_MANAGER: MsgManager = MsgManager::new();
+}
+
+#[derive(Debug)]
+pub struct MsgManager {
+    msg_queues: RwLock<HashMap<MsgId, MsgQueue>>,
+    msqid_manager: RwLock<IpcIdManager>,
+}
+
+impl MsgManager {
+    fn new() -> Self {
+        MsgManager {
+            msg_queues: RwLock::new(HashMap::new()),
+            msqid_manager: RwLock::new(IpcIdManager::new(MSGMNI)),
+        }
+    }
+
+    fn create_msg_queue(
+        &self,
+        msg_queues: &mut HashMap<MsgId, MsgQueue>,
+        key: key_t,
+        msgflg: IpcFlags,
+    ) -> Result<MsgId> {
+        let msqid = self.msqid_manager.write().unwrap().alloc_id()?;
+        let msg_queue = MsgQueue::new(msqid, key, msgflg.to_file_mode());
+        msg_queues.insert(msqid, msg_queue);
+        Ok(msqid)
+    }
+
+    pub fn do_msgget(&self, key: key_t, msgflg: IpcFlags) -> Result<MsgId> {
+        debug!("do_msgget: key: {:?}, msgflg: {:?}", key, msgflg);
+        let mut msg_queues = self.msg_queues.write().unwrap();
+        if key == IPC_PRIVATE {
+            return self.create_msg_queue(&mut msg_queues, key, msgflg);
+        }
+
+        let msg_queue = msg_queues
+            .values()
+            .find(|msg_queue| msg_queue.perm.key == key);
+        if let Some(msg_queue) = msg_queue {
+            if msgflg.contains(IpcFlags::IPC_CREAT) && msgflg.contains(IpcFlags::IPC_EXCL) {
+                return_errno!(EEXIST, "the message queue already exists for given key");
+            }
+            msg_queue.perm.check_perm()?;
+            Ok(msg_queue.msqid)
+        } else {
+            if !msgflg.contains(IpcFlags::IPC_CREAT) {
+                return_errno!(ENOENT, "no message queue exists for given key");
+            }
+            self.create_msg_queue(&mut msg_queues, key, msgflg)
+        }
+    }
+
+    pub fn do_msgsnd(
+        &self,
+        msqid: MsgId,
+        mtype: i64,
+        mtext: &[u8],
+        msgflg: MsgFlags,
+    ) -> Result<()> {
+        debug!(
+            "do_msgsnd: msqid: {:?}, mtype: {:?}, size: {:?}, msgflg: {:?}",
+            msqid,
+            mtype,
+            mtext.len(),
+            msgflg
+        );
+        if mtype <= 0 {
+            return_errno!(EINVAL, "the message type must be positive");
+        }
+        if mtext.len() > MSGMAX {
+            return_errno!(EINVAL, "the message is too large");
+        }
+
+        let pid = current!().process().pid();
+        let waiter = Waiter::new();
+        let mut waited_queue: Option<Arc<WaiterQueue>> = None;
+        loop {
+            let mut msg_queues = self.msg_queues.write().unwrap();
+            let msg_queue =
+                Self::get_msg_queue(&mut msg_queues, msqid, &waited_queue, |q| &q.senders)?;
+            msg_queue.perm.check_perm()?;
+            if mtext.len() > msg_queue.qbytes {
+                return_errno!(EINVAL, "the message is larger than the queue");
+            }
+
+            if msg_queue.has_space_for(mtext.len()) {
+                let msg = Message {
+                    mtype,
+                    mtext: mtext.to_vec(),
+                };
+                msg_queue.push(msg, pid);
+                return Ok(());
+            }
+            if msgflg.contains(MsgFlags::IPC_NOWAIT) {
+                return_errno!(EAGAIN, "the message queue is full");
+            }
+
+            // Start to wait
+            let senders = msg_queue.senders.clone();
+            senders.reset_and_enqueue(&waiter);
+            // Ensure that we drop any locks before wait
+            drop(msg_queues);
+            waiter.wait(None)?;
+            waited_queue = Some(senders);
+        }
+    }
+
+    /// Receive a message into the buffer, and return its type and the length of text.
+    pub fn do_msgrcv(
+        &self,
+        msqid: MsgId,
+        mtext: &mut [u8],
+        msgtyp: i64,
+        msgflg: MsgFlags,
+    ) -> Result<(i64, usize)> {
+        debug!(
+            "do_msgrcv: msqid: {:?}, size: {:?}, msgtyp: {:?}, msgflg: {:?}",
+            msqid,
+            mtext.len(),
+            msgtyp,
+            msgflg
+        );
+        if msgflg.contains(MsgFlags::MSG_COPY) {
+            return_errno!(ENOSYS, "MSG_COPY is not supported");
+        }
+
+        let pid = current!().process().pid();
+        let waiter = Waiter::new();
+        let mut waited_queue: Option<Arc<WaiterQueue>> = None;
+        loop {
+            let mut msg_queues = self.msg_queues.write().unwrap();
+            let msg_queue =
+                Self::get_msg_queue(&mut msg_queues, msqid, &waited_queue, |q| &q.receivers)?;
+            msg_queue.perm.check_perm()?;
+
+            if let Some(idx) = msg_queue.find(msgtyp, msgflg) {
+                let msg_len = msg_queue.messages[idx].mtext.len();
+                if msg_len > mtext.len() && !msgflg.contains(MsgFlags::MSG_NOERROR) {
+                    return_errno!(E2BIG, "the message is longer than the buffer");
+                }
+                let msg = msg_queue.remove(idx, pid);
+                let copy_len = msg_len.min(mtext.len());
+                mtext[..copy_len].copy_from_slice(&msg.mtext[..copy_len]);
+                return Ok((msg.mtype, copy_len));
+            }
+            if msgflg.contains(MsgFlags::IPC_NOWAIT) {
+                return_errno!(ENOMSG, "no message of the requested type");
+            }
+
+            // Start to wait
+            let receivers = msg_queue.receivers.clone();
+            receivers.reset_and_enqueue(&waiter);
+            // Ensure that we drop any locks before wait
+            drop(msg_queues);
+            waiter.wait(None)?;
+            waited_queue = Some(receivers);
+        }
+    }
+
+    // Get the message queue and check whether it has been removed while waiting.
+    fn get_msg_queue<'a>(
+        msg_queues: &'a mut HashMap<MsgId, MsgQueue>,
+        msqid: MsgId,
+        waited_queue: &Option<Arc<WaiterQueue>>,
+        waiters_of: impl Fn(&MsgQueue) -> &Arc<WaiterQueue>,
+    ) -> Result<&'a mut MsgQueue> {
+        let msg_queue = match msg_queues.get_mut(&msqid) {
+            Some(msg_queue) => msg_queue,
+            None if waited_queue.is_some() => {
+                return_errno!(EIDRM, "the message queue has been removed")
+            }
+            None => return_errno!(EINVAL, "cannot find message queue by msqid"),
+        };
+        // The msqid may have been reused by a new queue while we were waiting
+        if let Some(queue) = waited_queue {
+            if !Arc::ptr_eq(queue, waiters_of(msg_queue)) {
+                return_errno!(EIDRM, "the message queue has been removed");
+            }
+        }
+        Ok(msg_queue)
+    }
+
+    pub fn do_msgctl(&self, msqid: MsgId, cmd: CmdId, buf: Option<&mut msqid_ds_t>) -> Result<()> {
+        debug!(
+            "do_msgctl: msqid: {:?}, cmd: {:?}, buf: {:?}",
+            msqid, cmd, buf
+        );
+        let mut msg_queues = self.msg_queues.write().unwrap();
+        let msg_queue = msg_queues
+            .get_mut(&msqid)
+            .ok_or_else(|| errno!(EINVAL, "cannot find message queue by msqid"))?;
+        msg_queue.perm.check_perm()?;
+
+        match cmd {
+            IPC_RMID => {
+                msg_queues.remove(&msqid);
+                self.msqid_manager.write().unwrap().free_id(&msqid);
+            }
+            IPC_STAT => {
+                let buf = buf.ok_or_else(|| errno!(EFAULT, "buf is empty"))?;
+                *buf = msg_queue.to_c();
+            }
+            IPC_SET => {
+                let buf = buf.ok_or_else(|| errno!(EFAULT, "buf is empty"))?;
+                msg_queue.perm.set(&buf.msg_perm);
+                msg_queue.qbytes = buf.msg_qbytes as usize;
+                msg_queue.msg_ctime = current_time();
+                // There may be more space for senders now
+                msg_queue.senders.dequeue_and_wake_all();
+            }
+            _ => return_errno!(EINVAL, "unimplemented cmd"),
+        }
+        Ok(())
+    }
+}
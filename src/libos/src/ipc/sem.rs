@@ -0,0 +1,516 @@
+use super::*;
+
+use super::common::{
+    current_time, ipc_perm_t, key_t, CmdId, IpcFlags, IpcIdManager, IpcPerm, IPC_PRIVATE, IPC_RMID,
+    IPC_SET, IPC_STAT,
+};
+use crate::events::{Waiter, WaiterQueue};
+use crate::fs::FileMode;
+use crate::process::ThreadRef;
+use crate::time::time_t;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub type SemId = u32;
+
+// max num of semaphores per set
+const SEMMSL: usize = 32000;
+// max num of semaphore sets system wide,
+// also indicates the max semid - 1 in Occlum
+const SEMMNI: SemId = 32000;
+// max num of ops per semop call
+const SEMOPM: usize = 500;
+// max value of a semaphore
+const SEMVMX: i32 = 32767;
+// max value of an adjustment on exit
+const SEMAEM: i32 = SEMVMX;
+
+// For cmd in semctl()
+pub const GETPID: CmdId = 11;
+pub const GETVAL: CmdId = 12;
+pub const GETALL: CmdId = 13;
+pub const GETNCNT: CmdId = 14;
+pub const GETZCNT: CmdId = 15;
+pub const SETVAL: CmdId = 16;
+pub const SETALL: CmdId = 17;
+const SEM_STAT: CmdId = 18;
+const SEM_INFO: CmdId = 19;
+const SEM_STAT_ANY: CmdId = 20;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+#[repr(C)]
+pub struct semid_ds_t {
+    sem_perm: ipc_perm_t,
+    sem_otime: time_t,
+    unused1: u64,
+    sem_ctime: time_t,
+    unused2: u64,
+    sem_nsems: u64,
+    unused3: u64,
+    unused4: u64,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct sembuf_t {
+    sem_num: u16,
+    sem_op: i16,
+    sem_flg: i16,
+}
+
+bitflags! {
+    pub struct SemOpFlags: i16 {
+        const IPC_NOWAIT = 0o4000;
+        const SEM_UNDO = 0x1000;
+    }
+}
+
+/// A single semaphore operation.
+#[derive(Debug, Clone, Copy)]
+pub struct SemOp {
+    num: usize,
+    op: i16,
+    flags: SemOpFlags,
+}
+
+impl SemOp {
+    pub fn from_c(sembuf: &sembuf_t) -> Result<Self> {
+        let flags = SemOpFlags::from_bits(sembuf.sem_flg)
+            .ok_or_else(|| errno!(EINVAL, "invalid semop flags"))?;
+        Ok(Self {
+            num: sembuf.sem_num as usize,
+            op: sembuf.sem_op,
+            flags,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Semaphore {
+    val: i32,
+    // The pid of the process that did the last operation
+    pid: pid_t,
+    // The number of threads waiting for the value to increase
+    ncnt: usize,
+    // The number of threads waiting for the value to become zero
+    zcnt: usize,
+}
+
+#[derive(Debug)]
+struct SemSet {
+    semid: SemId,
+    perm: IpcPerm,
+    sems: Vec<Semaphore>,
+    sem_otime: time_t,
+    sem_ctime: time_t,
+    // The adjustments to be applied when a process exits, for ops with SEM_UNDO
+    undo_adjs: HashMap<pid_t, Vec<i32>>,
+    // Threads blocked in semop wait here and are waken up on every update
+    waiters: Arc<WaiterQueue>,
+}
+
+impl SemSet {
+    fn new(semid: SemId, key: key_t, nsems: usize, mode: FileMode) -> Self {
+        let sems = (0..nsems).map(|_| Semaphore::default()).collect();
+        SemSet {
+            semid,
+            perm: IpcPerm::new(key, mode),
+            sems,
+            sem_otime: 0,
+            sem_ctime: current_time(),
+            undo_adjs: HashMap::new(),
+            waiters: Arc::new(WaiterQueue::new()),
+        }
+    }
+
+    fn nsems(&self) -> usize {
+        self.sems.len()
+    }
+
+    fn sem(&self, semnum: usize) -> Result<&Semaphore> {
+        self.sems
+            .get(semnum)
+            .ok_or_else(|| errno!(EINVAL, "invalid semaphore number"))
+    }
+
+    fn sem_mut(&mut self, semnum: usize) -> Result<&mut Semaphore> {
+        self.sems
+            .get_mut(semnum)
+            .ok_or_else(|| errno!(EINVAL, "invalid semaphore number"))
+    }
+
+    /// Try to perform all operations atomically.
+    ///
+    /// Return the index of the operation that would block, if any. In this case,
+    /// none of the operations takes effect.
+    fn try_semop(&mut self, sops: &[SemOp], pid: pid_t) -> Result<Option<usize>> {
+        let mut new_vals: Vec<i32> = self.sems.iter().map(|sem| sem.val).collect();
+        let mut new_adjs: Option<Vec<i32>> = None;
+        for (idx, sop) in sops.iter().enumerate() {
+            let val = &mut new_vals[sop.num];
+            if sop.op == 0 {
+                if *val != 0 {
+                    return Ok(Some(idx));
+                }
+            } else {
+                let new_val = *val + sop.op as i32;
+                if new_val < 0 {
+                    return Ok(Some(idx));
+                }
+                if new_val > SEMVMX {
+                    return_errno!(ERANGE, "semaphore value is out of range");
+                }
+                *val = new_val;
+                if sop.flags.contains(SemOpFlags::SEM_UNDO) {
+                    let adjs = new_adjs.get_or_insert_with(|| {
+                        self.undo_adjs
+                            .get(&pid)
+                            .cloned()
+                            .unwrap_or_else(|| vec![0; self.sems.len()])
+                    });
+                    let new_adj = adjs[sop.num] - sop.op as i32;
+                    // The same range as Linux, where -SEMAEM - 1 is allowed
+                    if new_adj < -SEMAEM - 1 || new_adj > SEMAEM {
+                        return_errno!(ERANGE, "semaphore adjustment is out of range");
+                    }
+                    adjs[sop.num] = new_adj;
+                }
+            }
+        }
+
+        // All operations can be done now
+        if let Some(new_adjs) = new_adjs {
+            self.undo_adjs.insert(pid, new_adjs);
+        }
+        for sop in sops {
+            self.sems[sop.num].pid = pid;
+        }
+        for (sem, new_val) in self.sems.iter_mut().zip(new_vals) {
+            sem.val = new_val;
+        }
+        self.sem_otime = current_time();
+        Ok(None)
+    }
+
+    fn set_val(&mut self, semnum: usize, val: i32, pid: pid_t) -> Result<()> {
+        if val < 0 || val > SEMVMX {
+            return_errno!(ERANGE, "semaphore value is out of range");
+        }
+        let sem = self.sem_mut(semnum)?;
+        sem.val = val;
+        sem.pid = pid;
+        // The adjustments of this semaphore are cleared in all processes
+        for adjs in self.undo_adjs.values_mut() {
+            adjs[semnum] = 0;
+        }
+        self.sem_ctime = current_time();
+        self.waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    fn set_all(&mut self, vals: &[u16], pid: pid_t) -> Result<()> {
+        if vals.iter().any(|&val| val as i32 > SEMVMX) {
+            return_errno!(ERANGE, "semaphore value is out of range");
+        }
+        for (sem, &val) in self.sems.iter_mut().zip(vals) {
+            sem.val = val as i32;
+            sem.pid = pid;
+        }
+        self.undo_adjs.clear();
+        self.sem_ctime = current_time();
+        self.waiters.dequeue_and_wake_all();
+        Ok(())
+    }
+
+    fn apply_undo(&mut self, pid: pid_t) {
+        let adjs = match self.undo_adjs.remove(&pid) {
+            Some(adjs) => adjs,
+            None => return,
+        };
+        for (sem, adj) in self.sems.iter_mut().zip(adjs) {
+            if adj == 0 {
+                continue;
+            }
+            let adj = adj.max(-SEMAEM).min(SEMAEM);
+            // Linux clamps the value into the valid range instead of blocking
+            sem.val = (sem.val + adj).max(0).min(SEMVMX);
+            sem.pid = pid;
+        }
+        self.sem_otime = current_time();
+        self.waiters.dequeue_and_wake_all();
+    }
+
+    fn to_c(&self) -> semid_ds_t {
+        semid_ds_t {
+            sem_perm: self.perm.to_c(0),
+            sem_otime: self.sem_otime,
+            unused1: 0,
+            sem_ctime: self.sem_ctime,
+            unused2: 0,
+            sem_nsems: self.nsems() as u64,
+            unused3: 0,
+            unused4: 0,
+        }
+    }
+}
+
+impl Drop for SemSet {
+    fn drop(&mut self) {
+        debug!("drop sem set: {:?}", self.semid);
+        // Waiters will find the set removed and return EIDRM
+        self.waiters.dequeue_and_wake_all();
+    }
+}
+
+/// The argument for semctl, which is a union in C.
+#[derive(Debug)]
+pub enum SemctlArg<'a> {
+    Val(i32),
+    Buf(Option<&'a mut semid_ds_t>),
+    ConstBuf(&'a semid_ds_t),
+    Array(&'a mut [u16]),
+    None,
+}
+
+lazy_static! {
+    pub static ref SEM_MANAGER: SemManager = SemManager::new();
+}
+
+#[derive(Debug)]
+pub struct SemManager {
+    sem_sets: RwLock<HashMap<SemId, SemSet>>,
+    semid_manager: RwLock<IpcIdManager>,
+}
+
+impl SemManager {
+    fn new() -> Self {
+        SemManager {
+            sem_sets: RwLock::new(HashMap::new()),
+            semid_manager: RwLock::new(IpcIdManager::new(SEMMNI)),
+        }
+    }
+
+    fn create_sem_set(
+        &self,
+        sem_sets: &mut HashMap<SemId, SemSet>,
+        key: key_t,
+        nsems: usize,
+        semflg: IpcFlags,
+    ) -> Result<SemId> {
+        if nsems == 0 || nsems > SEMMSL {
+            return_errno!(EINVAL, "invalid number of semaphores");
+        }
+        let semid = self.semid_manager.write().unwrap().alloc_id()?;
+        let sem_set = SemSet::new(semid, key, nsems, semflg.to_file_mode());
+        sem_sets.insert(semid, sem_set);
+        Ok(semid)
+    }
+
+    pub fn do_semget(&self, key: key_t, nsems: usize, semflg: IpcFlags) -> Result<SemId> {
+        debug!(
+            "do_semget: key: {:?}, nsems: {:?}, semflg: {:?}",
+            key, nsems, semflg
+        );
+        if nsems > SEMMSL {
+            return_errno!(EINVAL, "invalid number of semaphores");
+        }
+
+        let mut sem_sets = self.sem_sets.write().unwrap();
+        if key == IPC_PRIVATE {
+            return self.create_sem_set(&mut sem_sets, key, nsems, semflg);
+        }
+
+        let sem_set = sem_sets.values().find(|sem_set| sem_set.perm.key == key);
+        if let Some(sem_set) = sem_set {
+            if semflg.contains(IpcFlags::IPC_CREAT) && semflg.contains(IpcFlags::IPC_EXCL) {
+                return_errno!(EEXIST, "the semaphore set already exists for given key");
+            }
+            if nsems > sem_set.nsems() {
+                return_errno!(EINVAL, "the nsems from user is too large");
+            }
+            sem_set.perm.check_perm()?;
+            Ok(sem_set.semid)
+        } else {
+            if !semflg.contains(IpcFlags::IPC_CREAT) {
+                return_errno!(ENOENT, "no semaphore set exists for given key");
+            }
+            self.create_sem_set(&mut sem_sets, key, nsems, semflg)
+        }
+    }
+
+    pub fn do_semtimedop(
+        &self,
+        semid: SemId,
+        sops: &[SemOp],
+        timeout: Option<&Duration>,
+    ) -> Result<()> {
+        debug!(
+            "do_semtimedop: semid: {:?}, sops: {:?}, timeout: {:?}",
+            semid, sops, timeout
+        );
+        if sops.len() == 0 {
+            return_errno!(EINVAL, "no semaphore operations");
+        }
+        if sops.len() > SEMOPM {
+            return_errno!(E2BIG, "too many semaphore operations");
+        }
+
+        let pid = current!().process().pid();
+        let mut remain = timeout.cloned();
+        let waiter = Waiter::new();
+        // The waiter queue of the set that we have been waiting on, if any
+        let mut waited_queue: Option<Arc<WaiterQueue>> = None;
+        loop {
+            let mut sem_sets = self.sem_sets.write().unwrap();
+            let sem_set = match sem_sets.get_mut(&semid) {
+                Some(sem_set) => sem_set,
+                None if waited_queue.is_some() => {
+                    return_errno!(EIDRM, "the semaphore set has been removed")
+                }
+                None => return_errno!(EINVAL, "cannot find semaphore set by semid"),
+            };
+            // The semid may have been reused by a new set while we were waiting
+            if let Some(queue) = waited_queue.as_ref() {
+                if !Arc::ptr_eq(queue, &sem_set.waiters) {
+                    return_errno!(EIDRM, "the semaphore set has been removed");
+                }
+            }
+            if sops.iter().any(|sop| sop.num >= sem_set.nsems()) {
+                return_errno!(EFBIG, "invalid semaphore number");
+            }
+            sem_set.perm.check_perm()?;
+
+            let blocking_sop = match sem_set.try_semop(sops, pid)? {
+                None => {
+                    // The values have been changed, so other waiters may proceed
+                    sem_set.waiters.dequeue_and_wake_all();
+                    return Ok(());
+                }
+                Some(idx) => sops[idx],
+            };
+            if blocking_sop.flags.contains(SemOpFlags::IPC_NOWAIT) {
+                return_errno!(EAGAIN, "the semaphore operation would block");
+            }
+            if let Some(remain) = remain.as_ref() {
+                if *remain == Duration::new(0, 0) {
+                    return_errno!(EAGAIN, "timeout");
+                }
+            }
+
+            // Start to wait
+            let waiters = sem_set.waiters.clone();
+            waiters.reset_and_enqueue(&waiter);
+            Self::update_wait_cnt(sem_set, &blocking_sop, true);
+            // Ensure that we drop any locks before wait
+            drop(sem_sets);
+            let res = waiter.wait_mut(remain.as_mut());
+
+            let mut sem_sets = self.sem_sets.write().unwrap();
+            if let Some(sem_set) = sem_sets.get_mut(&semid) {
+                if Arc::ptr_eq(&waiters, &sem_set.waiters) {
+                    Self::update_wait_cnt(sem_set, &blocking_sop, false);
+                }
+            }
+            drop(sem_sets);
+            waited_queue = Some(waiters);
+
+            if let Err(e) = res {
+                if e.errno() == Errno::ETIMEDOUT {
+                    return_errno!(EAGAIN, "timeout");
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    fn update_wait_cnt(sem_set: &mut SemSet, sop: &SemOp, is_waiting: bool) {
+        let sem = &mut sem_set.sems[sop.num];
+        let cnt = if sop.op == 0 {
+            &mut sem.zcnt
+        } else {
+            &mut sem.ncnt
+        };
+        if is_waiting {
+            *cnt += 1;
+        } else {
+            *cnt -= 1;
+        }
+    }
+
+    pub fn do_semctl(
+        &self,
+        semid: SemId,
+        semnum: usize,
+        cmd: CmdId,
+        arg: SemctlArg,
+    ) -> Result<isize> {
+        debug!(
+            "do_semctl: semid: {:?}, semnum: {:?}, cmd: {:?}, arg: {:?}",
+            semid, semnum, cmd, arg
+        );
+        let mut sem_sets = self.sem_sets.write().unwrap();
+        let sem_set = sem_sets
+            .get_mut(&semid)
+            .ok_or_else(|| errno!(EINVAL, "cannot find semaphore set by semid"))?;
+        sem_set.perm.check_perm()?;
+
+        let pid = current!().process().pid();
+        let ret = match (cmd, arg) {
+            (IPC_RMID, _) => {
+                sem_sets.remove(&semid);
+                self.semid_manager.write().unwrap().free_id(&semid);
+                0
+            }
+            (IPC_STAT, SemctlArg::Buf(buf)) => {
+                let buf = buf.ok_or_else(|| errno!(EFAULT, "buf is empty"))?;
+                *buf = sem_set.to_c();
+                0
+            }
+            (IPC_SET, SemctlArg::ConstBuf(buf)) => {
+                sem_set.perm.set(&buf.sem_perm);
+                sem_set.sem_ctime = current_time();
+                0
+            }
+            (GETVAL, _) => sem_set.sem(semnum)?.val as isize,
+            (GETPID, _) => sem_set.sem(semnum)?.pid as isize,
+            (GETNCNT, _) => sem_set.sem(semnum)?.ncnt as isize,
+            (GETZCNT, _) => sem_set.sem(semnum)?.zcnt as isize,
+            (GETALL, SemctlArg::Array(vals)) => {
+                for (val, sem) in vals.iter_mut().zip(sem_set.sems.iter()) {
+                    *val = sem.val as u16;
+                }
+                0
+            }
+            (SETVAL, SemctlArg::Val(val)) => {
+                sem_set.set_val(semnum, val, pid)?;
+                0
+            }
+            (SETALL, SemctlArg::Array(vals)) => {
+                sem_set.set_all(vals, pid)?;
+                0
+            }
+            _ => return_errno!(EINVAL, "unimplemented cmd"),
+        };
+        Ok(ret)
+    }
+
+    /// Get the number of semaphores in a set, which is needed to check the
+    /// user buffer of GETALL and SETALL.
+    pub fn nsems_of(&self, semid: SemId) -> Result<usize> {
+        let sem_sets = self.sem_sets.read().unwrap();
+        sem_sets
+            .get(&semid)
+            .map(|sem_set| sem_set.nsems())
+            .ok_or_else(|| errno!(EINVAL, "cannot find semaphore set by semid"))
+    }
+
+    pub fn undo_when_process_exit(&self, thread: &ThreadRef) {
+        let pid = thread.process().pid();
+        let mut sem_sets = self.sem_sets.write().unwrap();
+        for sem_set in sem_sets.values_mut() {
+            sem_set.apply_undo(pid);
+        }
+    }
+}
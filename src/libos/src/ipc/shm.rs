@@ -1,18 +1,18 @@
 use super::*;
 
+use super::common::{
+    current_time, ipc_perm_t, key_t, CmdId, IpcIdManager, IpcPerm, IPC_PRIVATE, IPC_RMID, IPC_STAT,
+};
 use crate::fs::FileMode;
-use crate::process::{do_getegid, do_geteuid, gid_t, uid_t, ThreadRef};
-use crate::time::{do_gettimeofday, time_t};
+use crate::process::{gid_t, uid_t, ThreadRef};
+use crate::time::time_t;
 use crate::vm::{
     ChunkRef, MunmapChunkFlag, VMInitializer, VMMapOptionsBuilder, VMPerms, VMRange,
     USER_SPACE_VM_MANAGER,
 };
 use std::collections::{HashMap, HashSet};
 
-#[allow(non_camel_case_types)]
-pub type key_t = u32;
 pub type ShmId = u32;
-pub type CmdId = u32;
 
 // min shared seg size (bytes)
 const SHMMIN: usize = 1;
@@ -22,36 +22,13 @@ const SHMMAX: usize = (usize::MAX - (1_usize << 24));
 // also indicates the max shmid - 1 in Occlum
 const SHMMNI: ShmId = 4096;
 
-const IPC_PRIVATE: key_t = 0;
-
 // For cmd in shmctl()
-const IPC_RMID: CmdId = 0;
-const IPC_SET: CmdId = 1;
-const IPC_STAT: CmdId = 2;
-const IPC_INFO: CmdId = 3;
 const SHM_LOCK: CmdId = 11;
 const SHM_UNLOCK: CmdId = 12;
 const SHM_STAT: CmdId = 13;
 const SHM_INFO: CmdId = 14;
 const SHM_STAT_ANY: CmdId = 15;
 
-#[allow(non_camel_case_types)]
-#[derive(Debug)]
-#[repr(C)]
-struct ipc_perm_t {
-    key: key_t,
-    uid: uid_t,
-    gid: gid_t,
-    cuid: uid_t,
-    cgid: gid_t,
-    mode: u16,
-    pad1: u16,
-    seq: u16,
-    pad2: u16,
-    unused1: u64,
-    unused2: u64,
-}
-
 #[allow(non_camel_case_types)]
 #[derive(Debug)]
 #[repr(C)]
@@ -117,13 +94,7 @@ impl ShmFlags {
 #[derive(Debug)]
 struct ShmSegment {
     shmid: ShmId,
-    key: key_t,
-
-    uid: uid_t,
-    gid: gid_t,
-    cuid: uid_t,
-    cgid: gid_t,
-    mode: FileMode,
+    perm: IpcPerm,
     status: ShmStatus,
 
     shm_atime: time_t,
@@ -150,16 +121,11 @@ impl ShmSegment {
 
         Ok(ShmSegment {
             shmid: shmid,
-            key: key,
-            uid: do_geteuid().unwrap() as u32,
-            cuid: do_geteuid().unwrap() as u32,
-            gid: do_getegid().unwrap() as u32,
-            cgid: do_getegid().unwrap() as u32,
-            mode: mode,
+            perm: IpcPerm::new(key, mode),
             status: ShmStatus::empty(),
             shm_atime: 0,
             shm_dtime: 0,
-            shm_ctime: ShmManager::current_time(),
+            shm_ctime: current_time(),
             shm_cpid: current!().process().pid(),
             shm_lpid: 0,
             shm_nattach: 0,
@@ -169,8 +135,7 @@ impl ShmSegment {
     }
 
     fn check_perm(&self) -> Result<()> {
-        // TODO: Add permission control
-        Ok(())
+        self.perm.check_perm()
     }
 
     fn set_destruction(&mut self) {
@@ -217,53 +182,6 @@ impl Drop for ShmSegment {
     }
 }
 
-#[derive(Debug)]
-struct ShmIdManager {
-    used_id: HashSet<ShmId>,
-    free_num: u32,
-    last_alloc_id: ShmId,
-}
-
-impl ShmIdManager {
-    fn new() -> Self {
-        let used_id = HashSet::new();
-        let free_num = SHMMNI as u32;
-        let last_alloc_id = SHMMNI - 1;
-        ShmIdManager {
-            used_id,
-            free_num,
-            last_alloc_id,
-        }
-    }
-
-    // Always return next free id for shmid
-    fn get_new_shmid(&mut self) -> Result<ShmId> {
-        if self.free_num == 0 {
-            return_errno!(ENOSPC, "all possible shared memory IDs have been taken");
-        } else {
-            self.free_num -= 1;
-        }
-        let mut id = self.last_alloc_id + 1;
-        loop {
-            if id == SHMMNI {
-                id = 0;
-            }
-            if !self.used_id.contains(&id) {
-                break;
-            }
-            id += 1;
-        }
-        self.last_alloc_id = id;
-        Ok(id)
-    }
-
-    fn free_shmid(&mut self, shmid: &ShmId) -> Result<()> {
-        self.free_num += 1;
-        self.used_id.remove(shmid);
-        Ok(())
-    }
-}
-
 lazy_static! {
     pub static ref SHM_MANAGER: ShmManager = ShmManager::new();
 }
@@ -271,29 +189,25 @@ lazy_static! {
 #[derive(Debug)]
 pub struct ShmManager {
     shm_segments: RwLock<HashMap<ShmId, ShmSegment>>,
-    shmid_manager: RwLock<ShmIdManager>,
+    shmid_manager: RwLock<IpcIdManager>,
 }
 
 impl ShmManager {
     fn new() -> Self {
         ShmManager {
             shm_segments: RwLock::new(HashMap::new()),
-            shmid_manager: RwLock::new(ShmIdManager::new()),
+            shmid_manager: RwLock::new(IpcIdManager::new(SHMMNI)),
         }
     }
 
-    fn current_time() -> time_t {
-        do_gettimeofday().sec()
-    }
-
     fn get_new_shmid(&self) -> Result<ShmId> {
         let mut shmid_manager = self.shmid_manager.write().unwrap();
-        shmid_manager.get_new_shmid()
+        shmid_manager.alloc_id()
     }
 
-    fn free_shmid(&self, shmid: &ShmId) -> Result<()> {
+    fn free_shmid(&self, shmid: &ShmId) {
         let mut shmid_manager = self.shmid_manager.write().unwrap();
-        shmid_manager.free_shmid(&shmid)
+        shmid_manager.free_id(&shmid)
     }
 
     fn shmctl_rmshm(&self, shmid: ShmId) -> Result<()> {
@@ -301,11 +215,11 @@ impl ShmManager {
         let shm = shm_segments.get_mut(&shmid);
 
         if let Some(shm) = shm {
-            shm.shm_ctime = ShmManager::current_time();
+            shm.shm_ctime = current_time();
             shm.set_destruction();
             if shm.shm_nattach == 0 {
                 let shmid = shm.shmid;
-                self.free_shmid(&shmid)?;
+                self.free_shmid(&shmid);
                 shm_segments.remove(&shmid);
             }
         } else {
@@ -323,19 +237,7 @@ impl ShmManager {
                 Some(buf) => buf,
                 None => return_errno!(EFAULT, "buf is empty"),
             };
-            let shm_perm = ipc_perm_t {
-                key: shm.key,
-                uid: shm.uid,
-                gid: shm.uid,
-                cuid: shm.cuid,
-                cgid: shm.cgid,
-                mode: shm.mode.bits() | shm.status.bits(),
-                pad1: 0,
-                seq: 0,
-                pad2: 0,
-                unused1: 0,
-                unused2: 0,
-            };
+            let shm_perm = shm.perm.to_c(shm.status.bits());
             let shmids = shmids_t {
                 shm_perm: shm_perm,
                 shm_segsz: shm.shm_size(),
@@ -388,7 +290,7 @@ impl ShmManager {
             // Get the shm from key if the segment is not marked to be destroyed
             let shm = shm_segments
                 .values()
-                .find(|&shm| !shm.is_destruction() && shm.key == key);
+                .find(|&shm| !shm.is_destruction() && shm.perm.key == key);
             let shmid = if let Some(shm) = shm {
                 if shmflg.contains(ShmFlags::IPC_CREAT) && shmflg.contains(ShmFlags::IPC_EXCL) {
                     return_errno!(
@@ -433,7 +335,7 @@ impl ShmManager {
             shm.check_perm()?;
 
             shm.shm_nattach += 1;
-            shm.shm_atime = ShmManager::current_time();
+            shm.shm_atime = current_time();
             shm.shm_add_pid(&pid)?;
             shm.shm_lpid = pid;
             shm.shm_start()
@@ -452,7 +354,7 @@ impl ShmManager {
             .find(|shm| shm.shm_start() == addr);
 
         if let Some(shm) = shm {
-            shm.shm_dtime = ShmManager::current_time();
+            shm.shm_dtime = current_time();
             shm.shm_lpid = pid;
             shm.shm_remove_pid(&pid)?;
             shm.shm_nattach -= 1;
@@ -1,8 +1,15 @@
 use super::*;
 
+use std::time::Duration;
 use util::mem_util::from_user;
 
-use super::shm::{shmids_t, CmdId, ShmFlags, ShmId, SHM_MANAGER};
+use super::common::{CmdId, IpcFlags, IPC_64, IPC_SET, IPC_STAT};
+use super::msg::{msqid_ds_t, MsgFlags, MsgId, MSG_MANAGER};
+use super::sem::{
+    sembuf_t, semid_ds_t, SemId, SemOp, SemctlArg, GETALL, SEM_MANAGER, SETALL, SETVAL,
+};
+use super::shm::{shmids_t, ShmFlags, ShmId, SHM_MANAGER};
+use crate::time::timespec_t;
 
 pub fn do_shmget(key: key_t, size: size_t, shmflg: i32) -> Result<isize> {
     let shmflg =
@@ -34,3 +41,136 @@ pub fn do_shmctl(shmid: i32, cmd: i32, buf_u: *mut shmids_t) -> Result<isize> {
     SHM_MANAGER.do_shmctl(shmid as ShmId, cmd as CmdId, buf)?;
     Ok(0)
 }
+
+pub fn do_semget(key: key_t, nsems: i32, semflg: i32) -> Result<isize> {
+    if nsems < 0 {
+        return_errno!(EINVAL, "invalid number of semaphores");
+    }
+    let semflg = IpcFlags::from_bits_truncate(semflg as u32);
+    let semid = SEM_MANAGER.do_semget(key, nsems as usize, semflg)?;
+    Ok(semid as isize)
+}
+
+pub fn do_semop(semid: i32, sops_u: *const sembuf_t, nsops: size_t) -> Result<isize> {
+    do_semtimedop(semid, sops_u, nsops, std::ptr::null())
+}
+
+pub fn do_semtimedop(
+    semid: i32,
+    sops_u: *const sembuf_t,
+    nsops: size_t,
+    timeout_u: *const timespec_t,
+) -> Result<isize> {
+    if semid < 0 {
+        return_errno!(EINVAL, "invalid semid");
+    }
+    let sops = {
+        from_user::check_array(sops_u, nsops)?;
+        let sops_c = unsafe { std::slice::from_raw_parts(sops_u, nsops) };
+        sops_c
+            .iter()
+            .map(|sop| SemOp::from_c(sop))
+            .collect::<Result<Vec<SemOp>>>()?
+    };
+    let timeout: Option<Duration> = if timeout_u.is_null() {
+        None
+    } else {
+        from_user::check_ptr(timeout_u)?;
+        let timeout = timespec_t::from_raw_ptr(timeout_u)?;
+        Some(timeout.as_duration())
+    };
+    SEM_MANAGER.do_semtimedop(semid as SemId, &sops, timeout.as_ref())?;
+    Ok(0)
+}
+
+pub fn do_semctl(semid: i32, semnum: i32, cmd: i32, arg: u64) -> Result<isize> {
+    if semid < 0 || semnum < 0 {
+        return_errno!(EINVAL, "invalid semid or semnum");
+    }
+    let semid = semid as SemId;
+    let cmd = cmd as CmdId & !IPC_64;
+    let arg = match cmd {
+        SETVAL => SemctlArg::Val(arg as i32),
+        IPC_STAT => {
+            let buf_u = arg as *mut semid_ds_t;
+            if buf_u.is_null() {
+                SemctlArg::Buf(None)
+            } else {
+                from_user::check_mut_ptr(buf_u)?;
+                SemctlArg::Buf(Some(unsafe { &mut *buf_u }))
+            }
+        }
+        IPC_SET => {
+            let buf_u = arg as *const semid_ds_t;
+            from_user::check_ptr(buf_u)?;
+            SemctlArg::ConstBuf(unsafe { &*buf_u })
+        }
+        GETALL | SETALL => {
+            let nsems = SEM_MANAGER.nsems_of(semid)?;
+            let array_u = arg as *mut u16;
+            from_user::check_mut_array(array_u, nsems)?;
+            SemctlArg::Array(unsafe { std::slice::from_raw_parts_mut(array_u, nsems) })
+        }
+        _ => SemctlArg::None,
+    };
+    SEM_MANAGER.do_semctl(semid, semnum as usize, cmd, arg)
+}
+
+pub fn do_msgget(key: key_t, msgflg: i32) -> Result<isize> {
+    let msgflg = IpcFlags::from_bits_truncate(msgflg as u32);
+    let msqid = MSG_MANAGER.do_msgget(key, msgflg)?;
+    Ok(msqid as isize)
+}
+
+// The layout of the message buffer in user space is
+// struct msgbuf {
+//     long mtype;
+//     char mtext[msgsz];
+// };
+const MTYPE_SIZE: usize = std::mem::size_of::<i64>();
+
+pub fn do_msgsnd(msqid: i32, msgp: *const u8, msgsz: size_t, msgflg: i32) -> Result<isize> {
+    if msqid < 0 || msgsz > isize::MAX as usize {
+        return_errno!(EINVAL, "invalid msqid or msgsz");
+    }
+    let msgflg =
+        MsgFlags::from_bits(msgflg as u32).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    from_user::check_array(msgp, MTYPE_SIZE + msgsz)?;
+    let mtype = unsafe { (msgp as *const i64).read_unaligned() };
+    let mtext = unsafe { std::slice::from_raw_parts(msgp.add(MTYPE_SIZE), msgsz) };
+    MSG_MANAGER.do_msgsnd(msqid as MsgId, mtype, mtext, msgflg)?;
+    Ok(0)
+}
+
+pub fn do_msgrcv(
+    msqid: i32,
+    msgp: *mut u8,
+    msgsz: size_t,
+    msgtyp: i64,
+    msgflg: i32,
+) -> Result<isize> {
+    if msqid < 0 || msgsz > isize::MAX as usize {
+        return_errno!(EINVAL, "invalid msqid or msgsz");
+    }
+    let msgflg =
+        MsgFlags::from_bits(msgflg as u32).ok_or_else(|| errno!(EINVAL, "invalid flags"))?;
+    from_user::check_mut_array(msgp, MTYPE_SIZE + msgsz)?;
+    let mtext = unsafe { std::slice::from_raw_parts_mut(msgp.add(MTYPE_SIZE), msgsz) };
+    let (mtype, len) = MSG_MANAGER.do_msgrcv(msqid as MsgId, mtext, msgtyp, msgflg)?;
+    unsafe { (msgp as *mut i64).write_unaligned(mtype) };
+    Ok(len as isize)
+}
+
+pub fn do_msgctl(msqid: i32, cmd: i32, buf_u: *mut msqid_ds_t) -> Result<isize> {
+    if msqid < 0 {
+        return_errno!(EINVAL, "invalid msqid");
+    }
+    let buf = if !buf_u.is_null() {
+        from_user::check_mut_ptr(buf_u)?;
+        Some(unsafe { &mut *buf_u })
+    } else {
+        None
+    };
+    MSG_MANAGER.do_msgctl(msqid as MsgId, cmd as CmdId & !IPC_64, buf)?;
+    Ok(0)
+}
@@ -19,18 +19,13 @@ impl ResourceLimits {
 impl Default for ResourceLimits {
     fn default() -> ResourceLimits {
         // Get memory space limit from Occlum.json
-        let cfg_heap_size: u64 = config::LIBOS_CONFIG.process.default_heap_size as u64;
         let cfg_stack_size: u64 = config::LIBOS_CONFIG.process.default_stack_size as u64;
-        let cfg_mmap_size: u64 = config::LIBOS_CONFIG.process.default_mmap_size as u64;
 
         let stack_size = rlimit_t::new(cfg_stack_size);
 
-        // Data segment consists of three parts: initialized data, uninitialized data, and heap.
-        // Here we just approximatively consider this equal to the size of heap size.
-        let data_size = rlimit_t::new(cfg_heap_size);
-        // Address space can be approximatively considered equal to the sum of application's
-        // heap, stack and mmap size.
-        let address_space = rlimit_t::new(cfg_heap_size + cfg_stack_size + cfg_mmap_size);
+        // Data segment, i.e., the used heap and the private writable mappings, is not limited
+        // by default. The heap is as large as the heap size in Occlum.json in this case. Nor is
+        // the address space, while the memory of an app is limited by its memory quota.
 
         // Set init open files limit to 1024 which is default value for Ubuntu
         let open_files = rlimit_t::new(1024);
@@ -38,9 +33,7 @@ impl Default for ResourceLimits {
         let mut rlimits = ResourceLimits {
             rlimits: [Default::default(); RLIMIT_COUNT],
         };
-        *rlimits.get_mut(resource_t::RLIMIT_DATA) = data_size;
         *rlimits.get_mut(resource_t::RLIMIT_STACK) = stack_size;
-        *rlimits.get_mut(resource_t::RLIMIT_AS) = address_space;
         *rlimits.get_mut(resource_t::RLIMIT_NOFILE) = open_files;
 
         rlimits
@@ -134,7 +127,12 @@ impl resource_t {
 /// since the rlimits object is shared between threads in a process, the
 /// semantic of limiting resource usage on a per-process basisi is preserved.
 ///
-/// Limitation: Current implementation only takes effect on child processes.
+/// RLIMIT_AS limits the total size of the memory mapped by a process, which is checked
+/// by mmap and mremap. RLIMIT_DATA limits the total size of the used heap and the private
+/// writable mappings, which is checked by brk, mmap and mremap.
+///
+/// Limitation: The other limits of memory only take effect on child processes, e.g.,
+/// the heap of a child is as large as RLIMIT_DATA if it is not unlimited.
 pub fn do_prlimit(
     pid: pid_t,
     resource: resource_t,
@@ -2,7 +2,7 @@ use std::any::Any;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::mem::{self, MaybeUninit};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Weak;
 use std::time::Duration;
 
@@ -50,6 +50,12 @@ use crate::prelude::*;
 ///
 /// This way, both LibOS files and host files can notify the `EpollFile` about
 /// their events.
+///
+/// A file added with `EPOLLEXCLUSIVE` is observed as an exclusive observer, so
+/// when many `EpollFile`s monitor the same file exclusively (e.g., a listening
+/// socket shared by worker threads), an event is taken by only one of them that
+/// has threads waiting on it. For host files, the flag is also passed to the
+/// host epoll file.
 pub struct EpollFile {
     // All interesting entries.
     interest: SgxMutex<HashMap<FileDesc, Arc<EpollEntry>>>,
@@ -57,6 +63,8 @@ pub struct EpollFile {
     ready: SgxMutex<VecDeque<Arc<EpollEntry>>>,
     // All threads that are waiting on this epoll file.
     waiters: WaiterQueue,
+    // The number of threads that are inside the wait method.
+    num_waiting: AtomicUsize,
     // A notifier to broadcast events on this epoll file.
     notifier: IoNotifier,
     // A helper to poll the events on the interesting host files.
@@ -72,6 +80,7 @@ impl EpollFile {
         let interest = Default::default();
         let ready = Default::default();
         let waiters = WaiterQueue::new();
+        let num_waiting = AtomicUsize::new(0);
         let notifier = IoNotifier::new();
         let host_file_epoller = HostFileEpoller::new();
         let weak_self = Default::default();
@@ -81,6 +90,7 @@ impl EpollFile {
             interest,
             ready,
             waiters,
+            num_waiting,
             notifier,
             host_file_epoller,
             weak_self,
@@ -143,6 +153,17 @@ impl EpollFile {
     ) -> Result<usize> {
         debug!("epoll wait: timeout = {:?}", timeout);
 
+        self.num_waiting.fetch_add(1, Ordering::Relaxed);
+        let ret = self.do_wait(revents, timeout);
+        self.num_waiting.fetch_sub(1, Ordering::Relaxed);
+        ret
+    }
+
+    fn do_wait(
+        &self,
+        revents: &mut [MaybeUninit<EpollEvent>],
+        timeout: Option<&Duration>,
+    ) -> Result<usize> {
         let mut timeout = timeout.cloned();
         let max_count = revents.len();
         let mut reinsert = VecDeque::with_capacity(max_count);
@@ -193,6 +214,7 @@ impl EpollFile {
 
                     // Behave differently according the epoll flags
 
+                    // The entry is disabled until it is re-armed by EPOLL_CTL_MOD
                     if inner.flags.contains(EpollFlags::ONE_SHOT) {
                         inner.event.mask = IoEvents::empty();
                     }
@@ -234,7 +256,12 @@ impl EpollFile {
         }
     }
 
-    fn add_interest(&self, fd: FileDesc, mut event: EpollEvent, flags: EpollFlags) -> Result<()> {
+    fn add_interest(
+        &self,
+        fd: FileDesc,
+        mut event: EpollEvent,
+        mut flags: EpollFlags,
+    ) -> Result<()> {
         let file = current!().file(fd)?;
 
         let arc_self = self.weak_self.upgrade().unwrap();
@@ -242,7 +269,10 @@ impl EpollFile {
             return_errno!(EINVAL, "a epoll file cannot epoll itself");
         }
 
-        self.check_flags(&flags);
+        self.check_flags(&mut flags, &event)?;
+        if flags.contains(EpollFlags::EXCLUSIVE) && file.as_any().is::<EpollFile>() {
+            return_errno!(EINVAL, "an epoll file cannot be monitored exclusively");
+        }
         self.prepare_event(&mut event);
 
         let ep_entry = Arc::new(EpollEntry::new(fd, file, event, flags));
@@ -263,12 +293,20 @@ impl EpollFile {
             // Start observing events on the target file.
             let weak_observer = self.weak_self.clone() as Weak<dyn Observer<_>>;
             let weak_ep_entry = Arc::downgrade(&ep_entry);
-            notifier.register(weak_observer, Some(IoEvents::all()), Some(weak_ep_entry));
+            if flags.contains(EpollFlags::EXCLUSIVE) {
+                notifier.register_exclusive(
+                    weak_observer,
+                    Some(IoEvents::all()),
+                    Some(weak_ep_entry),
+                );
+            } else {
+                notifier.register(weak_observer, Some(IoEvents::all()), Some(weak_ep_entry));
+            }
 
             // Handle host file
-            if ep_entry.file.host_fd().is_some() {
+            if let Some(host_fd) = ep_entry.host_fd {
                 self.host_file_epoller
-                    .add_file(ep_entry.file.clone(), event, flags);
+                    .add_file(host_fd, ep_entry.file.clone(), event, flags);
                 return Ok(());
             }
         }
@@ -293,15 +331,27 @@ impl EpollFile {
             let weak_observer = self.weak_self.clone() as Weak<dyn Observer<_>>;
             notifier.unregister(&weak_observer);
 
-            if ep_entry.file.host_fd().is_some() {
-                self.host_file_epoller.del_file(&ep_entry.file);
+            // Unregister by the record, as the file may have stopped being a host file
+            if let Some(host_fd) = ep_entry.host_fd {
+                self.host_file_epoller.del_file(host_fd);
             }
         }
         Ok(())
     }
 
-    fn mod_interest(&self, fd: FileDesc, mut event: EpollEvent, flags: EpollFlags) -> Result<()> {
-        self.check_flags(&flags);
+    fn mod_interest(
+        &self,
+        fd: FileDesc,
+        mut event: EpollEvent,
+        mut flags: EpollFlags,
+    ) -> Result<()> {
+        if flags.contains(EpollFlags::EXCLUSIVE) {
+            return_errno!(
+                EINVAL,
+                "EPOLLEXCLUSIVE can only be given when adding a file"
+            );
+        }
+        self.check_flags(&mut flags, &event)?;
         self.prepare_event(&mut event);
 
         // A critical section protected by the lock of self.interest
@@ -314,12 +364,19 @@ impl EpollFile {
 
             let new_ep_inner = EpollEntryInner { event, flags };
             let mut old_ep_inner = ep_entry.inner.lock().unwrap();
+            if old_ep_inner.flags.contains(EpollFlags::EXCLUSIVE) {
+                return_errno!(
+                    EINVAL,
+                    "a file added with EPOLLEXCLUSIVE cannot be modified"
+                );
+            }
             *old_ep_inner = new_ep_inner;
             drop(old_ep_inner);
 
+            if let Some(host_fd) = ep_entry.host_fd {
+                self.host_file_epoller.mod_file(host_fd, event, flags);
+            }
             if ep_entry.file.host_fd().is_some() {
-                self.host_file_epoller
-                    .mod_file(&ep_entry.file, event, flags);
                 return Ok(());
             }
 
@@ -331,7 +388,8 @@ impl EpollFile {
         Ok(())
     }
 
-    fn push_ready(&self, ep_entry: Arc<EpollEntry>) {
+    /// Push an entry into the ready list. Return the number of threads woken up.
+    fn push_ready(&self, ep_entry: Arc<EpollEntry>) -> usize {
         // Fast path to avoid locking
         if ep_entry.is_ready.load(Ordering::Relaxed) {
             // Concurrency note:
@@ -342,13 +400,13 @@ impl EpollFile {
             // `false`, then the `EpollEntry` must be popped out of the ready list and its
             // corresponding file must be polled in the `wait` method. This means that we have
             // taken into account any interesting events happened on the file so far.
-            return;
+            return 0;
         }
 
-        self.push_ready_iter(std::iter::once(ep_entry));
+        self.push_ready_iter(std::iter::once(ep_entry))
     }
 
-    fn push_ready_iter<I: Iterator<Item = Arc<EpollEntry>>>(&self, ep_entries: I) {
+    fn push_ready_iter<I: Iterator<Item = Arc<EpollEntry>>>(&self, ep_entries: I) -> usize {
         let mut has_pushed_any = false;
 
         // A critical section protected by self.ready.lock()
@@ -367,7 +425,9 @@ impl EpollFile {
         }
 
         if has_pushed_any {
-            self.mark_ready();
+            self.mark_ready()
+        } else {
+            0
         }
     }
 
@@ -386,15 +446,24 @@ impl EpollFile {
         }
     }
 
-    fn mark_ready(&self) {
+    fn mark_ready(&self) -> usize {
         self.notifier.broadcast(&IoEvents::IN);
-        self.waiters.dequeue_and_wake_all();
+        self.waiters.dequeue_and_wake_all()
     }
 
-    fn check_flags(&self, flags: &EpollFlags) {
-        if flags.intersects(EpollFlags::EXCLUSIVE | EpollFlags::WAKE_UP) {
-            warn!("{:?} contains unsupported flags", flags);
+    fn check_flags(&self, flags: &mut EpollFlags, event: &EpollEvent) -> Result<()> {
+        if flags.contains(EpollFlags::EXCLUSIVE) {
+            if flags.contains(EpollFlags::ONE_SHOT) {
+                return_errno!(EINVAL, "EPOLLEXCLUSIVE cannot be used with EPOLLONESHOT");
+            }
+            let allowed_events = IoEvents::IN | IoEvents::OUT | IoEvents::ERR | IoEvents::HUP;
+            if !(event.mask() - allowed_events).is_empty() {
+                return_errno!(EINVAL, "EPOLLEXCLUSIVE is used with unexpected events");
+            }
         }
+        // There is no system suspend to block, so EPOLLWAKEUP is simply ignored
+        *flags -= EpollFlags::WAKE_UP;
+        Ok(())
     }
 
     fn prepare_event(&self, event: &mut EpollEvent) {
@@ -473,6 +542,31 @@ impl Observer<IoEvents> for EpollFile {
 
         self.push_ready(ep_entry);
     }
+
+    fn on_exclusive_event(
+        &self,
+        events: &IoEvents,
+        metadata: &Option<Weak<dyn Any + Send + Sync>>,
+    ) -> bool {
+        let ep_entry_opt = metadata
+            .as_ref()
+            .and_then(|weak_any| weak_any.upgrade())
+            .and_then(|strong_any| strong_any.downcast().ok());
+        let ep_entry: Arc<EpollEntry> = match ep_entry_opt {
+            None => return false,
+            Some(ep_entry) => ep_entry,
+        };
+
+        // Leave the events that are not interesting to other observers
+        let mask = ep_entry.inner.lock().unwrap().event.mask();
+        if !events.is_empty() && !events.intersects(mask) {
+            return false;
+        }
+
+        // The events are taken if any thread is going to check the ready list
+        let num_woken = self.push_ready(ep_entry);
+        num_woken > 0 || self.num_waiting.load(Ordering::Relaxed) > 0
+    }
 }
 
 impl Observer<FileTableEvent> for EpollFile {
@@ -512,6 +606,9 @@ impl AsEpollFile for FileRef {
 struct EpollEntry {
     fd: FileDesc,
     file: FileRef,
+    // The host fd with which the file is added to the host epoll file. It is recorded since a
+    // file may stop being a host file after it is added, e.g., a socket that switches to loopback.
+    host_fd: Option<FileDesc>,
     inner: SgxMutex<EpollEntryInner>,
     // Whether the entry is in the ready list
     is_ready: AtomicBool,
@@ -523,10 +620,12 @@ impl EpollEntry {
     pub fn new(fd: FileDesc, file: FileRef, event: EpollEvent, flags: EpollFlags) -> Self {
         let is_ready = Default::default();
         let is_deleted = Default::default();
+        let host_fd = file.host_fd().map(|host_fd| host_fd.to_raw());
         let inner = SgxMutex::new(EpollEntryInner { event, flags });
         Self {
             fd,
             file,
+            host_fd,
             inner,
             is_ready,
             is_deleted,
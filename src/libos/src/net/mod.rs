@@ -2,14 +2,13 @@ use super::*;
 use std;
 use untrusted::{SliceAsMutPtrAndLen, SliceAsPtrAndLen, UntrustedSlice, UntrustedSliceAlloc};
 
-pub use self::io_multiplexing::{
-    clear_notifier_status, notify_thread, wait_for_notification, EpollEvent, IoEvent, PollEvent,
-    PollEventFlags, PollFd, THREAD_NOTIFIERS,
-};
+pub use self::io_multiplexing::{EpollEvent, EventMonitor, EventMonitorBuilder, PollFd};
 pub use self::socket::{
-    mmsghdr, msghdr, msghdr_mut, socketpair, unix_socket, AddressFamily, AsUnixSocket, FileFlags,
-    HostSocket, HostSocketType, HowToShut, Iovs, IovsMut, MsgHdr, MsgHdrFlags, MsgHdrMut,
-    RecvFlags, SendFlags, SliceAsLibcIovec, SockAddr, SocketType, UnixAddr,
+    init_net_ifaces, is_loopback_bind, is_loopback_peer, mmsghdr, mmsghdr_mut, msghdr, msghdr_mut,
+    netlink_socket, socketpair, switch_to_loopback, unix_socket, AddressFamily, AsLoopbackSocket,
+    AsNetlinkSocket, AsUnixSocket, FileFlags, HostSocket, HostSocketType, HowToShut, Iovs, IovsMut,
+    LoopbackSocket, MsgHdr, MsgHdrFlags, MsgHdrMut, NetlinkAddr, NetlinkSocket, RecvFlags,
+    SendFlags, SliceAsLibcIovec, SockAddr, SocketType, UnixAddr, UnixSocket, NETLINK_ROUTE,
 };
 pub use self::syscalls::*;
 
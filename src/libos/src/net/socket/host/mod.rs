@@ -1,10 +1,12 @@
 use std::any::Any;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem;
+use std::sync::Weak;
 
 use atomic::Atomic;
 
 use super::*;
+use crate::events::Observer;
 use crate::fs::{
     occlum_ocall_ioctl, AccessMode, CreationFlags, File, FileRef, HostFd, IoEvents, IoNotifier,
     IoctlCmd, StatusFlags,
@@ -18,9 +20,15 @@ mod socket_file;
 /// Native linux socket
 #[derive(Debug)]
 pub struct HostSocket {
+    domain: AddressFamily,
+    socket_type: SocketType,
     host_fd: HostFd,
     host_events: Atomic<IoEvents>,
-    notifier: IoNotifier,
+    notifier: Arc<IoNotifier>,
+    // The loopback socket that takes the place of the host socket. See `super::loopback`.
+    replacement: RwLock<Option<Replacement>>,
+    // The options set by setsockopt, which are carried to the replacement
+    sockopts: SgxMutex<Vec<(i32, i32, Vec<u8>)>>,
 }
 
 impl HostSocket {
@@ -36,16 +44,20 @@ impl HostSocket {
             protocol
         )) as FileDesc;
         let host_fd = HostFd::new(raw_host_fd);
-        Ok(HostSocket::from_host_fd(host_fd))
+        Ok(HostSocket::from_host_fd(host_fd, domain, socket_type))
     }
 
-    fn from_host_fd(host_fd: HostFd) -> HostSocket {
+    fn from_host_fd(host_fd: HostFd, domain: AddressFamily, socket_type: SocketType) -> HostSocket {
         let host_events = Atomic::new(IoEvents::empty());
-        let notifier = IoNotifier::new();
+        let notifier = Arc::new(IoNotifier::new());
         Self {
+            domain,
+            socket_type,
             host_fd,
             host_events,
             notifier,
+            replacement: RwLock::new(None),
+            sockopts: SgxMutex::new(Vec::new()),
         }
     }
 
@@ -83,7 +95,10 @@ impl HostSocket {
         } else {
             None
         };
-        Ok((HostSocket::from_host_fd(host_fd), addr_option))
+        Ok((
+            HostSocket::from_host_fd(host_fd, self.domain, self.socket_type),
+            addr_option,
+        ))
     }
 
     pub fn connect(&self, addr: &Option<SockAddr>) -> Result<()> {
@@ -129,6 +144,14 @@ impl HostSocket {
         Ok((bytes_recv, addr_option))
     }
 
+    pub fn domain(&self) -> AddressFamily {
+        self.domain
+    }
+
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
     pub fn raw_host_fd(&self) -> FileDesc {
         self.host_fd.to_raw()
     }
@@ -137,6 +160,94 @@ impl HostSocket {
         try_libc!(libc::ocall::shutdown(self.raw_host_fd() as i32, how.bits()));
         Ok(())
     }
+
+    /// Record an option that has been set on the host socket.
+    pub fn record_sockopt(&self, level: i32, optname: i32, optval: &[u8]) {
+        let mut sockopts = self.sockopts.lock().unwrap();
+        sockopts.retain(|(l, n, _)| (*l, *n) != (level, optname));
+        sockopts.push((level, optname, optval.to_vec()));
+    }
+
+    pub fn recorded_sockopts(&self) -> Vec<(i32, i32, Vec<u8>)> {
+        self.sockopts.lock().unwrap().clone()
+    }
+
+    /// The loopback socket that has taken the place of the host socket, if any.
+    pub fn replacement(&self) -> Option<FileRef> {
+        self.replacement
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|replacement| replacement.file.clone())
+    }
+
+    /// Let the socket created by `new_socket` take the place of the host socket, if the operation
+    /// `f` on it succeeds. If the host socket has been replaced, `f` is performed on the existing
+    /// replacement instead.
+    ///
+    /// The replacement is done inside the file, so all the file descriptors that refer to the
+    /// file, e.g., the duplicated ones and the ones in other processes, see the new socket.
+    pub fn replace_with<T>(
+        &self,
+        new_socket: impl FnOnce() -> Result<FileRef>,
+        f: impl FnOnce(&FileRef) -> Result<T>,
+    ) -> Result<T> {
+        let mut replacement = self.replacement.write().unwrap();
+        if let Some(replacement) = replacement.as_ref() {
+            return f(&replacement.file);
+        }
+
+        let file = new_socket()?;
+        let ret = f(&file)?;
+        let relay = Arc::new(Relay {
+            notifier: Arc::downgrade(&self.notifier),
+        });
+        if let Some(notifier) = file.notifier() {
+            notifier.register(
+                Arc::downgrade(&relay) as Weak<dyn Observer<IoEvents>>,
+                None,
+                None,
+            );
+        }
+        debug!(
+            "host socket {} is replaced by {:?}",
+            self.raw_host_fd(),
+            file
+        );
+        *replacement = Some(Replacement {
+            file,
+            _relay: relay,
+        });
+        Ok(ret)
+    }
+}
+
+/// The socket that takes the place of a host socket.
+struct Replacement {
+    file: FileRef,
+    _relay: Arc<Relay>,
+}
+
+impl Debug for Replacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Replacement")
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+/// An observer that relays the events of the replacement to the notifier of the host socket,
+/// which may have been registered by poll or epoll.
+struct Relay {
+    notifier: Weak<IoNotifier>,
+}
+
+impl Observer<IoEvents> for Relay {
+    fn on_event(&self, event: &IoEvents, _metadata: &Option<Weak<dyn Any + Send + Sync>>) {
+        if let Some(notifier) = self.notifier.upgrade() {
+            notifier.broadcast(event);
+        }
+    }
 }
 
 pub trait HostSocketType {
@@ -145,8 +256,13 @@ pub trait HostSocketType {
 
 impl HostSocketType for FileRef {
     fn as_host_socket(&self) -> Result<&HostSocket> {
-        self.as_any()
+        let socket = self
+            .as_any()
             .downcast_ref::<HostSocket>()
-            .ok_or_else(|| errno!(EBADF, "not a host socket"))
+            .ok_or_else(|| errno!(EBADF, "not a host socket"))?;
+        if socket.replacement().is_some() {
+            return_errno!(EBADF, "the host socket has been replaced");
+        }
+        Ok(socket)
     }
 }
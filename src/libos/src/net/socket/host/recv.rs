@@ -1,5 +1,7 @@
 use super::*;
+use crate::time::timespec_t;
 use crate::untrusted::{SliceAsMutPtrAndLen, SliceAsPtrAndLen, UntrustedSliceAlloc};
+use std::time::Duration;
 
 impl HostSocket {
     pub fn recv(&self, buf: &mut [u8], flags: RecvFlags) -> Result<usize> {
@@ -24,6 +26,173 @@ impl HostSocket {
         Ok(bytes_recvd)
     }
 
+    /// Receive multiple messages with a single OCall, and return the number of bytes received
+    /// for each of the received messages.
+    ///
+    /// The remaining time of `timeout` is updated on return.
+    pub fn recvmmsg(
+        &self,
+        msgs: &mut [MsgHdrMut],
+        flags: RecvFlags,
+        timeout: Option<&mut Duration>,
+    ) -> Result<Vec<usize>> {
+        let vlen = msgs.len();
+        // The data of all the messages share one untrusted buffer
+        let data_length = msgs.iter().map(|msg| msg.get_iovs().total_bytes()).sum();
+        let u_allocator = UntrustedSliceAlloc::new(data_length)?;
+        let mut u_data = Vec::with_capacity(vlen);
+        for msg in msgs.iter() {
+            let mut bufs = Vec::new();
+            for buf in msg.get_iovs().as_slices().iter() {
+                bufs.push(u_allocator.new_slice_mut(buf.len())?);
+            }
+            u_data.push(bufs);
+        }
+        // The names and the controls of all the messages are flattened
+        let mut namelens: Vec<libc::socklen_t> = msgs
+            .iter()
+            .map(|msg| msg.get_name_max_len() as libc::socklen_t)
+            .collect();
+        let mut controllens: Vec<usize> =
+            msgs.iter().map(|msg| msg.get_control_max_len()).collect();
+        let max_namelens = namelens.clone();
+        let max_controllens = controllens.clone();
+        let mut names = vec![0_u8; namelens.iter().map(|len| *len as usize).sum()];
+        let mut controls = vec![0_u8; controllens.iter().sum()];
+
+        let (lens_recvd, flags_recvd) = self.do_recvmmsg_untrusted_data(
+            &mut u_data,
+            flags,
+            &mut names,
+            &mut namelens,
+            &mut controls,
+            &mut controllens,
+            timeout,
+        )?;
+
+        // Check values returned from outside the enclave
+        let count = lens_recvd.len();
+        assert!(count <= vlen);
+        for i in 0..count {
+            let max_bytes_recvd = u_data[i].iter().map(|slice| slice.len()).sum();
+            // For MSG_TRUNC the real length of the datagram is returned
+            if flags.contains(RecvFlags::MSG_TRUNC) && lens_recvd[i] > max_bytes_recvd {
+                assert!(flags_recvd[i].contains(MsgHdrFlags::MSG_TRUNC));
+            } else {
+                assert!(lens_recvd[i] <= max_bytes_recvd);
+            }
+            assert!(namelens[i] <= max_namelens[i]);
+            assert!(controllens[i] <= max_controllens[i]);
+        }
+
+        // Copy the received messages into the enclave
+        let mut name_pos = 0;
+        let mut control_pos = 0;
+        for (i, msg) in msgs.iter_mut().enumerate().take(count) {
+            let name_end = name_pos + max_namelens[i] as usize;
+            let control_end = control_pos + max_controllens[i];
+            {
+                let (iovs, name, control) = msg.get_iovs_name_and_control_mut();
+                let mut remain = lens_recvd[i];
+                for (buf, u_buf) in iovs.as_slices_mut().iter_mut().zip(u_data[i].iter()) {
+                    let len = std::cmp::min(remain, buf.len());
+                    u_buf.write_to_slice(&mut buf[..len])?;
+                    remain -= len;
+                    if remain == 0 {
+                        break;
+                    }
+                }
+                if let Some(name) = name {
+                    name.copy_from_slice(&names[name_pos..name_end]);
+                }
+                if let Some(control) = control {
+                    control.copy_from_slice(&controls[control_pos..control_end]);
+                }
+            }
+            msg.set_name_len(namelens[i] as usize)?;
+            msg.set_control_len(controllens[i])?;
+            msg.set_flags(flags_recvd[i]);
+            name_pos = name_end;
+            control_pos = control_end;
+        }
+        Ok(lens_recvd)
+    }
+
+    fn do_recvmmsg_untrusted_data(
+        &self,
+        data: &mut [Vec<UntrustedSlice>],
+        flags: RecvFlags,
+        names: &mut [u8],
+        namelens: &mut [libc::socklen_t],
+        controls: &mut [u8],
+        controllens: &mut [usize],
+        timeout: Option<&mut Duration>,
+    ) -> Result<(Vec<usize>, Vec<MsgHdrFlags>)> {
+        // Prepare the arguments for OCall
+        let host_fd = self.raw_host_fd() as i32;
+        let vlen = data.len();
+        // Iovs of all the messages
+        let iovlens: Vec<usize> = data.iter().map(|bufs| bufs.len()).collect();
+        let mut raw_iovs: Vec<libc::iovec> = data
+            .iter()
+            .flat_map(|bufs| bufs.iter().map(|slice| slice.as_ref().as_libc_iovec()))
+            .collect();
+        let (msg_iovs, msg_iovs_len) = raw_iovs.as_mut_slice().as_mut_ptr_and_len();
+        // Names and controls
+        let (msg_names, msg_names_len) = names.as_mut_ptr_and_len();
+        let (msg_controls, msg_controls_len) = controls.as_mut_ptr_and_len();
+        // Outputs of each message
+        let mut msg_flags_recvd = vec![0_i32; vlen];
+        let mut msg_lens = vec![0_u32; vlen];
+        // Timeout
+        let mut raw_timeout = timeout.as_ref().map(|timeout| timespec_t::from(**timeout));
+        let raw_timeout_ptr = raw_timeout
+            .as_mut()
+            .map(|timeout| timeout as *mut timespec_t)
+            .unwrap_or(std::ptr::null_mut());
+
+        // Do OCall
+        let retval = try_libc!({
+            let mut retval = 0_i32;
+            let status = occlum_ocall_recvmmsg(
+                &mut retval as *mut i32,
+                host_fd,
+                msg_iovs,
+                msg_iovs_len,
+                iovlens.as_ptr(),
+                msg_names as *mut c_void,
+                msg_names_len,
+                namelens.as_mut_ptr(),
+                msg_controls as *mut c_void,
+                msg_controls_len,
+                controllens.as_mut_ptr(),
+                msg_flags_recvd.as_mut_ptr(),
+                msg_lens.as_mut_ptr(),
+                vlen as c_uint,
+                flags.bits(),
+                raw_timeout_ptr,
+            );
+            assert!(status == sgx_status_t::SGX_SUCCESS);
+            retval
+        });
+
+        if let (Some(timeout), Some(raw_timeout)) = (timeout, raw_timeout) {
+            raw_timeout.validate()?;
+            *timeout = std::cmp::min(*timeout, raw_timeout.as_duration());
+        }
+
+        // Guarantted by try_libc!
+        debug_assert!(retval >= 0);
+        let count = retval as usize;
+        assert!(count <= vlen);
+        let lens_recvd = msg_lens[..count].iter().map(|len| *len as usize).collect();
+        let flags_recvd = msg_flags_recvd[..count]
+            .iter()
+            .map(|flags| MsgHdrFlags::from_bits(*flags).unwrap())
+            .collect();
+        Ok((lens_recvd, flags_recvd))
+    }
+
     pub(super) fn do_recvmsg(
         &self,
         data: &mut [&mut [u8]],
@@ -154,4 +323,23 @@ extern "C" {
         msg_flags: *mut c_int,
         flags: c_int,
     ) -> sgx_status_t;
+
+    fn occlum_ocall_recvmmsg(
+        ret: *mut c_int,
+        fd: c_int,
+        msg_iovs: *mut libc::iovec,
+        msg_iovs_len: size_t,
+        msg_iovlens: *const size_t,
+        msg_names: *mut c_void,
+        msg_names_len: size_t,
+        msg_namelens: *mut libc::socklen_t,
+        msg_controls: *mut c_void,
+        msg_controls_len: size_t,
+        msg_controllens: *mut size_t,
+        msg_flags: *mut c_int,
+        msg_lens: *mut c_uint,
+        vlen: c_uint,
+        flags: c_int,
+        timeout: *mut timespec_t,
+    ) -> sgx_status_t;
 }
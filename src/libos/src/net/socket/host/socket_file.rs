@@ -12,10 +12,16 @@ use crate::fs::{
 //TODO: refactor write syscall to allow zero length with non-zero buffer
 impl File for HostSocket {
     fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.read(buf);
+        }
         self.recv(buf, RecvFlags::empty())
     }
 
     fn write(&self, buf: &[u8]) -> Result<usize> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.write(buf);
+        }
         self.send(buf, SendFlags::empty())
     }
 
@@ -34,11 +40,17 @@ impl File for HostSocket {
     }
 
     fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.readv(bufs);
+        }
         let (bytes_recvd, _, _, _) = self.do_recvmsg(bufs, RecvFlags::empty(), None, None)?;
         Ok(bytes_recvd)
     }
 
     fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.writev(bufs);
+        }
         self.do_sendmsg(bufs, SendFlags::empty(), None, None)
     }
 
@@ -47,6 +59,9 @@ impl File for HostSocket {
     }
 
     fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.ioctl(cmd);
+        }
         self.ioctl_impl(cmd)
     }
 
@@ -55,6 +70,9 @@ impl File for HostSocket {
     }
 
     fn status_flags(&self) -> Result<StatusFlags> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.status_flags();
+        }
         let ret = try_libc!(libc::ocall::fcntl_arg0(
             self.raw_host_fd() as i32,
             libc::F_GETFL
@@ -63,6 +81,9 @@ impl File for HostSocket {
     }
 
     fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        if let Some(replacement) = self.replacement() {
+            return replacement.set_status_flags(new_status_flags);
+        }
         let raw_status_flags = (new_status_flags & STATUS_FLAGS_MASK).bits();
         try_libc!(libc::ocall::fcntl_arg1(
             self.raw_host_fd() as i32,
@@ -73,15 +94,22 @@ impl File for HostSocket {
     }
 
     fn poll_new(&self) -> IoEvents {
+        if let Some(replacement) = self.replacement() {
+            return replacement.poll_new();
+        }
         self.host_events.load(Ordering::Acquire)
     }
 
+    // The replaced socket is polled by its events in the LibOS, like the other LibOS files
     fn host_fd(&self) -> Option<&HostFd> {
+        if self.replacement().is_some() {
+            return None;
+        }
         Some(&self.host_fd)
     }
 
     fn notifier(&self) -> Option<&IoNotifier> {
-        Some(&self.notifier)
+        Some(&*self.notifier)
     }
 
     fn update_host_events(&self, ready: &IoEvents, mask: &IoEvents, trigger_notifier: bool) {
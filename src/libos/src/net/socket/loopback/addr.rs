@@ -0,0 +1,118 @@
+use super::*;
+use std::mem;
+
+/// An IPv4 or IPv6 address with a port.
+///
+/// IPv4 addresses are stored as IPv4-mapped IPv6 addresses, so that an IPv6 socket can reach an
+/// IPv4 socket through the mapped address, as it can on Linux.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InetAddr {
+    ip: [u8; 16],
+    port: u16,
+}
+
+const V4_MAPPED_PREFIX: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff];
+
+impl InetAddr {
+    /// Parse the address of the given domain.
+    pub fn from_sockaddr(addr: &SockAddr, domain: AddressFamily) -> Result<Self> {
+        let family = unsafe { (*addr.as_ptr()).sa_family };
+        if family != domain as libc::sa_family_t {
+            return_errno!(EAFNOSUPPORT, "the address family mismatches the socket");
+        }
+
+        match domain {
+            AddressFamily::INET => {
+                let addr_in = unsafe { &*(addr.as_ptr() as *const libc::sockaddr_in) };
+                let mut ip = [0; 16];
+                ip[..12].copy_from_slice(&V4_MAPPED_PREFIX);
+                ip[12..].copy_from_slice(&addr_in.sin_addr.s_addr.to_ne_bytes());
+                Ok(Self {
+                    ip,
+                    port: u16::from_be(addr_in.sin_port),
+                })
+            }
+            AddressFamily::INET6 => {
+                let addr_in6 = unsafe { &*(addr.as_ptr() as *const libc::sockaddr_in6) };
+                Ok(Self {
+                    ip: addr_in6.sin6_addr.s6_addr,
+                    port: u16::from_be(addr_in6.sin6_port),
+                })
+            }
+            _ => return_errno!(EAFNOSUPPORT, "not an internet address"),
+        }
+    }
+
+    /// Convert to the address of the given domain.
+    pub fn to_sockaddr(&self, domain: AddressFamily) -> SockAddr {
+        let mut sock_addr = SockAddr::default();
+        let len = match domain {
+            AddressFamily::INET => {
+                let mut addr_in: libc::sockaddr_in = unsafe { mem::zeroed() };
+                addr_in.sin_family = libc::AF_INET as libc::sa_family_t;
+                addr_in.sin_port = self.port.to_be();
+                let mut octets = [0; 4];
+                octets.copy_from_slice(&self.ip[12..]);
+                addr_in.sin_addr.s_addr = u32::from_ne_bytes(octets);
+                unsafe {
+                    std::ptr::write(sock_addr.as_mut_ptr() as *mut libc::sockaddr_in, addr_in);
+                }
+                mem::size_of::<libc::sockaddr_in>()
+            }
+            _ => {
+                let mut addr_in6: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+                addr_in6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                addr_in6.sin6_port = self.port.to_be();
+                addr_in6.sin6_addr.s6_addr = self.ip;
+                unsafe {
+                    std::ptr::write(sock_addr.as_mut_ptr() as *mut libc::sockaddr_in6, addr_in6);
+                }
+                mem::size_of::<libc::sockaddr_in6>()
+            }
+        };
+        sock_addr.set_len(len).unwrap();
+        sock_addr
+    }
+
+    /// The loopback address of the given domain with the port.
+    pub fn loopback(domain: AddressFamily, port: u16) -> Self {
+        let mut addr = Self::unspecified(domain).with_port(port);
+        if domain == AddressFamily::INET {
+            addr.ip[12..].copy_from_slice(&[127, 0, 0, 1]);
+        } else {
+            addr.ip[15] = 1;
+        }
+        addr
+    }
+
+    /// The wildcard address of the given domain, which is reported by an unbound socket.
+    pub fn unspecified(domain: AddressFamily) -> Self {
+        let mut ip = [0; 16];
+        if domain == AddressFamily::INET {
+            ip[..12].copy_from_slice(&V4_MAPPED_PREFIX);
+        }
+        Self { ip, port: 0 }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn with_port(&self, port: u16) -> Self {
+        Self { ip: self.ip, port }
+    }
+
+    /// Whether the address is in 127.0.0.0/8 or is ::1.
+    pub fn is_loopback(&self) -> bool {
+        if self.is_v4() {
+            self.ip[12] == 127
+        } else {
+            self.ip[..15].iter().all(|&b| b == 0) && self.ip[15] == 1
+        }
+    }
+
+    /// Whether the address is an IPv4 (or IPv4-mapped) address.
+    pub fn is_v4(&self) -> bool {
+        self.ip[..12] == V4_MAPPED_PREFIX
+    }
+}
@@ -0,0 +1,415 @@
+use super::port_space::PORT_SPACE;
+use super::*;
+use fs::{AccessMode, IoEvents, IoNotifier, IoctlCmd, StatusFlags};
+use rcore_fs::vfs::{FileType, Metadata, Timespec};
+use std::any::Any;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The maximum length of the data of a UDP packet over IPv4
+const MAX_PAYLOAD_LEN: usize = 65507;
+
+/// The length of the receive queue, i.e., /proc/sys/net/core/rmem_default
+const RECV_BUF_SIZE: usize = 208 * 1024;
+
+/// A UDP packet.
+#[derive(Clone)]
+pub struct Packet {
+    data: Vec<u8>,
+    src_addr: InetAddr,
+}
+
+impl QueueMsg for Packet {
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// SOCK_DGRAM loopback socket, i.e., an in-enclave UDP socket.
+///
+/// Each socket owns a queue of the received packets, which is registered to the port space when
+/// the socket is bound. As UDP is unreliable, a packet is dropped silently if no socket is bound
+/// to the destination or the queue of the receiver is full.
+pub struct LoopbackDatagram {
+    domain: AddressFamily,
+    inner: SgxMutex<Inner>,
+    queue: Arc<MsgQueue<Packet>>,
+    nonblocking: AtomicBool,
+    sockopts: SockOptions,
+}
+
+struct Inner {
+    addr: Option<InetAddr>,
+    peer_addr: Option<InetAddr>,
+    is_write_shutdown: bool,
+}
+
+impl LoopbackDatagram {
+    pub fn new(domain: AddressFamily, flags: FileFlags) -> Self {
+        Self {
+            domain,
+            inner: SgxMutex::new(Inner {
+                addr: None,
+                peer_addr: None,
+                is_write_shutdown: false,
+            }),
+            queue: Arc::new(MsgQueue::new(RECV_BUF_SIZE)),
+            nonblocking: AtomicBool::new(flags.contains(FileFlags::SOCK_NONBLOCK)),
+            sockopts: SockOptions::new(),
+        }
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+    }
+
+    /// Bind the socket to an ephemeral port if it is not bound yet, so that the receiver can
+    /// reply to it.
+    fn autobind(&self, inner: &mut Inner, peer_addr: &InetAddr) -> Result<InetAddr> {
+        if let Some(addr) = inner.addr {
+            return Ok(addr);
+        }
+        let mut addr = if peer_addr.is_v4() {
+            InetAddr::loopback(AddressFamily::INET, 0)
+        } else {
+            InetAddr::loopback(AddressFamily::INET6, 0)
+        };
+        PORT_SPACE.bind_datagram(&mut addr, self.queue.clone())?;
+        inner.addr = Some(addr);
+        Ok(addr)
+    }
+
+    /// Send a packet to the given address, or to the connected peer if no address is given.
+    fn send(&self, bufs: &[&[u8]], flags: SendFlags, addr: &Option<SockAddr>) -> Result<usize> {
+        let len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if len > MAX_PAYLOAD_LEN {
+            return_errno!(EMSGSIZE, "the packet is too long");
+        }
+
+        let (src_addr, dst_addr) = {
+            let mut inner = self.inner();
+            if inner.is_write_shutdown {
+                return_errno!(EPIPE, "the socket has been shutdown for writing");
+            }
+            let dst_addr = match addr {
+                Some(addr) => parse_peer_addr(addr, self.domain)?,
+                None => inner
+                    .peer_addr
+                    .ok_or_else(|| errno!(EDESTADDRREQ, "the destination is not specified"))?,
+            };
+            (self.autobind(&mut inner, &dst_addr)?, dst_addr)
+        };
+
+        if let Some(queue) = PORT_SPACE.get_datagram(&dst_addr) {
+            let mut data = Vec::with_capacity(len);
+            for buf in bufs {
+                data.extend_from_slice(buf);
+            }
+            let packet = Packet { data, src_addr };
+            if let Err(e) = queue.push(packet, true) {
+                debug!("the packet to {:?} is dropped: {:?}", dst_addr, e.errno());
+            }
+        }
+        Ok(len)
+    }
+
+    /// Receive a packet. Return `None` if the receiving is shutdown and there are no more
+    /// packets.
+    fn recv(&self, flags: RecvFlags) -> Result<Option<Packet>> {
+        let nonblocking = self.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+        self.queue
+            .pop(nonblocking, flags.contains(RecvFlags::MSG_PEEK))
+    }
+
+    fn poll(&self) -> IoEvents {
+        let mut events = self.queue.poll_read() & IoEvents::IN;
+        if self.queue.is_write_shutdown() {
+            events |= IoEvents::RDHUP | IoEvents::IN;
+        }
+        // A UDP socket is always writable, as the packets that do not fit are dropped
+        events | IoEvents::OUT
+    }
+}
+
+impl LoopbackSocket for LoopbackDatagram {
+    fn domain(&self) -> AddressFamily {
+        self.domain
+    }
+
+    fn socket_type(&self) -> SocketType {
+        SocketType::DGRAM
+    }
+
+    fn addr(&self) -> Result<SockAddr> {
+        let addr = self
+            .inner()
+            .addr
+            .unwrap_or(InetAddr::unspecified(self.domain));
+        Ok(addr.to_sockaddr(self.domain))
+    }
+
+    fn peer_addr(&self) -> Result<SockAddr> {
+        self.inner()
+            .peer_addr
+            .map(|addr| addr.to_sockaddr(self.domain))
+            .ok_or_else(|| errno!(ENOTCONN, "the socket is not connected"))
+    }
+
+    fn bind(&self, addr: &SockAddr) -> Result<()> {
+        let mut addr = InetAddr::from_sockaddr(addr, self.domain)?;
+        if !addr.is_loopback() {
+            return_errno!(EADDRNOTAVAIL, "only the loopback addresses can be bound");
+        }
+
+        let mut inner = self.inner();
+        if inner.addr.is_some() {
+            return_errno!(EINVAL, "the socket is already bound");
+        }
+        PORT_SPACE.bind_datagram(&mut addr, self.queue.clone())?;
+        inner.addr = Some(addr);
+        Ok(())
+    }
+
+    fn listen(&self, backlog: i32) -> Result<()> {
+        return_errno!(EOPNOTSUPP, "datagram socket does not support listen");
+    }
+
+    /// Set the default destination of the sending. If the address is `None`, the socket is
+    /// disconnected.
+    fn connect(&self, addr: &Option<SockAddr>) -> Result<()> {
+        debug!("connect to {:?}", addr);
+
+        let mut inner = self.inner();
+        let peer_addr = match addr {
+            Some(addr) => {
+                let peer_addr = parse_peer_addr(addr, self.domain)?;
+                self.autobind(&mut inner, &peer_addr)?;
+                Some(peer_addr)
+            }
+            None => None,
+        };
+        inner.peer_addr = peer_addr;
+        Ok(())
+    }
+
+    fn accept(&self, flags: FileFlags) -> Result<(Arc<dyn File>, Option<SockAddr>)> {
+        return_errno!(EOPNOTSUPP, "datagram socket does not support accept");
+    }
+
+    fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<SockAddr>) -> Result<usize> {
+        self.send(&[buf], flags, addr)
+    }
+
+    fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<SockAddr>)> {
+        match self.recv(flags)? {
+            Some(packet) => {
+                let copied = packet.copy_to(&mut [buf]);
+                let data_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+                    packet.len()
+                } else {
+                    copied
+                };
+                Ok((data_len, Some(packet.src_addr.to_sockaddr(self.domain))))
+            }
+            None => Ok((0, None)),
+        }
+    }
+
+    fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
+        let addr = match msg_hdr.get_name() {
+            Some(name) => Some(unsafe {
+                SockAddr::try_from_raw(
+                    name.as_ptr() as *const libc::sockaddr,
+                    name.len() as libc::socklen_t,
+                )?
+            }),
+            None => None,
+        };
+        if msg_hdr.get_control().is_some() {
+            warn!("control messages are ignored by loopback datagram socket");
+        }
+        self.send(msg_hdr.get_iovs().as_slices(), flags, &addr)
+    }
+
+    fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
+        let packet = match self.recv(flags)? {
+            Some(packet) => packet,
+            None => {
+                msg_hdr.set_name_len(0)?;
+                msg_hdr.set_control_len(0)?;
+                return Ok(0);
+            }
+        };
+
+        let (copied, name_len) = {
+            let (iovs, name, _) = msg_hdr.get_iovs_name_and_control_mut();
+            let copied = packet.copy_to(iovs.as_slices_mut());
+            let src_addr = packet.src_addr.to_sockaddr(self.domain);
+            let name_len = name.map(|name| src_addr.copy_to_slice(name)).unwrap_or(0);
+            (copied, name_len)
+        };
+        msg_hdr.set_name_len(name_len)?;
+        msg_hdr.set_control_len(0)?;
+
+        if copied < packet.len() {
+            msg_hdr.set_flags(MsgHdrFlags::MSG_TRUNC);
+        } else {
+            msg_hdr.set_flags(MsgHdrFlags::empty());
+        }
+
+        if flags.contains(RecvFlags::MSG_TRUNC) {
+            Ok(packet.len())
+        } else {
+            Ok(copied)
+        }
+    }
+
+    fn shutdown(&self, how: HowToShut) -> Result<()> {
+        let mut inner = self.inner();
+        if inner.peer_addr.is_none() {
+            return_errno!(ENOTCONN, "the socket is not connected");
+        }
+        if how.to_shut_read() {
+            // No more packets can be pushed to the queue, while the queued ones can still be
+            // received.
+            self.queue.shutdown_write();
+        }
+        if how.to_shut_write() {
+            inner.is_write_shutdown = true;
+        }
+        Ok(())
+    }
+
+    fn sockopts(&self) -> &SockOptions {
+        &self.sockopts
+    }
+}
+
+impl File for LoopbackDatagram {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.readv(&mut [buf])
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.writev(&[buf])
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.read(buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.write(buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        // The part of the packet that does not fit into the buffers is discarded
+        Ok(self
+            .recv(RecvFlags::empty())?
+            .map(|packet| packet.copy_to(bufs))
+            .unwrap_or(0))
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.send(bufs, SendFlags::empty(), &None)
+    }
+
+    fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        match cmd {
+            IoctlCmd::TCGETS(_) => return_errno!(ENOTTY, "not tty device"),
+            IoctlCmd::TCSETS(_) => return_errno!(ENOTTY, "not tty device"),
+            IoctlCmd::FIONBIO(nonblocking) => {
+                self.set_nonblocking(**nonblocking != 0);
+            }
+            // The length of the next packet, which is what Linux reports for UDP sockets
+            IoctlCmd::FIONREAD(arg) => {
+                **arg = self.queue.next_msg_len().min(std::i32::MAX as usize) as i32;
+            }
+            _ => return_errno!(EINVAL, "unknown ioctl cmd for loopback socket"),
+        }
+        Ok(0)
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDWR)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        if self.nonblocking() {
+            Ok(StatusFlags::O_NONBLOCK)
+        } else {
+            Ok(StatusFlags::empty())
+        }
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        // Only O_NONBLOCK is supported
+        self.set_nonblocking(new_status_flags.contains(StatusFlags::O_NONBLOCK));
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        self.poll()
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(self.queue.notifier())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Socket,
+            mode: 0o666,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+}
+
+impl Debug for LoopbackDatagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoopbackDatagram")
+            .field("domain", &self.domain)
+            .field("addr", &self.inner().addr)
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for LoopbackDatagram {
+    fn drop(&mut self) {
+        // Drop the queued packets and the later ones
+        self.queue.shutdown_read();
+        if let Some(addr) = &self.inner().addr {
+            PORT_SPACE.unbind_datagram(addr);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! The in-enclave loopback transport.
+//!
+//! When `net.loopback` is set to `enclave` in Occlum.json, an AF_INET or AF_INET6 socket that is
+//! bound to a loopback address, or that connects or sends to a loopback address bound by another
+//! in-enclave socket, is replaced by a loopback socket. The data of the loopback sockets is
+//! transferred within the enclave, so it is never exposed to the host and costs no OCalls.
+//!
+//! The sockets bound to the wildcard address stay on the host, so they can still accept the
+//! connections from both the host and the enclave.
+//!
+//! The loopback socket takes the place of the host socket inside the same file, so the file
+//! descriptors duplicated from or shared with the socket are switched together. The options set
+//! on the host socket are carried to the loopback socket, or the switch fails with ENOPROTOOPT if
+//! the loopback transport can not honour any of them.
+
+use super::*;
+use config::ConfigLoopback;
+use fs::StatusFlags;
+
+mod addr;
+mod datagram;
+mod port_space;
+mod sockopt;
+mod stream;
+
+pub use self::addr::InetAddr;
+pub use self::datagram::LoopbackDatagram;
+pub use self::sockopt::SockOptions;
+pub use self::stream::LoopbackStream;
+
+use self::port_space::PORT_SPACE;
+
+/// Whether the in-enclave loopback transport is enabled.
+pub fn is_loopback_enabled() -> bool {
+    config::LIBOS_CONFIG.net.loopback == ConfigLoopback::Enclave
+}
+
+/// Create a loopback socket to replace a host socket of the given domain and type.
+pub fn loopback_socket(
+    domain: AddressFamily,
+    socket_type: SocketType,
+    nonblocking: bool,
+) -> Result<Arc<dyn File>> {
+    let flags = if nonblocking {
+        FileFlags::SOCK_NONBLOCK
+    } else {
+        FileFlags::empty()
+    };
+    match socket_type {
+        SocketType::STREAM => Ok(Arc::new(LoopbackStream::new(domain, flags))),
+        SocketType::DGRAM => Ok(Arc::new(LoopbackDatagram::new(domain, flags))),
+        _ => return_errno!(ESOCKTNOSUPPORT, "socket type is not supported"),
+    }
+}
+
+/// Let a loopback socket take the place of the host socket, which performs the operation `f`
+/// instead. The host socket is replaced only if the operation succeeds.
+pub fn switch_to_loopback<T>(
+    socket: &HostSocket,
+    f: impl FnOnce(&dyn LoopbackSocket) -> Result<T>,
+) -> Result<T> {
+    let new_socket = || {
+        let nonblocking = socket.status_flags()?.contains(StatusFlags::O_NONBLOCK);
+        let file = loopback_socket(socket.domain(), socket.socket_type(), nonblocking)?;
+        let loopback_socket = file.as_loopback_socket()?;
+        for (level, optname, optval) in socket.recorded_sockopts() {
+            loopback_socket.sockopts().set(level, optname, &optval)?;
+        }
+        Ok(file)
+    };
+    socket.replace_with(new_socket, |file| f(&*file.as_loopback_socket()?))
+}
+
+/// Whether a host socket that binds to the address should be replaced by a loopback socket.
+pub fn is_loopback_bind(socket: &HostSocket, addr: &SockAddr) -> bool {
+    is_loopback_enabled()
+        && is_supported(socket)
+        && InetAddr::from_sockaddr(addr, socket.domain())
+            .map(|addr| addr.is_loopback())
+            .unwrap_or(false)
+}
+
+/// Whether a host socket that connects or sends to the address should be replaced by a loopback
+/// socket, i.e., the address is bound by an in-enclave socket of the same type.
+pub fn is_loopback_peer(socket: &HostSocket, addr: &SockAddr) -> bool {
+    if !is_loopback_enabled() || !is_supported(socket) {
+        return false;
+    }
+    let addr = match InetAddr::from_sockaddr(addr, socket.domain()) {
+        Ok(addr) if addr.is_loopback() => addr,
+        _ => return false,
+    };
+    // A socket bound to the wildcard address stays on the host and is not in the port space, so
+    // the traffic to it goes through the host
+    match socket.socket_type() {
+        SocketType::STREAM => PORT_SPACE.get_listener(&addr).is_some(),
+        _ => PORT_SPACE.get_datagram(&addr).is_some(),
+    }
+}
+
+fn is_supported(socket: &HostSocket) -> bool {
+    (socket.domain() == AddressFamily::INET || socket.domain() == AddressFamily::INET6)
+        && (socket.socket_type() == SocketType::STREAM || socket.socket_type() == SocketType::DGRAM)
+}
+
+/// The socket operations of the loopback sockets.
+pub trait LoopbackSocket {
+    fn domain(&self) -> AddressFamily;
+
+    fn socket_type(&self) -> SocketType;
+
+    fn addr(&self) -> Result<SockAddr>;
+
+    fn peer_addr(&self) -> Result<SockAddr>;
+
+    fn bind(&self, addr: &SockAddr) -> Result<()>;
+
+    fn listen(&self, backlog: i32) -> Result<()>;
+
+    fn connect(&self, addr: &Option<SockAddr>) -> Result<()>;
+
+    fn accept(&self, flags: FileFlags) -> Result<(Arc<dyn File>, Option<SockAddr>)>;
+
+    fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<SockAddr>) -> Result<usize>;
+
+    fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<SockAddr>)>;
+
+    fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize>;
+
+    fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize>;
+
+    fn shutdown(&self, how: HowToShut) -> Result<()>;
+
+    fn sockopts(&self) -> &SockOptions;
+}
+
+pub trait AsLoopbackSocket {
+    /// Get the loopback socket of the file, which is either a loopback socket itself or a host
+    /// socket that has been replaced by one.
+    fn as_loopback_socket(&self) -> Result<LoopbackSocketRef>;
+}
+
+impl AsLoopbackSocket for FileRef {
+    fn as_loopback_socket(&self) -> Result<LoopbackSocketRef> {
+        let file = match self.as_any().downcast_ref::<HostSocket>() {
+            Some(socket) => socket
+                .replacement()
+                .ok_or_else(|| errno!(EBADF, "not a loopback socket"))?,
+            None => self.clone(),
+        };
+        downcast_loopback(&file)?;
+        Ok(LoopbackSocketRef(file))
+    }
+}
+
+/// A reference to a loopback socket, which keeps the file alive.
+pub struct LoopbackSocketRef(FileRef);
+
+impl std::ops::Deref for LoopbackSocketRef {
+    type Target = dyn LoopbackSocket;
+
+    fn deref(&self) -> &Self::Target {
+        // The type of the file has been checked on creation
+        downcast_loopback(&self.0).unwrap()
+    }
+}
+
+fn downcast_loopback(file: &FileRef) -> Result<&dyn LoopbackSocket> {
+    let any = file.as_any();
+    if let Some(stream) = any.downcast_ref::<LoopbackStream>() {
+        Ok(stream)
+    } else if let Some(datagram) = any.downcast_ref::<LoopbackDatagram>() {
+        Ok(datagram)
+    } else {
+        return_errno!(EBADF, "not a loopback socket")
+    }
+}
+
+/// Parse the destination of a loopback socket. Only the loopback addresses can be reached.
+fn parse_peer_addr(addr: &SockAddr, domain: AddressFamily) -> Result<InetAddr> {
+    let addr = InetAddr::from_sockaddr(addr, domain)?;
+    if !addr.is_loopback() {
+        return_errno!(ENETUNREACH, "only the loopback addresses are reachable");
+    }
+    Ok(addr)
+}
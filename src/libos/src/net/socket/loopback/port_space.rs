@@ -0,0 +1,98 @@
+use super::datagram::Packet;
+use super::stream::Listener;
+use super::*;
+
+lazy_static! {
+    pub(super) static ref PORT_SPACE: PortSpace = PortSpace::new();
+}
+
+/// The range of the ports assigned to the sockets that are not bound explicitly, which is the
+/// default value of /proc/sys/net/ipv4/ip_local_port_range on Linux.
+const EPHEMERAL_PORTS: std::ops::RangeInclusive<u16> = 32768..=60999;
+
+/// What is bound to an address by a loopback stream socket.
+#[derive(Clone)]
+pub enum StreamBinding {
+    /// A socket that is bound or connected but not listening
+    Idle,
+    Listener(Arc<Listener>),
+}
+
+/// The addresses bound by the loopback sockets. TCP and UDP have separate port spaces, as they
+/// do on Linux.
+pub struct PortSpace {
+    streams: SgxMutex<HashMap<InetAddr, StreamBinding>>,
+    datagrams: SgxMutex<HashMap<InetAddr, Arc<MsgQueue<Packet>>>>,
+}
+
+impl PortSpace {
+    pub fn new() -> Self {
+        Self {
+            streams: SgxMutex::new(HashMap::new()),
+            datagrams: SgxMutex::new(HashMap::new()),
+        }
+    }
+
+    /// Bind a stream socket to the address. If the port is zero, an unused port is assigned and
+    /// written back to the address.
+    pub fn bind_stream(&self, addr: &mut InetAddr) -> Result<()> {
+        let mut streams = self.streams.lock().unwrap();
+        *addr = Self::alloc_port(addr, |addr| streams.contains_key(addr))?;
+        streams.insert(*addr, StreamBinding::Idle);
+        Ok(())
+    }
+
+    pub fn set_listener(&self, addr: &InetAddr, listener: Arc<Listener>) -> Result<()> {
+        let mut streams = self.streams.lock().unwrap();
+        match streams.get(addr) {
+            Some(StreamBinding::Idle) => {
+                streams.insert(*addr, StreamBinding::Listener(listener));
+                Ok(())
+            }
+            Some(_) => return_errno!(EADDRINUSE, "another socket is listening on the address"),
+            None => return_errno!(EINVAL, "the socket is not bound"),
+        }
+    }
+
+    pub fn get_listener(&self, addr: &InetAddr) -> Option<Arc<Listener>> {
+        match self.streams.lock().unwrap().get(addr) {
+            Some(StreamBinding::Listener(listener)) => Some(listener.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn unbind_stream(&self, addr: &InetAddr) {
+        self.streams.lock().unwrap().remove(addr);
+    }
+
+    /// Bind the receive queue of a datagram socket to the address. If the port is zero, an
+    /// unused port is assigned and written back to the address.
+    pub fn bind_datagram(&self, addr: &mut InetAddr, queue: Arc<MsgQueue<Packet>>) -> Result<()> {
+        let mut datagrams = self.datagrams.lock().unwrap();
+        *addr = Self::alloc_port(addr, |addr| datagrams.contains_key(addr))?;
+        datagrams.insert(*addr, queue);
+        Ok(())
+    }
+
+    pub fn get_datagram(&self, addr: &InetAddr) -> Option<Arc<MsgQueue<Packet>>> {
+        self.datagrams.lock().unwrap().get(addr).cloned()
+    }
+
+    pub fn unbind_datagram(&self, addr: &InetAddr) {
+        self.datagrams.lock().unwrap().remove(addr);
+    }
+
+    fn alloc_port(addr: &InetAddr, is_used: impl Fn(&InetAddr) -> bool) -> Result<InetAddr> {
+        if addr.port() != 0 {
+            if is_used(addr) {
+                return_errno!(EADDRINUSE, "the address is already bound");
+            }
+            return Ok(*addr);
+        }
+
+        EPHEMERAL_PORTS
+            .map(|port| addr.with_port(port))
+            .find(|addr| !is_used(addr))
+            .ok_or_else(|| errno!(EADDRINUSE, "no ephemeral port is available"))
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+use std::collections::BTreeMap;
+
+/// The default size of the send and receive buffers, i.e., /proc/sys/net/core/[rw]mem_default
+const DEFAULT_BUF_SIZE: i32 = 208 * 1024;
+
+/// The socket options of a loopback socket.
+///
+/// Only the options that the loopback transport can honour are accepted, i.e., the ones whose
+/// effects are the same or meaningless within the enclave. The others, e.g., the timeouts, are
+/// rejected with ENOPROTOOPT instead of being ignored silently.
+#[derive(Debug)]
+pub struct SockOptions {
+    inner: SgxMutex<BTreeMap<(i32, i32), Vec<u8>>>,
+}
+
+impl SockOptions {
+    pub fn new() -> Self {
+        Self {
+            inner: SgxMutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn is_supported(level: i32, optname: i32) -> bool {
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_REUSEADDR)
+            | (libc::SOL_SOCKET, libc::SO_KEEPALIVE)
+            | (libc::SOL_SOCKET, libc::SO_BROADCAST)
+            | (libc::SOL_SOCKET, libc::SO_SNDBUF)
+            | (libc::SOL_SOCKET, libc::SO_RCVBUF) => true,
+            // There is no delay of the small packets within the enclave
+            (libc::IPPROTO_TCP, libc::TCP_NODELAY) => true,
+            _ => false,
+        }
+    }
+
+    pub fn set(&self, level: i32, optname: i32, optval: &[u8]) -> Result<()> {
+        if !Self::is_supported(level, optname) {
+            return_errno!(
+                ENOPROTOOPT,
+                "the socket option is not supported by loopback socket"
+            );
+        }
+        if optval.len() < std::mem::size_of::<i32>() {
+            return_errno!(EINVAL, "optlen is too short");
+        }
+        self.inner
+            .lock()
+            .unwrap()
+            .insert((level, optname), optval.to_vec());
+        Ok(())
+    }
+
+    /// Get the value of an option, or the default value if it has never been set.
+    pub fn get(&self, level: i32, optname: i32) -> Result<Vec<u8>> {
+        if !Self::is_supported(level, optname) {
+            return_errno!(
+                ENOPROTOOPT,
+                "the socket option is not supported by loopback socket"
+            );
+        }
+        if let Some(optval) = self.inner.lock().unwrap().get(&(level, optname)) {
+            return Ok(optval.clone());
+        }
+        let default_val = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) | (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                DEFAULT_BUF_SIZE
+            }
+            _ => 0,
+        };
+        Ok(default_val.to_ne_bytes().to_vec())
+    }
+
+    /// Get the value of an integer option.
+    pub fn get_int(&self, level: i32, optname: i32) -> Result<i32> {
+        let optval = self.get(level, optname)?;
+        let bytes = optval
+            .get(..std::mem::size_of::<i32>())
+            .ok_or_else(|| errno!(EINVAL, "the option is not an integer"))?;
+        Ok(i32::from_ne_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Duplicate the options, e.g., for a socket accepted from a listener.
+    pub fn duplicate(&self) -> Self {
+        Self {
+            inner: SgxMutex::new(self.inner.lock().unwrap().clone()),
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use super::super::unix::Stream;
+use super::port_space::PORT_SPACE;
+use super::*;
+use events::Observer;
+use fs::channel::Channel;
+use fs::{AccessMode, IoEvents, IoNotifier, IoctlCmd, StatusFlags};
+use rcore_fs::vfs::{FileType, Metadata, Timespec};
+use std::any::Any;
+use std::fmt;
+use std::sync::Weak;
+
+/// The maximum length of the queue of pending connections, i.e., /proc/sys/net/core/somaxconn
+const SOMAXCONN: usize = 4096;
+
+/// SOCK_STREAM loopback socket, i.e., an in-enclave TCP socket.
+///
+/// A connection is a pair of connected stream unix sockets. The connecting socket keeps one of
+/// them and pushes the other one to the listener, where it is picked up by accept.
+pub struct LoopbackStream {
+    domain: AddressFamily,
+    inner: SgxMutex<Status>,
+    // The notifier of the socket is kept unchanged when the socket gets connected. It relays the
+    // events of the underlying unix socket.
+    notifier: Arc<Relay>,
+    sockopts: SockOptions,
+}
+
+enum Status {
+    Idle {
+        addr: Option<InetAddr>,
+        nonblocking: bool,
+    },
+    Listening {
+        addr: InetAddr,
+        listener: Arc<Listener>,
+    },
+    Connected {
+        addr: InetAddr,
+        peer_addr: InetAddr,
+        stream: Arc<Stream>,
+        // An accepted socket shares the address of the listener, so it does not own a binding
+        owns_addr: bool,
+    },
+}
+
+impl LoopbackStream {
+    pub fn new(domain: AddressFamily, flags: FileFlags) -> Self {
+        Self {
+            domain,
+            inner: SgxMutex::new(Status::Idle {
+                addr: None,
+                nonblocking: flags.contains(FileFlags::SOCK_NONBLOCK),
+            }),
+            notifier: Arc::new(Relay::new()),
+            sockopts: SockOptions::new(),
+        }
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Status> {
+        self.inner.lock().unwrap()
+    }
+
+    fn nonblocking(&self) -> bool {
+        match &*self.inner() {
+            Status::Idle { nonblocking, .. } => *nonblocking,
+            Status::Listening { listener, .. } => listener.nonblocking(),
+            Status::Connected { stream, .. } => stream
+                .status_flags()
+                .map(|flags| flags.contains(StatusFlags::O_NONBLOCK))
+                .unwrap_or(false),
+        }
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        match &mut *self.inner() {
+            Status::Idle {
+                nonblocking: is_nonblocking,
+                ..
+            } => *is_nonblocking = nonblocking,
+            Status::Listening { listener, .. } => listener.set_nonblocking(nonblocking),
+            Status::Connected { stream, .. } => {
+                stream.set_status_flags(Self::status_flags_of(nonblocking))?
+            }
+        }
+        Ok(())
+    }
+
+    fn status_flags_of(nonblocking: bool) -> StatusFlags {
+        if nonblocking {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    /// Get the underlying stream of the connection. The lock is released before the data
+    /// transfer, which may block.
+    fn connected_stream(&self) -> Result<Arc<Stream>> {
+        match &*self.inner() {
+            Status::Connected { stream, .. } => Ok(stream.clone()),
+            _ => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    /// Check the flags of the data transfer. MSG_DONTWAIT is emulated by checking the readiness
+    /// of the stream in advance.
+    fn check_ready(stream: &Stream, dontwait: bool, events: IoEvents) -> Result<()> {
+        if dontwait && (stream.poll_new() & (events | IoEvents::HUP | IoEvents::RDHUP)).is_empty() {
+            return_errno!(EAGAIN, "the socket is not ready");
+        }
+        Ok(())
+    }
+}
+
+impl LoopbackSocket for LoopbackStream {
+    fn domain(&self) -> AddressFamily {
+        self.domain
+    }
+
+    fn socket_type(&self) -> SocketType {
+        SocketType::STREAM
+    }
+
+    fn addr(&self) -> Result<SockAddr> {
+        let addr = match &*self.inner() {
+            Status::Idle { addr, .. } => addr.unwrap_or(InetAddr::unspecified(self.domain)),
+            Status::Listening { addr, .. } => *addr,
+            Status::Connected { addr, .. } => *addr,
+        };
+        Ok(addr.to_sockaddr(self.domain))
+    }
+
+    fn peer_addr(&self) -> Result<SockAddr> {
+        match &*self.inner() {
+            Status::Connected { peer_addr, .. } => Ok(peer_addr.to_sockaddr(self.domain)),
+            _ => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn bind(&self, addr: &SockAddr) -> Result<()> {
+        let mut addr = InetAddr::from_sockaddr(addr, self.domain)?;
+        if !addr.is_loopback() {
+            return_errno!(EADDRNOTAVAIL, "only the loopback addresses can be bound");
+        }
+
+        match &mut *self.inner() {
+            Status::Idle {
+                addr: self_addr, ..
+            } if self_addr.is_none() => {
+                PORT_SPACE.bind_stream(&mut addr)?;
+                *self_addr = Some(addr);
+                Ok(())
+            }
+            _ => return_errno!(EINVAL, "the socket is already bound"),
+        }
+    }
+
+    fn listen(&self, backlog: i32) -> Result<()> {
+        let capacity = (backlog.max(1) as usize).min(SOMAXCONN);
+
+        let mut inner = self.inner();
+        match &*inner {
+            Status::Idle {
+                addr: Some(addr),
+                nonblocking,
+            } => {
+                let listener = Arc::new(Listener::new(
+                    capacity,
+                    *nonblocking,
+                    self.notifier.clone(),
+                )?);
+                PORT_SPACE.set_listener(addr, listener.clone())?;
+                *inner = Status::Listening {
+                    addr: *addr,
+                    listener,
+                };
+                Ok(())
+            }
+            Status::Idle { addr: None, .. } => {
+                return_errno!(EINVAL, "the socket is not bound")
+            }
+            // The backlog of a listening socket is kept unchanged
+            Status::Listening { .. } => Ok(()),
+            Status::Connected { .. } => return_errno!(EINVAL, "the socket is already connected"),
+        }
+    }
+
+    /// The connection is established immediately, so connect never blocks.
+    fn connect(&self, addr: &Option<SockAddr>) -> Result<()> {
+        debug!("connect to {:?}", addr);
+        let peer_addr = match addr {
+            Some(addr) => parse_peer_addr(addr, self.domain)?,
+            None => return_errno!(EINVAL, "invalid address"),
+        };
+
+        let mut inner = self.inner();
+        let (self_addr, nonblocking) = match &*inner {
+            Status::Idle { addr, nonblocking } => (*addr, *nonblocking),
+            Status::Listening { .. } => return_errno!(EINVAL, "the socket is listening"),
+            Status::Connected { .. } => return_errno!(EISCONN, "the socket is already connected"),
+        };
+
+        let listener = PORT_SPACE
+            .get_listener(&peer_addr)
+            .ok_or_else(|| errno!(ECONNREFUSED, "no one is listening on the address"))?;
+
+        let self_addr = match self_addr {
+            Some(addr) => addr,
+            None => {
+                // Bind to an ephemeral port of the loopback address of the peer's family
+                let mut addr = if peer_addr.is_v4() {
+                    InetAddr::loopback(AddressFamily::INET, 0)
+                } else {
+                    InetAddr::loopback(AddressFamily::INET6, 0)
+                };
+                PORT_SPACE.bind_stream(&mut addr)?;
+                *inner = Status::Idle {
+                    addr: Some(addr),
+                    nonblocking,
+                };
+                addr
+            }
+        };
+
+        let flags = if nonblocking {
+            FileFlags::SOCK_NONBLOCK
+        } else {
+            FileFlags::empty()
+        };
+        let (stream_self, stream_incoming) = Stream::socketpair(SocketType::STREAM, flags)?;
+        listener
+            .push_incoming(Incoming {
+                stream: stream_incoming,
+                peer_addr: self_addr,
+            })
+            .map_err(|e| match e.errno() {
+                Errno::EAGAIN => errno!(ECONNREFUSED, "the backlog is full"),
+                Errno::EPIPE => errno!(ECONNREFUSED, "the listener has been closed"),
+                _ => e,
+            })?;
+
+        let stream = Arc::new(stream_self);
+        self.notifier.observe(&stream);
+        *inner = Status::Connected {
+            addr: self_addr,
+            peer_addr,
+            stream,
+            owns_addr: true,
+        };
+        Ok(())
+    }
+
+    fn accept(&self, flags: FileFlags) -> Result<(Arc<dyn File>, Option<SockAddr>)> {
+        let (addr, listener) = match &*self.inner() {
+            Status::Listening { addr, listener } => (*addr, listener.clone()),
+            _ => return_errno!(EINVAL, "the socket is not listening"),
+        };
+
+        let incoming = listener.pop_incoming()?;
+        incoming.stream.set_status_flags(Self::status_flags_of(
+            flags.contains(FileFlags::SOCK_NONBLOCK),
+        ))?;
+
+        let stream = Arc::new(incoming.stream);
+        let notifier = Arc::new(Relay::new());
+        notifier.observe(&stream);
+        let new_socket = Self {
+            domain: self.domain,
+            inner: SgxMutex::new(Status::Connected {
+                addr,
+                peer_addr: incoming.peer_addr,
+                stream,
+                owns_addr: false,
+            }),
+            notifier,
+            // Like Linux, the accepted socket inherits the options of the listener
+            sockopts: self.sockopts.duplicate(),
+        };
+        let peer_addr = incoming.peer_addr.to_sockaddr(self.domain);
+        Ok((Arc::new(new_socket), Some(peer_addr)))
+    }
+
+    fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<SockAddr>) -> Result<usize> {
+        let stream = self.connected_stream()?;
+        Self::check_ready(
+            &stream,
+            flags.contains(SendFlags::MSG_DONTWAIT),
+            IoEvents::OUT,
+        )?;
+        stream.write(buf)
+    }
+
+    fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<SockAddr>)> {
+        let stream = self.connected_stream()?;
+        Self::check_ready(
+            &stream,
+            flags.contains(RecvFlags::MSG_DONTWAIT),
+            IoEvents::IN,
+        )?;
+        if flags.contains(RecvFlags::MSG_PEEK) {
+            warn!("MSG_PEEK is not supported by loopback stream socket");
+        }
+        Ok((stream.read(buf)?, None))
+    }
+
+    fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
+        let stream = self.connected_stream()?;
+        Self::check_ready(
+            &stream,
+            flags.contains(SendFlags::MSG_DONTWAIT),
+            IoEvents::OUT,
+        )?;
+        if msg_hdr.get_control().is_some() {
+            warn!("control messages are ignored by loopback stream socket");
+        }
+        stream.writev(msg_hdr.get_iovs().as_slices())
+    }
+
+    fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
+        let stream = self.connected_stream()?;
+        Self::check_ready(
+            &stream,
+            flags.contains(RecvFlags::MSG_DONTWAIT),
+            IoEvents::IN,
+        )?;
+        if flags.contains(RecvFlags::MSG_PEEK) {
+            warn!("MSG_PEEK is not supported by loopback stream socket");
+        }
+        let data_len = stream.readv(msg_hdr.get_iovs_mut().as_slices_mut())?;
+        msg_hdr.set_name_len(0)?;
+        msg_hdr.set_control_len(0)?;
+        msg_hdr.set_flags(MsgHdrFlags::empty());
+        Ok(data_len)
+    }
+
+    fn shutdown(&self, how: HowToShut) -> Result<()> {
+        self.connected_stream()?.shutdown(how)
+    }
+
+    fn sockopts(&self) -> &SockOptions {
+        &self.sockopts
+    }
+}
+
+impl File for LoopbackStream {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.connected_stream()?.read(buf)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.connected_stream()?.write(buf)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.read(buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.write(buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        self.connected_stream()?.readv(bufs)
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.connected_stream()?.writev(bufs)
+    }
+
+    fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        match cmd {
+            IoctlCmd::FIONBIO(nonblocking) => {
+                self.set_nonblocking(**nonblocking != 0)?;
+                Ok(0)
+            }
+            IoctlCmd::FIONREAD(_) => self.connected_stream()?.ioctl(cmd),
+            IoctlCmd::TCGETS(_) | IoctlCmd::TCSETS(_) => return_errno!(ENOTTY, "not tty device"),
+            _ => return_errno!(EINVAL, "unknown ioctl cmd for loopback socket"),
+        }
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDWR)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        Ok(Self::status_flags_of(self.nonblocking()))
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        // Only O_NONBLOCK is supported
+        self.set_nonblocking(new_status_flags.contains(StatusFlags::O_NONBLOCK))
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        match &*self.inner() {
+            Status::Idle { .. } => IoEvents::OUT | IoEvents::HUP,
+            Status::Listening { listener, .. } => listener.poll_new(),
+            Status::Connected { stream, .. } => stream.poll_new(),
+        }
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(&self.notifier.notifier)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Socket,
+            mode: 0o666,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+}
+
+impl Debug for LoopbackStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoopbackStream")
+            .field("domain", &self.domain)
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for LoopbackStream {
+    fn drop(&mut self) {
+        match &*self.inner() {
+            Status::Idle {
+                addr: Some(addr), ..
+            } => PORT_SPACE.unbind_stream(addr),
+            Status::Listening { addr, listener } => {
+                PORT_SPACE.unbind_stream(addr);
+                // Refuse the pending connections and wake up the blocked acceptors
+                listener.shutdown();
+            }
+            Status::Connected {
+                addr,
+                owns_addr: true,
+                ..
+            } => PORT_SPACE.unbind_stream(addr),
+            _ => {}
+        }
+    }
+}
+
+/// A connection waiting to be accepted.
+struct Incoming {
+    stream: Stream,
+    peer_addr: InetAddr,
+}
+
+/// The queue of the pending connections of a listening socket.
+///
+/// The connecting side never blocks: connect fails with ECONNREFUSED if the queue is full.
+pub struct Listener {
+    channel: Channel<Incoming>,
+    notifier: Arc<Relay>,
+}
+
+impl Listener {
+    fn new(capacity: usize, nonblocking: bool, notifier: Arc<Relay>) -> Result<Self> {
+        let channel = Channel::new(capacity)?;
+        channel.producer().set_nonblocking(true);
+        channel.consumer().set_nonblocking(nonblocking);
+        Ok(Self { channel, notifier })
+    }
+
+    fn push_incoming(&self, incoming: Incoming) -> Result<()> {
+        self.channel.push(incoming)?;
+        self.notifier.notifier.broadcast(&IoEvents::IN);
+        Ok(())
+    }
+
+    fn pop_incoming(&self) -> Result<Incoming> {
+        self.channel
+            .pop()?
+            .ok_or_else(|| errno!(EINVAL, "the socket is not listening"))
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.channel.consumer().is_nonblocking()
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.channel.consumer().set_nonblocking(nonblocking);
+    }
+
+    fn shutdown(&self) {
+        self.channel.shutdown();
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        if self.channel.items_to_consume() > 0 {
+            IoEvents::IN
+        } else {
+            IoEvents::empty()
+        }
+    }
+}
+
+/// An observer that relays the events of the underlying stream to the notifier of the socket.
+struct Relay {
+    notifier: IoNotifier,
+}
+
+impl Relay {
+    fn new() -> Self {
+        Self {
+            notifier: IoNotifier::new(),
+        }
+    }
+
+    fn observe(self: &Arc<Self>, stream: &Stream) {
+        stream.notifier().unwrap().register(
+            Arc::downgrade(self) as Weak<dyn Observer<IoEvents>>,
+            None,
+            None,
+        );
+    }
+}
+
+impl Observer<IoEvents> for Relay {
+    fn on_event(&self, event: &IoEvents, _metadata: &Option<Weak<dyn Any + Send + Sync>>) {
+        self.notifier.broadcast(event);
+    }
+}
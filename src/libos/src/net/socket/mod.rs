@@ -4,7 +4,10 @@ mod address_family;
 mod flags;
 mod host;
 mod iovs;
+mod loopback;
 mod msg;
+mod msg_queue;
+mod netlink;
 mod shutdown;
 mod socket_address;
 mod socket_type;
@@ -14,8 +17,17 @@ pub use self::address_family::AddressFamily;
 pub use self::flags::{FileFlags, MsgHdrFlags, RecvFlags, SendFlags};
 pub use self::host::{HostSocket, HostSocketType};
 pub use self::iovs::{Iovs, IovsMut, SliceAsLibcIovec};
-pub use self::msg::{mmsghdr, msghdr, msghdr_mut, CMessages, CmsgData, MsgHdr, MsgHdrMut};
+pub use self::loopback::{
+    is_loopback_bind, is_loopback_peer, switch_to_loopback, AsLoopbackSocket, LoopbackSocket,
+};
+pub use self::msg::{
+    mmsghdr, mmsghdr_mut, msghdr, msghdr_mut, CMessages, CmsgData, MsgHdr, MsgHdrMut,
+};
+use self::msg_queue::{MsgQueue, QueueMsg};
+pub use self::netlink::{
+    init_net_ifaces, netlink_socket, AsNetlinkSocket, NetlinkAddr, NetlinkSocket, NETLINK_ROUTE,
+};
 pub use self::shutdown::HowToShut;
 pub use self::socket_address::SockAddr;
 pub use self::socket_type::SocketType;
-pub use self::unix::{socketpair, unix_socket, AsUnixSocket, UnixAddr};
+pub use self::unix::{socketpair, unix_socket, AsUnixSocket, UnixAddr, UnixSocket};
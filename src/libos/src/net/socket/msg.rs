@@ -34,6 +34,13 @@ pub struct msghdr_mut {
     pub msg_flags: c_int,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mmsghdr_mut {
+    pub msg_hdr: msghdr_mut,
+    pub msg_len: c_uint,
+}
+
 /// MsgHdr is a memory-safe, immutable wrapper of msghdr
 pub struct MsgHdr<'a> {
     name: Option<&'a [u8]>,
@@ -261,7 +268,7 @@ impl<'a> CMessages<'a> {
 /// Control message data of variable type. The data resides next to `cmsghdr`.
 pub enum CmsgData<'a> {
     ScmRights(ScmRights<'a>),
-    ScmCredentials,
+    ScmCredentials(ScmCredentials<'a>),
 }
 
 impl<'a> CmsgData<'a> {
@@ -282,8 +289,9 @@ impl<'a> CmsgData<'a> {
     ///
     /// `data` must contain a valid control message and the control message must be type of
     /// `SOL_SOCKET` and level of `SCM_CREDENTIALS`.
-    unsafe fn as_credentials(_data: &'a [u8]) -> Self {
-        CmsgData::ScmCredentials
+    unsafe fn as_credentials(data: &'a [u8]) -> Self {
+        let scm_credentials = ScmCredentials { data };
+        CmsgData::ScmCredentials(scm_credentials)
     }
 
     fn try_from_cmsghdr(cmsg: &'a libc::cmsghdr) -> Option<Self> {
@@ -332,6 +340,19 @@ impl<'a> ScmRights<'a> {
     }
 }
 
+/// The data unit of this control message is the credentials of a process, i.e., struct ucred.
+///
+/// The level is equal to `SOL_SOCKET` and the type is equal to `SCM_CREDENTIALS`.
+pub struct ScmCredentials<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ScmCredentials<'a> {
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+}
+
 unsafe fn new_optional_slice<'a, T>(slice_ptr: *const T, slice_size: usize) -> Option<&'a [T]> {
     if !slice_ptr.is_null() {
         let slice = core::slice::from_raw_parts::<T>(slice_ptr, slice_size);
@@ -0,0 +1,218 @@
+use super::*;
+use events::{Waiter, WaiterQueue};
+use fs::{IoEvents, IoNotifier};
+use std::collections::VecDeque;
+
+/// A message whose boundary is preserved by `MsgQueue`.
+pub trait QueueMsg: Clone {
+    /// The data carried by the message
+    fn data(&self) -> &[u8];
+
+    /// The length of the data carried by the message
+    fn len(&self) -> usize {
+        self.data().len()
+    }
+
+    /// Copy the data to the buffers. Return the number of bytes copied.
+    ///
+    /// The part of the data that does not fit into the buffers is not copied, which is discarded
+    /// by the receiver as the message is always received as a whole.
+    fn copy_to(&self, bufs: &mut [&mut [u8]]) -> usize {
+        let data = self.data();
+        let mut copied = 0;
+        for buf in bufs.iter_mut() {
+            let remaining = &data[copied..];
+            if remaining.is_empty() {
+                break;
+            }
+            let len = buf.len().min(remaining.len());
+            buf[..len].copy_from_slice(&remaining[..len]);
+            copied += len;
+        }
+        copied
+    }
+}
+
+/// The receive queue of a message-oriented socket, e.g., a datagram or seqpacket unix socket.
+///
+/// Unlike `Channel`, a queue may have many writers, e.g., all the datagram sockets sending to the
+/// same address. So whether an operation blocks is decided by each call rather than the queue.
+pub struct MsgQueue<M> {
+    inner: SgxMutex<Inner<M>>,
+    /// The maximum total length of the data of the queued messages
+    capacity: usize,
+    /// The threads waiting for messages
+    readers: WaiterQueue,
+    /// The threads waiting for the space of the queue
+    writers: WaiterQueue,
+    notifier: IoNotifier,
+}
+
+struct Inner<M> {
+    msgs: VecDeque<M>,
+    /// The total length of the data of the queued messages
+    len: usize,
+    is_read_shutdown: bool,
+    is_write_shutdown: bool,
+}
+
+impl<M: QueueMsg> MsgQueue<M> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: SgxMutex::new(Inner {
+                msgs: VecDeque::new(),
+                len: 0,
+                is_read_shutdown: false,
+                is_write_shutdown: false,
+            }),
+            capacity,
+            readers: WaiterQueue::new(),
+            writers: WaiterQueue::new(),
+            notifier: IoNotifier::new(),
+        }
+    }
+
+    pub fn push(&self, msg: M, nonblocking: bool) -> Result<()> {
+        if msg.len() > self.capacity {
+            return_errno!(EMSGSIZE, "the message is too long");
+        }
+
+        let mut waiter = None;
+        loop {
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if inner.is_read_shutdown || inner.is_write_shutdown {
+                    return_errno!(EPIPE, "the queue has been shutdown");
+                }
+                if inner.len + msg.len() <= self.capacity {
+                    inner.len += msg.len();
+                    inner.msgs.push_back(msg);
+                    drop(inner);
+
+                    self.readers.dequeue_and_wake_all();
+                    self.notifier.broadcast(&IoEvents::IN);
+                    return Ok(());
+                }
+                if nonblocking {
+                    return_errno!(EAGAIN, "the queue is full");
+                }
+                // Enqueue the waiter with the lock held so that a wakeup is not missed
+                self.writers
+                    .reset_and_enqueue(waiter.get_or_insert_with(Waiter::new));
+            }
+            waiter.as_ref().unwrap().wait(None)?;
+        }
+    }
+
+    /// Pop the first message of the queue, or get a copy of it if `peek` is true.
+    ///
+    /// Return `None` if the queue is empty and has been shutdown.
+    pub fn pop(&self, nonblocking: bool, peek: bool) -> Result<Option<M>> {
+        let mut waiter = None;
+        loop {
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if peek {
+                    if let Some(msg) = inner.msgs.front() {
+                        return Ok(Some(msg.clone()));
+                    }
+                } else if let Some(msg) = inner.msgs.pop_front() {
+                    inner.len -= msg.len();
+                    drop(inner);
+
+                    self.writers.dequeue_and_wake_all();
+                    self.notifier.broadcast(&IoEvents::OUT);
+                    return Ok(Some(msg));
+                }
+                if inner.is_read_shutdown || inner.is_write_shutdown {
+                    return Ok(None);
+                }
+                if nonblocking {
+                    return_errno!(EAGAIN, "no message in the queue");
+                }
+                self.readers
+                    .reset_and_enqueue(waiter.get_or_insert_with(Waiter::new));
+            }
+            waiter.as_ref().unwrap().wait(None)?;
+        }
+    }
+
+    /// Stop receiving messages. The queued messages are discarded.
+    pub fn shutdown_read(&self) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_read_shutdown {
+                return;
+            }
+            inner.is_read_shutdown = true;
+            inner.msgs.clear();
+            inner.len = 0;
+        }
+        self.readers.dequeue_and_wake_all();
+        self.writers.dequeue_and_wake_all();
+        self.notifier.broadcast(&(IoEvents::IN | IoEvents::ERR));
+    }
+
+    /// Stop sending messages. The queued messages can still be received.
+    pub fn shutdown_write(&self) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_write_shutdown {
+                return;
+            }
+            inner.is_write_shutdown = true;
+        }
+        self.readers.dequeue_and_wake_all();
+        self.writers.dequeue_and_wake_all();
+        self.notifier.broadcast(&(IoEvents::IN | IoEvents::HUP));
+    }
+
+    pub fn is_read_shutdown(&self) -> bool {
+        self.inner.lock().unwrap().is_read_shutdown
+    }
+
+    pub fn is_write_shutdown(&self) -> bool {
+        self.inner.lock().unwrap().is_write_shutdown
+    }
+
+    /// The I/O events of the reading end, which is consistent with `Consumer::poll`.
+    pub fn poll_read(&self) -> IoEvents {
+        let inner = self.inner.lock().unwrap();
+        let mut events = IoEvents::empty();
+        if !inner.msgs.is_empty() || inner.is_read_shutdown {
+            events |= IoEvents::IN;
+        }
+        if inner.is_write_shutdown {
+            events |= IoEvents::HUP;
+        }
+        events
+    }
+
+    /// The I/O events of the writing end, which is consistent with `Producer::poll`.
+    pub fn poll_write(&self) -> IoEvents {
+        let inner = self.inner.lock().unwrap();
+        let mut events = IoEvents::empty();
+        if inner.len < self.capacity || inner.is_write_shutdown {
+            events |= IoEvents::OUT;
+        }
+        if inner.is_read_shutdown {
+            events |= IoEvents::ERR;
+        }
+        events
+    }
+
+    /// The total length of the data of the queued messages
+    pub fn bytes_to_read(&self) -> usize {
+        self.inner.lock().unwrap().len
+    }
+
+    /// The length of the data of the first message
+    pub fn next_msg_len(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.msgs.front().map(|msg| msg.len()).unwrap_or(0)
+    }
+
+    pub fn notifier(&self) -> &IoNotifier {
+        &self.notifier
+    }
+}
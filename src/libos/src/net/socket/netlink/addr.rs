@@ -0,0 +1,68 @@
+use super::*;
+use std::mem;
+
+/// struct sockaddr_nl
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct sockaddr_nl {
+    nl_family: libc::sa_family_t,
+    nl_pad: u16,
+    nl_pid: u32,
+    nl_groups: u32,
+}
+
+/// The address of a netlink socket, i.e., struct sockaddr_nl.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetlinkAddr {
+    /// The port ID. It is 0 for the kernel.
+    pub pid: u32,
+    /// The bitmask of the multicast groups
+    pub groups: u32,
+}
+
+impl NetlinkAddr {
+    /// The address of the kernel, which sends all the messages received by the sockets
+    pub const KERNEL: Self = Self { pid: 0, groups: 0 };
+
+    /// Caller should guarentee the sockaddr and addr_len are valid.
+    pub unsafe fn try_from_raw(
+        sockaddr: *const libc::sockaddr,
+        addr_len: libc::socklen_t,
+    ) -> Result<Self> {
+        if (addr_len as usize) < mem::size_of::<sockaddr_nl>() {
+            return_errno!(EINVAL, "the address is too short");
+        }
+        let sockaddr = &*(sockaddr as *const sockaddr_nl);
+        if sockaddr.nl_family != AddressFamily::NETLINK as libc::sa_family_t {
+            return_errno!(EINVAL, "not a valid address for netlink socket");
+        }
+        Ok(Self {
+            pid: sockaddr.nl_pid,
+            groups: sockaddr.nl_groups,
+        })
+    }
+
+    pub fn from_sock_addr(addr: &SockAddr) -> Result<Self> {
+        let (addr_ptr, addr_len) = addr.as_ptr_and_len();
+        unsafe { Self::try_from_raw(addr_ptr, addr_len as libc::socklen_t) }
+    }
+
+    pub fn copy_to_slice(&self, dst: &mut [u8]) -> usize {
+        let raw_addr = sockaddr_nl {
+            nl_family: AddressFamily::NETLINK as libc::sa_family_t,
+            nl_pad: 0,
+            nl_pid: self.pid,
+            nl_groups: self.groups,
+        };
+        let src = unsafe {
+            std::slice::from_raw_parts(&raw_addr as *const _ as *const u8, self.raw_len())
+        };
+        let copied = std::cmp::min(dst.len(), src.len());
+        dst[..copied].copy_from_slice(&src[..copied]);
+        copied
+    }
+
+    pub fn raw_len(&self) -> usize {
+        mem::size_of::<sockaddr_nl>()
+    }
+}
@@ -0,0 +1,464 @@
+//! The network interfaces, addresses and routes visible to the LibOS.
+//!
+//! They are captured from the host once at startup, through a host netlink socket. The host is
+//! untrusted, so every message is parsed and validated into the structs below, and only these
+//! structs are used to answer the requests of the netlink sockets. The raw bytes from the host
+//! never reach the user.
+
+use super::message::*;
+use super::*;
+
+/// The maximum length of an interface name, including the null byte
+const IFNAMSIZ: usize = 16;
+
+/// The maximum length of a hardware address
+const MAX_ADDR_LEN: usize = 32;
+
+/// The maximum number of the entries of a dump accepted from the host
+const MAX_HOST_ENTRIES: usize = 4096;
+
+/// The length of the buffer to receive the dumps from the host
+const HOST_RECV_BUF_SIZE: usize = 32 * 1024;
+
+lazy_static! {
+    pub static ref NET_IFACES: RwLock<NetIfaces> = RwLock::new(NetIfaces::default());
+}
+
+/// Capture the network interfaces of the host, which are filtered by `net.interfaces` of
+/// Occlum.json.
+pub fn init_net_ifaces() -> Result<()> {
+    let mut ifaces = NetIfaces::from_host()?;
+    if let Some(allowlist) = &config::LIBOS_CONFIG.net.interfaces {
+        ifaces.retain(allowlist);
+    }
+    debug!("network interfaces: {:?}", ifaces);
+    *NET_IFACES.write().unwrap() = ifaces;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct NetIfaces {
+    pub links: Vec<Link>,
+    pub addrs: Vec<IfAddr>,
+    pub routes: Vec<Route>,
+}
+
+/// A network interface
+#[derive(Debug)]
+pub struct Link {
+    pub index: i32,
+    pub name: String,
+    /// ARPHRD_*
+    pub type_: u16,
+    /// IFF_*
+    pub flags: u32,
+    pub mtu: Option<u32>,
+    /// IF_OPER_*
+    pub operstate: Option<u8>,
+    pub addr: Option<Vec<u8>>,
+    pub broadcast: Option<Vec<u8>>,
+}
+
+/// An IPv4 or IPv6 address of a network interface
+#[derive(Debug)]
+pub struct IfAddr {
+    pub family: u8,
+    pub prefix_len: u8,
+    /// IFA_F_*
+    pub flags: u8,
+    pub scope: u8,
+    pub index: i32,
+    /// The address, or the peer address of a point-to-point interface
+    pub addr: Option<Vec<u8>>,
+    /// The local address
+    pub local: Option<Vec<u8>>,
+    pub broadcast: Option<Vec<u8>>,
+    pub label: Option<String>,
+}
+
+/// An IPv4 or IPv6 route
+#[derive(Debug)]
+pub struct Route {
+    pub family: u8,
+    pub dst_len: u8,
+    pub table: u32,
+    /// RTPROT_*
+    pub protocol: u8,
+    /// RT_SCOPE_*
+    pub scope: u8,
+    /// RTN_*
+    pub type_: u8,
+    pub dst: Option<Vec<u8>>,
+    pub gateway: Option<Vec<u8>>,
+    pub prefsrc: Option<Vec<u8>>,
+    pub oif: Option<i32>,
+    pub priority: Option<u32>,
+}
+
+impl NetIfaces {
+    fn from_host() -> Result<Self> {
+        let socket = HostSocket::new(
+            AddressFamily::NETLINK,
+            SocketType::RAW,
+            FileFlags::SOCK_CLOEXEC,
+            NETLINK_ROUTE,
+        )?;
+
+        let mut links: Vec<Link> = Vec::new();
+        for payload in host_dump(&socket, RTM_GETLINK, &IfInfoMsg::default(), 1)? {
+            let link = Link::parse(&payload)?;
+            if links
+                .iter()
+                .any(|other| other.index == link.index || other.name == link.name)
+            {
+                return_errno!(EINVAL, "the interface is duplicated");
+            }
+            links.push(link);
+        }
+
+        let mut addrs = Vec::new();
+        for payload in host_dump(&socket, RTM_GETADDR, &IfAddrMsg::default(), 2)? {
+            if is_inet_payload(&payload) {
+                addrs.push(IfAddr::parse(&payload)?);
+            }
+        }
+
+        let mut routes = Vec::new();
+        for payload in host_dump(&socket, RTM_GETROUTE, &RtMsg::default(), 3)? {
+            if is_inet_payload(&payload) {
+                routes.push(Route::parse(&payload)?);
+            }
+        }
+
+        let mut ifaces = Self {
+            links,
+            addrs,
+            routes,
+        };
+        // Drop the addresses and the routes of the unknown interfaces
+        ifaces.retain_linked();
+        Ok(ifaces)
+    }
+
+    /// Keep only the interfaces whose names are in the allowlist.
+    fn retain(&mut self, allowlist: &HashSet<String>) {
+        self.links.retain(|link| allowlist.contains(&link.name));
+        // The routes not bound to an interface may reveal the others
+        self.routes.retain(|route| route.oif.is_some());
+        self.retain_linked();
+    }
+
+    fn retain_linked(&mut self) {
+        let links = &self.links;
+        let has_link = |index: i32| links.iter().any(|link| link.index == index);
+        self.addrs.retain(|addr| has_link(addr.index));
+        self.routes
+            .retain(|route| route.oif.map(|oif| has_link(oif)).unwrap_or(true));
+    }
+
+    pub fn link_by_index(&self, index: i32) -> Option<&Link> {
+        self.links.iter().find(|link| link.index == index)
+    }
+
+    pub fn link_by_name(&self, name: &str) -> Option<&Link> {
+        self.links.iter().find(|link| link.name == name)
+    }
+}
+
+impl Link {
+    fn parse(payload: &[u8]) -> Result<Self> {
+        let info = read_struct::<IfInfoMsg>(payload)
+            .ok_or_else(|| errno!(EINVAL, "the link message is truncated"))?;
+        if info.index <= 0 {
+            return_errno!(EINVAL, "the interface index is invalid");
+        }
+
+        let mut link = Self {
+            index: info.index,
+            name: String::new(),
+            type_: info.type_,
+            flags: info.flags,
+            mtu: None,
+            operstate: None,
+            addr: None,
+            broadcast: None,
+        };
+        for (type_, attr) in parse_attrs::<IfInfoMsg>(payload)? {
+            match type_ {
+                IFLA_IFNAME => link.name = parse_ifname(attr)?,
+                IFLA_MTU => link.mtu = Some(attr_u32(attr)?),
+                IFLA_OPERSTATE => link.operstate = Some(parse_u8(attr)?),
+                IFLA_ADDRESS => link.addr = Some(parse_hw_addr(attr)?),
+                IFLA_BROADCAST => link.broadcast = Some(parse_hw_addr(attr)?),
+                _ => (),
+            }
+        }
+        if link.name.is_empty() {
+            return_errno!(EINVAL, "the interface has no name");
+        }
+        Ok(link)
+    }
+
+    pub fn to_msg(&self, flags: u16, seq: u32, pid: u32) -> Vec<u8> {
+        let info = IfInfoMsg {
+            family: libc::AF_UNSPEC as u8,
+            type_: self.type_,
+            index: self.index,
+            flags: self.flags,
+            ..Default::default()
+        };
+        let mut builder = NlMsgBuilder::new(RTM_NEWLINK, flags, seq, pid, &info);
+        builder.add_attr_str(IFLA_IFNAME, &self.name);
+        if let Some(mtu) = self.mtu {
+            builder.add_attr_u32(IFLA_MTU, mtu);
+        }
+        if let Some(operstate) = self.operstate {
+            builder.add_attr(IFLA_OPERSTATE, &[operstate]);
+        }
+        if let Some(addr) = &self.addr {
+            builder.add_attr(IFLA_ADDRESS, addr);
+        }
+        if let Some(broadcast) = &self.broadcast {
+            builder.add_attr(IFLA_BROADCAST, broadcast);
+        }
+        builder.build()
+    }
+}
+
+impl IfAddr {
+    fn parse(payload: &[u8]) -> Result<Self> {
+        let info = read_struct::<IfAddrMsg>(payload)
+            .ok_or_else(|| errno!(EINVAL, "the address message is truncated"))?;
+        let addr_len = inet_addr_len(info.family)?;
+        if info.prefix_len as usize > addr_len * 8 || info.index == 0 {
+            return_errno!(EINVAL, "the address message is invalid");
+        }
+
+        let mut addr = Self {
+            family: info.family,
+            prefix_len: info.prefix_len,
+            flags: info.flags,
+            scope: info.scope,
+            index: info.index as i32,
+            addr: None,
+            local: None,
+            broadcast: None,
+            label: None,
+        };
+        for (type_, attr) in parse_attrs::<IfAddrMsg>(payload)? {
+            match type_ {
+                IFA_ADDRESS => addr.addr = Some(parse_inet_addr(attr, addr_len)?),
+                IFA_LOCAL => addr.local = Some(parse_inet_addr(attr, addr_len)?),
+                IFA_BROADCAST => addr.broadcast = Some(parse_inet_addr(attr, addr_len)?),
+                IFA_LABEL => addr.label = Some(parse_ifname(attr)?),
+                _ => (),
+            }
+        }
+        Ok(addr)
+    }
+
+    pub fn to_msg(&self, flags: u16, seq: u32, pid: u32) -> Vec<u8> {
+        let info = IfAddrMsg {
+            family: self.family,
+            prefix_len: self.prefix_len,
+            flags: self.flags,
+            scope: self.scope,
+            index: self.index as u32,
+        };
+        let mut builder = NlMsgBuilder::new(RTM_NEWADDR, flags, seq, pid, &info);
+        if let Some(addr) = &self.addr {
+            builder.add_attr(IFA_ADDRESS, addr);
+        }
+        if let Some(local) = &self.local {
+            builder.add_attr(IFA_LOCAL, local);
+        }
+        if let Some(broadcast) = &self.broadcast {
+            builder.add_attr(IFA_BROADCAST, broadcast);
+        }
+        if let Some(label) = &self.label {
+            builder.add_attr_str(IFA_LABEL, label);
+        }
+        builder.build()
+    }
+}
+
+impl Route {
+    fn parse(payload: &[u8]) -> Result<Self> {
+        let info = read_struct::<RtMsg>(payload)
+            .ok_or_else(|| errno!(EINVAL, "the route message is truncated"))?;
+        let addr_len = inet_addr_len(info.family)?;
+        if info.dst_len as usize > addr_len * 8 {
+            return_errno!(EINVAL, "the route message is invalid");
+        }
+
+        let mut route = Self {
+            family: info.family,
+            dst_len: info.dst_len,
+            table: info.table as u32,
+            protocol: info.protocol,
+            scope: info.scope,
+            type_: info.type_,
+            dst: None,
+            gateway: None,
+            prefsrc: None,
+            oif: None,
+            priority: None,
+        };
+        for (type_, attr) in parse_attrs::<RtMsg>(payload)? {
+            match type_ {
+                RTA_DST => route.dst = Some(parse_inet_addr(attr, addr_len)?),
+                RTA_GATEWAY => route.gateway = Some(parse_inet_addr(attr, addr_len)?),
+                RTA_PREFSRC => route.prefsrc = Some(parse_inet_addr(attr, addr_len)?),
+                RTA_OIF => {
+                    let oif = attr_u32(attr)? as i32;
+                    if oif <= 0 {
+                        return_errno!(EINVAL, "the interface index is invalid");
+                    }
+                    route.oif = Some(oif);
+                }
+                RTA_PRIORITY => route.priority = Some(attr_u32(attr)?),
+                RTA_TABLE => route.table = attr_u32(attr)?,
+                _ => (),
+            }
+        }
+        Ok(route)
+    }
+
+    pub fn to_msg(&self, flags: u16, seq: u32, pid: u32) -> Vec<u8> {
+        let info = RtMsg {
+            family: self.family,
+            dst_len: self.dst_len,
+            table: if self.table < 256 {
+                self.table as u8
+            } else {
+                RT_TABLE_COMPAT
+            },
+            protocol: self.protocol,
+            scope: self.scope,
+            type_: self.type_,
+            ..Default::default()
+        };
+        let mut builder = NlMsgBuilder::new(RTM_NEWROUTE, flags, seq, pid, &info);
+        builder.add_attr_u32(RTA_TABLE, self.table);
+        if let Some(dst) = &self.dst {
+            builder.add_attr(RTA_DST, dst);
+        }
+        if let Some(priority) = self.priority {
+            builder.add_attr_u32(RTA_PRIORITY, priority);
+        }
+        if let Some(prefsrc) = &self.prefsrc {
+            builder.add_attr(RTA_PREFSRC, prefsrc);
+        }
+        if let Some(gateway) = &self.gateway {
+            builder.add_attr(RTA_GATEWAY, gateway);
+        }
+        if let Some(oif) = self.oif {
+            builder.add_attr_u32(RTA_OIF, oif as u32);
+        }
+        builder.build()
+    }
+}
+
+/// Dump the objects of the request type from the host, and return the payloads of the replied
+/// messages.
+fn host_dump<T: Copy>(socket: &HostSocket, type_: u16, body: &T, seq: u32) -> Result<Vec<Vec<u8>>> {
+    let req = NlMsgBuilder::new(type_, NLM_F_REQUEST | NLM_F_DUMP, seq, 0, body).build();
+    // The message is sent to the kernel of the host without an address
+    socket.sendto(&req, SendFlags::empty(), &None)?;
+
+    // The reply of RTM_GETXXX is RTM_NEWXXX
+    let reply_type = type_ - 2;
+    let mut payloads = Vec::new();
+    let mut buf = vec![0; HOST_RECV_BUF_SIZE];
+    loop {
+        let (len, src_addr) = socket.recvfrom(&mut buf, RecvFlags::MSG_TRUNC)?;
+        if len > buf.len() {
+            return_errno!(EMSGSIZE, "the message from the host is truncated");
+        }
+        // Only the messages from the kernel of the host are accepted
+        let from_kernel = src_addr
+            .and_then(|addr| NetlinkAddr::from_sock_addr(&addr).ok())
+            .map(|addr| addr.pid == 0)
+            .unwrap_or(false);
+        if !from_kernel {
+            return_errno!(EINVAL, "the message is not from the kernel");
+        }
+
+        for msg in NlMsgIter::new(&buf[..len]) {
+            let (hdr, payload) = msg?;
+            if hdr.seq != seq {
+                return_errno!(EINVAL, "the sequence number mismatches");
+            }
+            match hdr.type_ {
+                NLMSG_DONE => return Ok(payloads),
+                NLMSG_ERROR => {
+                    let err = read_struct::<NlMsgErr>(payload).unwrap_or_default();
+                    error!("the host fails to dump type {}: {}", type_, err.error);
+                    return_errno!(EIO, "the host fails to dump");
+                }
+                t if t == reply_type => {
+                    if payloads.len() >= MAX_HOST_ENTRIES {
+                        return_errno!(E2BIG, "too many entries from the host");
+                    }
+                    payloads.push(payload.to_vec());
+                }
+                _ => return_errno!(EINVAL, "unexpected message from the host"),
+            }
+        }
+    }
+}
+
+/// Check the name like `dev_valid_name` of Linux.
+fn parse_ifname(attr: &[u8]) -> Result<String> {
+    let name = attr_str(attr)?;
+    if name.is_empty()
+        || name.len() >= IFNAMSIZ
+        || name == "."
+        || name == ".."
+        || name
+            .chars()
+            .any(|c| c == '/' || c == ':' || c.is_whitespace() || c.is_control())
+    {
+        return_errno!(EINVAL, "the interface name is invalid");
+    }
+    Ok(name.to_string())
+}
+
+fn parse_hw_addr(attr: &[u8]) -> Result<Vec<u8>> {
+    if attr.len() > MAX_ADDR_LEN {
+        return_errno!(EINVAL, "the hardware address is too long");
+    }
+    Ok(attr.to_vec())
+}
+
+fn parse_inet_addr(attr: &[u8], addr_len: usize) -> Result<Vec<u8>> {
+    if attr.len() != addr_len {
+        return_errno!(EINVAL, "the address length mismatches the family");
+    }
+    Ok(attr.to_vec())
+}
+
+fn parse_u8(attr: &[u8]) -> Result<u8> {
+    if attr.len() != 1 {
+        return_errno!(EINVAL, "the attribute is not a u8");
+    }
+    Ok(attr[0])
+}
+
+/// Whether the message is of AF_INET or AF_INET6. The messages of the other families, e.g., the
+/// multicast routes, are ignored.
+fn is_inet_payload(payload: &[u8]) -> bool {
+    // The family is the first field of both ifaddrmsg and rtmsg
+    payload
+        .first()
+        .map(|&family| inet_addr_len(family).is_ok())
+        .unwrap_or(false)
+}
+
+fn inet_addr_len(family: u8) -> Result<usize> {
+    match family as i32 {
+        libc::AF_INET => Ok(4),
+        libc::AF_INET6 => Ok(16),
+        _ => return_errno!(EINVAL, "the address family is not supported"),
+    }
+}
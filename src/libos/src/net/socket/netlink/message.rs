@@ -0,0 +1,285 @@
+//! The wire format of the netlink messages and the route attributes.
+
+use super::*;
+use std::mem;
+
+pub const NETLINK_ROUTE: i32 = 0;
+
+// The types of the control messages
+pub const NLMSG_NOOP: u16 = 1;
+pub const NLMSG_ERROR: u16 = 2;
+pub const NLMSG_DONE: u16 = 3;
+/// The types below are reserved for the control messages
+pub const NLMSG_MIN_TYPE: u16 = 0x10;
+
+// The flags of the messages
+pub const NLM_F_REQUEST: u16 = 0x1;
+pub const NLM_F_MULTI: u16 = 0x2;
+pub const NLM_F_ACK: u16 = 0x4;
+pub const NLM_F_ROOT: u16 = 0x100;
+pub const NLM_F_MATCH: u16 = 0x200;
+pub const NLM_F_DUMP: u16 = NLM_F_ROOT | NLM_F_MATCH;
+
+// The types of the rtnetlink messages
+pub const RTM_BASE: u16 = 16;
+pub const RTM_NEWLINK: u16 = 16;
+pub const RTM_GETLINK: u16 = 18;
+pub const RTM_NEWADDR: u16 = 20;
+pub const RTM_GETADDR: u16 = 22;
+pub const RTM_NEWROUTE: u16 = 24;
+pub const RTM_GETROUTE: u16 = 26;
+/// The last type of the rtnetlink messages known by Linux
+pub const RTM_MAX: u16 = 123;
+
+// The attributes of RTM_NEWLINK
+pub const IFLA_ADDRESS: u16 = 1;
+pub const IFLA_BROADCAST: u16 = 2;
+pub const IFLA_IFNAME: u16 = 3;
+pub const IFLA_MTU: u16 = 4;
+pub const IFLA_OPERSTATE: u16 = 16;
+
+// The attributes of RTM_NEWADDR
+pub const IFA_ADDRESS: u16 = 1;
+pub const IFA_LOCAL: u16 = 2;
+pub const IFA_LABEL: u16 = 3;
+pub const IFA_BROADCAST: u16 = 4;
+
+// The attributes of RTM_NEWROUTE
+pub const RTA_DST: u16 = 1;
+pub const RTA_OIF: u16 = 4;
+pub const RTA_GATEWAY: u16 = 5;
+pub const RTA_PRIORITY: u16 = 6;
+pub const RTA_PREFSRC: u16 = 7;
+pub const RTA_TABLE: u16 = 15;
+
+/// The table ID reported in `rtm_table` for the tables whose IDs do not fit in a byte
+pub const RT_TABLE_COMPAT: u8 = 252;
+
+/// The bits of the attribute type that are flags rather than the type
+const NLA_TYPE_MASK: u16 = !(0x8000 | 0x4000);
+
+const NLMSG_ALIGNTO: usize = 4;
+
+pub const fn nlmsg_align(len: usize) -> usize {
+    (len + NLMSG_ALIGNTO - 1) & !(NLMSG_ALIGNTO - 1)
+}
+
+/// struct nlmsghdr
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NlMsgHdr {
+    pub len: u32,
+    pub type_: u16,
+    pub flags: u16,
+    pub seq: u32,
+    pub pid: u32,
+}
+
+/// struct nlmsgerr
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NlMsgErr {
+    pub error: i32,
+    pub msg: NlMsgHdr,
+}
+
+/// struct ifinfomsg
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IfInfoMsg {
+    pub family: u8,
+    pub pad: u8,
+    pub type_: u16,
+    pub index: i32,
+    pub flags: u32,
+    pub change: u32,
+}
+
+/// struct ifaddrmsg
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IfAddrMsg {
+    pub family: u8,
+    pub prefix_len: u8,
+    pub flags: u8,
+    pub scope: u8,
+    pub index: u32,
+}
+
+/// struct rtmsg
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RtMsg {
+    pub family: u8,
+    pub dst_len: u8,
+    pub src_len: u8,
+    pub tos: u8,
+    pub table: u8,
+    pub protocol: u8,
+    pub scope: u8,
+    pub type_: u8,
+    pub flags: u32,
+}
+
+/// struct rtattr
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct RtAttr {
+    len: u16,
+    type_: u16,
+}
+
+/// Read a C struct from the beginning of the bytes. Return `None` if the bytes are too short.
+pub fn read_struct<T: Copy>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() < mem::size_of::<T>() {
+        return None;
+    }
+    Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+fn struct_as_bytes<T: Copy>(val: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(val as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+/// A netlink message under construction.
+pub struct NlMsgBuilder {
+    buf: Vec<u8>,
+}
+
+impl NlMsgBuilder {
+    pub fn new<T: Copy>(type_: u16, flags: u16, seq: u32, pid: u32, body: &T) -> Self {
+        let hdr = NlMsgHdr {
+            len: 0,
+            type_,
+            flags,
+            seq,
+            pid,
+        };
+        let mut builder = Self { buf: Vec::new() };
+        builder.put(struct_as_bytes(&hdr));
+        builder.put(struct_as_bytes(body));
+        builder
+    }
+
+    pub fn add_attr(&mut self, type_: u16, payload: &[u8]) -> &mut Self {
+        let attr = RtAttr {
+            len: (mem::size_of::<RtAttr>() + payload.len()) as u16,
+            type_,
+        };
+        self.put(struct_as_bytes(&attr));
+        self.put(payload);
+        self
+    }
+
+    pub fn add_attr_u32(&mut self, type_: u16, val: u32) -> &mut Self {
+        self.add_attr(type_, &val.to_ne_bytes())
+    }
+
+    /// Add a string attribute, which is terminated by a null byte.
+    pub fn add_attr_str(&mut self, type_: u16, val: &str) -> &mut Self {
+        let mut payload = Vec::with_capacity(val.len() + 1);
+        payload.extend_from_slice(val.as_bytes());
+        payload.push(0);
+        self.add_attr(type_, &payload)
+    }
+
+    pub fn build(mut self) -> Vec<u8> {
+        let len = self.buf.len() as u32;
+        self.buf[..4].copy_from_slice(&len.to_ne_bytes());
+        self.buf
+    }
+
+    /// Append the bytes and pad them to the alignment.
+    fn put(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(nlmsg_align(self.buf.len()), 0);
+    }
+}
+
+/// An iterator over the netlink messages in a datagram, which yields the header and the payload
+/// of each message.
+pub struct NlMsgIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> NlMsgIter<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for NlMsgIter<'a> {
+    type Item = Result<(NlMsgHdr, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let hdr = match read_struct::<NlMsgHdr>(self.buf) {
+            Some(hdr) => hdr,
+            None => {
+                self.buf = &[];
+                return Some(Err(errno!(EINVAL, "the message header is truncated")));
+            }
+        };
+        let len = hdr.len as usize;
+        if len < mem::size_of::<NlMsgHdr>() || len > self.buf.len() {
+            self.buf = &[];
+            return Some(Err(errno!(EINVAL, "the message length is invalid")));
+        }
+        let payload = &self.buf[mem::size_of::<NlMsgHdr>()..len];
+        // The padding of the last message may be omitted
+        self.buf = &self.buf[nlmsg_align(len).min(self.buf.len())..];
+        Some(Ok((hdr, payload)))
+    }
+}
+
+/// Parse the attributes following the fixed-size body `T` of a message.
+pub fn parse_attrs<T>(payload: &[u8]) -> Result<Vec<(u16, &[u8])>> {
+    let mut buf = payload
+        .get(nlmsg_align(mem::size_of::<T>())..)
+        .unwrap_or(&[]);
+    let mut attrs = Vec::new();
+    while buf.len() >= mem::size_of::<RtAttr>() {
+        let attr = read_struct::<RtAttr>(buf).unwrap();
+        let len = attr.len as usize;
+        if len < mem::size_of::<RtAttr>() || len > buf.len() {
+            return_errno!(EINVAL, "the attribute length is invalid");
+        }
+        attrs.push((
+            attr.type_ & NLA_TYPE_MASK,
+            &buf[mem::size_of::<RtAttr>()..len],
+        ));
+        buf = &buf[nlmsg_align(len).min(buf.len())..];
+    }
+    Ok(attrs)
+}
+
+/// Parse the payload of a `u32` attribute.
+pub fn attr_u32(payload: &[u8]) -> Result<u32> {
+    if payload.len() != mem::size_of::<u32>() {
+        return_errno!(EINVAL, "the attribute is not a u32");
+    }
+    Ok(read_struct::<u32>(payload).unwrap())
+}
+
+/// Parse the payload of a string attribute, which should be terminated by a null byte.
+pub fn attr_str(payload: &[u8]) -> Result<&str> {
+    let len = payload
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| errno!(EINVAL, "the string attribute is not terminated"))?;
+    std::str::from_utf8(&payload[..len]).map_err(|_| errno!(EINVAL, "the string is not UTF-8"))
+}
+
+/// Build an NLMSG_ERROR message in reply to the request. An error of zero acknowledges the
+/// request.
+pub fn error_msg(req: &NlMsgHdr, pid: u32, error: i32) -> Vec<u8> {
+    let body = NlMsgErr { error, msg: *req };
+    NlMsgBuilder::new(NLMSG_ERROR, 0, req.seq, pid, &body).build()
+}
+
+/// Build the NLMSG_DONE message that ends a dump.
+pub fn done_msg(req: &NlMsgHdr, pid: u32) -> Vec<u8> {
+    NlMsgBuilder::new(NLMSG_DONE, NLM_F_MULTI, req.seq, pid, &0_i32).build()
+}
@@ -0,0 +1,433 @@
+//! The in-LibOS netlink sockets of NETLINK_ROUTE.
+//!
+//! glibc's getifaddrs and `ip`-like tools discover the network interfaces through rtnetlink.
+//! Instead of forwarding the sockets to the host, the requests are answered by the LibOS from a
+//! validated snapshot of the interfaces of the host, which can be filtered by `net.interfaces`
+//! of Occlum.json. The other netlink protocols still go to the host.
+
+use super::*;
+use fs::{AccessMode, IoEvents, IoNotifier, IoctlCmd, StatusFlags};
+use rcore_fs::vfs::{FileType, Metadata, Timespec};
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+mod addr;
+mod iface;
+mod message;
+mod route;
+
+pub use self::addr::NetlinkAddr;
+pub use self::iface::init_net_ifaces;
+pub use self::message::NETLINK_ROUTE;
+
+/// The length of the receive queue, i.e., /proc/sys/net/core/rmem_default
+const RECV_BUF_SIZE: usize = 208 * 1024;
+
+lazy_static! {
+    /// The port IDs of the bound netlink sockets
+    static ref PORT_IDS: SgxMutex<HashSet<u32>> = SgxMutex::new(HashSet::new());
+}
+
+/// Create a netlink socket of the protocol.
+pub fn netlink_socket(
+    socket_type: SocketType,
+    flags: FileFlags,
+    protocol: i32,
+) -> Result<Arc<dyn File>> {
+    if protocol != NETLINK_ROUTE {
+        return_errno!(EPROTONOSUPPORT, "only NETLINK_ROUTE is supported");
+    }
+    match socket_type {
+        SocketType::RAW | SocketType::DGRAM => Ok(Arc::new(NetlinkSocket::new(socket_type, flags))),
+        _ => return_errno!(ESOCKTNOSUPPORT, "socket type is not supported"),
+    }
+}
+
+/// A datagram of netlink messages.
+#[derive(Clone)]
+pub struct Datagram {
+    data: Vec<u8>,
+}
+
+impl QueueMsg for Datagram {
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A NETLINK_ROUTE socket.
+///
+/// The requests sent to the kernel are handled as soon as they are sent, and the replies are
+/// pushed to the receive queue of the socket. Like Linux, the replies that do not fit into the
+/// queue are dropped, and the next receiving fails with ENOBUFS to tell the receiver.
+pub struct NetlinkSocket {
+    socket_type: SocketType,
+    inner: SgxMutex<Inner>,
+    queue: MsgQueue<Datagram>,
+    nonblocking: AtomicBool,
+    /// Whether any reply has been dropped since the last receiving
+    overrun: AtomicBool,
+}
+
+struct Inner {
+    /// The port ID, which is assigned when the socket is bound
+    port_id: Option<u32>,
+    groups: u32,
+    /// The default destination of the sending
+    peer_addr: NetlinkAddr,
+}
+
+impl NetlinkSocket {
+    pub fn new(socket_type: SocketType, flags: FileFlags) -> Self {
+        Self {
+            socket_type,
+            inner: SgxMutex::new(Inner {
+                port_id: None,
+                groups: 0,
+                peer_addr: NetlinkAddr::KERNEL,
+            }),
+            queue: MsgQueue::new(RECV_BUF_SIZE),
+            nonblocking: AtomicBool::new(flags.contains(FileFlags::SOCK_NONBLOCK)),
+            overrun: AtomicBool::new(false),
+        }
+    }
+
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
+    pub fn addr(&self) -> NetlinkAddr {
+        let inner = self.inner();
+        NetlinkAddr {
+            pid: inner.port_id.unwrap_or(0),
+            groups: inner.groups,
+        }
+    }
+
+    pub fn peer_addr(&self) -> NetlinkAddr {
+        self.inner().peer_addr
+    }
+
+    /// Bind the socket to the port ID, or to a unique one if the port ID is 0.
+    ///
+    /// The multicast groups are accepted but no notification will be sent, as the interfaces
+    /// never change.
+    pub fn bind(&self, addr: &NetlinkAddr) -> Result<()> {
+        let mut inner = self.inner();
+        match inner.port_id {
+            Some(port_id) if addr.pid != 0 && addr.pid != port_id => {
+                return_errno!(EINVAL, "the socket is already bound");
+            }
+            Some(_) => (),
+            None if addr.pid != 0 => {
+                if !PORT_IDS.lock().unwrap().insert(addr.pid) {
+                    return_errno!(EADDRINUSE, "the port ID is in use");
+                }
+                inner.port_id = Some(addr.pid);
+            }
+            None => {
+                Self::autobind(&mut inner);
+            }
+        }
+        inner.groups = addr.groups;
+        Ok(())
+    }
+
+    /// Set the default destination of the sending. If the address is `None`, the destination is
+    /// reset to the kernel.
+    pub fn connect(&self, addr: &Option<NetlinkAddr>) -> Result<()> {
+        let mut inner = self.inner();
+        inner.peer_addr = addr.unwrap_or(NetlinkAddr::KERNEL);
+        Self::autobind(&mut inner);
+        Ok(())
+    }
+
+    pub fn sendto(
+        &self,
+        buf: &[u8],
+        flags: SendFlags,
+        addr: &Option<NetlinkAddr>,
+    ) -> Result<usize> {
+        self.send(&[buf], flags, addr)
+    }
+
+    pub fn recvfrom(
+        &self,
+        buf: &mut [u8],
+        flags: RecvFlags,
+    ) -> Result<(usize, Option<NetlinkAddr>)> {
+        match self.recv(flags)? {
+            Some(datagram) => {
+                let copied = datagram.copy_to(&mut [buf]);
+                let data_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+                    datagram.len()
+                } else {
+                    copied
+                };
+                Ok((data_len, Some(NetlinkAddr::KERNEL)))
+            }
+            None => Ok((0, None)),
+        }
+    }
+
+    pub fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
+        let addr = match msg_hdr.get_name() {
+            Some(name) => Some(unsafe {
+                NetlinkAddr::try_from_raw(
+                    name.as_ptr() as *const libc::sockaddr,
+                    name.len() as libc::socklen_t,
+                )?
+            }),
+            None => None,
+        };
+        if msg_hdr.get_control().is_some() {
+            warn!("control messages are ignored by netlink socket");
+        }
+        self.send(msg_hdr.get_iovs().as_slices(), flags, &addr)
+    }
+
+    pub fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
+        let datagram = match self.recv(flags)? {
+            Some(datagram) => datagram,
+            None => {
+                msg_hdr.set_name_len(0)?;
+                msg_hdr.set_control_len(0)?;
+                return Ok(0);
+            }
+        };
+
+        let (copied, name_len) = {
+            let (iovs, name, _) = msg_hdr.get_iovs_name_and_control_mut();
+            let copied = datagram.copy_to(iovs.as_slices_mut());
+            let name_len = name
+                .map(|name| NetlinkAddr::KERNEL.copy_to_slice(name))
+                .unwrap_or(0);
+            (copied, name_len)
+        };
+        msg_hdr.set_name_len(name_len)?;
+        msg_hdr.set_control_len(0)?;
+
+        if copied < datagram.len() {
+            msg_hdr.set_flags(MsgHdrFlags::MSG_TRUNC);
+        } else {
+            msg_hdr.set_flags(MsgHdrFlags::empty());
+        }
+
+        if flags.contains(RecvFlags::MSG_TRUNC) {
+            Ok(datagram.len())
+        } else {
+            Ok(copied)
+        }
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+    }
+
+    /// Assign a unique port ID to the socket if it is not bound yet. Like Linux, the first socket
+    /// of a process gets the process ID.
+    fn autobind(inner: &mut Inner) -> u32 {
+        if let Some(port_id) = inner.port_id {
+            return port_id;
+        }
+
+        let mut port_ids = PORT_IDS.lock().unwrap();
+        let mut port_id = current!().process().pid() as u32;
+        // The other IDs are negative
+        let mut rover = -4096_i32;
+        while port_ids.contains(&port_id) {
+            port_id = rover as u32;
+            rover -= 1;
+        }
+        port_ids.insert(port_id);
+        inner.port_id = Some(port_id);
+        port_id
+    }
+
+    /// Send the requests to the kernel, whose replies are received by this socket.
+    fn send(&self, bufs: &[&[u8]], flags: SendFlags, addr: &Option<NetlinkAddr>) -> Result<usize> {
+        let port_id = {
+            let mut inner = self.inner();
+            let dst_addr = addr.unwrap_or(inner.peer_addr);
+            if dst_addr.groups != 0 {
+                return_errno!(EPERM, "sending to the multicast groups is not allowed");
+            }
+            if dst_addr.pid != 0 {
+                return_errno!(ECONNREFUSED, "only the kernel can be sent to");
+            }
+            Self::autobind(&mut inner)
+        };
+
+        let mut data = Vec::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+        for buf in bufs {
+            data.extend_from_slice(buf);
+        }
+        for reply in route::handle_requests(&data, port_id) {
+            if let Err(e) = self.queue.push(Datagram { data: reply }, true) {
+                if e.errno() != Errno::EAGAIN {
+                    return Err(e);
+                }
+                // The sending succeeds anyway, and the receiver is told by ENOBUFS
+                self.overrun.store(true, Ordering::Release);
+                self.queue.notifier().broadcast(&IoEvents::ERR);
+            }
+        }
+        Ok(data.len())
+    }
+
+    /// Receive a datagram. Return `None` if the receiving is shutdown and there are no more
+    /// datagrams.
+    fn recv(&self, flags: RecvFlags) -> Result<Option<Datagram>> {
+        if self.overrun.swap(false, Ordering::AcqRel) {
+            return_errno!(ENOBUFS, "replies are dropped as the queue is full");
+        }
+        let nonblocking = self.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+        self.queue
+            .pop(nonblocking, flags.contains(RecvFlags::MSG_PEEK))
+    }
+}
+
+impl File for NetlinkSocket {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.readv(&mut [buf])
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.writev(&[buf])
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.read(buf)
+    }
+
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            return_errno!(ESPIPE, "a nonzero position is not supported");
+        }
+        self.write(buf)
+    }
+
+    fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
+        // The part of the datagram that does not fit into the buffers is discarded
+        Ok(self
+            .recv(RecvFlags::empty())?
+            .map(|datagram| datagram.copy_to(bufs))
+            .unwrap_or(0))
+    }
+
+    fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
+        self.send(bufs, SendFlags::empty(), &None)
+    }
+
+    fn ioctl(&self, cmd: &mut IoctlCmd) -> Result<i32> {
+        match cmd {
+            IoctlCmd::TCGETS(_) => return_errno!(ENOTTY, "not tty device"),
+            IoctlCmd::TCSETS(_) => return_errno!(ENOTTY, "not tty device"),
+            IoctlCmd::FIONBIO(nonblocking) => {
+                self.set_nonblocking(**nonblocking != 0);
+            }
+            IoctlCmd::FIONREAD(arg) => {
+                **arg = self.queue.next_msg_len().min(std::i32::MAX as usize) as i32;
+            }
+            _ => return_errno!(EINVAL, "unknown ioctl cmd for netlink socket"),
+        }
+        Ok(0)
+    }
+
+    fn access_mode(&self) -> Result<AccessMode> {
+        Ok(AccessMode::O_RDWR)
+    }
+
+    fn status_flags(&self) -> Result<StatusFlags> {
+        if self.nonblocking() {
+            Ok(StatusFlags::O_NONBLOCK)
+        } else {
+            Ok(StatusFlags::empty())
+        }
+    }
+
+    fn set_status_flags(&self, new_status_flags: StatusFlags) -> Result<()> {
+        // Only O_NONBLOCK is supported
+        self.set_nonblocking(new_status_flags.contains(StatusFlags::O_NONBLOCK));
+        Ok(())
+    }
+
+    fn poll_new(&self) -> IoEvents {
+        // The replies are pushed without blocking, so the socket is always writable
+        let mut events = (self.queue.poll_read() & IoEvents::IN) | IoEvents::OUT;
+        if self.overrun.load(Ordering::Acquire) {
+            events |= IoEvents::ERR;
+        }
+        events
+    }
+
+    fn notifier(&self) -> Option<&IoNotifier> {
+        Some(self.queue.notifier())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata {
+            dev: 0,
+            inode: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: Timespec { sec: 0, nsec: 0 },
+            mtime: Timespec { sec: 0, nsec: 0 },
+            ctime: Timespec { sec: 0, nsec: 0 },
+            type_: FileType::Socket,
+            mode: 0o666,
+            nlinks: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+        })
+    }
+}
+
+impl Debug for NetlinkSocket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NetlinkSocket")
+            .field("socket_type", &self.socket_type)
+            .field("port_id", &self.inner().port_id)
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        if let Some(port_id) = self.inner().port_id {
+            PORT_IDS.lock().unwrap().remove(&port_id);
+        }
+    }
+}
+
+pub trait AsNetlinkSocket {
+    fn as_netlink_socket(&self) -> Result<&NetlinkSocket>;
+}
+
+impl AsNetlinkSocket for FileRef {
+    fn as_netlink_socket(&self) -> Result<&NetlinkSocket> {
+        self.as_any()
+            .downcast_ref::<NetlinkSocket>()
+            .ok_or_else(|| errno!(EBADF, "not a netlink socket"))
+    }
+}
@@ -0,0 +1,126 @@
+//! The handler of the NETLINK_ROUTE requests.
+//!
+//! Only the queries of the links, the addresses and the routes are supported. They are answered
+//! from `NET_IFACES`, so the results are the same during the lifetime of the enclave. Any
+//! modification is rejected with EPERM.
+
+use super::iface::{NetIfaces, NET_IFACES};
+use super::message::*;
+use super::*;
+use std::mem;
+
+/// The maximum length of a datagram of a dump, which is `NLMSG_GOODSIZE` of Linux
+const DUMP_DATAGRAM_LEN: usize = 3776;
+
+/// Handle the requests in a datagram sent by the socket of `pid`, and return the datagrams to
+/// reply.
+pub fn handle_requests(buf: &[u8], pid: u32) -> Vec<Vec<u8>> {
+    let ifaces = NET_IFACES.read().unwrap();
+    let mut replies = Vec::new();
+    // Like Linux, the malformed message and the ones after it are ignored
+    for (hdr, payload) in NlMsgIter::new(buf).map_while(|msg| msg.ok()) {
+        // Only the requests are handled, and the control messages are ignored
+        if hdr.flags & NLM_F_REQUEST == 0 || hdr.type_ < NLMSG_MIN_TYPE {
+            continue;
+        }
+
+        let is_dump = hdr.flags & NLM_F_DUMP != 0;
+        match handle_request(&ifaces, &hdr, payload, pid) {
+            Ok(msgs) if is_dump => {
+                let mut msgs = msgs;
+                msgs.push(done_msg(&hdr, pid));
+                replies.extend(pack_msgs(msgs));
+            }
+            Ok(msgs) => {
+                replies.extend(msgs);
+                if hdr.flags & NLM_F_ACK != 0 {
+                    replies.push(error_msg(&hdr, pid, 0));
+                }
+            }
+            Err(e) => {
+                debug!("netlink request {:?} fails: {:?}", hdr, e);
+                replies.push(error_msg(&hdr, pid, -(e.errno() as i32)));
+            }
+        }
+    }
+    replies
+}
+
+fn handle_request(
+    ifaces: &NetIfaces,
+    hdr: &NlMsgHdr,
+    payload: &[u8],
+    pid: u32,
+) -> Result<Vec<Vec<u8>>> {
+    let is_dump = hdr.flags & NLM_F_DUMP != 0;
+    let multi_flags = if is_dump { NLM_F_MULTI } else { 0 };
+    // The older tools send only the family, i.e., struct rtgenmsg, for a dump
+    let family = payload.first().cloned().unwrap_or(0);
+
+    match hdr.type_ {
+        RTM_GETLINK if is_dump => Ok(ifaces
+            .links
+            .iter()
+            .map(|link| link.to_msg(multi_flags, hdr.seq, pid))
+            .collect()),
+        RTM_GETLINK => {
+            let info = read_struct::<IfInfoMsg>(payload)
+                .ok_or_else(|| errno!(EINVAL, "the request is truncated"))?;
+            let link = if info.index > 0 {
+                ifaces.link_by_index(info.index)
+            } else {
+                let name = parse_attrs::<IfInfoMsg>(payload)?
+                    .into_iter()
+                    .find(|(type_, _)| *type_ == IFLA_IFNAME)
+                    .ok_or_else(|| errno!(EINVAL, "no interface is specified"))
+                    .and_then(|(_, attr)| attr_str(attr))?;
+                ifaces.link_by_name(name)
+            }
+            .ok_or_else(|| errno!(ENODEV, "no such interface"))?;
+            Ok(vec![link.to_msg(0, hdr.seq, pid)])
+        }
+        RTM_GETADDR if is_dump => {
+            // The index is only in the complete struct ifaddrmsg
+            let index = if payload.len() >= mem::size_of::<IfAddrMsg>() {
+                read_struct::<IfAddrMsg>(payload).unwrap().index as i32
+            } else {
+                0
+            };
+            Ok(ifaces
+                .addrs
+                .iter()
+                .filter(|addr| family == 0 || addr.family == family)
+                .filter(|addr| index == 0 || addr.index == index)
+                .map(|addr| addr.to_msg(multi_flags, hdr.seq, pid))
+                .collect())
+        }
+        RTM_GETROUTE if is_dump => Ok(ifaces
+            .routes
+            .iter()
+            .filter(|route| family == 0 || route.family == family)
+            .map(|route| route.to_msg(multi_flags, hdr.seq, pid))
+            .collect()),
+        RTM_GETADDR | RTM_GETROUTE => {
+            return_errno!(EOPNOTSUPP, "only the dump is supported");
+        }
+        // The kind of the request is NEW, DEL, GET or SET, in this order
+        type_ if type_ >= RTM_BASE && type_ <= RTM_MAX && (type_ - RTM_BASE) % 4 != 2 => {
+            return_errno!(EPERM, "the network configurations cannot be modified");
+        }
+        _ => return_errno!(EOPNOTSUPP, "the request is not supported"),
+    }
+}
+
+/// Pack the messages of a dump into as few datagrams as possible.
+fn pack_msgs(msgs: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let mut datagrams: Vec<Vec<u8>> = Vec::new();
+    for msg in msgs {
+        match datagrams.last_mut() {
+            Some(datagram) if datagram.len() + msg.len() <= DUMP_DATAGRAM_LEN => {
+                datagram.extend_from_slice(&msg);
+            }
+            _ => datagrams.push(msg),
+        }
+    }
+    datagrams
+}
@@ -0,0 +1,56 @@
+use super::*;
+use crate::process::gid_t;
+use std::mem;
+
+/// The credentials of a process passed through unix sockets, i.e., struct ucred.
+///
+/// The credentials are always taken from the process table of the LibOS, and the processes of
+/// the LibOS run as root, so the uid and the gid are 0 (see getuid and getgid).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    pub pid: pid_t,
+    pub uid: uid_t,
+    pub gid: gid_t,
+}
+
+impl Credentials {
+    /// The credentials reported when there is no peer, which is the same as Linux
+    pub const INVALID: Self = Self {
+        pid: 0,
+        uid: uid_t::MAX,
+        gid: gid_t::MAX,
+    };
+
+    /// The credentials of the current process.
+    pub fn current() -> Self {
+        Self {
+            pid: current!().process().pid(),
+            uid: 0,
+            gid: 0,
+        }
+    }
+
+    /// Parse and check the credentials claimed by the sender in an SCM_CREDENTIALS control
+    /// message.
+    ///
+    /// Linux allows a privileged sender to claim any credentials. Since all the processes of the
+    /// LibOS are privileged, this would make the credentials useless, so a sender can only claim
+    /// its own credentials.
+    pub fn from_claimed(data: &[u8]) -> Result<Self> {
+        if data.len() != mem::size_of::<Self>() {
+            return_errno!(EINVAL, "invalid length of the credentials");
+        }
+        let claimed = unsafe { std::ptr::read_unaligned(data.as_ptr() as *const Self) };
+        if claimed != Self::current() {
+            return_errno!(EPERM, "the credentials are not the ones of the sender");
+        }
+        Ok(claimed)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, mem::size_of::<Self>())
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use super::super::stream::address_space::{create_socket_file, remove_socket_file, ADDRESS_SPACE};
+use super::super::stream::DEFAULT_BUF_SIZE;
+use super::*;
+use fs::{File, IoEvents};
+use net::socket::{MsgHdr, MsgHdrMut};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+
+/// SOCK_DGRAM Unix socket. Each socket owns a queue of the received messages. Binding a socket
+/// registers its receiving end to the global address space, where the senders find it by the
+/// address. Connecting a socket records the receiving end of the peer, so the sending will not need
+/// an address, and makes the socket receive datagrams only from the peer.
+pub struct Datagram {
+    inner: SgxMutex<Inner>,
+    receiver: Arc<Receiver>,
+    nonblocking: AtomicBool,
+    pass_cred: AtomicBool,
+    // Only the sockets created by socketpair have the credentials of the peer
+    peer_cred: Credentials,
+}
+
+struct Inner {
+    addr: Option<Addr>,
+    peer: Option<Peer>,
+    is_write_shutdown: bool,
+}
+
+#[derive(Clone)]
+struct Peer {
+    // The peer created by socketpair has no address
+    addr: Option<Addr>,
+    receiver: Arc<Receiver>,
+}
+
+/// The receiving end of a datagram socket, which is what an address is bound to.
+pub struct Receiver {
+    queue: Arc<MsgQueue>,
+    /// The receiving end of the connected peer, which identifies the only allowed sender
+    peer: SgxMutex<Option<Weak<Receiver>>>,
+}
+
+impl Receiver {
+    fn new() -> Self {
+        Self {
+            queue: Arc::new(MsgQueue::new(DEFAULT_BUF_SIZE)),
+            peer: SgxMutex::new(None),
+        }
+    }
+
+    fn set_peer(&self, peer: Option<&Arc<Receiver>>) {
+        *self.peer.lock().unwrap() = peer.map(Arc::downgrade);
+    }
+
+    /// Whether the socket with the given receiving end can send to this socket. Like Linux, a
+    /// connected socket only accepts datagrams from its peer, even if the peer has been closed.
+    fn accepts(&self, sender: &Arc<Receiver>) -> bool {
+        match &*self.peer.lock().unwrap() {
+            Some(peer) => peer.as_ptr() == Arc::as_ptr(sender),
+            None => true,
+        }
+    }
+}
+
+impl Datagram {
+    pub fn new(flags: FileFlags) -> Self {
+        Self::with_peer_cred(flags, Credentials::INVALID)
+    }
+
+    fn with_peer_cred(flags: FileFlags, peer_cred: Credentials) -> Self {
+        Self {
+            inner: SgxMutex::new(Inner {
+                addr: None,
+                peer: None,
+                is_write_shutdown: false,
+            }),
+            receiver: Arc::new(Receiver::new()),
+            nonblocking: AtomicBool::new(flags.contains(FileFlags::SOCK_NONBLOCK)),
+            pass_cred: AtomicBool::new(false),
+            peer_cred,
+        }
+    }
+
+    pub fn socketpair(flags: FileFlags) -> Result<(Self, Self)> {
+        let socket_a = Self::with_peer_cred(flags, Credentials::current());
+        let socket_b = Self::with_peer_cred(flags, Credentials::current());
+        socket_a.inner().peer = Some(Peer {
+            addr: None,
+            receiver: socket_b.receiver.clone(),
+        });
+        socket_a.receiver.set_peer(Some(&socket_b.receiver));
+        socket_b.inner().peer = Some(Peer {
+            addr: None,
+            receiver: socket_a.receiver.clone(),
+        });
+        socket_b.receiver.set_peer(Some(&socket_a.receiver));
+        Ok((socket_a, socket_b))
+    }
+
+    pub(super) fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    pub(super) fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+    }
+
+    pub(super) fn queue(&self) -> &Arc<MsgQueue> {
+        &self.receiver.queue
+    }
+
+    pub(super) fn poll(&self) -> IoEvents {
+        let queue = self.queue();
+        let mut events = queue.poll_read() & IoEvents::IN;
+        // The receiving is shutdown
+        if queue.is_write_shutdown() {
+            events |= IoEvents::RDHUP | IoEvents::IN;
+        }
+
+        let inner = self.inner();
+        if inner.is_write_shutdown {
+            if queue.is_write_shutdown() {
+                events |= IoEvents::HUP;
+            }
+            events |= IoEvents::OUT;
+        } else if let Some(peer) = &inner.peer {
+            events |= peer.receiver.queue.poll_write() & IoEvents::OUT;
+        } else {
+            events |= IoEvents::OUT;
+        }
+        events
+    }
+
+    fn inner(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+
+    /// Send a message to the given address, or to the connected peer if no address is given.
+    pub(super) fn send(
+        &self,
+        bufs: &[&[u8]],
+        control: Option<&[u8]>,
+        flags: SendFlags,
+        addr: &Option<Addr>,
+    ) -> Result<usize> {
+        let (self_addr, target) = {
+            let inner = self.inner();
+            if inner.is_write_shutdown {
+                return_errno!(EPIPE, "the socket has been shutdown for writing");
+            }
+
+            let target = match addr {
+                Some(addr) => Self::find_receiver(addr)?,
+                None => inner
+                    .peer
+                    .as_ref()
+                    .map(|peer| peer.receiver.clone())
+                    .ok_or_else(|| errno!(ENOTCONN, "the socket is not connected"))?,
+            };
+            (inner.addr.clone(), target)
+        };
+        if !target.accepts(&self.receiver) {
+            return_errno!(EPERM, "the target socket is connected to another socket");
+        }
+        let target = &target.queue;
+
+        let msg = Message::new(bufs, control, self_addr)?;
+        let len = msg.len();
+        let nonblocking = self.nonblocking() || flags.contains(SendFlags::MSG_DONTWAIT);
+        target.push(msg, nonblocking).map_err(|e| match e.errno() {
+            Errno::EPIPE if target.is_read_shutdown() => {
+                errno!(ECONNREFUSED, "the peer socket has been closed")
+            }
+            _ => e,
+        })?;
+        Ok(len)
+    }
+
+    /// Receive a message. Return `None` if the receiving is shutdown and there are no more
+    /// messages.
+    pub(super) fn recv(&self, flags: RecvFlags) -> Result<Option<Message>> {
+        let nonblocking = self.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+        self.queue()
+            .pop(nonblocking, flags.contains(RecvFlags::MSG_PEEK))
+    }
+
+    fn find_receiver(addr: &Addr) -> Result<Arc<Receiver>> {
+        if let Some(receiver) = ADDRESS_SPACE.get_datagram_ref(addr) {
+            return Ok(receiver);
+        }
+        if ADDRESS_SPACE.get_listener_ref(addr).is_some() {
+            return_errno!(EPROTOTYPE, "the address is not bound by a datagram socket");
+        }
+        return_errno!(ECONNREFUSED, "no datagram socket is bound to the address");
+    }
+}
+
+impl UnixSocket for Datagram {
+    fn addr(&self) -> Option<Addr> {
+        self.inner().addr.clone()
+    }
+
+    fn peer_addr(&self) -> Result<Addr> {
+        self.inner()
+            .peer
+            .as_ref()
+            .map(|peer| peer.addr.clone())
+            .flatten()
+            .ok_or_else(|| errno!(ENOTCONN, "the socket is not connected"))
+    }
+
+    fn bind(&self, addr: &mut Addr) -> Result<()> {
+        let mut inner = self.inner();
+        if inner.addr.is_some() {
+            return_errno!(EINVAL, "the socket is already bound");
+        }
+        if ADDRESS_SPACE.is_bound(addr) {
+            return_errno!(EADDRINUSE, "the addr is already bound");
+        }
+
+        // Create the socket file last, so that a failed bind leaves nothing behind
+        let created = create_socket_file(addr)?;
+        if let Err(e) = ADDRESS_SPACE.add_datagram(addr, self.receiver.clone()) {
+            if created {
+                if let Err(e) = remove_socket_file(addr) {
+                    warn!("failed to remove the socket file: {:?}", e);
+                }
+            }
+            return Err(e);
+        }
+        inner.addr = Some(addr.clone());
+        Ok(())
+    }
+
+    fn listen(&self, backlog: i32) -> Result<()> {
+        return_errno!(EOPNOTSUPP, "datagram socket does not support listen");
+    }
+
+    /// Set the default destination of the sending. If the address is `None`, the socket is
+    /// disconnected.
+    fn connect(&self, addr: &Option<Addr>) -> Result<()> {
+        debug!("connect to {:?}", addr);
+
+        let peer = match addr {
+            Some(addr) => Some(Peer {
+                addr: Some(addr.clone()),
+                receiver: Self::find_receiver(addr)?,
+            }),
+            None => None,
+        };
+        if let Some(peer) = &peer {
+            if !peer.receiver.accepts(&self.receiver) {
+                return_errno!(EPERM, "the target socket is connected to another socket");
+            }
+        }
+        let mut inner = self.inner();
+        self.receiver
+            .set_peer(peer.as_ref().map(|peer| &peer.receiver));
+        inner.peer = peer;
+        Ok(())
+    }
+
+    fn accept(&self, flags: FileFlags) -> Result<(Arc<dyn File>, Option<Addr>)> {
+        return_errno!(EOPNOTSUPP, "datagram socket does not support accept");
+    }
+
+    fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<Addr>) -> Result<usize> {
+        self.send(&[buf], None, flags, addr)
+    }
+
+    fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<Addr>)> {
+        match self.recv(flags)? {
+            Some(msg) => {
+                let copied = msg.copy_to(&mut [buf]);
+                let data_len = if flags.contains(RecvFlags::MSG_TRUNC) {
+                    msg.len()
+                } else {
+                    copied
+                };
+                Ok((data_len, msg.src_addr().cloned()))
+            }
+            None => Ok((0, None)),
+        }
+    }
+
+    fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
+        let addr = match msg_hdr.get_name() {
+            Some(name) => Some(unsafe {
+                Addr::try_from_raw(
+                    name.as_ptr() as *const libc::sockaddr,
+                    name.len() as libc::socklen_t,
+                )?
+            }),
+            None => None,
+        };
+
+        let bufs = msg_hdr.get_iovs().as_slices();
+        self.send(bufs, msg_hdr.get_control(), flags, &addr)
+    }
+
+    fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
+        match self.recv(flags)? {
+            Some(msg) => msg.deliver(msg_hdr, flags, self.pass_cred()),
+            None => {
+                msg_hdr.set_name_len(0)?;
+                msg_hdr.set_control_len(0)?;
+                Ok(0)
+            }
+        }
+    }
+
+    fn shutdown(&self, how: HowToShut) -> Result<()> {
+        if how.to_shut_read() {
+            // No more messages can be pushed to the queue, while the queued ones can still be
+            // received.
+            self.queue().shutdown_write();
+        }
+        if how.to_shut_write() {
+            self.inner().is_write_shutdown = true;
+            self.queue().notifier().broadcast(&IoEvents::OUT);
+        }
+        Ok(())
+    }
+
+    fn peer_cred(&self) -> Credentials {
+        self.peer_cred
+    }
+
+    fn pass_cred(&self) -> bool {
+        self.pass_cred.load(Ordering::Acquire)
+    }
+
+    fn set_pass_cred(&self, pass_cred: bool) {
+        self.pass_cred.store(pass_cred, Ordering::Release);
+    }
+}
+
+impl Debug for Datagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Datagram")
+            .field("addr", &self.addr())
+            .field("nonblocking", &self.nonblocking())
+            .finish()
+    }
+}
+
+impl Drop for Datagram {
+    fn drop(&mut self) {
+        // Discard the queued messages and refuse the later ones
+        self.queue().shutdown_read();
+        if let Some(addr) = &self.inner().addr {
+            ADDRESS_SPACE.remove_addr(addr);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use super::*;
+
+mod datagram;
+mod file;
+
+pub use self::datagram::Datagram;
+pub(super) use self::datagram::Receiver;
@@ -0,0 +1,204 @@
+use super::*;
+use fs::FileRef;
+use net::socket::{CMessages, CmsgData, MsgHdrMut};
+use std::mem;
+
+/// The maximum number of files that can be passed in one SCM_RIGHTS control message
+const SCM_MAX_FD: usize = 253;
+
+/// A message sent through a datagram or seqpacket unix socket.
+///
+/// The message boundary is preserved: a message is always received as a whole, and the part that
+/// does not fit into the receive buffers is discarded.
+#[derive(Clone)]
+pub struct Message {
+    data: Vec<u8>,
+    /// The address of the sending socket, if it is bound
+    src_addr: Option<Addr>,
+    /// The files passed by SCM_RIGHTS
+    files: Vec<FileRef>,
+    /// The credentials of the sender, which are delivered as SCM_CREDENTIALS if requested
+    cred: Credentials,
+}
+
+impl Message {
+    /// Create a message from the data buffers and the control buffer of the sender.
+    ///
+    /// The file descriptors in SCM_RIGHTS control messages are resolved to files at sending time,
+    /// so the files are still delivered even if the sender closes the descriptors afterwards. The
+    /// credentials in SCM_CREDENTIALS control messages are checked against the sender.
+    pub fn new(bufs: &[&[u8]], control: Option<&[u8]>, src_addr: Option<Addr>) -> Result<Self> {
+        let data_len = bufs.iter().map(|buf| buf.len()).sum();
+        let mut data = Vec::with_capacity(data_len);
+        for buf in bufs {
+            data.extend_from_slice(buf);
+        }
+
+        let mut files = Vec::new();
+        if let Some(control) = control {
+            let mut control = control.to_vec();
+            let current = current!();
+            for cmsg in CMessages::from_bytes(&mut control) {
+                match cmsg {
+                    CmsgData::ScmRights(scm_rights) => {
+                        for fd in scm_rights.iter_fds() {
+                            files.push(current.file(fd)?);
+                        }
+                    }
+                    CmsgData::ScmCredentials(scm_credentials) => {
+                        Credentials::from_claimed(scm_credentials.data())?;
+                    }
+                }
+            }
+            if files.len() > SCM_MAX_FD {
+                return_errno!(EINVAL, "too many files to pass");
+            }
+        }
+
+        Ok(Self {
+            data,
+            src_addr,
+            files,
+            cred: Credentials::current(),
+        })
+    }
+
+    pub fn src_addr(&self) -> Option<&Addr> {
+        self.src_addr.as_ref()
+    }
+
+    /// Deliver the message to the receiver: the data, the address of the sender and the passed
+    /// files are filled into the message header. The credentials of the sender are filled, too, if
+    /// the receiver has enabled SO_PASSCRED.
+    ///
+    /// Return the length of the data received, or the real length of the message if MSG_TRUNC is
+    /// given in the flags.
+    pub fn deliver(
+        &self,
+        msg_hdr: &mut MsgHdrMut,
+        flags: RecvFlags,
+        pass_cred: bool,
+    ) -> Result<usize> {
+        let (copied, name_len, control_len, ctrunc) = {
+            let (iovs, name, mut control) = msg_hdr.get_iovs_name_and_control_mut();
+            let copied = self.copy_to(iovs.as_slices_mut());
+            let name_len = match (name, self.src_addr()) {
+                (Some(name), Some(addr)) => addr.copy_to_slice(name),
+                _ => 0,
+            };
+            let cloexec = flags.contains(RecvFlags::MSG_CMSG_CLOEXEC);
+            // Like Linux, the credentials come before the files
+            let (cred_len, cred_trunc) = if pass_cred {
+                self.put_credentials(control.as_deref_mut())
+            } else {
+                (0, false)
+            };
+            let (files_len, files_trunc) =
+                self.install_files(control.map(|control| &mut control[cred_len..]), cloexec);
+            (
+                copied,
+                name_len,
+                cred_len + files_len,
+                cred_trunc || files_trunc,
+            )
+        };
+        msg_hdr.set_name_len(name_len)?;
+        msg_hdr.set_control_len(control_len)?;
+
+        let mut msg_flags = MsgHdrFlags::empty();
+        if copied < self.len() {
+            msg_flags |= MsgHdrFlags::MSG_TRUNC;
+        }
+        if ctrunc {
+            msg_flags |= MsgHdrFlags::MSG_CTRUNC;
+        }
+        msg_hdr.set_flags(msg_flags);
+
+        if flags.contains(RecvFlags::MSG_TRUNC) {
+            Ok(self.len())
+        } else {
+            Ok(copied)
+        }
+    }
+
+    /// Write the credentials of the sender to the control buffer as an SCM_CREDENTIALS control
+    /// message. Return the length of the control buffer used and whether the control message is
+    /// truncated.
+    fn put_credentials(&self, control: Option<&mut [u8]>) -> (usize, bool) {
+        let data = self.cred.as_bytes();
+        let space = unsafe { libc::CMSG_SPACE(data.len() as u32) } as usize;
+        let control = match control {
+            Some(control) if control.len() >= space => control,
+            _ => return (0, true),
+        };
+
+        let header_len = unsafe { libc::CMSG_LEN(0) } as usize;
+        control[header_len..header_len + data.len()].copy_from_slice(data);
+
+        let mut cmsg: libc::cmsghdr = unsafe { mem::zeroed() };
+        cmsg.cmsg_len = unsafe { libc::CMSG_LEN(data.len() as u32) } as _;
+        cmsg.cmsg_level = libc::SOL_SOCKET;
+        cmsg.cmsg_type = libc::SCM_CREDENTIALS;
+        unsafe {
+            std::ptr::write_unaligned(control.as_mut_ptr() as *mut libc::cmsghdr, cmsg);
+        }
+        (space, false)
+    }
+
+    /// Install the passed files to the file table of the current process, and write their file
+    /// descriptors to the control buffer as an SCM_RIGHTS control message.
+    ///
+    /// The files that do not fit into the control buffer are discarded. Return the length of the
+    /// control buffer used and whether the control message is truncated.
+    fn install_files(&self, control: Option<&mut [u8]>, cloexec: bool) -> (usize, bool) {
+        if self.files.is_empty() {
+            return (0, false);
+        }
+        let control = match control {
+            Some(control) => control,
+            None => return (0, true),
+        };
+
+        let fd_size = mem::size_of::<FileDesc>();
+        let header_len = unsafe { libc::CMSG_LEN(0) } as usize;
+        if control.len() < header_len + fd_size {
+            return (0, true);
+        }
+        let nfds = self.files.len().min((control.len() - header_len) / fd_size);
+
+        let current = current!();
+        let data = &mut control[header_len..header_len + nfds * fd_size];
+        for (file, fd_bytes) in self.files.iter().zip(data.chunks_exact_mut(fd_size)) {
+            let fd = current.add_file(file.clone(), cloexec);
+            fd_bytes.copy_from_slice(&fd.to_ne_bytes());
+        }
+
+        let cmsg_len = unsafe { libc::CMSG_LEN((nfds * fd_size) as u32) } as usize;
+        let mut cmsg: libc::cmsghdr = unsafe { mem::zeroed() };
+        cmsg.cmsg_len = cmsg_len as _;
+        cmsg.cmsg_level = libc::SOL_SOCKET;
+        cmsg.cmsg_type = libc::SCM_RIGHTS;
+        unsafe {
+            std::ptr::write_unaligned(control.as_mut_ptr() as *mut libc::cmsghdr, cmsg);
+        }
+
+        let space = unsafe { libc::CMSG_SPACE((nfds * fd_size) as u32) } as usize;
+        (space.min(control.len()), nfds < self.files.len())
+    }
+}
+
+impl QueueMsg for Message {
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Message")
+            .field("len", &self.data.len())
+            .field("src_addr", &self.src_addr)
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
@@ -1,22 +1,34 @@
 use self::addr::Addr;
+use self::message::Message;
 use super::*;
 
 mod addr;
+mod cred;
+mod datagram;
+mod message;
 mod stream;
 
+/// The queue of the messages sent to a datagram or seqpacket unix socket
+type MsgQueue = super::MsgQueue<Message>;
+
 pub use self::addr::Addr as UnixAddr;
+pub use self::cred::Credentials;
+pub use self::datagram::Datagram;
 pub use self::stream::Stream;
 
-//TODO: rewrite this file when a new kind of uds is added
-pub fn unix_socket(socket_type: SocketType, flags: FileFlags, protocol: i32) -> Result<Stream> {
+pub fn unix_socket(
+    socket_type: SocketType,
+    flags: FileFlags,
+    protocol: i32,
+) -> Result<Arc<dyn File>> {
     if protocol != 0 && protocol != AddressFamily::LOCAL as i32 {
         return_errno!(EPROTONOSUPPORT, "protocol is not supported");
     }
 
-    if socket_type == SocketType::STREAM {
-        Ok(Stream::new(flags))
-    } else {
-        return_errno!(ESOCKTNOSUPPORT, "only stream type is supported");
+    match socket_type {
+        SocketType::STREAM | SocketType::SEQPACKET => Ok(Arc::new(Stream::new(socket_type, flags))),
+        SocketType::DGRAM => Ok(Arc::new(Datagram::new(flags))),
+        _ => return_errno!(ESOCKTNOSUPPORT, "socket type is not supported"),
     }
 }
 
@@ -24,26 +36,70 @@ pub fn socketpair(
     socket_type: SocketType,
     flags: FileFlags,
     protocol: i32,
-) -> Result<(Stream, Stream)> {
+) -> Result<(Arc<dyn File>, Arc<dyn File>)> {
     if protocol != 0 && protocol != AddressFamily::LOCAL as i32 {
         return_errno!(EPROTONOSUPPORT, "protocol is not supported");
     }
 
-    if socket_type == SocketType::STREAM {
-        Stream::socketpair(flags)
-    } else {
-        return_errno!(ESOCKTNOSUPPORT, "only stream type is supported");
+    match socket_type {
+        SocketType::STREAM | SocketType::SEQPACKET => {
+            let (socket_a, socket_b) = Stream::socketpair(socket_type, flags)?;
+            Ok((Arc::new(socket_a), Arc::new(socket_b)))
+        }
+        SocketType::DGRAM => {
+            let (socket_a, socket_b) = Datagram::socketpair(flags)?;
+            Ok((Arc::new(socket_a), Arc::new(socket_b)))
+        }
+        _ => return_errno!(ESOCKTNOSUPPORT, "socket type is not supported"),
     }
 }
 
+/// The socket operations of all types of unix sockets.
+pub trait UnixSocket {
+    fn addr(&self) -> Option<Addr>;
+
+    fn peer_addr(&self) -> Result<Addr>;
+
+    fn bind(&self, addr: &mut Addr) -> Result<()>;
+
+    fn listen(&self, backlog: i32) -> Result<()>;
+
+    fn connect(&self, addr: &Option<Addr>) -> Result<()>;
+
+    fn accept(&self, flags: FileFlags) -> Result<(Arc<dyn File>, Option<Addr>)>;
+
+    fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<Addr>) -> Result<usize>;
+
+    fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<Addr>)>;
+
+    fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize>;
+
+    fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize>;
+
+    fn shutdown(&self, how: HowToShut) -> Result<()>;
+
+    /// The credentials of the peer when the connection is established, i.e., SO_PEERCRED.
+    fn peer_cred(&self) -> Credentials;
+
+    /// Whether the credentials of the sender are received as SCM_CREDENTIALS, i.e., SO_PASSCRED.
+    fn pass_cred(&self) -> bool;
+
+    fn set_pass_cred(&self, pass_cred: bool);
+}
+
 pub trait AsUnixSocket {
-    fn as_unix_socket(&self) -> Result<&Stream>;
+    fn as_unix_socket(&self) -> Result<&dyn UnixSocket>;
 }
 
 impl AsUnixSocket for FileRef {
-    fn as_unix_socket(&self) -> Result<&Stream> {
-        self.as_any()
-            .downcast_ref::<Stream>()
-            .ok_or_else(|| errno!(EBADF, "not a unix socket"))
+    fn as_unix_socket(&self) -> Result<&dyn UnixSocket> {
+        let any = self.as_any();
+        if let Some(stream) = any.downcast_ref::<Stream>() {
+            Ok(stream)
+        } else if let Some(datagram) = any.downcast_ref::<Datagram>() {
+            Ok(datagram)
+        } else {
+            return_errno!(EBADF, "not a unix socket")
+        }
     }
 }
@@ -1,11 +1,54 @@
+use super::super::datagram::Receiver;
 use super::endpoint::Endpoint;
 use super::endpoint::RelayNotifier;
 use super::stream::Listener;
 use super::*;
+use fs::{CreationFlags, FileMode};
 use std::collections::btree_map::BTreeMap;
 
 lazy_static! {
-    pub(super) static ref ADDRESS_SPACE: AddressSpace = AddressSpace::new();
+    pub(in super::super) static ref ADDRESS_SPACE: AddressSpace = AddressSpace::new();
+}
+
+/// Create the corresponding file in the fs for a pathname address and fill the address with the
+/// inode number of the file. Return whether the file is created by this call.
+pub(in super::super) fn create_socket_file(addr: &mut Addr) -> Result<bool> {
+    if let Addr::File(inode_num, path) = addr {
+        let (corresponding_inode_num, created) = {
+            let current = current!();
+            let fs = current.fs().read().unwrap();
+            let mode = FileMode::from_bits(0o777).unwrap();
+            let (file_ref, created) = match fs.open_file(
+                path.path_str(),
+                (CreationFlags::O_CREAT | CreationFlags::O_EXCL).bits(),
+                mode,
+            ) {
+                Ok(file_ref) => (file_ref, true),
+                Err(e) if e.errno() == EEXIST => (fs.open_file(path.path_str(), 0, mode)?, false),
+                Err(e) => return Err(e),
+            };
+            (file_ref.metadata()?.inode, created)
+        };
+        *inode_num = Some(corresponding_inode_num);
+        return Ok(created);
+    }
+    Ok(false)
+}
+
+/// Remove the file created by `create_socket_file` when the address fails to be bound.
+pub(in super::super) fn remove_socket_file(addr: &Addr) -> Result<()> {
+    if let Addr::File(_, unix_path) = addr {
+        let path = unix_path.absolute();
+        let (dir_path, file_name) = path.rsplit_once('/').unwrap();
+        let dir_path = if dir_path.is_empty() { "/" } else { dir_path };
+        let dir_inode = {
+            let current = current!();
+            let fs = current.fs().read().unwrap();
+            fs.lookup_inode(dir_path)?
+        };
+        dir_inode.unlink(file_name)?;
+    }
+    Ok(())
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -24,11 +67,22 @@ impl AddressSpaceKey {
     }
 }
 
+/// What is bound to an address. The address space is shared by all types of unix sockets, so an
+/// address can only be bound by one socket no matter what its type is.
+#[derive(Clone)]
+pub enum Binding {
+    /// A stream or seqpacket socket that is bound but not listening
+    Idle,
+    Listener(Arc<Listener>),
+    /// The receiving end of a datagram socket
+    Datagram(Arc<Receiver>),
+}
+
 pub struct AddressSpace {
     // For "file", use inode number as "key" instead of path string so that listeners can still
     // be reached even if the socket file is moved or renamed.
-    file: SgxMutex<BTreeMap<AddressSpaceKey, Option<Arc<Listener>>>>,
-    abstr: SgxMutex<BTreeMap<AddressSpaceKey, Option<Arc<Listener>>>>,
+    file: SgxMutex<BTreeMap<AddressSpaceKey, Binding>>,
+    abstr: SgxMutex<BTreeMap<AddressSpaceKey, Binding>>,
 }
 
 impl AddressSpace {
@@ -45,7 +99,23 @@ impl AddressSpace {
         if space.contains_key(&key) {
             return_errno!(EADDRINUSE, "the addr is already bound");
         } else {
-            space.insert(key, None);
+            space.insert(key, Binding::Idle);
+            Ok(())
+        }
+    }
+
+    /// Bind the receiving end of a datagram socket to the address.
+    pub(in super::super) fn add_datagram(
+        &self,
+        addr: &Addr,
+        receiver: Arc<Receiver>,
+    ) -> Result<()> {
+        let key = Self::get_key(addr).ok_or_else(|| errno!(EINVAL, "can't find socket file"))?;
+        let mut space = self.get_space(addr);
+        if space.contains_key(&key) {
+            return_errno!(EADDRINUSE, "the addr is already bound");
+        } else {
+            space.insert(key, Binding::Datagram(receiver));
             Ok(())
         }
     }
@@ -54,24 +124,21 @@ impl AddressSpace {
         &self,
         addr: &Addr,
         capacity: usize,
+        socket_type: SocketType,
         nonblocking: bool,
         notifier: Arc<RelayNotifier>,
     ) -> Result<()> {
         let key = Self::get_key(addr).ok_or_else(|| errno!(EINVAL, "the socket is not bound"))?;
         let mut space = self.get_space(addr);
 
-        if let Some(option) = space.get(&key) {
-            if option.is_none() {
-                space.insert(
-                    key,
-                    Some(Arc::new(Listener::new(capacity, nonblocking, notifier)?)),
-                );
+        match space.get(&key) {
+            Some(Binding::Idle) => {
+                let listener = Listener::new(capacity, socket_type, nonblocking, notifier)?;
+                space.insert(key, Binding::Listener(Arc::new(listener)));
                 Ok(())
-            } else {
-                return_errno!(EINVAL, "the socket is already listened");
             }
-        } else {
-            return_errno!(EINVAL, "the socket is not bound");
+            Some(_) => return_errno!(EINVAL, "the socket is already listened"),
+            None => return_errno!(EINVAL, "the socket is not bound"),
         }
     }
 
@@ -79,15 +146,13 @@ impl AddressSpace {
         let key = Self::get_key(addr).ok_or_else(|| errno!(EINVAL, "the socket is not bound"))?;
         let mut space = self.get_space(addr);
 
-        if let Some(option) = space.get(&key) {
-            if let Some(listener) = option {
+        match space.get(&key) {
+            Some(Binding::Listener(listener)) => {
                 listener.resize(capacity);
-            } else {
-                return_errno!(EINVAL, "the socket is not listening");
+                Ok(())
             }
-            Ok(())
-        } else {
-            return_errno!(EINVAL, "the socket is not bound");
+            Some(_) => return_errno!(EINVAL, "the socket is not listening"),
+            None => return_errno!(EINVAL, "the socket is not bound"),
         }
     }
 
@@ -105,10 +170,30 @@ impl AddressSpace {
     }
 
     pub fn get_listener_ref(&self, addr: &Addr) -> Option<Arc<Listener>> {
+        match self.get_binding(addr) {
+            Some(Binding::Listener(listener)) => Some(listener),
+            _ => None,
+        }
+    }
+
+    /// Get the receiving end of the datagram socket bound to the address.
+    pub(in super::super) fn get_datagram_ref(&self, addr: &Addr) -> Option<Arc<Receiver>> {
+        match self.get_binding(addr) {
+            Some(Binding::Datagram(receiver)) => Some(receiver),
+            _ => None,
+        }
+    }
+
+    /// Whether the address is bound by any socket.
+    pub fn is_bound(&self, addr: &Addr) -> bool {
+        self.get_binding(addr).is_some()
+    }
+
+    fn get_binding(&self, addr: &Addr) -> Option<Binding> {
         let key = Self::get_key(addr);
         if let Some(key) = key {
             let space = self.get_space(addr);
-            space.get(&key).map(|x| x.clone()).flatten()
+            space.get(&key).cloned()
         } else {
             None
         }
@@ -124,10 +209,7 @@ impl AddressSpace {
         }
     }
 
-    fn get_space(
-        &self,
-        addr: &Addr,
-    ) -> SgxMutexGuard<'_, BTreeMap<AddressSpaceKey, Option<Arc<Listener>>>> {
+    fn get_space(&self, addr: &Addr) -> SgxMutexGuard<'_, BTreeMap<AddressSpaceKey, Binding>> {
         match addr {
             Addr::File(_, _) => self.file.lock().unwrap(),
             Addr::Abstract(_) => self.abstr.lock().unwrap(),
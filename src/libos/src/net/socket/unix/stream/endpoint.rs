@@ -3,27 +3,65 @@ use events::{Event, EventFilter, Notifier, Observer};
 use fs::channel::{Channel, Consumer, Producer};
 use fs::{IoEvents, IoNotifier};
 use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
 
 pub type Endpoint = Arc<Inner>;
 
-/// Constructor of two connected Endpoints
-pub fn end_pair(nonblocking: bool) -> Result<(Endpoint, Endpoint)> {
-    let (pro_a, con_a) = Channel::new(DEFAULT_BUF_SIZE)?.split();
-    let (pro_b, con_b) = Channel::new(DEFAULT_BUF_SIZE)?.split();
+/// Constructor of two connected Endpoints. The credentials of the owners of the two ends are
+/// recorded as the peer credentials of each other.
+pub fn end_pair(
+    socket_type: SocketType,
+    nonblocking: bool,
+    cred_a: Credentials,
+    cred_b: Credentials,
+) -> Result<(Endpoint, Endpoint)> {
+    let (channel_a, channel_b) = match socket_type {
+        SocketType::STREAM => {
+            let (pro_a, con_a) = Channel::new(DEFAULT_BUF_SIZE)?.split();
+            let (pro_b, con_b) = Channel::new(DEFAULT_BUF_SIZE)?.split();
+            (
+                EndChannel::Stream {
+                    reader: con_a,
+                    writer: pro_b,
+                },
+                EndChannel::Stream {
+                    reader: con_b,
+                    writer: pro_a,
+                },
+            )
+        }
+        SocketType::SEQPACKET => {
+            let queue_a = Arc::new(MsgQueue::new(DEFAULT_BUF_SIZE));
+            let queue_b = Arc::new(MsgQueue::new(DEFAULT_BUF_SIZE));
+            (
+                EndChannel::Seqpacket {
+                    reader: queue_a.clone(),
+                    writer: queue_b.clone(),
+                    nonblocking: AtomicBool::new(false),
+                },
+                EndChannel::Seqpacket {
+                    reader: queue_b,
+                    writer: queue_a,
+                    nonblocking: AtomicBool::new(false),
+                },
+            )
+        }
+        _ => return_errno!(ESOCKTNOSUPPORT, "not a connection-oriented socket type"),
+    };
 
     let mut end_a = Arc::new(Inner {
         addr: RwLock::new(None),
-        reader: con_a,
-        writer: pro_b,
+        channel: channel_a,
         peer: Weak::default(),
+        peer_cred: cred_b,
         ancillary: RwLock::new(None),
     });
     let end_b = Arc::new(Inner {
         addr: RwLock::new(None),
-        reader: con_b,
-        writer: pro_a,
+        channel: channel_b,
         peer: Arc::downgrade(&end_a),
+        peer_cred: cred_a,
         ancillary: RwLock::new(None),
     });
 
@@ -40,12 +78,29 @@ pub fn end_pair(nonblocking: bool) -> Result<(Endpoint, Endpoint)> {
 /// One end of the connected unix socket
 pub struct Inner {
     addr: RwLock<Option<Addr>>,
-    reader: Consumer<u8>,
-    writer: Producer<u8>,
+    channel: EndChannel,
     peer: Weak<Self>,
+    // Kept even if the peer is closed
+    peer_cred: Credentials,
     ancillary: RwLock<Option<Ancillary>>,
 }
 
+/// The channels used to transfer data between the two ends.
+///
+/// A stream socket transfers a sequence of bytes, while a seqpacket socket transfers messages
+/// whose boundaries are preserved.
+enum EndChannel {
+    Stream {
+        reader: Consumer<u8>,
+        writer: Producer<u8>,
+    },
+    Seqpacket {
+        reader: Arc<MsgQueue>,
+        writer: Arc<MsgQueue>,
+        nonblocking: AtomicBool,
+    },
+}
+
 impl Inner {
     pub fn addr(&self) -> Option<Addr> {
         self.addr.read().unwrap().clone()
@@ -59,35 +114,104 @@ impl Inner {
         self.peer.upgrade().map(|end| end.addr().clone()).flatten()
     }
 
+    pub fn peer_cred(&self) -> Credentials {
+        self.peer_cred
+    }
+
     pub fn set_nonblocking(&self, nonblocking: bool) {
-        self.reader.set_nonblocking(nonblocking);
-        self.writer.set_nonblocking(nonblocking);
+        match &self.channel {
+            EndChannel::Stream { reader, writer } => {
+                reader.set_nonblocking(nonblocking);
+                writer.set_nonblocking(nonblocking);
+            }
+            EndChannel::Seqpacket {
+                nonblocking: is_nonblocking,
+                ..
+            } => is_nonblocking.store(nonblocking, Ordering::Release),
+        }
     }
 
     pub fn nonblocking(&self) -> bool {
-        let cons_nonblocking = self.reader.is_nonblocking();
-        let prod_nonblocking = self.writer.is_nonblocking();
-        assert_eq!(cons_nonblocking, prod_nonblocking);
-        cons_nonblocking
+        match &self.channel {
+            EndChannel::Stream { reader, writer } => {
+                let cons_nonblocking = reader.is_nonblocking();
+                let prod_nonblocking = writer.is_nonblocking();
+                assert_eq!(cons_nonblocking, prod_nonblocking);
+                cons_nonblocking
+            }
+            EndChannel::Seqpacket { nonblocking, .. } => nonblocking.load(Ordering::Acquire),
+        }
     }
+
+    pub fn is_seqpacket(&self) -> bool {
+        matches!(self.channel, EndChannel::Seqpacket { .. })
+    }
+
     pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
-        self.reader.pop_slice(buf)
+        self.readv(&mut [buf])
     }
 
     pub fn write(&self, buf: &[u8]) -> Result<usize> {
-        self.writer.push_slice(buf)
+        self.writev(&[buf])
     }
 
     pub fn readv(&self, bufs: &mut [&mut [u8]]) -> Result<usize> {
-        self.reader.pop_slices(bufs)
+        match &self.channel {
+            EndChannel::Stream { reader, .. } => reader.pop_slices(bufs),
+            // The part of the message that does not fit into the buffers is discarded
+            EndChannel::Seqpacket { .. } => Ok(self
+                .recv_msg(self.nonblocking(), false)?
+                .map(|msg| msg.copy_to(bufs))
+                .unwrap_or(0)),
+        }
     }
 
     pub fn writev(&self, bufs: &[&[u8]]) -> Result<usize> {
-        self.writer.push_slices(bufs)
+        match &self.channel {
+            EndChannel::Stream { writer, .. } => writer.push_slices(bufs),
+            EndChannel::Seqpacket { .. } => {
+                let msg = Message::new(bufs, None, self.addr())?;
+                self.send_msg(msg, self.nonblocking())
+            }
+        }
+    }
+
+    /// Send a whole message to the peer of a seqpacket socket.
+    pub fn send_msg(&self, msg: Message, nonblocking: bool) -> Result<usize> {
+        match &self.channel {
+            EndChannel::Seqpacket { writer, .. } => {
+                let len = msg.len();
+                writer.push(msg, nonblocking)?;
+                Ok(len)
+            }
+            EndChannel::Stream { .. } => {
+                return_errno!(
+                    EOPNOTSUPP,
+                    "stream socket does not preserve message boundaries"
+                )
+            }
+        }
+    }
+
+    /// Receive a whole message from the peer of a seqpacket socket. Return `None` if the peer has
+    /// shutdown its writing side and there are no more messages.
+    pub fn recv_msg(&self, nonblocking: bool, peek: bool) -> Result<Option<Message>> {
+        match &self.channel {
+            EndChannel::Seqpacket { reader, .. } => reader.pop(nonblocking, peek),
+            EndChannel::Stream { .. } => {
+                return_errno!(
+                    EOPNOTSUPP,
+                    "stream socket does not preserve message boundaries"
+                )
+            }
+        }
     }
 
     pub fn bytes_to_read(&self) -> usize {
-        self.reader.items_to_consume()
+        match &self.channel {
+            EndChannel::Stream { reader, .. } => reader.items_to_consume(),
+            EndChannel::Seqpacket { reader, .. } => reader.bytes_to_read(),
+        }
     }
 
     pub fn shutdown(&self, how: HowToShut) -> Result<()> {
@@ -95,25 +219,49 @@ impl Inner {
             return_errno!(ENOTCONN, "The socket is not connected.");
         }
 
-        if how.to_shut_read() {
-            self.reader.shutdown()
-        }
-
-        if how.to_shut_write() {
-            self.writer.shutdown()
+        match &self.channel {
+            EndChannel::Stream { reader, writer } => {
+                if how.to_shut_read() {
+                    reader.shutdown()
+                }
+                if how.to_shut_write() {
+                    writer.shutdown()
+                }
+            }
+            EndChannel::Seqpacket { reader, writer, .. } => {
+                if how.to_shut_read() {
+                    reader.shutdown_read()
+                }
+                if how.to_shut_write() {
+                    writer.shutdown_write()
+                }
+            }
         }
 
         Ok(())
     }
 
     pub fn poll(&self) -> IoEvents {
-        let mut events = IoEvents::empty();
-        let reader_events = self.reader.poll();
-        let writer_events = self.writer.poll();
+        let (reader_events, writer_events, is_reader_shutdown, is_writer_shutdown) =
+            match &self.channel {
+                EndChannel::Stream { reader, writer } => (
+                    reader.poll(),
+                    writer.poll(),
+                    reader.is_self_shutdown(),
+                    writer.is_self_shutdown(),
+                ),
+                EndChannel::Seqpacket { reader, writer, .. } => (
+                    reader.poll_read(),
+                    writer.poll_write(),
+                    reader.is_read_shutdown(),
+                    writer.is_write_shutdown(),
+                ),
+            };
 
-        if reader_events.contains(IoEvents::HUP) || self.reader.is_self_shutdown() {
+        let mut events = IoEvents::empty();
+        if reader_events.contains(IoEvents::HUP) || is_reader_shutdown {
             events |= IoEvents::RDHUP | IoEvents::IN;
-            if writer_events.contains(IoEvents::ERR) || self.writer.is_self_shutdown() {
+            if writer_events.contains(IoEvents::ERR) || is_writer_shutdown {
                 events |= IoEvents::HUP | IoEvents::OUT;
             }
         }
@@ -135,13 +283,18 @@ impl Inner {
     }
 
     pub(self) fn register_relay_notifier(&self, observer: &Arc<RelayNotifier>) {
-        self.reader.notifier().register(
+        let (reader_notifier, writer_notifier) = match &self.channel {
+            EndChannel::Stream { reader, writer } => (reader.notifier(), writer.notifier()),
+            EndChannel::Seqpacket { reader, writer, .. } => (reader.notifier(), writer.notifier()),
+        };
+
+        reader_notifier.register(
             Arc::downgrade(observer) as Weak<dyn Observer<_>>,
             None,
             None,
         );
 
-        self.writer.notifier().register(
+        writer_notifier.register(
             Arc::downgrade(observer) as Weak<dyn Observer<_>>,
             None,
             None,
@@ -153,6 +306,17 @@ impl Inner {
     }
 }
 
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // The channels of a stream socket are shut down when they are dropped, while the message
+        // queues of a seqpacket socket are shared with the peer and have to be shut down here.
+        if let EndChannel::Seqpacket { reader, writer, .. } = &self.channel {
+            reader.shutdown_read();
+            writer.shutdown_write();
+        }
+    }
+}
+
 /// Ancillary data of connected unix socket's sent/received control message.
 #[derive(Clone, Debug)]
 pub struct Ancillary {
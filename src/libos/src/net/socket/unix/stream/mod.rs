@@ -1,8 +1,10 @@
 use super::*;
 
-mod address_space;
+pub(super) mod address_space;
 mod endpoint;
 mod file;
 mod stream;
 
 pub use stream::Stream;
+
+pub(super) use endpoint::DEFAULT_BUF_SIZE;
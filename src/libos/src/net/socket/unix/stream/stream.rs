@@ -1,59 +1,128 @@
-use super::address_space::ADDRESS_SPACE;
+use super::address_space::{create_socket_file, ADDRESS_SPACE};
 use super::endpoint::{end_pair, Ancillary, Endpoint, RelayNotifier};
 use super::*;
 use events::{Event, EventFilter, Notifier, Observer};
 use fs::channel::Channel;
 use fs::IoEvents;
-use fs::{CreationFlags, FileMode};
 use net::socket::{CMessages, CmsgData, Iovs, MsgHdr, MsgHdrMut};
 use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-/// SOCK_STREAM Unix socket. It has three statuses: unconnected, listening and connected.  When a
-/// socket is created, it is in unconnected status.  It will transfer to listening after listen is
-/// called and connected after connect is called. A socket in connected status can be obtained
-/// through a listening socket calling accept. Listening and connected are ultimate statuses. They
-/// will not transfer to other statuses.
+/// SOCK_STREAM or SOCK_SEQPACKET Unix socket. It has three statuses: unconnected, listening and
+/// connected.  When a socket is created, it is in unconnected status.  It will transfer to
+/// listening after listen is called and connected after connect is called. A socket in connected
+/// status can be obtained through a listening socket calling accept. Listening and connected are
+/// ultimate statuses. They will not transfer to other statuses.
+///
+/// The two types only differ in the data transfer: a seqpacket socket preserves the message
+/// boundaries.
 pub struct Stream {
+    socket_type: SocketType,
     inner: SgxMutex<Status>,
+    pass_cred: AtomicBool,
     // Use the internal notifier of RelayNotifier as the notifier of stream socket. It relays the
     // events of the endpoint, too.
     pub(super) notifier: Arc<RelayNotifier>,
 }
 
 impl Stream {
-    pub fn new(flags: FileFlags) -> Self {
+    pub fn new(socket_type: SocketType, flags: FileFlags) -> Self {
+        debug_assert!(socket_type == SocketType::STREAM || socket_type == SocketType::SEQPACKET);
         Self {
+            socket_type,
             inner: SgxMutex::new(Status::Idle(Info::new(
                 flags.contains(FileFlags::SOCK_NONBLOCK),
             ))),
+            pass_cred: AtomicBool::new(false),
             notifier: Arc::new(RelayNotifier::new()),
         }
     }
 
-    pub fn socketpair(flags: FileFlags) -> Result<(Self, Self)> {
+    pub fn socketpair(socket_type: SocketType, flags: FileFlags) -> Result<(Self, Self)> {
         let nonblocking = flags.contains(FileFlags::SOCK_NONBLOCK);
-        let (end_a, end_b) = end_pair(nonblocking)?;
+        let cred = Credentials::current();
+        let (end_a, end_b) = end_pair(socket_type, nonblocking, cred, cred)?;
+        let tid = current!().tid();
+        end_a.set_ancillary(Ancillary { tid });
+        end_b.set_ancillary(Ancillary { tid });
         let notifier_a = Arc::new(RelayNotifier::new());
         let notifier_b = Arc::new(RelayNotifier::new());
         notifier_a.observe_endpoint(&end_a);
         notifier_b.observe_endpoint(&end_b);
 
         let socket_a = Self {
+            socket_type,
             inner: SgxMutex::new(Status::Connected(end_a)),
+            pass_cred: AtomicBool::new(false),
             notifier: notifier_a,
         };
 
         let socket_b = Self {
+            socket_type,
             inner: SgxMutex::new(Status::Connected(end_b)),
+            pass_cred: AtomicBool::new(false),
             notifier: notifier_b,
         };
 
         Ok((socket_a, socket_b))
     }
 
-    pub fn addr(&self) -> Option<Addr> {
+    pub(super) fn nonblocking(&self) -> bool {
+        match &*self.inner() {
+            Status::Idle(info) => info.nonblocking(),
+            Status::Connected(endpoint) => endpoint.nonblocking(),
+            Status::Listening(addr) => ADDRESS_SPACE.get_listener_ref(&addr).unwrap().nonblocking(),
+        }
+    }
+
+    pub(super) fn set_nonblocking(&self, nonblocking: bool) {
+        match &mut *self.inner() {
+            Status::Idle(ref mut info) => info.set_nonblocking(nonblocking),
+            Status::Connected(ref mut endpoint) => endpoint.set_nonblocking(nonblocking),
+            Status::Listening(addr) => ADDRESS_SPACE
+                .get_listener_ref(&addr)
+                .unwrap()
+                .set_nonblocking(nonblocking),
+        }
+    }
+
+    pub(super) fn inner(&self) -> SgxMutexGuard<'_, Status> {
+        self.inner.lock().unwrap()
+    }
+
+    fn connected_endpoint(&self) -> Result<Endpoint> {
+        match &*self.inner() {
+            Status::Connected(endpoint) => Ok(endpoint.clone()),
+            _ => return_errno!(ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn ancillary(&self) -> Option<Ancillary> {
+        match &*self.inner() {
+            Status::Idle(_) => None,
+            Status::Listening(_) => None,
+            Status::Connected(endpoint) => endpoint.ancillary(),
+        }
+    }
+
+    fn peer_ancillary(&self) -> Option<Ancillary> {
+        if let Status::Connected(endpoint) = &*self.inner() {
+            endpoint.peer_ancillary()
+        } else {
+            None
+        }
+    }
+
+    fn set_ancillary(&self, ancillary: Ancillary) {
+        if let Status::Connected(endpoint) = &*self.inner() {
+            endpoint.set_ancillary(ancillary)
+        }
+    }
+}
+
+impl UnixSocket for Stream {
+    fn addr(&self) -> Option<Addr> {
         match &*self.inner() {
             Status::Idle(info) => info.addr().clone(),
             Status::Connected(endpoint) => endpoint.addr(),
@@ -61,7 +130,7 @@ impl Stream {
         }
     }
 
-    pub fn peer_addr(&self) -> Result<Addr> {
+    fn peer_addr(&self) -> Result<Addr> {
         if let Status::Connected(endpoint) = &*self.inner() {
             if let Some(addr) = endpoint.peer_addr() {
                 return Ok(addr);
@@ -70,21 +139,8 @@ impl Stream {
         return_errno!(ENOTCONN, "the socket is not connected");
     }
 
-    pub fn bind(&self, addr: &mut Addr) -> Result<()> {
-        if let Addr::File(inode_num, path) = addr {
-            // create the corresponding file in the fs and fill Addr with its inode
-            let corresponding_inode_num = {
-                let current = current!();
-                let fs = current.fs().read().unwrap();
-                let file_ref = fs.open_file(
-                    path.path_str(),
-                    CreationFlags::O_CREAT.bits(),
-                    FileMode::from_bits(0o777).unwrap(),
-                )?;
-                file_ref.metadata()?.inode
-            };
-            *inode_num = Some(corresponding_inode_num);
-        }
+    fn bind(&self, addr: &mut Addr) -> Result<()> {
+        create_socket_file(addr)?;
 
         match &mut *self.inner() {
             Status::Idle(ref mut info) => {
@@ -110,7 +166,7 @@ impl Stream {
         Ok(())
     }
 
-    pub fn listen(&self, backlog: i32) -> Result<()> {
+    fn listen(&self, backlog: i32) -> Result<()> {
         //TODO: restrict backlog accroding to /proc/sys/net/core/somaxconn
         if backlog < 0 {
             return_errno!(EINVAL, "negative backlog is not supported");
@@ -125,6 +181,7 @@ impl Stream {
                     ADDRESS_SPACE.add_listener(
                         addr,
                         capacity,
+                        self.socket_type,
                         info.nonblocking(),
                         self.notifier.clone(),
                     )?;
@@ -143,8 +200,11 @@ impl Stream {
 
     /// The establishment of the connection is very fast and can be done immediately.
     /// Therefore, the connect function in our implementation will never block.
-    pub fn connect(&self, addr: &Addr) -> Result<()> {
+    fn connect(&self, addr: &Option<Addr>) -> Result<()> {
         debug!("connect to {:?}", addr);
+        let addr = addr
+            .as_ref()
+            .ok_or_else(|| errno!(EINVAL, "invalid address"))?;
 
         let mut inner = self.inner();
         match &*inner {
@@ -156,7 +216,27 @@ impl Stream {
                     }
                 }
 
-                let (end_self, end_incoming) = end_pair(info.nonblocking())?;
+                let listener = match ADDRESS_SPACE.get_listener_ref(addr) {
+                    Some(listener) => listener,
+                    None if ADDRESS_SPACE.get_datagram_ref(addr).is_some() => {
+                        return_errno!(EPROTOTYPE, "the address is bound by a datagram socket");
+                    }
+                    None => {
+                        return_errno!(ECONNREFUSED, "no one's listening on the remote address")
+                    }
+                };
+                if listener.socket_type() != self.socket_type {
+                    return_errno!(EPROTOTYPE, "the socket type of the listener mismatches");
+                }
+
+                // Like Linux, the peer credentials of the connecting socket are the ones of the
+                // listening process
+                let (end_self, end_incoming) = end_pair(
+                    self.socket_type,
+                    info.nonblocking(),
+                    Credentials::current(),
+                    listener.cred(),
+                )?;
                 end_incoming.set_addr(addr);
                 if let Some(self_addr) = self_addr_opt {
                     end_self.set_addr(self_addr);
@@ -187,7 +267,7 @@ impl Stream {
         }
     }
 
-    pub fn accept(&self, flags: FileFlags) -> Result<(Self, Option<Addr>)> {
+    fn accept(&self, flags: FileFlags) -> Result<(Arc<dyn File>, Option<Addr>)> {
         let status = (*self.inner()).clone();
         match status {
             Status::Listening(addr) => {
@@ -203,26 +283,45 @@ impl Stream {
 
                 debug!("accept socket from {:?}", peer_addr);
 
-                Ok((
-                    Self {
-                        inner: SgxMutex::new(Status::Connected(endpoint)),
-                        notifier: notifier,
-                    },
-                    peer_addr,
-                ))
+                let new_socket = Self {
+                    socket_type: self.socket_type,
+                    inner: SgxMutex::new(Status::Connected(endpoint)),
+                    pass_cred: AtomicBool::new(self.pass_cred()),
+                    notifier: notifier,
+                };
+                Ok((Arc::new(new_socket), peer_addr))
             }
             _ => return_errno!(EINVAL, "the socket is not listening"),
         }
     }
 
-    // TODO: handle flags
-    pub fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<Addr>) -> Result<usize> {
+    // TODO: handle flags for stream socket
+    fn sendto(&self, buf: &[u8], flags: SendFlags, addr: &Option<Addr>) -> Result<usize> {
+        if self.socket_type == SocketType::SEQPACKET {
+            let endpoint = self.connected_endpoint()?;
+            let nonblocking = endpoint.nonblocking() || flags.contains(SendFlags::MSG_DONTWAIT);
+            let msg = Message::new(&[buf], None, endpoint.addr())?;
+            return endpoint.send_msg(msg, nonblocking);
+        }
         self.write(buf)
     }
 
-    // TODO: handle flags
-    pub fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<Addr>)> {
-        let data_len = self.read(buf)?;
+    // TODO: handle flags for stream socket
+    fn recvfrom(&self, buf: &mut [u8], flags: RecvFlags) -> Result<(usize, Option<Addr>)> {
+        let data_len = if self.socket_type == SocketType::SEQPACKET {
+            let endpoint = self.connected_endpoint()?;
+            let nonblocking = endpoint.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+            match endpoint.recv_msg(nonblocking, flags.contains(RecvFlags::MSG_PEEK))? {
+                Some(msg) if flags.contains(RecvFlags::MSG_TRUNC) => {
+                    msg.copy_to(&mut [buf]);
+                    msg.len()
+                }
+                Some(msg) => msg.copy_to(&mut [buf]),
+                None => 0,
+            }
+        } else {
+            self.read(buf)?
+        };
         let addr = self.peer_addr().ok();
 
         debug!("recvfrom {:?}", addr);
@@ -230,11 +329,34 @@ impl Stream {
         Ok((data_len, addr))
     }
 
-    pub fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
+    fn sendmsg(&self, msg_hdr: &MsgHdr, flags: SendFlags) -> Result<usize> {
+        if self.socket_type == SocketType::SEQPACKET {
+            // The passed files are carried by the message instead of the data stream
+            let endpoint = self.connected_endpoint()?;
+            let nonblocking = endpoint.nonblocking() || flags.contains(SendFlags::MSG_DONTWAIT);
+            let msg = Message::new(
+                msg_hdr.get_iovs().as_slices(),
+                msg_hdr.get_control(),
+                endpoint.addr(),
+            )?;
+            return endpoint.send_msg(msg, nonblocking);
+        }
+
         if !flags.is_empty() {
             warn!("unsupported flags: {:?}", flags);
         }
 
+        // The control messages are sent along with the data, so the credentials are checked
+        // before sending and the receiver can rely on them
+        if let Some(msg_control) = msg_hdr.get_control() {
+            let mut control = msg_control.to_vec();
+            for cmsg in CMessages::from_bytes(&mut control) {
+                if let CmsgData::ScmCredentials(scm_credentials) = cmsg {
+                    Credentials::from_claimed(scm_credentials.data())?;
+                }
+            }
+        }
+
         let bufs = msg_hdr.get_iovs().as_slices();
         let mut data_len = self.writev(bufs)?;
 
@@ -244,7 +366,20 @@ impl Stream {
         Ok(data_len)
     }
 
-    pub fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
+    fn recvmsg(&self, msg_hdr: &mut MsgHdrMut, flags: RecvFlags) -> Result<usize> {
+        if self.socket_type == SocketType::SEQPACKET {
+            let endpoint = self.connected_endpoint()?;
+            let nonblocking = endpoint.nonblocking() || flags.contains(RecvFlags::MSG_DONTWAIT);
+            return match endpoint.recv_msg(nonblocking, flags.contains(RecvFlags::MSG_PEEK))? {
+                Some(msg) => msg.deliver(msg_hdr, flags, self.pass_cred()),
+                None => {
+                    msg_hdr.set_name_len(0)?;
+                    msg_hdr.set_control_len(0)?;
+                    Ok(0)
+                }
+            };
+        }
+
         if !flags.is_empty() {
             warn!("unsupported flags: {:?}", flags);
         }
@@ -274,14 +409,14 @@ impl Stream {
                         current!().add_file(ipc_file.clone(), false)
                     })
                 }
-                // Unix credentials need not to be handled here
+                // Unix credentials have been checked by the sender
             }
         }
         Ok(data_len)
     }
 
     /// perform shutdown on the socket.
-    pub fn shutdown(&self, how: HowToShut) -> Result<()> {
+    fn shutdown(&self, how: HowToShut) -> Result<()> {
         if let Status::Connected(ref end) = &*self.inner() {
             end.shutdown(how)
         } else {
@@ -289,49 +424,21 @@ impl Stream {
         }
     }
 
-    pub(super) fn nonblocking(&self) -> bool {
+    fn peer_cred(&self) -> Credentials {
         match &*self.inner() {
-            Status::Idle(info) => info.nonblocking(),
-            Status::Connected(endpoint) => endpoint.nonblocking(),
-            Status::Listening(addr) => ADDRESS_SPACE.get_listener_ref(&addr).unwrap().nonblocking(),
+            Status::Idle(_) => Credentials::INVALID,
+            Status::Connected(endpoint) => endpoint.peer_cred(),
+            // Like Linux, a listening socket reports its own credentials
+            Status::Listening(addr) => ADDRESS_SPACE.get_listener_ref(addr).unwrap().cred(),
         }
     }
 
-    pub(super) fn set_nonblocking(&self, nonblocking: bool) {
-        match &mut *self.inner() {
-            Status::Idle(ref mut info) => info.set_nonblocking(nonblocking),
-            Status::Connected(ref mut endpoint) => endpoint.set_nonblocking(nonblocking),
-            Status::Listening(addr) => ADDRESS_SPACE
-                .get_listener_ref(&addr)
-                .unwrap()
-                .set_nonblocking(nonblocking),
-        }
+    fn pass_cred(&self) -> bool {
+        self.pass_cred.load(Ordering::Acquire)
     }
 
-    pub(super) fn inner(&self) -> SgxMutexGuard<'_, Status> {
-        self.inner.lock().unwrap()
-    }
-
-    fn ancillary(&self) -> Option<Ancillary> {
-        match &*self.inner() {
-            Status::Idle(_) => None,
-            Status::Listening(_) => None,
-            Status::Connected(endpoint) => endpoint.ancillary(),
-        }
-    }
-
-    fn peer_ancillary(&self) -> Option<Ancillary> {
-        if let Status::Connected(endpoint) = &*self.inner() {
-            endpoint.peer_ancillary()
-        } else {
-            None
-        }
-    }
-
-    fn set_ancillary(&self, ancillary: Ancillary) {
-        if let Status::Connected(endpoint) = &*self.inner() {
-            endpoint.set_ancillary(ancillary)
-        }
+    fn set_pass_cred(&self, pass_cred: bool) {
+        self.pass_cred.store(pass_cred, Ordering::Release);
     }
 }
 
@@ -410,13 +517,17 @@ impl Info {
 /// The writer end is always non-blocking. The connect function returns
 /// ECONNREFUSED rather than block when the channel is full.
 pub struct Listener {
+    socket_type: SocketType,
     channel: RwLock<Channel<Endpoint>>,
     notifier: Arc<RelayNotifier>,
+    // The credentials of the listening process
+    cred: Credentials,
 }
 
 impl Listener {
     pub(super) fn new(
         capacity: usize,
+        socket_type: SocketType,
         nonblocking: bool,
         notifier: Arc<RelayNotifier>,
     ) -> Result<Self> {
@@ -425,11 +536,21 @@ impl Listener {
         channel.consumer().set_nonblocking(nonblocking);
 
         Ok(Self {
+            socket_type,
             channel: RwLock::new(channel),
             notifier,
+            cred: Credentials::current(),
         })
     }
 
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
+    pub fn cred(&self) -> Credentials {
+        self.cred
+    }
+
     pub fn capacity(&self) -> usize {
         let channel = self.channel.read().unwrap();
         channel.capacity()
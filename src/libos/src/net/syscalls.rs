@@ -9,7 +9,7 @@ use misc::resource_t;
 use process::Process;
 use signal::{sigset_t, SigSet};
 use std::convert::TryFrom;
-use time::{timespec_t, timeval_t};
+use time::{timespec_t, timeval_t, up_time};
 use util::mem_util::from_user;
 
 pub fn do_socket(domain: c_int, socket_type: c_int, protocol: c_int) -> Result<isize> {
@@ -18,9 +18,9 @@ pub fn do_socket(domain: c_int, socket_type: c_int, protocol: c_int) -> Result<i
     let sock_type = SocketType::try_from(socket_type & (!file_flags.bits()))?;
 
     let file_ref: Arc<dyn File> = match sock_domain {
-        AddressFamily::LOCAL => {
-            let unix_socket = unix_socket(sock_type, file_flags, protocol)?;
-            Arc::new(unix_socket)
+        AddressFamily::LOCAL => unix_socket(sock_type, file_flags, protocol)?,
+        AddressFamily::NETLINK if protocol == NETLINK_ROUTE => {
+            netlink_socket(sock_type, file_flags, protocol)?
         }
         _ => {
             let socket = HostSocket::new(sock_domain, sock_type, file_flags, protocol)?;
@@ -43,11 +43,23 @@ pub fn do_bind(fd: c_int, addr: *const libc::sockaddr, addr_len: libc::socklen_t
     if let Ok(socket) = file_ref.as_host_socket() {
         let sock_addr = unsafe { SockAddr::try_from_raw(addr, addr_len)? };
         trace!("bind to addr: {:?}", sock_addr);
-        socket.bind(&sock_addr)?;
+        if is_loopback_bind(socket, &sock_addr) {
+            switch_to_loopback(socket, |loopback_socket| loopback_socket.bind(&sock_addr))?;
+        } else {
+            socket.bind(&sock_addr)?;
+        }
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let sock_addr = unsafe { SockAddr::try_from_raw(addr, addr_len)? };
+        trace!("bind to addr: {:?}", sock_addr);
+        loopback_socket.bind(&sock_addr)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let mut unix_addr = unsafe { UnixAddr::try_from_raw(addr, addr_len)? };
         trace!("bind to addr: {:?}", unix_addr);
         unix_socket.bind(&mut unix_addr)?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let netlink_addr = unsafe { NetlinkAddr::try_from_raw(addr, addr_len)? };
+        trace!("bind to addr: {:?}", netlink_addr);
+        netlink_socket.bind(&netlink_addr)?;
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -59,8 +71,12 @@ pub fn do_listen(fd: c_int, backlog: c_int) -> Result<isize> {
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
         socket.listen(backlog)?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.listen(backlog)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         unix_socket.listen(backlog)?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        return_errno!(EOPNOTSUPP, "netlink socket does not support listen");
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -89,16 +105,24 @@ pub fn do_connect(
             None
         };
 
-        socket.connect(&addr_option)?;
+        match &addr_option {
+            Some(sock_addr) if is_loopback_peer(socket, sock_addr) => {
+                switch_to_loopback(socket, |loopback_socket| {
+                    loopback_socket.connect(&addr_option)
+                })?
+            }
+            _ => socket.connect(&addr_option)?,
+        }
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let addr_option = try_connect_addr_from_raw(addr, addr_len, SockAddr::try_from_raw)?;
+        loopback_socket.connect(&addr_option)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        // TODO: support AF_UNSPEC address for datagram socket use
-        let addr = if addr_set {
-            unsafe { UnixAddr::try_from_raw(addr, addr_len)? }
-        } else {
-            return_errno!(EINVAL, "invalid address");
-        };
-
-        unix_socket.connect(&addr)?;
+        let addr_option = try_connect_addr_from_raw(addr, addr_len, UnixAddr::try_from_raw)?;
+        unix_socket.connect(&addr_option)?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        // The AF_UNSPEC address resets the destination to the kernel
+        let addr_option = try_connect_addr_from_raw(addr, addr_len, NetlinkAddr::try_from_raw)?;
+        netlink_socket.connect(&addr_option)?;
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -106,6 +130,24 @@ pub fn do_connect(
     Ok(0)
 }
 
+/// Parse the address given to connect by `from_raw`. Return `None` if the address is null or an
+/// AF_UNSPEC address, which dissolves the association of a datagram socket.
+fn try_connect_addr_from_raw<T>(
+    addr: *const libc::sockaddr,
+    addr_len: libc::socklen_t,
+    from_raw: unsafe fn(*const libc::sockaddr, libc::socklen_t) -> Result<T>,
+) -> Result<Option<T>> {
+    if addr.is_null() {
+        return Ok(None);
+    }
+    let is_unspec = addr_len as usize >= std::mem::size_of::<libc::sa_family_t>()
+        && unsafe { (*addr).sa_family } == libc::AF_UNSPEC as libc::sa_family_t;
+    if is_unspec {
+        return Ok(None);
+    }
+    Ok(Some(unsafe { from_raw(addr, addr_len)? }))
+}
+
 pub fn do_accept(
     fd: c_int,
     addr: *mut libc::sockaddr,
@@ -135,6 +177,25 @@ pub fn do_accept4(
         let new_file_ref: Arc<dyn File> = Arc::new(new_socket_file);
         let new_fd = current!().add_file(new_file_ref, close_on_spawn);
 
+        if addr_set {
+            if let Some(sock_addr) = sock_addr_option {
+                let mut buf =
+                    unsafe { std::slice::from_raw_parts_mut(addr as *mut u8, *addr_len as usize) };
+                sock_addr.copy_to_slice(&mut buf);
+                unsafe {
+                    *addr_len = sock_addr.len() as u32;
+                }
+            } else {
+                unsafe {
+                    *addr_len = 0;
+                }
+            }
+        }
+        Ok(new_fd as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let (new_file_ref, sock_addr_option) = loopback_socket.accept(file_flags)?;
+        let new_fd = current!().add_file(new_file_ref, close_on_spawn);
+
         if addr_set {
             if let Some(sock_addr) = sock_addr_option {
                 let mut buf =
@@ -151,8 +212,7 @@ pub fn do_accept4(
         }
         Ok(new_fd as isize)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        let (new_socket_file, sock_addr_option) = unix_socket.accept(file_flags)?;
-        let new_file_ref: Arc<dyn File> = Arc::new(new_socket_file);
+        let (new_file_ref, sock_addr_option) = unix_socket.accept(file_flags)?;
         let new_fd = current!().add_file(new_file_ref, close_on_spawn);
 
         if addr_set {
@@ -170,6 +230,8 @@ pub fn do_accept4(
             }
         }
         Ok(new_fd as isize)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        return_errno!(EOPNOTSUPP, "netlink socket does not support accept");
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -182,8 +244,12 @@ pub fn do_shutdown(fd: c_int, how: c_int) -> Result<isize> {
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
         socket.shutdown(how)?;
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        loopback_socket.shutdown(how)?;
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         unix_socket.shutdown(how)?;
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        return_errno!(EOPNOTSUPP, "netlink socket does not support shutdown");
     } else {
         return_errno!(EBADF, "not a host socket")
     }
@@ -211,9 +277,24 @@ pub fn do_setsockopt(
             optval,
             optlen
         ));
+        // The options are carried over if the socket is replaced by a loopback socket
+        socket.record_sockopt(level, optname, copy_sockopt_from_user(optval, optlen)?);
         Ok(ret as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let optval = copy_sockopt_from_user(optval, optlen)?;
+        loopback_socket.sockopts().set(level, optname, optval)?;
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        warn!("setsockopt for unix socket is unimplemented");
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_PASSCRED) => {
+                let val = copy_int_sockopt_from_user(optval, optlen)?;
+                unix_socket.set_pass_cred(val != 0);
+            }
+            _ => warn!("setsockopt for unix socket is unimplemented"),
+        }
+        Ok(0)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        warn!("setsockopt for netlink socket is unimplemented");
         Ok(0)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
@@ -243,14 +324,88 @@ pub fn do_getsockopt(
             optlen
         ));
         Ok(ret as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let val = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_TYPE) => loopback_socket.socket_type() as i32,
+            // The connection of a loopback socket is established immediately without errors
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            _ => loopback_socket.sockopts().get_int(level, optname)?,
+        };
+        copy_int_sockopt_to_user(val, optval, optlen)?;
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
-        warn!("getsockopt for unix socket is unimplemented");
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_PEERCRED) => {
+                // Like Linux, the credentials are truncated to the length of the buffer
+                from_user::check_mut_ptr(optlen)?;
+                let cred = unix_socket.peer_cred();
+                let src = cred.as_bytes();
+                let len = std::cmp::min(unsafe { *optlen } as usize, src.len());
+                from_user::check_mut_array(optval as *mut u8, len)?;
+                unsafe {
+                    std::ptr::copy_nonoverlapping(src.as_ptr(), optval as *mut u8, len);
+                    *optlen = len as u32;
+                }
+            }
+            (libc::SOL_SOCKET, libc::SO_PASSCRED) => {
+                copy_int_sockopt_to_user(unix_socket.pass_cred() as i32, optval, optlen)?;
+            }
+            _ => warn!("getsockopt for unix socket is unimplemented"),
+        }
+        Ok(0)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let val = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_TYPE) => netlink_socket.socket_type() as i32,
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            _ => {
+                warn!("getsockopt for netlink socket is unimplemented");
+                return Ok(0);
+            }
+        };
+        copy_int_sockopt_to_user(val, optval, optlen)?;
         Ok(0)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
 }
 
+/// Copy the value of a socket option from the user.
+fn copy_sockopt_from_user<'a>(optval: *const c_void, optlen: libc::socklen_t) -> Result<&'a [u8]> {
+    if optlen == 0 {
+        return Ok(&[]);
+    }
+    from_user::check_array(optval as *const u8, optlen as usize)?;
+    Ok(unsafe { std::slice::from_raw_parts(optval as *const u8, optlen as usize) })
+}
+
+/// Copy the value of an integer socket option from the user.
+fn copy_int_sockopt_from_user(optval: *const c_void, optlen: libc::socklen_t) -> Result<i32> {
+    if (optlen as usize) < std::mem::size_of::<i32>() {
+        return_errno!(EINVAL, "optlen is too short");
+    }
+    from_user::check_array(optval as *const u8, optlen as usize)?;
+    Ok(unsafe { std::ptr::read_unaligned(optval as *const i32) })
+}
+
+/// Copy the value of an integer socket option to the user.
+fn copy_int_sockopt_to_user(
+    val: i32,
+    optval: *mut c_void,
+    optlen: *mut libc::socklen_t,
+) -> Result<()> {
+    from_user::check_mut_ptr(optlen)?;
+    let len = std::mem::size_of::<i32>();
+    if (unsafe { *optlen } as usize) < len {
+        return_errno!(EINVAL, "optlen is too short");
+    }
+    from_user::check_mut_array(optval as *mut u8, len)?;
+    unsafe {
+        *(optval as *mut i32) = val;
+        *optlen = len as u32;
+    }
+    Ok(())
+}
+
 pub fn do_getpeername(
     fd: c_int,
     addr: *mut libc::sockaddr,
@@ -272,6 +427,16 @@ pub fn do_getpeername(
             addr_len
         ));
         Ok(ret as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let name = loopback_socket.peer_addr()?;
+        let mut dst = unsafe {
+            std::slice::from_raw_parts_mut(addr as *mut _ as *mut u8, *addr_len as usize)
+        };
+        name.copy_to_slice(dst);
+        unsafe {
+            *addr_len = name.len() as u32;
+        }
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let name = unix_socket.peer_addr()?;
         let mut dst = unsafe {
@@ -282,6 +447,16 @@ pub fn do_getpeername(
             *addr_len = name.raw_len() as u32;
         }
         Ok(0)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let name = netlink_socket.peer_addr();
+        let mut dst = unsafe {
+            std::slice::from_raw_parts_mut(addr as *mut _ as *mut u8, *addr_len as usize)
+        };
+        name.copy_to_slice(dst);
+        unsafe {
+            *addr_len = name.raw_len() as u32;
+        }
+        Ok(0)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -312,6 +487,16 @@ pub fn do_getsockname(
             addr_len
         ));
         Ok(ret as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let name = loopback_socket.addr()?;
+        let mut dst = unsafe {
+            std::slice::from_raw_parts_mut(addr as *mut _ as *mut u8, *addr_len as usize)
+        };
+        name.copy_to_slice(dst);
+        unsafe {
+            *addr_len = name.len() as u32;
+        }
+        Ok(0)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let name_opt = unix_socket.addr();
         if let Some(name) = name_opt {
@@ -329,6 +514,16 @@ pub fn do_getsockname(
             }
         }
         Ok(0)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let name = netlink_socket.addr();
+        let mut dst = unsafe {
+            std::slice::from_raw_parts_mut(addr as *mut _ as *mut u8, *addr_len as usize)
+        };
+        name.copy_to_slice(dst);
+        unsafe {
+            *addr_len = name.raw_len() as u32;
+        }
+        Ok(0)
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -368,7 +563,26 @@ pub fn do_sendto(
             None
         };
 
-        socket
+        let bytes_sent = match &addr_option {
+            Some(sock_addr)
+                if socket.socket_type() == SocketType::DGRAM
+                    && is_loopback_peer(socket, sock_addr) =>
+            {
+                switch_to_loopback(socket, |loopback_socket| {
+                    loopback_socket.sendto(buf, send_flags, &addr_option)
+                })?
+            }
+            _ => socket.sendto(buf, send_flags, &addr_option)?,
+        };
+        Ok(bytes_sent as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let addr_option = if addr_set {
+            Some(unsafe { SockAddr::try_from_raw(addr, addr_len)? })
+        } else {
+            None
+        };
+
+        loopback_socket
             .sendto(buf, send_flags, &addr_option)
             .map(|u| u as isize)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
@@ -381,6 +595,16 @@ pub fn do_sendto(
         unix_socket
             .sendto(buf, send_flags, &addr_option)
             .map(|u| u as isize)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let addr_option = if addr_set {
+            Some(unsafe { NetlinkAddr::try_from_raw(addr, addr_len)? })
+        } else {
+            None
+        };
+
+        netlink_socket
+            .sendto(buf, send_flags, &addr_option)
+            .map(|u| u as isize)
     } else {
         return_errno!(EBADF, "unsupported file type");
     }
@@ -430,6 +654,23 @@ pub fn do_recvfrom(
             }
         }
         Ok(data_len as isize)
+    } else if let Ok(loopback_socket) = file_ref.as_loopback_socket() {
+        let (data_len, sock_addr_option) = loopback_socket.recvfrom(buf, recv_flags)?;
+        if addr_set {
+            if let Some(sock_addr) = sock_addr_option {
+                let mut buf =
+                    unsafe { std::slice::from_raw_parts_mut(addr as *mut u8, *addr_len as usize) };
+                sock_addr.copy_to_slice(&mut buf);
+                unsafe {
+                    *addr_len = sock_addr.len() as u32;
+                }
+            } else {
+                unsafe {
+                    *addr_len = 0;
+                }
+            }
+        }
+        Ok(data_len as isize)
     } else if let Ok(unix_socket) = file_ref.as_unix_socket() {
         let (data_len, sock_addr_option) = unix_socket.recvfrom(buf, recv_flags)?;
         if addr_set {
@@ -447,6 +688,23 @@ pub fn do_recvfrom(
             }
         }
         Ok(data_len as isize)
+    } else if let Ok(netlink_socket) = file_ref.as_netlink_socket() {
+        let (data_len, sock_addr_option) = netlink_socket.recvfrom(buf, recv_flags)?;
+        if addr_set {
+            if let Some(sock_addr) = sock_addr_option {
+                let mut buf =
+                    unsafe { std::slice::from_raw_parts_mut(addr as *mut u8, *addr_len as usize) };
+                sock_addr.copy_to_slice(&mut buf);
+                unsafe {
+                    *addr_len = sock_addr.raw_len() as u32;
+                }
+            } else {
+                unsafe {
+                    *addr_len = 0;
+                }
+            }
+        }
+        Ok(data_len as isize)
     } else {
         return_errno!(ENOTSOCK, "not a socket");
     }
@@ -473,8 +731,8 @@ pub fn do_socketpair(
 
         let current = current!();
         let mut files = current.files().lock().unwrap();
-        sock_pair[0] = files.put(Arc::new(client_socket), close_on_spawn);
-        sock_pair[1] = files.put(Arc::new(server_socket), close_on_spawn);
+        sock_pair[0] = files.put(client_socket, close_on_spawn);
+        sock_pair[1] = files.put(server_socket, close_on_spawn);
 
         debug!("socketpair: ({}, {})", sock_pair[0], sock_pair[1]);
         Ok(0)
@@ -503,6 +761,25 @@ pub fn do_sendmsg(fd: c_int, msg_ptr: *const msghdr, flags_c: c_int) -> Result<i
 
     let file_ref = current!().file(fd as FileDesc)?;
     if let Ok(socket) = file_ref.as_host_socket() {
+        let addr_option = match msg_hdr.get_name() {
+            Some(name) if socket.socket_type() == SocketType::DGRAM => Some(unsafe {
+                SockAddr::try_from_raw(
+                    name.as_ptr() as *const libc::sockaddr,
+                    name.len() as libc::socklen_t,
+                )?
+            }),
+            _ => None,
+        };
+        let bytes_sent = match &addr_option {
+            Some(sock_addr) if is_loopback_peer(socket, sock_addr) => {
+                switch_to_loopback(socket, |loopback_socket| {
+                    loopback_socket.sendmsg(&msg_hdr, flags)
+                })?
+            }
+            _ => socket.sendmsg(&msg_hdr, flags)?,
+        };
+        Ok(bytes_sent as isize)
+    } else if let Ok(socket) = file_ref.as_loopback_socket() {
         socket
             .sendmsg(&msg_hdr, flags)
             .map(|bytes_sent| bytes_sent as isize)
@@ -510,6 +787,10 @@ pub fn do_sendmsg(fd: c_int, msg_ptr: *const msghdr, flags_c: c_int) -> Result<i
         socket
             .sendmsg(&msg_hdr, flags)
             .map(|bytes_sent| bytes_sent as isize)
+    } else if let Ok(socket) = file_ref.as_netlink_socket() {
+        socket
+            .sendmsg(&msg_hdr, flags)
+            .map(|bytes_sent| bytes_sent as isize)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -538,10 +819,18 @@ pub fn do_recvmsg(fd: c_int, msg_mut_ptr: *mut msghdr_mut, flags_c: c_int) -> Re
         socket
             .recvmsg(&mut msg_hdr_mut, flags)
             .map(|bytes_recvd| bytes_recvd as isize)
+    } else if let Ok(socket) = file_ref.as_loopback_socket() {
+        socket
+            .recvmsg(&mut msg_hdr_mut, flags)
+            .map(|bytes_recvd| bytes_recvd as isize)
     } else if let Ok(socket) = file_ref.as_unix_socket() {
         socket
             .recvmsg(&mut msg_hdr_mut, flags)
             .map(|bytes_recvd| bytes_recvd as isize)
+    } else if let Ok(socket) = file_ref.as_netlink_socket() {
+        socket
+            .recvmsg(&mut msg_hdr_mut, flags)
+            .map(|bytes_recvd| bytes_recvd as isize)
     } else {
         return_errno!(ENOTSOCK, "not a socket")
     }
@@ -564,7 +853,10 @@ pub fn do_sendmmsg(
     let flags = SendFlags::from_bits_truncate(flags_c);
     let file_ref = current!().file(fd as FileDesc)?;
 
-    if let Ok(socket) = file_ref.as_host_socket() {
+    if file_ref.as_host_socket().is_ok()
+        || file_ref.as_loopback_socket().is_ok()
+        || file_ref.as_netlink_socket().is_ok()
+    {
         let mut send_count = 0;
         for mmsg in (msgvec) {
             if !mmsg.msg_hdr.check_member_ptrs().is_ok() {
@@ -579,8 +871,14 @@ pub fn do_sendmmsg(
                 }
             };
 
-            if socket
-                .sendmsg(&msg, flags)
+            let ret = if let Ok(socket) = file_ref.as_host_socket() {
+                socket.sendmsg(&msg, flags)
+            } else if let Ok(socket) = file_ref.as_loopback_socket() {
+                socket.sendmsg(&msg, flags)
+            } else {
+                file_ref.as_netlink_socket()?.sendmsg(&msg, flags)
+            };
+            if ret
                 .map(|bytes_sent| {
                     mmsg.msg_len = bytes_sent as u32;
                     mmsg.msg_len
@@ -601,6 +899,127 @@ pub fn do_sendmmsg(
     }
 }
 
+/// The maximum number of the messages received by a recvmmsg call
+const UIO_MAXIOV: usize = 1024;
+
+pub fn do_recvmmsg(
+    fd: c_int,
+    msgvec_ptr: *mut mmsghdr_mut,
+    vlen: c_uint,
+    flags_c: c_int,
+    timeout_ptr: *mut timespec_t,
+) -> Result<isize> {
+    debug!(
+        "recvmmsg: fd: {}, msg: {:?}, vlen: {}, flags: 0x{:x}, timeout: {:?}",
+        fd, msgvec_ptr, vlen, flags_c, timeout_ptr
+    );
+
+    // Like Linux, the number of the messages is silently capped
+    let vlen = std::cmp::min(vlen as usize, UIO_MAXIOV);
+    if vlen == 0 {
+        return Ok(0);
+    }
+    from_user::check_mut_array(msgvec_ptr, vlen)?;
+    let mut timeout = if timeout_ptr.is_null() {
+        None
+    } else {
+        from_user::check_mut_ptr(timeout_ptr)?;
+        Some(timespec_t::from_raw_ptr(timeout_ptr)?.as_duration())
+    };
+
+    let msgvec = unsafe { std::slice::from_raw_parts_mut(msgvec_ptr, vlen) };
+    let flags = RecvFlags::from_bits_truncate(flags_c);
+    let file_ref = current!().file(fd as FileDesc)?;
+
+    let recv_count = if let Ok(socket) = file_ref.as_host_socket() {
+        // All the messages are received from the host with a single OCall
+        let bytes_recvd = {
+            let mut msgs = Vec::with_capacity(vlen);
+            for mmsg in msgvec.iter_mut() {
+                mmsg.msg_hdr.check_member_ptrs()?;
+                msgs.push(unsafe { MsgHdrMut::from_c(&mut mmsg.msg_hdr) }?);
+            }
+            socket.recvmmsg(&mut msgs, flags, timeout.as_mut())?
+        };
+        for (mmsg, len) in msgvec.iter_mut().zip(bytes_recvd.iter()) {
+            mmsg.msg_len = *len as c_uint;
+        }
+        bytes_recvd.len()
+    } else if file_ref.as_unix_socket().is_ok()
+        || file_ref.as_loopback_socket().is_ok()
+        || file_ref.as_netlink_socket().is_ok()
+    {
+        do_recvmmsg_one_by_one(&file_ref, msgvec, flags, timeout.as_mut())?
+    } else {
+        return_errno!(ENOTSOCK, "not a socket");
+    };
+
+    // Like Linux, the remaining time is written back
+    if let Some(timeout) = timeout {
+        unsafe {
+            *timeout_ptr = timespec_t::from(timeout);
+        }
+    }
+    Ok(recv_count as isize)
+}
+
+/// Receive the messages by calling recvmsg for each of them.
+///
+/// Like Linux, the timeout is only checked after a message is received, and an error after
+/// receiving some messages is not reported.
+fn do_recvmmsg_one_by_one(
+    file_ref: &FileRef,
+    msgvec: &mut [mmsghdr_mut],
+    flags: RecvFlags,
+    timeout: Option<&mut Duration>,
+) -> Result<usize> {
+    let wait_for_one = flags.contains(RecvFlags::MSG_WAITFORONE);
+    let mut flags = flags - RecvFlags::MSG_WAITFORONE;
+    let start_time = up_time::get().unwrap();
+    let is_timeout = |timeout: Option<&Duration>| {
+        timeout.map_or(false, |timeout| {
+            up_time::get().unwrap() - start_time >= *timeout
+        })
+    };
+
+    let mut recv_count = 0;
+    for mmsg in msgvec.iter_mut() {
+        let res = mmsg.msg_hdr.check_member_ptrs().and_then(|_| {
+            let mut msg = unsafe { MsgHdrMut::from_c(&mut mmsg.msg_hdr) }?;
+            if let Ok(socket) = file_ref.as_unix_socket() {
+                socket.recvmsg(&mut msg, flags)
+            } else if let Ok(socket) = file_ref.as_loopback_socket() {
+                socket.recvmsg(&mut msg, flags)
+            } else {
+                file_ref.as_netlink_socket()?.recvmsg(&mut msg, flags)
+            }
+        });
+        match res {
+            Ok(bytes_recvd) => mmsg.msg_len = bytes_recvd as c_uint,
+            Err(e) if recv_count > 0 => {
+                debug!("recvmmsg stops after {} messages: {:?}", recv_count, e);
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+        recv_count += 1;
+
+        // Only the first message is waited for
+        if wait_for_one {
+            flags |= RecvFlags::MSG_DONTWAIT;
+        }
+        if is_timeout(timeout.as_deref()) {
+            break;
+        }
+    }
+
+    if let Some(timeout) = timeout {
+        let elapsed = up_time::get().unwrap() - start_time;
+        *timeout = timeout.saturating_sub(elapsed);
+    }
+    Ok(recv_count)
+}
+
 #[allow(non_camel_case_types)]
 trait c_msghdr_ext {
     fn check_member_ptrs(&self) -> Result<()>;
@@ -668,6 +1087,84 @@ pub fn do_select(
     writefds: *mut libc::fd_set,
     exceptfds: *mut libc::fd_set,
     timeout: *mut timeval_t,
+) -> Result<isize> {
+    let mut timeout_c = if !timeout.is_null() {
+        from_user::check_mut_ptr(timeout)?;
+        let timeval = unsafe { &mut *timeout };
+        timeval.validate()?;
+        Some(timeval)
+    } else {
+        None
+    };
+    let mut timeout = timeout_c.as_ref().map(|timeout_c| timeout_c.as_duration());
+
+    let ret = do_select_common(nfds, readfds, writefds, exceptfds, timeout.as_mut(), None);
+
+    if let Some(timeout_c) = timeout_c {
+        *timeout_c = timeout.unwrap().into();
+    }
+
+    ret
+}
+
+/// The sixth argument of pselect6, which packs the signal mask and its size.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct sigset_argpack_t {
+    ss: *const sigset_t,
+    ss_len: size_t,
+}
+
+pub fn do_pselect6(
+    nfds: c_int,
+    readfds: *mut libc::fd_set,
+    writefds: *mut libc::fd_set,
+    exceptfds: *mut libc::fd_set,
+    timeout: *mut timespec_t,
+    sig_data: *const sigset_argpack_t,
+) -> Result<isize> {
+    let mut timeout_c = if !timeout.is_null() {
+        from_user::check_mut_ptr(timeout)?;
+        let timespec = unsafe { &mut *timeout };
+        timespec.validate()?;
+        Some(timespec)
+    } else {
+        None
+    };
+    let mut timeout = timeout_c.as_ref().map(|timeout_c| timeout_c.as_duration());
+
+    let sigmask = if !sig_data.is_null() {
+        from_user::check_ptr(sig_data)?;
+        let sig_data = unsafe { &*sig_data };
+        copy_sigmask_from_user(sig_data.ss, sig_data.ss_len)?
+    } else {
+        None
+    };
+
+    let ret = do_select_common(
+        nfds,
+        readfds,
+        writefds,
+        exceptfds,
+        timeout.as_mut(),
+        sigmask,
+    );
+
+    // Like Linux, the remaining time is written back
+    if let Some(timeout_c) = timeout_c {
+        *timeout_c = timeout.unwrap().into();
+    }
+
+    ret
+}
+
+fn do_select_common(
+    nfds: c_int,
+    readfds: *mut libc::fd_set,
+    writefds: *mut libc::fd_set,
+    exceptfds: *mut libc::fd_set,
+    timeout: Option<&mut Duration>,
+    sigmask: Option<SigSet>,
 ) -> Result<isize> {
     let nfds = {
         let soft_rlimit_nofile = current!()
@@ -685,16 +1182,6 @@ pub fn do_select(
         nfds as FileDesc
     };
 
-    let mut timeout_c = if !timeout.is_null() {
-        from_user::check_ptr(timeout)?;
-        let timeval = unsafe { &mut *timeout };
-        timeval.validate()?;
-        Some(timeval)
-    } else {
-        None
-    };
-    let mut timeout = timeout_c.as_ref().map(|timeout_c| timeout_c.as_duration());
-
     let readfds = if !readfds.is_null() {
         from_user::check_mut_ptr(readfds)?;
         Some(unsafe { &mut *readfds })
@@ -714,13 +1201,26 @@ pub fn do_select(
         None
     };
 
-    let ret = io_multiplexing::do_select(nfds, readfds, writefds, exceptfds, timeout.as_mut());
-
-    if let Some(timeout_c) = timeout_c {
-        *timeout_c = timeout.unwrap().into();
+    let do_select = || io_multiplexing::do_select(nfds, readfds, writefds, exceptfds, timeout);
+    match sigmask {
+        Some(sigmask) => signal::do_with_sig_mask(sigmask, do_select),
+        None => do_select(),
     }
+}
 
-    ret
+/// Copy the signal mask given to pselect6, ppoll, epoll_pwait or io_uring_enter from the user.
+pub fn copy_sigmask_from_user(
+    sigmask: *const sigset_t,
+    sigset_size: usize,
+) -> Result<Option<SigSet>> {
+    if sigmask.is_null() {
+        return Ok(None);
+    }
+    if sigset_size != std::mem::size_of::<sigset_t>() {
+        return_errno!(EINVAL, "unexpected sigset size");
+    }
+    from_user::check_ptr(sigmask)?;
+    Ok(Some(SigSet::from_c(unsafe { *sigmask })))
 }
 
 pub fn do_ppoll(
@@ -728,18 +1228,18 @@ pub fn do_ppoll(
     nfds: libc::nfds_t,
     timeout_ts: *const timespec_t,
     sigmask: *const sigset_t,
+    sigset_size: size_t,
 ) -> Result<isize> {
     let mut timeout = if timeout_ts.is_null() {
         None
     } else {
         from_user::check_ptr(timeout_ts)?;
         let timeout_ts = unsafe { &*timeout_ts };
+        timeout_ts.validate()?;
         Some(timeout_ts.as_duration())
     };
-    if !sigmask.is_null() {
-        warn!("ppoll sigmask is not supported!");
-    }
-    do_poll_common(fds, nfds, timeout.as_mut(), None)
+    let sigmask = copy_sigmask_from_user(sigmask, sigset_size)?;
+    do_poll_common(fds, nfds, timeout.as_mut(), sigmask)
 }
 
 pub fn do_poll(fds: *mut libc::pollfd, nfds: libc::nfds_t, timeout_ms: c_int) -> Result<isize> {
@@ -779,7 +1279,12 @@ fn do_poll_common(
         .map(|raw| PollFd::from_raw(raw))
         .collect();
 
-    let count = io_multiplexing::do_poll_new(&poll_fds, timeout)?;
+    let count = match sigmask {
+        Some(sigmask) => {
+            signal::do_with_sig_mask(sigmask, || io_multiplexing::do_poll(&poll_fds, timeout))?
+        }
+        None => io_multiplexing::do_poll(&poll_fds, timeout)?,
+    };
 
     for (raw_poll_fd, poll_fd) in raw_poll_fds.iter_mut().zip(poll_fds.iter()) {
         raw_poll_fd.revents = poll_fd.revents().get().to_raw() as i16;
@@ -898,12 +1403,13 @@ pub fn do_epoll_pwait(
     events: *mut libc::epoll_event,
     maxevents: c_int,
     timeout: c_int,
-    sigmask: *const usize, //TODO:add sigset_t
+    sigmask: *const sigset_t,
+    sigset_size: size_t,
 ) -> Result<isize> {
-    if !sigmask.is_null() {
-        warn!("epoll_pwait cannot handle signal mask, yet");
-    } else {
-        debug!("epoll_wait");
+    match copy_sigmask_from_user(sigmask, sigset_size)? {
+        Some(sigmask) => {
+            signal::do_with_sig_mask(sigmask, || do_epoll_wait(epfd, events, maxevents, timeout))
+        }
+        None => do_epoll_wait(epfd, events, maxevents, timeout),
     }
-    do_epoll_wait(epfd, events, maxevents, timeout)
 }
@@ -0,0 +1,618 @@
+//! Core dump generation.
+//!
+//! When a process is killed by a signal whose default action is to dump core (e.g.,
+//! SIGSEGV or SIGABRT), an ELF core file is written to the directory given by
+//! `process.core_dump.dir` in Occlum.json, named `core.<pid>`. The core file can be
+//! loaded by GDB together with the executable for post-mortem debugging.
+//!
+//! The core file consists of
+//! * a PT_NOTE segment with the NT_PRSTATUS, NT_PRPSINFO, NT_AUXV and NT_FPREGSET
+//! notes of the thread that receives the signal, followed by the NT_PRSTATUS and
+//! NT_FPREGSET notes of each other thread;
+//! * a PT_LOAD segment for each VMA of the process, which contains the memory content
+//! of the VMA if it is readable and committed.
+//!
+//! With EDMM, the memory that is never accessed may not be committed, and reading it
+//! would commit it. So the content of the memory that is not known to be committed is
+//! not dumped, except for the stacks in use, which are above the stack pointers of
+//! the threads. If there are too many VMAs for the program header count of the ELF
+//! header, the count is given by the section header 0 like Linux.
+//!
+//! The file is truncated at RLIMIT_CORE, so the memory contents beyond the limit are
+//! missing, like on Linux.
+//!
+//! The other threads of the process are stopped before the memory is dumped, so that
+//! they don't change the memory being dumped. The registers of a thread can only be
+//! accessed by the thread itself, so each thread saves its notes when it is stopped.
+//! A thread that is blocked in the LibOS and is not stopped in time is not dumped.
+//!
+//! As the core file contains the whole memory of the process, which may include
+//! secrets, core dumps are disabled in release enclaves unless
+//! `process.core_dump.enable_in_release` is set.
+
+use std::mem;
+use std::time::Duration;
+
+use goblin::elf::header::{
+    EI_CLASS, EI_DATA, EI_VERSION, ELFCLASS64, ELFDATA2LSB, ELFMAG, EM_X86_64, ET_CORE, EV_CURRENT,
+};
+use goblin::elf::program_header::{PF_R, PF_W, PF_X, PT_LOAD, PT_NOTE};
+use goblin::elf::section_header::SHT_NULL;
+
+use super::untrusted_event::set_event;
+use super::{pid_t, ProcessRef, TermStatus, ThreadRef, ThreadStatus};
+use crate::config::{ConfigCoreDump, LIBOS_CONFIG};
+use crate::events::{Waiter, WaiterQueue};
+use crate::fs::{AccessMode, CreationFlags, FileMode, FsView};
+use crate::interrupt::broadcast_interrupts;
+use crate::misc::resource_t;
+use crate::prelude::*;
+use crate::signal::Signal;
+use crate::syscall::{CpuContext, FpRegs};
+use crate::util::sgx::allow_debug;
+use crate::vm::{VMPerms, VMRange, PAGE_SIZE};
+
+const ELF_HDR_SIZE: usize = 64;
+const PROGRAM_HDR_SIZE: usize = 56;
+const SECTION_HDR_SIZE: usize = 64;
+
+// The program header count of the ELF header when the real count is given by the
+// section header 0
+const PN_XNUM: usize = 0xffff;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_FPREGSET: u32 = 2;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+
+// The sizes of struct elf_prstatus and struct elf_prpsinfo on x86-64
+const PRSTATUS_SIZE: usize = 336;
+const PRPSINFO_SIZE: usize = 136;
+
+// The values of the segment registers of 64-bit user code on Linux
+const USER_CS: u64 = 0x33;
+const USER_SS: u64 = 0x2b;
+
+// The time to wait for the other threads to be stopped
+const STOP_THREADS_TIMEOUT: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    // The core dumps in progress, for which the stopped threads save their notes.
+    // K: pid, V: the core dump in progress
+    static ref CORE_DUMPS: SgxMutex<HashMap<pid_t, Arc<CoreDump>>> = SgxMutex::new(HashMap::new());
+}
+
+/// The core dump in progress of a process.
+struct CoreDump {
+    siginfo: CoreSigInfo,
+    // The notes of the stopped threads
+    thread_notes: SgxMutex<Vec<ThreadNotes>>,
+    // The thread writing the core file, which waits for the other threads to be stopped
+    waiters: WaiterQueue,
+}
+
+/// The NT_PRSTATUS and NT_FPREGSET notes of a stopped thread.
+struct ThreadNotes {
+    tid: pid_t,
+    notes: Vec<u8>,
+    // The stack of the thread is in use above the stack pointer
+    stack_pointer: usize,
+}
+
+/// The signal that causes the core dump, which is recorded in NT_PRSTATUS.
+#[derive(Clone, Copy, Debug)]
+struct CoreSigInfo {
+    signo: u32,
+    code: u32,
+    errno: u32,
+}
+
+impl CoreSigInfo {
+    fn new(signal: &dyn Signal) -> Self {
+        let info = signal.to_info();
+        Self {
+            signo: info.si_signo as u32,
+            code: info.si_code as u32,
+            errno: info.si_errno as u32,
+        }
+    }
+}
+
+/// Force the process killed by the signal to exit, after writing its core file.
+///
+/// No core file is generated if core dumps are not enabled in the config, or RLIMIT_CORE
+/// is zero. The process is killed by the signal without a core dump in this case, or if
+/// the core file can't be written.
+pub fn dump_core_and_exit(
+    signal: &dyn Signal,
+    thread: &ThreadRef,
+    process: &ProcessRef,
+    cpu_context: &CpuContext,
+) {
+    let config = match LIBOS_CONFIG.process.core_dump.as_ref() {
+        Some(config) if config.enable_in_release || allow_debug() => config,
+        _ => {
+            process.force_exit(TermStatus::Killed(signal.num()));
+            return;
+        }
+    };
+    let core_limit = thread
+        .rlimits()
+        .lock()
+        .unwrap()
+        .get(resource_t::RLIMIT_CORE)
+        .get_cur();
+    if core_limit == 0 {
+        process.force_exit(TermStatus::Killed(signal.num()));
+        return;
+    }
+
+    let pid = process.pid();
+    let core_dump = {
+        let mut core_dumps = CORE_DUMPS.lock().unwrap();
+        // Another thread is dumping the process, which stops this thread as well
+        if core_dumps.contains_key(&pid) {
+            return;
+        }
+        let core_dump = Arc::new(CoreDump {
+            siginfo: CoreSigInfo::new(signal),
+            thread_notes: SgxMutex::new(Vec::new()),
+            waiters: WaiterQueue::new(),
+        });
+        core_dumps.insert(pid, core_dump.clone());
+        core_dump
+    };
+
+    // Stop the other threads so that they don't change the memory while it is dumped
+    let other_threads: Vec<ThreadRef> = process
+        .threads()
+        .into_iter()
+        .filter(|other| other.tid() != thread.tid())
+        .collect();
+    other_threads.iter().for_each(|other| other.force_stop());
+    let _ = broadcast_interrupts();
+    wait_for_stopped_threads(&core_dump, &other_threads);
+
+    let core_limit = core_limit.min(usize::max_value() as u64) as usize;
+    let res = write_core_file(config, core_limit, &core_dump, thread, process, cpu_context);
+    CORE_DUMPS.lock().unwrap().remove(&pid);
+    let term_status = match res {
+        Ok(()) => TermStatus::Dumped(signal.num()),
+        Err(e) => {
+            warn!("failed to dump core: {:?}", e);
+            TermStatus::Killed(signal.num())
+        }
+    };
+    process.force_exit(term_status);
+
+    // The stopped threads exit once they are resumed since the process has been forced
+    // to exit
+    other_threads.iter().for_each(|other| {
+        other.resume();
+        let thread_ptr = other.raw_ptr();
+        if thread_ptr != 0 {
+            set_event(thread_ptr as *const c_void);
+        }
+    });
+}
+
+/// Save the notes of the current thread for the core dump of its process, if there is
+/// one in progress. This is called when the thread is stopped.
+pub fn save_notes_for_core_dump(thread: &ThreadRef, cpu_context: &CpuContext) {
+    let process = thread.process();
+    let core_dump = match CORE_DUMPS.lock().unwrap().get(&process.pid()) {
+        Some(core_dump) => core_dump.clone(),
+        None => return,
+    };
+
+    let mut notes = Vec::new();
+    let prstatus = prstatus(&core_dump.siginfo, thread, process, cpu_context);
+    push_note(&mut notes, NT_PRSTATUS, &prstatus);
+    let fpregs = fpregs(cpu_context);
+    push_note(&mut notes, NT_FPREGSET, &fpregs);
+    core_dump.thread_notes.lock().unwrap().push(ThreadNotes {
+        tid: thread.tid(),
+        notes,
+        stack_pointer: cpu_context.rsp as usize,
+    });
+    core_dump.waiters.dequeue_and_wake_all();
+}
+
+// Wait until the threads are stopped or exit, or the timeout expires
+fn wait_for_stopped_threads(core_dump: &CoreDump, threads: &[ThreadRef]) {
+    let waiter = Waiter::new();
+    let mut remaining = STOP_THREADS_TIMEOUT;
+    loop {
+        // Enqueue the waiter before checking the threads so that no wakeup is missed
+        core_dump.waiters.reset_and_enqueue(&waiter);
+        let all_stopped = {
+            let thread_notes = core_dump.thread_notes.lock().unwrap();
+            threads.iter().all(|thread| {
+                thread.status() == ThreadStatus::Exited
+                    || thread_notes
+                        .iter()
+                        .any(|thread_notes| thread_notes.tid == thread.tid())
+            })
+        };
+        if all_stopped {
+            return;
+        }
+        if let Err(e) = waiter.wait_mut(Some(&mut remaining)) {
+            if e.errno() == ETIMEDOUT {
+                warn!("some threads are not stopped in time, which are not dumped");
+                return;
+            }
+        }
+    }
+}
+
+fn write_core_file(
+    config: &ConfigCoreDump,
+    core_limit: usize,
+    core_dump: &CoreDump,
+    thread: &ThreadRef,
+    process: &ProcessRef,
+    cpu_context: &CpuContext,
+) -> Result<()> {
+    let vm = thread.vm();
+    // Collect the VMAs first to avoid holding the locks of the VM while writing the file
+    let mut vmas: Vec<(VMRange, VMPerms, bool)> = Vec::new();
+    vm.for_each_vma(process.pid(), |vma| {
+        vmas.push((*vma.range(), vma.perms(), vm.is_committed(vma)));
+    });
+    vmas.sort_by_key(|(range, _, _)| range.start());
+
+    let thread_notes = core_dump.thread_notes.lock().unwrap();
+    let stack_pointers: Vec<usize> = std::iter::once(cpu_context.rsp as usize)
+        .chain(thread_notes.iter().map(|other| other.stack_pointer))
+        .collect();
+    // A segment is dumped if it is readable and committed. The stack in use is committed,
+    // which is split from the rest of its VMA.
+    let mut segments: Vec<(VMRange, VMPerms, bool)> = Vec::with_capacity(vmas.len());
+    for (range, perms, committed) in vmas {
+        if !perms.can_read() || committed {
+            segments.push((range, perms, perms.can_read()));
+            continue;
+        }
+        let stack_pointer = stack_pointers
+            .iter()
+            .filter(|stack_pointer| range.contains(**stack_pointer))
+            .min();
+        match stack_pointer {
+            Some(stack_pointer) => {
+                let stack_start = align_down(*stack_pointer, PAGE_SIZE);
+                if stack_start > range.start() {
+                    let unused_range = VMRange::new(range.start(), stack_start)?;
+                    segments.push((unused_range, perms, false));
+                }
+                let stack_range = VMRange::new(stack_start, range.end())?;
+                segments.push((stack_range, perms, true));
+            }
+            None => segments.push((range, perms, false)),
+        }
+    }
+
+    // The notes of the thread that receives the signal come first, which is the
+    // current thread in GDB
+    let notes = {
+        let mut notes = Vec::new();
+        let prstatus = prstatus(&core_dump.siginfo, thread, process, cpu_context);
+        push_note(&mut notes, NT_PRSTATUS, &prstatus);
+        let prpsinfo = prpsinfo(process);
+        push_note(&mut notes, NT_PRPSINFO, &prpsinfo);
+        let auxv = auxv(vm.get_auxv());
+        push_note(&mut notes, NT_AUXV, &auxv);
+        let fpregs = fpregs(cpu_context);
+        push_note(&mut notes, NT_FPREGSET, &fpregs);
+        for other in thread_notes.iter() {
+            notes.extend_from_slice(&other.notes);
+        }
+        notes
+    };
+    drop(thread_notes);
+
+    // The layout of the core file: ELF header, program headers, the section header 0 if
+    // there are too many program headers, notes and then the page-aligned memory
+    // contents of the segments.
+    let num_phdrs = 1 + segments.len();
+    let phdrs_end = ELF_HDR_SIZE + num_phdrs * PROGRAM_HDR_SIZE;
+    let notes_offset = if num_phdrs >= PN_XNUM {
+        phdrs_end + SECTION_HDR_SIZE
+    } else {
+        phdrs_end
+    };
+    let mut headers = Vec::with_capacity(notes_offset);
+    push_elf_header(&mut headers, num_phdrs, phdrs_end);
+    push_program_header(&mut headers, PT_NOTE, 0, notes_offset, 0, notes.len(), 0);
+    let mut data_offset = align_up(notes_offset + notes.len(), PAGE_SIZE);
+    let mut dumped_segments = Vec::with_capacity(segments.len());
+    for (range, perms, dumped) in segments.iter() {
+        let file_size = if *dumped { range.size() } else { 0 };
+        push_program_header(
+            &mut headers,
+            PT_LOAD,
+            segment_flags(perms),
+            data_offset,
+            range.start(),
+            file_size,
+            range.size(),
+        );
+        if file_size > 0 {
+            dumped_segments.push((data_offset, range));
+        }
+        data_offset += file_size;
+    }
+    if num_phdrs >= PN_XNUM {
+        push_extnum_section_header(&mut headers, num_phdrs);
+    }
+    debug_assert!(headers.len() == notes_offset);
+
+    let path = format!("{}/core.{}", config.dir, process.pid());
+    let core_file = CoreFile {
+        file: FsView::new().open_file(
+            &path,
+            AccessMode::O_WRONLY as u32
+                | CreationFlags::O_CREAT.bits()
+                | CreationFlags::O_TRUNC.bits(),
+            FileMode::from_bits(0o600).unwrap(),
+        )?,
+        limit: core_limit,
+    };
+    core_file.write_at(0, &headers)?;
+    core_file.write_at(notes_offset, &notes)?;
+    for (offset, range) in dumped_segments {
+        let buf = unsafe { std::slice::from_raw_parts(range.start() as *const u8, range.size()) };
+        core_file.write_at(offset, buf)?;
+    }
+
+    info!("Core dumped to {}", path);
+    Ok(())
+}
+
+/// The core file, which is truncated at RLIMIT_CORE.
+struct CoreFile {
+    file: FileRef,
+    limit: usize,
+}
+
+impl CoreFile {
+    /// Write the buffer at the offset, where the part beyond the limit is dropped.
+    fn write_at(&self, mut offset: usize, buf: &[u8]) -> Result<()> {
+        if offset >= self.limit {
+            return Ok(());
+        }
+        let mut buf = &buf[..buf.len().min(self.limit - offset)];
+        while !buf.is_empty() {
+            let len = self.file.write_at(offset, buf)?;
+            if len == 0 {
+                return_errno!(EIO, "failed to write the core file");
+            }
+            offset += len;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+}
+
+// If there are too many program headers, the section header 0 is at `shdr_offset`,
+// which gives the real count of the program headers.
+fn push_elf_header(buf: &mut Vec<u8>, num_phdrs: usize, shdr_offset: usize) {
+    let has_extnum = num_phdrs >= PN_XNUM;
+    let mut ident = [0_u8; 16];
+    ident[..ELFMAG.len()].copy_from_slice(ELFMAG);
+    ident[EI_CLASS] = ELFCLASS64;
+    ident[EI_DATA] = ELFDATA2LSB;
+    ident[EI_VERSION] = EV_CURRENT;
+    buf.extend_from_slice(&ident);
+    push_u16(buf, ET_CORE); // e_type
+    push_u16(buf, EM_X86_64); // e_machine
+    push_u32(buf, EV_CURRENT as u32); // e_version
+    push_u64(buf, 0); // e_entry
+    push_u64(buf, ELF_HDR_SIZE as u64); // e_phoff
+    push_u64(buf, if has_extnum { shdr_offset as u64 } else { 0 }); // e_shoff
+    push_u32(buf, 0); // e_flags
+    push_u16(buf, ELF_HDR_SIZE as u16); // e_ehsize
+    push_u16(buf, PROGRAM_HDR_SIZE as u16); // e_phentsize
+    push_u16(buf, num_phdrs.min(PN_XNUM) as u16); // e_phnum
+    push_u16(
+        buf,
+        if has_extnum {
+            SECTION_HDR_SIZE as u16
+        } else {
+            0
+        },
+    ); // e_shentsize
+    push_u16(buf, if has_extnum { 1 } else { 0 }); // e_shnum
+    push_u16(buf, 0); // e_shstrndx
+}
+
+// The section header 0, whose sh_info is the real count of the program headers
+fn push_extnum_section_header(buf: &mut Vec<u8>, num_phdrs: usize) {
+    push_u32(buf, 0); // sh_name
+    push_u32(buf, SHT_NULL); // sh_type
+    push_u64(buf, 0); // sh_flags
+    push_u64(buf, 0); // sh_addr
+    push_u64(buf, 0); // sh_offset
+    push_u64(buf, 0); // sh_size
+    push_u32(buf, 0); // sh_link
+    push_u32(buf, num_phdrs as u32); // sh_info
+    push_u64(buf, 0); // sh_addralign
+    push_u64(buf, 0); // sh_entsize
+}
+
+fn push_program_header(
+    buf: &mut Vec<u8>,
+    p_type: u32,
+    p_flags: u32,
+    offset: usize,
+    vaddr: usize,
+    file_size: usize,
+    mem_size: usize,
+) {
+    let align = if p_type == PT_LOAD { PAGE_SIZE } else { 4 };
+    push_u32(buf, p_type);
+    push_u32(buf, p_flags);
+    push_u64(buf, offset as u64);
+    push_u64(buf, vaddr as u64); // p_vaddr
+    push_u64(buf, 0); // p_paddr
+    push_u64(buf, file_size as u64);
+    push_u64(buf, mem_size as u64);
+    push_u64(buf, align as u64);
+}
+
+fn segment_flags(perms: &VMPerms) -> u32 {
+    let mut flags = 0;
+    if perms.can_read() {
+        flags |= PF_R;
+    }
+    if perms.can_write() {
+        flags |= PF_W;
+    }
+    if perms.can_execute() {
+        flags |= PF_X;
+    }
+    flags
+}
+
+fn push_note(buf: &mut Vec<u8>, note_type: u32, desc: &[u8]) {
+    const NOTE_NAME: &[u8] = b"CORE\0";
+    push_u32(buf, NOTE_NAME.len() as u32);
+    push_u32(buf, desc.len() as u32);
+    push_u32(buf, note_type);
+    buf.extend_from_slice(NOTE_NAME);
+    buf.resize(align_up(buf.len(), 4), 0);
+    buf.extend_from_slice(desc);
+    buf.resize(align_up(buf.len(), 4), 0);
+}
+
+/// Serialize the struct elf_prstatus of the thread.
+fn prstatus(
+    siginfo: &CoreSigInfo,
+    thread: &ThreadRef,
+    process: &ProcessRef,
+    cpu_context: &CpuContext,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(PRSTATUS_SIZE);
+    // pr_info
+    push_u32(&mut buf, siginfo.signo);
+    push_u32(&mut buf, siginfo.code);
+    push_u32(&mut buf, siginfo.errno);
+    // pr_cursig and the padding, where the signal is the same for all threads
+    push_u16(&mut buf, siginfo.signo as u16);
+    push_u16(&mut buf, 0);
+    // pr_sigpend
+    push_u64(&mut buf, 0);
+    // pr_sighold
+    push_u64(&mut buf, thread.sig_mask().read().unwrap().to_c());
+    // pr_pid, pr_ppid, pr_pgrp and pr_sid
+    push_u32(&mut buf, thread.tid() as u32);
+    push_u32(&mut buf, process.parent().pid() as u32);
+    push_u32(&mut buf, process.pgid() as u32);
+    push_u32(&mut buf, 0);
+    // pr_utime, pr_stime, pr_cutime and pr_cstime
+    buf.resize(buf.len() + 4 * 16, 0);
+    // pr_reg, in the order of struct user_regs_struct
+    let regs = [
+        cpu_context.r15,
+        cpu_context.r14,
+        cpu_context.r13,
+        cpu_context.r12,
+        cpu_context.rbp,
+        cpu_context.rbx,
+        cpu_context.r11,
+        cpu_context.r10,
+        cpu_context.r9,
+        cpu_context.r8,
+        cpu_context.rax,
+        cpu_context.rcx,
+        cpu_context.rdx,
+        cpu_context.rsi,
+        cpu_context.rdi,
+        u64::MAX, // orig_rax
+        cpu_context.rip,
+        USER_CS,
+        cpu_context.rflags,
+        cpu_context.rsp,
+        USER_SS,
+        thread.task().user_fs() as u64, // fs_base
+        0,                              // gs_base
+        0,                              // ds
+        0,                              // es
+        0,                              // fs
+        0,                              // gs
+    ];
+    for reg in regs.iter() {
+        push_u64(&mut buf, *reg);
+    }
+    // pr_fpvalid and the padding
+    push_u32(&mut buf, 1);
+    push_u32(&mut buf, 0);
+    debug_assert!(buf.len() == PRSTATUS_SIZE);
+    buf
+}
+
+/// Serialize the struct elf_prpsinfo of the process.
+fn prpsinfo(process: &ProcessRef) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(PRPSINFO_SIZE);
+    // pr_state, pr_sname, pr_zomb and pr_nice
+    buf.extend_from_slice(&[0, b'R', 0, 0]);
+    push_u32(&mut buf, 0);
+    // pr_flag
+    push_u64(&mut buf, 0);
+    // pr_uid and pr_gid
+    push_u32(&mut buf, 0);
+    push_u32(&mut buf, 0);
+    // pr_pid, pr_ppid, pr_pgrp and pr_sid
+    push_u32(&mut buf, process.pid() as u32);
+    push_u32(&mut buf, process.parent().pid() as u32);
+    push_u32(&mut buf, process.pgid() as u32);
+    push_u32(&mut buf, 0);
+    // pr_fname and pr_psargs, which are NUL-terminated and may be truncated
+    let exec_path = process.exec_path();
+    let exec_name = exec_path.rsplit('/').next().unwrap_or(exec_path);
+    push_c_str(&mut buf, exec_name, 16);
+    push_c_str(&mut buf, exec_path, 80);
+    debug_assert!(buf.len() == PRPSINFO_SIZE);
+    buf
+}
+
+fn auxv(pairs: &[(u64, u64)]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity((pairs.len() + 1) * 2 * mem::size_of::<u64>());
+    for (key, val) in pairs.iter() {
+        push_u64(&mut buf, *key);
+        push_u64(&mut buf, *val);
+    }
+    // AT_NULL
+    push_u64(&mut buf, 0);
+    push_u64(&mut buf, 0);
+    buf
+}
+
+fn fpregs(cpu_context: &CpuContext) -> Vec<u8> {
+    if !cpu_context.fpregs.is_null() {
+        unsafe { cpu_context.fpregs.as_ref().unwrap().as_slice().to_vec() }
+    } else {
+        // The floating point registers are not saved, so they have not been changed
+        // since the user code traps into the LibOS.
+        FpRegs::save().as_slice().to_vec()
+    }
+}
+
+fn push_c_str(buf: &mut Vec<u8>, s: &str, len: usize) {
+    let bytes = s.as_bytes();
+    let copy_len = bytes.len().min(len - 1);
+    buf.extend_from_slice(&bytes[..copy_len]);
+    buf.resize(buf.len() + len - copy_len, 0);
+}
+
+fn push_u16(buf: &mut Vec<u8>, val: u16) {
+    buf.extend_from_slice(&val.to_le_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, val: u32) {
+    buf.extend_from_slice(&val.to_le_bytes());
+}
+
+fn push_u64(buf: &mut Vec<u8>, val: u64) {
+    buf.extend_from_slice(&val.to_le_bytes());
+}
@@ -7,7 +7,7 @@ use super::do_vfork::{is_vforked_child_process, vfork_return_to_parent};
 use super::pgrp::clean_pgrp_when_exit;
 use super::process::{Process, ProcessFilter};
 use super::{table, ProcessRef, TermStatus, ThreadRef, ThreadStatus};
-use crate::ipc::SHM_MANAGER;
+use crate::ipc::{SEM_MANAGER, SHM_MANAGER};
 use crate::prelude::*;
 use crate::signal::{KernelSignal, SigNum};
 use crate::syscall::CpuContext;
@@ -93,6 +93,9 @@ fn exit_process(thread: &ThreadRef, term_status: TermStatus) {
     let process = thread.process();
     let pid = process.pid();
 
+    // Cancel the AIO requests that have not been started
+    process.aio_contexts().clear();
+
     // Deadlock note: always lock parent first, then child.
 
     // Lock the idle process since it may adopt new children.
@@ -121,6 +124,10 @@ fn exit_process(thread: &ThreadRef, term_status: TermStatus) {
     // Clean used VM
     USER_SPACE_VM_MANAGER.free_chunks_when_exit(thread);
     SHM_MANAGER.detach_shm_when_process_exit(thread);
+    // Apply the semaphore adjustments of SEM_UNDO ops
+    SEM_MANAGER.undo_when_process_exit(thread);
+    // Timers are not inherited by the new program nor kept after exit
+    process.posix_timers().clear();
 
     // The parent is the idle process
     if parent_inner.is_none() {
@@ -132,6 +139,7 @@ fn exit_process(thread: &ThreadRef, term_status: TermStatus) {
         clean_pgrp_when_exit(process);
 
         process_inner.exit(term_status, &idle_ref, &mut idle_inner);
+        process.exit_waiters().dequeue_and_wake_all();
 
         // For vfork-and-exit children, just clean them to free the pid
         let _ = reap_zombie_child_created_with_vfork(pid);
@@ -143,6 +151,7 @@ fn exit_process(thread: &ThreadRef, term_status: TermStatus) {
     let mut parent_inner = parent_inner.unwrap();
 
     process_inner.exit(term_status, &idle_ref, &mut idle_inner);
+    process.exit_waiters().dequeue_and_wake_all();
 
     // For vfork-and-exit children, just clean them to free the pid
     let _ = reap_zombie_child_created_with_vfork(pid);
@@ -198,6 +207,11 @@ fn exit_process_for_execve(
     term_status: TermStatus,
 ) {
     let process = thread.process();
+    let usage = process.resource_usage();
+    let children_usage = process.children_usage();
+
+    // Cancel the AIO requests that have not been started
+    process.aio_contexts().clear();
 
     // Deadlock note: always lock parent first, then child.
     // Lock the idle process since it may adopt new children.
@@ -227,12 +241,18 @@ fn exit_process_for_execve(
     let mut process_inner = process.inner();
     // Clean used VM
     USER_SPACE_VM_MANAGER.free_chunks_when_exit(thread);
+    process.posix_timers().clear();
 
     let mut new_parent_inner = new_parent_ref.inner();
     let pid = process.pid();
 
     // Let new_process to adopt the children of current process
     process_inner.exit(term_status, &new_parent_ref, &mut new_parent_inner);
+    process.exit_waiters().dequeue_and_wake_all();
+
+    // The resource usage is kept across execve
+    new_parent_ref.add_exited_thread_usage(&usage);
+    new_parent_ref.add_children_usage(&children_usage);
 
     // For vfork-and-exit children, we don't need to adopt them here.
     // Because the new parent process share the same pid with the old parent process.
@@ -513,6 +513,7 @@ fn wait_event_timeout(thread: *const c_void, timeout: &Option<FutexTimeout>) ->
         })
         .unwrap_or((0, 0 as *const _, 0));
     let mut errno: c_int = 0;
+    let _sleep_guard = super::rusage::SleepGuard::new();
     unsafe {
         sgx_ret = sgx_thread_wait_untrusted_event_timeout_ocall(
             &mut ret as *mut c_int,
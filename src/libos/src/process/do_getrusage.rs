@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use super::rusage::ResourceUsage;
+use crate::prelude::*;
+use crate::time::{clock_t, up_time, SC_CLK_TCK};
+
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub enum RusageWho {
+    RUSAGE_SELF,
+    RUSAGE_CHILDREN,
+    RUSAGE_THREAD,
+}
+
+impl RusageWho {
+    pub fn from_raw(who: i32) -> Result<RusageWho> {
+        Ok(match who {
+            0 => RusageWho::RUSAGE_SELF,
+            -1 => RusageWho::RUSAGE_CHILDREN,
+            1 => RusageWho::RUSAGE_THREAD,
+            _ => return_errno!(EINVAL, "invalid who"),
+        })
+    }
+}
+
+pub fn do_getrusage(who: RusageWho) -> Result<ResourceUsage> {
+    debug!("getrusage: who: {:?}", who);
+    let current = current!();
+    let usage = match who {
+        RusageWho::RUSAGE_SELF => current.process().resource_usage(),
+        RusageWho::RUSAGE_CHILDREN => current.process().children_usage(),
+        RusageWho::RUSAGE_THREAD => current.resource_usage(),
+    };
+    Ok(usage)
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct tms_t {
+    tms_utime: clock_t,
+    tms_stime: clock_t,
+    tms_cutime: clock_t,
+    tms_cstime: clock_t,
+}
+
+/// Get the process times, and the elapsed real time since the system boot.
+///
+/// All times are expressed in clock ticks.
+pub fn do_times() -> Result<(tms_t, clock_t)> {
+    let current = current!();
+    let process = current.process();
+    let self_usage = process.resource_usage();
+    let children_usage = process.children_usage();
+    let tms = tms_t {
+        tms_utime: to_clock_ticks(self_usage.utime),
+        tms_stime: to_clock_ticks(self_usage.stime),
+        tms_cutime: to_clock_ticks(children_usage.utime),
+        tms_cstime: to_clock_ticks(children_usage.stime),
+    };
+    let elapsed = up_time::get().unwrap_or_default();
+    Ok((tms, to_clock_ticks(elapsed)))
+}
+
+fn to_clock_ticks(duration: Duration) -> clock_t {
+    (duration.as_millis() as u64 * SC_CLK_TCK / 1000) as clock_t
+}
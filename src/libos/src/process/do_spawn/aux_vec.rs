@@ -83,4 +83,15 @@ impl AuxVec {
     pub fn table(&self) -> &HashMap<AuxKey, u64> {
         &self.table
     }
+
+    /// Return the key-value pairs sorted by the keys, excluding AT_NULL.
+    pub fn to_pairs(&self) -> Vec<(u64, u64)> {
+        let mut pairs: Vec<(u64, u64)> = self
+            .table
+            .iter()
+            .map(|(key, val)| (*key as u64, *val))
+            .collect();
+        pairs.sort_unstable();
+        pairs
+    }
 }
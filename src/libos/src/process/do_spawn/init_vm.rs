@@ -1,37 +1,49 @@
 use std::ptr;
 
 use super::super::elf_file::ElfFile;
+use crate::config::LIBOS_CONFIG;
 use crate::misc::{resource_t, rlimit_t};
 use crate::prelude::*;
-use crate::vm::{ProcessVM, ProcessVMBuilder};
+use crate::vm::{MemQuota, ProcessVM, ProcessVMBuilder};
 
 pub fn do_init<'a, 'b>(
+    file_path: &str,
     elf_file: &'b ElfFile<'a>,
     ldso_elf_file: &'b ElfFile<'a>,
 ) -> Result<ProcessVM> {
     let mut process_vm = if current!().process().pid() == 0 {
         // Parent process is idle process and we can skip checking rlimit because main
-        // process will directly use memory configuration in Occlum.json
+        // process will directly use memory configuration in Occlum.json. The process
+        // starts a new app, which has its own memory quota.
         ProcessVMBuilder::new(vec![elf_file, ldso_elf_file])
+            .set_mem_quota(MemQuota::for_app(file_path))
+            .clone()
             .build()
             .cause_err(|e| errno!(e.errno(), "failed to create process VM"))?
     } else {
         // Parent process is not idle process. Inherit parent process's resource limit.
         let rlimit = current!().rlimits().lock().unwrap().clone();
-        let child_heap_size = rlimit.get(resource_t::RLIMIT_DATA).get_cur();
+        // RLIMIT_DATA is unlimited by default, then the heap size in Occlum.json is used
+        let data_limit = rlimit.get(resource_t::RLIMIT_DATA).get_cur();
+        let child_heap_size = if data_limit == u64::max_value() {
+            LIBOS_CONFIG.process.default_heap_size as u64
+        } else {
+            data_limit
+        };
         let child_stack_size = rlimit.get(resource_t::RLIMIT_STACK).get_cur();
-        let child_mmap_size =
-            rlimit.get(resource_t::RLIMIT_AS).get_cur() - child_heap_size - child_stack_size;
+        // RLIMIT_AS is checked when the child maps memory, and so is the memory quota
+        // of the app, which is shared with the parent
+        let mem_quota = current!().vm().mem_quota().cloned();
 
         debug!(
-            "new process: heap_size = {:?}, stack_size = {:?}, mmap_size = {:?}",
-            child_heap_size, child_stack_size, child_mmap_size
+            "new process: heap_size = {:?}, stack_size = {:?}",
+            child_heap_size, child_stack_size
         );
 
         ProcessVMBuilder::new(vec![elf_file, ldso_elf_file])
             .set_heap_size(child_heap_size as usize)
             .set_stack_size(child_stack_size as usize)
-            .set_mmap_size(child_mmap_size as usize)
+            .set_mem_quota(mem_quota)
             .clone()
             .build()
             .cause_err(|e| errno!(e.errno(), "failed to create process VM"))?
@@ -202,7 +202,7 @@ fn new_process_common(
     let new_process_ref = {
         let process_ref = current_ref.process().clone();
 
-        let vm = init_vm::do_init(&exec_elf_hdr, &ldso_elf_hdr)?;
+        let mut vm = init_vm::do_init(file_path, &exec_elf_hdr, &ldso_elf_hdr)?;
         let mut auxvec = init_auxvec(&vm, &exec_elf_hdr)?;
 
         // Notify debugger to load the symbols from elf file
@@ -250,6 +250,7 @@ fn new_process_common(
                 )?
             }
         };
+        vm.set_auxv(auxvec.to_pairs());
         let vm_ref = Arc::new(vm);
         let files_ref = {
             let files = init_files(current_ref, file_actions, host_stdio_fds, &reuse_tid)?;
@@ -1,3 +1,4 @@
+use super::core_dump::save_notes_for_core_dump;
 use super::untrusted_event::{set_event, wait_event};
 use super::{ProcessFilter, ProcessRef, TermStatus, ThreadId, ThreadRef};
 use crate::fs::FileTable;
@@ -215,9 +216,11 @@ fn close_files_opened_by_child(current: &ThreadRef, parent_file_table: &FileTabl
     Ok(())
 }
 
-pub fn handle_force_stop() {
+pub fn handle_force_stop(curr_user_ctxt: &CpuContext) {
     let current = current!();
     if current.is_forced_to_stop() {
+        // The thread may be stopped for the core dump of the process
+        save_notes_for_core_dump(&current, curr_user_ctxt);
         let current_thread_ptr = current.raw_ptr();
         info!(
             "Thread 0x{:x} is forced to stop ...",
@@ -114,6 +114,10 @@ fn free_zombie_child(mut parent_inner: SgxMutexGuard<ProcessInner>, zombie_pid:
     // This has to be done after removing from process table to make sure process.pgid() can work.
     clean_pgrp_when_exit(&zombie);
 
+    // The parent reaps the resource usage of the child and its waited-for descendants
+    let zombie_usage = zombie.resource_usage() + zombie.children_usage();
+    zombie.parent().add_children_usage(&zombie_usage);
+
     let zombie_inner = zombie.inner();
     zombie_inner.term_status().unwrap().as_u32() as i32
 }
@@ -21,8 +21,10 @@ use self::process::{ProcessBuilder, ProcessInner};
 use self::thread::{ThreadBuilder, ThreadId, ThreadInner};
 use self::wait::{WaitQueue, Waiter};
 
+pub use self::core_dump::dump_core_and_exit;
 pub use self::do_exit::handle_force_exit;
 pub use self::do_futex::{futex_wait, futex_wake};
+pub use self::do_getrusage::tms_t;
 pub use self::do_robust_list::RobustListHead;
 pub use self::do_spawn::do_spawn_without_exec;
 pub use self::do_vfork::{do_vfork, handle_force_stop};
@@ -35,12 +37,14 @@ pub use self::task::Task;
 pub use self::term_status::{ForcedExitStatus, TermStatus};
 pub use self::thread::{Thread, ThreadStatus};
 
+mod core_dump;
 mod do_arch_prctl;
 mod do_clone;
 mod do_exec;
 mod do_exit;
 mod do_futex;
 mod do_getpid;
+mod do_getrusage;
 mod do_robust_list;
 mod do_set_tid_address;
 mod do_spawn;
@@ -58,6 +62,7 @@ mod wait;
 
 pub mod current;
 pub mod elf_file;
+pub mod rusage;
 pub mod table;
 pub mod task;
 
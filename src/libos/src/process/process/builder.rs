@@ -5,10 +5,13 @@ use super::super::{
     FileTableRef, ForcedExitStatus, FsViewRef, NiceValueRef, ProcessGrpRef, ProcessRef,
     ProcessVMRef, ResourceLimitsRef, SchedAgentRef,
 };
-use super::{Process, ProcessInner};
+use super::{Process, ProcessInner, ProcessUsage};
+use crate::events::WaiterQueue;
+use crate::fs::aio::AioContexts;
 use crate::fs::FileMode;
 use crate::prelude::*;
 use crate::signal::{SigDispositions, SigQueues, SigSet};
+use crate::time::posix_timer::PosixTimers;
 
 #[derive(Debug)]
 pub struct ProcessBuilder {
@@ -135,6 +138,10 @@ impl ProcessBuilder {
             let sig_dispositions = RwLock::new(self.sig_dispositions.unwrap_or_default());
             let sig_queues = RwLock::new(SigQueues::new());
             let forced_exit_status = ForcedExitStatus::new();
+            let posix_timers = PosixTimers::new();
+            let aio_contexts = AioContexts::new();
+            let usage = SgxMutex::new(ProcessUsage::default());
+            let exit_waiters = WaiterQueue::new();
             let start_time = crate::time::up_time::get().unwrap();
             Arc::new(Process {
                 pid,
@@ -147,6 +154,10 @@ impl ProcessBuilder {
                 sig_dispositions,
                 sig_queues,
                 forced_exit_status,
+                posix_timers,
+                aio_contexts,
+                usage,
+                exit_waiters,
             })
         };
 
@@ -1,11 +1,15 @@
 use std::fmt;
 use std::time::Duration;
 
+use super::rusage::ResourceUsage;
 use super::wait::WaitQueue;
 use super::{ForcedExitStatus, ProcessGrpRef, ProcessRef, TermStatus, ThreadRef};
+use crate::events::WaiterQueue;
+use crate::fs::aio::AioContexts;
 use crate::fs::FileMode;
 use crate::prelude::*;
 use crate::signal::{SigDispositions, SigNum, SigQueues};
+use crate::time::posix_timer::PosixTimers;
 
 pub use self::builder::ProcessBuilder;
 pub use self::idle::IDLE;
@@ -27,6 +31,21 @@ pub struct Process {
     sig_dispositions: RwLock<SigDispositions>,
     sig_queues: RwLock<SigQueues>,
     forced_exit_status: ForcedExitStatus,
+    // Timers
+    posix_timers: PosixTimers,
+    // AIO
+    aio_contexts: AioContexts,
+    // Resource usage
+    usage: SgxMutex<ProcessUsage>,
+    // Waiters for the process to exit
+    exit_waiters: WaiterQueue,
+}
+
+/// The resource usage that is not counted by the live threads of a process.
+#[derive(Debug, Default)]
+pub struct ProcessUsage {
+    exited_threads: ResourceUsage,
+    children: ResourceUsage,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -156,6 +175,50 @@ impl Process {
         &self.sig_dispositions
     }
 
+    /// Get the POSIX timers created by timer_create.
+    pub fn posix_timers(&self) -> &PosixTimers {
+        &self.posix_timers
+    }
+
+    /// Get the AIO contexts created by io_setup.
+    pub fn aio_contexts(&self) -> &AioContexts {
+        &self.aio_contexts
+    }
+
+    /// Get the waiters to be woken up when the process becomes a zombie.
+    pub fn exit_waiters(&self) -> &WaiterQueue {
+        &self.exit_waiters
+    }
+
+    /// Get the resource usage of all threads of the process, both live and exited.
+    pub fn resource_usage(&self) -> ResourceUsage {
+        // An exiting thread moves its usage to the exited threads with the
+        // inner lock held, so read both under the lock.
+        let inner = self.inner();
+        let exited_usage = self.usage.lock().unwrap().exited_threads;
+        let threads = inner.threads().map(|threads| threads.as_slice());
+        threads
+            .unwrap_or(&[])
+            .iter()
+            .fold(exited_usage, |usage, thread| {
+                usage + thread.resource_usage()
+            })
+    }
+
+    /// Get the resource usage of all children that have been waited for, and
+    /// their descendants that have been waited for.
+    pub fn children_usage(&self) -> ResourceUsage {
+        self.usage.lock().unwrap().children
+    }
+
+    pub(super) fn add_exited_thread_usage(&self, thread_usage: &ResourceUsage) {
+        self.usage.lock().unwrap().exited_threads += *thread_usage;
+    }
+
+    pub(super) fn add_children_usage(&self, child_usage: &ResourceUsage) {
+        self.usage.lock().unwrap().children += *child_usage;
+    }
+
     pub fn term_status(&self) -> Option<TermStatus> {
         self.forced_exit_status.term_status()
     }
@@ -0,0 +1,242 @@
+//! Resource usage accounting of threads and processes.
+//!
+//! The total CPU time of a thread is measured by the CPU-time clock of its
+//! host thread. As the LibOS cannot observe mode switches cheaply, the split of
+//! the CPU time into the user and system time is estimated by sampling: on
+//! every tick, a thread that is running user code is charged a user tick, and a
+//! thread that is running LibOS code is charged a system tick. A thread that is
+//! sleeping in the LibOS is charged nothing.
+//!
+//! As the ratio of the ticks changes over time, the user and system time are
+//! clamped to never decrease, in the same way as cputime_adjust() in Linux.
+//!
+//! No page faults are counted, i.e., ru_minflt and ru_majflt are always zero.
+//! The memory of a mapping is committed when the mapping is created, so the
+//! LibOS never resolves a page fault by itself; a page fault always ends up as
+//! a fault signal to the user.
+
+use std::ops::{Add, AddAssign};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::Duration;
+
+use super::{table, ThreadRef, ThreadStatus};
+use crate::prelude::*;
+use crate::time::timeval_t;
+
+/// The per-thread resource usage counters.
+pub struct ThreadUsage {
+    state: AtomicU8,
+    user_ticks: AtomicU64,
+    sys_ticks: AtomicU64,
+    nvcsw: AtomicU64,
+    nivcsw: AtomicU64,
+    // The user and system time reported last time
+    prev_cputime: SgxMutex<(Duration, Duration)>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+enum ExecState {
+    User = 0,
+    LibOS = 1,
+    Sleeping = 2,
+}
+
+impl ThreadUsage {
+    pub fn new() -> Self {
+        Self {
+            // A new thread starts in the LibOS and then jumps to the user space
+            state: AtomicU8::new(ExecState::LibOS as u8),
+            user_ticks: AtomicU64::new(0),
+            sys_ticks: AtomicU64::new(0),
+            nvcsw: AtomicU64::new(0),
+            nivcsw: AtomicU64::new(0),
+            prev_cputime: SgxMutex::new((Duration::default(), Duration::default())),
+        }
+    }
+
+    /// Called when the thread traps into the LibOS.
+    pub fn enter_libos(&self) {
+        self.set_state(ExecState::LibOS);
+    }
+
+    /// Called when the thread returns to the user space.
+    pub fn exit_libos(&self) {
+        self.set_state(ExecState::User);
+    }
+
+    /// Called when the thread is preempted, i.e., it is interrupted.
+    pub fn inc_nivcsw(&self) {
+        self.nivcsw.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn set_state(&self, state: ExecState) -> ExecState {
+        match self.state.swap(state as u8, Ordering::Relaxed) {
+            0 => ExecState::User,
+            1 => ExecState::LibOS,
+            _ => ExecState::Sleeping,
+        }
+    }
+
+    fn tick(&self) {
+        let state = self.state.load(Ordering::Relaxed);
+        if state == ExecState::User as u8 {
+            self.user_ticks.fetch_add(1, Ordering::Relaxed);
+        } else if state == ExecState::LibOS as u8 {
+            self.sys_ticks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the resource usage given the total CPU time consumed by the thread.
+    pub fn usage(&self, cputime: Duration) -> ResourceUsage {
+        let (utime, stime) = self.split_cputime(cputime);
+        ResourceUsage {
+            utime,
+            stime,
+            minflt: 0,
+            majflt: 0,
+            nvcsw: self.nvcsw.load(Ordering::Relaxed),
+            nivcsw: self.nivcsw.load(Ordering::Relaxed),
+        }
+    }
+
+    // Split the CPU time into the user and system time according to the ratio
+    // of the ticks. Neither of them decreases, which is what cputime_adjust()
+    // in Linux guarantees.
+    fn split_cputime(&self, cputime: Duration) -> (Duration, Duration) {
+        let mut prev = self.prev_cputime.lock().unwrap();
+        let (prev_utime, prev_stime) = *prev;
+        // The CPU time may be read with a coarser granularity than before
+        if cputime <= prev_utime + prev_stime {
+            return *prev;
+        }
+
+        let user_ticks = self.user_ticks.load(Ordering::Relaxed) as u128;
+        let sys_ticks = self.sys_ticks.load(Ordering::Relaxed) as u128;
+        let mut stime = if sys_ticks == 0 {
+            Duration::default()
+        } else if user_ticks == 0 {
+            cputime
+        } else {
+            let nanos = cputime.as_nanos() * sys_ticks / (user_ticks + sys_ticks);
+            Duration::from_nanos(nanos as u64)
+        };
+        stime = stime.max(prev_stime);
+        let mut utime = cputime - stime;
+        if utime < prev_utime {
+            utime = prev_utime;
+            stime = cputime - utime;
+        }
+
+        *prev = (utime, stime);
+        *prev
+    }
+}
+
+/// Mark the current thread as sleeping in the LibOS until the guard is dropped.
+///
+/// Each sleep, except a nested one, is counted as a voluntary context switch.
+pub struct SleepGuard {
+    thread: ThreadRef,
+    prev_state: ExecState,
+}
+
+impl SleepGuard {
+    pub fn new() -> Self {
+        let thread = current!();
+        let usage = thread.usage();
+        let prev_state = usage.set_state(ExecState::Sleeping);
+        if prev_state != ExecState::Sleeping {
+            usage.nvcsw.fetch_add(1, Ordering::Relaxed);
+        }
+        Self { thread, prev_state }
+    }
+}
+
+impl Drop for SleepGuard {
+    fn drop(&mut self) {
+        self.thread.usage().set_state(self.prev_state);
+    }
+}
+
+/// Charge a tick to every running thread. The function is called periodically.
+pub fn tick_all_threads() {
+    for thread in table::get_all_threads() {
+        if thread.status() == ThreadStatus::Running {
+            thread.usage().tick();
+        }
+    }
+}
+
+/// The resource usage of a thread, a process, or the children of a process.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ResourceUsage {
+    pub utime: Duration,
+    pub stime: Duration,
+    pub minflt: u64,
+    pub majflt: u64,
+    pub nvcsw: u64,
+    pub nivcsw: u64,
+}
+
+impl Add for ResourceUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            utime: self.utime + other.utime,
+            stime: self.stime + other.stime,
+            minflt: self.minflt + other.minflt,
+            majflt: self.majflt + other.majflt,
+            nvcsw: self.nvcsw + other.nvcsw,
+            nivcsw: self.nivcsw + other.nivcsw,
+        }
+    }
+}
+
+impl AddAssign for ResourceUsage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl ResourceUsage {
+    /// The total CPU time, i.e., the user time plus the system time.
+    pub fn cputime(&self) -> Duration {
+        self.utime + self.stime
+    }
+
+    pub fn to_c(&self) -> rusage_t {
+        rusage_t {
+            ru_utime: timeval_t::from(self.utime),
+            ru_stime: timeval_t::from(self.stime),
+            ru_minflt: self.minflt as i64,
+            ru_majflt: self.majflt as i64,
+            ru_nvcsw: self.nvcsw as i64,
+            ru_nivcsw: self.nivcsw as i64,
+            ..Default::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct rusage_t {
+    ru_utime: timeval_t,
+    ru_stime: timeval_t,
+    ru_maxrss: i64,
+    ru_ixrss: i64,
+    ru_idrss: i64,
+    ru_isrss: i64,
+    ru_minflt: i64,
+    ru_majflt: i64,
+    ru_nswap: i64,
+    ru_inblock: i64,
+    ru_oublock: i64,
+    ru_msgsnd: i64,
+    ru_msgrcv: i64,
+    ru_nsignals: i64,
+    ru_nvcsw: i64,
+    ru_nivcsw: i64,
+}
@@ -2,12 +2,14 @@ use super::do_arch_prctl::ArchPrctlCode;
 use super::do_clone::CloneFlags;
 use super::do_exec::do_exec;
 use super::do_futex::{FutexFlags, FutexOp, FutexTimeout};
+use super::do_getrusage::{tms_t, RusageWho};
 use super::do_robust_list::RobustListHead;
 use super::do_spawn::FileAction;
 use super::do_wait4::WaitOptions;
 use super::pgrp::*;
 use super::prctl::PrctlCmd;
 use super::process::ProcessFilter;
+use super::rusage::rusage_t;
 use super::spawn_attribute::{clone_spawn_atrributes_safely, posix_spawnattr_t, SpawnAttr};
 use crate::prelude::*;
 use crate::syscall::CpuContext;
@@ -400,6 +402,28 @@ pub fn do_wait4(pid: i32, exit_status_ptr: *mut i32, options: u32) -> Result<isi
     }
 }
 
+pub fn do_getrusage(who: i32, usage_ptr: *mut rusage_t) -> Result<isize> {
+    check_mut_ptr(usage_ptr)?;
+    let who = RusageWho::from_raw(who)?;
+    let usage = super::do_getrusage::do_getrusage(who)?;
+    unsafe {
+        *usage_ptr = usage.to_c();
+    }
+    Ok(0)
+}
+
+pub fn do_times(buf: *mut tms_t) -> Result<isize> {
+    let (tms, elapsed) = super::do_getrusage::do_times()?;
+    // The buffer is allowed to be NULL
+    if !buf.is_null() {
+        check_mut_ptr(buf)?;
+        unsafe {
+            *buf = tms;
+        }
+    }
+    Ok(elapsed as isize)
+}
+
 pub fn do_getpid() -> Result<isize> {
     let pid = super::do_getpid::do_getpid();
     Ok(pid as isize)
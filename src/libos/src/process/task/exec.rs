@@ -57,6 +57,12 @@ pub fn exec(libos_tid: pid_t, host_tid: pid_t) -> Result<i32> {
         TermStatus::Killed(signum) => {
             info!("Thread killed: tid = {}, signum = {:?}", libos_tid, signum);
         }
+        TermStatus::Dumped(signum) => {
+            info!(
+                "Thread killed with core dumped: tid = {}, signum = {:?}",
+                libos_tid, signum
+            );
+        }
     }
 
     // Disable current::get()
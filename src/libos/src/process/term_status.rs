@@ -37,12 +37,11 @@ impl ForcedExitStatus {
     }
 }
 
-// TODO: support core dump
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TermStatus {
     Exited(u8),
     Killed(SigNum),
-    //Dumped(SigNum),
+    Dumped(SigNum),
 }
 
 impl TermStatus {
@@ -51,7 +50,7 @@ impl TermStatus {
         match *self {
             TermStatus::Exited(status) => (status as u32) << 8,
             TermStatus::Killed(signum) => (signum.as_u8() as u32),
-            //TermStatus::Dumped(signum) => (signum.as_u8() as u32) | 0x80,
+            TermStatus::Dumped(signum) => (signum.as_u8() as u32) | 0x80,
         }
     }
 }
@@ -3,7 +3,7 @@ use std::ptr::NonNull;
 use super::{
     FileTableRef, FsViewRef, NiceValueRef, ProcessRef, ProcessVM, ProcessVMRef, ResourceLimitsRef,
     RobustListHead, SchedAgentRef, SigQueues, SigSet, Task, Thread, ThreadId, ThreadInner,
-    ThreadName, ThreadRef,
+    ThreadName, ThreadRef, ThreadUsage,
 };
 use crate::events::HostEventFd;
 use crate::prelude::*;
@@ -135,12 +135,14 @@ impl ThreadBuilder {
         let sig_mask = RwLock::new(self.sig_mask.unwrap_or_default());
         let sig_queues = RwLock::new(SigQueues::new());
         let sig_tmp_mask = RwLock::new(SigSet::new_empty());
+        let sig_saved_mask = RwLock::new(None);
         let sig_stack = SgxMutex::new(None);
         let profiler = if cfg!(feature = "syscall_timing") {
             SgxMutex::new(Some(ThreadProfiler::new()))
         } else {
             SgxMutex::new(None)
         };
+        let usage = ThreadUsage::new();
         let host_eventfd = Arc::new(HostEventFd::new()?);
         let raw_ptr = RwLock::new(0);
 
@@ -161,8 +163,10 @@ impl ThreadBuilder {
             sig_queues,
             sig_mask,
             sig_tmp_mask,
+            sig_saved_mask,
             sig_stack,
             profiler,
+            usage,
             host_eventfd,
             raw_ptr,
         });
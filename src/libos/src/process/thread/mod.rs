@@ -1,14 +1,14 @@
 use std::fmt;
 use std::ptr::NonNull;
 
+use super::rusage::{ResourceUsage, ThreadUsage};
 use super::task::Task;
 use super::{
     FileTableRef, ForcedExitStatus, FsViewRef, NiceValueRef, ProcessRef, ProcessVM, ProcessVMRef,
     ResourceLimitsRef, RobustListHead, SchedAgentRef, TermStatus, ThreadRef,
 };
 use crate::events::HostEventFd;
-use crate::fs::{EventCreationFlags, EventFile};
-use crate::net::THREAD_NOTIFIERS;
+use crate::fs::AsMq;
 use crate::prelude::*;
 use crate::signal::{SigQueues, SigSet, SigStack};
 use crate::time::ThreadProfiler;
@@ -44,9 +44,12 @@ pub struct Thread {
     sig_queues: RwLock<SigQueues>,
     sig_mask: RwLock<SigSet>,
     sig_tmp_mask: RwLock<SigSet>,
+    sig_saved_mask: RwLock<Option<SigSet>>,
     sig_stack: SgxMutex<Option<SigStack>>,
     // System call timing
     profiler: SgxMutex<Option<ThreadProfiler>>,
+    // Resource usage
+    usage: ThreadUsage,
     // Misc
     host_eventfd: Arc<HostEventFd>,
     raw_ptr: RwLock<usize>,
@@ -107,6 +110,15 @@ impl Thread {
         &self.sig_tmp_mask
     }
 
+    /// Get the signal mask saved by a syscall that temporarily replaces the
+    /// signal mask, e.g., pselect6 and ppoll.
+    ///
+    /// The saved mask is restored after the signal that interrupts the syscall
+    /// is delivered, or when the syscall returns if no signal is delivered.
+    pub fn sig_saved_mask(&self) -> &RwLock<Option<SigSet>> {
+        &self.sig_saved_mask
+    }
+
     /// Get the alternate signal stack.
     pub fn sig_stack(&self) -> &SgxMutex<Option<SigStack>> {
         &self.sig_stack
@@ -117,6 +129,22 @@ impl Thread {
         &self.profiler
     }
 
+    /// Get the resource usage counters
+    pub fn usage(&self) -> &ThreadUsage {
+        &self.usage
+    }
+
+    /// Get the resource usage of the thread
+    pub fn resource_usage(&self) -> ResourceUsage {
+        let host_tid = self.sched().lock().unwrap().host_tid();
+        // The thread has not started yet or has exited if there is no host thread
+        let cputime = host_tid
+            .and_then(|host_tid| crate::time::do_host_thread_cputime(host_tid).ok())
+            .map(|ts| ts.as_duration())
+            .unwrap_or_default();
+        self.usage.usage(cputime)
+    }
+
     /// Get the host thread's raw pointer of this libos thread
     pub fn raw_ptr(&self) -> usize {
         self.raw_ptr.read().unwrap().clone()
@@ -140,6 +168,9 @@ impl Thread {
         // a deadlock.
         let file = self.files().lock().unwrap().del(fd)?;
         file.release_advisory_locks();
+        if let Ok(mq_file) = file.as_mq() {
+            mq_file.on_close(&self.files().lock().unwrap());
+        }
         Ok(())
     }
 
@@ -228,19 +259,6 @@ impl Thread {
             self.inner().start();
         }
 
-        let eventfd = EventFile::new(
-            0,
-            EventCreationFlags::EFD_CLOEXEC | EventCreationFlags::EFD_NONBLOCK,
-        )
-        .unwrap();
-
-        let event_file = THREAD_NOTIFIERS.lock().unwrap().insert(self.tid(), eventfd);
-
-        assert!(
-            event_file.is_none(),
-            "this thread should not have an eventfd before start"
-        );
-
         #[cfg(feature = "syscall_timing")]
         self.profiler()
             .lock()
@@ -261,16 +279,13 @@ impl Thread {
             .stop()
             .unwrap();
 
-        THREAD_NOTIFIERS
-            .lock()
-            .unwrap()
-            .remove(&self.tid())
-            .unwrap();
-
-        self.sched().lock().unwrap().detach();
-
-        // Remove this thread from its owner process
+        // Remove this thread from its owner process. The resource usage is kept
+        // in the process, before the host thread is detached, under the same
+        // lock so that it is counted exactly once.
         let mut process_inner = self.process.inner();
+        let usage = self.resource_usage();
+        self.sched().lock().unwrap().detach();
+        self.process.add_exited_thread_usage(&usage);
         let threads = process_inner.threads_mut().unwrap();
         let thread_i = threads
             .iter()
@@ -58,6 +58,30 @@ impl<T> From<*const T> for sigval_t {
     }
 }
 
+/// The structure that specifies how to notify a process of an asynchronous event,
+/// e.g., the arrival of a message on an empty POSIX message queue.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct sigevent_t {
+    pub sigev_value: sigval_t,
+    pub sigev_signo: i32,
+    pub sigev_notify: i32,
+    // The union of the thread ID and the thread function/attributes
+    pub sigev_un: [i32; 12],
+}
+
+impl sigevent_t {
+    /// The thread ID for SIGEV_THREAD_ID
+    pub fn sigev_notify_thread_id(&self) -> pid_t {
+        self.sigev_un[0] as pid_t
+    }
+}
+
+pub const SIGEV_SIGNAL: i32 = 0;
+pub const SIGEV_NONE: i32 = 1;
+pub const SIGEV_THREAD: i32 = 2;
+pub const SIGEV_THREAD_ID: i32 = 4;
+
 #[derive(Clone, Copy)]
 #[repr(C)]
 pub struct siginfo_t {
@@ -0,0 +1,66 @@
+use std::sync::Weak;
+
+use super::{SigNum, SigSet};
+use crate::events::{Observer, Waiter, WaiterQueueObserver};
+use crate::prelude::*;
+
+/// Wait until a signal that is not blocked is pending.
+///
+/// The signal is delivered when the syscall returns, so the function always
+/// returns EINTR, except that the process is terminated by the signal.
+pub fn do_pause() -> Result<()> {
+    debug!("do_pause");
+
+    let thread = current!();
+    let process = thread.process().clone();
+    let interest = !*thread.sig_mask().read().unwrap();
+
+    let observer = WaiterQueueObserver::<SigNum>::new();
+    let weak_observer = Arc::downgrade(&observer) as Weak<dyn Observer<_>>;
+    thread.sig_queues().read().unwrap().notifier().register(
+        weak_observer.clone(),
+        Some(interest),
+        None,
+    );
+    process.sig_queues().read().unwrap().notifier().register(
+        weak_observer.clone(),
+        Some(interest),
+        None,
+    );
+
+    let has_pending_signal = || -> bool {
+        let pending = process.sig_queues().read().unwrap().pending()
+            | thread.sig_queues().read().unwrap().pending();
+        !(pending & interest).empty()
+    };
+    let waiter = Waiter::new();
+    loop {
+        if has_pending_signal() || process.is_forced_to_exit() {
+            break;
+        }
+        observer.waiter_queue().reset_and_enqueue(&waiter);
+        // Check again in case that a signal arrived before the waiter is enqueued
+        if has_pending_signal() {
+            break;
+        }
+        // The waiter is woken up by the arrival of a signal or interrupted
+        if waiter.wait(None).is_err() {
+            break;
+        }
+    }
+
+    thread
+        .sig_queues()
+        .read()
+        .unwrap()
+        .notifier()
+        .unregister(&weak_observer);
+    process
+        .sig_queues()
+        .read()
+        .unwrap()
+        .notifier()
+        .unregister(&weak_observer);
+
+    return_errno!(EINTR, "interrupted by a signal");
+}
@@ -41,6 +41,51 @@ pub fn do_rt_sigprocmask(
     Ok(())
 }
 
+/// Run `f` with the signal mask of the current thread temporarily replaced by `sig_mask`, as
+/// pselect6, ppoll and epoll_pwait do.
+///
+/// If `f` is interrupted by a signal, the original mask is restored after the signal is
+/// delivered, i.e., the signal handler runs with the temporary mask in effect. Otherwise, the
+/// original mask is restored right away so that no signal unblocked only by the temporary mask
+/// is delivered.
+///
+/// Like Linux, `f` is not run at all if a signal unblocked by the temporary mask is already
+/// pending.
+pub fn do_with_sig_mask<T>(mut sig_mask: SigSet, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let thread = current!();
+    let old_sig_mask = {
+        let mut curr_sig_mask = thread.sig_mask().write().unwrap();
+        let old_sig_mask = *curr_sig_mask;
+        sig_mask -= SIGKILL;
+        sig_mask -= SIGSTOP;
+        *curr_sig_mask = sig_mask;
+        old_sig_mask
+    };
+
+    // A signal that arrived earlier would not wake up `f`, so check the pending signals first
+    let has_pending_signal = {
+        let sig_mask = *thread.sig_mask().read().unwrap();
+        let pending = thread.process().sig_queues().read().unwrap().pending()
+            | thread.sig_queues().read().unwrap().pending();
+        !(pending & !sig_mask).empty()
+    };
+    let ret = if has_pending_signal {
+        Err(errno!(EINTR, "interrupted by a pending signal"))
+    } else {
+        f()
+    };
+
+    match &ret {
+        Err(e) if e.errno() == EINTR => {
+            *thread.sig_saved_mask().write().unwrap() = Some(old_sig_mask);
+        }
+        _ => {
+            *thread.sig_mask().write().unwrap() = old_sig_mask;
+        }
+    }
+    ret
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 pub enum MaskOp {
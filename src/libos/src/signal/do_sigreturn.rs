@@ -4,7 +4,7 @@ use super::sig_stack::SigStackFlags;
 use super::{SigAction, SigActionFlags, SigDefaultAction, SigSet, Signal};
 use crate::lazy_static::__Deref;
 use crate::prelude::*;
-use crate::process::{ProcessRef, TermStatus, ThreadRef};
+use crate::process::{dump_core_and_exit, ProcessRef, TermStatus, ThreadRef};
 use crate::syscall::{CpuContext, FpRegs};
 use aligned::{Aligned, A16};
 use core::arch::x86_64::{_fxrstor, _fxsave};
@@ -71,6 +71,12 @@ pub fn deliver_signal(cpu_context: &mut CpuContext) {
         do_deliver_signal(&thread, &process, cpu_context);
     }
 
+    // Restore the signal mask replaced by the syscall if no signal handler has taken it
+    let saved_sig_mask = thread.sig_saved_mask().write().unwrap().take();
+    if let Some(saved_sig_mask) = saved_sig_mask {
+        *thread.sig_mask().write().unwrap() = saved_sig_mask;
+    }
+
     // Ensure the tmp signal mask is cleared before sysret
     let mut tmp_sig_mask = thread.sig_tmp_mask().write().unwrap();
     *tmp_sig_mask = SigSet::new_empty();
@@ -155,11 +161,15 @@ fn handle_signal(
             let default_action = SigDefaultAction::from_signum(signal.num());
             match default_action {
                 SigDefaultAction::Ign => true,
-                SigDefaultAction::Term | SigDefaultAction::Core => {
+                SigDefaultAction::Term => {
                     let term_status = TermStatus::Killed(signal.num());
                     process.force_exit(term_status);
                     false
                 }
+                SigDefaultAction::Core => {
+                    dump_core_and_exit(signal.as_ref(), thread, process, cpu_context);
+                    false
+                }
                 SigDefaultAction::Stop => {
                     warn!("SIGSTOP is unsupported");
                     true
@@ -205,7 +215,14 @@ fn handle_signals_by_user(
 ) -> Result<()> {
     let old_sigmask = {
         let mut sigmask = thread.sig_mask().write().unwrap();
-        let old_sigmask = *sigmask;
+        // If the signal mask is temporarily replaced by the syscall, the saved one is restored
+        // when the signal handler returns
+        let old_sigmask = thread
+            .sig_saved_mask()
+            .write()
+            .unwrap()
+            .take()
+            .unwrap_or(*sigmask);
         *sigmask = new_sigmask;
         if !flags.contains(SigActionFlags::SA_NODEFER) {
             // Block the current signal while executing the signal handler
@@ -129,7 +129,8 @@ impl Drop for PendingSigWaiter {
     }
 }
 
-fn dequeue_pending_signal(
+/// Dequeue a pending signal of interest from the process or the thread.
+pub fn dequeue_pending_signal(
     interest: &SigSet,
     thread: &ThreadRef,
     process: &ProcessRef,
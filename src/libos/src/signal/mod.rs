@@ -4,10 +4,15 @@ use crate::prelude::*;
 
 use sig_action::{SigAction, SigActionFlags, SigDefaultAction};
 
-pub use self::c_types::{sigaction_t, siginfo_t, sigset_t, stack_t};
+pub use self::c_types::{
+    sigaction_t, sigevent_t, siginfo_t, sigset_t, sigval_t, stack_t, SIGEV_NONE, SIGEV_SIGNAL,
+    SIGEV_THREAD, SIGEV_THREAD_ID, SI_TIMER,
+};
 pub use self::constants::*;
 pub use self::do_kill::do_kill_from_outside_enclave;
+pub use self::do_sigprocmask::do_with_sig_mask;
 pub use self::do_sigreturn::{deliver_signal, force_signal};
+pub use self::do_sigtimedwait::dequeue_pending_signal;
 pub use self::sig_dispositions::SigDispositions;
 pub use self::sig_num::SigNum;
 pub use self::sig_queues::SigQueues;
@@ -18,6 +23,7 @@ pub use self::syscalls::*;
 
 mod c_types;
 mod do_kill;
+mod do_pause;
 mod do_sigaction;
 mod do_sigaltstack;
 mod do_sigpending;
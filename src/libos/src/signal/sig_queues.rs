@@ -94,9 +94,10 @@ impl SigQueues {
 
             let queue = self.get_std_queue_mut(signum);
             let signal = queue.take();
-            if signal.is_some() {
+            if let Some(signal) = signal {
                 self.count -= 1;
-                return signal;
+                signal.on_dequeued();
+                return Some(signal);
             }
         }
 
@@ -116,9 +117,10 @@ impl SigQueues {
 
             let queue = self.get_rt_queue_mut(signum);
             let signal = queue.pop_front();
-            if signal.is_some() {
+            if let Some(signal) = signal {
                 self.count -= 1;
-                return signal;
+                signal.on_dequeued();
+                return Some(signal);
             }
         }
 
@@ -17,4 +17,8 @@ pub trait Signal: Send + Sync + Debug {
 
     /// Returns the siginfo_t that gives more details about a signal.
     fn to_info(&self) -> siginfo_t;
+
+    /// Called when the signal is dequeued to be delivered, which is the time
+    /// for a signal to take a snapshot of the details it reports.
+    fn on_dequeued(&self) {}
 }
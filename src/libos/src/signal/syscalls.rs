@@ -80,6 +80,11 @@ pub fn do_tgkill(pid: i32, tid: pid_t, sig: c_int) -> Result<isize> {
     Ok(0)
 }
 
+pub fn do_pause() -> Result<isize> {
+    super::do_pause::do_pause()?;
+    Ok(0)
+}
+
 pub fn do_rt_sigprocmask(
     how: c_int,
     set_ptr: *const sigset_t,
@@ -16,51 +16,68 @@ use std::ffi::{CStr, CString};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem::MaybeUninit;
 use std::ptr;
+use time::itimer::{itimerval_t, ITimerType};
+use time::posix_timer::timer_t;
 use time::{clockid_t, itimerspec_t, timespec_t, timeval_t};
 use util::log::{self, LevelFilter};
 use util::mem_util::from_user::*;
 
 use crate::config::user_rootfs_config;
 use crate::exception::do_handle_exception;
+use crate::fs::aio::{aio_context_t, io_event_t, iocb_t};
+use crate::fs::io_uring::io_uring_params;
 use crate::fs::{
     do_access, do_chdir, do_chmod, do_chown, do_close, do_creat, do_dup, do_dup2, do_dup3,
     do_eventfd, do_eventfd2, do_faccessat, do_fallocate, do_fchdir, do_fchmod, do_fchmodat,
-    do_fchown, do_fchownat, do_fcntl, do_fdatasync, do_flock, do_fstat, do_fstatat, do_fstatfs,
-    do_fsync, do_ftruncate, do_futimesat, do_getcwd, do_getdents, do_getdents64, do_ioctl,
-    do_lchown, do_link, do_linkat, do_lseek, do_lstat, do_mkdir, do_mkdirat, do_mount,
-    do_mount_rootfs, do_open, do_openat, do_pipe, do_pipe2, do_pread, do_preadv, do_pwrite,
-    do_pwritev, do_read, do_readlink, do_readlinkat, do_readv, do_rename, do_renameat, do_rmdir,
-    do_sendfile, do_stat, do_statfs, do_symlink, do_symlinkat, do_sync, do_timerfd_create,
-    do_timerfd_gettime, do_timerfd_settime, do_truncate, do_umask, do_umount, do_unlink,
-    do_unlinkat, do_utime, do_utimensat, do_utimes, do_write, do_writev, iovec_t, utimbuf_t,
-    AsTimer, File, FileDesc, FileRef, HostStdioFds, Stat, Statfs,
+    do_fchown, do_fchownat, do_fcntl, do_fdatasync, do_fgetxattr, do_flistxattr, do_flock,
+    do_fremovexattr, do_fsetxattr, do_fstat, do_fstatat, do_fstatfs, do_fsync, do_ftruncate,
+    do_futimesat, do_getcwd, do_getdents, do_getdents64, do_getxattr, do_inotify_add_watch,
+    do_inotify_init, do_inotify_init1, do_inotify_rm_watch, do_io_cancel, do_io_destroy,
+    do_io_getevents, do_io_setup, do_io_submit, do_io_uring_enter, do_io_uring_register,
+    do_io_uring_setup, do_ioctl, do_lchown, do_lgetxattr, do_link, do_linkat, do_listxattr,
+    do_llistxattr, do_lremovexattr, do_lseek, do_lsetxattr, do_lstat, do_memfd_create, do_mkdir,
+    do_mkdirat, do_mount, do_mount_rootfs, do_mq_getsetattr, do_mq_notify, do_mq_open,
+    do_mq_timedreceive, do_mq_timedsend, do_mq_unlink, do_open, do_openat, do_pipe, do_pipe2,
+    do_pread, do_preadv, do_pwrite, do_pwritev, do_read, do_readlink, do_readlinkat, do_readv,
+    do_removexattr, do_rename, do_renameat, do_rmdir, do_sendfile, do_setxattr, do_signalfd,
+    do_signalfd4, do_splice, do_stat, do_statfs, do_symlink, do_symlinkat, do_sync, do_tee,
+    do_timerfd_create, do_timerfd_gettime, do_timerfd_settime, do_truncate, do_umask, do_umount,
+    do_unlink, do_unlinkat, do_utime, do_utimensat, do_utimes, do_vmsplice, do_write, do_writev,
+    iovec_t, mq_attr_t, utimbuf_t, AsTimer, File, FileDesc, FileRef, HostStdioFds, Stat, Statfs,
 };
 use crate::interrupt::{do_handle_interrupt, sgx_interrupt_info_t};
-use crate::ipc::{do_shmat, do_shmctl, do_shmdt, do_shmget, key_t, shmids_t};
+use crate::ipc::{
+    do_msgctl, do_msgget, do_msgrcv, do_msgsnd, do_semctl, do_semget, do_semop, do_semtimedop,
+    do_shmat, do_shmctl, do_shmdt, do_shmget, key_t, msqid_ds_t, sembuf_t, shmids_t,
+};
 use crate::misc::{resource_t, rlimit_t, sysinfo_t, utsname_t, RandFlags};
 use crate::net::{
     do_accept, do_accept4, do_bind, do_connect, do_epoll_create, do_epoll_create1, do_epoll_ctl,
     do_epoll_pwait, do_epoll_wait, do_getpeername, do_getsockname, do_getsockopt, do_listen,
-    do_poll, do_ppoll, do_recvfrom, do_recvmsg, do_select, do_sendmmsg, do_sendmsg, do_sendto,
-    do_setsockopt, do_shutdown, do_socket, do_socketpair, mmsghdr, msghdr, msghdr_mut,
+    do_poll, do_ppoll, do_pselect6, do_recvfrom, do_recvmmsg, do_recvmsg, do_select, do_sendmmsg,
+    do_sendmsg, do_sendto, do_setsockopt, do_shutdown, do_socket, do_socketpair, mmsghdr,
+    mmsghdr_mut, msghdr, msghdr_mut, sigset_argpack_t,
 };
+use crate::process::rusage::rusage_t;
 use crate::process::{
     do_arch_prctl, do_clone, do_execve, do_exit, do_exit_group, do_futex, do_get_robust_list,
     do_getegid, do_geteuid, do_getgid, do_getgroups, do_getpgid, do_getpgrp, do_getpid, do_getppid,
-    do_gettid, do_getuid, do_prctl, do_set_robust_list, do_set_tid_address, do_setpgid,
-    do_spawn_for_glibc, do_spawn_for_musl, do_vfork, do_wait4, pid_t, posix_spawnattr_t, FdOp,
-    RobustListHead, SpawnFileActions, ThreadStatus,
+    do_getrusage, do_gettid, do_getuid, do_prctl, do_set_robust_list, do_set_tid_address,
+    do_setpgid, do_spawn_for_glibc, do_spawn_for_musl, do_times, do_vfork, do_wait4, pid_t,
+    posix_spawnattr_t, tms_t, FdOp, RobustListHead, SpawnFileActions, ThreadStatus,
 };
 use crate::sched::{
     do_get_priority, do_getcpu, do_sched_getaffinity, do_sched_setaffinity, do_sched_yield,
     do_set_priority,
 };
 use crate::signal::{
-    do_kill, do_rt_sigaction, do_rt_sigpending, do_rt_sigprocmask, do_rt_sigreturn,
-    do_rt_sigtimedwait, do_sigaltstack, do_tgkill, do_tkill, sigaction_t, siginfo_t, sigset_t,
-    stack_t,
+    do_kill, do_pause, do_rt_sigaction, do_rt_sigpending, do_rt_sigprocmask, do_rt_sigreturn,
+    do_rt_sigtimedwait, do_sigaltstack, do_tgkill, do_tkill, sigaction_t, sigevent_t, siginfo_t,
+    sigset_t, stack_t,
+};
+use crate::vm::{
+    MAdvice, MLockAllFlags, MLockFlags, MMapFlags, MRemapFlags, MSyncFlags, VMPerms, PAGE_SIZE,
 };
-use crate::vm::{MMapFlags, MRemapFlags, MSyncFlags, VMPerms};
 use crate::{fs, process, std, vm};
 
 use super::*;
@@ -121,18 +138,18 @@ macro_rules! process_syscall_table_with_callback {
             (SchedYield = 24) => do_sched_yield(),
             (Mremap = 25) => do_mremap(old_addr: usize, old_size: usize, new_size: usize, flags: i32, new_addr: usize),
             (Msync = 26) => do_msync(addr: usize, size: usize, flags: u32),
-            (Mincore = 27) => handle_unsupported(),
-            (Madvise = 28) => handle_unsupported(),
+            (Mincore = 27) => do_mincore(addr: usize, size: usize, vec: *mut u8),
+            (Madvise = 28) => do_madvise(addr: usize, size: usize, advice: i32),
             (Shmget = 29) => do_shmget(key: key_t, size: size_t, shmflg: i32),
             (Shmat = 30) => do_shmat(shmid: i32, shmaddr: usize, shmflg: i32),
             (Shmctl = 31) => do_shmctl(shmid: i32, cmd: i32, buf: *mut shmids_t),
             (Dup = 32) => do_dup(old_fd: FileDesc),
             (Dup2 = 33) => do_dup2(old_fd: FileDesc, new_fd: FileDesc),
-            (Pause = 34) => handle_unsupported(),
+            (Pause = 34) => do_pause(),
             (Nanosleep = 35) => do_nanosleep(req_u: *const timespec_t, rem_u: *mut timespec_t),
-            (Getitimer = 36) => handle_unsupported(),
-            (Alarm = 37) => handle_unsupported(),
-            (Setitimer = 38) => handle_unsupported(),
+            (Getitimer = 36) => do_getitimer(which: i32, curr_value_u: *mut itimerval_t),
+            (Alarm = 37) => do_alarm(seconds: u32),
+            (Setitimer = 38) => do_setitimer(which: i32, new_value_u: *const itimerval_t, old_value_u: *mut itimerval_t),
             (Getpid = 39) => do_getpid(),
             (Sendfile = 40) => do_sendfile(out_fd: FileDesc, in_fd: FileDesc, offset_ptr: *mut off_t, count: usize),
             (Socket = 41) => do_socket(domain: c_int, socket_type: c_int, protocol: c_int),
@@ -158,14 +175,14 @@ macro_rules! process_syscall_table_with_callback {
             (Wait4 = 61) => do_wait4(pid: i32, _exit_status: *mut i32, options: u32),
             (Kill = 62) => do_kill(pid: i32, sig: c_int),
             (Uname = 63) => do_uname(name: *mut utsname_t),
-            (Semget = 64) => handle_unsupported(),
-            (Semop = 65) => handle_unsupported(),
-            (Semctl = 66) => handle_unsupported(),
+            (Semget = 64) => do_semget(key: key_t, nsems: i32, semflg: i32),
+            (Semop = 65) => do_semop(semid: i32, sops: *const sembuf_t, nsops: size_t),
+            (Semctl = 66) => do_semctl(semid: i32, semnum: i32, cmd: i32, arg: u64),
             (Shmdt = 67) => do_shmdt(shmaddr: usize),
-            (Msgget = 68) => handle_unsupported(),
-            (Msgsnd = 69) => handle_unsupported(),
-            (Msgrcv = 70) => handle_unsupported(),
-            (Msgctl = 71) => handle_unsupported(),
+            (Msgget = 68) => do_msgget(key: key_t, msgflg: i32),
+            (Msgsnd = 69) => do_msgsnd(msqid: i32, msgp: *const u8, msgsz: size_t, msgflg: i32),
+            (Msgrcv = 70) => do_msgrcv(msqid: i32, msgp: *mut u8, msgsz: size_t, msgtyp: i64, msgflg: i32),
+            (Msgctl = 71) => do_msgctl(msqid: i32, cmd: i32, buf: *mut msqid_ds_t),
             (Fcntl = 72) => do_fcntl(fd: FileDesc, cmd: u32, arg: u64),
             (Flock = 73) => do_flock(fd: FileDesc, operation: i32),
             (Fsync = 74) => do_fsync(fd: FileDesc),
@@ -192,9 +209,9 @@ macro_rules! process_syscall_table_with_callback {
             (Umask = 95) => do_umask(mask: u16),
             (Gettimeofday = 96) => do_gettimeofday(tv_u: *mut timeval_t),
             (Getrlimit = 97) => do_gettrlimit(resource: u32, rlim: *mut rlimit_t),
-            (Getrusage = 98) => handle_unsupported(),
+            (Getrusage = 98) => do_getrusage(who: i32, usage: *mut rusage_t),
             (SysInfo = 99) => do_sysinfo(info: *mut sysinfo_t),
-            (Times = 100) => handle_unsupported(),
+            (Times = 100) => do_times(buf: *mut tms_t),
             (Ptrace = 101) => handle_unsupported(),
             (Getuid = 102) => do_getuid(),
             (SysLog = 103) => handle_unsupported(),
@@ -243,10 +260,10 @@ macro_rules! process_syscall_table_with_callback {
             (SchedGetPriorityMax = 146) => handle_unsupported(),
             (SchedGetPriorityMin = 147) => handle_unsupported(),
             (SchedRrGetInterval = 148) => handle_unsupported(),
-            (Mlock = 149) => handle_unsupported(),
-            (Munlock = 150) => handle_unsupported(),
-            (Mlockall = 151) => handle_unsupported(),
-            (Munlockall = 152) => handle_unsupported(),
+            (Mlock = 149) => do_mlock(addr: usize, size: usize),
+            (Munlock = 150) => do_munlock(addr: usize, size: usize),
+            (Mlockall = 151) => do_mlockall(flags: u32),
+            (Munlockall = 152) => do_munlockall(),
             (Vhangup = 153) => handle_unsupported(),
             (ModifyLdt = 154) => handle_unsupported(),
             (PivotRoot = 155) => handle_unsupported(),
@@ -282,29 +299,29 @@ macro_rules! process_syscall_table_with_callback {
             (Security = 185) => handle_unsupported(),
             (Gettid = 186) => do_gettid(),
             (Readahead = 187) => handle_unsupported(),
-            (Setxattr = 188) => handle_unsupported(),
-            (Lsetxattr = 189) => handle_unsupported(),
-            (Fsetxattr = 190) => handle_unsupported(),
-            (Getxattr = 191) => handle_unsupported(),
-            (Lgetxattr = 192) => handle_unsupported(),
-            (Fgetxattr = 193) => handle_unsupported(),
-            (Listxattr = 194) => handle_unsupported(),
-            (Llistxattr = 195) => handle_unsupported(),
-            (Flistxattr = 196) => handle_unsupported(),
-            (Removexattr = 197) => handle_unsupported(),
-            (Lremovexattr = 198) => handle_unsupported(),
-            (Fremovexattr = 199) => handle_unsupported(),
+            (Setxattr = 188) => do_setxattr(path: *const i8, name: *const i8, value: *const u8, size: usize, flags: i32),
+            (Lsetxattr = 189) => do_lsetxattr(path: *const i8, name: *const i8, value: *const u8, size: usize, flags: i32),
+            (Fsetxattr = 190) => do_fsetxattr(fd: FileDesc, name: *const i8, value: *const u8, size: usize, flags: i32),
+            (Getxattr = 191) => do_getxattr(path: *const i8, name: *const i8, value: *mut u8, size: usize),
+            (Lgetxattr = 192) => do_lgetxattr(path: *const i8, name: *const i8, value: *mut u8, size: usize),
+            (Fgetxattr = 193) => do_fgetxattr(fd: FileDesc, name: *const i8, value: *mut u8, size: usize),
+            (Listxattr = 194) => do_listxattr(path: *const i8, list: *mut u8, size: usize),
+            (Llistxattr = 195) => do_llistxattr(path: *const i8, list: *mut u8, size: usize),
+            (Flistxattr = 196) => do_flistxattr(fd: FileDesc, list: *mut u8, size: usize),
+            (Removexattr = 197) => do_removexattr(path: *const i8, name: *const i8),
+            (Lremovexattr = 198) => do_lremovexattr(path: *const i8, name: *const i8),
+            (Fremovexattr = 199) => do_fremovexattr(fd: FileDesc, name: *const i8),
             (Tkill = 200) => do_tkill(tid: pid_t, sig: c_int),
             (Time = 201) => do_time(tloc_u: *mut time_t),
             (Futex = 202) => do_futex(futex_addr: *const i32, futex_op: u32, futex_val: i32, timeout: u64, futex_new_addr: *const i32, bitset: u32),
             (SchedSetaffinity = 203) => do_sched_setaffinity(pid: pid_t, cpusize: size_t, buf: *const c_uchar),
             (SchedGetaffinity = 204) => do_sched_getaffinity(pid: pid_t, cpusize: size_t, buf: *mut c_uchar),
             (SetThreadArea = 205) => handle_unsupported(),
-            (IoSetup = 206) => handle_unsupported(),
-            (IoDestroy = 207) => handle_unsupported(),
-            (IoGetevents = 208) => handle_unsupported(),
-            (IoSubmit = 209) => handle_unsupported(),
-            (IoCancel = 210) => handle_unsupported(),
+            (IoSetup = 206) => do_io_setup(nr_events: u32, ctx_ptr: *mut aio_context_t),
+            (IoDestroy = 207) => do_io_destroy(ctx_id: aio_context_t),
+            (IoGetevents = 208) => do_io_getevents(ctx_id: aio_context_t, min_nr: i64, nr: i64, events_ptr: *mut io_event_t, timeout_ptr: *const timespec_t),
+            (IoSubmit = 209) => do_io_submit(ctx_id: aio_context_t, nr: i64, iocbpp: *const *const iocb_t),
+            (IoCancel = 210) => do_io_cancel(ctx_id: aio_context_t, iocb_ptr: *const iocb_t, result: *mut io_event_t),
             (GetThreadArea = 211) => handle_unsupported(),
             (LookupDcookie = 212) => handle_unsupported(),
             (EpollCreate = 213) => do_epoll_create(size: c_int),
@@ -314,13 +331,13 @@ macro_rules! process_syscall_table_with_callback {
             (Getdents64 = 217) => do_getdents64(fd: FileDesc, buf: *mut u8, buf_size: usize),
             (SetTidAddress = 218) => do_set_tid_address(tidptr: *mut pid_t),
             (RestartSysCall = 219) => handle_unsupported(),
-            (Semtimedop = 220) => handle_unsupported(),
+            (Semtimedop = 220) => do_semtimedop(semid: i32, sops: *const sembuf_t, nsops: size_t, timeout: *const timespec_t),
             (Fadvise64 = 221) => handle_unsupported(),
-            (TimerCreate = 222) => handle_unsupported(),
-            (TimerSettime = 223) => handle_unsupported(),
-            (TimerGettime = 224) => handle_unsupported(),
-            (TimerGetoverrun = 225) => handle_unsupported(),
-            (TimerDelete = 226) => handle_unsupported(),
+            (TimerCreate = 222) => do_timer_create(clockid: clockid_t, sevp: *const sigevent_t, timerid_u: *mut timer_t),
+            (TimerSettime = 223) => do_timer_settime(timerid: timer_t, flags: i32, new_value_u: *const itimerspec_t, old_value_u: *mut itimerspec_t),
+            (TimerGettime = 224) => do_timer_gettime(timerid: timer_t, curr_value_u: *mut itimerspec_t),
+            (TimerGetoverrun = 225) => do_timer_getoverrun(timerid: timer_t),
+            (TimerDelete = 226) => do_timer_delete(timerid: timer_t),
             (ClockSettime = 227) => handle_unsupported(),
             (ClockGettime = 228) => do_clock_gettime(clockid: clockid_t, ts_u: *mut timespec_t),
             (ClockGetres = 229) => do_clock_getres(clockid: clockid_t, res_u: *mut timespec_t),
@@ -334,12 +351,12 @@ macro_rules! process_syscall_table_with_callback {
             (Mbind = 237) => handle_unsupported(),
             (SetMempolicy = 238) => handle_unsupported(),
             (GetMempolicy = 239) => handle_unsupported(),
-            (MqOpen = 240) => handle_unsupported(),
-            (MqUnlink = 241) => handle_unsupported(),
-            (MqTimedsend = 242) => handle_unsupported(),
-            (MqTimedreceive = 243) => handle_unsupported(),
-            (MqNotify = 244) => handle_unsupported(),
-            (MqGetsetattr = 245) => handle_unsupported(),
+            (MqOpen = 240) => do_mq_open(name: *const i8, oflag: u32, mode: u16, attr: *const mq_attr_t),
+            (MqUnlink = 241) => do_mq_unlink(name: *const i8),
+            (MqTimedsend = 242) => do_mq_timedsend(mqdes: FileDesc, msg_ptr: *const u8, msg_len: usize, msg_prio: u32, abs_timeout: *const timespec_t),
+            (MqTimedreceive = 243) => do_mq_timedreceive(mqdes: FileDesc, msg_ptr: *mut u8, msg_len: usize, msg_prio: *mut u32, abs_timeout: *const timespec_t),
+            (MqNotify = 244) => do_mq_notify(mqdes: FileDesc, sevp: *const sigevent_t),
+            (MqGetsetattr = 245) => do_mq_getsetattr(mqdes: FileDesc, new_attr: *const mq_attr_t, old_attr: *mut mq_attr_t),
             (KexecLoad = 246) => handle_unsupported(),
             (Waitid = 247) => handle_unsupported(),
             (AddKey = 248) => handle_unsupported(),
@@ -347,9 +364,9 @@ macro_rules! process_syscall_table_with_callback {
             (Keyctl = 250) => handle_unsupported(),
             (IoprioSet = 251) => handle_unsupported(),
             (IoprioGet = 252) => handle_unsupported(),
-            (InotifyInit = 253) => handle_unsupported(),
-            (InotifyAddWatch = 254) => handle_unsupported(),
-            (InotifyRmWatch = 255) => handle_unsupported(),
+            (InotifyInit = 253) => do_inotify_init(),
+            (InotifyAddWatch = 254) => do_inotify_add_watch(fd: FileDesc, path: *const i8, mask: u32),
+            (InotifyRmWatch = 255) => do_inotify_rm_watch(fd: FileDesc, wd: i32),
             (MigratePages = 256) => handle_unsupported(),
             (Openat = 257) => do_openat(dirfd: i32, path: *const i8, flags: u32, mode: u16),
             (Mkdirat = 258) => do_mkdirat(dirfd: i32, path: *const i8, mode: u16),
@@ -364,36 +381,36 @@ macro_rules! process_syscall_table_with_callback {
             (Readlinkat = 267) => do_readlinkat(dirfd: i32, path: *const i8, buf: *mut u8, size: usize),
             (Fchmodat = 268) => do_fchmodat(dirfd: i32, path: *const i8, mode: u16),
             (Faccessat = 269) => do_faccessat(dirfd: i32, path: *const i8, mode: u32, flags: u32),
-            (Pselect6 = 270) => handle_unsupported(),
-            (Ppoll = 271) => do_ppoll(fds: *mut libc::pollfd, nfds: libc::nfds_t, timeout_ts: *const timespec_t, sigmask: *const sigset_t),
+            (Pselect6 = 270) => do_pselect6(nfds: c_int, readfds: *mut libc::fd_set, writefds: *mut libc::fd_set, exceptfds: *mut libc::fd_set, timeout: *mut timespec_t, sig_data: *const sigset_argpack_t),
+            (Ppoll = 271) => do_ppoll(fds: *mut libc::pollfd, nfds: libc::nfds_t, timeout_ts: *const timespec_t, sigmask: *const sigset_t, sigset_size: size_t),
             (Unshare = 272) => handle_unsupported(),
             (SetRobustList = 273) => do_set_robust_list(list_head_ptr: *mut RobustListHead, len: usize),
             (GetRobustList = 274) => do_get_robust_list(tid: pid_t, list_head_ptr_ptr: *mut *mut RobustListHead, len_ptr: *mut usize),
-            (Splice = 275) => handle_unsupported(),
-            (Tee = 276) => handle_unsupported(),
+            (Splice = 275) => do_splice(fd_in: FileDesc, off_in_ptr: *mut off_t, fd_out: FileDesc, off_out_ptr: *mut off_t, len: usize, flags: u32),
+            (Tee = 276) => do_tee(fd_in: FileDesc, fd_out: FileDesc, len: usize, flags: u32),
             (SyncFileRange = 277) => handle_unsupported(),
-            (Vmsplice = 278) => handle_unsupported(),
+            (Vmsplice = 278) => do_vmsplice(fd: FileDesc, iov_ptr: *const iovec_t, nr_segs: usize, flags: u32),
             (MovePages = 279) => handle_unsupported(),
             (Utimensat = 280) => do_utimensat(dirfd: i32, path: *const i8, times: *const timespec_t, flags: i32),
-            (EpollPwait = 281) => do_epoll_pwait(epfd: c_int, events: *mut libc::epoll_event, maxevents: c_int, timeout: c_int, sigmask: *const usize),
-            (Signalfd = 282) => handle_unsupported(),
+            (EpollPwait = 281) => do_epoll_pwait(epfd: c_int, events: *mut libc::epoll_event, maxevents: c_int, timeout: c_int, sigmask: *const sigset_t, sigset_size: size_t),
+            (Signalfd = 282) => do_signalfd(fd: c_int, mask: *const sigset_t, sizemask: usize),
             (TimerfdCreate = 283) => do_timerfd_create(clockid: clockid_t, flags: i32 ),
             (Eventfd = 284) => do_eventfd(init_val: u32),
             (Fallocate = 285) => do_fallocate(fd: FileDesc, mode: u32, offset: off_t, len: off_t),
             (TimerfdSettime = 286) => do_timerfd_settime(fd: FileDesc, flags: i32, new_value: *const itimerspec_t, old_value: *mut itimerspec_t),
             (TimerfdGettime = 287) => do_timerfd_gettime(fd: FileDesc, curr_value: *mut itimerspec_t),
             (Accept4 = 288) => do_accept4(fd: c_int, addr: *mut libc::sockaddr, addr_len: *mut libc::socklen_t, flags: c_int),
-            (Signalfd4 = 289) => handle_unsupported(),
+            (Signalfd4 = 289) => do_signalfd4(fd: c_int, mask: *const sigset_t, sizemask: usize, flags: i32),
             (Eventfd2 = 290) => do_eventfd2(init_val: u32, flags: i32),
             (EpollCreate1 = 291) => do_epoll_create1(flags: c_int),
             (Dup3 = 292) => do_dup3(old_fd: FileDesc, new_fd: FileDesc, flags: u32),
             (Pipe2 = 293) => do_pipe2(fds_u: *mut i32, flags: u32),
-            (InotifyInit1 = 294) => handle_unsupported(),
+            (InotifyInit1 = 294) => do_inotify_init1(flags: i32),
             (Preadv = 295) => do_preadv(fd: FileDesc, iov: *mut iovec_t, count: i32, offset: off_t),
             (Pwritev = 296) => do_pwritev(fd: FileDesc, iov: *const iovec_t, count: i32, offset: off_t),
             (RtTgsigqueueinfo = 297) => handle_unsupported(),
             (PerfEventOpen = 298) => handle_unsupported(),
-            (Recvmmsg = 299) => handle_unsupported(),
+            (Recvmmsg = 299) => do_recvmmsg(fd: c_int, msg_ptr: *mut mmsghdr_mut, vlen: c_uint, flags_c: c_int, timeout: *mut timespec_t),
             (FanotifyInit = 300) => handle_unsupported(),
             (FanotifyMark = 301) => handle_unsupported(),
             (Prlimit64 = 302) => do_prlimit(pid: pid_t, resource: u32, new_limit: *const rlimit_t, old_limit: *mut rlimit_t),
@@ -413,13 +430,16 @@ macro_rules! process_syscall_table_with_callback {
             (Renameat2 = 316) => handle_unsupported(),
             (Seccomp = 317) => handle_unsupported(),
             (Getrandom = 318) => do_getrandom(buf: *mut u8, len: size_t, flags: u32),
-            (MemfdCreate = 319) => handle_unsupported(),
+            (MemfdCreate = 319) => do_memfd_create(name: *const i8, flags: u32),
             (KexecFileLoad = 320) => handle_unsupported(),
             (Bpf = 321) => handle_unsupported(),
             (Execveat = 322) => handle_unsupported(),
             (Userfaultfd = 323) => handle_unsupported(),
             (Membarrier = 324) => handle_unsupported(),
-            (Mlock2 = 325) => handle_unsupported(),
+            (Mlock2 = 325) => do_mlock2(addr: usize, size: usize, flags: u32),
+            (IoUringSetup = 425) => do_io_uring_setup(entries: u32, params_ptr: *mut io_uring_params),
+            (IoUringEnter = 426) => do_io_uring_enter(fd: u32, to_submit: u32, min_complete: u32, flags: u32, sig_mask: *const sigset_t, sigset_size: size_t),
+            (IoUringRegister = 427) => do_io_uring_register(fd: u32, opcode: u32, arg: *const c_void, nr_args: u32),
 
             // Occlum-specific system calls
             (SpawnGlibc = 359) => do_spawn_for_glibc(child_pid_ptr: *mut u32, path: *const i8, argv: *const *const i8, envp: *const *const i8, fa: *const SpawnFileActions, attribute_list: *const posix_spawnattr_t),
@@ -619,6 +639,8 @@ pub extern "C" fn occlum_syscall(user_context: *mut CpuContext) -> ! {
 }
 
 fn do_syscall(user_context: &mut CpuContext) {
+    current!().usage().enter_libos();
+
     // Extract arguments from the CPU context. The arguments follows Linux's syscall ABI.
     let num = user_context.rax as u32;
     let arg0 = user_context.rdi as isize;
@@ -734,7 +756,7 @@ fn do_syscall(user_context: &mut CpuContext) {
 
     crate::signal::deliver_signal(user_context);
 
-    crate::process::handle_force_stop();
+    crate::process::handle_force_stop(user_context);
 
     crate::process::handle_force_exit();
 }
@@ -751,6 +773,7 @@ fn do_sysret(user_context: &mut CpuContext) -> ! {
         fn do_exit_task() -> !;
     }
     if current!().status() != ThreadStatus::Exited {
+        current!().usage().exit_libos();
         // Restore the floating point registers
         // Todo: Is it correct to do fxstor in kernel?
         let fpregs = user_context.fpregs;
@@ -828,6 +851,56 @@ fn do_msync(addr: usize, size: usize, flags: u32) -> Result<isize> {
     Ok(0)
 }
 
+fn do_mincore(addr: usize, size: usize, vec: *mut u8) -> Result<isize> {
+    let vec = {
+        let len = size
+            .checked_add(PAGE_SIZE - 1)
+            .ok_or_else(|| errno!(ENOMEM, "the size is too large"))?
+            / PAGE_SIZE;
+        if len == 0 {
+            &mut []
+        } else {
+            check_mut_array(vec, len)?;
+            unsafe { std::slice::from_raw_parts_mut(vec, len) }
+        }
+    };
+    vm::do_mincore(addr, size, vec)?;
+    Ok(0)
+}
+
+fn do_madvise(addr: usize, size: usize, advice: i32) -> Result<isize> {
+    let advice = MAdvice::from_i32(advice)?;
+    vm::do_madvise(addr, size, advice)?;
+    Ok(0)
+}
+
+fn do_mlock(addr: usize, size: usize) -> Result<isize> {
+    vm::do_mlock(addr, size, MLockFlags::empty())?;
+    Ok(0)
+}
+
+fn do_mlock2(addr: usize, size: usize, flags: u32) -> Result<isize> {
+    let flags = MLockFlags::from_u32(flags)?;
+    vm::do_mlock(addr, size, flags)?;
+    Ok(0)
+}
+
+fn do_munlock(addr: usize, size: usize) -> Result<isize> {
+    vm::do_munlock(addr, size)?;
+    Ok(0)
+}
+
+fn do_mlockall(flags: u32) -> Result<isize> {
+    let flags = MLockAllFlags::from_u32(flags)?;
+    vm::do_mlockall(flags)?;
+    Ok(0)
+}
+
+fn do_munlockall() -> Result<isize> {
+    vm::do_munlockall()?;
+    Ok(0)
+}
+
 fn do_sysinfo(info: *mut sysinfo_t) -> Result<isize> {
     check_mut_ptr(info)?;
     let info = unsafe { &mut *info };
@@ -914,6 +987,107 @@ fn do_clock_nanosleep(
     Ok(0)
 }
 
+fn do_setitimer(
+    which: i32,
+    new_value_u: *const itimerval_t,
+    old_value_u: *mut itimerval_t,
+) -> Result<isize> {
+    // A null new_value is treated as a zero timer, as Linux does
+    let new_value = if !new_value_u.is_null() {
+        check_ptr(new_value_u)?;
+        itimerval_t::from_raw_ptr(new_value_u)?
+    } else {
+        itimerval_t::default()
+    };
+    if !old_value_u.is_null() {
+        check_mut_ptr(old_value_u)?;
+    }
+    let which = ITimerType::from_raw(which)?;
+    let old_value = time::itimer::do_setitimer(which, &new_value)?;
+    if !old_value_u.is_null() {
+        unsafe {
+            *old_value_u = old_value;
+        }
+    }
+    Ok(0)
+}
+
+fn do_getitimer(which: i32, curr_value_u: *mut itimerval_t) -> Result<isize> {
+    check_mut_ptr(curr_value_u)?;
+    let which = ITimerType::from_raw(which)?;
+    let curr_value = time::itimer::do_getitimer(which)?;
+    unsafe {
+        *curr_value_u = curr_value;
+    }
+    Ok(0)
+}
+
+fn do_alarm(seconds: u32) -> Result<isize> {
+    let remaining = time::itimer::do_alarm(seconds)?;
+    Ok(remaining as isize)
+}
+
+fn do_timer_create(
+    clockid: clockid_t,
+    sevp: *const sigevent_t,
+    timerid_u: *mut timer_t,
+) -> Result<isize> {
+    check_mut_ptr(timerid_u)?;
+    let sigevent = if !sevp.is_null() {
+        check_ptr(sevp)?;
+        Some(unsafe { &*sevp })
+    } else {
+        None
+    };
+    let clockid = time::ClockID::from_raw(clockid)?;
+    let timer_id = time::posix_timer::do_timer_create(clockid, sigevent)?;
+    unsafe {
+        *timerid_u = timer_id;
+    }
+    Ok(0)
+}
+
+fn do_timer_settime(
+    timerid: timer_t,
+    flags: i32,
+    new_value_u: *const itimerspec_t,
+    old_value_u: *mut itimerspec_t,
+) -> Result<isize> {
+    let new_value = {
+        check_ptr(new_value_u)?;
+        itimerspec_t::from_raw_ptr(new_value_u)?
+    };
+    if !old_value_u.is_null() {
+        check_mut_ptr(old_value_u)?;
+    }
+    let old_value = time::posix_timer::do_timer_settime(timerid, flags, &new_value)?;
+    if !old_value_u.is_null() {
+        unsafe {
+            *old_value_u = old_value;
+        }
+    }
+    Ok(0)
+}
+
+fn do_timer_gettime(timerid: timer_t, curr_value_u: *mut itimerspec_t) -> Result<isize> {
+    check_mut_ptr(curr_value_u)?;
+    let curr_value = time::posix_timer::do_timer_gettime(timerid)?;
+    unsafe {
+        *curr_value_u = curr_value;
+    }
+    Ok(0)
+}
+
+fn do_timer_getoverrun(timerid: timer_t) -> Result<isize> {
+    let overrun = time::posix_timer::do_timer_getoverrun(timerid)?;
+    Ok(overrun as isize)
+}
+
+fn do_timer_delete(timerid: timer_t) -> Result<isize> {
+    time::posix_timer::do_timer_delete(timerid)?;
+    Ok(0)
+}
+
 // TODO: handle remainder
 fn do_nanosleep(req_u: *const timespec_t, rem_u: *mut timespec_t) -> Result<isize> {
     let req = {
@@ -0,0 +1,116 @@
+//! The interval timers of setitimer(2) and alarm(2).
+//!
+//! Each process has one timer of each type, which are POSIX timers that send
+//! kernel signals to the process. ITIMER_VIRTUAL counts the user time of the
+//! process and ITIMER_PROF counts both the user and system time, as accounted
+//! for the threads of the process by the LibOS.
+
+use super::posix_timer::TimerClock;
+use super::*;
+use crate::signal::{SigNum, SIGALRM, SIGPROF, SIGVTALRM};
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct itimerval_t {
+    it_interval: timeval_t,
+    it_value: timeval_t,
+}
+
+impl itimerval_t {
+    pub fn from_raw_ptr(ptr: *const itimerval_t) -> Result<itimerval_t> {
+        let itv = unsafe { *ptr };
+        itv.it_interval.validate()?;
+        itv.it_value.validate()?;
+        Ok(itv)
+    }
+
+    fn to_itimerspec(&self) -> itimerspec_t {
+        itimerspec_t {
+            it_interval: timespec_t::from(self.it_interval),
+            it_value: timespec_t::from(self.it_value),
+        }
+    }
+
+    fn from_itimerspec(its: &itimerspec_t) -> Self {
+        let to_timeval = |ts: &timespec_t| -> timeval_t {
+            let tv = timeval_t::from(ts.as_duration());
+            // Do not report an armed timer as disarmed due to the truncation
+            if tv.sec() == 0 && tv.usec() == 0 && ts.nsec() > 0 {
+                timeval_t::new(0, 1)
+            } else {
+                tv
+            }
+        };
+        Self {
+            it_interval: to_timeval(&its.it_interval),
+            it_value: to_timeval(&its.it_value),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub enum ITimerType {
+    ITIMER_REAL = 0,
+    ITIMER_VIRTUAL = 1,
+    ITIMER_PROF = 2,
+}
+
+impl ITimerType {
+    pub fn from_raw(which: i32) -> Result<ITimerType> {
+        Ok(match which {
+            0 => ITimerType::ITIMER_REAL,
+            1 => ITimerType::ITIMER_VIRTUAL,
+            2 => ITimerType::ITIMER_PROF,
+            _ => return_errno!(EINVAL, "invalid interval timer type"),
+        })
+    }
+
+    pub fn clock(&self) -> TimerClock {
+        match self {
+            ITimerType::ITIMER_REAL => TimerClock::Host(ClockID::CLOCK_MONOTONIC),
+            ITimerType::ITIMER_VIRTUAL => TimerClock::ProcessUserTime,
+            ITimerType::ITIMER_PROF => TimerClock::ProcessCpuTime,
+        }
+    }
+
+    pub fn signum(&self) -> SigNum {
+        match self {
+            ITimerType::ITIMER_REAL => SIGALRM,
+            ITimerType::ITIMER_VIRTUAL => SIGVTALRM,
+            ITimerType::ITIMER_PROF => SIGPROF,
+        }
+    }
+}
+
+pub fn do_setitimer(which: ITimerType, new_value: &itimerval_t) -> Result<itimerval_t> {
+    debug!("setitimer: which: {:?}, new_value: {:?}", which, new_value);
+    let current = current!();
+    let itimer = current.process().posix_timers().itimer(which).clone();
+    let old_value = itimer.set_time(0, &new_value.to_itimerspec())?;
+    Ok(itimerval_t::from_itimerspec(&old_value))
+}
+
+pub fn do_getitimer(which: ITimerType) -> Result<itimerval_t> {
+    let current = current!();
+    let itimer = current.process().posix_timers().itimer(which).clone();
+    let curr_value = itimer.time()?;
+    Ok(itimerval_t::from_itimerspec(&curr_value))
+}
+
+/// Arrange for SIGALRM to be sent in the given seconds, returning the seconds
+/// remaining until the previously scheduled alarm.
+pub fn do_alarm(seconds: u32) -> Result<u32> {
+    let new_value = itimerval_t {
+        it_interval: timeval_t::default(),
+        it_value: timeval_t::new(seconds as time_t, 0),
+    };
+    let old_value = do_setitimer(ITimerType::ITIMER_REAL, &new_value)?.it_value;
+    // Round to the nearest second, but never report zero for a pending alarm
+    let mut remaining = old_value.sec() as u32;
+    if (remaining == 0 && old_value.usec() > 0) || old_value.usec() >= 500_000 {
+        remaining += 1;
+    }
+    Ok(remaining)
+}
@@ -8,6 +8,8 @@ use std::time::Duration;
 use std::{fmt, u64};
 use syscall::SyscallNum;
 
+pub mod itimer;
+pub mod posix_timer;
 mod profiler;
 pub mod timer_slack;
 pub mod up_time;
@@ -184,6 +186,20 @@ pub fn do_clock_gettime(clockid: ClockID) -> Result<timespec_t> {
         fn occlum_ocall_clock_gettime(clockid: clockid_t, tp: *mut timespec_t) -> sgx_status_t;
     }
 
+    // The CPU-time clocks of the host measure the whole enclave, not a LibOS
+    // process or thread
+    match clockid {
+        ClockID::CLOCK_PROCESS_CPUTIME_ID => {
+            let cputime = current!().process().resource_usage().cputime();
+            return Ok(timespec_t::from(cputime));
+        }
+        ClockID::CLOCK_THREAD_CPUTIME_ID => {
+            let cputime = current!().resource_usage().cputime();
+            return Ok(timespec_t::from(cputime));
+        }
+        _ => {}
+    }
+
     let mut tv: timespec_t = Default::default();
     unsafe {
         occlum_ocall_clock_gettime(clockid as clockid_t, &mut tv as *mut timespec_t);
@@ -246,9 +262,11 @@ pub fn do_clock_nanosleep(
             return_errno!(EOPNOTSUPP, "does not support sleeping against this clockid");
         }
     }
+    let sleep_guard = crate::process::rusage::SleepGuard::new();
     let sgx_status = unsafe {
         occlum_ocall_clock_nanosleep(&mut ret, clockid as clockid_t, flags, req, &mut u_rem)
     };
+    drop(sleep_guard);
     assert!(sgx_status == sgx_status_t::SGX_SUCCESS);
     assert!(ret == 0 || ret == Errno::EINTR as i32);
     if ret != 0 {
@@ -289,6 +307,28 @@ pub fn do_thread_getcpuclock() -> Result<timespec_t> {
     Ok(tv)
 }
 
+/// Get the CPU time consumed by a host thread, which may not be the current one.
+pub fn do_host_thread_cputime(host_tid: pid_t) -> Result<timespec_t> {
+    extern "C" {
+        fn occlum_ocall_get_thread_cputime(
+            ret: *mut c_int,
+            host_tid: pid_t,
+            tp: *mut timespec_t,
+        ) -> sgx_status_t;
+    }
+
+    let mut tv: timespec_t = Default::default();
+    try_libc!({
+        let mut retval: i32 = 0;
+        let status =
+            occlum_ocall_get_thread_cputime(&mut retval, host_tid, &mut tv as *mut timespec_t);
+        assert!(status == sgx_status_t::SGX_SUCCESS);
+        retval
+    });
+    tv.validate()?;
+    Ok(tv)
+}
+
 pub fn do_rdtsc() -> (u32, u32) {
     extern "C" {
         fn occlum_ocall_rdtsc(low: *mut u32, high: *mut u32) -> sgx_status_t;
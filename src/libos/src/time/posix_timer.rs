@@ -0,0 +1,490 @@
+//! POSIX per-process interval timers, i.e., timer_create(2) and its friends.
+//!
+//! The expirations of the timers are checked by the periodic tick driven by the
+//! interrupt thread outside the enclave. So the resolution of the timers is no
+//! finer than the interval of the tick. The timers of the CPU-time clocks are
+//! measured by the resource usage accounting of the LibOS processes and threads.
+
+use super::itimer::ITimerType;
+use super::*;
+use crate::process::{table, ProcessRef, ProcessStatus, Thread, ThreadStatus};
+use crate::signal::{
+    sigevent_t, siginfo_t, sigval_t, KernelSignal, SigNum, Signal, SIGALRM, SIGEV_NONE,
+    SIGEV_SIGNAL, SIGEV_THREAD, SIGEV_THREAD_ID, SI_TIMER,
+};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Weak;
+
+#[allow(non_camel_case_types)]
+pub type timer_t = i32;
+
+/// The maximum number of POSIX timers that a process can create
+const MAX_NUM_TIMERS: usize = 4096;
+
+/// The POSIX timers of a process, including the interval timers of setitimer
+#[derive(Debug)]
+pub struct PosixTimers {
+    inner: SgxMutex<PosixTimersInner>,
+    itimers: [Arc<PosixTimer>; 3],
+}
+
+#[derive(Debug)]
+struct PosixTimersInner {
+    timers: HashMap<timer_t, Arc<PosixTimer>>,
+    next_id: timer_t,
+}
+
+impl PosixTimers {
+    pub fn new() -> Self {
+        let inner = PosixTimersInner {
+            timers: HashMap::new(),
+            next_id: 0,
+        };
+        let itimers = [
+            ITimerType::ITIMER_REAL,
+            ITimerType::ITIMER_VIRTUAL,
+            ITimerType::ITIMER_PROF,
+        ]
+        .map(|which| {
+            let notify = TimerNotify::Kernel(which.signum());
+            Arc::new(PosixTimer::new(-1, which.clock(), notify))
+        });
+        Self {
+            inner: SgxMutex::new(inner),
+            itimers,
+        }
+    }
+
+    pub fn create(&self, clockid: ClockID, sigevent: Option<&sigevent_t>) -> Result<timer_t> {
+        let clock = match clockid {
+            ClockID::CLOCK_REALTIME | ClockID::CLOCK_MONOTONIC | ClockID::CLOCK_BOOTTIME => {
+                TimerClock::Host(clockid)
+            }
+            ClockID::CLOCK_PROCESS_CPUTIME_ID => TimerClock::ProcessCpuTime,
+            ClockID::CLOCK_THREAD_CPUTIME_ID => {
+                TimerClock::ThreadCpuTime(Arc::downgrade(&current!()))
+            }
+            _ => return_errno!(EINVAL, "unsupported clock for POSIX timers"),
+        };
+        let notify = sigevent.map(TimerNotify::from_sigevent).transpose()?;
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.timers.len() >= MAX_NUM_TIMERS {
+            return_errno!(EAGAIN, "too many timers");
+        }
+        // Find an unused ID, starting from the one next to the last allocated
+        let mut id = inner.next_id;
+        while inner.timers.contains_key(&id) {
+            id = id.checked_add(1).unwrap_or(0);
+        }
+        inner.next_id = id.checked_add(1).unwrap_or(0);
+
+        // By default, SIGALRM is sent to the process with the timer ID as the value
+        let notify = notify.unwrap_or(TimerNotify::Signal {
+            signum: SIGALRM,
+            value: sigval_t::from(id),
+            tid: None,
+        });
+        let timer = Arc::new(PosixTimer::new(id, clock, notify));
+        inner.timers.insert(id, timer);
+        Ok(id)
+    }
+
+    pub fn get(&self, id: timer_t) -> Result<Arc<PosixTimer>> {
+        self.inner
+            .lock()
+            .unwrap()
+            .timers
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| errno!(EINVAL, "invalid timer ID"))
+    }
+
+    pub fn delete(&self, id: timer_t) -> Result<()> {
+        let timer = self
+            .inner
+            .lock()
+            .unwrap()
+            .timers
+            .remove(&id)
+            .ok_or_else(|| errno!(EINVAL, "invalid timer ID"))?;
+        timer.disarm();
+        Ok(())
+    }
+
+    /// Get the interval timer of setitimer.
+    pub fn itimer(&self, which: ITimerType) -> &Arc<PosixTimer> {
+        &self.itimers[which as usize]
+    }
+
+    /// Delete all the timers, which is done when the process exits.
+    pub fn clear(&self) {
+        let timers: Vec<_> = self.inner.lock().unwrap().timers.drain().collect();
+        for (_, timer) in timers {
+            timer.disarm();
+        }
+        for itimer in &self.itimers {
+            itimer.disarm();
+        }
+    }
+
+    fn timers(&self) -> Vec<Arc<PosixTimer>> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .timers
+            .values()
+            .chain(self.itimers.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+/// The clock that a timer measures the time against
+#[derive(Debug, Clone)]
+pub enum TimerClock {
+    /// A clock of the host, e.g., CLOCK_MONOTONIC
+    Host(ClockID),
+    /// The CPU time consumed by all threads of the process
+    ProcessCpuTime,
+    /// The user CPU time consumed by all threads of the process
+    ProcessUserTime,
+    /// The CPU time consumed by a thread of the process
+    ThreadCpuTime(Weak<Thread>),
+}
+
+impl TimerClock {
+    fn now(&self, process: &ProcessRef) -> Result<Duration> {
+        let now = match self {
+            TimerClock::Host(clockid) => do_clock_gettime(*clockid)?.as_duration(),
+            TimerClock::ProcessCpuTime => process.resource_usage().cputime(),
+            TimerClock::ProcessUserTime => process.resource_usage().utime,
+            TimerClock::ThreadCpuTime(thread) => thread
+                .upgrade()
+                .ok_or_else(|| errno!(EINVAL, "the thread of the clock has exited"))?
+                .resource_usage()
+                .cputime(),
+        };
+        Ok(now)
+    }
+}
+
+/// How to notify the process when a timer expires
+#[derive(Debug, Clone, Copy)]
+enum TimerNotify {
+    None,
+    Signal {
+        signum: SigNum,
+        value: sigval_t,
+        // The thread to send the signal to, or the whole process if it is None
+        tid: Option<pid_t>,
+    },
+    // Send a kernel signal to the process, which is what the interval timers do
+    Kernel(SigNum),
+}
+
+unsafe impl Sync for TimerNotify {}
+unsafe impl Send for TimerNotify {}
+
+impl TimerNotify {
+    fn from_sigevent(sigevent: &sigevent_t) -> Result<Self> {
+        let signum = || -> Result<SigNum> {
+            u8::try_from(sigevent.sigev_signo)
+                .map_err(|_| errno!(EINVAL, "invalid signal number"))
+                .and_then(SigNum::from_u8)
+        };
+
+        let notify = match sigevent.sigev_notify {
+            SIGEV_NONE => TimerNotify::None,
+            SIGEV_SIGNAL => TimerNotify::Signal {
+                signum: signum()?,
+                value: sigevent.sigev_value,
+                tid: None,
+            },
+            SIGEV_THREAD_ID => {
+                let tid = sigevent.sigev_notify_thread_id();
+                let is_same_process = table::get_thread(tid)
+                    .map(|thread| thread.process().pid() == current!().process().pid())
+                    .unwrap_or(false);
+                if !is_same_process {
+                    return_errno!(EINVAL, "the thread is not in the calling process");
+                }
+                TimerNotify::Signal {
+                    signum: signum()?,
+                    value: sigevent.sigev_value,
+                    tid: Some(tid),
+                }
+            }
+            // The C library implements SIGEV_THREAD with SIGEV_THREAD_ID
+            SIGEV_THREAD => return_errno!(EINVAL, "SIGEV_THREAD is not supported"),
+            _ => return_errno!(EINVAL, "invalid notification method"),
+        };
+        Ok(notify)
+    }
+}
+
+#[derive(Debug)]
+pub struct PosixTimer {
+    id: timer_t,
+    clock: TimerClock,
+    notify: TimerNotify,
+    state: SgxMutex<TimerState>,
+}
+
+#[derive(Debug, Default)]
+struct TimerState {
+    // The next expiration time against the clock of the timer, or None if disarmed
+    expire_time: Option<Duration>,
+    interval: Duration,
+    // Whether the signal of an expiration is generated but not delivered yet
+    is_signal_pending: bool,
+    // The number of the expirations after the pending signal is generated
+    pending_overrun: i32,
+    // The overrun count of the last delivered signal
+    overrun: i32,
+}
+
+impl PosixTimer {
+    fn new(id: timer_t, clock: TimerClock, notify: TimerNotify) -> Self {
+        Self {
+            id,
+            clock,
+            notify,
+            state: SgxMutex::new(TimerState::default()),
+        }
+    }
+
+    pub fn id(&self) -> timer_t {
+        self.id
+    }
+
+    /// Arm or disarm the timer, returning the previous setting.
+    pub fn set_time(&self, flags: i32, new_value: &itimerspec_t) -> Result<itimerspec_t> {
+        if flags & !TIMER_ABSTIME != 0 {
+            return_errno!(EINVAL, "invalid flags");
+        }
+        let now = self.clock.now(current!().process())?;
+        let value = new_value.it_value.as_duration();
+
+        let mut state = self.state.lock().unwrap();
+        let old_value = state.time(now);
+        state.interval = new_value.it_interval.as_duration();
+        state.expire_time = if value == Duration::from_secs(0) {
+            None
+        } else if flags & TIMER_ABSTIME != 0 {
+            Some(value)
+        } else {
+            Some(now + value)
+        };
+        state.pending_overrun = 0;
+        Ok(old_value)
+    }
+
+    pub fn time(&self) -> Result<itimerspec_t> {
+        let now = self.clock.now(current!().process())?;
+        Ok(self.state.lock().unwrap().time(now))
+    }
+
+    pub fn overrun(&self) -> i32 {
+        self.state.lock().unwrap().overrun
+    }
+
+    fn is_armed(&self) -> bool {
+        self.state.lock().unwrap().expire_time.is_some()
+    }
+
+    fn disarm(&self) {
+        self.state.lock().unwrap().expire_time = None;
+    }
+
+    /// Update the timer if it expires at the given time, and return the signal
+    /// to send if there should be one.
+    fn expire(self: &Arc<Self>, now: Duration) -> Option<Box<dyn Signal>> {
+        let mut state = self.state.lock().unwrap();
+        let expire_time = match state.expire_time {
+            Some(expire_time) if expire_time <= now => expire_time,
+            _ => return None,
+        };
+
+        // A periodic timer may expire many times between two ticks
+        let num_expirations = if state.interval == Duration::from_secs(0) {
+            state.expire_time = None;
+            1
+        } else {
+            let interval_ns = state.interval.as_nanos();
+            let num_expirations = (now - expire_time).as_nanos() / interval_ns + 1;
+            let next_expire_ns = expire_time.as_nanos() + interval_ns * num_expirations;
+            state.expire_time = Some(Duration::from_nanos(next_expire_ns as u64));
+            num_expirations.min(i32::MAX as u128) as i32
+        };
+
+        match self.notify {
+            TimerNotify::None => return None,
+            // Kernel signals do not track overruns
+            TimerNotify::Kernel(signum) => return Some(Box::new(KernelSignal::new(signum))),
+            TimerNotify::Signal { .. } => {}
+        }
+        // Only one signal of a timer can be pending at a time, the other
+        // expirations are counted as overruns, which saturate at DELAYTIMER_MAX.
+        if state.is_signal_pending {
+            state.pending_overrun = state.pending_overrun.saturating_add(num_expirations);
+            return None;
+        }
+        state.is_signal_pending = true;
+        state.pending_overrun = num_expirations - 1;
+        Some(Box::new(TimerSignal {
+            timer: self.clone(),
+            overrun: AtomicI32::new(0),
+        }))
+    }
+
+    fn send_signal(&self, signal: Box<dyn Signal>, process: &ProcessRef) {
+        if let TimerNotify::Signal { tid: Some(tid), .. } = self.notify {
+            if let Ok(thread) = table::get_thread(tid) {
+                if thread.process().pid() == process.pid()
+                    && thread.status() != ThreadStatus::Exited
+                {
+                    thread.sig_queues().write().unwrap().enqueue(signal);
+                    return;
+                }
+            }
+        }
+        process.sig_queues().write().unwrap().enqueue(signal);
+    }
+}
+
+impl TimerState {
+    fn time(&self, now: Duration) -> itimerspec_t {
+        let remaining = match self.expire_time {
+            None => Duration::from_secs(0),
+            Some(expire_time) if expire_time > now => expire_time - now,
+            // The expiration is not handled by the tick yet
+            Some(expire_time) => {
+                if self.interval == Duration::from_secs(0) {
+                    Duration::from_nanos(1)
+                } else {
+                    let interval_ns = self.interval.as_nanos();
+                    let elapsed_ns = (now - expire_time).as_nanos() % interval_ns;
+                    Duration::from_nanos((interval_ns - elapsed_ns) as u64)
+                }
+            }
+        };
+        itimerspec_t {
+            it_interval: timespec_t::from(self.interval),
+            it_value: timespec_t::from(remaining),
+        }
+    }
+}
+
+/// The signal generated by the expiration of a POSIX timer
+#[derive(Debug)]
+struct TimerSignal {
+    timer: Arc<PosixTimer>,
+    // The overrun count taken when the signal is dequeued
+    overrun: AtomicI32,
+}
+
+impl Signal for TimerSignal {
+    fn num(&self) -> SigNum {
+        match self.timer.notify {
+            TimerNotify::Signal { signum, .. } => signum,
+            TimerNotify::None | TimerNotify::Kernel(_) => unreachable!(),
+        }
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        let mut info = siginfo_t::new(self.num(), SI_TIMER);
+        info.set_si_timerid(self.timer.id);
+        info.set_si_overrune(self.overrun.load(Ordering::Relaxed));
+        if let TimerNotify::Signal { value, .. } = self.timer.notify {
+            info.set_si_value(value);
+        }
+        info
+    }
+
+    fn on_dequeued(&self) {
+        // The overruns are taken into account when the signal is delivered
+        let mut state = self.timer.state.lock().unwrap();
+        state.overrun = state.pending_overrun;
+        state.pending_overrun = 0;
+        self.overrun.store(state.overrun, Ordering::Relaxed);
+    }
+}
+
+impl Drop for TimerSignal {
+    // The signal is dropped after being delivered, ignored or discarded. In all
+    // the cases, the timer can generate a new signal.
+    fn drop(&mut self) {
+        let mut state = self.timer.state.lock().unwrap();
+        state.is_signal_pending = false;
+        state.pending_overrun = 0;
+    }
+}
+
+/// Fire the expired POSIX timers of all processes.
+///
+/// This is called periodically by the interrupt thread.
+pub fn handle_expired_timers() {
+    // Read each host clock at most once
+    let mut clock_readings: [Option<Duration>; 8] = Default::default();
+
+    for process in table::get_all_processes() {
+        if process.status() == ProcessStatus::Zombie {
+            continue;
+        }
+        // Read the CPU times of the process at most once
+        let mut process_cputime = None;
+        let mut process_utime = None;
+        for timer in process.posix_timers().timers() {
+            if !timer.is_armed() {
+                continue;
+            }
+            let mut thread_cputime = None;
+            let reading = match timer.clock {
+                TimerClock::Host(clockid) => &mut clock_readings[clockid as usize],
+                TimerClock::ProcessCpuTime => &mut process_cputime,
+                TimerClock::ProcessUserTime => &mut process_utime,
+                TimerClock::ThreadCpuTime(_) => &mut thread_cputime,
+            };
+            let now = match *reading {
+                Some(now) => now,
+                None => match timer.clock.now(&process) {
+                    Ok(now) => *reading.insert(now),
+                    Err(_) => continue,
+                },
+            };
+            if let Some(signal) = timer.expire(now) {
+                timer.send_signal(signal, &process);
+            }
+        }
+    }
+}
+
+pub fn do_timer_create(clockid: ClockID, sigevent: Option<&sigevent_t>) -> Result<timer_t> {
+    current!()
+        .process()
+        .posix_timers()
+        .create(clockid, sigevent)
+}
+
+pub fn do_timer_settime(
+    timer_id: timer_t,
+    flags: i32,
+    new_value: &itimerspec_t,
+) -> Result<itimerspec_t> {
+    let timer = current!().process().posix_timers().get(timer_id)?;
+    timer.set_time(flags, new_value)
+}
+
+pub fn do_timer_gettime(timer_id: timer_t) -> Result<itimerspec_t> {
+    let timer = current!().process().posix_timers().get(timer_id)?;
+    timer.time()
+}
+
+pub fn do_timer_getoverrun(timer_id: timer_t) -> Result<i32> {
+    let timer = current!().process().posix_timers().get(timer_id)?;
+    Ok(timer.overrun())
+}
+
+pub fn do_timer_delete(timer_id: timer_t) -> Result<()> {
+    current!().process().posix_timers().delete(timer_id)
+}
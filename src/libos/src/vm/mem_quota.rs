@@ -0,0 +1,110 @@
+//! The memory quota of an app, which limits the total size of the memory mapped
+//! by all the processes of the app.
+//!
+//! An app consists of a process spawned by the idle process, e.g., by `occlum run`
+//! or `occlum exec`, and all its descendants. The quota is given by the path of the
+//! program in Occlum.json, or the default quota otherwise.
+//!
+//! The memory is charged to the quota when it is mapped and uncharged when it is
+//! unmapped. The memory shared by several processes is charged by each of them.
+
+use super::*;
+
+use crate::config::LIBOS_CONFIG;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub type MemQuotaRef = Arc<MemQuota>;
+
+#[derive(Debug)]
+pub struct MemQuota {
+    size: usize,
+    // The size of the memory charged by all the processes of the app
+    used: AtomicUsize,
+}
+
+impl MemQuota {
+    /// Get the quota of the app started by the program, if there is any.
+    pub fn for_app(path: &str) -> Option<MemQuotaRef> {
+        let config = &LIBOS_CONFIG.process;
+        let size = config
+            .mem_quotas
+            .get(path)
+            .copied()
+            .or(config.default_mem_quota)?;
+        let used = AtomicUsize::new(0);
+        Some(Arc::new(Self { size, used }))
+    }
+
+    // Charge the memory of the size, which fails if the quota is exceeded. The
+    // check and the charge are done at once so that the concurrent charges can't
+    // exceed the quota together.
+    fn charge(&self, size: usize) -> Result<()> {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(size)
+                    .filter(|new_used| *new_used <= self.size)
+            })
+            .map_err(|_| errno!(ENOMEM, "exceeding the memory quota of the app"))?;
+        Ok(())
+    }
+
+    fn uncharge(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// The memory charged to the quota of an app by a process of the app.
+///
+/// The memory left charged is uncharged when the process exits, or when the charge
+/// is dropped, e.g., the process fails to be created.
+#[derive(Debug)]
+pub struct MemQuotaCharge {
+    quota: MemQuotaRef,
+    // The size of the memory charged by the process
+    size: AtomicUsize,
+}
+
+impl MemQuotaCharge {
+    pub fn new(quota: MemQuotaRef) -> Self {
+        let size = AtomicUsize::new(0);
+        Self { quota, size }
+    }
+
+    pub fn quota(&self) -> &MemQuotaRef {
+        &self.quota
+    }
+
+    /// Charge the memory of the size mapped by the process.
+    pub fn charge(&self, size: usize) -> Result<()> {
+        if size == 0 {
+            return Ok(());
+        }
+        self.quota.charge(size)?;
+        self.size.fetch_add(size, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Uncharge the memory of the size unmapped by the process. No more than the
+    /// memory charged by the process is uncharged.
+    pub fn uncharge(&self, size: usize) {
+        let old_size = self
+            .size
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old_size| {
+                Some(old_size.saturating_sub(size))
+            })
+            .unwrap();
+        self.quota.uncharge(old_size.min(size));
+    }
+
+    /// Uncharge all the memory charged by the process.
+    pub fn uncharge_all(&self) {
+        let size = self.size.swap(0, Ordering::Relaxed);
+        self.quota.uncharge(size);
+    }
+}
+
+impl Drop for MemQuotaCharge {
+    fn drop(&mut self) {
+        self.uncharge_all();
+    }
+}
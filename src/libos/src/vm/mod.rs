@@ -63,6 +63,8 @@ use std::fmt;
 
 mod chunk;
 mod free_space_manager;
+mod mem_quota;
+mod oom_killer;
 mod process_vm;
 mod shm_manager;
 mod user_space_vm;
@@ -77,9 +79,13 @@ mod vm_util;
 use self::vm_layout::VMLayout;
 
 pub use self::chunk::{ChunkRef, ChunkType};
-pub use self::process_vm::{MMapFlags, MRemapFlags, MSyncFlags, ProcessVM, ProcessVMBuilder};
+pub use self::mem_quota::{MemQuota, MemQuotaRef};
+pub use self::process_vm::{
+    MAdvice, MLockAllFlags, MLockFlags, MMapFlags, MRemapFlags, MSyncFlags, MemStat, ProcessVM,
+    ProcessVMBuilder,
+};
 pub use self::user_space_vm::USER_SPACE_VM_MANAGER;
-pub use self::vm_area::VMArea;
+pub use self::vm_area::{VMAdvice, VMArea};
 pub use self::vm_manager::MunmapChunkFlag;
 pub use self::vm_perms::VMPerms;
 pub use self::vm_range::VMRange;
@@ -154,4 +160,40 @@ pub fn do_msync(addr: usize, size: usize, flags: MSyncFlags) -> Result<()> {
     current!().vm().msync(addr, size)
 }
 
+pub fn do_madvise(addr: usize, size: usize, advice: MAdvice) -> Result<()> {
+    debug!(
+        "madvise: addr: {:#x}, size: {:#x}, advice: {:?}",
+        addr, size, advice
+    );
+    current!().vm().madvise(addr, size, advice)
+}
+
+pub fn do_mincore(addr: usize, size: usize, vec: &mut [u8]) -> Result<()> {
+    debug!("mincore: addr: {:#x}, size: {:#x}", addr, size);
+    current!().vm().mincore(addr, size, vec)
+}
+
+pub fn do_mlock(addr: usize, size: usize, flags: MLockFlags) -> Result<()> {
+    debug!(
+        "mlock: addr: {:#x}, size: {:#x}, flags: {:?}",
+        addr, size, flags
+    );
+    current!().vm().mlock(addr, size)
+}
+
+pub fn do_munlock(addr: usize, size: usize) -> Result<()> {
+    debug!("munlock: addr: {:#x}, size: {:#x}", addr, size);
+    current!().vm().munlock(addr, size)
+}
+
+pub fn do_mlockall(flags: MLockAllFlags) -> Result<()> {
+    debug!("mlockall: flags: {:?}", flags);
+    current!().vm().mlockall(flags)
+}
+
+pub fn do_munlockall() -> Result<()> {
+    debug!("munlockall");
+    current!().vm().munlockall()
+}
+
 pub const PAGE_SIZE: usize = 4096;
@@ -0,0 +1,80 @@
+//! The OOM killer, which frees the memory for an allocation by killing the
+//! process using the most memory.
+
+use super::*;
+
+use super::mem_quota::MemQuotaRef;
+use crate::events::Waiter;
+use crate::interrupt::broadcast_interrupts;
+use crate::process::{table, ProcessStatus};
+use crate::signal::{KernelSignal, SIGKILL};
+use std::time::Duration;
+
+// The memory of the victim is released when it exits, which is waited for
+// until the timeout.
+const VICTIM_EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Kill the process using the most memory with SIGKILL, and wait for it to exit.
+///
+/// If the memory quota of an app is exceeded, the victim is chosen from the
+/// processes of the app, whose quota is given. Otherwise, the user space is
+/// exhausted, and the victim is chosen from all the processes.
+///
+/// The memory used by a process is its proportional size, i.e., the shared
+/// mappings are divided evenly by the processes sharing them. The idle process,
+/// the init process and the current process are never killed. An error is
+/// returned if there is no process to kill or the victim does not exit in time.
+pub fn kill_largest_process(app_quota: Option<&MemQuotaRef>) -> Result<()> {
+    let current_pid = current!().process().pid();
+    let victim = table::get_all_processes()
+        .into_iter()
+        .filter(|process| {
+            let pid = process.pid();
+            pid != 0
+                && pid != 1
+                && pid != current_pid
+                && process.status() != ProcessStatus::Zombie
+                && !process.is_forced_to_exit()
+        })
+        .filter_map(|process| {
+            let main_thread = process.main_thread()?;
+            let vm = main_thread.vm();
+            if let Some(app_quota) = app_quota {
+                let in_app = vm
+                    .mem_quota()
+                    .map_or(false, |quota| Arc::ptr_eq(quota, app_quota));
+                if !in_app {
+                    return None;
+                }
+            }
+            let mem_size = vm.mem_stat(process.pid()).proportional_size;
+            Some((process, mem_size))
+        })
+        .max_by_key(|(_, mem_size)| *mem_size)
+        .map(|(process, _)| process)
+        .ok_or_else(|| errno!(ENOMEM, "no process to kill"))?;
+
+    warn!("out of memory: kill process {}", victim.pid());
+    victim
+        .sig_queues()
+        .write()
+        .unwrap()
+        .enqueue(Box::new(KernelSignal::new(SIGKILL)));
+    let _ = broadcast_interrupts();
+
+    let waiter = Waiter::new();
+    let mut remaining = VICTIM_EXIT_TIMEOUT;
+    loop {
+        // Enqueue the waiter before checking the status so that the wakeup on
+        // exit is not missed
+        victim.exit_waiters().reset_and_enqueue(&waiter);
+        if victim.status() == ProcessStatus::Zombie {
+            return Ok(());
+        }
+        if let Err(e) = waiter.wait_mut(Some(&mut remaining)) {
+            if e.errno() == ETIMEDOUT {
+                return_errno!(ENOMEM, "the killed process does not exit in time");
+            }
+        }
+    }
+}
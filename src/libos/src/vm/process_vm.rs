@@ -1,19 +1,26 @@
 use super::*;
 
 use super::chunk::*;
+use super::mem_quota::{MemQuotaCharge, MemQuotaRef};
+use super::oom_killer::kill_largest_process;
 use super::user_space_vm::USER_SPACE_VM_MANAGER;
-use super::vm_area::VMArea;
+use super::vm_area::{VMAdvice, VMArea};
 use super::vm_manager::MunmapChunkFlag;
 use super::vm_perms::VMPerms;
 use super::vm_util::{
     FileBacked, VMInitializer, VMMapAddr, VMMapOptions, VMMapOptionsBuilder, VMRemapOptions,
 };
 use crate::config;
+use crate::fs::io_uring::AsIoUring;
+use crate::fs::AsINodeFile;
 use crate::ipc::SHM_MANAGER;
+use crate::misc::resource_t;
 use crate::process::elf_file::{ElfFile, ProgramHeaderExt};
 use crate::util::sync::rw_lock::RwLockWriteGuard;
 
+use sgx_trts::enclave::rsgx_is_supported_EDMM;
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // Used for heap and stack start address randomization.
 const RANGE_FOR_RANDOMIZATION: usize = 256 * 4096; // 1M
@@ -23,7 +30,7 @@ pub struct ProcessVMBuilder<'a, 'b> {
     elfs: Vec<&'b ElfFile<'a>>,
     heap_size: Option<usize>,
     stack_size: Option<usize>,
-    mmap_size: Option<usize>,
+    mem_quota: Option<MemQuotaRef>,
 }
 
 impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
@@ -32,7 +39,7 @@ impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
             elfs: elfs,
             heap_size: None,
             stack_size: None,
-            mmap_size: None,
+            mem_quota: None,
         }
     }
 
@@ -46,8 +53,8 @@ impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
         self
     }
 
-    pub fn set_mmap_size(&mut self, mmap_size: usize) -> &mut Self {
-        self.mmap_size = Some(mmap_size);
+    pub fn set_mem_quota(&mut self, mem_quota: Option<MemQuotaRef>) -> &mut Self {
+        self.mem_quota = mem_quota;
         self
     }
 
@@ -108,6 +115,13 @@ impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
             },
         );
 
+        // The charge is dropped, i.e., the memory is uncharged, if the process VM
+        // fails to be built
+        let mem_quota = self.mem_quota.clone().map(MemQuotaCharge::new);
+        if let Some(mem_quota) = &mem_quota {
+            mem_quota.charge(process_layout.size())?;
+        }
+
         // Now that we end up with the memory layout required by the process,
         // let's allocate the memory for the process
         let mut chunks = HashSet::new();
@@ -192,6 +206,10 @@ impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
             heap_range,
             stack_range,
             brk,
+            libos_mappings: SgxMutex::new(Vec::new()),
+            auxv: Vec::new(),
+            mlock_future: AtomicBool::new(false),
+            mem_quota,
             mem_chunks,
         })
     }
@@ -207,7 +225,6 @@ impl<'a, 'b> ProcessVMBuilder<'a, 'b> {
         };
         validate_size(self.heap_size)?;
         validate_size(self.stack_size)?;
-        validate_size(self.mmap_size)?;
         Ok(())
     }
 
@@ -279,6 +296,15 @@ pub struct ProcessVM {
     heap_range: VMRange,
     stack_range: VMRange,
     brk: RwLock<usize>,
+    // The memory mapped by the LibOS for the process, e.g., the rings of io_uring, which
+    // cannot be unmapped, remapped or protected by the user
+    libos_mappings: SgxMutex<Vec<VMRange>>,
+    // The auxiliary vector passed to the process, which is saved for core dumps
+    auxv: Vec<(u64, u64)>,
+    // Whether the memory mapped in the future is locked, i.e., mlockall(MCL_FUTURE)
+    mlock_future: AtomicBool,
+    // The memory charged to the quota of the app that the process belongs to
+    mem_quota: Option<MemQuotaCharge>,
     // Memory safety notes: the mem_chunks field must be the last one.
     //
     // Rust drops fields in the same order as they are declared. So by making
@@ -295,6 +321,10 @@ impl Default for ProcessVM {
             heap_range: Default::default(),
             stack_range: Default::default(),
             brk: Default::default(),
+            libos_mappings: SgxMutex::new(Vec::new()),
+            auxv: Vec::new(),
+            mlock_future: AtomicBool::new(false),
+            mem_quota: None,
             mem_chunks: Arc::new(RwLock::new(HashSet::new())),
         }
     }
@@ -450,6 +480,14 @@ impl ProcessVM {
         self.get_stack_range().start()
     }
 
+    pub fn get_auxv(&self) -> &[(u64, u64)] {
+        &self.auxv
+    }
+
+    pub fn set_auxv(&mut self, auxv: Vec<(u64, u64)>) {
+        self.auxv = auxv;
+    }
+
     pub fn get_brk(&self) -> usize {
         *self.brk.read().unwrap()
     }
@@ -467,10 +505,26 @@ impl ProcessVM {
             // Get page-aligned old brk address.
             let old_brk = align_up(*brk_guard, PAGE_SIZE);
 
-            // Reset the memory when brk shrinks.
+            // The heap can't grow beyond RLIMIT_DATA, which may be lowered after the
+            // heap is allocated.
+            if new_brk > old_brk
+                && self
+                    .check_data_limit(new_brk - old_brk, old_brk - heap_start)
+                    .is_err()
+            {
+                warn!("New brk address exceeds RLIMIT_DATA");
+                return Ok(*brk_guard);
+            }
+
+            // Reset the memory permissions and contents when brk shrinks.
             if new_brk < old_brk {
                 let shrink_brk_range =
                     VMRange::new(new_brk, old_brk).expect("shrink brk range must be valid");
+                USER_SPACE_VM_MANAGER.mprotect(
+                    shrink_brk_range.start(),
+                    shrink_brk_range.size(),
+                    VMPerms::DEFAULT,
+                )?;
                 USER_SPACE_VM_MANAGER.reset_memory(shrink_brk_range)?;
             }
 
@@ -526,24 +580,74 @@ impl ProcessVM {
                 VMInitializer::DoNothing()
             } else {
                 let file_ref = current!().file(fd)?;
+                // The rings of io_uring have been mapped by io_uring_setup
+                if let Ok(io_uring) = file_ref.as_io_uring() {
+                    return io_uring.mmap(addr, size, flags, offset);
+                }
                 // Only shared, file-backed memory mappings have write-back files
                 let need_write_back = if flags.contains(MMapFlags::MAP_SHARED) {
                     true
                 } else {
                     false
                 };
+                if need_write_back && perms.can_write() {
+                    if let Ok(inode_file) = file_ref.as_inode_file() {
+                        inode_file.check_seals_for_shared_mmap()?;
+                    }
+                }
                 VMInitializer::FileBacked {
                     file: FileBacked::new(file_ref, offset, need_write_back),
                 }
             }
         };
+        if let VMMapAddr::Force(addr) = addr_option {
+            self.check_libos_mappings(addr, size)?;
+        }
+        // The memory replaced by MAP_FIXED is not counted twice
+        let replaced_size = match addr_option {
+            VMMapAddr::Force(addr) => VMRange::new_with_size(addr, align_up(size, PAGE_SIZE))
+                .map_or(0, |range| USER_SPACE_VM_MANAGER.mapped_size(&range)),
+            _ => 0,
+        };
+        let new_size = align_up(size, PAGE_SIZE).saturating_sub(replaced_size);
+        self.check_address_space_limit(new_size)?;
+        if perms.can_write() && !flags.contains(MMapFlags::MAP_SHARED) {
+            self.check_data_limit(new_size, self.heap_size())?;
+        }
+        let locked =
+            flags.contains(MMapFlags::MAP_LOCKED) || self.mlock_future.load(Ordering::Relaxed);
+        if locked {
+            let replaced_range = match addr_option {
+                VMMapAddr::Force(addr) => {
+                    VMRange::new_with_size(addr, align_up(size, PAGE_SIZE)).ok()
+                }
+                _ => None,
+            };
+            self.check_memlock_limit(align_up(size, PAGE_SIZE), replaced_range.as_ref())
+                .map_err(|_| errno!(EAGAIN, "exceeding RLIMIT_MEMLOCK"))?;
+        }
+
         let mmap_options = VMMapOptionsBuilder::default()
             .size(size)
             .addr(addr_option)
             .perms(perms)
             .initializer(initializer)
             .build()?;
-        let mmap_addr = USER_SPACE_VM_MANAGER.mmap(&mmap_options)?;
+        self.charge_mem_quota(new_size)?;
+        let mmap_addr = USER_SPACE_VM_MANAGER.mmap(&mmap_options).map_err(|e| {
+            self.uncharge_mem_quota(new_size);
+            e
+        })?;
+        if locked {
+            // The new mapping is unmapped if it can't be locked
+            let res = VMRange::new_with_size(mmap_addr, align_up(size, PAGE_SIZE))
+                .and_then(|mmap_range| self.lock_range(&mmap_range));
+            if let Err(e) = res {
+                let _ = USER_SPACE_VM_MANAGER.munmap(mmap_addr, size);
+                self.uncharge_mem_quota(align_up(size, PAGE_SIZE));
+                return Err(e);
+            }
+        }
         Ok(mmap_addr)
     }
 
@@ -555,11 +659,94 @@ impl ProcessVM {
         flags: MRemapFlags,
     ) -> Result<usize> {
         let mremap_option = VMRemapOptions::new(old_addr, old_size, new_size, flags)?;
-        USER_SPACE_VM_MANAGER.mremap(&mremap_option)
+        self.check_libos_mappings(old_addr, old_size)?;
+        if let Some(new_addr) = flags.new_addr() {
+            self.check_libos_mappings(new_addr, new_size)?;
+        }
+        let grown_size =
+            align_up(new_size, PAGE_SIZE).saturating_sub(align_up(old_size, PAGE_SIZE));
+        self.check_address_space_limit(grown_size)?;
+        let old_range = VMRange::new_with_size(old_addr, align_up(old_size, PAGE_SIZE))?;
+        if self.is_data(&old_range) {
+            self.check_data_limit(grown_size, self.heap_size())?;
+        }
+        // The remapped memory stays locked if the old memory is locked
+        let locked = self.is_locked(&old_range);
+        if locked {
+            self.check_memlock_limit(grown_size, None)
+                .map_err(|_| errno!(EAGAIN, "exceeding RLIMIT_MEMLOCK"))?;
+        }
+
+        // The memory shrunk, and the memory replaced at the new address, are uncharged
+        let shrunk_size =
+            align_up(old_size, PAGE_SIZE).saturating_sub(align_up(new_size, PAGE_SIZE));
+        let replaced_size = flags.new_addr().map_or(0, |new_addr| {
+            VMRange::new_with_size(new_addr, align_up(new_size, PAGE_SIZE))
+                .map_or(0, |range| USER_SPACE_VM_MANAGER.mapped_size(&range))
+        });
+        self.charge_mem_quota(grown_size)?;
+        let new_addr = USER_SPACE_VM_MANAGER.mremap(&mremap_option).map_err(|e| {
+            self.uncharge_mem_quota(grown_size);
+            e
+        })?;
+        self.uncharge_mem_quota(shrunk_size + replaced_size);
+        if locked {
+            let new_range = VMRange::new_with_size(new_addr, align_up(new_size, PAGE_SIZE))?;
+            self.lock_range(&new_range)?;
+        }
+        Ok(new_addr)
     }
 
     pub fn munmap(&self, addr: usize, size: usize) -> Result<()> {
-        USER_SPACE_VM_MANAGER.munmap(addr, size)
+        self.check_libos_mappings(addr, size)?;
+        let unmapped_size = VMRange::new_with_size(addr, align_up(size, PAGE_SIZE))
+            .map_or(0, |range| USER_SPACE_VM_MANAGER.mapped_size(&range));
+        USER_SPACE_VM_MANAGER.munmap(addr, size)?;
+        self.uncharge_mem_quota(unmapped_size);
+        Ok(())
+    }
+
+    /// Map anonymous memory for the LibOS, which is accessed by both the LibOS and the
+    /// user, but cannot be unmapped, remapped or protected by the user. So the LibOS can
+    /// access the memory without checking it until the memory is unmapped by
+    /// `munmap_libos`, or freed when the process exits.
+    pub fn mmap_libos(&self, size: usize) -> Result<VMRange> {
+        let mut libos_mappings = self.libos_mappings.lock().unwrap();
+        let addr = self.mmap(
+            0,
+            size,
+            VMPerms::READ | VMPerms::WRITE,
+            MMapFlags::MAP_PRIVATE | MMapFlags::MAP_ANONYMOUS,
+            0,
+            0,
+        )?;
+        let range = VMRange::new_with_size(addr, align_up(size, PAGE_SIZE))?;
+        libos_mappings.push(range);
+        Ok(range)
+    }
+
+    /// Unmap the memory mapped by `mmap_libos`, which must be done by a thread of the
+    /// process.
+    pub fn munmap_libos(&self, range: &VMRange) -> Result<()> {
+        let mut libos_mappings = self.libos_mappings.lock().unwrap();
+        libos_mappings.retain(|mapping| mapping != range);
+        USER_SPACE_VM_MANAGER.munmap(range.start(), range.size())?;
+        self.uncharge_mem_quota(range.size());
+        Ok(())
+    }
+
+    fn check_libos_mappings(&self, addr: usize, size: usize) -> Result<()> {
+        let end = addr.saturating_add(size);
+        let overlapped = self
+            .libos_mappings
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|mapping| addr < mapping.end() && mapping.start() < end);
+        if overlapped {
+            return_errno!(EINVAL, "the memory is mapped by the LibOS");
+        }
+        Ok(())
     }
 
     pub fn mprotect(&self, addr: usize, size: usize, perms: VMPerms) -> Result<()> {
@@ -570,6 +757,7 @@ impl ProcessVM {
             align_up(size, PAGE_SIZE)
         };
         let protect_range = VMRange::new_with_size(addr, size)?;
+        self.check_libos_mappings(addr, size)?;
 
         return USER_SPACE_VM_MANAGER.mprotect(addr, size, perms);
     }
@@ -582,10 +770,553 @@ impl ProcessVM {
         return USER_SPACE_VM_MANAGER.msync_by_file(sync_file);
     }
 
+    pub fn madvise(&self, addr: usize, size: usize, advice: MAdvice) -> Result<()> {
+        if addr % PAGE_SIZE != 0 {
+            return_errno!(EINVAL, "the address must be page aligned");
+        }
+        let size = align_up(size, PAGE_SIZE);
+        if size == 0 {
+            return Ok(());
+        }
+        let advise_range = VMRange::new_with_size(addr, size)?;
+        if USER_SPACE_VM_MANAGER.mapped_size(&advise_range) != size {
+            return_errno!(ENOMEM, "the range is not fully mapped");
+        }
+
+        match advice {
+            MAdvice::MADV_DONTNEED | MAdvice::MADV_FREE => {
+                if self.is_locked(&advise_range) {
+                    return_errno!(EINVAL, "the memory is locked");
+                }
+                if advice == MAdvice::MADV_FREE
+                    && !USER_SPACE_VM_MANAGER.is_private_anonymous(&advise_range)
+                {
+                    return_errno!(EINVAL, "MADV_FREE only applies to private anonymous memory");
+                }
+                // The ELF images are not linear mappings of the ELF files, so
+                // they are kept as is
+                let mut reset_ranges = vec![advise_range];
+                for elf_range in self.elf_ranges.iter() {
+                    reset_ranges = reset_ranges
+                        .iter()
+                        .flat_map(|range| range.subtract(elf_range))
+                        .collect();
+                }
+                for range in reset_ranges {
+                    USER_SPACE_VM_MANAGER.reset_memory(range)?;
+                }
+            }
+            // All the memory is populated at mmap, so the access patterns are
+            // only kept for the VMAs, and the files are read ahead for
+            // MADV_WILLNEED to warm up their caches
+            MAdvice::MADV_NORMAL => self.update_advice(
+                &advise_range,
+                VMAdvice::empty(),
+                VMAdvice::RAND_READ | VMAdvice::SEQ_READ,
+            )?,
+            MAdvice::MADV_RANDOM => {
+                self.update_advice(&advise_range, VMAdvice::RAND_READ, VMAdvice::SEQ_READ)?
+            }
+            MAdvice::MADV_SEQUENTIAL => {
+                self.update_advice(&advise_range, VMAdvice::SEQ_READ, VMAdvice::RAND_READ)?
+            }
+            MAdvice::MADV_WILLNEED => self.read_ahead_files(&advise_range),
+            // Spawned processes never inherit the memory of the parent, and the
+            // child of vfork shares the memory of the parent like Linux. So the
+            // advice is only kept for the VMAs.
+            MAdvice::MADV_DONTFORK => {
+                self.update_advice(&advise_range, VMAdvice::DONT_COPY, VMAdvice::empty())?
+            }
+            MAdvice::MADV_DOFORK => {
+                self.update_advice(&advise_range, VMAdvice::empty(), VMAdvice::DONT_COPY)?
+            }
+            MAdvice::MADV_WIPEONFORK => {
+                if !USER_SPACE_VM_MANAGER.is_private_anonymous(&advise_range) {
+                    return_errno!(
+                        EINVAL,
+                        "MADV_WIPEONFORK only applies to private anonymous memory"
+                    );
+                }
+                self.update_advice(&advise_range, VMAdvice::WIPE_ON_FORK, VMAdvice::empty())?
+            }
+            MAdvice::MADV_KEEPONFORK => {
+                self.update_advice(&advise_range, VMAdvice::empty(), VMAdvice::WIPE_ON_FORK)?
+            }
+            MAdvice::MADV_REMOVE => {
+                return_errno!(EOPNOTSUPP, "freeing the backing store is not supported");
+            }
+            _ => {
+                trace!("madvise: {:?} is ignored", advice);
+            }
+        }
+        Ok(())
+    }
+
+    // Set and clear the advice of the memory of the current process in the range
+    fn update_advice(&self, range: &VMRange, set: VMAdvice, clear: VMAdvice) -> Result<()> {
+        USER_SPACE_VM_MANAGER.update_vmas(range, |vma| vma.set_advice((vma.advice() - clear) | set))
+    }
+
+    // Read the files backing the memory in the range ahead, which warms up the
+    // caches of the files, e.g., the page cache of the host, for the accesses to
+    // the files that follow. Like Linux, the errors are ignored.
+    fn read_ahead_files(&self, range: &VMRange) {
+        const READ_AHEAD_BUF_SIZE: usize = 64 * 1024;
+
+        let mut file_ranges = Vec::new();
+        self.for_each_vma(current!().process().pid(), |vma| {
+            if let (Some((file, offset)), Some(read_range)) =
+                (vma.init_file(), vma.range().intersect(range))
+            {
+                let offset = offset + (read_range.start() - vma.start());
+                file_ranges.push((file.clone(), offset, read_range.size()));
+            }
+        });
+
+        let mut buf = vec![0; READ_AHEAD_BUF_SIZE];
+        for (file, offset, len) in file_ranges {
+            let mut read_len = 0;
+            while read_len < len {
+                let buf_len = (len - read_len).min(buf.len());
+                match file.read_at(offset + read_len, &mut buf[..buf_len]) {
+                    Ok(0) | Err(_) => break,
+                    Ok(buf_read_len) => read_len += buf_read_len,
+                }
+            }
+        }
+    }
+
+    /// Report the residency of the pages in the range, one byte per page.
+    ///
+    /// The memory is populated at mmap and never swapped, so all the pages
+    /// are resident.
+    pub fn mincore(&self, addr: usize, size: usize, vec: &mut [u8]) -> Result<()> {
+        if addr % PAGE_SIZE != 0 {
+            return_errno!(EINVAL, "the address must be page aligned");
+        }
+        let size = align_up(size, PAGE_SIZE);
+        debug_assert!(vec.len() == size / PAGE_SIZE);
+        if size == 0 {
+            return Ok(());
+        }
+        let range =
+            VMRange::new_with_size(addr, size).map_err(|_| errno!(ENOMEM, "invalid range"))?;
+        if USER_SPACE_VM_MANAGER.mapped_size(&range) != size {
+            return_errno!(ENOMEM, "the range is not fully mapped");
+        }
+        vec.iter_mut().for_each(|page| *page = 1);
+        Ok(())
+    }
+
+    /// Lock the memory in the range.
+    ///
+    /// The memory is never swapped, so locking it means tracking the locked
+    /// memory, which is limited by RLIMIT_MEMLOCK, and making sure that it is
+    /// committed.
+    pub fn mlock(&self, addr: usize, size: usize) -> Result<()> {
+        let lock_range = match Self::page_range(addr, size)? {
+            Some(lock_range) => lock_range,
+            None => return Ok(()),
+        };
+        if USER_SPACE_VM_MANAGER.mapped_size(&lock_range) != lock_range.size() {
+            return_errno!(ENOMEM, "the range is not fully mapped");
+        }
+        self.check_memlock_limit(lock_range.size(), Some(&lock_range))?;
+        self.lock_range(&lock_range)
+    }
+
+    pub fn munlock(&self, addr: usize, size: usize) -> Result<()> {
+        let unlock_range = match Self::page_range(addr, size)? {
+            Some(unlock_range) => unlock_range,
+            None => return Ok(()),
+        };
+        if USER_SPACE_VM_MANAGER.mapped_size(&unlock_range) != unlock_range.size() {
+            return_errno!(ENOMEM, "the range is not fully mapped");
+        }
+        USER_SPACE_VM_MANAGER.mlock(&unlock_range, false)
+    }
+
+    pub fn mlockall(&self, flags: MLockAllFlags) -> Result<()> {
+        if flags.contains(MLockAllFlags::MCL_CURRENT) {
+            let process_range = *self.get_process_range();
+            let mapped_size = USER_SPACE_VM_MANAGER.mapped_size(&process_range);
+            self.check_memlock_limit(mapped_size, Some(&process_range))?;
+            self.lock_range(&process_range)?;
+        }
+        self.mlock_future
+            .store(flags.contains(MLockAllFlags::MCL_FUTURE), Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn munlockall(&self) -> Result<()> {
+        self.mlock_future.store(false, Ordering::Relaxed);
+        USER_SPACE_VM_MANAGER.mlock(self.get_process_range(), false)
+    }
+
+    // Lock the memory of the current process in the range and commit it
+    fn lock_range(&self, range: &VMRange) -> Result<()> {
+        USER_SPACE_VM_MANAGER.mlock(range, true)?;
+        self.commit_memory(range);
+        Ok(())
+    }
+
+    // Commit the readable memory of the current process in the range.
+    //
+    // The whole user space is committed when it is reserved unless EDMM is
+    // supported, with which the pages may be committed on demand when they are
+    // accessed. So the pages are touched to be committed.
+    fn commit_memory(&self, range: &VMRange) {
+        if !rsgx_is_supported_EDMM() {
+            return;
+        }
+
+        let mut readable_ranges = Vec::new();
+        self.for_each_vma(current!().process().pid(), |vma| {
+            if vma.perms().can_read() {
+                readable_ranges.extend(vma.range().intersect(range));
+            }
+        });
+        for readable_range in readable_ranges {
+            for page_addr in (readable_range.start()..readable_range.end()).step_by(PAGE_SIZE) {
+                unsafe {
+                    std::ptr::read_volatile(page_addr as *const u8);
+                }
+            }
+        }
+    }
+
+    /// Whether the memory of the VMA is known to be committed.
+    ///
+    /// Without EDMM, the whole user space is committed. With EDMM, only the memory
+    /// populated or locked by the LibOS is known to be committed, i.e., the ELF
+    /// images, the file mappings and the locked memory. The other memory may be
+    /// committed on demand, which happens when it is accessed for the first time.
+    pub fn is_committed(&self, vma: &VMArea) -> bool {
+        !rsgx_is_supported_EDMM()
+            || vma.init_file().is_some()
+            || vma.is_locked()
+            || self
+                .elf_ranges
+                .iter()
+                .any(|elf_range| elf_range.is_superset_of(vma.range()))
+    }
+
+    // Return the page-aligned range that covers [addr, addr + size), or None if it is empty
+    fn page_range(addr: usize, size: usize) -> Result<Option<VMRange>> {
+        let start = align_down(addr, PAGE_SIZE);
+        let end = addr
+            .checked_add(size)
+            .filter(|end| *end <= usize::max_value() - PAGE_SIZE)
+            .map(|end| align_up(end, PAGE_SIZE))
+            .ok_or_else(|| errno!(ENOMEM, "the range is too large"))?;
+        if start == end {
+            return Ok(None);
+        }
+        Ok(Some(VMRange::new(start, end)?))
+    }
+
+    // Check whether any memory of the current process in the range is locked
+    fn is_locked(&self, range: &VMRange) -> bool {
+        let mut locked = false;
+        self.for_each_vma(current!().process().pid(), |vma| {
+            locked |= vma.is_locked() && vma.overlap_with(range);
+        });
+        locked
+    }
+
+    // Check whether locking memory of the size exceeds RLIMIT_MEMLOCK. The memory
+    // which is in the given range and already locked is not counted twice.
+    fn check_memlock_limit(&self, size: usize, lock_range: Option<&VMRange>) -> Result<()> {
+        let current = current!();
+        let memlock_limit = current
+            .rlimits()
+            .lock()
+            .unwrap()
+            .get(resource_t::RLIMIT_MEMLOCK)
+            .get_cur();
+        if size == 0 {
+            return Ok(());
+        }
+        if memlock_limit == 0 {
+            return_errno!(EPERM, "locking memory is not allowed by RLIMIT_MEMLOCK");
+        }
+        // Avoid counting the locked memory if there is no limit
+        if memlock_limit == u64::max_value() {
+            return Ok(());
+        }
+
+        let mut locked_size = 0;
+        self.for_each_vma(current.process().pid(), |vma| {
+            if !vma.is_locked() {
+                return;
+            }
+            let relocked_size = lock_range.map_or(0, |range| {
+                vma.range().intersect(range).map_or(0, |r| r.size())
+            });
+            locked_size += vma.size() - relocked_size;
+        });
+        if (locked_size + size) as u64 > memlock_limit {
+            return_errno!(ENOMEM, "exceeding RLIMIT_MEMLOCK");
+        }
+        Ok(())
+    }
+
     // Return: a copy of the found region
     pub fn find_mmap_region(&self, addr: usize) -> Result<VMRange> {
         USER_SPACE_VM_MANAGER.find_mmap_region(addr)
     }
+
+    /// Check whether mapping more memory of the size for the current process
+    /// exceeds RLIMIT_AS.
+    fn check_address_space_limit(&self, size: usize) -> Result<()> {
+        let current = current!();
+        let as_limit = current
+            .rlimits()
+            .lock()
+            .unwrap()
+            .get(resource_t::RLIMIT_AS)
+            .get_cur();
+        // Avoid counting the mapped memory if there is no limit
+        if as_limit == u64::max_value() || size == 0 {
+            return Ok(());
+        }
+
+        let mut mapped_size = 0;
+        self.for_each_vma(current.process().pid(), |vma| mapped_size += vma.size());
+        if (mapped_size + size) as u64 > as_limit {
+            return_errno!(ENOMEM, "exceeding RLIMIT_AS");
+        }
+        Ok(())
+    }
+
+    /// Get the memory quota of the app that the process belongs to.
+    pub fn mem_quota(&self) -> Option<&MemQuotaRef> {
+        self.mem_quota.as_ref().map(|mem_quota| mem_quota.quota())
+    }
+
+    // Charge the memory of the size to be mapped by the current process to the
+    // memory quota of the app. If the quota is exceeded, and the OOM policy is to
+    // kill the largest process, the largest process of the app is killed and the
+    // memory is charged again.
+    fn charge_mem_quota(&self, size: usize) -> Result<()> {
+        let mem_quota = match &self.mem_quota {
+            Some(mem_quota) => mem_quota,
+            None => return Ok(()),
+        };
+        match mem_quota.charge(size) {
+            Err(e)
+                if e.errno() == ENOMEM
+                    && config::LIBOS_CONFIG.resource_limits.oom_policy
+                        == config::ConfigOomPolicy::KillLargest =>
+            {
+                kill_largest_process(Some(mem_quota.quota()))?;
+                mem_quota.charge(size)
+            }
+            res => res,
+        }
+    }
+
+    fn uncharge_mem_quota(&self, size: usize) {
+        if let Some(mem_quota) = &self.mem_quota {
+            mem_quota.uncharge(size);
+        }
+    }
+
+    /// Uncharge all the memory of the process from the memory quota of the app,
+    /// which is done when the process exits.
+    pub fn uncharge_all_mem_quota(&self) {
+        if let Some(mem_quota) = &self.mem_quota {
+            mem_quota.uncharge_all();
+        }
+    }
+
+    /// Check whether mapping more private writable memory of the size for the
+    /// current process exceeds RLIMIT_DATA. The data memory consists of the used
+    /// part of the heap, whose size is given, and the private writable mappings
+    /// other than the heap and the stack.
+    fn check_data_limit(&self, size: usize, heap_size: usize) -> Result<()> {
+        let current = current!();
+        let data_limit = current
+            .rlimits()
+            .lock()
+            .unwrap()
+            .get(resource_t::RLIMIT_DATA)
+            .get_cur();
+        // Avoid counting the mapped memory if there is no limit
+        if data_limit == u64::max_value() || size == 0 {
+            return Ok(());
+        }
+
+        let mut data_size = heap_size;
+        self.for_each_vma(current.process().pid(), |vma| {
+            if Self::is_data_vma(vma) {
+                let stat = self.vma_mem_stat(vma);
+                data_size += stat.size - stat.stack - stat.heap;
+            }
+        });
+        if (data_size + size) as u64 > data_limit {
+            return_errno!(ENOMEM, "exceeding RLIMIT_DATA");
+        }
+        Ok(())
+    }
+
+    // Check whether any memory of the current process in the range is private
+    // writable memory
+    fn is_data(&self, range: &VMRange) -> bool {
+        let mut is_data = false;
+        self.for_each_vma(current!().process().pid(), |vma| {
+            is_data |= Self::is_data_vma(vma) && vma.overlap_with(range);
+        });
+        is_data
+    }
+
+    fn is_data_vma(vma: &VMArea) -> bool {
+        vma.perms().can_write() && !vma.is_shared() && vma.writeback_file().is_none()
+    }
+
+    // Get the size of the used part of the heap
+    fn heap_size(&self) -> usize {
+        align_up(*self.brk.read().unwrap(), PAGE_SIZE).saturating_sub(self.heap_range.start())
+    }
+
+    /// Call the function on each VMA of the process, which is not in the address order.
+    pub fn for_each_vma<F: FnMut(&VMArea)>(&self, pid: pid_t, mut f: F) {
+        let mem_chunks = self.mem_chunks.read().unwrap();
+        for chunk in mem_chunks.iter() {
+            match chunk.internal() {
+                ChunkType::SingleVMA(vma) => {
+                    let vma = vma.lock().unwrap();
+                    if vma.size() > 0 {
+                        f(&vma);
+                    }
+                }
+                ChunkType::MultiVMA(internal_manager) => {
+                    let internal = internal_manager.lock().unwrap();
+                    internal
+                        .chunk_manager()
+                        .vmas()
+                        .iter()
+                        .map(|vma_obj| vma_obj.vma())
+                        // The VMAs of other processes and the sentries are skipped
+                        .filter(|vma| vma.size() > 0 && vma.belong_to(pid))
+                        .for_each(&mut f);
+                }
+            }
+        }
+    }
+
+    /// Get the memory statistics of a VMA of the process.
+    pub fn vma_mem_stat(&self, vma: &VMArea) -> MemStat {
+        let size = vma.size();
+        let overlapped_size = |range: &VMRange| {
+            range
+                .intersect(vma.range())
+                .map_or(0, |overlapped| overlapped.size())
+        };
+        let stack = overlapped_size(&self.stack_range);
+        let mut stat = MemStat {
+            size,
+            proportional_size: size / vma.shared_process_count(),
+            heap: overlapped_size(&self.heap_range),
+            stack,
+            elf: self.elf_ranges.iter().map(overlapped_size).sum(),
+            ..Default::default()
+        };
+        if vma.is_shared() {
+            stat.shared = size;
+            if vma.shared_process_count() > 1 {
+                stat.shared_with_others = size;
+            }
+        } else if vma.init_file().is_some() {
+            stat.file = size;
+        } else {
+            stat.anonymous = size;
+        }
+        if vma.perms().can_write() && !vma.is_shared() {
+            stat.data = size - stack;
+        }
+        if vma.is_locked() {
+            stat.locked = size;
+        }
+        stat
+    }
+
+    /// Get the memory statistics of the process, including the fragmentation
+    /// of the multi-VMA chunks used by the process.
+    pub fn mem_stat(&self, pid: pid_t) -> MemStat {
+        let mut stat = MemStat::default();
+        self.for_each_vma(pid, |vma| stat += self.vma_mem_stat(vma));
+
+        let mem_chunks = self.mem_chunks.read().unwrap();
+        for chunk in mem_chunks.iter() {
+            match chunk.internal() {
+                ChunkType::SingleVMA(_) => stat.single_vma_chunks += 1,
+                ChunkType::MultiVMA(internal_manager) => {
+                    stat.multi_vma_chunks += 1;
+                    stat.multi_vma_chunks_size += chunk.range().size();
+                    stat.multi_vma_chunks_free += internal_manager.lock().unwrap().free_size();
+                }
+            }
+        }
+        stat
+    }
+}
+
+/// The memory statistics of a process or a VMA, which are shown in procfs.
+///
+/// The memory of a mapping is committed when it is mapped and is never
+/// swapped, so the resident size of a mapping is always its size.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemStat {
+    /// The size of the mappings
+    pub size: usize,
+    /// The size of the mappings, where a shared mapping is divided evenly by
+    /// the processes sharing it
+    pub proportional_size: usize,
+    /// The size of the private anonymous mappings
+    pub anonymous: usize,
+    /// The size of the private file mappings, including the ELF images
+    pub file: usize,
+    /// The size of the shared mappings managed by the shared memory manager
+    pub shared: usize,
+    /// The size of the shared mappings which are used by other processes too
+    pub shared_with_others: usize,
+    /// The size of the private writable mappings, excluding the stack
+    pub data: usize,
+    pub elf: usize,
+    pub heap: usize,
+    pub stack: usize,
+    /// The size of the mappings locked by mlock and its friends
+    pub locked: usize,
+    /// The number of single-VMA chunks used by the process
+    pub single_vma_chunks: usize,
+    /// The number of multi-VMA chunks used by the process
+    pub multi_vma_chunks: usize,
+    /// The total size of the multi-VMA chunks used by the process
+    pub multi_vma_chunks_size: usize,
+    /// The free size of the multi-VMA chunks used by the process. The free
+    /// space is usually fragmented and may not fit large mappings.
+    pub multi_vma_chunks_free: usize,
+}
+
+impl std::ops::AddAssign for MemStat {
+    fn add_assign(&mut self, other: Self) {
+        self.size += other.size;
+        self.proportional_size += other.proportional_size;
+        self.anonymous += other.anonymous;
+        self.file += other.file;
+        self.shared += other.shared;
+        self.shared_with_others += other.shared_with_others;
+        self.data += other.data;
+        self.elf += other.elf;
+        self.heap += other.heap;
+        self.stack += other.stack;
+        self.locked += other.locked;
+        self.single_vma_chunks += other.single_vma_chunks;
+        self.multi_vma_chunks += other.multi_vma_chunks;
+        self.multi_vma_chunks_size += other.multi_vma_chunks_size;
+        self.multi_vma_chunks_free += other.multi_vma_chunks_free;
+    }
 }
 
 bitflags! {
@@ -656,6 +1387,58 @@ impl Default for MRemapFlags {
     }
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MAdvice {
+    MADV_NORMAL = 0,
+    MADV_RANDOM = 1,
+    MADV_SEQUENTIAL = 2,
+    MADV_WILLNEED = 3,
+    MADV_DONTNEED = 4,
+    MADV_FREE = 8,
+    MADV_REMOVE = 9,
+    MADV_DONTFORK = 10,
+    MADV_DOFORK = 11,
+    MADV_MERGEABLE = 12,
+    MADV_UNMERGEABLE = 13,
+    MADV_HUGEPAGE = 14,
+    MADV_NOHUGEPAGE = 15,
+    MADV_DONTDUMP = 16,
+    MADV_DODUMP = 17,
+    MADV_WIPEONFORK = 18,
+    MADV_KEEPONFORK = 19,
+    MADV_COLD = 20,
+    MADV_PAGEOUT = 21,
+}
+
+impl MAdvice {
+    pub fn from_i32(raw: i32) -> Result<Self> {
+        let advice = match raw {
+            0 => Self::MADV_NORMAL,
+            1 => Self::MADV_RANDOM,
+            2 => Self::MADV_SEQUENTIAL,
+            3 => Self::MADV_WILLNEED,
+            4 => Self::MADV_DONTNEED,
+            8 => Self::MADV_FREE,
+            9 => Self::MADV_REMOVE,
+            10 => Self::MADV_DONTFORK,
+            11 => Self::MADV_DOFORK,
+            12 => Self::MADV_MERGEABLE,
+            13 => Self::MADV_UNMERGEABLE,
+            14 => Self::MADV_HUGEPAGE,
+            15 => Self::MADV_NOHUGEPAGE,
+            16 => Self::MADV_DONTDUMP,
+            17 => Self::MADV_DODUMP,
+            18 => Self::MADV_WIPEONFORK,
+            19 => Self::MADV_KEEPONFORK,
+            20 => Self::MADV_COLD,
+            21 => Self::MADV_PAGEOUT,
+            _ => return_errno!(EINVAL, "unknown madvise advice"),
+        };
+        Ok(advice)
+    }
+}
+
 bitflags! {
     pub struct MSyncFlags : u32 {
         const MS_ASYNC      = 0x1;
@@ -664,6 +1447,37 @@ bitflags! {
     }
 }
 
+bitflags! {
+    pub struct MLockFlags : u32 {
+        const MLOCK_ONFAULT = 0x1;
+    }
+}
+
+impl MLockFlags {
+    pub fn from_u32(bits: u32) -> Result<Self> {
+        MLockFlags::from_bits(bits).ok_or_else(|| errno!(EINVAL, "containing unknown bits"))
+    }
+}
+
+bitflags! {
+    pub struct MLockAllFlags : u32 {
+        const MCL_CURRENT = 0x1;
+        const MCL_FUTURE  = 0x2;
+        const MCL_ONFAULT = 0x4;
+    }
+}
+
+impl MLockAllFlags {
+    pub fn from_u32(bits: u32) -> Result<Self> {
+        let flags = MLockAllFlags::from_bits(bits)
+            .ok_or_else(|| errno!(EINVAL, "containing unknown bits"))?;
+        if !flags.intersects(Self::MCL_CURRENT | Self::MCL_FUTURE) {
+            return_errno!(EINVAL, "must lock either current or future memory");
+        }
+        Ok(flags)
+    }
+}
+
 impl MSyncFlags {
     pub fn from_u32(bits: u32) -> Result<Self> {
         let flags =
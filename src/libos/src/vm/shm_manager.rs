@@ -9,7 +9,9 @@ use rcore_fs::vfs::{FileType, Metadata};
 use std::collections::HashMap;
 use std::sync::{Arc, Weak};
 
-type InodeId = usize;
+// Inode numbers are only unique within a file system, e.g., the RamFS of
+// /dev/shm and that of memfd. (File system, Inode number)
+type InodeId = (usize, usize);
 
 /// Shared VM manager.
 #[derive(Debug)]
@@ -52,7 +54,7 @@ impl ShmManager {
         Self::qualified_for_sharing(options)?;
 
         let backed_file = options.initializer().backed_file().unwrap();
-        let inode_id = backed_file.metadata().inode;
+        let inode_id = Self::inode_id_of_file(backed_file.file_ref());
         let offset = backed_file.offset();
 
         let shared_chunk = match self.shared_chunks.get(&inode_id) {
@@ -183,7 +185,7 @@ impl ShmManager {
             let mut new_vma = Self::vma_of(&new_chunk);
             new_vma.mark_shared();
 
-            let inode_id = backed_file.metadata().inode;
+            let inode_id = Self::inode_id_of_file(backed_file.file_ref());
             debug_assert_eq!(inode_id, Self::inode_id_of(&new_vma));
             (inode_id, new_vma.start())
         };
@@ -268,10 +270,18 @@ impl ShmManager {
     fn inode_id_of(vma: &SgxMutexGuard<VMArea>) -> InodeId {
         debug_assert!(vma.is_shared());
         vma.writeback_file()
-            .map(|(file, _)| file.metadata().unwrap().inode)
+            .map(|(file, _)| Self::inode_id_of_file(file))
             .unwrap()
     }
 
+    pub(super) fn inode_id_of_file(file: &FileRef) -> InodeId {
+        let fs_id = file
+            .fs()
+            .map(|fs| Arc::as_ptr(&fs) as *const u8 as usize)
+            .unwrap_or(0);
+        (fs_id, file.metadata().unwrap().inode)
+    }
+
     fn apply_new_perms_if_higher(vma: &mut SgxMutexGuard<VMArea>, new_perms: VMPerms) {
         debug_assert!(vma.is_shared());
         let old_perms = vma.perms();
@@ -2,7 +2,8 @@ use super::*;
 
 use super::vm_perms::VMPerms;
 use super::vm_range::VMRange;
-use super::vm_util::FileBacked;
+use super::vm_util::{FileBacked, VMInitializer};
+use crate::fs::AsINodeFile;
 
 use intrusive_collections::rbtree::{Link, RBTree};
 use intrusive_collections::{intrusive_adapter, KeyAdapter};
@@ -14,6 +15,25 @@ pub struct VMArea {
     perms: VMPerms,
     file_backed: Option<FileBacked>,
     access: VMAccess,
+    /// Whether the memory is locked by mlock and its friends
+    locked: bool,
+    /// The advice given by madvise that is kept for the memory
+    advice: VMAdvice,
+}
+
+bitflags! {
+    /// The advice given by madvise that is kept for a VMA, like the VM flags of Linux.
+    #[derive(Default)]
+    pub struct VMAdvice: u32 {
+        /// The memory is expected to be accessed randomly (MADV_RANDOM)
+        const RAND_READ = 0x1;
+        /// The memory is expected to be accessed sequentially (MADV_SEQUENTIAL)
+        const SEQ_READ = 0x2;
+        /// The memory is not available to the child (MADV_DONTFORK)
+        const DONT_COPY = 0x4;
+        /// The memory is zeroed for the child (MADV_WIPEONFORK)
+        const WIPE_ON_FORK = 0x8;
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -37,11 +57,13 @@ impl VMArea {
             perms,
             file_backed,
             access: VMAccess::Private(pid),
+            locked: false,
+            advice: VMAdvice::empty(),
         }
     }
 
-    /// Create a new VMArea object that inherits the write-back file (if any), but has
-    /// a new range and permissions.
+    /// Create a new VMArea object that inherits the write-back file (if any), the
+    /// lock state and the advice, but has a new range and permissions.
     pub fn inherits_file_from(
         vma: &VMArea,
         new_range: VMRange,
@@ -72,6 +94,8 @@ impl VMArea {
             perms: new_perms,
             file_backed: new_backed_file,
             access,
+            locked: vma.locked,
+            advice: vma.advice,
         }
     }
 
@@ -125,6 +149,22 @@ impl VMArea {
         self.perms = new_perms;
     }
 
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    pub fn advice(&self) -> VMAdvice {
+        self.advice
+    }
+
+    pub fn set_advice(&mut self, advice: VMAdvice) {
+        self.advice = advice;
+    }
+
     pub fn subtract(&self, other: &VMRange) -> Vec<VMArea> {
         self.deref()
             .subtract(other)
@@ -185,6 +225,14 @@ impl VMArea {
             return false;
         }
 
+        if self.is_locked() != other.is_locked() {
+            return false;
+        }
+
+        if self.advice() != other.advice() {
+            return false;
+        }
+
         let self_writeback_file = self.writeback_file();
         let other_writeback_file = other.writeback_file();
         match (self_writeback_file, other_writeback_file) {
@@ -220,6 +268,14 @@ impl VMArea {
         if left.perms() != right.perms() {
             return false;
         }
+        // The two VMAs must be both locked or both unlocked
+        if left.is_locked() != right.is_locked() {
+            return false;
+        }
+        // The two VMAs must have the same advice
+        if left.advice() != right.advice() {
+            return false;
+        }
 
         // If the two VMAs have write-back files, the files must be the same and
         // the two file regions must be continuous.
@@ -238,14 +294,14 @@ impl VMArea {
     }
 
     /// Flush a file-backed VMA to its file. This has no effect on anonymous VMA.
-    pub fn flush_backed_file(&self) {
+    pub fn flush_backed_file(&self) -> Result<()> {
         self.flush_backed_file_with_cond(|_| true)
     }
 
     /// Same as `flush_backed_file()`, except that an extra condition on the file needs to satisfy.
-    pub fn flush_backed_file_with_cond<F: Fn(&FileRef) -> bool>(&self, cond_fn: F) {
+    pub fn flush_backed_file_with_cond<F: Fn(&FileRef) -> bool>(&self, cond_fn: F) -> Result<()> {
         let (file, file_offset) = match self.writeback_file() {
-            None => return,
+            None => return Ok(()),
             Some((file_and_offset)) => file_and_offset,
         };
         let file_writable = file
@@ -253,12 +309,51 @@ impl VMArea {
             .map(|ac| ac.writable())
             .unwrap_or_default();
         if !file_writable {
-            return;
+            return Ok(());
         }
         if !cond_fn(file) {
-            return;
+            return Ok(());
         }
-        file.write_at(file_offset, unsafe { self.as_slice() });
+        let buf = unsafe { self.as_slice() };
+        // The seals added after the mapping is created do not affect the mapping
+        match file.as_inode_file() {
+            Ok(inode_file) => inode_file.write_back_at(file_offset, buf)?,
+            Err(_) => file.write_at(file_offset, buf)?,
+        };
+        Ok(())
+    }
+
+    /// Reset the memory contents in the range as if the memory was just mapped,
+    /// i.e., anonymous memory is zeroed and file-backed memory is read from
+    /// the file again. Shared memory is kept since the contents are shared.
+    pub fn reset_memory(&self, range: &VMRange) -> Result<()> {
+        let reset_range = match self.range().intersect(range) {
+            Some(reset_range) => reset_range,
+            None => return Ok(()),
+        };
+        if self.is_shared() || self.writeback_file().is_some() {
+            return Ok(());
+        }
+
+        let initializer = match self.init_file() {
+            Some((file, offset)) => VMInitializer::FileBacked {
+                file: FileBacked::new(
+                    file.clone(),
+                    offset + (reset_range.start() - self.start()),
+                    false,
+                ),
+            },
+            None => VMInitializer::FillZeros(),
+        };
+        // The memory must be writable to be reset
+        if !self.perms().can_write() {
+            VMPerms::apply_perms(&reset_range, VMPerms::DEFAULT);
+        }
+        let res = unsafe { initializer.init_slice(reset_range.as_slice_mut()) };
+        if !self.perms().can_write() {
+            VMPerms::apply_perms(&reset_range, self.perms());
+        }
+        res
     }
 
     pub fn is_shared(&self) -> bool {
@@ -268,6 +363,14 @@ impl VMArea {
         }
     }
 
+    /// The number of processes which can access the VMA.
+    pub fn shared_process_count(&self) -> usize {
+        match &self.access {
+            VMAccess::Private(_) => 1,
+            VMAccess::Shared(pid_table) => pid_table.len().max(1),
+        }
+    }
+
     pub fn mark_shared(&mut self) {
         let access = match self.access {
             VMAccess::Private(pid) => VMAccess::Shared(HashMap::from([(pid, 1)])),
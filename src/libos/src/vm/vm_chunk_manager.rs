@@ -83,7 +83,9 @@ impl ChunkManager {
                 continue;
             }
 
-            vma.flush_backed_file();
+            if let Err(e) = vma.flush_backed_file() {
+                warn!("failed to flush the file-backed memory: {:?}", e);
+            }
 
             if !vma.perms().is_default() {
                 VMPerms::apply_perms(vma, VMPerms::default());
@@ -169,7 +171,9 @@ impl ChunkManager {
             };
 
             // File-backed VMA needs to be flushed upon munmap
-            intersection_vma.flush_backed_file();
+            if let Err(e) = intersection_vma.flush_backed_file() {
+                warn!("failed to flush the file-backed memory: {:?}", e);
+            }
             if !&intersection_vma.perms().is_default() {
                 VMPerms::apply_perms(&intersection_vma, VMPerms::default());
             }
@@ -401,7 +405,7 @@ impl ChunkManager {
                 None => continue,
                 Some(vma) => vma,
             };
-            vma.flush_backed_file();
+            vma.flush_backed_file()?;
         }
         Ok(())
     }
@@ -411,8 +415,75 @@ impl ChunkManager {
     pub fn msync_by_file(&mut self, sync_file: &FileRef) {
         for vma_obj in &self.vmas {
             let is_same_file = |file: &FileRef| -> bool { Arc::ptr_eq(&file, &sync_file) };
-            vma_obj.vma().flush_backed_file_with_cond(is_same_file);
+            if let Err(e) = vma_obj.vma().flush_backed_file_with_cond(is_same_file) {
+                warn!("failed to flush the file-backed memory: {:?}", e);
+            }
+        }
+    }
+
+    /// Update the VMAs of the process in the given range by `f`, e.g., to lock the
+    /// memory. The VMAs across the boundaries of the range are split first.
+    pub fn update_vmas<F: Fn(&mut VMArea)>(&mut self, update_range: &VMRange, pid: pid_t, f: F) {
+        self.split_vma_at(update_range.start(), pid);
+        self.split_vma_at(update_range.end(), pid);
+
+        let mut vmas_cursor = self
+            .vmas
+            .lower_bound_mut(Bound::Included(&update_range.start()));
+        while !vmas_cursor.is_null() {
+            let vma = vmas_cursor.get().unwrap().vma();
+            if vma.start() >= update_range.end() {
+                break;
+            }
+            if vma.size() > 0 && vma.belong_to(pid) {
+                debug_assert!(update_range.is_superset_of(vma.range()));
+                let mut new_vma = vma.clone();
+                f(&mut new_vma);
+                if !new_vma.is_the_same_to(vma) {
+                    vmas_cursor.replace_with(VMAObj::new_vma_obj(new_vma));
+                }
+            }
+            vmas_cursor.move_next();
         }
+
+        self.merge_all_vmas();
+    }
+
+    // Split the VMA of the process that contains the address into two VMAs at the address
+    fn split_vma_at(&mut self, addr: usize, pid: pid_t) {
+        let mut vmas_cursor = self.vmas.upper_bound_mut(Bound::Excluded(&addr));
+        if vmas_cursor.is_null() {
+            return;
+        }
+        let mut containing_vma = vmas_cursor.get().unwrap().vma().clone();
+        if !containing_vma.belong_to(pid) || containing_vma.end() <= addr {
+            return;
+        }
+
+        let new_vma = {
+            let range = VMRange::new(addr, containing_vma.end()).unwrap();
+            VMArea::inherits_file_from(
+                &containing_vma,
+                range,
+                containing_vma.perms(),
+                containing_vma.access().clone(),
+            )
+        };
+        containing_vma.set_end(addr);
+
+        vmas_cursor.replace_with(VMAObj::new_vma_obj(containing_vma));
+        vmas_cursor.insert(VMAObj::new_vma_obj(new_vma));
+    }
+
+    /// Reset the memory contents of the VMAs of the process in the given range.
+    pub fn reset_memory(&self, reset_range: &VMRange, pid: pid_t) -> Result<()> {
+        for vma_obj in &self.vmas {
+            let vma = vma_obj.vma();
+            if vma.belong_to(pid) && vma.overlap_with(reset_range) {
+                vma.reset_memory(reset_range)?;
+            }
+        }
+        Ok(())
     }
 
     pub fn find_mmap_region(&self, addr: usize) -> Result<VMRange> {
@@ -9,6 +9,7 @@ use super::vm_area::{VMAccess, VMArea};
 use super::vm_chunk_manager::ChunkManager;
 use super::vm_perms::VMPerms;
 use super::vm_util::*;
+use crate::config::{ConfigOomPolicy, LIBOS_CONFIG};
 use crate::process::{ThreadRef, ThreadStatus};
 
 use std::collections::BTreeSet;
@@ -74,6 +75,23 @@ impl VMManager {
     }
 
     pub fn mmap(&self, options: &VMMapOptions) -> Result<usize> {
+        let res = self.mmap_without_oom_handling(options);
+        // The memory of other processes can't help with the fixed addresses
+        let is_fixed_addr = matches!(options.addr(), VMMapAddr::Need(_) | VMMapAddr::Force(_));
+        match res {
+            Err(e)
+                if e.errno() == ENOMEM
+                    && !is_fixed_addr
+                    && LIBOS_CONFIG.resource_limits.oom_policy == ConfigOomPolicy::KillLargest =>
+            {
+                super::oom_killer::kill_largest_process(None)?;
+                self.mmap_without_oom_handling(options)
+            }
+            res => res,
+        }
+    }
+
+    fn mmap_without_oom_handling(&self, options: &VMMapOptions) -> Result<usize> {
         if options.is_shared() {
             let res = self.internal().mmap_shared_chunk(options);
             match res {
@@ -320,45 +338,113 @@ impl VMManager {
         }
     }
 
-    // Reset memory permission to default (R/W) and reset the memory contents to zero. Currently only used by brk.
+    /// Reset the memory contents of the current process in the range as if the
+    /// memory was just mapped. This is used by brk and madvise(MADV_DONTNEED).
     pub fn reset_memory(&self, reset_range: VMRange) -> Result<()> {
-        let intersect_chunks = {
-            let chunks = self
-                .internal()
-                .chunks
-                .iter()
-                .filter(|&chunk| chunk.range().intersect(&reset_range).is_some())
-                .map(|chunk| chunk.clone())
-                .collect::<Vec<_>>();
+        let current = current!();
+        let pid = current.process().pid();
+        let process_mem_chunks = current.vm().mem_chunks().read().unwrap();
+        for chunk in process_mem_chunks
+            .iter()
+            .filter(|chunk| chunk.range().overlap_with(&reset_range))
+        {
+            match chunk.internal() {
+                ChunkType::MultiVMA(manager) => manager
+                    .lock()
+                    .unwrap()
+                    .chunk_manager()
+                    .reset_memory(&reset_range, pid)?,
+                ChunkType::SingleVMA(vma) => vma.lock().unwrap().reset_memory(&reset_range)?,
+            }
+        }
+        Ok(())
+    }
 
-            // In the heap area, there shouldn't be any default chunks or chunks owned by other process.
-            if chunks
-                .iter()
-                .any(|chunk| !chunk.is_owned_by_current_process() || !chunk.is_single_vma())
-            {
-                return_errno!(EINVAL, "There is something wrong with the intersect chunks");
+    /// Get the size of the memory mapped by the current process in the range.
+    pub fn mapped_size(&self, range: &VMRange) -> usize {
+        let current = current!();
+        let pid = current.process().pid();
+        let intersect_size = |vma: &VMArea| -> usize {
+            if !vma.belong_to(pid) {
+                return 0;
             }
-            chunks
+            vma.range().intersect(range).map_or(0, |range| range.size())
         };
+        let process_mem_chunks = current.vm().mem_chunks().read().unwrap();
+        process_mem_chunks
+            .iter()
+            .filter(|chunk| chunk.range().overlap_with(range))
+            .map(|chunk| match chunk.internal() {
+                ChunkType::MultiVMA(manager) => manager
+                    .lock()
+                    .unwrap()
+                    .chunk_manager()
+                    .vmas()
+                    .iter()
+                    .map(|vma_obj| intersect_size(vma_obj.vma()))
+                    .sum::<usize>(),
+                ChunkType::SingleVMA(vma) => intersect_size(&vma.lock().unwrap()),
+            })
+            .sum()
+    }
 
-        intersect_chunks.iter().for_each(|chunk| {
-            if let ChunkType::SingleVMA(vma) = chunk.internal() {
-                if let Some(intersection_range) = chunk.range().intersect(&reset_range) {
-                    let mut internal_manager = self.internal();
-                    internal_manager.mprotect_single_vma_chunk(
-                        &chunk,
-                        intersection_range,
-                        VMPerms::DEFAULT,
-                    );
+    /// Check whether all the memory in the range is private and anonymous.
+    pub fn is_private_anonymous(&self, range: &VMRange) -> bool {
+        let current = current!();
+        let pid = current.process().pid();
+        let is_private_anonymous = |vma: &VMArea| -> bool {
+            !vma.belong_to(pid)
+                || !vma.overlap_with(range)
+                || (!vma.is_shared() && vma.init_file().is_none())
+        };
+        let process_mem_chunks = current.vm().mem_chunks().read().unwrap();
+        process_mem_chunks
+            .iter()
+            .filter(|chunk| chunk.range().overlap_with(range))
+            .all(|chunk| match chunk.internal() {
+                ChunkType::MultiVMA(manager) => manager
+                    .lock()
+                    .unwrap()
+                    .chunk_manager()
+                    .vmas()
+                    .iter()
+                    .all(|vma_obj| is_private_anonymous(vma_obj.vma())),
+                ChunkType::SingleVMA(vma) => is_private_anonymous(&vma.lock().unwrap()),
+            })
+    }
 
-                    unsafe {
-                        let buf = intersection_range.as_slice_mut();
-                        buf.iter_mut().for_each(|b| *b = 0)
-                    }
+    /// Lock or unlock the memory of the current process in the range.
+    pub fn mlock(&self, lock_range: &VMRange, locked: bool) -> Result<()> {
+        self.update_vmas(lock_range, |vma| vma.set_locked(locked))
+    }
+
+    /// Update the VMAs of the current process in the range by `f`, e.g., to lock
+    /// the memory. The VMAs across the boundaries of the range are split first.
+    pub fn update_vmas<F: Fn(&mut VMArea)>(&self, update_range: &VMRange, f: F) -> Result<()> {
+        let current = current!();
+        let pid = current.process().pid();
+        let chunks = {
+            let process_mem_chunks = current.vm().mem_chunks().read().unwrap();
+            process_mem_chunks
+                .iter()
+                .filter(|chunk| chunk.range().overlap_with(update_range))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        for chunk in chunks {
+            match chunk.internal() {
+                ChunkType::MultiVMA(manager) => manager
+                    .lock()
+                    .unwrap()
+                    .chunk_manager_mut()
+                    .update_vmas(update_range, pid, &f),
+                ChunkType::SingleVMA(_) => {
+                    self.internal()
+                        .update_vmas_of_single_vma_chunk(&chunk, update_range, &f);
                 }
             }
-        });
-
+        }
         Ok(())
     }
 
@@ -387,7 +473,7 @@ impl VMManager {
             }
             ChunkType::SingleVMA(vma) => {
                 let vma = vma.lock().unwrap();
-                vma.flush_backed_file();
+                vma.flush_backed_file()?;
             }
         }
         Ok(())
@@ -408,13 +494,43 @@ impl VMManager {
                         .msync_by_file(sync_file);
                 }
                 ChunkType::SingleVMA(vma) => {
-                    vma.lock()
+                    if let Err(e) = vma
+                        .lock()
                         .unwrap()
-                        .flush_backed_file_with_cond(is_same_file);
+                        .flush_backed_file_with_cond(is_same_file)
+                    {
+                        warn!("failed to flush the file-backed memory: {:?}", e);
+                    }
                 }
             });
     }
 
+    /// Check whether the inode of the given file is mapped as shared and writable
+    /// by any process, through any open file of the inode.
+    pub fn has_writable_shared_mapping(&self, file: &FileRef) -> bool {
+        let inode_id = ShmManager::inode_id_of_file(file);
+        let is_writable_mapping = |vma: &VMArea| -> bool {
+            vma.perms().can_write()
+                && vma
+                    .writeback_file()
+                    .map(|(backed_file, _)| ShmManager::inode_id_of_file(backed_file) == inode_id)
+                    .unwrap_or(false)
+        };
+        self.internal()
+            .chunks
+            .iter()
+            .any(|chunk| match chunk.internal() {
+                ChunkType::MultiVMA(manager) => manager
+                    .lock()
+                    .unwrap()
+                    .chunk_manager()
+                    .vmas()
+                    .iter()
+                    .any(|vma_obj| is_writable_mapping(vma_obj.vma())),
+                ChunkType::SingleVMA(vma) => is_writable_mapping(&vma.lock().unwrap()),
+            })
+    }
+
     pub fn mremap(&self, options: &VMRemapOptions) -> Result<usize> {
         let old_addr = options.old_addr();
         let old_size = options.old_size();
@@ -519,6 +635,8 @@ impl VMManager {
         mem_chunks.clear();
 
         assert!(mem_chunks.len() == 0);
+        // The memory is no longer charged to the quota of the app
+        thread.vm().uncharge_all_mem_quota();
     }
 }
 
@@ -639,7 +757,9 @@ impl InternalVMManager {
         };
 
         // File-backed VMA needs to be flushed upon munmap
-        intersection_vma.flush_backed_file();
+        if let Err(e) = intersection_vma.flush_backed_file() {
+            warn!("failed to flush the file-backed memory: {:?}", e);
+        }
 
         // Reset memory permissions
         if !&intersection_vma.perms().is_default() {
@@ -761,7 +881,9 @@ impl InternalVMManager {
         {
             let vma = chunk.get_vma_for_single_vma_chunk();
             // Flush memory contents to backed file
-            vma.flush_backed_file();
+            if let Err(e) = vma.flush_backed_file() {
+                warn!("failed to flush the file-backed memory: {:?}", e);
+            }
             // Reset memory permissions
             if !vma.perms().is_default() {
                 VMPerms::apply_perms(&vma, VMPerms::default());
@@ -932,6 +1054,59 @@ impl InternalVMManager {
         Ok(())
     }
 
+    // Update the part of the single VMA chunk in the range by `f`. The VMA is split
+    // into multiple chunks if the range covers part of it.
+    pub fn update_vmas_of_single_vma_chunk<F: Fn(&mut VMArea)>(
+        &mut self,
+        chunk: &ChunkRef,
+        update_range: &VMRange,
+        f: F,
+    ) {
+        let vma = match chunk.internal() {
+            ChunkType::MultiVMA(_) => {
+                unreachable!();
+            }
+            ChunkType::SingleVMA(vma) => vma,
+        };
+
+        let mut updated_vmas = {
+            let mut containing_vma = vma.lock().unwrap();
+            let mut new_vma = containing_vma.clone();
+            f(&mut new_vma);
+            if new_vma.is_the_same_to(&containing_vma) {
+                return;
+            }
+            let intersection_range = match containing_vma.range().intersect(update_range) {
+                Some(range) => range,
+                None => return,
+            };
+            // Like mprotect, a shared VMA is updated in an all-or-nothing manner.
+            if containing_vma.is_shared() || intersection_range == *containing_vma.range() {
+                *containing_vma = new_vma;
+                return;
+            }
+
+            let mut updated_vma = VMArea::inherits_file_from(
+                &containing_vma,
+                intersection_range,
+                containing_vma.perms(),
+                containing_vma.access().clone(),
+            );
+            f(&mut updated_vma);
+            let mut updated_vmas = containing_vma.subtract(&intersection_range);
+            updated_vmas.push(updated_vma);
+            updated_vmas
+        };
+
+        // The first VMA takes the place of the old chunk and the others are new chunks
+        let current = current!();
+        let first_vma = updated_vmas.remove(0);
+        self.update_single_vma_chunk(&current, chunk, first_vma);
+        updated_vmas.into_iter().for_each(|vma| {
+            self.add_new_chunk(&current, vma);
+        });
+    }
+
     // Must make sure that all the chunks are valid before adding new chunks
     fn add_new_chunk(&mut self, current_thread: &ThreadRef, new_vma: VMArea) {
         let new_vma_chunk = Arc::new(Chunk::new_chunk_with_vma(new_vma));
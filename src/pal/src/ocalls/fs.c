@@ -4,6 +4,7 @@
 #include <unistd.h>
 #include <sys/ioctl.h>
 #include <sys/vfs.h>
+#include <sys/xattr.h>
 
 void occlum_ocall_sync(void) {
     sync();
@@ -45,4 +46,22 @@ int occlum_ocall_ioctl(int fd, int request, void *arg, size_t len) {
 
 int occlum_ocall_statfs(const char *path, struct statfs *buf) {
     return statfs(path, buf);
-}
\ No newline at end of file
+}
+
+ssize_t occlum_ocall_lgetxattr(const char *path, const char *name, void *value,
+                               size_t size) {
+    return lgetxattr(path, name, value, size);
+}
+
+int occlum_ocall_lsetxattr(const char *path, const char *name, const void *value,
+                           size_t size, int flags) {
+    return lsetxattr(path, name, value, size, flags);
+}
+
+ssize_t occlum_ocall_llistxattr(const char *path, char *list, size_t size) {
+    return llistxattr(path, list, size);
+}
+
+int occlum_ocall_lremovexattr(const char *path, const char *name) {
+    return lremovexattr(path, name);
+}
@@ -1,3 +1,4 @@
+#define _GNU_SOURCE
 #include <sys/time.h>
 #include <sys/types.h>
 #include <sys/select.h>
@@ -5,6 +6,7 @@
 #include <errno.h>
 #include <stdio.h>
 #include <stddef.h>
+#include <stdlib.h>
 #include "ocalls.h"
 
 ssize_t occlum_ocall_sendmsg(int sockfd,
@@ -56,35 +58,59 @@ ssize_t occlum_ocall_recvmsg(int sockfd,
     return ret;
 }
 
-int occlum_ocall_poll(struct pollfd *fds,
-                      nfds_t nfds,
-                      struct timeval *timeout,
-                      int efd) {
-    struct timeval start_tv, end_tv, elapsed_tv;
-    int real_timeout = (timeout == NULL) ? -1 :
-                       (timeout->tv_sec * 1000 + timeout->tv_usec / 1000);
-    if (timeout != NULL) {
-        gettimeofday(&start_tv, NULL);
+int occlum_ocall_recvmmsg(int sockfd,
+                          struct iovec *msg_iovs,
+                          size_t msg_iovs_len,
+                          const size_t *msg_iovlens,
+                          void *msg_names,
+                          size_t msg_names_len,
+                          socklen_t *msg_namelens,
+                          void *msg_controls,
+                          size_t msg_controls_len,
+                          size_t *msg_controllens,
+                          int *msg_flags_recv,
+                          unsigned int *msg_lens,
+                          unsigned int vlen,
+                          int flags,
+                          struct timespec *timeout) {
+    struct mmsghdr *msgvec = calloc(vlen, sizeof(struct mmsghdr));
+    if (msgvec == NULL) {
+        errno = ENOMEM;
+        return -1;
     }
 
-    int ret = poll(fds, nfds, real_timeout);
-
-    if (timeout != NULL) {
-        gettimeofday(&end_tv, NULL);
-        timersub(&end_tv, &start_tv, &elapsed_tv);
-        if timercmp(timeout, &elapsed_tv, >= ) {
-            timersub(timeout, &elapsed_tv, timeout);
-        } else {
-            timeout->tv_sec = 0;
-            timeout->tv_usec = 0;
+    // Split the flattened buffers into the messages
+    size_t iov_pos = 0, name_pos = 0, control_pos = 0;
+    for (unsigned int i = 0; i < vlen; i++) {
+        struct msghdr *msg = &msgvec[i].msg_hdr;
+        if (msg_iovlens[i] > msg_iovs_len - iov_pos ||
+                msg_namelens[i] > msg_names_len - name_pos ||
+                msg_controllens[i] > msg_controls_len - control_pos) {
+            free(msgvec);
+            errno = EINVAL;
+            return -1;
         }
+
+        msg->msg_iov = msg_iovs + iov_pos;
+        msg->msg_iovlen = msg_iovlens[i];
+        iov_pos += msg_iovlens[i];
+        msg->msg_name = msg_namelens[i] > 0 ? (char *)msg_names + name_pos : NULL;
+        msg->msg_namelen = msg_namelens[i];
+        name_pos += msg_namelens[i];
+        msg->msg_control = msg_controllens[i] > 0 ? (char *)msg_controls + control_pos : NULL;
+        msg->msg_controllen = msg_controllens[i];
+        control_pos += msg_controllens[i];
     }
 
+    int ret = recvmmsg(sockfd, msgvec, vlen, flags, timeout);
     int saved_errno = errno;
-    // clear the status of the eventfd
-    uint64_t u = 0;
-    read(efd, &u, sizeof(uint64_t));
-    // restore the errno of poll
+    for (int i = 0; i < ret; i++) {
+        msg_namelens[i] = msgvec[i].msg_hdr.msg_namelen;
+        msg_controllens[i] = msgvec[i].msg_hdr.msg_controllen;
+        msg_flags_recv[i] = msgvec[i].msg_hdr.msg_flags;
+        msg_lens[i] = msgvec[i].msg_len;
+    }
+    free(msgvec);
     errno = saved_errno;
     return ret;
 }
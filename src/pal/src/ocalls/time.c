@@ -32,6 +32,14 @@ int occlum_ocall_thread_getcpuclock(struct timespec *tp) {
     return clock_gettime(thread_clock_id, tp);
 }
 
+// The CPU-time clock of an arbitrary thread, which is encoded in the same way
+// as MAKE_THREAD_CPUCLOCK(tid, CPUCLOCK_SCHED) in the Linux kernel
+#define THREAD_CPUCLOCK_ID(tid) ((~(clockid_t)(tid) << 3) | 6)
+
+int occlum_ocall_get_thread_cputime(pid_t host_tid, struct timespec *tp) {
+    return clock_gettime(THREAD_CPUCLOCK_ID(host_tid), tp);
+}
+
 void occlum_ocall_rdtsc(uint32_t *low, uint32_t *high) {
     uint64_t rax, rdx;
     asm volatile("rdtsc" : "=a"(rax), "=d"(rdx));
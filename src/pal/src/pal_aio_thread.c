@@ -0,0 +1,120 @@
+#include <pthread.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include "Enclave_u.h"
+#include "pal_aio_thread.h"
+#include "pal_enclave.h"
+#include "pal_error.h"
+#include "pal_log.h"
+#include "errno2str.h"
+
+// The range of the time to wait before retrying the ECall if no TCS is free
+#define MIN_BACKOFF_US      1000
+#define MAX_BACKOFF_US      100000
+
+// The host threads that execute the AIO requests in the enclave. The number of
+// them is given by the LibOS, which is the number of TCSs reserved for them by
+// tools/gen_internal_conf according to Occlum.json.
+static pthread_t *threads = NULL;
+static int num_threads = 0;
+static volatile int is_running = 0;
+static pthread_mutex_t lock = PTHREAD_MUTEX_INITIALIZER;
+
+static void *thread_func(void *_data) {
+    sgx_enclave_id_t eid = pal_get_enclave_id();
+    useconds_t backoff_us = MIN_BACKOFF_US;
+
+    // The ECall returns when the worker is idle for a while, which gives us a
+    // chance to check whether to stop
+    while (__atomic_load_n(&is_running, __ATOMIC_ACQUIRE)) {
+        int ecall_ret = 0;
+        sgx_status_t ecall_status = occlum_ecall_run_aio_worker(eid, &ecall_ret);
+        if (ecall_status == SGX_ERROR_OUT_OF_TCS) {
+            // The TCSs are taken by the LibOS threads for now, so try again later
+            usleep(backoff_us);
+            backoff_us = backoff_us * 2 < MAX_BACKOFF_US ? backoff_us * 2 : MAX_BACKOFF_US;
+            continue;
+        }
+        backoff_us = MIN_BACKOFF_US;
+
+        // Stop the worker instead of the whole program, while the other
+        // workers may still be able to execute the requests
+        if (ecall_status != SGX_SUCCESS) {
+            const char *sgx_err = pal_get_sgx_error_msg(ecall_status);
+            PAL_ERROR("Failed to do ECall: occlum_ecall_run_aio_worker with error code 0x%x: %s",
+                      ecall_status, sgx_err);
+            break;
+        }
+        if (ecall_ret < 0) {
+            int errno_ = -ecall_ret;
+            PAL_ERROR("Unexpected error from occlum_ecall_run_aio_worker: %s", errno2str(errno_));
+            break;
+        }
+    }
+
+    return NULL;
+}
+
+// Start the AIO threads if they are not running. This is called by the LibOS
+// when AIO is used for the first time, so the threads take no TCS otherwise.
+int occlum_ocall_start_aio_threads(int max_num_threads) {
+    int ret = 0;
+
+    pthread_mutex_lock(&lock);
+    if (is_running) {
+        goto out;
+    }
+
+    if (threads == NULL) {
+        threads = calloc(max_num_threads, sizeof(pthread_t));
+        if (threads == NULL) {
+            PAL_ERROR("Failed to allocate the AIO threads");
+            ret = -1;
+            goto out;
+        }
+    }
+
+    __atomic_store_n(&is_running, 1, __ATOMIC_RELEASE);
+    for (num_threads = 0; num_threads < max_num_threads; num_threads++) {
+        int create_ret = 0;
+        if ((create_ret = pthread_create(&threads[num_threads], NULL, thread_func, NULL))) {
+            // The threads that have been started are enough to execute the requests
+            if (num_threads > 0) {
+                break;
+            }
+            __atomic_store_n(&is_running, 0, __ATOMIC_RELEASE);
+
+            errno = create_ret;
+            PAL_ERROR("Failed to start the AIO threads: %s", errno2str(errno));
+            ret = -1;
+            goto out;
+        }
+    }
+out:
+    pthread_mutex_unlock(&lock);
+    return ret;
+}
+
+int pal_aio_threads_stop(void) {
+    pthread_mutex_lock(&lock);
+    // The AIO threads are not started unless AIO is used
+    if (!is_running) {
+        pthread_mutex_unlock(&lock);
+        return 0;
+    }
+
+    __atomic_store_n(&is_running, 0, __ATOMIC_RELEASE);
+
+    int ret = 0;
+    for (int i = 0; i < num_threads; i++) {
+        int join_ret = 0;
+        if ((join_ret = pthread_join(threads[i], NULL))) {
+            errno = join_ret;
+            PAL_ERROR("Failed to free the AIO thread: %s", errno2str(errno));
+            ret = -1;
+        }
+    }
+    num_threads = 0;
+    pthread_mutex_unlock(&lock);
+    return ret;
+}
@@ -0,0 +1,6 @@
+#ifndef __PAL_AIO_THREAD_H__
+#define __PAL_AIO_THREAD_H__
+
+int pal_aio_threads_stop(void);
+
+#endif /* __PAL_AIO_THREAD_H__ */
@@ -1,5 +1,6 @@
 #include <occlum_pal_api.h>
 #include "Enclave_u.h"
+#include "pal_aio_thread.h"
 #include "pal_enclave.h"
 #include "pal_error.h"
 #include "pal_load_file.h"
@@ -149,11 +150,15 @@ int occlum_pal_init(const struct occlum_pal_attr *attr) {
 
     if (pal_run_init_process() < 0) {
         PAL_ERROR("Failed to run the init process: %s", errno2str(errno));
-        goto stop_interrupt_thread;
+        goto stop_aio_threads;
     }
 
     return 0;
 
+stop_aio_threads:
+    if (pal_aio_threads_stop() < 0) {
+        PAL_WARN("Cannot stop the AIO threads: %s", errno2str(errno));
+    }
 stop_interrupt_thread:
     if (pal_interrupt_thread_stop() < 0) {
         PAL_WARN("Cannot stop the interrupt thread: %s", errno2str(errno));
@@ -284,6 +289,11 @@ int occlum_pal_destroy(void) {
     }
 
     int ret = 0;
+    if (pal_aio_threads_stop() < 0) {
+        ret = -1;
+        PAL_WARN("Cannot stop the AIO threads: %s", errno2str(errno));
+    }
+
     if (pal_interrupt_thread_stop() < 0) {
         ret = -1;
         PAL_WARN("Cannot stop the interrupt thread: %s", errno2str(errno));
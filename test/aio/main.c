@@ -0,0 +1,375 @@
+#define _GNU_SOURCE
+#include <sys/eventfd.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <linux/aio_abi.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define FILE_PATH       "/root/test_aio_file.txt"
+#define BUF_SIZE        4096
+
+static int io_setup(unsigned nr_events, aio_context_t *ctx) {
+    return syscall(SYS_io_setup, nr_events, ctx);
+}
+
+static int io_destroy(aio_context_t ctx) {
+    return syscall(SYS_io_destroy, ctx);
+}
+
+static int io_submit(aio_context_t ctx, long nr, struct iocb **iocbs) {
+    return syscall(SYS_io_submit, ctx, nr, iocbs);
+}
+
+static int io_cancel(aio_context_t ctx, struct iocb *iocb, struct io_event *result) {
+    return syscall(SYS_io_cancel, ctx, iocb, result);
+}
+
+static int io_getevents(aio_context_t ctx, long min_nr, long nr, struct io_event *events,
+                        struct timespec *timeout) {
+    return syscall(SYS_io_getevents, ctx, min_nr, nr, events, timeout);
+}
+
+static void prep_rw(struct iocb *iocb, int opcode, int fd, void *buf, size_t len,
+                    off_t offset) {
+    memset(iocb, 0, sizeof(*iocb));
+    iocb->aio_lio_opcode = opcode;
+    iocb->aio_fildes = fd;
+    iocb->aio_buf = (uint64_t)(uintptr_t)buf;
+    iocb->aio_nbytes = len;
+    iocb->aio_offset = offset;
+    iocb->aio_data = (uint64_t)(uintptr_t)iocb;
+}
+
+// Wait for the events, and check that each request completes with the expected result
+static int wait_events(aio_context_t ctx, struct iocb **iocbs, int nr, int64_t *expected) {
+    struct io_event events[8];
+    struct timespec timeout = { .tv_sec = 5, .tv_nsec = 0 };
+
+    if (io_getevents(ctx, nr, nr, events, &timeout) != nr) {
+        THROW_ERROR("failed to get the events");
+    }
+    for (int i = 0; i < nr; i++) {
+        int j;
+        for (j = 0; j < nr; j++) {
+            if (events[i].obj == (uint64_t)(uintptr_t)iocbs[j]) {
+                break;
+            }
+        }
+        if (j == nr || events[i].data != iocbs[j]->aio_data) {
+            THROW_ERROR("the event does not match any request");
+        }
+        if (events[i].res != expected[j]) {
+            THROW_ERROR("the result of request %d is %lld, expected %lld", j,
+                        (long long)events[i].res, (long long)expected[j]);
+        }
+    }
+    return 0;
+}
+
+static int open_test_file(void) {
+    int fd = open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0666);
+    if (fd < 0) {
+        THROW_ERROR("failed to open the test file");
+    }
+    return fd;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_setup_and_destroy(void) {
+    aio_context_t ctx = 1;
+
+    if (io_setup(8, &ctx) != -1 || errno != EINVAL) {
+        THROW_ERROR("a non-zero context should be rejected");
+    }
+    ctx = 0;
+    if (io_setup(0, &ctx) != -1 || errno != EINVAL) {
+        THROW_ERROR("zero events should be rejected");
+    }
+    if (io_setup(8, &ctx) < 0 || ctx == 0) {
+        THROW_ERROR("failed to set up the context");
+    }
+    if (io_destroy(ctx) < 0) {
+        THROW_ERROR("failed to destroy the context");
+    }
+    if (io_destroy(ctx) != -1 || errno != EINVAL) {
+        THROW_ERROR("a destroyed context should be invalid");
+    }
+    return 0;
+}
+
+static int test_write_and_read(void) {
+    static char write_buf[BUF_SIZE], read_buf[BUF_SIZE];
+    aio_context_t ctx = 0;
+    struct iocb iocbs[2];
+    struct iocb *iocb_ptrs[2] = { &iocbs[0], &iocbs[1] };
+    struct iovec iovs[2];
+
+    int fd = open_test_file();
+    if (fd < 0 || io_setup(8, &ctx) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    memset(write_buf, 'a', BUF_SIZE / 2);
+    memset(write_buf + BUF_SIZE / 2, 'b', BUF_SIZE / 2);
+
+    // Write the first half with pwrite and the second half with pwritev
+    iovs[0] = (struct iovec) { .iov_base = write_buf + BUF_SIZE / 2, .iov_len = BUF_SIZE / 4 };
+    iovs[1] = (struct iovec) {
+        .iov_base = write_buf + BUF_SIZE * 3 / 4, .iov_len = BUF_SIZE / 4
+    };
+    prep_rw(&iocbs[0], IOCB_CMD_PWRITE, fd, write_buf, BUF_SIZE / 2, 0);
+    prep_rw(&iocbs[1], IOCB_CMD_PWRITEV, fd, iovs, 2, BUF_SIZE / 2);
+    if (io_submit(ctx, 2, iocb_ptrs) != 2) {
+        THROW_ERROR("failed to submit the writes");
+    }
+    int64_t write_results[2] = { BUF_SIZE / 2, BUF_SIZE / 2 };
+    if (wait_events(ctx, iocb_ptrs, 2, write_results) < 0) {
+        return -1;
+    }
+
+    // Read the whole file with pread, and read beyond the end of the file
+    prep_rw(&iocbs[0], IOCB_CMD_PREAD, fd, read_buf, BUF_SIZE, 0);
+    prep_rw(&iocbs[1], IOCB_CMD_PREAD, fd, read_buf, BUF_SIZE, BUF_SIZE);
+    if (io_submit(ctx, 2, iocb_ptrs) != 2) {
+        THROW_ERROR("failed to submit the reads");
+    }
+    int64_t read_results[2] = { BUF_SIZE, 0 };
+    if (wait_events(ctx, iocb_ptrs, 2, read_results) < 0) {
+        return -1;
+    }
+    if (memcmp(write_buf, read_buf, BUF_SIZE) != 0) {
+        THROW_ERROR("the data read is not the data written");
+    }
+
+    io_destroy(ctx);
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+static int test_write_buf_unmapped_after_submit(void) {
+    static char read_buf[BUF_SIZE];
+    aio_context_t ctx = 0;
+    struct iocb iocb;
+    struct iocb *iocb_ptr = &iocb;
+
+    int fd = open_test_file();
+    if (fd < 0 || io_setup(8, &ctx) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    char *write_buf = mmap(NULL, BUF_SIZE, PROT_READ | PROT_WRITE,
+                           MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (write_buf == MAP_FAILED) {
+        THROW_ERROR("failed to map the buffer");
+    }
+    memset(write_buf, 'c', BUF_SIZE);
+
+    // The data to write is taken at submission time, so the buffer can be
+    // unmapped before the request completes
+    prep_rw(&iocb, IOCB_CMD_PWRITE, fd, write_buf, BUF_SIZE, 0);
+    if (io_submit(ctx, 1, &iocb_ptr) != 1) {
+        THROW_ERROR("failed to submit the write");
+    }
+    if (munmap(write_buf, BUF_SIZE) < 0) {
+        THROW_ERROR("failed to unmap the buffer");
+    }
+    int64_t write_result = BUF_SIZE;
+    if (wait_events(ctx, &iocb_ptr, 1, &write_result) < 0) {
+        return -1;
+    }
+
+    if (pread(fd, read_buf, BUF_SIZE, 0) != BUF_SIZE) {
+        THROW_ERROR("failed to read the file");
+    }
+    for (int i = 0; i < BUF_SIZE; i++) {
+        if (read_buf[i] != 'c') {
+            THROW_ERROR("the data read is not the data written");
+        }
+    }
+
+    io_destroy(ctx);
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+static int test_eventfd_notification(void) {
+    char buf[16] = "hello";
+    aio_context_t ctx = 0;
+    struct iocb iocbs[2];
+    struct iocb *iocb_ptrs[2] = { &iocbs[0], &iocbs[1] };
+    uint64_t count = 0;
+
+    int fd = open_test_file();
+    int efd = eventfd(0, 0);
+    if (fd < 0 || efd < 0 || io_setup(8, &ctx) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    prep_rw(&iocbs[0], IOCB_CMD_PWRITE, fd, buf, strlen(buf), 0);
+    prep_rw(&iocbs[1], IOCB_CMD_FSYNC, fd, NULL, 0, 0);
+    for (int i = 0; i < 2; i++) {
+        iocbs[i].aio_flags = IOCB_FLAG_RESFD;
+        iocbs[i].aio_resfd = efd;
+    }
+    if (io_submit(ctx, 2, iocb_ptrs) != 2) {
+        THROW_ERROR("failed to submit the requests");
+    }
+
+    // Each completion increases the counter of the eventfd by one
+    while (count < 2) {
+        uint64_t val;
+        if (read(efd, &val, sizeof(val)) != sizeof(val)) {
+            THROW_ERROR("failed to read the eventfd");
+        }
+        count += val;
+    }
+    if (count != 2) {
+        THROW_ERROR("the eventfd counter is %llu", (unsigned long long)count);
+    }
+    int64_t results[2] = { strlen(buf), 0 };
+    if (wait_events(ctx, iocb_ptrs, 2, results) < 0) {
+        return -1;
+    }
+
+    // An fd that is not an eventfd is rejected
+    iocbs[0].aio_resfd = fd;
+    if (io_submit(ctx, 1, iocb_ptrs) != -1 || errno != EINVAL) {
+        THROW_ERROR("a non-eventfd aio_resfd should be rejected");
+    }
+
+    io_destroy(ctx);
+    close(efd);
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+static int test_getevents_timeout(void) {
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 10 * 1000 * 1000 };
+    struct io_event event;
+    aio_context_t ctx = 0;
+
+    if (io_setup(8, &ctx) < 0) {
+        THROW_ERROR("failed to set up the context");
+    }
+    if (io_getevents(ctx, 1, 1, &event, &timeout) != 0) {
+        THROW_ERROR("no event should be returned");
+    }
+    if (io_getevents(ctx, 2, 1, &event, &timeout) != -1 || errno != EINVAL) {
+        THROW_ERROR("min_nr greater than nr should be rejected");
+    }
+    io_destroy(ctx);
+    return 0;
+}
+
+static int test_invalid_requests(void) {
+    char buf[16];
+    aio_context_t ctx = 0;
+    struct iocb iocbs[2];
+    struct iocb *iocb_ptrs[2] = { &iocbs[0], &iocbs[1] };
+    struct io_event event;
+
+    int fd = open_test_file();
+    int wronly_fd = open(FILE_PATH, O_WRONLY);
+    if (fd < 0 || wronly_fd < 0 || io_setup(1, &ctx) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    prep_rw(&iocbs[0], 100, fd, buf, sizeof(buf), 0);
+    if (io_submit(ctx, 1, iocb_ptrs) != -1 || errno != EINVAL) {
+        THROW_ERROR("an invalid opcode should be rejected");
+    }
+    prep_rw(&iocbs[0], IOCB_CMD_PREAD, -1, buf, sizeof(buf), 0);
+    if (io_submit(ctx, 1, iocb_ptrs) != -1 || errno != EBADF) {
+        THROW_ERROR("an invalid fd should be rejected");
+    }
+    prep_rw(&iocbs[0], IOCB_CMD_PREAD, wronly_fd, buf, sizeof(buf), 0);
+    if (io_submit(ctx, 1, iocb_ptrs) != -1 || errno != EBADF) {
+        THROW_ERROR("reading a write-only file should be rejected");
+    }
+    prep_rw(&iocbs[0], IOCB_CMD_PREAD, fd, buf, sizeof(buf), -1);
+    if (io_submit(ctx, 1, iocb_ptrs) != -1 || errno != EINVAL) {
+        THROW_ERROR("a negative offset should be rejected");
+    }
+
+    // The context has only one slot, so the second request is not submitted
+    prep_rw(&iocbs[0], IOCB_CMD_PREAD, fd, buf, sizeof(buf), 0);
+    prep_rw(&iocbs[1], IOCB_CMD_PREAD, fd, buf, sizeof(buf), 0);
+    if (io_submit(ctx, 2, iocb_ptrs) != 1) {
+        THROW_ERROR("only one request should be submitted");
+    }
+    int64_t result = 0;
+    if (wait_events(ctx, iocb_ptrs, 1, &result) < 0) {
+        return -1;
+    }
+
+    // A completed request cannot be canceled
+    if (io_cancel(ctx, &iocbs[0], &event) != -1 || errno != EINVAL) {
+        THROW_ERROR("a completed request should not be canceled");
+    }
+
+    io_destroy(ctx);
+    close(wronly_fd);
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+static int test_destroy_with_inflight_requests(void) {
+    static char buf[BUF_SIZE];
+    aio_context_t ctx = 0;
+    struct iocb iocbs[8];
+    struct iocb *iocb_ptrs[8];
+
+    int fd = open_test_file();
+    if (fd < 0 || io_setup(8, &ctx) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    for (int i = 0; i < 8; i++) {
+        prep_rw(&iocbs[i], IOCB_CMD_PWRITE, fd, buf, BUF_SIZE, i * BUF_SIZE);
+        iocb_ptrs[i] = &iocbs[i];
+    }
+    if (io_submit(ctx, 8, iocb_ptrs) != 8) {
+        THROW_ERROR("failed to submit the requests");
+    }
+    // The requests are either completed or canceled before io_destroy returns
+    if (io_destroy(ctx) < 0) {
+        THROW_ERROR("failed to destroy the context");
+    }
+
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_setup_and_destroy),
+    TEST_CASE(test_write_and_read),
+    TEST_CASE(test_write_buf_unmapped_after_submit),
+    TEST_CASE(test_eventfd_notification),
+    TEST_CASE(test_getevents_timeout),
+    TEST_CASE(test_invalid_requests),
+    TEST_CASE(test_destroy_with_inflight_requests),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,320 @@
+#define _GNU_SOURCE
+#include <sys/procfs.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/types.h>
+#include <sys/wait.h>
+#include <elf.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <sched.h>
+#include <signal.h>
+#include <spawn.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// The directory is the same as process.core_dump.dir in Occlum.json
+#define CORE_DUMP_DIR       "/host/core"
+
+// The RLIMIT_CORE of the child process whose core file is truncated
+#define CORE_SIZE_LIMIT     (64 * 1024)
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int spawn_and_wait_crashed_child(const char *cmd, pid_t *child_pid, int *status) {
+    char *child_argv[] = {"core_dump", (char *)cmd, NULL};
+
+    int ret = posix_spawn(child_pid, "/bin/core_dump", NULL, NULL, child_argv, NULL);
+    if (ret != 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    ret = waitpid(*child_pid, status, 0);
+    if (ret != *child_pid) {
+        THROW_ERROR("failed to wait the child process");
+    }
+    if (!WIFSIGNALED(*status) || WTERMSIG(*status) != SIGABRT) {
+        THROW_ERROR("child process is expected to be killed by SIGABRT");
+    }
+    return 0;
+}
+
+static void get_core_file_path(pid_t pid, char *buf, size_t len) {
+    snprintf(buf, len, "%s/core.%d", CORE_DUMP_DIR, pid);
+}
+
+// The NT_PRSTATUS note of the thread receiving the signal comes first, which is the
+// main thread in the test cases, followed by the ones of the other threads
+static int check_notes(int fd, const Elf64_Phdr *phdr, pid_t pid, int num_threads) {
+    char *notes = malloc(phdr->p_filesz);
+    if (notes == NULL) {
+        THROW_ERROR("failed to allocate the buffer for notes");
+    }
+    if (pread(fd, notes, phdr->p_filesz, phdr->p_offset) != phdr->p_filesz) {
+        free(notes);
+        THROW_ERROR("failed to read the notes");
+    }
+
+    int num_prstatus = 0;
+    int found_auxv = 0;
+    size_t offset = 0;
+    while (offset + sizeof(Elf64_Nhdr) <= phdr->p_filesz) {
+        Elf64_Nhdr *nhdr = (Elf64_Nhdr *)(notes + offset);
+        offset += sizeof(Elf64_Nhdr);
+        const char *name = notes + offset;
+        offset += (nhdr->n_namesz + 3) & ~3;
+        const char *desc = notes + offset;
+        offset += (nhdr->n_descsz + 3) & ~3;
+        if (offset > phdr->p_filesz || strcmp(name, "CORE") != 0) {
+            break;
+        }
+
+        if (nhdr->n_type == NT_PRSTATUS) {
+            const struct elf_prstatus *prstatus = (const struct elf_prstatus *)desc;
+            if (nhdr->n_descsz != sizeof(struct elf_prstatus) ||
+                    (num_prstatus == 0 && prstatus->pr_pid != pid) ||
+                    prstatus->pr_cursig != SIGABRT) {
+                free(notes);
+                THROW_ERROR("invalid NT_PRSTATUS note");
+            }
+            num_prstatus++;
+        } else if (nhdr->n_type == NT_AUXV) {
+            const Elf64_auxv_t *auxv = (const Elf64_auxv_t *)desc;
+            size_t num_entries = nhdr->n_descsz / sizeof(Elf64_auxv_t);
+            if (num_entries == 0 || auxv[num_entries - 1].a_type != AT_NULL) {
+                free(notes);
+                THROW_ERROR("invalid NT_AUXV note");
+            }
+            found_auxv = 1;
+        }
+    }
+    free(notes);
+
+    if (num_prstatus != num_threads || !found_auxv) {
+        THROW_ERROR("the notes are incomplete");
+    }
+    return 0;
+}
+
+static int check_core_file(const char *path, pid_t pid, int num_threads) {
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        THROW_ERROR("failed to open the core file");
+    }
+
+    Elf64_Ehdr ehdr;
+    if (read(fd, &ehdr, sizeof(ehdr)) != sizeof(ehdr)) {
+        close(fd);
+        THROW_ERROR("failed to read the ELF header");
+    }
+    if (memcmp(ehdr.e_ident, ELFMAG, SELFMAG) != 0 || ehdr.e_type != ET_CORE ||
+            ehdr.e_machine != EM_X86_64 || ehdr.e_phentsize != sizeof(Elf64_Phdr)) {
+        close(fd);
+        THROW_ERROR("invalid ELF header");
+    }
+
+    int num_notes = 0;
+    int num_loads = 0;
+    for (int i = 0; i < ehdr.e_phnum; i++) {
+        Elf64_Phdr phdr;
+        off_t offset = ehdr.e_phoff + i * sizeof(phdr);
+        if (pread(fd, &phdr, sizeof(phdr), offset) != sizeof(phdr)) {
+            close(fd);
+            THROW_ERROR("failed to read the program header");
+        }
+        if (phdr.p_type == PT_NOTE) {
+            if (check_notes(fd, &phdr, pid, num_threads) < 0) {
+                close(fd);
+                THROW_ERROR("failed to check the notes");
+            }
+            num_notes++;
+        } else if (phdr.p_type == PT_LOAD) {
+            if (phdr.p_filesz > phdr.p_memsz) {
+                close(fd);
+                THROW_ERROR("invalid PT_LOAD segment");
+            }
+            num_loads++;
+        }
+    }
+    close(fd);
+
+    if (num_notes != 1 || num_loads == 0) {
+        THROW_ERROR("unexpected program headers");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Child processes
+// ============================================================================
+
+static int crashed_child(void) {
+    abort();
+    return 0;
+}
+
+static void *spinning_thread_func(void *arg) {
+    volatile int *is_running = (volatile int *)arg;
+    *is_running = 1;
+    while (1) {
+        // Keep running in the user space
+    }
+    return NULL;
+}
+
+static int crashed_child_with_threads(void) {
+    static volatile int is_running = 0;
+    pthread_t thread;
+    if (pthread_create(&thread, NULL, spinning_thread_func, (void *)&is_running) != 0) {
+        return EXIT_FAILURE;
+    }
+    while (!is_running) {
+        sched_yield();
+    }
+    abort();
+    return 0;
+}
+
+static int crashed_child_with_small_rlimit(void) {
+    struct rlimit rlim = { .rlim_cur = CORE_SIZE_LIMIT, .rlim_max = RLIM_INFINITY };
+    if (setrlimit(RLIMIT_CORE, &rlim) < 0) {
+        return EXIT_FAILURE;
+    }
+    abort();
+    return 0;
+}
+
+static int crashed_child_without_core(void) {
+    struct rlimit rlim = { .rlim_cur = 0, .rlim_max = RLIM_INFINITY };
+    if (setrlimit(RLIMIT_CORE, &rlim) < 0) {
+        return EXIT_FAILURE;
+    }
+    abort();
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_core_dump(void) {
+    pid_t child_pid;
+    int status;
+    char path[128];
+
+    if (spawn_and_wait_crashed_child("crashed_child", &child_pid, &status) < 0) {
+        THROW_ERROR("failed to run the crashed child");
+    }
+    if (!WCOREDUMP(status)) {
+        THROW_ERROR("the core of the child process is expected to be dumped");
+    }
+
+    get_core_file_path(child_pid, path, sizeof(path));
+    if (check_core_file(path, child_pid, 1) < 0) {
+        THROW_ERROR("failed to check the core file");
+    }
+    unlink(path);
+    return 0;
+}
+
+static int test_core_dump_with_threads(void) {
+    pid_t child_pid;
+    int status;
+    char path[128];
+
+    if (spawn_and_wait_crashed_child("crashed_child_with_threads", &child_pid, &status) < 0) {
+        THROW_ERROR("failed to run the crashed child");
+    }
+    if (!WCOREDUMP(status)) {
+        THROW_ERROR("the core of the child process is expected to be dumped");
+    }
+
+    get_core_file_path(child_pid, path, sizeof(path));
+    if (check_core_file(path, child_pid, 2) < 0) {
+        THROW_ERROR("failed to check the core file");
+    }
+    unlink(path);
+    return 0;
+}
+
+static int test_core_dump_with_small_rlimit(void) {
+    pid_t child_pid;
+    int status;
+    char path[128];
+    struct stat stat_buf;
+
+    if (spawn_and_wait_crashed_child("crashed_child_with_small_rlimit", &child_pid,
+                                     &status) < 0) {
+        THROW_ERROR("failed to run the crashed child");
+    }
+    if (!WCOREDUMP(status)) {
+        THROW_ERROR("the core of the child process is expected to be dumped");
+    }
+
+    get_core_file_path(child_pid, path, sizeof(path));
+    if (stat(path, &stat_buf) < 0) {
+        THROW_ERROR("failed to stat the core file");
+    }
+    unlink(path);
+    if (stat_buf.st_size == 0 || stat_buf.st_size > CORE_SIZE_LIMIT) {
+        THROW_ERROR("the core file is expected to be truncated at RLIMIT_CORE");
+    }
+    return 0;
+}
+
+static int test_core_dump_with_zero_rlimit(void) {
+    pid_t child_pid;
+    int status;
+    char path[128];
+
+    if (spawn_and_wait_crashed_child("crashed_child_without_core", &child_pid, &status) < 0) {
+        THROW_ERROR("failed to run the crashed child");
+    }
+    if (WCOREDUMP(status)) {
+        THROW_ERROR("the core of the child process is not expected to be dumped");
+    }
+
+    get_core_file_path(child_pid, path, sizeof(path));
+    if (access(path, F_OK) == 0 || errno != ENOENT) {
+        THROW_ERROR("the core file is not expected to exist");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_core_dump),
+    TEST_CASE(test_core_dump_with_threads),
+    TEST_CASE(test_core_dump_with_small_rlimit),
+    TEST_CASE(test_core_dump_with_zero_rlimit),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1) {
+        const char *cmd = argv[1];
+        if (strcmp(cmd, "crashed_child") == 0) {
+            return crashed_child();
+        } else if (strcmp(cmd, "crashed_child_with_threads") == 0) {
+            return crashed_child_with_threads();
+        } else if (strcmp(cmd, "crashed_child_with_small_rlimit") == 0) {
+            return crashed_child_with_small_rlimit();
+        } else if (strcmp(cmd, "crashed_child_without_core") == 0) {
+            return crashed_child_without_core();
+        } else {
+            fprintf(stderr, "ERROR: unknown command: %s\n", cmd);
+            return EXIT_FAILURE;
+        }
+    }
+
+    if (mkdir(CORE_DUMP_DIR, 0755) < 0 && errno != EEXIST) {
+        THROW_ERROR("failed to create the core dump directory");
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,287 @@
+#define _GNU_SOURCE
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <errno.h>
+#include <pthread.h>
+#include <stdint.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define NUM_WORKERS         4
+#define WAIT_TIMEOUT_MS     1000
+
+struct worker_arg {
+    pthread_t tid;
+    // The epoll fd to wait on, or -1 to create one exclusively for the worker
+    int epfd;
+    // The fd that is monitored
+    int fd;
+    // The function to consume the event
+    int (*consume)(int fd);
+};
+
+static volatile int num_ready_workers = 0;
+static volatile int num_wakeups = 0;
+
+static int consume_eventfd(int fd) {
+    uint64_t val;
+    return read(fd, &val, sizeof(val)) == sizeof(val) ? 0 : -1;
+}
+
+static int consume_listener(int fd) {
+    int conn_fd = accept4(fd, NULL, NULL, SOCK_NONBLOCK);
+    if (conn_fd < 0) {
+        return -1;
+    }
+    close(conn_fd);
+    return 0;
+}
+
+static void *worker_main(void *_arg) {
+    struct worker_arg *arg = _arg;
+    struct epoll_event event = { .events = EPOLLIN | EPOLLEXCLUSIVE };
+    int epfd = arg->epfd;
+
+    if (epfd < 0) {
+        epfd = epoll_create1(0);
+        if (epfd < 0 || epoll_ctl(epfd, EPOLL_CTL_ADD, arg->fd, &event) < 0) {
+            return (void *) -1;
+        }
+    }
+    __atomic_fetch_add(&num_ready_workers, 1, __ATOMIC_SEQ_CST);
+
+    int ret = epoll_wait(epfd, &event, 1, WAIT_TIMEOUT_MS);
+    if (ret < 0) {
+        return (void *) -1;
+    }
+    if (ret > 0) {
+        __atomic_fetch_add(&num_wakeups, 1, __ATOMIC_SEQ_CST);
+        if (arg->consume != NULL && arg->consume(arg->fd) < 0) {
+            return (void *) -1;
+        }
+    }
+
+    if (arg->epfd < 0) {
+        close(epfd);
+    }
+    return NULL;
+}
+
+// Start the workers that wait on the fd, trigger an event on the fd, and return the number of
+// workers that are woken up by the event
+static int count_wakeups(int epfd, int fd, int (*consume)(int), int (*trigger)(void *),
+                         void *trigger_arg) {
+    struct worker_arg args[NUM_WORKERS];
+    void *worker_ret;
+    int ret = 0;
+
+    num_ready_workers = 0;
+    num_wakeups = 0;
+    for (int i = 0; i < NUM_WORKERS; i++) {
+        args[i].epfd = epfd;
+        args[i].fd = fd;
+        args[i].consume = consume;
+        if (pthread_create(&args[i].tid, NULL, worker_main, &args[i]) != 0) {
+            THROW_ERROR("failed to create a worker");
+        }
+    }
+    while (__atomic_load_n(&num_ready_workers, __ATOMIC_SEQ_CST) < NUM_WORKERS) {
+        usleep(10 * 1000);
+    }
+    // Make sure that all the workers are blocked in epoll_wait
+    usleep(200 * 1000);
+
+    if (trigger(trigger_arg) < 0) {
+        ret = -1;
+    }
+    for (int i = 0; i < NUM_WORKERS; i++) {
+        pthread_join(args[i].tid, &worker_ret);
+        if (worker_ret != NULL) {
+            ret = -1;
+        }
+    }
+    if (ret < 0) {
+        THROW_ERROR("the workers fail");
+    }
+    return num_wakeups;
+}
+
+static int write_eventfd(void *arg) {
+    uint64_t val = 1;
+    return write(*(int *)arg, &val, sizeof(val)) == sizeof(val) ? 0 : -1;
+}
+
+static int connect_to(void *arg) {
+    struct sockaddr_in *addr = arg;
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0 || connect(fd, (struct sockaddr *)addr, sizeof(*addr)) < 0) {
+        return -1;
+    }
+    // Keep the connection until the workers are done
+    usleep(100 * 1000);
+    close(fd);
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_exclusive_eventfd(void) {
+    int fd = eventfd(0, EFD_NONBLOCK);
+    if (fd < 0) {
+        THROW_ERROR("failed to create an eventfd");
+    }
+
+    int wakeups = count_wakeups(-1, fd, consume_eventfd, write_eventfd, &fd);
+    if (wakeups != 1) {
+        THROW_ERROR("%d workers are woken up by one event", wakeups);
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_exclusive_listener(void) {
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_port = 0,
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+    };
+    socklen_t addr_len = sizeof(addr);
+
+    int fd = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (fd < 0 || bind(fd, (struct sockaddr *)&addr, addr_len) < 0 ||
+            getsockname(fd, (struct sockaddr *)&addr, &addr_len) < 0 || listen(fd, 8) < 0) {
+        THROW_ERROR("failed to listen");
+    }
+
+    int wakeups = count_wakeups(-1, fd, consume_listener, connect_to, &addr);
+    if (wakeups != 1) {
+        THROW_ERROR("%d workers are woken up by one connection", wakeups);
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_oneshot(void) {
+    struct epoll_event event = { .events = EPOLLIN | EPOLLONESHOT };
+    int epfd = epoll_create1(0);
+    int fd = eventfd(0, EFD_NONBLOCK);
+    if (epfd < 0 || fd < 0 || epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    // The event is not consumed, but is reported only once
+    int wakeups = count_wakeups(epfd, fd, NULL, write_eventfd, &fd);
+    if (wakeups != 1) {
+        THROW_ERROR("a one-shot event is reported %d times", wakeups);
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 0) {
+        THROW_ERROR("a disabled file is reported");
+    }
+
+    // Re-arm the file
+    event.events = EPOLLIN | EPOLLONESHOT;
+    if (epoll_ctl(epfd, EPOLL_CTL_MOD, fd, &event) < 0) {
+        THROW_ERROR("failed to re-arm the file");
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 1 || !(event.events & EPOLLIN)) {
+        THROW_ERROR("the re-armed file is not reported");
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 0) {
+        THROW_ERROR("a disabled file is reported");
+    }
+
+    close(fd);
+    close(epfd);
+    return 0;
+}
+
+static int test_wakeup(void) {
+    struct epoll_event event = { .events = EPOLLIN | EPOLLWAKEUP };
+    uint64_t val = 1;
+    int epfd = epoll_create1(0);
+    int fd = eventfd(0, EFD_NONBLOCK);
+    if (epfd < 0 || fd < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        THROW_ERROR("EPOLLWAKEUP should be accepted");
+    }
+    if (write(fd, &val, sizeof(val)) != sizeof(val)) {
+        THROW_ERROR("failed to write the eventfd");
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 1 || !(event.events & EPOLLIN)) {
+        THROW_ERROR("the event is not reported");
+    }
+
+    close(fd);
+    close(epfd);
+    return 0;
+}
+
+static int test_invalid_exclusive(void) {
+    struct epoll_event event = { .events = EPOLLIN | EPOLLEXCLUSIVE | EPOLLONESHOT };
+    int epfd = epoll_create1(0);
+    int inner_epfd = epoll_create1(0);
+    int fd = eventfd(0, EFD_NONBLOCK);
+    if (epfd < 0 || inner_epfd < 0 || fd < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) != -1 || errno != EINVAL) {
+        THROW_ERROR("EPOLLEXCLUSIVE with EPOLLONESHOT should be rejected");
+    }
+    event.events = EPOLLIN | EPOLLEXCLUSIVE;
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, inner_epfd, &event) != -1 || errno != EINVAL) {
+        THROW_ERROR("an epoll fd should not be added with EPOLLEXCLUSIVE");
+    }
+
+    event.events = EPOLLIN;
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        THROW_ERROR("failed to add the file");
+    }
+    event.events = EPOLLIN | EPOLLEXCLUSIVE;
+    if (epoll_ctl(epfd, EPOLL_CTL_MOD, fd, &event) != -1 || errno != EINVAL) {
+        THROW_ERROR("EPOLLEXCLUSIVE should be rejected by EPOLL_CTL_MOD");
+    }
+
+    if (epoll_ctl(epfd, EPOLL_CTL_DEL, fd, NULL) < 0 ||
+            epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        THROW_ERROR("failed to add the file with EPOLLEXCLUSIVE");
+    }
+    event.events = EPOLLIN | EPOLLOUT;
+    if (epoll_ctl(epfd, EPOLL_CTL_MOD, fd, &event) != -1 || errno != EINVAL) {
+        THROW_ERROR("a file added with EPOLLEXCLUSIVE should not be modified");
+    }
+
+    close(fd);
+    close(inner_epfd);
+    close(epfd);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_exclusive_eventfd),
+    TEST_CASE(test_exclusive_listener),
+    TEST_CASE(test_oneshot),
+    TEST_CASE(test_wakeup),
+    TEST_CASE(test_invalid_exclusive),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,311 @@
+#define _GNU_SOURCE
+#include <sys/epoll.h>
+#include <sys/inotify.h>
+#include <sys/stat.h>
+#include <sys/types.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define TEST_DIR        "/root/inotify_test_dir"
+#define EVENT_BUF_SIZE  4096
+
+static char event_buf[EVENT_BUF_SIZE] __attribute__((aligned(__alignof__(struct inotify_event))));
+
+// Read all the pending events and check whether one with the mask and name is among them
+static int read_and_find_event(int inotify_fd, uint32_t mask, const char *name,
+                               uint32_t *cookie) {
+    ssize_t len = read(inotify_fd, event_buf, sizeof(event_buf));
+    if (len <= 0) {
+        return -1;
+    }
+    for (char *ptr = event_buf; ptr < event_buf + len;) {
+        struct inotify_event *event = (struct inotify_event *)ptr;
+        if ((event->mask & mask) == mask &&
+                ((name == NULL && event->len == 0) ||
+                 (name != NULL && event->len > 0 && strcmp(event->name, name) == 0))) {
+            if (cookie != NULL) {
+                *cookie = event->cookie;
+            }
+            return 0;
+        }
+        ptr += sizeof(struct inotify_event) + event->len;
+    }
+    return -1;
+}
+
+static void cleanup_test_dir(void) {
+    unlink(TEST_DIR "/file");
+    unlink(TEST_DIR "/renamed");
+    rmdir(TEST_DIR "/subdir");
+    rmdir(TEST_DIR);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_inotify_init(void) {
+    int fd = inotify_init1(IN_NONBLOCK | IN_CLOEXEC);
+    if (fd < 0) {
+        THROW_ERROR("inotify_init1 failed");
+    }
+    if (read(fd, event_buf, sizeof(event_buf)) != -1 || errno != EAGAIN) {
+        close(fd);
+        THROW_ERROR("reading an empty inotify file should fail with EAGAIN");
+    }
+    close(fd);
+
+    if (inotify_init1(-1) != -1 || errno != EINVAL) {
+        THROW_ERROR("inotify_init1 with invalid flags should fail with EINVAL");
+    }
+    return 0;
+}
+
+static int test_dir_events(void) {
+    uint32_t from_cookie = 0, to_cookie = 0;
+    int ret = -1;
+
+    cleanup_test_dir();
+    if (mkdir(TEST_DIR, 0755) < 0) {
+        THROW_ERROR("failed to create the test dir");
+    }
+    int fd = inotify_init1(IN_NONBLOCK);
+    if (fd < 0) {
+        cleanup_test_dir();
+        THROW_ERROR("inotify_init1 failed");
+    }
+    int wd = inotify_add_watch(fd, TEST_DIR, IN_ALL_EVENTS);
+    if (wd < 0) {
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("inotify_add_watch failed");
+    }
+
+    int file_fd = open(TEST_DIR "/file", O_WRONLY | O_CREAT, 0644);
+    if (file_fd < 0) {
+        goto out;
+    }
+    if (read_and_find_event(fd, IN_CREATE, "file", NULL) < 0) {
+        printf("IN_CREATE is not received\n");
+        close(file_fd);
+        goto out;
+    }
+    if (write(file_fd, "hello", 5) != 5 ||
+            read_and_find_event(fd, IN_MODIFY, "file", NULL) < 0) {
+        printf("IN_MODIFY is not received\n");
+        close(file_fd);
+        goto out;
+    }
+    close(file_fd);
+    if (read_and_find_event(fd, IN_CLOSE_WRITE, "file", NULL) < 0) {
+        printf("IN_CLOSE_WRITE is not received\n");
+        goto out;
+    }
+    if (chmod(TEST_DIR "/file", 0600) < 0 ||
+            read_and_find_event(fd, IN_ATTRIB, "file", NULL) < 0) {
+        printf("IN_ATTRIB is not received\n");
+        goto out;
+    }
+    if (mkdir(TEST_DIR "/subdir", 0755) < 0 ||
+            read_and_find_event(fd, IN_CREATE | IN_ISDIR, "subdir", NULL) < 0) {
+        printf("IN_CREATE | IN_ISDIR is not received\n");
+        goto out;
+    }
+    if (rename(TEST_DIR "/file", TEST_DIR "/renamed") < 0) {
+        goto out;
+    }
+    // The two events of a rename are read at once
+    ssize_t len = read(fd, event_buf, sizeof(event_buf));
+    for (char *ptr = event_buf; len > 0 && ptr < event_buf + len;) {
+        struct inotify_event *event = (struct inotify_event *)ptr;
+        if (event->mask & IN_MOVED_FROM) {
+            from_cookie = event->cookie;
+        } else if (event->mask & IN_MOVED_TO) {
+            to_cookie = event->cookie;
+        }
+        ptr += sizeof(struct inotify_event) + event->len;
+    }
+    if (from_cookie == 0 || from_cookie != to_cookie) {
+        printf("IN_MOVED_FROM and IN_MOVED_TO do not match\n");
+        goto out;
+    }
+    if (unlink(TEST_DIR "/renamed") < 0 ||
+            read_and_find_event(fd, IN_DELETE, "renamed", NULL) < 0) {
+        printf("IN_DELETE is not received\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    cleanup_test_dir();
+    if (ret < 0) {
+        THROW_ERROR("unexpected inotify events");
+    }
+    return 0;
+}
+
+static int test_rm_watch_and_delete_self(void) {
+    cleanup_test_dir();
+    if (mkdir(TEST_DIR, 0755) < 0) {
+        THROW_ERROR("failed to create the test dir");
+    }
+    int fd = inotify_init1(IN_NONBLOCK);
+    if (fd < 0) {
+        cleanup_test_dir();
+        THROW_ERROR("inotify_init1 failed");
+    }
+
+    int wd = inotify_add_watch(fd, TEST_DIR, IN_CREATE);
+    if (wd < 0 || inotify_rm_watch(fd, wd) < 0) {
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("failed to add or remove a watch");
+    }
+    if (read_and_find_event(fd, IN_IGNORED, NULL, NULL) < 0) {
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("IN_IGNORED is not received after inotify_rm_watch");
+    }
+    if (inotify_rm_watch(fd, wd) != -1 || errno != EINVAL) {
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("removing a removed watch should fail with EINVAL");
+    }
+
+    wd = inotify_add_watch(fd, TEST_DIR, IN_DELETE_SELF);
+    if (wd < 0 || rmdir(TEST_DIR) < 0) {
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("failed to watch and remove the dir");
+    }
+    if (read_and_find_event(fd, IN_DELETE_SELF, NULL, NULL) < 0) {
+        close(fd);
+        THROW_ERROR("IN_DELETE_SELF is not received");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_file_watch_and_rename_overwrite(void) {
+    int ret = -1;
+
+    cleanup_test_dir();
+    if (mkdir(TEST_DIR, 0755) < 0) {
+        THROW_ERROR("failed to create the test dir");
+    }
+    int file_fd = open(TEST_DIR "/file", O_WRONLY | O_CREAT, 0644);
+    int target_fd = open(TEST_DIR "/renamed", O_WRONLY | O_CREAT, 0644);
+    close(target_fd);
+    int fd = inotify_init1(IN_NONBLOCK);
+    if (file_fd < 0 || target_fd < 0 || fd < 0) {
+        goto out;
+    }
+
+    // Only the file itself is watched, not its parent
+    if (inotify_add_watch(fd, TEST_DIR "/file", IN_MODIFY) < 0 ||
+            write(file_fd, "hello", 5) != 5 ||
+            read_and_find_event(fd, IN_MODIFY, NULL, NULL) < 0) {
+        printf("IN_MODIFY is not received on the watched file\n");
+        goto out;
+    }
+
+    // The target replaced by a rename is deleted
+    if (inotify_add_watch(fd, TEST_DIR "/renamed", IN_DELETE_SELF) < 0 ||
+            rename(TEST_DIR "/file", TEST_DIR "/renamed") < 0) {
+        goto out;
+    }
+    ssize_t len = read(fd, event_buf, sizeof(event_buf));
+    int deleted = 0, ignored = 0;
+    for (char *ptr = event_buf; len > 0 && ptr < event_buf + len;) {
+        struct inotify_event *event = (struct inotify_event *)ptr;
+        deleted |= (event->mask & IN_DELETE_SELF) != 0;
+        ignored |= (event->mask & IN_IGNORED) != 0;
+        ptr += sizeof(struct inotify_event) + event->len;
+    }
+    if (!deleted || !ignored) {
+        printf("IN_DELETE_SELF and IN_IGNORED are not received on the replaced file\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (file_fd >= 0) {
+        close(file_fd);
+    }
+    if (fd >= 0) {
+        close(fd);
+    }
+    cleanup_test_dir();
+    if (ret < 0) {
+        THROW_ERROR("unexpected inotify events on files");
+    }
+    return 0;
+}
+
+static int test_poll_and_epoll(void) {
+    struct pollfd pfd;
+    struct epoll_event event = { .events = EPOLLIN };
+
+    cleanup_test_dir();
+    if (mkdir(TEST_DIR, 0755) < 0) {
+        THROW_ERROR("failed to create the test dir");
+    }
+    int fd = inotify_init1(IN_NONBLOCK);
+    int epfd = epoll_create1(0);
+    if (fd < 0 || epfd < 0 || inotify_add_watch(fd, TEST_DIR, IN_CREATE) < 0 ||
+            epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        cleanup_test_dir();
+        THROW_ERROR("failed to set up inotify and epoll");
+    }
+
+    pfd.fd = fd;
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 0) != 0) {
+        close(epfd);
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("inotify file should not be readable without events");
+    }
+    if (mkdir(TEST_DIR "/subdir", 0755) < 0) {
+        close(epfd);
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("mkdir failed");
+    }
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || !(event.events & EPOLLIN) ||
+            poll(&pfd, 1, 0) != 1 || !(pfd.revents & POLLIN)) {
+        close(epfd);
+        close(fd);
+        cleanup_test_dir();
+        THROW_ERROR("inotify file should be readable after an event");
+    }
+    close(epfd);
+    close(fd);
+    cleanup_test_dir();
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_inotify_init),
+    TEST_CASE(test_dir_events),
+    TEST_CASE(test_rm_watch_and_delete_self),
+    TEST_CASE(test_file_watch_and_rename_overwrite),
+    TEST_CASE(test_poll_and_epoll),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
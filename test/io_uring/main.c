@@ -0,0 +1,368 @@
+#define _GNU_SOURCE
+#include <sys/eventfd.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <linux/io_uring.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdatomic.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+#ifndef SYS_io_uring_setup
+#define SYS_io_uring_setup      425
+#define SYS_io_uring_enter      426
+#define SYS_io_uring_register   427
+#endif
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define FILE_PATH       "/root/test_io_uring_file.txt"
+#define NUM_ENTRIES     8
+
+struct ring {
+    int fd;
+    struct io_uring_params params;
+    unsigned *sq_head;
+    unsigned *sq_tail;
+    unsigned *sq_mask;
+    unsigned *sq_array;
+    unsigned *cq_head;
+    unsigned *cq_tail;
+    unsigned *cq_mask;
+    struct io_uring_cqe *cqes;
+    struct io_uring_sqe *sqes;
+};
+
+static int io_uring_setup(unsigned entries, struct io_uring_params *params) {
+    return syscall(SYS_io_uring_setup, entries, params);
+}
+
+static int io_uring_enter(int fd, unsigned to_submit, unsigned min_complete, unsigned flags) {
+    return syscall(SYS_io_uring_enter, fd, to_submit, min_complete, flags, NULL, _NSIG / 8);
+}
+
+static int io_uring_register(int fd, unsigned opcode, void *arg, unsigned nr_args) {
+    return syscall(SYS_io_uring_register, fd, opcode, arg, nr_args);
+}
+
+static int ring_init(struct ring *ring) {
+    memset(ring, 0, sizeof(*ring));
+    ring->fd = io_uring_setup(NUM_ENTRIES, &ring->params);
+    if (ring->fd < 0) {
+        THROW_ERROR("failed to set up io_uring");
+    }
+
+    struct io_uring_params *p = &ring->params;
+    size_t sq_size = p->sq_off.array + p->sq_entries * sizeof(unsigned);
+    size_t cq_size = p->cq_off.cqes + p->cq_entries * sizeof(struct io_uring_cqe);
+    size_t sqes_size = p->sq_entries * sizeof(struct io_uring_sqe);
+    char *sq = mmap(NULL, sq_size, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE,
+                    ring->fd, IORING_OFF_SQ_RING);
+    char *cq = mmap(NULL, cq_size, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE,
+                    ring->fd, IORING_OFF_CQ_RING);
+    ring->sqes = mmap(NULL, sqes_size, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE,
+                      ring->fd, IORING_OFF_SQES);
+    if (sq == MAP_FAILED || cq == MAP_FAILED || ring->sqes == MAP_FAILED) {
+        THROW_ERROR("failed to map the rings");
+    }
+
+    ring->sq_head = (unsigned *)(sq + p->sq_off.head);
+    ring->sq_tail = (unsigned *)(sq + p->sq_off.tail);
+    ring->sq_mask = (unsigned *)(sq + p->sq_off.ring_mask);
+    ring->sq_array = (unsigned *)(sq + p->sq_off.array);
+    ring->cq_head = (unsigned *)(cq + p->cq_off.head);
+    ring->cq_tail = (unsigned *)(cq + p->cq_off.tail);
+    ring->cq_mask = (unsigned *)(cq + p->cq_off.ring_mask);
+    ring->cqes = (struct io_uring_cqe *)(cq + p->cq_off.cqes);
+    return 0;
+}
+
+// Queue an entry to the submission queue ring, which is assumed not to be full
+static struct io_uring_sqe *queue_sqe(struct ring *ring, int opcode, int fd, uint64_t user_data) {
+    unsigned tail = *ring->sq_tail;
+    unsigned idx = tail & *ring->sq_mask;
+    struct io_uring_sqe *sqe = &ring->sqes[idx];
+
+    memset(sqe, 0, sizeof(*sqe));
+    sqe->opcode = opcode;
+    sqe->fd = fd;
+    sqe->user_data = user_data;
+    ring->sq_array[idx] = idx;
+    atomic_store_explicit((_Atomic unsigned *)ring->sq_tail, tail + 1, memory_order_release);
+    return sqe;
+}
+
+// Pop a completion, or return -1 if the completion queue ring is empty
+static int pop_cqe(struct ring *ring, struct io_uring_cqe *cqe) {
+    unsigned head = *ring->cq_head;
+    unsigned tail = atomic_load_explicit((_Atomic unsigned *)ring->cq_tail, memory_order_acquire);
+    if (head == tail) {
+        return -1;
+    }
+    *cqe = ring->cqes[head & *ring->cq_mask];
+    atomic_store_explicit((_Atomic unsigned *)ring->cq_head, head + 1, memory_order_release);
+    return 0;
+}
+
+// Submit the queued entries, wait for a completion and check it
+static int submit_and_check(struct ring *ring, unsigned to_submit, uint64_t user_data,
+                            int32_t res) {
+    struct io_uring_cqe cqe;
+
+    if (io_uring_enter(ring->fd, to_submit, 1, IORING_ENTER_GETEVENTS) != to_submit) {
+        THROW_ERROR("failed to submit the requests");
+    }
+    if (pop_cqe(ring, &cqe) < 0) {
+        THROW_ERROR("no completion");
+    }
+    if (cqe.user_data != user_data || cqe.res != res) {
+        THROW_ERROR("unexpected completion: user_data = %lu, res = %d",
+                    (unsigned long)cqe.user_data, cqe.res);
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_setup(void) {
+    struct io_uring_params params;
+    struct ring ring;
+
+    memset(&params, 0, sizeof(params));
+    if (io_uring_setup(0, &params) != -1 || errno != EINVAL) {
+        THROW_ERROR("zero entries should be rejected");
+    }
+    params.flags = IORING_SETUP_SQPOLL;
+    if (io_uring_setup(NUM_ENTRIES, &params) != -1 || errno != EINVAL) {
+        THROW_ERROR("IORING_SETUP_SQPOLL should be rejected");
+    }
+
+    if (ring_init(&ring) < 0) {
+        return -1;
+    }
+    if (ring.params.sq_entries != NUM_ENTRIES || ring.params.cq_entries != 2 * NUM_ENTRIES) {
+        THROW_ERROR("unexpected number of entries");
+    }
+    if (!(ring.params.features & IORING_FEAT_NODROP)) {
+        THROW_ERROR("IORING_FEAT_NODROP should be supported");
+    }
+    if (mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, ring.fd, 0x1000) != MAP_FAILED ||
+            errno != EINVAL) {
+        THROW_ERROR("an invalid offset should be rejected");
+    }
+    close(ring.fd);
+    return 0;
+}
+
+static int test_nop_and_invalid_opcode(void) {
+    struct ring ring;
+    struct io_uring_cqe cqe;
+
+    if (ring_init(&ring) < 0) {
+        return -1;
+    }
+    queue_sqe(&ring, IORING_OP_NOP, -1, 1);
+    if (submit_and_check(&ring, 1, 1, 0) < 0) {
+        return -1;
+    }
+    queue_sqe(&ring, 0xff, -1, 2);
+    if (submit_and_check(&ring, 1, 2, -EINVAL) < 0) {
+        return -1;
+    }
+    if (pop_cqe(&ring, &cqe) == 0) {
+        THROW_ERROR("no more completions are expected");
+    }
+    close(ring.fd);
+    return 0;
+}
+
+static int test_file_read_write(void) {
+    const char *msg = "Hello from io_uring";
+    char buf1[8] = {0};
+    char buf2[32] = {0};
+    struct iovec iov[2] = {
+        { .iov_base = buf1, .iov_len = sizeof(buf1) },
+        { .iov_base = buf2, .iov_len = sizeof(buf2) },
+    };
+    struct io_uring_sqe *sqe;
+    struct ring ring;
+
+    int fd = open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0666);
+    if (fd < 0 || ring_init(&ring) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    sqe = queue_sqe(&ring, IORING_OP_WRITE, fd, 1);
+    sqe->addr = (uint64_t)(uintptr_t)msg;
+    sqe->len = strlen(msg);
+    sqe->off = 0;
+    if (submit_and_check(&ring, 1, 1, strlen(msg)) < 0) {
+        return -1;
+    }
+
+    sqe = queue_sqe(&ring, IORING_OP_READV, fd, 2);
+    sqe->addr = (uint64_t)(uintptr_t)iov;
+    sqe->len = 2;
+    sqe->off = 0;
+    if (submit_and_check(&ring, 1, 2, strlen(msg)) < 0) {
+        return -1;
+    }
+    if (memcmp(buf1, msg, sizeof(buf1)) != 0 ||
+            strcmp(buf2, msg + sizeof(buf1)) != 0) {
+        THROW_ERROR("the data read is different from the data written");
+    }
+
+    close(ring.fd);
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+static int test_pipe_read_pending(void) {
+    const char *msg = "ping";
+    char buf[16] = {0};
+    struct io_uring_sqe *sqe;
+    struct io_uring_cqe cqe;
+    struct ring ring;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0 || ring_init(&ring) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    // Reading an empty pipe is kept pending
+    sqe = queue_sqe(&ring, IORING_OP_READ, pipe_fds[0], 1);
+    sqe->addr = (uint64_t)(uintptr_t)buf;
+    sqe->len = sizeof(buf);
+    sqe->off = -1;
+    if (io_uring_enter(ring.fd, 1, 0, 0) != 1) {
+        THROW_ERROR("failed to submit the request");
+    }
+    if (pop_cqe(&ring, &cqe) == 0) {
+        THROW_ERROR("the request should be pending");
+    }
+
+    if (write(pipe_fds[1], msg, strlen(msg)) != strlen(msg)) {
+        THROW_ERROR("failed to write the pipe");
+    }
+    if (submit_and_check(&ring, 0, 1, strlen(msg)) < 0) {
+        return -1;
+    }
+    if (strcmp(buf, msg) != 0) {
+        THROW_ERROR("the data read is different from the data written");
+    }
+
+    close(ring.fd);
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return 0;
+}
+
+static int test_poll_add_and_remove(void) {
+    struct io_uring_sqe *sqe;
+    struct io_uring_cqe cqe;
+    struct ring ring;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0 || ring_init(&ring) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+
+    // The write end of a pipe is ready
+    sqe = queue_sqe(&ring, IORING_OP_POLL_ADD, pipe_fds[1], 1);
+    sqe->poll_events = POLLOUT;
+    if (submit_and_check(&ring, 1, 1, POLLOUT) < 0) {
+        return -1;
+    }
+
+    // The read end of an empty pipe is not ready until the poll is removed
+    sqe = queue_sqe(&ring, IORING_OP_POLL_ADD, pipe_fds[0], 2);
+    sqe->poll_events = POLLIN;
+    sqe = queue_sqe(&ring, IORING_OP_POLL_REMOVE, -1, 3);
+    sqe->addr = 2;
+    if (io_uring_enter(ring.fd, 2, 2, IORING_ENTER_GETEVENTS) != 2) {
+        THROW_ERROR("failed to submit the requests");
+    }
+    if (pop_cqe(&ring, &cqe) < 0 || cqe.user_data != 2 || cqe.res != -ECANCELED) {
+        THROW_ERROR("the poll request should be canceled");
+    }
+    if (pop_cqe(&ring, &cqe) < 0 || cqe.user_data != 3 || cqe.res != 0) {
+        THROW_ERROR("the remove request should succeed");
+    }
+
+    close(ring.fd);
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return 0;
+}
+
+static int test_register_probe_and_eventfd(void) {
+    size_t probe_size = sizeof(struct io_uring_probe) + 256 * sizeof(struct io_uring_probe_op);
+    struct io_uring_probe *probe = calloc(1, probe_size);
+    struct ring ring;
+    uint64_t count = 0;
+
+    if (probe == NULL || ring_init(&ring) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    if (io_uring_register(ring.fd, IORING_REGISTER_PROBE, probe, 256) < 0) {
+        THROW_ERROR("failed to probe the operations");
+    }
+    if (probe->ops_len <= IORING_OP_RECV ||
+            !(probe->ops[IORING_OP_READ].flags & IO_URING_OP_SUPPORTED) ||
+            !(probe->ops[IORING_OP_RECV].flags & IO_URING_OP_SUPPORTED) ||
+            (probe->ops[IORING_OP_OPENAT].flags & IO_URING_OP_SUPPORTED)) {
+        THROW_ERROR("unexpected probe result");
+    }
+    free(probe);
+
+    int efd = eventfd(0, 0);
+    if (efd < 0 || io_uring_register(ring.fd, IORING_REGISTER_EVENTFD, &efd, 1) < 0) {
+        THROW_ERROR("failed to register the eventfd");
+    }
+    if (io_uring_register(ring.fd, IORING_REGISTER_EVENTFD, &efd, 1) != -1 || errno != EBUSY) {
+        THROW_ERROR("registering an eventfd twice should be rejected");
+    }
+    queue_sqe(&ring, IORING_OP_NOP, -1, 1);
+    if (submit_and_check(&ring, 1, 1, 0) < 0) {
+        return -1;
+    }
+    if (read(efd, &count, sizeof(count)) != sizeof(count) || count != 1) {
+        THROW_ERROR("the eventfd should be notified");
+    }
+    if (io_uring_register(ring.fd, IORING_UNREGISTER_EVENTFD, NULL, 0) < 0) {
+        THROW_ERROR("failed to unregister the eventfd");
+    }
+
+    close(efd);
+    close(ring.fd);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_setup),
+    TEST_CASE(test_nop_and_invalid_opcode),
+    TEST_CASE(test_file_read_write),
+    TEST_CASE(test_pipe_read_pending),
+    TEST_CASE(test_poll_add_and_remove),
+    TEST_CASE(test_register_probe_and_eventfd),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
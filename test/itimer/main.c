@@ -0,0 +1,215 @@
+#define _GNU_SOURCE
+#include <sys/time.h>
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static volatile sig_atomic_t received_signum = 0;
+
+static void signal_handler(int signum) {
+    received_signum = signum;
+}
+
+static int install_handler(int signum) {
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = signal_handler;
+    received_signum = 0;
+    return sigaction(signum, &sa, NULL);
+}
+
+static void restore_handler(int signum) {
+    signal(signum, SIG_DFL);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_alarm(void) {
+    if (alarm(0) != 0) {
+        THROW_ERROR("there should be no pending alarm");
+    }
+    if (alarm(100) != 0) {
+        THROW_ERROR("there should be no pending alarm");
+    }
+    unsigned int remaining = alarm(0);
+    if (remaining < 99 || remaining > 100) {
+        THROW_ERROR("unexpected remaining seconds of the alarm");
+    }
+    if (alarm(0) != 0) {
+        THROW_ERROR("the alarm is not canceled");
+    }
+    return 0;
+}
+
+static int test_alarm_and_pause(void) {
+    if (install_handler(SIGALRM) < 0) {
+        THROW_ERROR("sigaction failed");
+    }
+    alarm(1);
+    // pause() returns only when a signal is caught
+    if (pause() != -1 || errno != EINTR) {
+        restore_handler(SIGALRM);
+        THROW_ERROR("pause should fail with EINTR");
+    }
+    if (received_signum != SIGALRM) {
+        restore_handler(SIGALRM);
+        THROW_ERROR("SIGALRM is not received");
+    }
+    restore_handler(SIGALRM);
+    return 0;
+}
+
+static int test_setitimer_and_getitimer(void) {
+    struct itimerval new_value = {
+        .it_value = { .tv_sec = 10, .tv_usec = 0 },
+        .it_interval = { .tv_sec = 2, .tv_usec = 500 },
+    };
+    struct itimerval old_value, curr_value;
+
+    if (setitimer(ITIMER_REAL, &new_value, &old_value) < 0 ||
+            old_value.it_value.tv_sec != 0 || old_value.it_value.tv_usec != 0) {
+        THROW_ERROR("setitimer failed or the timer was armed");
+    }
+    if (getitimer(ITIMER_REAL, &curr_value) < 0 || curr_value.it_value.tv_sec > 10 ||
+            curr_value.it_value.tv_sec < 9 || curr_value.it_interval.tv_sec != 2 ||
+            curr_value.it_interval.tv_usec != 500) {
+        THROW_ERROR("unexpected value of the armed timer");
+    }
+
+    memset(&new_value, 0, sizeof(new_value));
+    if (setitimer(ITIMER_REAL, &new_value, &old_value) < 0 ||
+            old_value.it_value.tv_sec < 9) {
+        THROW_ERROR("failed to disarm the timer");
+    }
+    if (getitimer(ITIMER_REAL, &curr_value) < 0 || curr_value.it_value.tv_sec != 0 ||
+            curr_value.it_value.tv_usec != 0) {
+        THROW_ERROR("the timer is not disarmed");
+    }
+
+    if (getitimer(3, &curr_value) != -1 || errno != EINVAL) {
+        THROW_ERROR("getitimer with an invalid type should fail with EINVAL");
+    }
+    new_value.it_value.tv_usec = 1000 * 1000;
+    if (setitimer(ITIMER_REAL, &new_value, NULL) != -1 || errno != EINVAL) {
+        THROW_ERROR("setitimer with an invalid value should fail with EINVAL");
+    }
+    return 0;
+}
+
+static int test_periodic_real_timer(void) {
+    struct itimerval new_value = {
+        .it_value = { .tv_sec = 0, .tv_usec = 50 * 1000 },
+        .it_interval = { .tv_sec = 0, .tv_usec = 50 * 1000 },
+    };
+    int count = 0;
+
+    if (install_handler(SIGALRM) < 0) {
+        THROW_ERROR("sigaction failed");
+    }
+    if (setitimer(ITIMER_REAL, &new_value, NULL) < 0) {
+        restore_handler(SIGALRM);
+        THROW_ERROR("setitimer failed");
+    }
+    for (int i = 0; i < 200 && count < 3; i++) {
+        usleep(10 * 1000);
+        if (received_signum == SIGALRM) {
+            received_signum = 0;
+            count++;
+        }
+    }
+    memset(&new_value, 0, sizeof(new_value));
+    setitimer(ITIMER_REAL, &new_value, NULL);
+    restore_handler(SIGALRM);
+    if (count < 3) {
+        THROW_ERROR("the periodic timer does not keep expiring");
+    }
+    return 0;
+}
+
+static int test_prof_timer(void) {
+    struct itimerval new_value = {
+        .it_value = { .tv_sec = 0, .tv_usec = 50 * 1000 },
+    };
+    struct timespec start, now;
+
+    if (install_handler(SIGPROF) < 0) {
+        THROW_ERROR("sigaction failed");
+    }
+    if (setitimer(ITIMER_PROF, &new_value, NULL) < 0) {
+        restore_handler(SIGPROF);
+        THROW_ERROR("setitimer failed");
+    }
+    // Consume the CPU time until the timer expires
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    do {
+        clock_gettime(CLOCK_MONOTONIC, &now);
+    } while (received_signum != SIGPROF && now.tv_sec - start.tv_sec < 5);
+    restore_handler(SIGPROF);
+    if (received_signum != SIGPROF) {
+        THROW_ERROR("SIGPROF is not received");
+    }
+    return 0;
+}
+
+static int test_virtual_timer(void) {
+    struct itimerval new_value = {
+        .it_value = { .tv_sec = 0, .tv_usec = 50 * 1000 },
+    };
+    struct timespec sleep_time = { .tv_sec = 0, .tv_nsec = 200 * 1000 * 1000 };
+    struct timespec start, now;
+    volatile unsigned long counter = 0;
+
+    if (install_handler(SIGVTALRM) < 0) {
+        THROW_ERROR("sigaction failed");
+    }
+    if (setitimer(ITIMER_VIRTUAL, &new_value, NULL) < 0) {
+        restore_handler(SIGVTALRM);
+        THROW_ERROR("setitimer failed");
+    }
+    // Sleeping consumes no user time of the process
+    nanosleep(&sleep_time, NULL);
+    if (received_signum == SIGVTALRM) {
+        restore_handler(SIGVTALRM);
+        THROW_ERROR("SIGVTALRM is received while sleeping");
+    }
+    // Consume the user time until the timer expires
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    do {
+        for (int i = 0; i < 1000000; i++) {
+            counter++;
+        }
+        clock_gettime(CLOCK_MONOTONIC, &now);
+    } while (received_signum != SIGVTALRM && now.tv_sec - start.tv_sec < 5);
+    restore_handler(SIGVTALRM);
+    if (received_signum != SIGVTALRM) {
+        THROW_ERROR("SIGVTALRM is not received");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_alarm),
+    TEST_CASE(test_alarm_and_pause),
+    TEST_CASE(test_setitimer_and_getitimer),
+    TEST_CASE(test_periodic_real_timer),
+    TEST_CASE(test_prof_timer),
+    TEST_CASE(test_virtual_timer),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,600 @@
+#define _GNU_SOURCE
+#include <sys/epoll.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// The loopback traffic is handled inside the enclave when "net.loopback" is set to "enclave"
+// in Occlum.json. The test checks the socket semantics, which are the same as on Linux.
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static const char *request = "ping from the client";
+static const char *response = "pong from the server";
+
+static void fill_loopback_addr(struct sockaddr_in *addr, in_port_t port) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sin_family = AF_INET;
+    addr->sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    addr->sin_port = htons(port);
+}
+
+static void fill_loopback_addr6(struct sockaddr_in6 *addr, in_port_t port) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sin6_family = AF_INET6;
+    addr->sin6_addr = in6addr_loopback;
+    addr->sin6_port = htons(port);
+}
+
+// Create a listening socket bound to an ephemeral port of the loopback address and get the
+// bound address
+static int create_listener(int domain, struct sockaddr_storage *addr, socklen_t *addr_len) {
+    int listen_fd = socket(domain, SOCK_STREAM, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create a stream socket");
+    }
+
+    if (domain == AF_INET) {
+        fill_loopback_addr((struct sockaddr_in *)addr, 0);
+        *addr_len = sizeof(struct sockaddr_in);
+    } else {
+        fill_loopback_addr6((struct sockaddr_in6 *)addr, 0);
+        *addr_len = sizeof(struct sockaddr_in6);
+    }
+    if (bind(listen_fd, (struct sockaddr *)addr, *addr_len) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to bind to the loopback address");
+    }
+    if (listen(listen_fd, 8) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to listen");
+    }
+    if (getsockname(listen_fd, (struct sockaddr *)addr, addr_len) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to get the bound address");
+    }
+    return listen_fd;
+}
+
+static in_port_t port_of(struct sockaddr_storage *addr) {
+    if (addr->ss_family == AF_INET) {
+        return ntohs(((struct sockaddr_in *)addr)->sin_port);
+    }
+    return ntohs(((struct sockaddr_in6 *)addr)->sin6_port);
+}
+
+// Connect to the listener and accept the connection
+static int connect_pair(int listen_fd, struct sockaddr_storage *addr, socklen_t addr_len,
+                        int *fds) {
+    fds[0] = socket(addr->ss_family, SOCK_STREAM, 0);
+    if (fds[0] < 0) {
+        THROW_ERROR("failed to create a stream socket");
+    }
+    if (connect(fds[0], (struct sockaddr *)addr, addr_len) < 0) {
+        close(fds[0]);
+        THROW_ERROR("failed to connect to the listener");
+    }
+    fds[1] = accept(listen_fd, NULL, NULL);
+    if (fds[1] < 0) {
+        close(fds[0]);
+        THROW_ERROR("failed to accept the connection");
+    }
+    return 0;
+}
+
+static void close_pair(int *fds) {
+    close(fds[0]);
+    close(fds[1]);
+}
+
+static int check_transfer(int sender, int receiver, const char *msg) {
+    char buf[64] = {0};
+    size_t len = strlen(msg);
+
+    if (send(sender, msg, len, 0) != len) {
+        THROW_ERROR("failed to send the data");
+    }
+    if (recv(receiver, buf, sizeof(buf), 0) != len || strcmp(buf, msg) != 0) {
+        THROW_ERROR("the received data mismatches");
+    }
+    return 0;
+}
+
+static int __test_tcp_connection(int domain) {
+    struct sockaddr_storage listen_addr, addr, peer_addr;
+    socklen_t listen_addr_len, addr_len, peer_addr_len;
+    int listen_fd, fds[2];
+    char buf[8];
+    int ret = -1;
+
+    listen_fd = create_listener(domain, &listen_addr, &listen_addr_len);
+    if (listen_fd < 0) {
+        return -1;
+    }
+    if (port_of(&listen_addr) == 0) {
+        close(listen_fd);
+        THROW_ERROR("no port is assigned to the listener");
+    }
+    if (connect_pair(listen_fd, &listen_addr, listen_addr_len, fds) < 0) {
+        close(listen_fd);
+        return -1;
+    }
+
+    // The client is bound to an ephemeral port, which is the peer address of the server
+    addr_len = sizeof(addr);
+    peer_addr_len = sizeof(peer_addr);
+    if (getsockname(fds[0], (struct sockaddr *)&addr, &addr_len) < 0 ||
+            getpeername(fds[1], (struct sockaddr *)&peer_addr, &peer_addr_len) < 0) {
+        printf("failed to get the addresses of the connection\n");
+        goto out;
+    }
+    if (addr_len != peer_addr_len || memcmp(&addr, &peer_addr, addr_len) != 0 ||
+            port_of(&addr) == 0) {
+        printf("the address of the client mismatches the peer address of the server\n");
+        goto out;
+    }
+    peer_addr_len = sizeof(peer_addr);
+    if (getpeername(fds[0], (struct sockaddr *)&peer_addr, &peer_addr_len) < 0 ||
+            port_of(&peer_addr) != port_of(&listen_addr)) {
+        printf("the peer address of the client is not the listener\n");
+        goto out;
+    }
+
+    if (check_transfer(fds[0], fds[1], request) < 0 ||
+            check_transfer(fds[1], fds[0], response) < 0) {
+        goto out;
+    }
+
+    // The server reads EOF after the client shuts down the writing
+    if (shutdown(fds[0], SHUT_WR) < 0) {
+        printf("failed to shutdown the client\n");
+        goto out;
+    }
+    if (recv(fds[1], buf, sizeof(buf), 0) != 0) {
+        printf("EOF is expected after shutdown\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close_pair(fds);
+    close(listen_fd);
+    return ret;
+}
+
+// ============================================================================
+// Test cases for TCP
+// ============================================================================
+
+static int test_tcp_ipv4(void) {
+    return __test_tcp_connection(AF_INET);
+}
+
+static int test_tcp_ipv6(void) {
+    return __test_tcp_connection(AF_INET6);
+}
+
+static int test_tcp_nonblocking_accept(void) {
+    struct sockaddr_storage addr;
+    socklen_t addr_len;
+    int listen_fd, fds[2];
+    int ret = -1;
+
+    listen_fd = create_listener(AF_INET, &addr, &addr_len);
+    if (listen_fd < 0) {
+        return -1;
+    }
+    if (fcntl(listen_fd, F_SETFL, O_NONBLOCK) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to set the listener nonblocking");
+    }
+    if (accept(listen_fd, NULL, NULL) >= 0 || errno != EAGAIN) {
+        close(listen_fd);
+        THROW_ERROR("accept should fail with EAGAIN");
+    }
+    if (connect_pair(listen_fd, &addr, addr_len, fds) < 0) {
+        close(listen_fd);
+        return -1;
+    }
+
+    // The accepted socket is blocking unless SOCK_NONBLOCK is given to accept4
+    if (fcntl(fds[1], F_GETFL) & O_NONBLOCK) {
+        printf("the accepted socket should be blocking\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close_pair(fds);
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_epoll(void) {
+    struct sockaddr_storage addr;
+    socklen_t addr_len;
+    struct epoll_event event = {0};
+    int listen_fd, epfd, fds[2] = {-1, -1};
+    int ret = -1;
+
+    listen_fd = create_listener(AF_INET, &addr, &addr_len);
+    if (listen_fd < 0) {
+        return -1;
+    }
+    epfd = epoll_create1(0);
+    if (epfd < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to create an epoll instance");
+    }
+
+    event.events = EPOLLIN;
+    event.data.fd = listen_fd;
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, listen_fd, &event) < 0) {
+        printf("failed to watch the listener\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 0) {
+        printf("the listener should not be ready\n");
+        goto out;
+    }
+
+    // The listener is readable once a connection comes
+    fds[0] = socket(AF_INET, SOCK_STREAM, 0);
+    if (fds[0] < 0 || connect(fds[0], (struct sockaddr *)&addr, addr_len) < 0) {
+        printf("failed to connect to the listener\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || event.data.fd != listen_fd ||
+            !(event.events & EPOLLIN)) {
+        printf("the listener should be readable\n");
+        goto out;
+    }
+    fds[1] = accept(listen_fd, NULL, NULL);
+    if (fds[1] < 0) {
+        printf("failed to accept the connection\n");
+        goto out;
+    }
+
+    // The accepted socket is readable once data comes
+    event.events = EPOLLIN;
+    event.data.fd = fds[1];
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, fds[1], &event) < 0) {
+        printf("failed to watch the accepted socket\n");
+        goto out;
+    }
+    if (send(fds[0], request, strlen(request), 0) != strlen(request)) {
+        printf("failed to send the data\n");
+        goto out;
+    }
+    do {
+        if (epoll_wait(epfd, &event, 1, 1000) != 1) {
+            printf("the accepted socket should be readable\n");
+            goto out;
+        }
+    } while (event.data.fd != fds[1]);
+    if (!(event.events & EPOLLIN)) {
+        printf("the accepted socket should be readable\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (fds[0] >= 0) {
+        close(fds[0]);
+    }
+    if (fds[1] >= 0) {
+        close(fds[1]);
+    }
+    close(epfd);
+    close(listen_fd);
+    return ret;
+}
+
+// A socket added to epoll before it is switched to the loopback transport stays watched, and it
+// can be removed from epoll and closed afterwards
+static int test_tcp_epoll_before_connect(void) {
+    struct sockaddr_storage addr;
+    socklen_t addr_len;
+    struct epoll_event event = {0};
+    int listen_fd, epfd, fds[2] = {-1, -1};
+    int ret = -1;
+
+    listen_fd = create_listener(AF_INET, &addr, &addr_len);
+    if (listen_fd < 0) {
+        return -1;
+    }
+    epfd = epoll_create1(0);
+    fds[0] = socket(AF_INET, SOCK_STREAM, 0);
+    if (epfd < 0 || fds[0] < 0) {
+        printf("failed to create the epoll instance and the socket\n");
+        goto out;
+    }
+
+    event.events = EPOLLIN;
+    event.data.fd = fds[0];
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &event) < 0) {
+        printf("failed to watch the socket\n");
+        goto out;
+    }
+    if (connect(fds[0], (struct sockaddr *)&addr, addr_len) < 0) {
+        printf("failed to connect to the listener\n");
+        goto out;
+    }
+    fds[1] = accept(listen_fd, NULL, NULL);
+    if (fds[1] < 0) {
+        printf("failed to accept the connection\n");
+        goto out;
+    }
+    if (send(fds[1], response, strlen(response), 0) != strlen(response)) {
+        printf("failed to send the data\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || event.data.fd != fds[0] ||
+            !(event.events & EPOLLIN)) {
+        printf("the switched socket should be readable\n");
+        goto out;
+    }
+
+    if (epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], NULL) < 0) {
+        printf("failed to stop watching the switched socket\n");
+        goto out;
+    }
+    if (epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], NULL) == 0 || errno != ENOENT) {
+        printf("the switched socket should not be watched any more\n");
+        goto out;
+    }
+    if (epoll_wait(epfd, &event, 1, 0) != 0) {
+        printf("no event should be reported after the socket is removed\n");
+        goto out;
+    }
+    if (close(fds[0]) < 0) {
+        fds[0] = -1;
+        printf("failed to close the switched socket\n");
+        goto out;
+    }
+    fds[0] = -1;
+    ret = 0;
+out:
+    if (fds[0] >= 0) {
+        close(fds[0]);
+    }
+    if (fds[1] >= 0) {
+        close(fds[1]);
+    }
+    if (epfd >= 0) {
+        close(epfd);
+    }
+    close(listen_fd);
+    return ret;
+}
+
+static int test_tcp_connection_refused(void) {
+    struct sockaddr_storage addr;
+    socklen_t addr_len;
+    int listen_fd, fd;
+
+    // Get an unused port by closing the listener
+    listen_fd = create_listener(AF_INET, &addr, &addr_len);
+    if (listen_fd < 0) {
+        return -1;
+    }
+    close(listen_fd);
+
+    fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a stream socket");
+    }
+    if (connect(fd, (struct sockaddr *)&addr, addr_len) == 0 || errno != ECONNREFUSED) {
+        close(fd);
+        THROW_ERROR("connect should fail with ECONNREFUSED");
+    }
+    close(fd);
+    return 0;
+}
+
+// The socket is switched to the loopback transport on bind, which must be seen by all the
+// file descriptors of the socket, along with the options set before the switch
+static int test_tcp_dup_and_sockopt_before_bind(void) {
+    struct sockaddr_storage addr;
+    socklen_t addr_len = sizeof(struct sockaddr_in);
+    int fd, dup_fd, fds[2];
+    int optval = 1;
+    socklen_t optlen = sizeof(optval);
+    int ret = -1;
+
+    fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a stream socket");
+    }
+    dup_fd = dup(fd);
+    if (dup_fd < 0) {
+        close(fd);
+        THROW_ERROR("failed to dup the socket");
+    }
+    if (setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &optval, sizeof(optval)) < 0) {
+        printf("failed to set SO_REUSEADDR\n");
+        goto out;
+    }
+    fill_loopback_addr((struct sockaddr_in *)&addr, 0);
+    if (bind(fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        printf("failed to bind to the loopback address\n");
+        goto out;
+    }
+
+    // Listen and get the bound address through the other file descriptor
+    if (listen(dup_fd, 8) < 0) {
+        printf("failed to listen on the duplicated fd\n");
+        goto out;
+    }
+    if (getsockname(dup_fd, (struct sockaddr *)&addr, &addr_len) < 0 || port_of(&addr) == 0) {
+        printf("failed to get the bound address from the duplicated fd\n");
+        goto out;
+    }
+    optval = 0;
+    if (getsockopt(dup_fd, SOL_SOCKET, SO_REUSEADDR, &optval, &optlen) < 0 || optval == 0) {
+        printf("SO_REUSEADDR is not kept\n");
+        goto out;
+    }
+    optlen = sizeof(short);
+    if (getsockopt(dup_fd, SOL_SOCKET, SO_REUSEADDR, &optval, &optlen) == 0 || errno != EINVAL) {
+        printf("getsockopt with a short buffer should fail with EINVAL\n");
+        goto out;
+    }
+
+    if (connect_pair(fd, &addr, addr_len, fds) < 0) {
+        goto out;
+    }
+    ret = check_transfer(fds[0], fds[1], request);
+    close_pair(fds);
+out:
+    close(dup_fd);
+    close(fd);
+    if (ret < 0) {
+        THROW_ERROR("failed to use the socket switched on bind");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for UDP
+// ============================================================================
+
+static int test_udp_sendto_and_recvfrom(void) {
+    struct sockaddr_in server_addr, client_addr, src_addr;
+    socklen_t addr_len = sizeof(server_addr);
+    socklen_t src_addr_len;
+    int server_fd, client_fd;
+    char buf[64];
+    int ret = -1;
+
+    server_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    client_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (server_fd < 0 || client_fd < 0) {
+        THROW_ERROR("failed to create datagram sockets");
+    }
+    fill_loopback_addr(&server_addr, 0);
+    if (bind(server_fd, (struct sockaddr *)&server_addr, addr_len) < 0 ||
+            getsockname(server_fd, (struct sockaddr *)&server_addr, &addr_len) < 0) {
+        printf("failed to bind the server\n");
+        goto out;
+    }
+
+    // The unbound client is bound to an ephemeral port when it sends
+    if (sendto(client_fd, request, strlen(request), 0, (struct sockaddr *)&server_addr,
+               sizeof(server_addr)) != strlen(request)) {
+        printf("failed to send the request\n");
+        goto out;
+    }
+    addr_len = sizeof(client_addr);
+    if (getsockname(client_fd, (struct sockaddr *)&client_addr, &addr_len) < 0 ||
+            client_addr.sin_port == 0) {
+        printf("the client is not bound\n");
+        goto out;
+    }
+
+    memset(buf, 0, sizeof(buf));
+    src_addr_len = sizeof(src_addr);
+    if (recvfrom(server_fd, buf, sizeof(buf), 0, (struct sockaddr *)&src_addr,
+                 &src_addr_len) != strlen(request) || strcmp(buf, request) != 0) {
+        printf("the received request mismatches\n");
+        goto out;
+    }
+    if (src_addr_len != sizeof(src_addr) || src_addr.sin_port != client_addr.sin_port ||
+            src_addr.sin_addr.s_addr != htonl(INADDR_LOOPBACK)) {
+        printf("the source address mismatches the client\n");
+        goto out;
+    }
+
+    // Reply to the source address
+    if (sendto(server_fd, response, strlen(response), 0, (struct sockaddr *)&src_addr,
+               src_addr_len) != strlen(response)) {
+        printf("failed to send the response\n");
+        goto out;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (recv(client_fd, buf, sizeof(buf), 0) != strlen(response) ||
+            strcmp(buf, response) != 0) {
+        printf("the received response mismatches\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(server_fd);
+    close(client_fd);
+    return ret;
+}
+
+static int test_udp_connect_and_truncation(void) {
+    struct sockaddr_in server_addr;
+    socklen_t addr_len = sizeof(server_addr);
+    int server_fd, client_fd, nbytes;
+    char buf[4];
+    int ret = -1;
+
+    server_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    client_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (server_fd < 0 || client_fd < 0) {
+        THROW_ERROR("failed to create datagram sockets");
+    }
+    fill_loopback_addr(&server_addr, 0);
+    if (bind(server_fd, (struct sockaddr *)&server_addr, addr_len) < 0 ||
+            getsockname(server_fd, (struct sockaddr *)&server_addr, &addr_len) < 0) {
+        printf("failed to bind the server\n");
+        goto out;
+    }
+    if (connect(client_fd, (struct sockaddr *)&server_addr, addr_len) < 0) {
+        printf("failed to connect to the server\n");
+        goto out;
+    }
+
+    if (send(client_fd, request, strlen(request), 0) != strlen(request) ||
+            send(client_fd, request, strlen(request), 0) != strlen(request)) {
+        printf("failed to send the requests\n");
+        goto out;
+    }
+    // FIONREAD reports the length of the next packet
+    if (ioctl(server_fd, FIONREAD, &nbytes) < 0 || nbytes != strlen(request)) {
+        printf("FIONREAD should report the length of the next packet\n");
+        goto out;
+    }
+    // The part that does not fit is discarded, and MSG_TRUNC gives the real length
+    if (recv(server_fd, buf, sizeof(buf), 0) != sizeof(buf) ||
+            recv(server_fd, buf, sizeof(buf), MSG_TRUNC) != strlen(request)) {
+        printf("the packet is not truncated\n");
+        goto out;
+    }
+    if (recv(server_fd, buf, sizeof(buf), MSG_DONTWAIT) >= 0 || errno != EAGAIN) {
+        printf("recv should fail with EAGAIN\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(server_fd);
+    close(client_fd);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_tcp_ipv4),
+    TEST_CASE(test_tcp_ipv6),
+    TEST_CASE(test_tcp_nonblocking_accept),
+    TEST_CASE(test_tcp_epoll),
+    TEST_CASE(test_tcp_epoll_before_connect),
+    TEST_CASE(test_tcp_connection_refused),
+    TEST_CASE(test_tcp_dup_and_sockopt_before_bind),
+    TEST_CASE(test_udp_sendto_and_recvfrom),
+    TEST_CASE(test_udp_connect_and_truncation),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,289 @@
+#define _GNU_SOURCE
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#ifndef MFD_CLOEXEC
+#define MFD_CLOEXEC         0x0001U
+#define MFD_ALLOW_SEALING   0x0002U
+#endif
+
+#ifndef F_ADD_SEALS
+#define F_ADD_SEALS         1033
+#define F_GET_SEALS         1034
+#define F_SEAL_SEAL         0x0001
+#define F_SEAL_SHRINK       0x0002
+#define F_SEAL_GROW         0x0004
+#define F_SEAL_WRITE        0x0008
+#endif
+
+#ifndef F_SEAL_FUTURE_WRITE
+#define F_SEAL_FUTURE_WRITE 0x0010
+#endif
+
+#define PAGE_SIZE           4096
+
+static const char test_data[] = "Hello from memfd";
+
+static int create_memfd(const char *name, unsigned int flags) {
+    return syscall(SYS_memfd_create, name, flags);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_create(void) {
+    struct stat stat_buf;
+    int fd = create_memfd("test_create", MFD_CLOEXEC);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (fcntl(fd, F_GETFD) != FD_CLOEXEC) {
+        close(fd);
+        THROW_ERROR("the close-on-exec flag is not set");
+    }
+    if (fstat(fd, &stat_buf) < 0 || !S_ISREG(stat_buf.st_mode) || stat_buf.st_size != 0) {
+        close(fd);
+        THROW_ERROR("a memfd should be an empty regular file");
+    }
+    close(fd);
+
+    if (create_memfd("test_create", 0x100) != -1 || errno != EINVAL) {
+        THROW_ERROR("memfd_create with invalid flags should fail with EINVAL");
+    }
+    return 0;
+}
+
+static int test_read_and_write(void) {
+    char buf[sizeof(test_data)] = { 0 };
+    int fd = create_memfd("test_read_and_write", 0);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (write(fd, test_data, sizeof(test_data)) != sizeof(test_data)) {
+        close(fd);
+        THROW_ERROR("write failed");
+    }
+    if (pread(fd, buf, sizeof(buf), 0) != sizeof(buf) || strcmp(buf, test_data) != 0) {
+        close(fd);
+        THROW_ERROR("the data read is not the data written");
+    }
+    if (ftruncate(fd, PAGE_SIZE) < 0 || lseek(fd, 0, SEEK_END) != PAGE_SIZE) {
+        close(fd);
+        THROW_ERROR("ftruncate failed");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_mmap_shared(void) {
+    char buf[sizeof(test_data)] = { 0 };
+    int fd = create_memfd("test_mmap_shared", 0);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (ftruncate(fd, PAGE_SIZE) < 0) {
+        close(fd);
+        THROW_ERROR("ftruncate failed");
+    }
+    char *addr = mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (addr == MAP_FAILED) {
+        close(fd);
+        THROW_ERROR("mmap failed");
+    }
+    strcpy(addr, test_data);
+    if (msync(addr, PAGE_SIZE, MS_SYNC) < 0) {
+        munmap(addr, PAGE_SIZE);
+        close(fd);
+        THROW_ERROR("msync failed");
+    }
+    munmap(addr, PAGE_SIZE);
+    if (pread(fd, buf, sizeof(buf), 0) != sizeof(buf) || strcmp(buf, test_data) != 0) {
+        close(fd);
+        THROW_ERROR("the data written to the mapping is not in the file");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_sealing_not_allowed(void) {
+    int fd = create_memfd("test_sealing_not_allowed", 0);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (fcntl(fd, F_GET_SEALS) != F_SEAL_SEAL) {
+        close(fd);
+        THROW_ERROR("a memfd without MFD_ALLOW_SEALING should be sealed with F_SEAL_SEAL");
+    }
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE) != -1 || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("adding seals should fail with EPERM");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_seal_shrink_and_grow(void) {
+    int fd = create_memfd("test_seal_shrink_and_grow", MFD_ALLOW_SEALING);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (ftruncate(fd, PAGE_SIZE) < 0 ||
+            fcntl(fd, F_ADD_SEALS, F_SEAL_SHRINK | F_SEAL_GROW) < 0 ||
+            fcntl(fd, F_GET_SEALS) != (F_SEAL_SHRINK | F_SEAL_GROW)) {
+        close(fd);
+        THROW_ERROR("failed to add seals");
+    }
+    if (ftruncate(fd, PAGE_SIZE / 2) != -1 || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("shrinking a sealed file should fail with EPERM");
+    }
+    if (ftruncate(fd, PAGE_SIZE * 2) != -1 || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("growing a sealed file should fail with EPERM");
+    }
+    if (pwrite(fd, test_data, sizeof(test_data), PAGE_SIZE) != -1 || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("writing beyond the end of a sealed file should fail with EPERM");
+    }
+    // Writes within the file are still allowed
+    if (pwrite(fd, test_data, sizeof(test_data), 0) != sizeof(test_data)) {
+        close(fd);
+        THROW_ERROR("writing within the sealed file failed");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_seal_write(void) {
+    int fd = create_memfd("test_seal_write", MFD_ALLOW_SEALING);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (ftruncate(fd, PAGE_SIZE) < 0) {
+        close(fd);
+        THROW_ERROR("ftruncate failed");
+    }
+
+    char *addr = mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (addr == MAP_FAILED) {
+        close(fd);
+        THROW_ERROR("mmap failed");
+    }
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE) != -1 || errno != EBUSY) {
+        munmap(addr, PAGE_SIZE);
+        close(fd);
+        THROW_ERROR("sealing a file with writable shared mappings should fail with EBUSY");
+    }
+    munmap(addr, PAGE_SIZE);
+
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE) < 0) {
+        close(fd);
+        THROW_ERROR("failed to add F_SEAL_WRITE");
+    }
+    if (write(fd, test_data, sizeof(test_data)) != -1 || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("writing a sealed file should fail with EPERM");
+    }
+    addr = mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (addr != MAP_FAILED || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("mapping a sealed file as shared and writable should fail with EPERM");
+    }
+    addr = mmap(NULL, PAGE_SIZE, PROT_READ, MAP_SHARED, fd, 0);
+    if (addr == MAP_FAILED) {
+        close(fd);
+        THROW_ERROR("mapping a sealed file as read-only should succeed");
+    }
+    munmap(addr, PAGE_SIZE);
+    close(fd);
+    return 0;
+}
+
+static int test_seal_future_write(void) {
+    char buf[sizeof(test_data)] = { 0 };
+    int fd = create_memfd("test_seal_future_write", MFD_ALLOW_SEALING);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (ftruncate(fd, PAGE_SIZE) < 0) {
+        close(fd);
+        THROW_ERROR("ftruncate failed");
+    }
+    char *addr = mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (addr == MAP_FAILED) {
+        close(fd);
+        THROW_ERROR("mmap failed");
+    }
+
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_FUTURE_WRITE) < 0) {
+        munmap(addr, PAGE_SIZE);
+        close(fd);
+        THROW_ERROR("failed to add F_SEAL_FUTURE_WRITE");
+    }
+    if (write(fd, test_data, sizeof(test_data)) != -1 || errno != EPERM) {
+        munmap(addr, PAGE_SIZE);
+        close(fd);
+        THROW_ERROR("writing a sealed file should fail with EPERM");
+    }
+    // The existing writable mapping keeps writing to the file
+    strcpy(addr, test_data);
+    if (msync(addr, PAGE_SIZE, MS_SYNC) < 0) {
+        munmap(addr, PAGE_SIZE);
+        close(fd);
+        THROW_ERROR("msync failed");
+    }
+    munmap(addr, PAGE_SIZE);
+    if (pread(fd, buf, sizeof(buf), 0) != sizeof(buf) || strcmp(buf, test_data) != 0) {
+        close(fd);
+        THROW_ERROR("the data written to the existing mapping is lost");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_seal_seal(void) {
+    int fd = create_memfd("test_seal_seal", MFD_ALLOW_SEALING);
+    if (fd < 0) {
+        THROW_ERROR("memfd_create failed");
+    }
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_SEAL) < 0) {
+        close(fd);
+        THROW_ERROR("failed to add F_SEAL_SEAL");
+    }
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_GROW) != -1 || errno != EPERM) {
+        close(fd);
+        THROW_ERROR("adding seals after F_SEAL_SEAL should fail with EPERM");
+    }
+    close(fd);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_create),
+    TEST_CASE(test_read_and_write),
+    TEST_CASE(test_mmap_shared),
+    TEST_CASE(test_sealing_not_allowed),
+    TEST_CASE(test_seal_shrink_and_grow),
+    TEST_CASE(test_seal_write),
+    TEST_CASE(test_seal_future_write),
+    TEST_CASE(test_seal_seal),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -2,6 +2,7 @@
 #include <sys/types.h>
 #include <sys/stat.h>
 #include <sys/mman.h>
+#include <sys/resource.h>
 #include <unistd.h>
 #include <stdio.h>
 #include <stdlib.h>
@@ -36,6 +37,43 @@ static void *get_a_stack_ptr() {
     return (void *) &a;
 }
 
+// Get the size of the locked memory in bytes from VmLck of /proc/self/status
+static long get_locked_size() {
+    FILE *fp = fopen("/proc/self/status", "r");
+    if (fp == NULL) {
+        return -1;
+    }
+    char line[256];
+    long locked_kb = -1;
+    while (fgets(line, sizeof(line), fp) != NULL) {
+        if (sscanf(line, "VmLck: %ld kB", &locked_kb) == 1) {
+            break;
+        }
+    }
+    fclose(fp);
+    return locked_kb < 0 ? -1 : locked_kb * KB;
+}
+
+// Check whether the mapping starting at the address is shown as locked in /proc/self/maps
+static int is_locked_in_maps(void *addr) {
+    FILE *fp = fopen("/proc/self/maps", "r");
+    if (fp == NULL) {
+        return -1;
+    }
+    char prefix[32];
+    snprintf(prefix, sizeof(prefix), "%lx-", (unsigned long)addr);
+    char line[512];
+    int locked = -1;
+    while (fgets(line, sizeof(line), fp) != NULL) {
+        if (strncmp(line, prefix, strlen(prefix)) == 0) {
+            locked = strstr(line, " (locked)") != NULL;
+            break;
+        }
+    }
+    fclose(fp);
+    return locked;
+}
+
 // ============================================================================
 // Test suite initialization
 // ============================================================================
@@ -1417,6 +1455,332 @@ int test_file_backed_mremap_mem_may_move() {
     return _test_file_backed_mremap(file_backed_mremap_mem_may_move);
 }
 
+// ============================================================================
+// Test cases for madvise and mincore
+// ============================================================================
+
+int test_madvise_dontneed_anonymous() {
+    size_t len = 4 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+    memset(buf, 0xab, len);
+
+    // Only the middle two pages are zeroed
+    if (madvise(buf + PAGE_SIZE, 2 * PAGE_SIZE, MADV_DONTNEED) < 0) {
+        THROW_ERROR("madvise failed");
+    }
+    if (check_bytes_in_buf(buf, PAGE_SIZE, 0xab) < 0 ||
+            check_bytes_in_buf(buf + PAGE_SIZE, 2 * PAGE_SIZE, 0) < 0 ||
+            check_bytes_in_buf(buf + 3 * PAGE_SIZE, PAGE_SIZE, 0xab) < 0) {
+        THROW_ERROR("the memory is not reset as expected");
+    }
+
+    // MADV_FREE may or may not free the memory
+    if (madvise(buf, len, MADV_FREE) < 0) {
+        THROW_ERROR("madvise failed");
+    }
+
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    return 0;
+}
+
+int test_madvise_dontneed_private_file() {
+    const char *file_path = "/root/mmap_file.data";
+    int fd = open(file_path, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0) {
+        THROW_ERROR("file creation failed");
+    }
+    size_t len = 2 * PAGE_SIZE;
+    fill_file_with_repeated_bytes(fd, len, byte_val_1);
+
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+    memset(buf, byte_val_2, len);
+
+    // The private file mapping is read from the file again
+    if (madvise(buf, len, MADV_DONTNEED) < 0) {
+        THROW_ERROR("madvise failed");
+    }
+    if (check_bytes_in_buf(buf, len, byte_val_1) < 0) {
+        THROW_ERROR("the memory is not read from the file again");
+    }
+    // MADV_FREE only applies to private anonymous memory
+    if (madvise(buf, len, MADV_FREE) != -1 || errno != EINVAL) {
+        THROW_ERROR("madvise MADV_FREE on file-backed memory should fail with EINVAL");
+    }
+    // Hints are accepted
+    if (madvise(buf, len, MADV_WILLNEED) < 0 || madvise(buf, len, MADV_SEQUENTIAL) < 0 ||
+            madvise(buf, len, MADV_RANDOM) < 0 || madvise(buf, len, MADV_DONTFORK) < 0) {
+        THROW_ERROR("madvise with hints failed");
+    }
+    // MADV_WIPEONFORK only applies to private anonymous memory
+    if (madvise(buf, len, MADV_WIPEONFORK) != -1 || errno != EINVAL) {
+        THROW_ERROR("madvise MADV_WIPEONFORK on file-backed memory should fail with EINVAL");
+    }
+
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    close(fd);
+    unlink(file_path);
+    return 0;
+}
+
+int test_madvise_with_invalid_args() {
+    size_t len = 2 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+
+    if (madvise(buf + 1, PAGE_SIZE, MADV_DONTNEED) != -1 || errno != EINVAL) {
+        THROW_ERROR("madvise with a non page aligned address should fail with EINVAL");
+    }
+    if (madvise(buf, PAGE_SIZE, 1000) != -1 || errno != EINVAL) {
+        THROW_ERROR("madvise with an invalid advice should fail with EINVAL");
+    }
+    if (madvise(buf, 0, MADV_DONTNEED) < 0) {
+        THROW_ERROR("madvise with zero length should succeed");
+    }
+
+    if (munmap(buf + PAGE_SIZE, PAGE_SIZE) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    if (madvise(buf, len, MADV_DONTNEED) != -1 || errno != ENOMEM) {
+        THROW_ERROR("madvise on unmapped memory should fail with ENOMEM");
+    }
+
+    if (munmap(buf, PAGE_SIZE) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    return 0;
+}
+
+int test_mincore() {
+    size_t len = 3 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+
+    // The length is rounded up to pages
+    unsigned char vec[3] = {0};
+    if (mincore(buf, len - 1, vec) < 0) {
+        THROW_ERROR("mincore failed");
+    }
+    for (int i = 0; i < 3; i++) {
+        if ((vec[i] & 1) != 1) {
+            THROW_ERROR("the mapped pages should be resident");
+        }
+    }
+
+    if (mincore(buf + 1, PAGE_SIZE, vec) != -1 || errno != EINVAL) {
+        THROW_ERROR("mincore with a non page aligned address should fail with EINVAL");
+    }
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    if (mincore(buf, len, vec) != -1 || errno != ENOMEM) {
+        THROW_ERROR("mincore on unmapped memory should fail with ENOMEM");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for mlock and its friends
+// ============================================================================
+
+int test_mlock_and_munlock() {
+    size_t len = 4 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+    long old_locked_size = get_locked_size();
+    if (old_locked_size < 0) {
+        THROW_ERROR("failed to get the locked size");
+    }
+
+    // The range is rounded to pages, so the middle two pages are locked
+    if (mlock(buf + PAGE_SIZE + 1, PAGE_SIZE) < 0) {
+        THROW_ERROR("mlock failed");
+    }
+    if (get_locked_size() != old_locked_size + 2 * PAGE_SIZE) {
+        THROW_ERROR("the middle two pages should be locked");
+    }
+    // Locking the locked memory again is not counted twice
+    if (syscall(SYS_mlock2, buf, 3 * PAGE_SIZE, MLOCK_ONFAULT) < 0) {
+        THROW_ERROR("mlock2 failed");
+    }
+    if (get_locked_size() != old_locked_size + 3 * PAGE_SIZE) {
+        THROW_ERROR("the first three pages should be locked");
+    }
+    // The locked memory can't be discarded
+    if (madvise(buf, PAGE_SIZE, MADV_DONTNEED) != -1 || errno != EINVAL) {
+        THROW_ERROR("madvise MADV_DONTNEED on locked memory should fail with EINVAL");
+    }
+
+    if (munlock(buf + PAGE_SIZE, PAGE_SIZE) < 0) {
+        THROW_ERROR("munlock failed");
+    }
+    if (get_locked_size() != old_locked_size + 2 * PAGE_SIZE) {
+        THROW_ERROR("the second page should be unlocked");
+    }
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    if (get_locked_size() != old_locked_size) {
+        THROW_ERROR("the unmapped memory should not be locked");
+    }
+    return 0;
+}
+
+int test_mlock_with_invalid_args() {
+    size_t len = 2 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+
+    if (syscall(SYS_mlock2, buf, len, 0x100) != -1 || errno != EINVAL) {
+        THROW_ERROR("mlock2 with invalid flags should fail with EINVAL");
+    }
+    if (mlockall(0) != -1 || errno != EINVAL) {
+        THROW_ERROR("mlockall with no flags should fail with EINVAL");
+    }
+
+    if (munmap(buf + PAGE_SIZE, PAGE_SIZE) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    if (mlock(buf, len) != -1 || errno != ENOMEM) {
+        THROW_ERROR("mlock on unmapped memory should fail with ENOMEM");
+    }
+    if (munlock(buf, len) != -1 || errno != ENOMEM) {
+        THROW_ERROR("munlock on unmapped memory should fail with ENOMEM");
+    }
+
+    if (munmap(buf, PAGE_SIZE) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    return 0;
+}
+
+int test_mlock_with_rlimit_memlock() {
+    size_t len = 2 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+    long locked_size = get_locked_size();
+    if (locked_size < 0) {
+        THROW_ERROR("failed to get the locked size");
+    }
+
+    struct rlimit old_rlim;
+    if (getrlimit(RLIMIT_MEMLOCK, &old_rlim) < 0) {
+        THROW_ERROR("getrlimit failed");
+    }
+    // Only one more page can be locked
+    struct rlimit new_rlim = {
+        .rlim_cur = locked_size + PAGE_SIZE,
+        .rlim_max = old_rlim.rlim_max,
+    };
+    if (setrlimit(RLIMIT_MEMLOCK, &new_rlim) < 0) {
+        THROW_ERROR("setrlimit failed");
+    }
+
+    int ret = 0;
+    if (mlock(buf, len) != -1 || errno != ENOMEM) {
+        printf("mlock exceeding RLIMIT_MEMLOCK should fail with ENOMEM\n");
+        ret = -1;
+    } else if (mlock(buf, PAGE_SIZE) < 0) {
+        printf("mlock within RLIMIT_MEMLOCK should succeed\n");
+        ret = -1;
+    } else if (mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+                    MAP_PRIVATE | MAP_ANONYMOUS | MAP_LOCKED, -1, 0) != MAP_FAILED
+               || errno != EAGAIN) {
+        printf("mmap with MAP_LOCKED exceeding RLIMIT_MEMLOCK should fail with EAGAIN\n");
+        ret = -1;
+    }
+
+    if (setrlimit(RLIMIT_MEMLOCK, &old_rlim) < 0) {
+        THROW_ERROR("setrlimit failed");
+    }
+    if (ret < 0) {
+        THROW_ERROR("RLIMIT_MEMLOCK is not enforced");
+    }
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    return 0;
+}
+
+int test_mlockall_and_munlockall() {
+    if (mlockall(MCL_CURRENT | MCL_FUTURE) < 0) {
+        THROW_ERROR("mlockall failed");
+    }
+    long locked_size = get_locked_size();
+    if (locked_size <= 0) {
+        THROW_ERROR("the current memory should be locked");
+    }
+
+    // The future memory is locked too
+    size_t len = 2 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+    if (get_locked_size() < locked_size + len) {
+        THROW_ERROR("the new mapping should be locked");
+    }
+
+    if (munlockall() < 0) {
+        THROW_ERROR("munlockall failed");
+    }
+    if (get_locked_size() != 0) {
+        THROW_ERROR("all the memory should be unlocked");
+    }
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    return 0;
+}
+
+int test_mlock_shown_in_maps() {
+    size_t len = 2 * PAGE_SIZE;
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+
+    if (mlock(buf, PAGE_SIZE) < 0) {
+        THROW_ERROR("mlock failed");
+    }
+    if (is_locked_in_maps(buf) != 1) {
+        THROW_ERROR("the locked page should be shown as locked");
+    }
+    if (is_locked_in_maps(buf + PAGE_SIZE) != 0) {
+        THROW_ERROR("the unlocked page should not be shown as locked");
+    }
+
+    if (munlock(buf, PAGE_SIZE) < 0) {
+        THROW_ERROR("munlock failed");
+    }
+    if (is_locked_in_maps(buf) != 0) {
+        THROW_ERROR("the unlocked page should not be shown as locked");
+    }
+    if (munmap(buf, len) < 0) {
+        THROW_ERROR("munmap failed");
+    }
+    return 0;
+}
+
 // ============================================================================
 // Test suite main
 // ============================================================================
@@ -1464,6 +1828,15 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_mprotect_multiple_vmas),
     TEST_CASE(test_mprotect_grow_down),
     TEST_CASE(test_mremap_concurrent),
+    TEST_CASE(test_madvise_dontneed_anonymous),
+    TEST_CASE(test_madvise_dontneed_private_file),
+    TEST_CASE(test_madvise_with_invalid_args),
+    TEST_CASE(test_mincore),
+    TEST_CASE(test_mlock_and_munlock),
+    TEST_CASE(test_mlock_with_invalid_args),
+    TEST_CASE(test_mlock_with_rlimit_memlock),
+    TEST_CASE(test_mlockall_and_munlockall),
+    TEST_CASE(test_mlock_shown_in_maps),
 };
 
 int main() {
@@ -0,0 +1,376 @@
+#define _GNU_SOURCE
+#include <sys/epoll.h>
+#include <sys/stat.h>
+#include <sys/types.h>
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <mqueue.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Global definitions
+// ============================================================================
+
+#define MQ_NAME         "/test_mqueue"
+#define MQ_MAXMSG       4
+#define MQ_MSGSIZE      64
+
+static mqd_t open_test_mq(int extra_flags) {
+    struct mq_attr attr = {
+        .mq_maxmsg = MQ_MAXMSG,
+        .mq_msgsize = MQ_MSGSIZE,
+    };
+    return mq_open(MQ_NAME, O_RDWR | O_CREAT | O_EXCL | extra_flags, 0600, &attr);
+}
+
+static void close_test_mq(mqd_t mqd) {
+    mq_close(mqd);
+    mq_unlink(MQ_NAME);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_mq_open_and_unlink(void) {
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_open(MQ_NAME, O_RDWR | O_CREAT | O_EXCL, 0600, NULL) != (mqd_t) -1 ||
+            errno != EEXIST) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_open with O_EXCL should fail with EEXIST");
+    }
+    close_test_mq(mqd);
+
+    if (mq_open(MQ_NAME, O_RDWR) != (mqd_t) -1 || errno != ENOENT) {
+        THROW_ERROR("mq_open should fail with ENOENT after unlink");
+    }
+    if (mq_unlink(MQ_NAME) != -1 || errno != ENOENT) {
+        THROW_ERROR("mq_unlink should fail with ENOENT");
+    }
+    return 0;
+}
+
+static int test_mq_priority(void) {
+    const char *msgs[] = {"low", "high", "middle", "high2"};
+    unsigned int prios[] = {1, 10, 5, 10};
+    const char *expected_msgs[] = {"high", "high2", "middle", "low"};
+    unsigned int expected_prios[] = {10, 10, 5, 1};
+    char buf[MQ_MSGSIZE];
+    unsigned int prio;
+
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    for (int i = 0; i < 4; i++) {
+        if (mq_send(mqd, msgs[i], strlen(msgs[i]) + 1, prios[i]) < 0) {
+            close_test_mq(mqd);
+            THROW_ERROR("mq_send failed");
+        }
+    }
+    for (int i = 0; i < 4; i++) {
+        ssize_t len = mq_receive(mqd, buf, sizeof(buf), &prio);
+        if (len != strlen(expected_msgs[i]) + 1 || strcmp(buf, expected_msgs[i]) != 0 ||
+                prio != expected_prios[i]) {
+            close_test_mq(mqd);
+            THROW_ERROR("received an unexpected message");
+        }
+    }
+    close_test_mq(mqd);
+    return 0;
+}
+
+static int test_mq_nonblocking_and_timeout(void) {
+    char buf[MQ_MSGSIZE] = "msg";
+    struct mq_attr attr, old_attr;
+    struct timespec timeout;
+
+    mqd_t mqd = open_test_mq(O_NONBLOCK);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_receive(mqd, buf, sizeof(buf), NULL) != -1 || errno != EAGAIN) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_receive on an empty queue should fail with EAGAIN");
+    }
+    for (int i = 0; i < MQ_MAXMSG; i++) {
+        if (mq_send(mqd, buf, 4, 0) < 0) {
+            close_test_mq(mqd);
+            THROW_ERROR("mq_send failed");
+        }
+    }
+    if (mq_send(mqd, buf, 4, 0) != -1 || errno != EAGAIN) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_send on a full queue should fail with EAGAIN");
+    }
+    if (mq_send(mqd, buf, MQ_MSGSIZE + 1, 0) != -1 || errno != EMSGSIZE) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_send of a too long message should fail with EMSGSIZE");
+    }
+
+    if (mq_getattr(mqd, &attr) < 0 || attr.mq_curmsgs != MQ_MAXMSG ||
+            attr.mq_maxmsg != MQ_MAXMSG || attr.mq_msgsize != MQ_MSGSIZE ||
+            !(attr.mq_flags & O_NONBLOCK)) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_getattr returned unexpected attributes");
+    }
+    attr.mq_flags = 0;
+    if (mq_setattr(mqd, &attr, &old_attr) < 0 || !(old_attr.mq_flags & O_NONBLOCK)) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_setattr failed");
+    }
+
+    // Now the descriptor is blocking, so a full queue makes the sender time out
+    clock_gettime(CLOCK_REALTIME, &timeout);
+    timeout.tv_nsec += 100 * 1000 * 1000;
+    if (timeout.tv_nsec >= 1000 * 1000 * 1000) {
+        timeout.tv_sec += 1;
+        timeout.tv_nsec -= 1000 * 1000 * 1000;
+    }
+    if (mq_timedsend(mqd, buf, 4, 0, &timeout) != -1 || errno != ETIMEDOUT) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_timedsend should fail with ETIMEDOUT");
+    }
+    close_test_mq(mqd);
+    return 0;
+}
+
+static int test_mq_poll_and_epoll(void) {
+    char buf[MQ_MSGSIZE] = "msg";
+    struct pollfd pfd;
+    struct epoll_event event = { .events = EPOLLIN };
+
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    pfd.fd = mqd;
+    pfd.events = POLLIN | POLLOUT;
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLOUT) {
+        close_test_mq(mqd);
+        THROW_ERROR("an empty queue should only be writable");
+    }
+
+    int epfd = epoll_create1(0);
+    if (epfd < 0 || epoll_ctl(epfd, EPOLL_CTL_ADD, mqd, &event) < 0) {
+        close_test_mq(mqd);
+        THROW_ERROR("failed to add the queue to epoll");
+    }
+    if (mq_send(mqd, buf, 4, 0) < 0) {
+        close(epfd);
+        close_test_mq(mqd);
+        THROW_ERROR("mq_send failed");
+    }
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || !(event.events & EPOLLIN)) {
+        close(epfd);
+        close_test_mq(mqd);
+        THROW_ERROR("epoll_wait should report the queue as readable");
+    }
+    close(epfd);
+    close_test_mq(mqd);
+    return 0;
+}
+
+static volatile int notified_value = 0;
+
+static void mq_notify_handler(int signum, siginfo_t *info, void *ucontext) {
+    if (info->si_code == SI_MESGQ) {
+        notified_value = info->si_value.sival_int;
+    }
+}
+
+static int test_mq_notify(void) {
+    char buf[MQ_MSGSIZE] = "msg";
+    struct sigaction sa = {
+        .sa_sigaction = mq_notify_handler,
+        .sa_flags = SA_SIGINFO,
+    };
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGUSR1,
+        .sigev_value.sival_int = 42,
+    };
+
+    if (sigaction(SIGUSR1, &sa, NULL) < 0) {
+        THROW_ERROR("sigaction failed");
+    }
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_notify(mqd, &sev) < 0) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_notify failed");
+    }
+    if (mq_send(mqd, buf, 4, 0) < 0) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_send failed");
+    }
+    for (int i = 0; i < 100 && notified_value == 0; i++) {
+        usleep(10 * 1000);
+    }
+    if (notified_value != 42) {
+        close_test_mq(mqd);
+        THROW_ERROR("the notification signal is not received");
+    }
+
+    // The registration is removed after the notification, so register again
+    if (mq_notify(mqd, &sev) < 0 || mq_notify(mqd, NULL) < 0) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_notify failed to register or unregister");
+    }
+    close_test_mq(mqd);
+    signal(SIGUSR1, SIG_DFL);
+    return 0;
+}
+
+static int get_notify_pid(void) {
+    char buf[128] = {0};
+    int fd = open("/dev/mqueue" MQ_NAME, O_RDONLY);
+    if (fd < 0) {
+        return -1;
+    }
+    int len = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    char *pos = strstr(buf, "NOTIFY_PID:");
+    if (len <= 0 || pos == NULL) {
+        return -1;
+    }
+    return atoi(pos + strlen("NOTIFY_PID:"));
+}
+
+static int test_mq_notify_invalid_signo(void) {
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        // Must not be truncated into a valid signal number
+        .sigev_signo = 256 + SIGUSR1,
+    };
+
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_notify(mqd, &sev) != -1 || errno != EINVAL) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_notify should reject an invalid signal number");
+    }
+    close_test_mq(mqd);
+    return 0;
+}
+
+static int test_mq_notify_removed_on_last_close(void) {
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_NONE,
+    };
+
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+    mqd_t another_mqd = mq_open(MQ_NAME, O_RDWR);
+    if (another_mqd == (mqd_t) -1) {
+        close_test_mq(mqd);
+        THROW_ERROR("mq_open failed");
+    }
+    if (mq_notify(mqd, &sev) < 0) {
+        mq_close(another_mqd);
+        close_test_mq(mqd);
+        THROW_ERROR("mq_notify failed");
+    }
+
+    // Still registered as long as one descriptor of the queue is open
+    mq_close(mqd);
+    if (get_notify_pid() != getpid()) {
+        close_test_mq(another_mqd);
+        THROW_ERROR("the registration should be kept");
+    }
+    mq_close(another_mqd);
+    if (get_notify_pid() != 0) {
+        mq_unlink(MQ_NAME);
+        THROW_ERROR("the registration should be removed on the last close");
+    }
+    mq_unlink(MQ_NAME);
+    return 0;
+}
+
+static int test_dev_mqueue(void) {
+    char buf[128];
+    int found = 0;
+
+    mqd_t mqd = open_test_mq(0);
+    if (mqd == (mqd_t) -1) {
+        THROW_ERROR("mq_open failed");
+    }
+
+    DIR *dir = opendir("/dev/mqueue");
+    if (dir == NULL) {
+        close_test_mq(mqd);
+        THROW_ERROR("failed to open /dev/mqueue");
+    }
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (strcmp(entry->d_name, MQ_NAME + 1) == 0) {
+            found = 1;
+        }
+    }
+    closedir(dir);
+    if (!found) {
+        close_test_mq(mqd);
+        THROW_ERROR("the queue is not listed in /dev/mqueue");
+    }
+
+    int fd = open("/dev/mqueue" MQ_NAME, O_RDONLY);
+    if (fd < 0) {
+        close_test_mq(mqd);
+        THROW_ERROR("failed to open the queue in /dev/mqueue");
+    }
+    memset(buf, 0, sizeof(buf));
+    if (read(fd, buf, sizeof(buf) - 1) <= 0 || strncmp(buf, "QSIZE:", 6) != 0) {
+        close(fd);
+        close_test_mq(mqd);
+        THROW_ERROR("unexpected status of the queue");
+    }
+    close(fd);
+
+    // Removing the file from /dev/mqueue removes the queue
+    if (unlink("/dev/mqueue" MQ_NAME) < 0) {
+        close_test_mq(mqd);
+        THROW_ERROR("failed to unlink the queue in /dev/mqueue");
+    }
+    if (mq_unlink(MQ_NAME) != -1 || errno != ENOENT) {
+        mq_close(mqd);
+        THROW_ERROR("the queue should have been removed");
+    }
+    mq_close(mqd);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_mq_open_and_unlink),
+    TEST_CASE(test_mq_priority),
+    TEST_CASE(test_mq_nonblocking_and_timeout),
+    TEST_CASE(test_mq_poll_and_epoll),
+    TEST_CASE(test_mq_notify),
+    TEST_CASE(test_mq_notify_invalid_signo),
+    TEST_CASE(test_mq_notify_removed_on_last_close),
+    TEST_CASE(test_dev_mqueue),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,301 @@
+#define _GNU_SOURCE
+#include <sys/ipc.h>
+#include <sys/msg.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/types.h>
+#include <sys/wait.h>
+#include <pthread.h>
+#include <time.h>
+#include <stdlib.h>
+#include <stdio.h>
+#include <spawn.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Global definitions
+// ============================================================================
+
+#define S_IRWUSER   (S_IRUSR | S_IWUSR)
+
+#define TEST_PROCESS_COMMU      0
+#define TEST_PROCESS_COMMU_ARGC 3
+
+#define ARG_BUF_SZ  64
+#define MSG_TEXT_SZ 64
+
+#define SUCCESS     1
+#define FAIL        (-1)
+
+const char prog_name[] = "/bin/msg";
+const char msg_text[] = "Hello from msg queue";
+
+struct msg_buf {
+    long mtype;
+    char mtext[MSG_TEXT_SZ];
+};
+
+// ============================================================================
+// Helper macro and function
+// ============================================================================
+#define INFO(fmt, ...)   do { \
+    printf("\t\t[file: %s, line: %d, func: %s] " fmt, \
+    __FILE__, __LINE__, __func__, ##__VA_ARGS__); \
+} while (0)
+
+static int execute_in_child(char **const child_argv) {
+    int ret;
+    pid_t child_pid;
+    int child_status;
+
+    ret = posix_spawn(&child_pid, prog_name, NULL, NULL, (char **const)child_argv, NULL);
+    if (ret < 0) {
+        THROW_ERROR("Failed to spawn a child process");
+    }
+    ret = waitpid(child_pid, &child_status, 0);
+    if (ret < 0) {
+        THROW_ERROR("Failed to waitpid() for child process");
+    }
+    if (!WIFEXITED(child_status) || WEXITSTATUS(child_status) != 0) {
+        INFO("The test in child failed\n");
+        return FAIL;
+    }
+
+    return SUCCESS;
+}
+
+static int send_msg(int msqid, long mtype, const char *text, int flags) {
+    struct msg_buf msg;
+
+    msg.mtype = mtype;
+    strncpy(msg.mtext, text, MSG_TEXT_SZ);
+    return msgsnd(msqid, &msg, strlen(text) + 1, flags);
+}
+
+// ============================================================================
+// Test cases for msg
+// ============================================================================
+static int test_msgget_msqid_from_key(void) {
+    int ret, msqid;
+    key_t key;
+
+    srand(time(NULL));
+    key = random();
+    ret = msgget(key, S_IRWUSER);
+    if (ret != -1 || errno != ENOENT) {
+        INFO("msgget() should return ENOENT, ret: %d errno: %d\n", ret, errno);
+        return FAIL;
+    }
+    msqid = msgget(key, IPC_CREAT | IPC_EXCL | S_IRWUSER);
+    if (msqid < 0) {
+        THROW_ERROR("msgget() cannot create the message queue");
+    }
+    if ((ret = msgget(key, S_IRWUSER)) != msqid) {
+        INFO("msqid mismatches, correct: %d actual: %d\n", msqid, ret);
+        return FAIL;
+    }
+    ret = msgget(key, IPC_CREAT | IPC_EXCL | S_IRWUSER);
+    if (ret != -1 || errno != EEXIST) {
+        INFO("msgget() should return EEXIST, ret: %d errno: %d\n", ret, errno);
+        return FAIL;
+    }
+    if (msgctl(msqid, IPC_RMID, NULL) < 0) {
+        THROW_ERROR("Cannot remove the message queue");
+    }
+    return SUCCESS;
+}
+
+static int test_msg_type_selection(void) {
+    int msqid;
+    ssize_t len;
+    struct msg_buf msg;
+    struct msqid_ds ds;
+
+    msqid = msgget(IPC_PRIVATE, IPC_CREAT | S_IRWUSER);
+    if (msqid < 0) {
+        THROW_ERROR("msgget() cannot create the message queue");
+    }
+    if (send_msg(msqid, 3, "three", 0) < 0 || send_msg(msqid, 1, "one", 0) < 0 ||
+            send_msg(msqid, 2, "two", 0) < 0) {
+        THROW_ERROR("msgsnd() failed");
+    }
+
+    if (msgctl(msqid, IPC_STAT, &ds) < 0) {
+        THROW_ERROR("msgctl(IPC_STAT) failed");
+    }
+    if (ds.msg_qnum != 3 || ds.msg_lspid != getpid()) {
+        THROW_ERROR("IPC_STAT returns wrong info");
+    }
+
+    // Get the message of type 2
+    len = msgrcv(msqid, &msg, MSG_TEXT_SZ, 2, 0);
+    if (len != strlen("two") + 1 || msg.mtype != 2 || strcmp(msg.mtext, "two") != 0) {
+        THROW_ERROR("msgrcv() gets a wrong message of given type");
+    }
+    // Get the message with the lowest type that is not greater than 3
+    len = msgrcv(msqid, &msg, MSG_TEXT_SZ, -3, 0);
+    if (len < 0 || msg.mtype != 1) {
+        THROW_ERROR("msgrcv() gets a wrong message of lowest type");
+    }
+    // Get the first message whose type is not 1
+    len = msgrcv(msqid, &msg, MSG_TEXT_SZ, 1, MSG_EXCEPT);
+    if (len < 0 || msg.mtype != 3) {
+        THROW_ERROR("msgrcv() with MSG_EXCEPT gets a wrong message");
+    }
+    // The queue is empty now
+    len = msgrcv(msqid, &msg, MSG_TEXT_SZ, 0, IPC_NOWAIT);
+    if (len != -1 || errno != ENOMSG) {
+        THROW_ERROR("msgrcv() should return ENOMSG");
+    }
+
+    if (msgctl(msqid, IPC_RMID, NULL) < 0) {
+        THROW_ERROR("Cannot remove the message queue");
+    }
+    return SUCCESS;
+}
+
+static int test_msg_too_long(void) {
+    int msqid;
+    ssize_t len;
+    struct msg_buf msg;
+
+    msqid = msgget(IPC_PRIVATE, IPC_CREAT | S_IRWUSER);
+    if (msqid < 0) {
+        THROW_ERROR("msgget() cannot create the message queue");
+    }
+    if (send_msg(msqid, 1, msg_text, 0) < 0) {
+        THROW_ERROR("msgsnd() failed");
+    }
+    len = msgrcv(msqid, &msg, 4, 0, IPC_NOWAIT);
+    if (len != -1 || errno != E2BIG) {
+        THROW_ERROR("msgrcv() should return E2BIG");
+    }
+    len = msgrcv(msqid, &msg, 4, 0, IPC_NOWAIT | MSG_NOERROR);
+    if (len != 4 || strncmp(msg.mtext, msg_text, 4) != 0) {
+        THROW_ERROR("msgrcv() with MSG_NOERROR should truncate the message");
+    }
+
+    if (msgctl(msqid, IPC_RMID, NULL) < 0) {
+        THROW_ERROR("Cannot remove the message queue");
+    }
+    return SUCCESS;
+}
+
+static void *thread_remove_queue(void *arg) {
+    int msqid = *(int *)arg;
+
+    usleep(100 * 1000);
+    msgctl(msqid, IPC_RMID, NULL);
+    return NULL;
+}
+
+static int test_msgrcv_removed_queue(void) {
+    int msqid;
+    ssize_t len;
+    pthread_t tid;
+    struct msg_buf msg;
+
+    msqid = msgget(IPC_PRIVATE, IPC_CREAT | S_IRWUSER);
+    if (msqid < 0) {
+        THROW_ERROR("msgget() cannot create the message queue");
+    }
+    if (pthread_create(&tid, NULL, thread_remove_queue, &msqid) != 0) {
+        THROW_ERROR("pthread_create() failed");
+    }
+    len = msgrcv(msqid, &msg, MSG_TEXT_SZ, 0, 0);
+    pthread_join(tid, NULL);
+    if (len != -1 || errno != EIDRM) {
+        THROW_ERROR("msgrcv() should return EIDRM after the queue is removed");
+    }
+    return SUCCESS;
+}
+
+static int test_process_communication(void) {
+    int msqid, ret;
+    ssize_t len;
+    struct msg_buf msg;
+    char *child_argv[TEST_PROCESS_COMMU_ARGC + 1];
+
+    msqid = msgget(IPC_PRIVATE, IPC_CREAT | S_IRWUSER);
+    if (msqid < 0) {
+        THROW_ERROR("msgget() cannot create the message queue");
+    }
+
+    for (int i = 0; i < TEST_PROCESS_COMMU_ARGC; i++) {
+        child_argv[i] = (char *)malloc(ARG_BUF_SZ);
+    }
+    snprintf(child_argv[0], ARG_BUF_SZ, "%s", prog_name);
+    snprintf(child_argv[1], ARG_BUF_SZ, "%d", TEST_PROCESS_COMMU);
+    snprintf(child_argv[2], ARG_BUF_SZ, "%d", msqid);
+    child_argv[TEST_PROCESS_COMMU_ARGC] = NULL;
+    ret = execute_in_child(child_argv);
+    for (int i = 0; i < TEST_PROCESS_COMMU_ARGC; i++) {
+        free(child_argv[i]);
+    }
+    if (ret != SUCCESS) {
+        return FAIL;
+    }
+
+    len = msgrcv(msqid, &msg, MSG_TEXT_SZ, 0, IPC_NOWAIT);
+    if (len < 0 || strcmp(msg.mtext, msg_text) != 0) {
+        THROW_ERROR("the message from child mismatches");
+    }
+    if (msgctl(msqid, IPC_RMID, NULL) < 0) {
+        THROW_ERROR("Cannot remove the message queue");
+    }
+    return SUCCESS;
+}
+
+// ============================================================================
+// Funtion invoked in child process for inter-process communication
+// ============================================================================
+
+static int child_test_process_communication(int argc, const char *argv[]) {
+    int msqid;
+
+    if (argc != TEST_PROCESS_COMMU_ARGC) {
+        INFO("Invalid argument, argc: %d\n", argc);
+        return FAIL;
+    }
+    msqid = atoi(argv[2]);
+    if (send_msg(msqid, 1, msg_text, 0) < 0) {
+        THROW_ERROR("msgsnd() failed in child");
+    }
+    return SUCCESS;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_msgget_msqid_from_key),
+    TEST_CASE(test_msg_type_selection),
+    TEST_CASE(test_msg_too_long),
+    TEST_CASE(test_msgrcv_removed_queue),
+    TEST_CASE(test_process_communication),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc == 1) {
+        // Parent process will arrive here
+        return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+    } else {
+        // Child process will arrive here
+        int option = atoi(argv[1]), ret;
+        switch (option) {
+            case TEST_PROCESS_COMMU:
+                ret = child_test_process_communication(argc, argv);
+                break;
+            default:
+                INFO("Invalid option: %d\n", option);
+                ret = FAIL;
+        }
+        if (ret == SUCCESS) {
+            return 0;
+        } else {
+            return -1;
+        }
+    }
+}
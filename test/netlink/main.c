@@ -0,0 +1,304 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <linux/netlink.h>
+#include <linux/rtnetlink.h>
+#include <arpa/inet.h>
+#include <net/if.h>
+#include <netinet/in.h>
+#include <errno.h>
+#include <ifaddrs.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define RECV_BUF_SIZE   8192
+
+struct link_request {
+    struct nlmsghdr hdr;
+    struct ifinfomsg info;
+    char attrs[64];
+};
+
+static int open_route_socket(__u32 *pid) {
+    struct sockaddr_nl addr = { .nl_family = AF_NETLINK };
+    socklen_t addr_len = sizeof(addr);
+
+    int fd = socket(AF_NETLINK, SOCK_RAW | SOCK_CLOEXEC, NETLINK_ROUTE);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a netlink socket");
+    }
+    // The port ID is assigned by the kernel when it is 0
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        close(fd);
+        THROW_ERROR("failed to bind the netlink socket");
+    }
+    if (getsockname(fd, (struct sockaddr *)&addr, &addr_len) < 0 ||
+            addr_len != sizeof(addr) || addr.nl_family != AF_NETLINK || addr.nl_pid == 0) {
+        close(fd);
+        THROW_ERROR("no port ID is assigned");
+    }
+    *pid = addr.nl_pid;
+    return fd;
+}
+
+static int send_request(int fd, struct nlmsghdr *hdr) {
+    struct sockaddr_nl kernel = { .nl_family = AF_NETLINK };
+
+    if (sendto(fd, hdr, hdr->nlmsg_len, 0, (struct sockaddr *)&kernel,
+               sizeof(kernel)) != hdr->nlmsg_len) {
+        THROW_ERROR("failed to send the request");
+    }
+    return 0;
+}
+
+// Receive the replies of a request, and return the number of the RTM_NEWLINK messages of the
+// loopback interface
+static int recv_links(int fd, __u32 pid, __u32 seq, int is_dump) {
+    char buf[RECV_BUF_SIZE];
+    struct sockaddr_nl src_addr;
+    socklen_t addr_len;
+    int num_loopback = 0;
+
+    while (1) {
+        addr_len = sizeof(src_addr);
+        ssize_t len = recvfrom(fd, buf, sizeof(buf), 0, (struct sockaddr *)&src_addr,
+                               &addr_len);
+        if (len <= 0) {
+            THROW_ERROR("failed to receive the replies");
+        }
+        // All the messages are from the kernel
+        if (addr_len != sizeof(src_addr) || src_addr.nl_family != AF_NETLINK ||
+                src_addr.nl_pid != 0) {
+            THROW_ERROR("the replies are not from the kernel");
+        }
+
+        for (struct nlmsghdr *hdr = (struct nlmsghdr *)buf; NLMSG_OK(hdr, len);
+                hdr = NLMSG_NEXT(hdr, len)) {
+            if (hdr->nlmsg_seq != seq || hdr->nlmsg_pid != pid) {
+                THROW_ERROR("the sequence number or port ID mismatches");
+            }
+            if (hdr->nlmsg_type == NLMSG_DONE) {
+                return num_loopback;
+            }
+            if (hdr->nlmsg_type == NLMSG_ERROR) {
+                THROW_ERROR("the request fails");
+            }
+            if (hdr->nlmsg_type != RTM_NEWLINK) {
+                THROW_ERROR("unexpected message type %d", hdr->nlmsg_type);
+            }
+
+            struct ifinfomsg *info = NLMSG_DATA(hdr);
+            if (info->ifi_index <= 0) {
+                THROW_ERROR("the interface index is invalid");
+            }
+            if (info->ifi_flags & IFF_LOOPBACK) {
+                num_loopback++;
+            }
+            if (!is_dump) {
+                return num_loopback;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_getifaddrs(void) {
+    struct ifaddrs *ifaddrs;
+    int found_loopback = 0;
+
+    if (getifaddrs(&ifaddrs) < 0) {
+        THROW_ERROR("getifaddrs failed");
+    }
+    for (struct ifaddrs *ifa = ifaddrs; ifa != NULL; ifa = ifa->ifa_next) {
+        if (ifa->ifa_name == NULL || strlen(ifa->ifa_name) >= IFNAMSIZ) {
+            freeifaddrs(ifaddrs);
+            THROW_ERROR("the interface name is invalid");
+        }
+        if (ifa->ifa_addr == NULL || ifa->ifa_addr->sa_family != AF_INET) {
+            continue;
+        }
+        struct sockaddr_in *addr = (struct sockaddr_in *)ifa->ifa_addr;
+        if (addr->sin_addr.s_addr == htonl(INADDR_LOOPBACK)) {
+            if (!(ifa->ifa_flags & IFF_LOOPBACK) || !(ifa->ifa_flags & IFF_UP)) {
+                freeifaddrs(ifaddrs);
+                THROW_ERROR("the flags of the loopback interface are wrong");
+            }
+            found_loopback = 1;
+        }
+    }
+    freeifaddrs(ifaddrs);
+
+    if (!found_loopback) {
+        THROW_ERROR("127.0.0.1 is not found");
+    }
+    return 0;
+}
+
+static int test_dump_links(void) {
+    struct link_request req;
+    __u32 pid;
+    int fd, ret;
+
+    fd = open_route_socket(&pid);
+    if (fd < 0) {
+        return -1;
+    }
+
+    memset(&req, 0, sizeof(req));
+    req.hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifinfomsg));
+    req.hdr.nlmsg_type = RTM_GETLINK;
+    req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_DUMP;
+    req.hdr.nlmsg_seq = 1;
+    req.info.ifi_family = AF_UNSPEC;
+    if (send_request(fd, &req.hdr) < 0) {
+        close(fd);
+        return -1;
+    }
+
+    ret = recv_links(fd, pid, 1, 1);
+    close(fd);
+    if (ret < 0) {
+        return -1;
+    }
+    if (ret != 1) {
+        THROW_ERROR("the loopback interface is not dumped exactly once");
+    }
+    return 0;
+}
+
+static int test_get_link_by_name(void) {
+    struct link_request req;
+    struct rtattr *attr;
+    __u32 pid;
+    int fd, ret;
+
+    fd = open_route_socket(&pid);
+    if (fd < 0) {
+        return -1;
+    }
+
+    memset(&req, 0, sizeof(req));
+    req.hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifinfomsg));
+    req.hdr.nlmsg_type = RTM_GETLINK;
+    req.hdr.nlmsg_flags = NLM_F_REQUEST;
+    req.hdr.nlmsg_seq = 2;
+    req.info.ifi_family = AF_UNSPEC;
+    attr = (struct rtattr *)((char *)&req + NLMSG_ALIGN(req.hdr.nlmsg_len));
+    attr->rta_type = IFLA_IFNAME;
+    attr->rta_len = RTA_LENGTH(strlen("lo") + 1);
+    strcpy(RTA_DATA(attr), "lo");
+    req.hdr.nlmsg_len = NLMSG_ALIGN(req.hdr.nlmsg_len) + RTA_ALIGN(attr->rta_len);
+    if (send_request(fd, &req.hdr) < 0) {
+        close(fd);
+        return -1;
+    }
+
+    ret = recv_links(fd, pid, 2, 0);
+    close(fd);
+    if (ret < 0) {
+        return -1;
+    }
+    if (ret != 1) {
+        THROW_ERROR("the loopback interface is not found by name");
+    }
+    return 0;
+}
+
+static int test_get_unknown_link(void) {
+    struct link_request req;
+    char buf[RECV_BUF_SIZE];
+    __u32 pid;
+    int fd;
+
+    fd = open_route_socket(&pid);
+    if (fd < 0) {
+        return -1;
+    }
+
+    memset(&req, 0, sizeof(req));
+    req.hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifinfomsg));
+    req.hdr.nlmsg_type = RTM_GETLINK;
+    req.hdr.nlmsg_flags = NLM_F_REQUEST;
+    req.hdr.nlmsg_seq = 3;
+    req.info.ifi_index = 0x7fffffff;
+    if (send_request(fd, &req.hdr) < 0) {
+        close(fd);
+        return -1;
+    }
+
+    ssize_t len = recv(fd, buf, sizeof(buf), 0);
+    close(fd);
+    struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+    if (len < 0 || !NLMSG_OK(hdr, len) || hdr->nlmsg_type != NLMSG_ERROR) {
+        THROW_ERROR("an error is expected");
+    }
+    struct nlmsgerr *err = NLMSG_DATA(hdr);
+    if (err->error != -ENODEV || err->msg.nlmsg_seq != 3) {
+        THROW_ERROR("ENODEV is expected");
+    }
+    return 0;
+}
+
+// The replies that do not fit into the receive queue are dropped, which is reported to the
+// receiver instead of the sender
+static int test_recv_queue_overrun(void) {
+    struct link_request req;
+    char buf[RECV_BUF_SIZE];
+    __u32 pid;
+    int fd, ret = -1;
+
+    fd = open_route_socket(&pid);
+    if (fd < 0) {
+        return -1;
+    }
+
+    memset(&req, 0, sizeof(req));
+    req.hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifinfomsg));
+    req.hdr.nlmsg_type = RTM_GETLINK;
+    req.hdr.nlmsg_flags = NLM_F_REQUEST;
+    req.info.ifi_index = 0x7fffffff;
+    // Each error reply is small, so send enough requests to fill the queue
+    for (int i = 0; i < 16384; i++) {
+        req.hdr.nlmsg_seq = i;
+        if (send_request(fd, &req.hdr) < 0) {
+            goto out;
+        }
+    }
+
+    if (recv(fd, buf, sizeof(buf), MSG_DONTWAIT) != -1 || errno != ENOBUFS) {
+        printf("the receiver should be told by ENOBUFS\n");
+        goto out;
+    }
+    // The replies in the queue can still be received
+    if (recv(fd, buf, sizeof(buf), MSG_DONTWAIT) <= 0) {
+        printf("failed to receive the queued replies\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_getifaddrs),
+    TEST_CASE(test_dump_links),
+    TEST_CASE(test_get_link_by_name),
+    TEST_CASE(test_get_unknown_link),
+    TEST_CASE(test_recv_queue_overrun),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -0,0 +1,359 @@
+#define _GNU_SOURCE
+#include <sys/syscall.h>
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define MS  (1000 * 1000L) // 1ms = 1,000,000ns
+
+static int block_signal(int signum, sigset_t *mask) {
+    sigemptyset(mask);
+    sigaddset(mask, signum);
+    return sigprocmask(SIG_BLOCK, mask, NULL);
+}
+
+static void unblock_signal(sigset_t *mask) {
+    sigprocmask(SIG_UNBLOCK, mask, NULL);
+}
+
+// Not all C libraries name the field of the thread ID, which follows sigev_notify
+static void set_sigev_thread_id(struct sigevent *sev, pid_t tid) {
+    *(pid_t *)((char *)&sev->sigev_notify + sizeof(sev->sigev_notify)) = tid;
+}
+
+static int wait_signal(sigset_t *mask, siginfo_t *info, long timeout_ms) {
+    struct timespec timeout = {
+        .tv_sec = timeout_ms / 1000,
+        .tv_nsec = (timeout_ms % 1000) * MS,
+    };
+    return sigtimedwait(mask, info, &timeout);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_create_and_delete(void) {
+    timer_t timer;
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGUSR1,
+    };
+
+    if (timer_create(CLOCK_REALTIME, &sev, &timer) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+    if (timer_delete(timer) < 0) {
+        THROW_ERROR("timer_delete failed");
+    }
+    if (timer_delete(timer) != -1 || errno != EINVAL) {
+        THROW_ERROR("deleting a deleted timer should fail with EINVAL");
+    }
+
+    sev.sigev_signo = 0;
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != -1 || errno != EINVAL) {
+        THROW_ERROR("timer_create with an invalid signal should fail with EINVAL");
+    }
+    if (timer_create(CLOCK_THREAD_CPUTIME_ID + 100, NULL, &timer) != -1 || errno != EINVAL) {
+        THROW_ERROR("timer_create with an invalid clock should fail with EINVAL");
+    }
+
+    // The C library never passes SIGEV_THREAD to the kernel
+    int kernel_timer;
+    sev.sigev_notify = SIGEV_THREAD;
+    sev.sigev_signo = SIGUSR1;
+    if (syscall(SYS_timer_create, CLOCK_MONOTONIC, &sev, &kernel_timer) != -1 ||
+            errno != EINVAL) {
+        THROW_ERROR("timer_create with SIGEV_THREAD should fail with EINVAL");
+    }
+    return 0;
+}
+
+static int test_settime_and_gettime(void) {
+    timer_t timer;
+    struct sigevent sev = { .sigev_notify = SIGEV_NONE };
+    struct itimerspec new_value = {
+        .it_value = { .tv_sec = 10, .tv_nsec = 0 },
+        .it_interval = { .tv_sec = 1, .tv_nsec = 0 },
+    };
+    struct itimerspec old_value, curr_value;
+
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) < 0) {
+        THROW_ERROR("timer_create failed");
+    }
+    if (timer_settime(timer, 0, &new_value, &old_value) < 0 ||
+            old_value.it_value.tv_sec != 0 || old_value.it_value.tv_nsec != 0) {
+        timer_delete(timer);
+        THROW_ERROR("timer_settime failed or the timer was armed");
+    }
+    if (timer_gettime(timer, &curr_value) < 0 || curr_value.it_value.tv_sec > 10 ||
+            curr_value.it_value.tv_sec < 9 || curr_value.it_interval.tv_sec != 1) {
+        timer_delete(timer);
+        THROW_ERROR("unexpected value of the armed timer");
+    }
+
+    // Disarm the timer
+    memset(&new_value, 0, sizeof(new_value));
+    if (timer_settime(timer, 0, &new_value, &old_value) < 0 ||
+            old_value.it_value.tv_sec < 9) {
+        timer_delete(timer);
+        THROW_ERROR("failed to disarm the timer");
+    }
+    if (timer_gettime(timer, &curr_value) < 0 || curr_value.it_value.tv_sec != 0 ||
+            curr_value.it_value.tv_nsec != 0) {
+        timer_delete(timer);
+        THROW_ERROR("the timer is not disarmed");
+    }
+
+    new_value.it_value.tv_nsec = 1000 * MS;
+    if (timer_settime(timer, 0, &new_value, NULL) != -1 || errno != EINVAL) {
+        timer_delete(timer);
+        THROW_ERROR("timer_settime with an invalid value should fail with EINVAL");
+    }
+    timer_delete(timer);
+    return 0;
+}
+
+static int test_signal_notification(void) {
+    timer_t timer;
+    sigset_t mask;
+    siginfo_t info;
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGUSR1,
+        .sigev_value.sival_int = 1234,
+    };
+    struct itimerspec new_value = {
+        .it_value = { .tv_sec = 0, .tv_nsec = 50 * MS },
+    };
+
+    if (block_signal(SIGUSR1, &mask) < 0) {
+        THROW_ERROR("failed to block the signal");
+    }
+    if (timer_create(CLOCK_REALTIME, &sev, &timer) < 0) {
+        unblock_signal(&mask);
+        THROW_ERROR("timer_create failed");
+    }
+    if (timer_settime(timer, 0, &new_value, NULL) < 0) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("timer_settime failed");
+    }
+    if (wait_signal(&mask, &info, 1000) != SIGUSR1 || info.si_code != SI_TIMER ||
+            info.si_value.sival_int != 1234) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("the timer signal is not received");
+    }
+
+    // A one-shot timer is disarmed after the expiration
+    if (wait_signal(&mask, &info, 200) != -1 || errno != EAGAIN) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("a one-shot timer should expire only once");
+    }
+    timer_delete(timer);
+    unblock_signal(&mask);
+    return 0;
+}
+
+static int test_cpu_time_clock(void) {
+    timer_t timer;
+    sigset_t mask, pending;
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGUSR1,
+    };
+    struct itimerspec new_value = {
+        .it_value = { .tv_sec = 0, .tv_nsec = 100 * MS },
+    };
+    struct timespec sleep_time = { .tv_sec = 0, .tv_nsec = 300 * MS };
+    struct timespec start, now;
+
+    if (block_signal(SIGUSR1, &mask) < 0) {
+        THROW_ERROR("failed to block the signal");
+    }
+    if (timer_create(CLOCK_PROCESS_CPUTIME_ID, &sev, &timer) < 0 ||
+            timer_settime(timer, 0, &new_value, NULL) < 0) {
+        unblock_signal(&mask);
+        THROW_ERROR("failed to create and arm the timer");
+    }
+
+    // Sleeping consumes no CPU time of the process
+    nanosleep(&sleep_time, NULL);
+    sigpending(&pending);
+    if (sigismember(&pending, SIGUSR1)) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("the CPU-time timer expired while sleeping");
+    }
+
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    do {
+        sigpending(&pending);
+        clock_gettime(CLOCK_MONOTONIC, &now);
+    } while (!sigismember(&pending, SIGUSR1) && now.tv_sec - start.tv_sec < 5);
+    if (!sigismember(&pending, SIGUSR1)) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("the CPU-time timer did not expire while running");
+    }
+
+    siginfo_t info;
+    wait_signal(&mask, &info, 0);
+    timer_delete(timer);
+    unblock_signal(&mask);
+    return 0;
+}
+
+static int test_abstime(void) {
+    timer_t timer;
+    sigset_t mask;
+    siginfo_t info;
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGUSR2,
+    };
+    struct itimerspec new_value = { 0 };
+
+    if (block_signal(SIGUSR2, &mask) < 0) {
+        THROW_ERROR("failed to block the signal");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) < 0) {
+        unblock_signal(&mask);
+        THROW_ERROR("timer_create failed");
+    }
+    clock_gettime(CLOCK_MONOTONIC, &new_value.it_value);
+    new_value.it_value.tv_nsec += 50 * MS;
+    if (new_value.it_value.tv_nsec >= 1000 * MS) {
+        new_value.it_value.tv_sec += 1;
+        new_value.it_value.tv_nsec -= 1000 * MS;
+    }
+    if (timer_settime(timer, TIMER_ABSTIME, &new_value, NULL) < 0) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("timer_settime failed");
+    }
+    if (wait_signal(&mask, &info, 1000) != SIGUSR2 || info.si_code != SI_TIMER) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("the timer signal is not received");
+    }
+    timer_delete(timer);
+    unblock_signal(&mask);
+    return 0;
+}
+
+static int test_overrun(void) {
+    timer_t timer;
+    sigset_t mask;
+    siginfo_t info;
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_SIGNAL,
+        .sigev_signo = SIGRTMIN,
+    };
+    struct itimerspec new_value = {
+        .it_value = { .tv_sec = 0, .tv_nsec = 10 * MS },
+        .it_interval = { .tv_sec = 0, .tv_nsec = 10 * MS },
+    };
+
+    if (block_signal(SIGRTMIN, &mask) < 0) {
+        THROW_ERROR("failed to block the signal");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) < 0) {
+        unblock_signal(&mask);
+        THROW_ERROR("timer_create failed");
+    }
+    if (timer_settime(timer, 0, &new_value, NULL) < 0) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("timer_settime failed");
+    }
+
+    // The signal is blocked, so the expirations in the meantime are overruns
+    usleep(300 * 1000);
+    if (wait_signal(&mask, &info, 1000) != SIGRTMIN || info.si_code != SI_TIMER) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("the timer signal is not received");
+    }
+    int overrun = timer_getoverrun(timer);
+    if (overrun <= 0 || overrun != info.si_overrun) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("unexpected overrun count");
+    }
+
+    // Consume the signal of the expiration after the last delivery, if any
+    timer_delete(timer);
+    while (wait_signal(&mask, &info, 0) == SIGRTMIN);
+    unblock_signal(&mask);
+    return 0;
+}
+
+static int test_thread_id_notification(void) {
+    timer_t timer;
+    sigset_t mask;
+    siginfo_t info;
+    struct sigevent sev = {
+        .sigev_notify = SIGEV_THREAD_ID,
+        .sigev_signo = SIGUSR1,
+    };
+    struct itimerspec new_value = {
+        .it_value = { .tv_sec = 0, .tv_nsec = 50 * MS },
+    };
+
+    set_sigev_thread_id(&sev, syscall(SYS_gettid));
+    if (block_signal(SIGUSR1, &mask) < 0) {
+        THROW_ERROR("failed to block the signal");
+    }
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) < 0) {
+        unblock_signal(&mask);
+        THROW_ERROR("timer_create failed");
+    }
+    if (timer_settime(timer, 0, &new_value, NULL) < 0) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("timer_settime failed");
+    }
+    if (wait_signal(&mask, &info, 1000) != SIGUSR1 || info.si_code != SI_TIMER) {
+        timer_delete(timer);
+        unblock_signal(&mask);
+        THROW_ERROR("the timer signal is not received by the thread");
+    }
+    timer_delete(timer);
+
+    set_sigev_thread_id(&sev, -1);
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != -1 || errno != EINVAL) {
+        unblock_signal(&mask);
+        THROW_ERROR("timer_create with an invalid thread should fail with EINVAL");
+    }
+    unblock_signal(&mask);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_create_and_delete),
+    TEST_CASE(test_settime_and_gettime),
+    TEST_CASE(test_signal_notification),
+    TEST_CASE(test_cpu_time_clock),
+    TEST_CASE(test_abstime),
+    TEST_CASE(test_overrun),
+    TEST_CASE(test_thread_id_notification),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
@@ -1,5 +1,6 @@
 #define _GNU_SOURCE
 #include <sys/types.h>
+#include <sys/mman.h>
 #include <sys/vfs.h>
 #include <fcntl.h>
 #include <limits.h>
@@ -48,6 +49,26 @@ static int test_read_from_procfs(const char *proc_inode) {
     return 0;
 }
 
+// Get the value of the field in /proc/self/status, or -1 on failure
+static long get_proc_self_status_field(const char *field) {
+    char line[256] = { 0 };
+    long value = -1;
+    size_t field_len = strlen(field);
+
+    FILE *fp = fopen("/proc/self/status", "r");
+    if (fp == NULL) {
+        return -1;
+    }
+    while (fgets(line, sizeof(line), fp) != NULL) {
+        if (strncmp(line, field, field_len) == 0 && line[field_len] == ':') {
+            value = strtol(line + field_len + 1, NULL, 10);
+            break;
+        }
+    }
+    fclose(fp);
+    return value;
+}
+
 // ============================================================================
 // Test cases for procfs
 // ============================================================================
@@ -188,6 +209,68 @@ static int test_read_from_proc_self_stat() {
     return 0;
 }
 
+static int test_read_from_proc_self_status() {
+    const size_t len = 1024 * 1024;
+
+    if (get_proc_self_status_field("Pid") != getpid()) {
+        THROW_ERROR("failed to check the pid in /proc/self/status");
+    }
+    long rss = get_proc_self_status_field("VmRSS");
+    if (rss <= 0 || get_proc_self_status_field("MultiVMAChunksFree") < 0) {
+        THROW_ERROR("failed to get the memory usage in /proc/self/status");
+    }
+
+    // The memory is committed when it is mapped
+    void *addr = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        THROW_ERROR("mmap failed");
+    }
+    long new_rss = get_proc_self_status_field("VmRSS");
+    munmap(addr, len);
+    if (new_rss - rss < (long)(len / 1024)) {
+        THROW_ERROR("the mapped memory is not counted in VmRSS");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_statm() {
+    const char *proc_self_statm = "/proc/self/statm";
+    FILE *fp = fopen(proc_self_statm, "r");
+    if (fp == NULL) {
+        THROW_ERROR("failed to fopen: %s", proc_self_statm);
+    }
+
+    unsigned long size, resident, shared, text, lib, data, dt;
+    int ret = fscanf(fp, "%lu %lu %lu %lu %lu %lu %lu", &size, &resident, &shared, &text,
+                     &lib, &data, &dt);
+    fclose(fp);
+    if (ret != 7) {
+        THROW_ERROR("failed to parse the 7 items");
+    }
+    if (size == 0 || resident > size || text > size || data > size) {
+        THROW_ERROR("failed to check the result in %s", proc_self_statm);
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_smaps() {
+    const char *proc_smaps = "/proc/self/smaps";
+
+    if (test_read_from_procfs(proc_smaps) < 0) {
+        THROW_ERROR("failed to read the smaps");
+    }
+    return 0;
+}
+
+static int test_read_from_proc_self_smaps_rollup() {
+    const char *proc_smaps_rollup = "/proc/self/smaps_rollup";
+
+    if (test_read_from_procfs(proc_smaps_rollup) < 0) {
+        THROW_ERROR("failed to read the smaps_rollup");
+    }
+    return 0;
+}
+
 static int test_read_from_proc_meminfo() {
     const char *proc_meminfo = "/proc/meminfo";
 
@@ -302,6 +385,10 @@ static test_case_t test_cases[] = {
     TEST_CASE(test_readdir_self),
     TEST_CASE(test_readdir_self_fd),
     TEST_CASE(test_read_from_proc_self_maps),
+    TEST_CASE(test_read_from_proc_self_status),
+    TEST_CASE(test_read_from_proc_self_statm),
+    TEST_CASE(test_read_from_proc_self_smaps),
+    TEST_CASE(test_read_from_proc_self_smaps_rollup),
 };
 
 int main(int argc, const char *argv[]) {
@@ -0,0 +1,209 @@
+#define _GNU_SOURCE
+#include <sys/select.h>
+#include <errno.h>
+#include <poll.h>
+#include <signal.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static volatile sig_atomic_t sigusr1_count = 0;
+
+static void handle_sigusr1(int num) {
+    sigusr1_count++;
+}
+
+// Install the handler of SIGUSR1 and block it
+static int setup_sigusr1(sigset_t *old_mask) {
+    struct sigaction sa;
+    sigset_t mask;
+
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = handle_sigusr1;
+    if (sigaction(SIGUSR1, &sa, NULL) < 0) {
+        THROW_ERROR("failed to install the signal handler");
+    }
+    sigemptyset(&mask);
+    sigaddset(&mask, SIGUSR1);
+    if (sigprocmask(SIG_BLOCK, &mask, old_mask) < 0) {
+        THROW_ERROR("failed to block SIGUSR1");
+    }
+    sigusr1_count = 0;
+    return 0;
+}
+
+static int is_sigusr1_blocked(void) {
+    sigset_t mask;
+    sigprocmask(SIG_BLOCK, NULL, &mask);
+    return sigismember(&mask, SIGUSR1);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_pselect_pipe(void) {
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 10 * 1000 * 1000 };
+    fd_set readfds, writefds;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+
+    FD_ZERO(&readfds);
+    FD_SET(pipe_fds[0], &readfds);
+    if (pselect(pipe_fds[0] + 1, &readfds, NULL, NULL, &timeout, NULL) != 0) {
+        THROW_ERROR("an empty pipe should not be readable");
+    }
+
+    if (write(pipe_fds[1], "x", 1) != 1) {
+        THROW_ERROR("failed to write the pipe");
+    }
+    FD_ZERO(&readfds);
+    FD_SET(pipe_fds[0], &readfds);
+    FD_ZERO(&writefds);
+    FD_SET(pipe_fds[1], &writefds);
+    int nfds = pipe_fds[0] > pipe_fds[1] ? pipe_fds[0] + 1 : pipe_fds[1] + 1;
+    if (pselect(nfds, &readfds, &writefds, NULL, NULL, NULL) != 2 ||
+            !FD_ISSET(pipe_fds[0], &readfds) || !FD_ISSET(pipe_fds[1], &writefds)) {
+        THROW_ERROR("the pipe should be readable and writable");
+    }
+
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return 0;
+}
+
+static int test_pselect_pending_signal(void) {
+    struct timespec timeout = { .tv_sec = 5, .tv_nsec = 0 };
+    sigset_t old_mask;
+    fd_set readfds;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0 || setup_sigusr1(&old_mask) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    // The signal stays pending until pselect unblocks it atomically
+    kill(getpid(), SIGUSR1);
+    if (sigusr1_count != 0) {
+        THROW_ERROR("the blocked signal is delivered");
+    }
+
+    FD_ZERO(&readfds);
+    FD_SET(pipe_fds[0], &readfds);
+    if (pselect(pipe_fds[0] + 1, &readfds, NULL, NULL, &timeout, &old_mask) != -1 ||
+            errno != EINTR) {
+        THROW_ERROR("pselect should be interrupted by the pending signal");
+    }
+    if (sigusr1_count != 1) {
+        THROW_ERROR("the signal handler is not called");
+    }
+    if (!is_sigusr1_blocked()) {
+        THROW_ERROR("the signal mask is not restored");
+    }
+
+    sigprocmask(SIG_SETMASK, &old_mask, NULL);
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return 0;
+}
+
+static int test_pselect_blocked_signal(void) {
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 10 * 1000 * 1000 };
+    sigset_t old_mask, mask;
+    fd_set readfds;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0 || setup_sigusr1(&old_mask) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    kill(getpid(), SIGUSR1);
+
+    // The signal is still blocked by the temporary mask
+    sigemptyset(&mask);
+    sigaddset(&mask, SIGUSR1);
+    FD_ZERO(&readfds);
+    FD_SET(pipe_fds[0], &readfds);
+    if (pselect(pipe_fds[0] + 1, &readfds, NULL, NULL, &timeout, &mask) != 0) {
+        THROW_ERROR("pselect should time out");
+    }
+    if (sigusr1_count != 0) {
+        THROW_ERROR("the blocked signal is delivered");
+    }
+
+    // The pending signal is delivered once it is unblocked
+    sigprocmask(SIG_SETMASK, &old_mask, NULL);
+    if (sigusr1_count != 1) {
+        THROW_ERROR("the signal handler is not called");
+    }
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return 0;
+}
+
+static int test_ppoll_pending_signal(void) {
+    struct timespec timeout = { .tv_sec = 5, .tv_nsec = 0 };
+    sigset_t old_mask;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0 || setup_sigusr1(&old_mask) < 0) {
+        THROW_ERROR("failed to set up");
+    }
+    kill(getpid(), SIGUSR1);
+
+    struct pollfd poll_fd = { .fd = pipe_fds[0], .events = POLLIN };
+    if (ppoll(&poll_fd, 1, &timeout, &old_mask) != -1 || errno != EINTR) {
+        THROW_ERROR("ppoll should be interrupted by the pending signal");
+    }
+    if (sigusr1_count != 1) {
+        THROW_ERROR("the signal handler is not called");
+    }
+    if (!is_sigusr1_blocked()) {
+        THROW_ERROR("the signal mask is not restored");
+    }
+
+    sigprocmask(SIG_SETMASK, &old_mask, NULL);
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+    return 0;
+}
+
+static int test_select_invalid_fd(void) {
+    struct timeval timeout = { .tv_sec = 0, .tv_usec = 0 };
+    fd_set readfds;
+    int pipe_fds[2];
+
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    close(pipe_fds[0]);
+    close(pipe_fds[1]);
+
+    FD_ZERO(&readfds);
+    FD_SET(pipe_fds[0], &readfds);
+    if (select(pipe_fds[0] + 1, &readfds, NULL, NULL, &timeout) != -1 || errno != EBADF) {
+        THROW_ERROR("select should fail with EBADF on a closed fd");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_pselect_pipe),
+    TEST_CASE(test_pselect_pending_signal),
+    TEST_CASE(test_pselect_blocked_signal),
+    TEST_CASE(test_ppoll_pending_signal),
+    TEST_CASE(test_select_invalid_fd),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
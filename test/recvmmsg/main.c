@@ -0,0 +1,221 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <arpa/inet.h>
+#include <netinet/in.h>
+#include <errno.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define MAX_VLEN        4
+#define BUF_SIZE        64
+
+static const char *msgs[] = {"first message", "second", "the third message"};
+
+struct recv_bufs {
+    struct mmsghdr mmsgs[MAX_VLEN];
+    struct iovec iovs[MAX_VLEN];
+    char data[MAX_VLEN][BUF_SIZE];
+    struct sockaddr_in addrs[MAX_VLEN];
+};
+
+static void init_recv_bufs(struct recv_bufs *bufs, int with_addr) {
+    memset(bufs, 0, sizeof(*bufs));
+    for (int i = 0; i < MAX_VLEN; i++) {
+        bufs->iovs[i].iov_base = bufs->data[i];
+        bufs->iovs[i].iov_len = BUF_SIZE;
+        bufs->mmsgs[i].msg_hdr.msg_iov = &bufs->iovs[i];
+        bufs->mmsgs[i].msg_hdr.msg_iovlen = 1;
+        if (with_addr) {
+            bufs->mmsgs[i].msg_hdr.msg_name = &bufs->addrs[i];
+            bufs->mmsgs[i].msg_hdr.msg_namelen = sizeof(bufs->addrs[i]);
+        }
+    }
+}
+
+static int send_msgs(int fd, int num) {
+    for (int i = 0; i < num; i++) {
+        if (send(fd, msgs[i], strlen(msgs[i]), 0) != strlen(msgs[i])) {
+            THROW_ERROR("failed to send a message");
+        }
+    }
+    return 0;
+}
+
+// Check the first num messages are received in order
+static int check_msgs(struct recv_bufs *bufs, int num) {
+    for (int i = 0; i < num; i++) {
+        if (bufs->mmsgs[i].msg_len != strlen(msgs[i]) ||
+                strcmp(bufs->data[i], msgs[i]) != 0) {
+            THROW_ERROR("the message %d is wrong", i);
+        }
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_host_udp_socket(void) {
+    struct sockaddr_in addr = { .sin_family = AF_INET };
+    struct sockaddr_in sender_addr;
+    socklen_t addr_len = sizeof(addr);
+    struct recv_bufs bufs;
+    int recv_fd, send_fd, ret;
+
+    // The socket bound to the wildcard address stays a host socket
+    recv_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    send_fd = socket(AF_INET, SOCK_DGRAM, 0);
+    if (recv_fd < 0 || send_fd < 0) {
+        THROW_ERROR("failed to create the sockets");
+    }
+    addr.sin_addr.s_addr = htonl(INADDR_ANY);
+    if (bind(recv_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+            getsockname(recv_fd, (struct sockaddr *)&addr, &addr_len) < 0) {
+        THROW_ERROR("failed to bind the receiver");
+    }
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    if (connect(send_fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        THROW_ERROR("failed to connect the sender");
+    }
+    addr_len = sizeof(sender_addr);
+    if (getsockname(send_fd, (struct sockaddr *)&sender_addr, &addr_len) < 0) {
+        THROW_ERROR("failed to get the address of the sender");
+    }
+    if (send_msgs(send_fd, ARRAY_SIZE(msgs)) < 0) {
+        return -1;
+    }
+
+    init_recv_bufs(&bufs, 1);
+    ret = recvmmsg(recv_fd, bufs.mmsgs, ARRAY_SIZE(msgs), 0, NULL);
+    if (ret != ARRAY_SIZE(msgs)) {
+        THROW_ERROR("recvmmsg returns %d", ret);
+    }
+    if (check_msgs(&bufs, ret) < 0) {
+        return -1;
+    }
+    for (int i = 0; i < ret; i++) {
+        if (bufs.mmsgs[i].msg_hdr.msg_namelen != sizeof(struct sockaddr_in) ||
+                bufs.addrs[i].sin_port != sender_addr.sin_port) {
+            THROW_ERROR("the source address is wrong");
+        }
+    }
+
+    close(recv_fd);
+    close(send_fd);
+    return 0;
+}
+
+static int test_unix_socket(void) {
+    struct recv_bufs bufs;
+    int fds[2], ret;
+
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+    if (send_msgs(fds[0], ARRAY_SIZE(msgs)) < 0) {
+        return -1;
+    }
+
+    init_recv_bufs(&bufs, 0);
+    ret = recvmmsg(fds[1], bufs.mmsgs, ARRAY_SIZE(msgs), 0, NULL);
+    if (ret != ARRAY_SIZE(msgs)) {
+        THROW_ERROR("recvmmsg returns %d", ret);
+    }
+    if (check_msgs(&bufs, ret) < 0) {
+        return -1;
+    }
+
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+static int test_wait_for_one(void) {
+    struct recv_bufs bufs;
+    int fds[2], ret;
+
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+    if (send_msgs(fds[0], 2) < 0) {
+        return -1;
+    }
+
+    // Only the first message is waited for, so the available ones are returned
+    init_recv_bufs(&bufs, 0);
+    ret = recvmmsg(fds[1], bufs.mmsgs, MAX_VLEN, MSG_WAITFORONE, NULL);
+    if (ret != 2) {
+        THROW_ERROR("recvmmsg returns %d", ret);
+    }
+    if (check_msgs(&bufs, ret) < 0) {
+        return -1;
+    }
+
+    // No message is available
+    ret = recvmmsg(fds[1], bufs.mmsgs, MAX_VLEN, MSG_WAITFORONE | MSG_DONTWAIT, NULL);
+    if (ret != -1 || errno != EAGAIN) {
+        THROW_ERROR("EAGAIN is expected");
+    }
+
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+static int test_timeout(void) {
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 0 };
+    struct recv_bufs bufs;
+    int fds[2], ret;
+
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+    if (send_msgs(fds[0], 2) < 0) {
+        return -1;
+    }
+
+    // The timeout is checked after a message is received
+    init_recv_bufs(&bufs, 0);
+    ret = recvmmsg(fds[1], bufs.mmsgs, MAX_VLEN, 0, &timeout);
+    if (ret != 1) {
+        THROW_ERROR("recvmmsg returns %d", ret);
+    }
+    if (check_msgs(&bufs, ret) < 0) {
+        return -1;
+    }
+    if (timeout.tv_sec != 0 || timeout.tv_nsec != 0) {
+        THROW_ERROR("the remaining time is wrong");
+    }
+
+    timeout.tv_nsec = 1000000000;
+    ret = recvmmsg(fds[1], bufs.mmsgs, MAX_VLEN, 0, &timeout);
+    if (ret != -1 || errno != EINVAL) {
+        THROW_ERROR("EINVAL is expected for an invalid timeout");
+    }
+
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_host_udp_socket),
+    TEST_CASE(test_unix_socket),
+    TEST_CASE(test_wait_for_one),
+    TEST_CASE(test_timeout),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
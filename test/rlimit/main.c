@@ -1,15 +1,166 @@
+#define _GNU_SOURCE
+#include <sys/mman.h>
 #include <sys/resource.h>
+#include <sys/syscall.h>
+#include <errno.h>
 #include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
 
-int main(int argc, const char *argv[]) {
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define KB              (1024UL)
+#define MB              (1024 * KB)
+#define PAGE_SIZE       (4 * KB)
+
+// Get the size of the memory mapped by the process from /proc/self/status
+static long get_mapped_size(void) {
+    char line[256] = { 0 };
+    long size_kb = -1;
+
+    FILE *fp = fopen("/proc/self/status", "r");
+    if (fp == NULL) {
+        return -1;
+    }
+    while (fgets(line, sizeof(line), fp) != NULL) {
+        if (strncmp(line, "VmSize:", 7) == 0) {
+            size_kb = strtol(line + 7, NULL, 10);
+            break;
+        }
+    }
+    fclose(fp);
+    return size_kb < 0 ? -1 : size_kb * KB;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+int test_get_and_set_rlimit() {
     struct rlimit rlim;
     if (getrlimit(RLIMIT_AS, &rlim) < 0) {
-        printf("ERROR: getrlimit failed\n");
-        return -1;
+        THROW_ERROR("getrlimit failed");
     }
     if (setrlimit(RLIMIT_AS, &rlim) < 0) {
-        printf("ERROR: getrlimit failed\n");
-        return -1;
+        THROW_ERROR("setrlimit failed");
+    }
+    return 0;
+}
+
+int test_rlimit_as() {
+    struct rlimit old_rlim, rlim;
+    if (getrlimit(RLIMIT_AS, &old_rlim) < 0) {
+        THROW_ERROR("getrlimit failed");
+    }
+    long mapped_size = get_mapped_size();
+    if (mapped_size < 0) {
+        THROW_ERROR("failed to get the mapped size");
+    }
+
+    // Allow mapping 2MB more memory
+    rlim.rlim_cur = mapped_size + 2 * MB;
+    rlim.rlim_max = old_rlim.rlim_max;
+    if (setrlimit(RLIMIT_AS, &rlim) < 0) {
+        THROW_ERROR("setrlimit failed");
+    }
+
+    // The memory leaked on failures doesn't matter as the test fails anyway
+    int ret = 0;
+    void *addr = mmap(NULL, MB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        ret = -1;
+    } else if (mmap(NULL, 4 * MB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1,
+                    0) != MAP_FAILED || errno != ENOMEM) {
+        ret = -1;
+    } else if (mremap(addr, MB, 4 * MB, MREMAP_MAYMOVE) != MAP_FAILED || errno != ENOMEM) {
+        ret = -1;
+    } else {
+        munmap(addr, MB);
+    }
+
+    if (setrlimit(RLIMIT_AS, &old_rlim) < 0) {
+        THROW_ERROR("failed to restore RLIMIT_AS");
+    }
+    if (ret < 0) {
+        THROW_ERROR("RLIMIT_AS is not enforced by mmap and mremap");
     }
     return 0;
 }
+
+int test_rlimit_data() {
+    struct rlimit old_rlim, rlim;
+    if (getrlimit(RLIMIT_DATA, &old_rlim) < 0) {
+        THROW_ERROR("getrlimit failed");
+    }
+    unsigned long cur_brk = syscall(SYS_brk, 0);
+    unsigned long new_brk = (cur_brk + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE + PAGE_SIZE;
+
+    // The heap can't grow at all
+    rlim.rlim_cur = 0;
+    rlim.rlim_max = old_rlim.rlim_max;
+    if (setrlimit(RLIMIT_DATA, &rlim) < 0) {
+        THROW_ERROR("setrlimit failed");
+    }
+    unsigned long limited_brk = syscall(SYS_brk, new_brk);
+    if (setrlimit(RLIMIT_DATA, &old_rlim) < 0) {
+        THROW_ERROR("failed to restore RLIMIT_DATA");
+    }
+    if (limited_brk != cur_brk) {
+        THROW_ERROR("RLIMIT_DATA is not enforced by brk");
+    }
+
+    if (syscall(SYS_brk, new_brk) != new_brk) {
+        THROW_ERROR("failed to grow the heap");
+    }
+    syscall(SYS_brk, cur_brk);
+    return 0;
+}
+
+int test_rlimit_data_mmap() {
+    struct rlimit old_rlim, rlim;
+    if (getrlimit(RLIMIT_DATA, &old_rlim) < 0) {
+        THROW_ERROR("getrlimit failed");
+    }
+
+    // No more private writable memory can be mapped
+    rlim.rlim_cur = 0;
+    rlim.rlim_max = old_rlim.rlim_max;
+    if (setrlimit(RLIMIT_DATA, &rlim) < 0) {
+        THROW_ERROR("setrlimit failed");
+    }
+    void *private_addr = mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+                              MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    int private_errno = errno;
+    void *readonly_addr = mmap(NULL, PAGE_SIZE, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (setrlimit(RLIMIT_DATA, &old_rlim) < 0) {
+        THROW_ERROR("failed to restore RLIMIT_DATA");
+    }
+
+    if (private_addr != MAP_FAILED || private_errno != ENOMEM) {
+        THROW_ERROR("RLIMIT_DATA is not enforced by mmap");
+    }
+    if (readonly_addr == MAP_FAILED) {
+        THROW_ERROR("read-only memory should not be limited by RLIMIT_DATA");
+    }
+    munmap(readonly_addr, PAGE_SIZE);
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_get_and_set_rlimit),
+    TEST_CASE(test_rlimit_as),
+    TEST_CASE(test_rlimit_data),
+    TEST_CASE(test_rlimit_data_mmap),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
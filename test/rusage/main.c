@@ -0,0 +1,211 @@
+#define _GNU_SOURCE
+#include <sys/resource.h>
+#include <sys/times.h>
+#include <sys/wait.h>
+#include <errno.h>
+#include <spawn.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define CHILD_BUSY_MS   200
+
+static long timeval_to_ms(const struct timeval *tv) {
+    return tv->tv_sec * 1000 + tv->tv_usec / 1000;
+}
+
+static long timeval_to_us(const struct timeval *tv) {
+    return tv->tv_sec * 1000000 + tv->tv_usec;
+}
+
+static long cpu_time_ms(const struct rusage *usage) {
+    return timeval_to_ms(&usage->ru_utime) + timeval_to_ms(&usage->ru_stime);
+}
+
+// Consume the CPU time in the user space for the given milliseconds
+static void busy_loop(long ms) {
+    struct timespec start, now;
+    volatile unsigned long counter = 0;
+
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    do {
+        for (int i = 0; i < 100000; i++) {
+            counter++;
+        }
+        clock_gettime(CLOCK_MONOTONIC, &now);
+    } while ((now.tv_sec - start.tv_sec) * 1000 + (now.tv_nsec - start.tv_nsec) / 1000000 < ms);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_getrusage_self(void) {
+    struct rusage before, after;
+
+    if (getrusage(RUSAGE_SELF, &before) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    busy_loop(100);
+    if (getrusage(RUSAGE_SELF, &after) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    if (cpu_time_ms(&after) - cpu_time_ms(&before) < 50) {
+        THROW_ERROR("the consumed CPU time is not accounted");
+    }
+    return 0;
+}
+
+static int test_getrusage_thread(void) {
+    struct rusage thread_usage, self_usage;
+
+    busy_loop(50);
+    if (getrusage(RUSAGE_THREAD, &thread_usage) < 0 ||
+            getrusage(RUSAGE_SELF, &self_usage) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    if (cpu_time_ms(&thread_usage) <= 0 ||
+            cpu_time_ms(&thread_usage) > cpu_time_ms(&self_usage)) {
+        THROW_ERROR("unexpected CPU time of the thread");
+    }
+    return 0;
+}
+
+static int test_cpu_time_never_decreases(void) {
+    struct rusage prev, usage;
+
+    if (getrusage(RUSAGE_THREAD, &prev) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    // Alternate between the user and system time, so that the ratio of them changes
+    for (int i = 0; i < 200; i++) {
+        if (i % 2 == 0) {
+            busy_loop(1);
+        } else {
+            for (int j = 0; j < 1000; j++) {
+                getppid();
+            }
+        }
+        if (getrusage(RUSAGE_THREAD, &usage) < 0) {
+            THROW_ERROR("getrusage failed");
+        }
+        if (timeval_to_us(&usage.ru_utime) < timeval_to_us(&prev.ru_utime) ||
+                timeval_to_us(&usage.ru_stime) < timeval_to_us(&prev.ru_stime)) {
+            THROW_ERROR("the user or system time decreases");
+        }
+        prev = usage;
+    }
+    if (usage.ru_minflt != 0 || usage.ru_majflt != 0) {
+        THROW_ERROR("no page faults should be counted");
+    }
+    return 0;
+}
+
+static int test_voluntary_context_switches(void) {
+    struct rusage before, after;
+
+    if (getrusage(RUSAGE_THREAD, &before) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    usleep(10 * 1000);
+    if (getrusage(RUSAGE_THREAD, &after) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    if (after.ru_nvcsw <= before.ru_nvcsw) {
+        THROW_ERROR("sleeping is not counted as a voluntary context switch");
+    }
+    return 0;
+}
+
+static int test_getrusage_children(void) {
+    struct rusage before, after;
+    int status;
+    pid_t child_pid;
+    char *child_argv[] = {"rusage", "busy", NULL};
+
+    if (getrusage(RUSAGE_CHILDREN, &before) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    if (posix_spawn(&child_pid, "/bin/rusage", NULL, NULL, child_argv, NULL) < 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    // The usage of the child is not reaped until the child is waited for
+    if (waitpid(child_pid, &status, 0) < 0 || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0) {
+        THROW_ERROR("failed to wait for the child process");
+    }
+    if (getrusage(RUSAGE_CHILDREN, &after) < 0) {
+        THROW_ERROR("getrusage failed");
+    }
+    if (cpu_time_ms(&after) - cpu_time_ms(&before) < CHILD_BUSY_MS / 2) {
+        THROW_ERROR("the CPU time of the child is not reaped");
+    }
+    return 0;
+}
+
+static int test_getrusage_invalid_args(void) {
+    struct rusage usage;
+
+    if (getrusage(2, &usage) != -1 || errno != EINVAL) {
+        THROW_ERROR("getrusage with an invalid who should fail with EINVAL");
+    }
+    return 0;
+}
+
+static int test_times(void) {
+    struct tms before, after;
+    clock_t elapsed_before, elapsed_after;
+    long ticks_per_sec = sysconf(_SC_CLK_TCK);
+
+    elapsed_before = times(&before);
+    if (elapsed_before == (clock_t) -1) {
+        THROW_ERROR("times failed");
+    }
+    busy_loop(100);
+    elapsed_after = times(&after);
+    if (elapsed_after == (clock_t) -1) {
+        THROW_ERROR("times failed");
+    }
+    if (elapsed_after - elapsed_before < ticks_per_sec / 20) {
+        THROW_ERROR("the elapsed real time is not correct");
+    }
+    if ((after.tms_utime + after.tms_stime) - (before.tms_utime + before.tms_stime) <
+            ticks_per_sec / 20) {
+        THROW_ERROR("the consumed CPU time is not accounted");
+    }
+    // The children have been waited for in the previous test case
+    if (after.tms_cutime + after.tms_cstime < CHILD_BUSY_MS / 2 * ticks_per_sec / 1000) {
+        THROW_ERROR("the CPU time of the children is not accounted");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_getrusage_self),
+    TEST_CASE(test_getrusage_thread),
+    TEST_CASE(test_cpu_time_never_decreases),
+    TEST_CASE(test_voluntary_context_switches),
+    TEST_CASE(test_getrusage_children),
+    TEST_CASE(test_getrusage_invalid_args),
+    TEST_CASE(test_times),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1 && strcmp(argv[1], "busy") == 0) {
+        // Child process will arrive here
+        busy_loop(CHILD_BUSY_MS);
+        return 0;
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
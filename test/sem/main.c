@@ -0,0 +1,376 @@
+#define _GNU_SOURCE
+#include <sys/ipc.h>
+#include <sys/sem.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/types.h>
+#include <sys/wait.h>
+#include <pthread.h>
+#include <time.h>
+#include <stdlib.h>
+#include <stdio.h>
+#include <spawn.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Global definitions
+// ============================================================================
+
+#define S_IRWUSER   (S_IRUSR | S_IWUSR)
+
+#define TEST_SEM_UNDO       0
+#define TEST_SEM_UNDO_ARGC  3
+
+#define ARG_BUF_SZ  64
+
+#define SUCCESS     1
+#define FAIL        (-1)
+
+const char prog_name[] = "/bin/sem";
+
+// The caller must define this union according to semctl(2)
+union semun {
+    int val;
+    struct semid_ds *buf;
+    unsigned short *array;
+};
+
+// ============================================================================
+// Helper macro and function
+// ============================================================================
+#define INFO(fmt, ...)   do { \
+    printf("\t\t[file: %s, line: %d, func: %s] " fmt, \
+    __FILE__, __LINE__, __func__, ##__VA_ARGS__); \
+} while (0)
+
+static int execute_in_child(char **const child_argv) {
+    int ret;
+    pid_t child_pid;
+    int child_status;
+
+    ret = posix_spawn(&child_pid, prog_name, NULL, NULL, (char **const)child_argv, NULL);
+    if (ret < 0) {
+        THROW_ERROR("Failed to spawn a child process");
+    }
+    ret = waitpid(child_pid, &child_status, 0);
+    if (ret < 0) {
+        THROW_ERROR("Failed to waitpid() for child process");
+    }
+    if (!WIFEXITED(child_status) || WEXITSTATUS(child_status) != 0) {
+        INFO("The test in child failed\n");
+        return FAIL;
+    }
+
+    return SUCCESS;
+}
+
+// ============================================================================
+// Test cases for sem
+// ============================================================================
+static int test_semget_semid_from_key(void) {
+    int ret, semid;
+    key_t key;
+
+    srand(time(NULL));
+    key = random();
+    ret = semget(key, 1, S_IRWUSER);
+    if (ret != -1 || errno != ENOENT) {
+        INFO("semget() should return ENOENT, ret: %d errno: %d\n", ret, errno);
+        return FAIL;
+    }
+
+    semid = semget(key, 2, IPC_CREAT | IPC_EXCL | S_IRWUSER);
+    if (semid < 0) {
+        THROW_ERROR("semget() cannot create the semaphore set");
+    }
+    ret = semget(key, 2, S_IRWUSER);
+    if (ret != semid) {
+        INFO("semid mismatches, correct: %d actual: %d\n", semid, ret);
+        return FAIL;
+    }
+    ret = semget(key, 3, S_IRWUSER);
+    if (ret != -1 || errno != EINVAL) {
+        INFO("semget() should return EINVAL for too large nsems, ret: %d errno: %d\n", ret, errno);
+        return FAIL;
+    }
+    ret = semget(key, 2, IPC_CREAT | IPC_EXCL | S_IRWUSER);
+    if (ret != -1 || errno != EEXIST) {
+        INFO("semget() should return EEXIST, ret: %d errno: %d\n", ret, errno);
+        return FAIL;
+    }
+
+    if (semctl(semid, 0, IPC_RMID) < 0) {
+        THROW_ERROR("Cannot remove the semaphore set");
+    }
+    return SUCCESS;
+}
+
+static int test_semctl_values(void) {
+    int semid, ret;
+    unsigned short vals[3] = {1, 2, 3};
+    unsigned short out_vals[3] = {0};
+    struct semid_ds ds;
+    union semun arg;
+
+    semid = semget(IPC_PRIVATE, 3, IPC_CREAT | S_IRWUSER);
+    if (semid < 0) {
+        THROW_ERROR("semget() cannot create the semaphore set");
+    }
+
+    arg.array = vals;
+    if (semctl(semid, 0, SETALL, arg) < 0) {
+        THROW_ERROR("semctl(SETALL) failed");
+    }
+    arg.array = out_vals;
+    if (semctl(semid, 0, GETALL, arg) < 0) {
+        THROW_ERROR("semctl(GETALL) failed");
+    }
+    if (memcmp(vals, out_vals, sizeof(vals)) != 0) {
+        THROW_ERROR("values got by GETALL mismatch");
+    }
+
+    arg.val = 10;
+    if (semctl(semid, 1, SETVAL, arg) < 0) {
+        THROW_ERROR("semctl(SETVAL) failed");
+    }
+    if ((ret = semctl(semid, 1, GETVAL)) != 10) {
+        THROW_ERROR("semctl(GETVAL) returns wrong value: %d", ret);
+    }
+    if ((ret = semctl(semid, 1, GETPID)) != getpid()) {
+        THROW_ERROR("semctl(GETPID) returns wrong pid: %d", ret);
+    }
+
+    arg.buf = &ds;
+    if (semctl(semid, 0, IPC_STAT, arg) < 0) {
+        THROW_ERROR("semctl(IPC_STAT) failed");
+    }
+    if (ds.sem_nsems != 3) {
+        THROW_ERROR("IPC_STAT returns wrong nsems: %lu", (unsigned long)ds.sem_nsems);
+    }
+
+    if (semctl(semid, 0, IPC_RMID) < 0) {
+        THROW_ERROR("Cannot remove the semaphore set");
+    }
+    if (semctl(semid, 0, GETVAL) != -1 || errno != EINVAL) {
+        THROW_ERROR("the semaphore set should have been removed");
+    }
+    return SUCCESS;
+}
+
+static int test_semop_nowait_and_timeout(void) {
+    int semid, ret;
+    struct sembuf sop = { .sem_num = 0, .sem_op = -1, .sem_flg = IPC_NOWAIT };
+    struct timespec timeout = { .tv_sec = 0, .tv_nsec = 100 * 1000 * 1000 };
+
+    semid = semget(IPC_PRIVATE, 1, IPC_CREAT | S_IRWUSER);
+    if (semid < 0) {
+        THROW_ERROR("semget() cannot create the semaphore set");
+    }
+
+    ret = semop(semid, &sop, 1);
+    if (ret != -1 || errno != EAGAIN) {
+        THROW_ERROR("semop() with IPC_NOWAIT should return EAGAIN");
+    }
+
+    sop.sem_flg = 0;
+    ret = semtimedop(semid, &sop, 1, &timeout);
+    if (ret != -1 || errno != EAGAIN) {
+        THROW_ERROR("semtimedop() should time out with EAGAIN");
+    }
+
+    sop.sem_op = 2;
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop() failed to increase the value");
+    }
+    // Wait-for-zero should block since the value is 2
+    sop.sem_op = 0;
+    sop.sem_flg = IPC_NOWAIT;
+    ret = semop(semid, &sop, 1);
+    if (ret != -1 || errno != EAGAIN) {
+        THROW_ERROR("semop() waiting for zero should return EAGAIN");
+    }
+
+    if (semctl(semid, 0, IPC_RMID) < 0) {
+        THROW_ERROR("Cannot remove the semaphore set");
+    }
+    return SUCCESS;
+}
+
+static int test_semop_invalid_args(void) {
+    int semid, ret;
+    struct sembuf sop = { .sem_num = 0, .sem_op = 0, .sem_flg = 0 };
+
+    semid = semget(IPC_PRIVATE, 1, IPC_CREAT | S_IRWUSER);
+    if (semid < 0) {
+        THROW_ERROR("semget() cannot create the semaphore set");
+    }
+    ret = semop(semid, &sop, 0);
+    if (ret != -1 || errno != EINVAL) {
+        THROW_ERROR("semop() with no operations should return EINVAL");
+    }
+
+    // Raise the value without SEM_UNDO, so that the adjustment can grow by decreasing it
+    sop.sem_op = 32767;
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop() failed to increase the value");
+    }
+    sop.sem_op = -32767;
+    sop.sem_flg = SEM_UNDO;
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop() failed to decrease the value with SEM_UNDO");
+    }
+    sop.sem_op = 1;
+    sop.sem_flg = 0;
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop() failed to increase the value");
+    }
+    // The adjustment would be SEMAEM + 1
+    sop.sem_op = -1;
+    sop.sem_flg = SEM_UNDO;
+    ret = semop(semid, &sop, 1);
+    if (ret != -1 || errno != ERANGE) {
+        THROW_ERROR("semop() overflowing the adjustment should return ERANGE");
+    }
+    // The failed operation takes no effect
+    if (semctl(semid, 0, GETVAL) != 1) {
+        THROW_ERROR("the value of semaphore should be one");
+    }
+
+    if (semctl(semid, 0, IPC_RMID) < 0) {
+        THROW_ERROR("Cannot remove the semaphore set");
+    }
+    return SUCCESS;
+}
+
+static void *thread_post(void *arg) {
+    int semid = *(int *)arg;
+    struct sembuf sop = { .sem_num = 0, .sem_op = 1, .sem_flg = 0 };
+
+    usleep(100 * 1000);
+    semop(semid, &sop, 1);
+    return NULL;
+}
+
+static int test_semop_blocking_wakeup(void) {
+    int semid;
+    pthread_t tid;
+    struct sembuf sop = { .sem_num = 0, .sem_op = -1, .sem_flg = 0 };
+
+    semid = semget(IPC_PRIVATE, 1, IPC_CREAT | S_IRWUSER);
+    if (semid < 0) {
+        THROW_ERROR("semget() cannot create the semaphore set");
+    }
+    if (pthread_create(&tid, NULL, thread_post, &semid) != 0) {
+        THROW_ERROR("pthread_create() failed");
+    }
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop() failed to wait for the semaphore");
+    }
+    pthread_join(tid, NULL);
+
+    if (semctl(semid, 0, GETVAL) != 0) {
+        THROW_ERROR("the value of semaphore should be zero");
+    }
+    if (semctl(semid, 0, IPC_RMID) < 0) {
+        THROW_ERROR("Cannot remove the semaphore set");
+    }
+    return SUCCESS;
+}
+
+static int test_sem_undo_on_exit(void) {
+    int semid, ret;
+    char *child_argv[TEST_SEM_UNDO_ARGC + 1];
+    union semun arg;
+
+    semid = semget(IPC_PRIVATE, 1, IPC_CREAT | S_IRWUSER);
+    if (semid < 0) {
+        THROW_ERROR("semget() cannot create the semaphore set");
+    }
+    arg.val = 1;
+    if (semctl(semid, 0, SETVAL, arg) < 0) {
+        THROW_ERROR("semctl(SETVAL) failed");
+    }
+
+    for (int i = 0; i < TEST_SEM_UNDO_ARGC; i++) {
+        child_argv[i] = (char *)malloc(ARG_BUF_SZ);
+    }
+    snprintf(child_argv[0], ARG_BUF_SZ, "%s", prog_name);
+    snprintf(child_argv[1], ARG_BUF_SZ, "%d", TEST_SEM_UNDO);
+    snprintf(child_argv[2], ARG_BUF_SZ, "%d", semid);
+    child_argv[TEST_SEM_UNDO_ARGC] = NULL;
+    ret = execute_in_child(child_argv);
+    for (int i = 0; i < TEST_SEM_UNDO_ARGC; i++) {
+        free(child_argv[i]);
+    }
+    if (ret != SUCCESS) {
+        return FAIL;
+    }
+
+    // The decrement done by the child should be undone when it exits
+    if ((ret = semctl(semid, 0, GETVAL)) != 1) {
+        THROW_ERROR("SEM_UNDO is not applied on exit, value: %d", ret);
+    }
+    if (semctl(semid, 0, IPC_RMID) < 0) {
+        THROW_ERROR("Cannot remove the semaphore set");
+    }
+    return SUCCESS;
+}
+
+// ============================================================================
+// Funtion invoked in child process
+// ============================================================================
+
+static int child_test_sem_undo(int argc, const char *argv[]) {
+    int semid;
+    struct sembuf sop = { .sem_num = 0, .sem_op = -1, .sem_flg = SEM_UNDO };
+
+    if (argc != TEST_SEM_UNDO_ARGC) {
+        INFO("Invalid argument, argc: %d\n", argc);
+        return FAIL;
+    }
+    semid = atoi(argv[2]);
+    if (semop(semid, &sop, 1) < 0) {
+        THROW_ERROR("semop() failed in child");
+    }
+    if (semctl(semid, 0, GETVAL) != 0) {
+        THROW_ERROR("the value of semaphore should be zero");
+    }
+    return SUCCESS;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_semget_semid_from_key),
+    TEST_CASE(test_semctl_values),
+    TEST_CASE(test_semop_nowait_and_timeout),
+    TEST_CASE(test_semop_invalid_args),
+    TEST_CASE(test_semop_blocking_wakeup),
+    TEST_CASE(test_sem_undo_on_exit),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc == 1) {
+        // Parent process will arrive here
+        return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+    } else {
+        // Child process will arrive here
+        int option = atoi(argv[1]), ret;
+        switch (option) {
+            case TEST_SEM_UNDO:
+                ret = child_test_sem_undo(argc, argv);
+                break;
+            default:
+                INFO("Invalid option: %d\n", option);
+                ret = FAIL;
+        }
+        if (ret == SUCCESS) {
+            return 0;
+        } else {
+            return -1;
+        }
+    }
+}
@@ -0,0 +1,223 @@
+#define _GNU_SOURCE
+#include <sys/epoll.h>
+#include <sys/signalfd.h>
+#include <sys/types.h>
+#include <errno.h>
+#include <pthread.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static int block_signals(sigset_t *mask) {
+    sigemptyset(mask);
+    sigaddset(mask, SIGUSR1);
+    sigaddset(mask, SIGUSR2);
+    return sigprocmask(SIG_BLOCK, mask, NULL);
+}
+
+static void unblock_signals(sigset_t *mask) {
+    sigprocmask(SIG_UNBLOCK, mask, NULL);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_signalfd_read(void) {
+    sigset_t mask;
+    struct signalfd_siginfo ssi;
+
+    if (block_signals(&mask) < 0) {
+        THROW_ERROR("failed to block signals");
+    }
+    int fd = signalfd(-1, &mask, SFD_NONBLOCK | SFD_CLOEXEC);
+    if (fd < 0) {
+        unblock_signals(&mask);
+        THROW_ERROR("signalfd failed");
+    }
+    if (read(fd, &ssi, sizeof(ssi)) != -1 || errno != EAGAIN) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("reading without pending signals should fail with EAGAIN");
+    }
+
+    kill(getpid(), SIGUSR1);
+    if (read(fd, &ssi, sizeof(ssi)) != sizeof(ssi) || ssi.ssi_signo != SIGUSR1 ||
+            ssi.ssi_code != SI_USER || ssi.ssi_pid != getpid()) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("unexpected signalfd_siginfo for kill");
+    }
+
+    union sigval value = { .sival_int = 1234 };
+    sigqueue(getpid(), SIGUSR2, value);
+    if (read(fd, &ssi, sizeof(ssi)) != sizeof(ssi) || ssi.ssi_signo != SIGUSR2 ||
+            ssi.ssi_code != SI_QUEUE || ssi.ssi_int != 1234) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("unexpected signalfd_siginfo for sigqueue");
+    }
+
+    // A buffer smaller than a record is invalid
+    kill(getpid(), SIGUSR1);
+    if (read(fd, &ssi, sizeof(ssi) - 1) != -1 || errno != EINVAL) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("reading with a small buffer should fail with EINVAL");
+    }
+    if (read(fd, &ssi, sizeof(ssi)) != sizeof(ssi)) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("the pending signal is not consumed");
+    }
+
+    close(fd);
+    unblock_signals(&mask);
+    return 0;
+}
+
+static int test_signalfd_update_mask(void) {
+    sigset_t mask, usr2_mask;
+    struct signalfd_siginfo ssi;
+
+    if (block_signals(&mask) < 0) {
+        THROW_ERROR("failed to block signals");
+    }
+    sigemptyset(&usr2_mask);
+    sigaddset(&usr2_mask, SIGUSR2);
+    int fd = signalfd(-1, &usr2_mask, SFD_NONBLOCK);
+    if (fd < 0) {
+        unblock_signals(&mask);
+        THROW_ERROR("signalfd failed");
+    }
+
+    kill(getpid(), SIGUSR1);
+    if (read(fd, &ssi, sizeof(ssi)) != -1 || errno != EAGAIN) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("signals not in the mask should not be read");
+    }
+    if (signalfd(fd, &mask, 0) != fd) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("failed to update the mask");
+    }
+    if (read(fd, &ssi, sizeof(ssi)) != sizeof(ssi) || ssi.ssi_signo != SIGUSR1) {
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("the signal should be read after updating the mask");
+    }
+
+    close(fd);
+    unblock_signals(&mask);
+    return 0;
+}
+
+static int test_signalfd_epoll(void) {
+    sigset_t mask;
+    struct signalfd_siginfo ssi;
+    struct epoll_event event = { .events = EPOLLIN };
+    struct pollfd pfd;
+
+    if (block_signals(&mask) < 0) {
+        THROW_ERROR("failed to block signals");
+    }
+    int fd = signalfd(-1, &mask, SFD_NONBLOCK);
+    int epfd = epoll_create1(0);
+    if (fd < 0 || epfd < 0 || epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &event) < 0) {
+        unblock_signals(&mask);
+        THROW_ERROR("failed to set up signalfd and epoll");
+    }
+
+    pfd.fd = fd;
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 0) != 0) {
+        close(epfd);
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("signalfd should not be readable without pending signals");
+    }
+    kill(getpid(), SIGUSR2);
+    if (epoll_wait(epfd, &event, 1, 1000) != 1 || !(event.events & EPOLLIN)) {
+        close(epfd);
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("epoll_wait should report signalfd as readable");
+    }
+    if (read(fd, &ssi, sizeof(ssi)) != sizeof(ssi) || ssi.ssi_signo != SIGUSR2) {
+        close(epfd);
+        close(fd);
+        unblock_signals(&mask);
+        THROW_ERROR("unexpected signal");
+    }
+
+    close(epfd);
+    close(fd);
+    unblock_signals(&mask);
+    return 0;
+}
+
+static int signalfd_in_thread = -1;
+
+static void *read_in_another_thread(void *arg) {
+    struct signalfd_siginfo ssi;
+    struct pollfd pfd = { .fd = signalfd_in_thread, .events = POLLIN };
+
+    // The signal is directed at this thread, not the one that created the signalfd
+    pthread_kill(pthread_self(), SIGUSR1);
+    if (poll(&pfd, 1, 0) != 1 || !(pfd.revents & POLLIN)) {
+        return (void *) -1;
+    }
+    if (read(signalfd_in_thread, &ssi, sizeof(ssi)) != sizeof(ssi) ||
+            ssi.ssi_signo != SIGUSR1) {
+        return (void *) -1;
+    }
+    return NULL;
+}
+
+static int test_signalfd_read_in_another_thread(void) {
+    sigset_t mask;
+    pthread_t thread;
+    void *ret;
+
+    if (block_signals(&mask) < 0) {
+        THROW_ERROR("failed to block signals");
+    }
+    signalfd_in_thread = signalfd(-1, &mask, SFD_NONBLOCK);
+    if (signalfd_in_thread < 0) {
+        unblock_signals(&mask);
+        THROW_ERROR("signalfd failed");
+    }
+    if (pthread_create(&thread, NULL, read_in_another_thread, NULL) != 0 ||
+            pthread_join(thread, &ret) != 0 || ret != NULL) {
+        close(signalfd_in_thread);
+        unblock_signals(&mask);
+        THROW_ERROR("the signal of the reading thread is not read");
+    }
+
+    close(signalfd_in_thread);
+    unblock_signals(&mask);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_signalfd_read),
+    TEST_CASE(test_signalfd_update_mask),
+    TEST_CASE(test_signalfd_epoll),
+    TEST_CASE(test_signalfd_read_in_another_thread),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
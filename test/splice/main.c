@@ -0,0 +1,289 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <sys/uio.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define FILE_PATH       "/root/test_splice_file.txt"
+
+static const char MSG[] = "Hello from splice!";
+
+static void free_pipe(int *pipe) {
+    close(pipe[0]);
+    close(pipe[1]);
+}
+
+static int check_read(int fd, const char *expected, size_t len) {
+    char buf[128] = {0};
+    if (len > sizeof(buf) || read(fd, buf, len) != len) {
+        THROW_ERROR("failed to read the expected length");
+    }
+    if (memcmp(buf, expected, len) != 0) {
+        THROW_ERROR("the data read is not as expected");
+    }
+    return 0;
+}
+
+static int create_file_with_msg(void) {
+    int fd = open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0666);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a file");
+    }
+    if (write(fd, MSG, sizeof(MSG)) != sizeof(MSG)) {
+        close(fd);
+        THROW_ERROR("failed to write the file");
+    }
+    return fd;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+int test_splice_pipe_to_pipe() {
+    int in_pipe[2], out_pipe[2];
+    if (pipe(in_pipe) < 0 || pipe(out_pipe) < 0) {
+        THROW_ERROR("failed to create pipes");
+    }
+
+    if (write(in_pipe[1], MSG, sizeof(MSG)) != sizeof(MSG)) {
+        THROW_ERROR("failed to write the pipe");
+    }
+    ssize_t len = splice(in_pipe[0], NULL, out_pipe[1], NULL, 1024, 0);
+    if (len != sizeof(MSG)) {
+        THROW_ERROR("failed to splice between pipes");
+    }
+    if (check_read(out_pipe[0], MSG, sizeof(MSG)) < 0) {
+        THROW_ERROR("failed to check the output pipe");
+    }
+
+    // The input pipe is drained
+    len = splice(in_pipe[0], NULL, out_pipe[1], NULL, 1024, SPLICE_F_NONBLOCK);
+    if (len != -1 || errno != EAGAIN) {
+        THROW_ERROR("splice on an empty pipe should fail with EAGAIN");
+    }
+    // All the writers of the input pipe are closed
+    close(in_pipe[1]);
+    if (splice(in_pipe[0], NULL, out_pipe[1], NULL, 1024, 0) != 0) {
+        THROW_ERROR("splice should return 0 at the end of the pipe");
+    }
+
+    close(in_pipe[0]);
+    free_pipe(out_pipe);
+    return 0;
+}
+
+int test_splice_file_to_pipe() {
+    int pipe_fds[2];
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    int fd = create_file_with_msg();
+    if (fd < 0) {
+        THROW_ERROR("failed to create the file");
+    }
+
+    // Splice from an offset, which is updated while the file offset is not
+    loff_t offset = 6;
+    ssize_t len = splice(fd, &offset, pipe_fds[1], NULL, 1024, SPLICE_F_MORE);
+    if (len != sizeof(MSG) - 6 || offset != sizeof(MSG)) {
+        THROW_ERROR("failed to splice the file with an offset");
+    }
+    if (check_read(pipe_fds[0], MSG + 6, sizeof(MSG) - 6) < 0) {
+        THROW_ERROR("failed to check the pipe");
+    }
+    if (lseek(fd, 0, SEEK_CUR) != sizeof(MSG)) {
+        THROW_ERROR("the file offset should not be changed");
+    }
+
+    // Splice from the file offset
+    lseek(fd, 0, SEEK_SET);
+    len = splice(fd, NULL, pipe_fds[1], NULL, 5, 0);
+    if (len != 5 || lseek(fd, 0, SEEK_CUR) != 5) {
+        THROW_ERROR("failed to splice the file from the file offset");
+    }
+    if (check_read(pipe_fds[0], MSG, 5) < 0) {
+        THROW_ERROR("failed to check the pipe");
+    }
+
+    close(fd);
+    unlink(FILE_PATH);
+    free_pipe(pipe_fds);
+    return 0;
+}
+
+int test_splice_pipe_to_file() {
+    int pipe_fds[2];
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    int fd = open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0666);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a file");
+    }
+
+    if (write(pipe_fds[1], MSG, sizeof(MSG)) != sizeof(MSG)) {
+        THROW_ERROR("failed to write the pipe");
+    }
+    loff_t offset = 0;
+    ssize_t len = splice(pipe_fds[0], NULL, fd, &offset, 1024, 0);
+    if (len != sizeof(MSG) || offset != sizeof(MSG)) {
+        THROW_ERROR("failed to splice the pipe to the file");
+    }
+    if (check_read(fd, MSG, sizeof(MSG)) < 0) {
+        THROW_ERROR("failed to check the file");
+    }
+
+    close(fd);
+    unlink(FILE_PATH);
+    free_pipe(pipe_fds);
+    return 0;
+}
+
+int test_splice_pipe_to_socket() {
+    int pipe_fds[2], socks[2];
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, socks) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+
+    if (write(pipe_fds[1], MSG, sizeof(MSG)) != sizeof(MSG)) {
+        THROW_ERROR("failed to write the pipe");
+    }
+    ssize_t len = splice(pipe_fds[0], NULL, socks[0], NULL, 1024, SPLICE_F_MORE);
+    if (len != sizeof(MSG)) {
+        THROW_ERROR("failed to splice the pipe to the socket");
+    }
+    if (check_read(socks[1], MSG, sizeof(MSG)) < 0) {
+        THROW_ERROR("failed to check the socket");
+    }
+
+    free_pipe(socks);
+    free_pipe(pipe_fds);
+    return 0;
+}
+
+int test_splice_invalid_args() {
+    int pipe_fds[2];
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+    int fd = create_file_with_msg();
+    if (fd < 0) {
+        THROW_ERROR("failed to create the file");
+    }
+
+    loff_t offset = 0;
+    if (splice(pipe_fds[0], NULL, pipe_fds[1], NULL, 1, 0) != -1 || errno != EINVAL) {
+        THROW_ERROR("splicing a pipe to itself should fail with EINVAL");
+    }
+    if (splice(fd, NULL, fd, NULL, 1, 0) != -1 || errno != EINVAL) {
+        THROW_ERROR("splicing without pipes should fail with EINVAL");
+    }
+    if (splice(fd, NULL, pipe_fds[1], &offset, 1, 0) != -1 || errno != ESPIPE) {
+        THROW_ERROR("splicing with the offset of a pipe should fail with ESPIPE");
+    }
+    if (splice(fd, NULL, pipe_fds[1], NULL, 1, 0x10) != -1 || errno != EINVAL) {
+        THROW_ERROR("splicing with invalid flags should fail with EINVAL");
+    }
+    if (splice(pipe_fds[1], NULL, fd, NULL, 1, 0) != -1 || errno != EBADF) {
+        THROW_ERROR("splicing from a write-only pipe should fail with EBADF");
+    }
+
+    close(fd);
+    unlink(FILE_PATH);
+    free_pipe(pipe_fds);
+    return 0;
+}
+
+int test_tee() {
+    int in_pipe[2], out_pipe[2];
+    if (pipe(in_pipe) < 0 || pipe(out_pipe) < 0) {
+        THROW_ERROR("failed to create pipes");
+    }
+
+    if (write(in_pipe[1], MSG, sizeof(MSG)) != sizeof(MSG)) {
+        THROW_ERROR("failed to write the pipe");
+    }
+    ssize_t len = tee(in_pipe[0], out_pipe[1], 5, 0);
+    if (len != 5) {
+        THROW_ERROR("failed to tee between pipes");
+    }
+    // The data is duplicated, not consumed
+    if (check_read(out_pipe[0], MSG, 5) < 0 ||
+            check_read(in_pipe[0], MSG, sizeof(MSG)) < 0) {
+        THROW_ERROR("failed to check the pipes");
+    }
+
+    len = tee(in_pipe[0], out_pipe[1], 5, SPLICE_F_NONBLOCK);
+    if (len != -1 || errno != EAGAIN) {
+        THROW_ERROR("tee on an empty pipe should fail with EAGAIN");
+    }
+    if (tee(in_pipe[0], in_pipe[1], 5, 0) != -1 || errno != EINVAL) {
+        THROW_ERROR("tee a pipe to itself should fail with EINVAL");
+    }
+
+    free_pipe(in_pipe);
+    free_pipe(out_pipe);
+    return 0;
+}
+
+int test_vmsplice() {
+    int pipe_fds[2];
+    if (pipe(pipe_fds) < 0) {
+        THROW_ERROR("failed to create a pipe");
+    }
+
+    struct iovec iov[2] = {
+        { .iov_base = (void *)MSG, .iov_len = 6 },
+        { .iov_base = (void *)(MSG + 6), .iov_len = sizeof(MSG) - 6 },
+    };
+    ssize_t len = vmsplice(pipe_fds[1], iov, 2, SPLICE_F_GIFT);
+    if (len != sizeof(MSG)) {
+        THROW_ERROR("failed to vmsplice to the pipe");
+    }
+
+    char buf[sizeof(MSG)] = {0};
+    struct iovec read_iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+    len = vmsplice(pipe_fds[0], &read_iov, 1, 0);
+    if (len != sizeof(MSG) || memcmp(buf, MSG, sizeof(MSG)) != 0) {
+        THROW_ERROR("failed to vmsplice from the pipe");
+    }
+
+    len = vmsplice(pipe_fds[0], &read_iov, 1, SPLICE_F_NONBLOCK);
+    if (len != -1 || errno != EAGAIN) {
+        THROW_ERROR("vmsplice on an empty pipe should fail with EAGAIN");
+    }
+
+    free_pipe(pipe_fds);
+    return 0;
+}
+
+// ============================================================================
+// Test suite
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_splice_pipe_to_pipe),
+    TEST_CASE(test_splice_file_to_pipe),
+    TEST_CASE(test_splice_pipe_to_file),
+    TEST_CASE(test_splice_pipe_to_socket),
+    TEST_CASE(test_splice_invalid_args),
+    TEST_CASE(test_tee),
+    TEST_CASE(test_vmsplice),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
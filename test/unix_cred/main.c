@@ -0,0 +1,258 @@
+#define _GNU_SOURCE
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <errno.h>
+#include <spawn.h>
+#include <stddef.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+#define SOCK_PATH       "/tmp/unix_cred.sock"
+#define CRED_SPACE      CMSG_SPACE(sizeof(struct ucred))
+
+static socklen_t fill_path_addr(struct sockaddr_un *addr, const char *path) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sun_family = AF_UNIX;
+    strcpy(addr->sun_path, path);
+    return offsetof(struct sockaddr_un, sun_path) + strlen(path) + 1;
+}
+
+static int get_peer_cred(int fd, struct ucred *cred) {
+    socklen_t len = sizeof(*cred);
+    if (getsockopt(fd, SOL_SOCKET, SO_PEERCRED, cred, &len) < 0 || len != sizeof(*cred)) {
+        THROW_ERROR("failed to get the peer credentials");
+    }
+    return 0;
+}
+
+static int check_cred(struct ucred *cred, pid_t pid) {
+    if (cred->pid != pid || cred->uid != getuid() || cred->gid != getgid()) {
+        THROW_ERROR("the credentials are wrong: pid = %d, uid = %d, gid = %d", cred->pid,
+                    cred->uid, cred->gid);
+    }
+    return 0;
+}
+
+// Send a byte with the credentials in an SCM_CREDENTIALS control message
+static ssize_t send_with_cred(int fd, struct ucred *cred) {
+    char data = 'x';
+    char control[CRED_SPACE];
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+
+    memset(control, 0, sizeof(control));
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_CREDENTIALS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred));
+    memcpy(CMSG_DATA(cmsg), cred, sizeof(*cred));
+    return sendmsg(fd, &msg, 0);
+}
+
+// Receive a byte and the credentials in an SCM_CREDENTIALS control message
+static int recv_with_cred(int fd, struct ucred *cred) {
+    char data;
+    char control[CRED_SPACE];
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+
+    memset(control, 0, sizeof(control));
+    if (recvmsg(fd, &msg, 0) <= 0) {
+        THROW_ERROR("failed to receive the message");
+    }
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_CREDENTIALS ||
+            cmsg->cmsg_len != CMSG_LEN(sizeof(struct ucred))) {
+        THROW_ERROR("no credentials are received");
+    }
+    memcpy(cred, CMSG_DATA(cmsg), sizeof(*cred));
+    return 0;
+}
+
+// The child process connects to the parent and sends its credentials
+static int run_client(void) {
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_path_addr(&addr, SOCK_PATH);
+    struct ucred cred;
+
+    int fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (fd < 0 || connect(fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        THROW_ERROR("failed to connect to the parent");
+    }
+    // The peer credentials are the ones of the listening process
+    if (get_peer_cred(fd, &cred) < 0 || check_cred(&cred, getppid()) < 0) {
+        return -1;
+    }
+
+    cred.pid = getpid();
+    cred.uid = getuid();
+    cred.gid = getgid();
+    if (send_with_cred(fd, &cred) != 1) {
+        THROW_ERROR("failed to send the credentials");
+    }
+    close(fd);
+    return 0;
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_socketpair_peer_cred(void) {
+    struct ucred cred;
+    int fds[2];
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+    for (int i = 0; i < 2; i++) {
+        if (get_peer_cred(fds[i], &cred) < 0 || check_cred(&cred, getpid()) < 0) {
+            return -1;
+        }
+    }
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+static int test_unconnected_peer_cred(void) {
+    struct ucred cred;
+
+    int fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a socket");
+    }
+    if (get_peer_cred(fd, &cred) < 0) {
+        return -1;
+    }
+    if (cred.pid != 0 || cred.uid != (uid_t) -1 || cred.gid != (gid_t) -1) {
+        THROW_ERROR("the credentials of an unconnected socket are wrong");
+    }
+    close(fd);
+    return 0;
+}
+
+static int test_pass_cred(void) {
+    struct ucred cred;
+    int fds[2], val;
+    socklen_t len = sizeof(val);
+
+    if (socketpair(AF_UNIX, SOCK_SEQPACKET, 0, fds) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+    val = 1;
+    if (setsockopt(fds[1], SOL_SOCKET, SO_PASSCRED, &val, sizeof(val)) < 0) {
+        THROW_ERROR("failed to set SO_PASSCRED");
+    }
+    val = 0;
+    if (getsockopt(fds[1], SOL_SOCKET, SO_PASSCRED, &val, &len) < 0 || val != 1) {
+        THROW_ERROR("failed to get SO_PASSCRED");
+    }
+
+    // The credentials are received even if the sender does not send them
+    if (send(fds[0], "x", 1, 0) != 1) {
+        THROW_ERROR("failed to send a message");
+    }
+    if (recv_with_cred(fds[1], &cred) < 0 || check_cred(&cred, getpid()) < 0) {
+        return -1;
+    }
+
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+static int test_forged_cred(void) {
+    struct ucred cred = { .pid = getpid() + 1, .uid = getuid(), .gid = getgid() };
+    int fds[2];
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a socket pair");
+    }
+    if (send_with_cred(fds[0], &cred) != -1 || errno != EPERM) {
+        THROW_ERROR("the forged pid is not rejected");
+    }
+    cred.pid = getpid();
+    cred.uid = getuid() + 1;
+    if (send_with_cred(fds[0], &cred) != -1 || errno != EPERM) {
+        THROW_ERROR("the forged uid is not rejected");
+    }
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+static int test_inter_process_cred(void) {
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_path_addr(&addr, SOCK_PATH);
+    const char *child_argv[] = {"/bin/unix_cred", "client", NULL};
+    struct ucred cred;
+    int listen_fd, fd, child_pid, status;
+
+    unlink(SOCK_PATH);
+    listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (listen_fd < 0 || bind(listen_fd, (struct sockaddr *)&addr, addr_len) < 0 ||
+            listen(listen_fd, 1) < 0) {
+        THROW_ERROR("failed to listen");
+    }
+    if (posix_spawn(&child_pid, child_argv[0], NULL, NULL, (char *const *)child_argv,
+                    NULL) != 0) {
+        THROW_ERROR("failed to spawn a child process");
+    }
+    fd = accept(listen_fd, NULL, NULL);
+    if (fd < 0) {
+        THROW_ERROR("failed to accept");
+    }
+    // Wait for the whole message to be sent
+    if (wait4(child_pid, &status, 0, NULL) < 0 || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0) {
+        THROW_ERROR("the child process fails");
+    }
+
+    if (get_peer_cred(fd, &cred) < 0 || check_cred(&cred, child_pid) < 0) {
+        return -1;
+    }
+    if (recv_with_cred(fd, &cred) < 0 || check_cred(&cred, child_pid) < 0) {
+        return -1;
+    }
+
+    close(fd);
+    close(listen_fd);
+    unlink(SOCK_PATH);
+    return 0;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_socketpair_peer_cred),
+    TEST_CASE(test_unconnected_peer_cred),
+    TEST_CASE(test_pass_cred),
+    TEST_CASE(test_forged_cred),
+    TEST_CASE(test_inter_process_cred),
+};
+
+int main(int argc, const char *argv[]) {
+    if (argc > 1 && strcmp(argv[1], "client") == 0) {
+        return run_client() < 0 ? -1 : 0;
+    }
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
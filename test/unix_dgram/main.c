@@ -0,0 +1,505 @@
+#define _GNU_SOURCE
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stddef.h>
+#include <string.h>
+#include <unistd.h>
+#include "test.h"
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+static const char *msgs[] = {"first message", "second", "the third message"};
+
+static socklen_t fill_path_addr(struct sockaddr_un *addr, const char *path) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sun_family = AF_UNIX;
+    strcpy(addr->sun_path, path);
+    return offsetof(struct sockaddr_un, sun_path) + strlen(path) + 1;
+}
+
+static socklen_t fill_abstract_addr(struct sockaddr_un *addr, const char *name) {
+    memset(addr, 0, sizeof(*addr));
+    addr->sun_family = AF_UNIX;
+    strcpy(addr->sun_path + 1, name);
+    return offsetof(struct sockaddr_un, sun_path) + strlen(name) + 1;
+}
+
+// Send all the messages through fds[0] and check they are received from fds[1] one by one
+static int check_msg_boundaries(int *fds) {
+    char buf[64];
+
+    for (int i = 0; i < ARRAY_SIZE(msgs); i++) {
+        if (send(fds[0], msgs[i], strlen(msgs[i]), 0) != strlen(msgs[i])) {
+            THROW_ERROR("failed to send a message");
+        }
+    }
+    for (int i = 0; i < ARRAY_SIZE(msgs); i++) {
+        memset(buf, 0, sizeof(buf));
+        if (recv(fds[1], buf, sizeof(buf), 0) != strlen(msgs[i]) ||
+                strcmp(buf, msgs[i]) != 0) {
+            THROW_ERROR("the message boundary is not preserved");
+        }
+    }
+    return 0;
+}
+
+static int check_truncation(int *fds) {
+    char buf[4];
+    const char *msg = msgs[0];
+
+    if (send(fds[0], msg, strlen(msg), 0) != strlen(msg) ||
+            send(fds[0], msg, strlen(msg), 0) != strlen(msg)) {
+        THROW_ERROR("failed to send a message");
+    }
+    // With MSG_TRUNC, the real length of the message is returned
+    if (recv(fds[1], buf, sizeof(buf), MSG_TRUNC) != strlen(msg)) {
+        THROW_ERROR("recv with MSG_TRUNC should return the real length");
+    }
+
+    struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+    struct msghdr msg_hdr = { .msg_iov = &iov, .msg_iovlen = 1 };
+    if (recvmsg(fds[1], &msg_hdr, 0) != sizeof(buf) || !(msg_hdr.msg_flags & MSG_TRUNC)) {
+        THROW_ERROR("MSG_TRUNC should be set for a truncated message");
+    }
+    // The remaining part of the truncated message is discarded
+    if (recv(fds[1], buf, sizeof(buf), MSG_DONTWAIT) != -1 || errno != EAGAIN) {
+        THROW_ERROR("the truncated message is not discarded");
+    }
+    return 0;
+}
+
+static int create_seqpacket_pair(int *fds, const char *path) {
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_path_addr(&addr, path);
+
+    int listen_fd = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    if (listen_fd < 0) {
+        THROW_ERROR("failed to create a seqpacket socket");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) < 0 || listen(listen_fd, 5) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to bind and listen");
+    }
+
+    fds[0] = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    if (fds[0] < 0 || connect(fds[0], (struct sockaddr *)&addr, addr_len) < 0) {
+        close(listen_fd);
+        THROW_ERROR("failed to connect");
+    }
+    fds[1] = accept(listen_fd, NULL, NULL);
+    close(listen_fd);
+    unlink(path);
+    if (fds[1] < 0) {
+        close(fds[0]);
+        THROW_ERROR("failed to accept");
+    }
+    return 0;
+}
+
+static void close_pair(int *fds) {
+    close(fds[0]);
+    close(fds[1]);
+}
+
+// ============================================================================
+// Test cases
+// ============================================================================
+
+static int test_dgram_sendto_and_recvfrom(void) {
+    const char *path = "/tmp/test_unix_dgram";
+    struct sockaddr_un addr, src_addr;
+    socklen_t addr_len = fill_path_addr(&addr, path);
+    socklen_t src_addr_len = sizeof(src_addr);
+    char buf[64] = { 0 };
+    int ret = -1;
+
+    int server_fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    int client_fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (server_fd < 0 || client_fd < 0) {
+        THROW_ERROR("failed to create datagram sockets");
+    }
+    if (bind(server_fd, (struct sockaddr *)&addr, addr_len) < 0) {
+        printf("failed to bind\n");
+        goto out;
+    }
+    if (sendto(client_fd, msgs[0], strlen(msgs[0]), 0, (struct sockaddr *)&addr,
+               addr_len) != strlen(msgs[0])) {
+        printf("failed to sendto\n");
+        goto out;
+    }
+    if (recvfrom(server_fd, buf, sizeof(buf), 0, (struct sockaddr *)&src_addr,
+                 &src_addr_len) != strlen(msgs[0]) || strcmp(buf, msgs[0]) != 0) {
+        printf("failed to recvfrom\n");
+        goto out;
+    }
+    // The sender is not bound, so its address is unnamed
+    if (src_addr_len > sizeof(sa_family_t)) {
+        printf("the address of an unbound sender should be empty\n");
+        goto out;
+    }
+    // A bound socket can not be bound by others
+    if (bind(client_fd, (struct sockaddr *)&addr, addr_len) != -1 || errno != EADDRINUSE) {
+        printf("binding a used address should fail with EADDRINUSE\n");
+        goto out;
+    }
+    if (listen(server_fd, 5) != -1 || errno != EOPNOTSUPP) {
+        printf("listen on a datagram socket should fail with EOPNOTSUPP\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(server_fd);
+    close(client_fd);
+    unlink(path);
+    return ret;
+}
+
+static int test_dgram_connect(void) {
+    struct sockaddr_un server_addr, client_addr, src_addr;
+    socklen_t server_addr_len = fill_abstract_addr(&server_addr, "test_unix_dgram_server");
+    socklen_t client_addr_len = fill_abstract_addr(&client_addr, "test_unix_dgram_client");
+    socklen_t src_addr_len = sizeof(src_addr);
+    char buf[64] = { 0 };
+    int fds[2] = { -1, -1 };
+    int ret = -1;
+
+    fds[0] = socket(AF_UNIX, SOCK_DGRAM, 0);
+    fds[1] = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (fds[0] < 0 || fds[1] < 0) {
+        THROW_ERROR("failed to create datagram sockets");
+    }
+    if (bind(fds[1], (struct sockaddr *)&server_addr, server_addr_len) < 0 ||
+            bind(fds[0], (struct sockaddr *)&client_addr, client_addr_len) < 0) {
+        printf("failed to bind abstract addresses\n");
+        goto out;
+    }
+    if (send(fds[0], msgs[0], strlen(msgs[0]), 0) != -1 || errno != ENOTCONN) {
+        printf("send on an unconnected socket should fail with ENOTCONN\n");
+        goto out;
+    }
+    if (connect(fds[0], (struct sockaddr *)&server_addr, server_addr_len) < 0) {
+        printf("failed to connect\n");
+        goto out;
+    }
+    if (check_msg_boundaries(fds) < 0) {
+        goto out;
+    }
+
+    // The address of a bound sender is reported
+    if (send(fds[0], msgs[0], strlen(msgs[0]), 0) != strlen(msgs[0]) ||
+            recvfrom(fds[1], buf, sizeof(buf), 0, (struct sockaddr *)&src_addr,
+                     &src_addr_len) != strlen(msgs[0])) {
+        printf("failed to send and receive\n");
+        goto out;
+    }
+    if (src_addr_len != client_addr_len ||
+            memcmp(&src_addr, &client_addr, client_addr_len) != 0) {
+        printf("the source address is wrong\n");
+        goto out;
+    }
+
+    // Disconnect by an AF_UNSPEC address
+    struct sockaddr unspec_addr = { .sa_family = AF_UNSPEC };
+    if (connect(fds[0], &unspec_addr, sizeof(unspec_addr)) < 0 ||
+            send(fds[0], msgs[0], strlen(msgs[0]), 0) != -1 || errno != ENOTCONN) {
+        printf("failed to disconnect\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close_pair(fds);
+    return ret;
+}
+
+static int test_dgram_connected_receiver(void) {
+    struct sockaddr_un server_addr, client_addr;
+    socklen_t server_addr_len = fill_abstract_addr(&server_addr, "test_unix_dgram_server");
+    socklen_t client_addr_len = fill_abstract_addr(&client_addr, "test_unix_dgram_client");
+    int fds[2] = { -1, -1 };
+    int ret = -1;
+
+    fds[0] = socket(AF_UNIX, SOCK_DGRAM, 0);
+    fds[1] = socket(AF_UNIX, SOCK_DGRAM, 0);
+    int other_fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (fds[0] < 0 || fds[1] < 0 || other_fd < 0) {
+        THROW_ERROR("failed to create datagram sockets");
+    }
+    if (bind(fds[0], (struct sockaddr *)&client_addr, client_addr_len) < 0 ||
+            bind(fds[1], (struct sockaddr *)&server_addr, server_addr_len) < 0 ||
+            connect(fds[1], (struct sockaddr *)&client_addr, client_addr_len) < 0) {
+        printf("failed to bind and connect\n");
+        goto out;
+    }
+    // A connected socket only receives datagrams from its peer
+    if (sendto(other_fd, msgs[0], strlen(msgs[0]), 0, (struct sockaddr *)&server_addr,
+               server_addr_len) != -1 || errno != EPERM) {
+        printf("sending to a socket connected to another one should fail with EPERM\n");
+        goto out;
+    }
+    if (connect(other_fd, (struct sockaddr *)&server_addr, server_addr_len) != -1 ||
+            errno != EPERM) {
+        printf("connecting to a socket connected to another one should fail with EPERM\n");
+        goto out;
+    }
+    if (sendto(fds[0], msgs[0], strlen(msgs[0]), 0, (struct sockaddr *)&server_addr,
+               server_addr_len) != strlen(msgs[0])) {
+        printf("failed to send from the peer\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close_pair(fds);
+    close(other_fd);
+    return ret;
+}
+
+static int test_dgram_bind_failure(void) {
+    const char *path = "/tmp/test_unix_dgram_bind";
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_path_addr(&addr, path);
+    struct sockaddr_un abstract_addr;
+    socklen_t abstract_addr_len = fill_abstract_addr(&abstract_addr, "test_unix_dgram_bind");
+    int ret = -1;
+
+    int fd = socket(AF_UNIX, SOCK_DGRAM, 0);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a datagram socket");
+    }
+    if (bind(fd, (struct sockaddr *)&abstract_addr, abstract_addr_len) < 0) {
+        printf("failed to bind\n");
+        goto out;
+    }
+    if (bind(fd, (struct sockaddr *)&addr, addr_len) != -1 || errno != EINVAL) {
+        printf("binding a bound socket should fail with EINVAL\n");
+        goto out;
+    }
+    // A failed bind leaves no socket file behind
+    if (access(path, F_OK) != -1 || errno != ENOENT) {
+        printf("the socket file should not be created\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(fd);
+    unlink(path);
+    return ret;
+}
+
+static int test_dgram_closed_peer(void) {
+    int fds[2];
+
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a datagram socket pair");
+    }
+    close(fds[1]);
+    if (send(fds[0], msgs[0], strlen(msgs[0]), 0) != -1 || errno != ECONNREFUSED) {
+        close(fds[0]);
+        THROW_ERROR("sending to a closed peer should fail with ECONNREFUSED");
+    }
+    close(fds[0]);
+    return 0;
+}
+
+static int test_dgram_socketpair(void) {
+    int fds[2];
+    int bytes_to_read = 0;
+
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a datagram socket pair");
+    }
+    if (check_msg_boundaries(fds) < 0 || check_truncation(fds) < 0) {
+        close_pair(fds);
+        return -1;
+    }
+
+    // FIONREAD reports the length of the next message
+    if (send(fds[0], msgs[1], strlen(msgs[1]), 0) != strlen(msgs[1]) ||
+            send(fds[0], msgs[2], strlen(msgs[2]), 0) != strlen(msgs[2]) ||
+            ioctl(fds[1], FIONREAD, &bytes_to_read) < 0 || bytes_to_read != strlen(msgs[1])) {
+        close_pair(fds);
+        THROW_ERROR("FIONREAD should report the length of the next message");
+    }
+    close_pair(fds);
+    return 0;
+}
+
+static int test_seqpacket_socketpair(void) {
+    int fds[2];
+
+    if (socketpair(AF_UNIX, SOCK_SEQPACKET, 0, fds) < 0) {
+        THROW_ERROR("failed to create a seqpacket socket pair");
+    }
+    if (check_msg_boundaries(fds) < 0 || check_truncation(fds) < 0) {
+        close_pair(fds);
+        return -1;
+    }
+
+    // The end of file is reached after the peer is closed
+    char buf[16];
+    close(fds[0]);
+    if (recv(fds[1], buf, sizeof(buf), 0) != 0) {
+        close(fds[1]);
+        THROW_ERROR("recv should return 0 after the peer is closed");
+    }
+    close(fds[1]);
+    return 0;
+}
+
+static int test_seqpacket_connect(void) {
+    int fds[2];
+
+    if (create_seqpacket_pair(fds, "/tmp/test_unix_seqpacket") < 0) {
+        return -1;
+    }
+    if (check_msg_boundaries(fds) < 0) {
+        close_pair(fds);
+        return -1;
+    }
+    close_pair(fds);
+    return 0;
+}
+
+static int test_connect_type_mismatch(void) {
+    const char *path = "/tmp/test_unix_type_mismatch";
+    struct sockaddr_un addr;
+    socklen_t addr_len = fill_path_addr(&addr, path);
+    int ret = -1;
+
+    int listen_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+    int seqpacket_fd = socket(AF_UNIX, SOCK_SEQPACKET, 0);
+    if (listen_fd < 0 || seqpacket_fd < 0) {
+        THROW_ERROR("failed to create sockets");
+    }
+    if (bind(listen_fd, (struct sockaddr *)&addr, addr_len) < 0 || listen(listen_fd, 5) < 0) {
+        printf("failed to bind and listen\n");
+        goto out;
+    }
+    if (connect(seqpacket_fd, (struct sockaddr *)&addr, addr_len) != -1 ||
+            errno != EPROTOTYPE) {
+        printf("connecting to a socket of another type should fail with EPROTOTYPE\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    close(listen_fd);
+    close(seqpacket_fd);
+    unlink(path);
+    return ret;
+}
+
+static int __test_scm_rights(int *fds) {
+    const char *file_path = "/root/test_unix_dgram_scm_rights.txt";
+    char buf[64] = { 0 };
+    char data = 'x';
+    int ret = -1;
+
+    int file_fd = open(file_path, O_RDWR | O_CREAT | O_TRUNC, 0666);
+    if (file_fd < 0) {
+        THROW_ERROR("failed to create a file");
+    }
+    if (write(file_fd, msgs[0], strlen(msgs[0])) != strlen(msgs[0])) {
+        printf("failed to write the file\n");
+        goto out;
+    }
+
+    // Send the fd along with one byte of data
+    union {
+        struct cmsghdr align;
+        char buf[CMSG_SPACE(sizeof(int))];
+    } control;
+    struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+    struct msghdr msg_hdr = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control.buf,
+        .msg_controllen = sizeof(control.buf),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg_hdr);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+    memcpy(CMSG_DATA(cmsg), &file_fd, sizeof(int));
+    if (sendmsg(fds[0], &msg_hdr, 0) != 1) {
+        printf("failed to send the fd\n");
+        goto out;
+    }
+    // The file can still be received after the sender closes it
+    close(file_fd);
+    file_fd = -1;
+
+    memset(&control, 0, sizeof(control));
+    msg_hdr.msg_controllen = sizeof(control.buf);
+    if (recvmsg(fds[1], &msg_hdr, 0) != 1) {
+        printf("failed to receive the fd\n");
+        goto out;
+    }
+    cmsg = CMSG_FIRSTHDR(&msg_hdr);
+    if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS ||
+            cmsg->cmsg_len != CMSG_LEN(sizeof(int))) {
+        printf("the control message is wrong\n");
+        goto out;
+    }
+    memcpy(&file_fd, CMSG_DATA(cmsg), sizeof(int));
+    if (pread(file_fd, buf, sizeof(buf), 0) != strlen(msgs[0]) || strcmp(buf, msgs[0]) != 0) {
+        printf("the received fd does not refer to the sent file\n");
+        goto out;
+    }
+    ret = 0;
+out:
+    if (file_fd >= 0) {
+        close(file_fd);
+    }
+    unlink(file_path);
+    return ret;
+}
+
+static int test_dgram_scm_rights(void) {
+    int fds[2];
+    int ret;
+
+    if (socketpair(AF_UNIX, SOCK_DGRAM, 0, fds) < 0) {
+        THROW_ERROR("failed to create a datagram socket pair");
+    }
+    ret = __test_scm_rights(fds);
+    close_pair(fds);
+    return ret;
+}
+
+static int test_seqpacket_scm_rights(void) {
+    int fds[2];
+    int ret;
+
+    if (create_seqpacket_pair(fds, "/tmp/test_unix_seqpacket_scm_rights") < 0) {
+        return -1;
+    }
+    ret = __test_scm_rights(fds);
+    close_pair(fds);
+    return ret;
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_dgram_sendto_and_recvfrom),
+    TEST_CASE(test_dgram_connect),
+    TEST_CASE(test_dgram_connected_receiver),
+    TEST_CASE(test_dgram_bind_failure),
+    TEST_CASE(test_dgram_closed_peer),
+    TEST_CASE(test_dgram_socketpair),
+    TEST_CASE(test_seqpacket_socketpair),
+    TEST_CASE(test_seqpacket_connect),
+    TEST_CASE(test_connect_type_mismatch),
+    TEST_CASE(test_dgram_scm_rights),
+    TEST_CASE(test_seqpacket_scm_rights),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
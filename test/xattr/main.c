@@ -0,0 +1,328 @@
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/xattr.h>
+#include <errno.h>
+#include <fcntl.h>
+#include "test_fs.h"
+
+// ============================================================================
+// Helper function
+// ============================================================================
+
+// The xattrs are supported by the SEFS, but not by the UnionFS of the rootfs
+#define MNT_DIR             "/mnt_xattr_sefs"
+#define MNT_OPTIONS         "dir=./xattr_test/mnt_sefs"
+
+static const char *xattr_name = "user.test_name";
+static const char *xattr_value = "test_value";
+
+static int mount_sefs(void) {
+    if (mkdir(MNT_DIR, 00775) < 0 && errno != EEXIST) {
+        THROW_ERROR("failed to create the mount dir");
+    }
+    if (mount("sefs", MNT_DIR, "sefs", 0, MNT_OPTIONS) < 0) {
+        THROW_ERROR("failed to mount sefs");
+    }
+    return 0;
+}
+
+static int umount_sefs(void) {
+    if (umount(MNT_DIR) < 0) {
+        THROW_ERROR("failed to umount sefs");
+    }
+    return 0;
+}
+
+static int create_file(const char *file_path) {
+    int fd;
+    int flags = O_RDONLY | O_CREAT | O_TRUNC;
+    int mode = 00666;
+
+    fd = open(file_path, flags, mode);
+    if (fd < 0) {
+        THROW_ERROR("failed to create a file");
+    }
+    close(fd);
+    return 0;
+}
+
+static int remove_file(const char *file_path) {
+    int ret;
+
+    ret = unlink(file_path);
+    if (ret < 0) {
+        THROW_ERROR("failed to unlink the created file");
+    }
+    return 0;
+}
+
+static int check_xattr_value(const char *file_path) {
+    char buf[64] = { 0 };
+    ssize_t size;
+
+    size = getxattr(file_path, xattr_name, NULL, 0);
+    if (size != strlen(xattr_value)) {
+        THROW_ERROR("failed to get the size of the xattr value");
+    }
+    size = getxattr(file_path, xattr_name, buf, sizeof(buf));
+    if (size != strlen(xattr_value) || strncmp(buf, xattr_value, size) != 0) {
+        THROW_ERROR("the xattr value is not the one set");
+    }
+    return 0;
+}
+
+// ============================================================================
+// Test cases for xattr
+// ============================================================================
+
+static int __test_setxattr_and_getxattr(const char *file_path) {
+    char buf[4];
+
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), 0) < 0) {
+        THROW_ERROR("failed to set the xattr");
+    }
+    if (check_xattr_value(file_path) < 0) {
+        return -1;
+    }
+    if (getxattr(file_path, xattr_name, buf, sizeof(buf)) != -1 || errno != ERANGE) {
+        THROW_ERROR("getxattr with a small buffer should fail with ERANGE");
+    }
+    if (getxattr(file_path, "user.not_exist", buf, sizeof(buf)) != -1 || errno != ENODATA) {
+        THROW_ERROR("getting a nonexistent xattr should fail with ENODATA");
+    }
+    if (getxattr(file_path, "unknown.test_name", buf, sizeof(buf)) != -1 ||
+            errno != EOPNOTSUPP) {
+        THROW_ERROR("getting an xattr of an unknown namespace should fail with EOPNOTSUPP");
+    }
+    return 0;
+}
+
+static int __test_setxattr_flags(const char *file_path) {
+    const char *new_value = "new_value";
+    char buf[64] = { 0 };
+
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), XATTR_REPLACE) != -1 ||
+            errno != ENODATA) {
+        THROW_ERROR("replacing a nonexistent xattr should fail with ENODATA");
+    }
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), XATTR_CREATE) < 0) {
+        THROW_ERROR("failed to create the xattr");
+    }
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), XATTR_CREATE) != -1 ||
+            errno != EEXIST) {
+        THROW_ERROR("creating an existing xattr should fail with EEXIST");
+    }
+    if (setxattr(file_path, xattr_name, new_value, strlen(new_value), XATTR_REPLACE) < 0) {
+        THROW_ERROR("failed to replace the xattr");
+    }
+    if (getxattr(file_path, xattr_name, buf, sizeof(buf)) != strlen(new_value) ||
+            strcmp(buf, new_value) != 0) {
+        THROW_ERROR("the xattr value is not replaced");
+    }
+    return 0;
+}
+
+static int __test_listxattr(const char *file_path) {
+    const char *xattr_names[] = {"user.name1", "user.name2", "trusted.name3"};
+    char list[128] = { 0 };
+    ssize_t list_size, expected_size = 0;
+
+    for (int i = 0; i < ARRAY_SIZE(xattr_names); i++) {
+        if (setxattr(file_path, xattr_names[i], xattr_value, strlen(xattr_value), 0) < 0) {
+            THROW_ERROR("failed to set the xattr");
+        }
+        expected_size += strlen(xattr_names[i]) + 1;
+    }
+    list_size = listxattr(file_path, NULL, 0);
+    if (list_size != expected_size) {
+        THROW_ERROR("failed to get the size of the xattr list");
+    }
+    if (listxattr(file_path, list, list_size - 1) != -1 || errno != ERANGE) {
+        THROW_ERROR("listxattr with a small buffer should fail with ERANGE");
+    }
+    if (listxattr(file_path, list, sizeof(list)) != list_size) {
+        THROW_ERROR("failed to list the xattrs");
+    }
+    for (int i = 0; i < ARRAY_SIZE(xattr_names); i++) {
+        bool found = false;
+        for (char *name = list; name < list + list_size; name += strlen(name) + 1) {
+            if (strcmp(name, xattr_names[i]) == 0) {
+                found = true;
+                break;
+            }
+        }
+        if (!found) {
+            THROW_ERROR("the xattr is not in the list");
+        }
+    }
+    return 0;
+}
+
+static int __test_removexattr(const char *file_path) {
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), 0) < 0) {
+        THROW_ERROR("failed to set the xattr");
+    }
+    if (removexattr(file_path, xattr_name) < 0) {
+        THROW_ERROR("failed to remove the xattr");
+    }
+    if (getxattr(file_path, xattr_name, NULL, 0) != -1 || errno != ENODATA) {
+        THROW_ERROR("the xattr is not removed");
+    }
+    if (removexattr(file_path, xattr_name) != -1 || errno != ENODATA) {
+        THROW_ERROR("removing a nonexistent xattr should fail with ENODATA");
+    }
+    return 0;
+}
+
+static int __test_fd_xattr(const char *file_path) {
+    char buf[64] = { 0 };
+    int fd = open(file_path, O_RDONLY);
+    if (fd < 0) {
+        THROW_ERROR("failed to open the file");
+    }
+    if (fsetxattr(fd, xattr_name, xattr_value, strlen(xattr_value), 0) < 0) {
+        close(fd);
+        THROW_ERROR("failed to set the xattr by fd");
+    }
+    // The xattr set by fd is visible by path
+    if (check_xattr_value(file_path) < 0) {
+        close(fd);
+        return -1;
+    }
+    if (fgetxattr(fd, xattr_name, buf, sizeof(buf)) != strlen(xattr_value) ||
+            flistxattr(fd, NULL, 0) != strlen(xattr_name) + 1) {
+        close(fd);
+        THROW_ERROR("failed to get the xattr by fd");
+    }
+    if (fremovexattr(fd, xattr_name) < 0 || flistxattr(fd, NULL, 0) != 0) {
+        close(fd);
+        THROW_ERROR("failed to remove the xattr by fd");
+    }
+    close(fd);
+    return 0;
+}
+
+static int __test_lsetxattr_on_symlink(const char *file_path) {
+    const char *link_path = MNT_DIR "/test_xattr_link";
+
+    if (symlink(file_path, link_path) < 0) {
+        THROW_ERROR("failed to create a symlink");
+    }
+    // The user xattrs are not allowed on symlinks
+    if (lsetxattr(link_path, xattr_name, xattr_value, strlen(xattr_value), 0) != -1 ||
+            errno != EPERM) {
+        unlink(link_path);
+        THROW_ERROR("setting a user xattr on a symlink should fail with EPERM");
+    }
+    // The symlink is followed by setxattr
+    if (setxattr(link_path, xattr_name, xattr_value, strlen(xattr_value), 0) < 0) {
+        unlink(link_path);
+        THROW_ERROR("failed to set the xattr through the symlink");
+    }
+    unlink(link_path);
+    return check_xattr_value(file_path);
+}
+
+typedef int(*test_xattr_func_t)(const char *);
+
+static int test_xattr_framework(test_xattr_func_t fn) {
+    const char *file_path = MNT_DIR "/test_filesystem_xattr.txt";
+
+    if (mount_sefs() < 0) {
+        return -1;
+    }
+    if (create_file(file_path) < 0) {
+        return -1;
+    }
+    if (fn(file_path) < 0) {
+        return -1;
+    }
+    if (remove_file(file_path) < 0) {
+        return -1;
+    }
+    return umount_sefs();
+}
+
+static int test_setxattr_and_getxattr() {
+    return test_xattr_framework(__test_setxattr_and_getxattr);
+}
+
+static int test_setxattr_flags() {
+    return test_xattr_framework(__test_setxattr_flags);
+}
+
+static int test_listxattr() {
+    return test_xattr_framework(__test_listxattr);
+}
+
+static int test_removexattr() {
+    return test_xattr_framework(__test_removexattr);
+}
+
+static int test_fd_xattr() {
+    return test_xattr_framework(__test_fd_xattr);
+}
+
+static int test_lsetxattr_on_symlink() {
+    return test_xattr_framework(__test_lsetxattr_on_symlink);
+}
+
+static int test_xattr_persisted_after_remount() {
+    const char *file_path = MNT_DIR "/test_xattr_remount.txt";
+
+    if (mount_sefs() < 0) {
+        return -1;
+    }
+    if (create_file(file_path) < 0) {
+        return -1;
+    }
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), 0) < 0) {
+        THROW_ERROR("failed to set the xattr");
+    }
+    if (umount_sefs() < 0) {
+        return -1;
+    }
+
+    if (mount_sefs() < 0) {
+        return -1;
+    }
+    if (check_xattr_value(file_path) < 0) {
+        THROW_ERROR("the xattr is not kept after remounting");
+    }
+    if (remove_file(file_path) < 0) {
+        return -1;
+    }
+    return umount_sefs();
+}
+
+static int test_xattr_not_supported_by_unionfs() {
+    const char *file_path = "/root/test_xattr_unionfs.txt";
+
+    if (create_file(file_path) < 0) {
+        return -1;
+    }
+    if (setxattr(file_path, xattr_name, xattr_value, strlen(xattr_value), 0) != -1 ||
+            errno != EOPNOTSUPP) {
+        THROW_ERROR("setting an xattr on the UnionFS should fail with EOPNOTSUPP");
+    }
+    return remove_file(file_path);
+}
+
+// ============================================================================
+// Test suite main
+// ============================================================================
+
+static test_case_t test_cases[] = {
+    TEST_CASE(test_setxattr_and_getxattr),
+    TEST_CASE(test_setxattr_flags),
+    TEST_CASE(test_listxattr),
+    TEST_CASE(test_removexattr),
+    TEST_CASE(test_fd_xattr),
+    TEST_CASE(test_lsetxattr_on_symlink),
+    TEST_CASE(test_xattr_persisted_after_remount),
+    TEST_CASE(test_xattr_not_supported_by_unionfs),
+};
+
+int main(int argc, const char *argv[]) {
+    return test_suite_run(test_cases, ARRAY_SIZE(test_cases));
+}
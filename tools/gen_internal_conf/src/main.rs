@@ -11,6 +11,7 @@ use lazy_static::lazy_static;
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
@@ -144,8 +145,11 @@ fn main() {
             enclave_config_file_path
         );
 
-        // get the number of TCS
-        let tcs_num = occlum_config.resource_limits.max_num_of_threads;
+        // get the number of TCS, where the AIO worker threads do not count as LibOS threads
+        // A TCS is reserved for each of the AIO worker threads, which are started by the PAL
+        // with the number passed to the LibOS
+        let tcs_num = occlum_config.resource_limits.max_num_of_threads
+            + occlum_config.resource_limits.num_of_aio_workers;
         let tcs_min_pool = tcs_num;
         let tcs_max_num = std::cmp::max(tcs_num, DEFAULT_CONFIG.num_of_tcs_max);
 
@@ -279,13 +283,19 @@ fn main() {
         let occlum_json_config = InternalOcclumJson {
             resource_limits: InternalResourceLimits {
                 user_space_size: occlum_config.resource_limits.user_space_size.to_string(),
+                oom_policy: occlum_config.resource_limits.oom_policy,
+                num_of_aio_workers: occlum_config.resource_limits.num_of_aio_workers,
             },
             process: OcclumProcess {
                 default_stack_size: occlum_config.process.default_stack_size,
                 default_heap_size: occlum_config.process.default_heap_size,
                 default_mmap_size: occlum_config.process.default_mmap_size,
+                default_mem_quota: occlum_config.process.default_mem_quota,
+                mem_quotas: occlum_config.process.mem_quotas,
+                core_dump: occlum_config.process.core_dump,
             },
             env: occlum_config.env,
+            net: occlum_config.net,
             app: app_config,
         };
 
@@ -480,6 +490,8 @@ struct OcclumConfiguration {
     env: serde_json::Value,
     metadata: OcclumMetadata,
     mount: Vec<OcclumMount>,
+    #[serde(default)]
+    net: OcclumNet,
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -490,15 +502,65 @@ struct OcclumResourceLimits {
     kernel_space_heap_max_size: Option<String>,
     kernel_space_stack_size: String,
     user_space_size: String,
+    #[serde(default = "OcclumResourceLimits::default_oom_policy")]
+    oom_policy: String,
+    #[serde(default = "OcclumResourceLimits::default_num_of_aio_workers")]
+    num_of_aio_workers: u32,
     #[cfg(feature = "ms_buffer")]
     marshal_buffer_size: Option<String>,
 }
 
+impl OcclumResourceLimits {
+    fn default_oom_policy() -> String {
+        "fail".to_string()
+    }
+
+    fn default_num_of_aio_workers() -> u32 {
+        2
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
 struct OcclumProcess {
     default_stack_size: String,
     default_heap_size: String,
     default_mmap_size: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_mem_quota: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mem_quotas: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    core_dump: Option<OcclumCoreDump>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+struct OcclumCoreDump {
+    dir: String,
+    #[serde(default)]
+    enable_in_release: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+struct OcclumNet {
+    #[serde(default = "OcclumNet::default_loopback")]
+    loopback: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interfaces: Option<Vec<String>>,
+}
+
+impl OcclumNet {
+    fn default_loopback() -> String {
+        "host".to_string()
+    }
+}
+
+impl Default for OcclumNet {
+    fn default() -> Self {
+        Self {
+            loopback: Self::default_loopback(),
+            interfaces: None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -541,6 +603,8 @@ struct OcclumMountOptions {
     pub temporary: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cache_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xattr_namespace: Option<String>,
 }
 
 #[inline]
@@ -588,6 +652,8 @@ struct EnclaveConfiguration {
 #[derive(Debug, PartialEq, Clone, Serialize)]
 struct InternalResourceLimits {
     user_space_size: String,
+    oom_policy: String,
+    num_of_aio_workers: u32,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize)]
@@ -595,5 +661,6 @@ struct InternalOcclumJson {
     resource_limits: InternalResourceLimits,
     process: OcclumProcess,
     env: serde_json::Value,
+    net: OcclumNet,
     app: serde_json::Value,
 }